@@ -0,0 +1,156 @@
+#[cfg(feature = "s3")]
+use aws_smithy_http::result::SdkError;
+use polars::error::PolarsError;
+#[cfg(feature = "s3")]
+use std::fmt;
+
+#[cfg(feature = "s3")]
+use crate::aws_s3::{AWSLoadFileError, AWSWriteFileError};
+use crate::config_value::ConfigValueError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("IO error. {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Polars error. {0}")]
+    Polars(#[from] PolarsError),
+    #[error("Unable to parse toml. {0}")]
+    TomlDe(#[from] toml::de::Error),
+    #[error("Unable to serialize toml. {0}")]
+    TomlSer(#[from] toml::ser::Error),
+    #[error("Unable to parse json. {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid config. {0}")]
+    Config(#[from] ConfigValueError),
+    #[error("MongoDB error. {0}")]
+    Mongo(String),
+    #[error("Kafka error. {0}")]
+    Kafka(String),
+    #[error("Avro error. {0}")]
+    Avro(String),
+    #[error("AWS S3 error. {0}")]
+    AwsS3(String),
+    #[error("HTTP error. {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Web driver error. {0}")]
+    WebDriver(String),
+    #[error("Scraping of {url} failed. {message}")]
+    Scrape { url: String, message: String },
+    #[error("{context}. {source}")]
+    Context {
+        context: String,
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(feature = "s3")]
+impl<E> From<SdkError<E>> for Error
+where
+    SdkError<E>: fmt::Display,
+{
+    fn from(err: SdkError<E>) -> Self {
+        Self::AwsS3(err.to_string())
+    }
+}
+
+#[cfg(feature = "s3")]
+impl From<AWSWriteFileError> for Error {
+    fn from(err: AWSWriteFileError) -> Self {
+        Self::AwsS3(format!("{err:?}"))
+    }
+}
+
+#[cfg(feature = "s3")]
+impl From<AWSLoadFileError> for Error {
+    fn from(err: AWSLoadFileError) -> Self {
+        Self::AwsS3(format!("{err:?}"))
+    }
+}
+
+#[cfg(feature = "browser")]
+impl From<thirtyfour::error::WebDriverError> for Error {
+    fn from(err: thirtyfour::error::WebDriverError) -> Self {
+        Self::WebDriver(err.to_string())
+    }
+}
+
+#[cfg(feature = "browser")]
+impl From<thirtyfour_sync::error::WebDriverError> for Error {
+    fn from(err: thirtyfour_sync::error::WebDriverError) -> Self {
+        Self::WebDriver(err.to_string())
+    }
+}
+
+#[cfg(feature = "mongo")]
+impl From<mongodb::error::Error> for Error {
+    fn from(err: mongodb::error::Error) -> Self {
+        Self::Mongo(err.to_string())
+    }
+}
+
+#[cfg(feature = "kafka")]
+impl From<rdkafka::error::KafkaError> for Error {
+    fn from(err: rdkafka::error::KafkaError) -> Self {
+        Self::Kafka(err.to_string())
+    }
+}
+
+#[cfg(feature = "kafka")]
+impl From<apache_avro::Error> for Error {
+    fn from(err: apache_avro::Error) -> Self {
+        Self::Avro(err.to_string())
+    }
+}
+
+impl Error {
+    pub fn scrape(url: &str, message: &str) -> Self {
+        Self::Scrape {
+            url: url.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+pub trait ResultExt<T> {
+    fn context(self, context: &str) -> Result<T>;
+
+    fn with_context<F: FnOnce() -> String>(self, context_func: F) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, context: &str) -> Result<T> {
+        self.with_context(|| context.to_string())
+    }
+
+    fn with_context<F: FnOnce() -> String>(self, context_func: F) -> Result<T> {
+        self.map_err(|e| Error::Context {
+            context: context_func(),
+            source: Box::new(e.into()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_error_context() {
+        let result = fs::read_to_string("not_exist_file.toml").context("Unable to load the config");
+        let error = result.unwrap_err();
+        assert!(matches!(error, Error::Context { .. }));
+        assert!(error
+            .to_string()
+            .starts_with("Unable to load the config. IO error."));
+        let error = Error::scrape("https://www.nowgoal.com/football/live", "Blocked");
+        assert_eq!(
+            error.to_string(),
+            "Scraping of https://www.nowgoal.com/football/live failed. Blocked"
+        );
+    }
+}
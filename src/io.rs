@@ -1,5 +1,11 @@
+#[cfg(feature = "s3")]
 pub mod aws_s3;
+#[cfg(feature = "duckdb")]
 pub mod duck_db;
 pub mod file_compress;
 pub mod file_io;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "mongo")]
+pub mod mongo;
 pub mod redis;
@@ -1,73 +1,524 @@
+use crate::config::AWSConfig;
+use crate::disk_guard::DiskGuard;
+use crate::dry_run::DryRun;
+use crate::error::ResultExt;
+use crate::file_io::{CleanupReport, FileEntry, FileIO};
 use crate::logger::ProjectLogger;
+use crate::secrets_provider::SecretsProvider;
+use crate::shared::Shared;
 use crate::time_operation;
 use crate::time_operation::SecPrecision;
+use aws_sdk_s3::config::Builder as S3ConfigBuilder;
 use aws_sdk_s3::error::{
-    CompleteMultipartUploadError, CreateMultipartUploadError, GetObjectError, ListObjectsV2Error,
-    PutObjectError, UploadPartError,
+    CompleteMultipartUploadError, CopyObjectError, CreateMultipartUploadError, GetObjectError,
+    ListObjectsV2Error, PutObjectError, UploadPartError,
+};
+use aws_sdk_s3::model::{
+    CompletedMultipartUpload, CompletedPart, Delete, Object, ObjectIdentifier, StorageClass, Tag,
+    Tagging,
 };
-use aws_sdk_s3::model::{CompletedMultipartUpload, CompletedPart, Object};
 use aws_sdk_s3::output::ListObjectsV2Output;
 use aws_sdk_s3::types::ByteStream;
 use aws_sdk_s3::{Client, Credentials, Region};
-use aws_smithy_http::body::SdkBody;
 use aws_smithy_http::result::SdkError;
-use chrono::{DateTime, TimeZone};
+use bytes::Bytes;
+use chrono::{DateTime, TimeZone, Utc};
+use futures::stream::{self, StreamExt, TryStreamExt};
 use polars::error::PolarsError;
 use polars::frame::DataFrame;
 use polars::io::{SerReader, SerWriter};
 use polars::prelude::{CsvReadOptions, CsvWriter, ParquetReader, ParquetWriter};
+use rand::{thread_rng, Rng};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::fs;
+use std::future::Future;
 use std::io::{Cursor, SeekFrom};
 use std::path::{Path, PathBuf};
 use std::result::Result;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use toml;
 
 const MULTIPART_SIZE: usize = 1024 * 1024 * 1024; // 1GB per part
 const LIMIT_SINGLE_UPLOAD: usize = 5 * MULTIPART_SIZE;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferProgress {
+    pub transferred_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub elapsed: Duration,
+}
+
+impl TransferProgress {
+    const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+
+    pub fn get_percent(&self) -> Option<f64> {
+        self.total_bytes
+            .filter(|total_bytes| *total_bytes > 0)
+            .map(|total_bytes| self.transferred_bytes as f64 / total_bytes as f64 * 100.0)
+    }
+
+    pub fn get_mb_per_sec(&self) -> f64 {
+        self.transferred_bytes as f64
+            / Self::BYTES_PER_MB
+            / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for TransferProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let transferred_mb = self.transferred_bytes as f64 / Self::BYTES_PER_MB;
+        match (self.get_percent(), self.total_bytes) {
+            (Some(percent), Some(total_bytes)) => write!(
+                f,
+                "{percent:.1}% ({transferred_mb:.1} of {:.1} MB) at {:.1} MB/s",
+                total_bytes as f64 / Self::BYTES_PER_MB,
+                self.get_mb_per_sec()
+            ),
+            _ => write!(
+                f,
+                "{transferred_mb:.1} MB at {:.1} MB/s",
+                self.get_mb_per_sec()
+            ),
+        }
+    }
+}
+
+// Called after every chunk of a download and every part of an upload, e.g.
+// &|progress| println!("{progress}") for interactive runs.
+pub type ProgressFunc = dyn Fn(&TransferProgress) + Send + Sync;
+
+struct ProgressTracker<'f> {
+    progress_func: Option<&'f ProgressFunc>,
+    total_bytes: Option<u64>,
+    transferred_bytes: AtomicU64,
+    start_time: Instant,
+}
+
+impl<'f> ProgressTracker<'f> {
+    fn new(progress_func: Option<&'f ProgressFunc>, total_bytes: Option<u64>) -> Self {
+        Self {
+            progress_func,
+            total_bytes,
+            transferred_bytes: AtomicU64::new(0),
+            start_time: Instant::now(),
+        }
+    }
+
+    fn add_bytes(&self, num_bytes: u64) -> TransferProgress {
+        let transferred_bytes = self
+            .transferred_bytes
+            .fetch_add(num_bytes, Ordering::Relaxed)
+            + num_bytes;
+        let transfer_progress = TransferProgress {
+            transferred_bytes,
+            total_bytes: self.total_bytes,
+            elapsed: self.start_time.elapsed(),
+        };
+        if let Some(progress_func) = self.progress_func {
+            progress_func(&transfer_progress);
+        }
+        transfer_progress
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct S3RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for S3RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: Self::MAX_ATTEMPTS,
+            initial_backoff: Self::INITIAL_BACKOFF,
+            max_backoff: Self::MAX_BACKOFF,
+        }
+    }
+}
+
+impl S3RetryPolicy {
+    const MAX_ATTEMPTS: u32 = 4;
+    const INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+    const MAX_BACKOFF: Duration = Duration::from_secs(20);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_max_attempts(&mut self, max_attempts: u32) {
+        self.max_attempts = max_attempts.max(1);
+    }
+
+    pub fn set_initial_backoff(&mut self, initial_backoff: Duration) {
+        self.initial_backoff = initial_backoff;
+    }
+
+    pub fn set_max_backoff(&mut self, max_backoff: Duration) {
+        self.max_backoff = max_backoff;
+    }
+
+    pub fn get_max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    // Exponential backoff capped at max_backoff, with the second half jittered so that parallel
+    // tasks throttled together do not retry together.
+    pub fn get_backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        let mut rng = thread_rng();
+        backoff / 2 + backoff.mul_f64(rng.gen_range(0.0..0.5))
+    }
+
+    // Timeouts, dropped connections, throttling (429, 503 SlowDown) and server errors are
+    // retried. Client errors such as a missing key are returned at once.
+    pub fn is_retryable<E>(error: &SdkError<E>) -> bool {
+        match error {
+            SdkError::TimeoutError(_)
+            | SdkError::DispatchFailure(_)
+            | SdkError::ResponseError(_) => true,
+            SdkError::ServiceError(_) => error.raw_response().map_or(false, |response| {
+                let status = response.http().status().as_u16();
+                status == 429 || status >= 500
+            }),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct S3WriteOptions {
+    tags: Vec<(String, String)>,
+    metadata: HashMap<String, String>,
+    storage_class: Option<StorageClass>,
+}
+
+impl S3WriteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_tag(&mut self, key: &str, value: &str) {
+        self.tags.push((key.to_string(), value.to_string()));
+    }
+
+    pub fn add_metadata(&mut self, key: &str, value: &str) {
+        self.metadata.insert(key.to_string(), value.to_string());
+    }
+
+    // e.g. StorageClass::StandardIa or StorageClass::GlacierIr when archiving raw scrapes
+    pub fn set_storage_class(&mut self, storage_class: StorageClass) {
+        self.storage_class = Some(storage_class);
+    }
+
+    // Tags are sent as a url encoded query string in the put request.
+    pub fn get_tagging(&self) -> Option<String> {
+        if self.tags.is_empty() {
+            return None;
+        }
+        let tag_str: Vec<String> = self
+            .tags
+            .iter()
+            .map(|(key, value)| format!("{}={}", encode_tag(key), encode_tag(value)))
+            .collect();
+        Some(tag_str.join("&"))
+    }
+
+    fn get_metadata(&self) -> Option<HashMap<String, String>> {
+        (!self.metadata.is_empty()).then(|| self.metadata.clone())
+    }
+
+    fn get_storage_class(&self) -> Option<StorageClass> {
+        self.storage_class.clone()
+    }
+}
+
+// Request bodies are held in memory as Bytes, so they can be cloned cheaply for every retry.
+fn clone_body(body: &Bytes) -> ByteStream {
+    ByteStream::from(body.clone())
+}
+
+fn encode_tag(tag: &str) -> String {
+    tag.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3ObjectMetadata {
+    pub content_length: i64,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub e_tag: Option<String>,
+    pub storage_class: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+// For minio, LocalStack or the other S3 compatible storage in the tests and on-prem deployments.
+// They usually need the path style addressing, i.e. endpoint/bucket/key. Anonymous loads no
+// credentials, for the public buckets.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct S3Endpoint {
+    pub endpoint_url: Option<String>,
+    #[serde(default)]
+    pub force_path_style: bool,
+    #[serde(default)]
+    pub anonymous: bool,
+}
+
+impl S3Endpoint {
+    pub fn new(endpoint_url: &str) -> Self {
+        Self {
+            endpoint_url: Some(endpoint_url.to_string()),
+            force_path_style: true,
+            anonymous: false,
+        }
+    }
+
+    pub fn set_force_path_style(mut self, force_path_style: bool) -> Self {
+        self.force_path_style = force_path_style;
+        self
+    }
+
+    pub fn set_anonymous(mut self, anonymous: bool) -> Self {
+        self.anonymous = anonymous;
+        self
+    }
+
+    fn apply_to_builder(&self, s3_config_builder: S3ConfigBuilder) -> S3ConfigBuilder {
+        let s3_config_builder = s3_config_builder.force_path_style(self.force_path_style);
+        match &self.endpoint_url {
+            Some(endpoint_url) => s3_config_builder.endpoint_url(endpoint_url),
+            None => s3_config_builder,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AWSFileIO<'a> {
-    project_logger: &'a ProjectLogger,
+    project_logger: Shared<'a, ProjectLogger>,
     client: Client,
+    write_options: S3WriteOptions,
+    retry_policy: S3RetryPolicy,
+    multipart_concurrency: usize,
+    disk_guard: Option<Shared<'a, DiskGuard<'a>>>,
 }
 
 impl<'a> AWSFileIO<'a> {
     const MAX_KEY: i32 = 100;
+    const MAX_DELETE_KEY: usize = 1000;
+    const MULTIPART_CONCURRENCY: usize = 4;
+    const ANONYMOUS_REGION: &'a str = "us-east-1";
+
+    pub async fn new(project_logger: impl Into<Shared<'a, ProjectLogger>>) -> AWSFileIO<'a> {
+        Self::new_with_endpoint(project_logger, &S3Endpoint::default()).await
+    }
 
-    pub async fn new(project_logger: &'a ProjectLogger) -> AWSFileIO<'a> {
+    // The region of the api key file is still used with a custom endpoint, e.g. us-east-1 for
+    // minio. The api key file is not needed for an anonymous endpoint, which uses us-east-1.
+    pub async fn new_with_endpoint(
+        project_logger: impl Into<Shared<'a, ProjectLogger>>,
+        s3_endpoint: &S3Endpoint,
+    ) -> AWSFileIO<'a> {
+        let project_logger = project_logger.into();
+        if s3_endpoint.anonymous {
+            return Self::with_anonymous(project_logger, Self::ANONYMOUS_REGION, s3_endpoint);
+        }
         let api_key = APIKey::load_apikey();
-        let credentials = Credentials::new(
+        Self::with_credentials(
+            project_logger,
             &api_key.aws_api_id,
             &api_key.aws_api_secret,
-            None,
-            None,
-            "s3_access",
-        );
-        let region = Region::new(api_key.aws_api_region.clone());
+            &api_key.aws_api_region,
+            s3_endpoint,
+        )
+        .await
+    }
+
+    pub async fn try_new(
+        project_logger: impl Into<Shared<'a, ProjectLogger>>,
+    ) -> crate::error::Result<AWSFileIO<'a>> {
+        let api_key = APIKey::try_load_apikey()?;
+        Ok(Self::with_credentials(
+            project_logger.into(),
+            &api_key.aws_api_id,
+            &api_key.aws_api_secret,
+            &api_key.aws_api_region,
+            &S3Endpoint::default(),
+        )
+        .await)
+    }
+
+    pub async fn from_config(
+        project_logger: impl Into<Shared<'a, ProjectLogger>>,
+        aws_config: &AWSConfig,
+    ) -> AWSFileIO<'a> {
+        let project_logger = project_logger.into();
+        let mut aws_file_io = if aws_config.endpoint.anonymous {
+            Self::with_anonymous(
+                project_logger,
+                &aws_config.aws_api_region,
+                &aws_config.endpoint,
+            )
+        } else {
+            Self::with_credentials(
+                project_logger,
+                &aws_config.aws_api_id,
+                &aws_config.aws_api_secret,
+                &aws_config.aws_api_region,
+                &aws_config.endpoint,
+            )
+            .await
+        };
+        if let Some(max_attempts) = aws_config.max_attempts {
+            aws_file_io.retry_policy.set_max_attempts(max_attempts);
+        }
+        if let Some(initial_backoff) = aws_config.initial_backoff {
+            aws_file_io
+                .retry_policy
+                .set_initial_backoff(initial_backoff.get_duration());
+        }
+        if let Some(max_backoff) = aws_config.max_backoff {
+            aws_file_io
+                .retry_policy
+                .set_max_backoff(max_backoff.get_duration());
+        }
+        aws_file_io
+    }
+
+    pub async fn from_secrets(
+        project_logger: impl Into<Shared<'a, ProjectLogger>>,
+        secrets_provider: &dyn SecretsProvider,
+    ) -> AWSFileIO<'a> {
+        Self::with_credentials(
+            project_logger.into(),
+            &secrets_provider.require_secret(APIKey::AWS_API_ID_SECRET),
+            &secrets_provider.require_secret(APIKey::AWS_API_SECRET_SECRET),
+            &secrets_provider.require_secret(APIKey::AWS_API_REGION_SECRET),
+            &S3Endpoint::default(),
+        )
+        .await
+    }
+
+    async fn with_credentials(
+        project_logger: Shared<'a, ProjectLogger>,
+        aws_api_id: &str,
+        aws_api_secret: &str,
+        aws_api_region: &str,
+        s3_endpoint: &S3Endpoint,
+    ) -> AWSFileIO<'a> {
+        let credentials = Credentials::new(aws_api_id, aws_api_secret, None, None, "s3_access");
+        let region = Region::new(aws_api_region.to_string());
         let config = aws_config::from_env()
             .credentials_provider(credentials)
             .region(region)
             .load()
             .await;
-        let client = Client::new(&config);
+        let s3_config = s3_endpoint
+            .apply_to_builder(S3ConfigBuilder::from(&config))
+            .build();
+        Self::with_client(project_logger, Client::from_conf(s3_config))
+    }
+
+    // No credentials are loaded, not even from the environment.
+    fn with_anonymous(
+        project_logger: Shared<'a, ProjectLogger>,
+        aws_api_region: &str,
+        s3_endpoint: &S3Endpoint,
+    ) -> AWSFileIO<'a> {
+        let region = Region::new(aws_api_region.to_string());
+        let s3_config = s3_endpoint
+            .apply_to_builder(S3ConfigBuilder::new().region(region))
+            .build();
+        Self::with_client(project_logger, Client::from_conf(s3_config))
+    }
+
+    fn with_client(project_logger: Shared<'a, ProjectLogger>, client: Client) -> AWSFileIO<'a> {
         Self {
             project_logger,
             client,
+            write_options: S3WriteOptions::default(),
+            retry_policy: S3RetryPolicy::default(),
+            multipart_concurrency: Self::MULTIPART_CONCURRENCY,
+            disk_guard: None,
+        }
+    }
+
+    // Every concurrent part holds MULTIPART_SIZE bytes in memory.
+    pub fn set_multipart_concurrency(&mut self, multipart_concurrency: usize) {
+        self.multipart_concurrency = multipart_concurrency.max(1);
+    }
+
+    pub fn set_retry_policy(&mut self, retry_policy: S3RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    // Checked before every file downloaded afterwards.
+    pub fn set_disk_guard(&mut self, disk_guard: impl Into<Shared<'a, DiskGuard<'a>>>) {
+        self.disk_guard = Some(disk_guard.into());
+    }
+
+    async fn send_with_retry<T, E, F, Fut>(
+        &self,
+        operation: &str,
+        mut send_func: F,
+    ) -> Result<T, SdkError<E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, SdkError<E>>>,
+        SdkError<E>: fmt::Display,
+    {
+        let mut attempt = 1;
+        loop {
+            match send_func().await {
+                Ok(output) => {
+                    if attempt > 1 {
+                        let debug_str =
+                            format!("S3 {operation} succeeded after {attempt} attempts.");
+                        self.project_logger.log_debug(&debug_str);
+                    }
+                    return Ok(output);
+                }
+                Err(e)
+                    if attempt < self.retry_policy.get_max_attempts()
+                        && S3RetryPolicy::is_retryable(&e) =>
+                {
+                    let backoff = self.retry_policy.get_backoff(attempt);
+                    let warn_str = format!(
+                        "S3 {operation} failed on attempt {attempt} of {}. Retry in {} ms. {e}",
+                        self.retry_policy.get_max_attempts(),
+                        backoff.as_millis()
+                    );
+                    self.project_logger.log_warn(&warn_str);
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
         }
     }
 
+    // Applied to every object written afterwards, including multipart uploads.
+    pub fn set_write_options(&mut self, write_options: S3WriteOptions) {
+        self.write_options = write_options;
+    }
+
     fn add_stash_for_folder_suffix(folder_name: &Path) -> PathBuf {
-        if folder_name
-            .to_string_lossy()
-            .chars()
-            .last()
-            .unwrap_or_else(|| panic!("Folder name is empty"))
-            != '/'
-        {
+        if !folder_name.to_string_lossy().ends_with('/') {
             folder_name.join("")
         } else {
             folder_name.to_path_buf()
@@ -75,22 +526,23 @@ impl<'a> AWSFileIO<'a> {
     }
 
     pub async fn check_bucket_exist(&self, bucket_name: &str) -> bool {
-        self.client
-            .head_bucket()
-            .bucket(bucket_name)
-            .send()
-            .await
-            .is_ok()
+        self.send_with_retry("head_bucket", || {
+            self.client.head_bucket().bucket(bucket_name).send()
+        })
+        .await
+        .is_ok()
     }
 
     pub async fn check_folder_exist(&self, bucket_name: &str, folder_name: &Path) -> bool {
-        self.client
-            .head_object()
-            .bucket(bucket_name)
-            .key(Self::add_stash_for_folder_suffix(folder_name).to_string_lossy())
-            .send()
-            .await
-            .is_ok()
+        self.send_with_retry("head_object", || {
+            self.client
+                .head_object()
+                .bucket(bucket_name)
+                .key(Self::add_stash_for_folder_suffix(folder_name).to_string_lossy())
+                .send()
+        })
+        .await
+        .is_ok()
     }
 
     pub async fn check_file_exist(
@@ -100,13 +552,15 @@ impl<'a> AWSFileIO<'a> {
         file_name: &str,
     ) -> bool {
         let full_path = folder_name.join(file_name);
-        self.client
-            .head_object()
-            .bucket(bucket_name)
-            .key(full_path.to_string_lossy())
-            .send()
-            .await
-            .is_ok()
+        self.send_with_retry("head_object", || {
+            self.client
+                .head_object()
+                .bucket(bucket_name)
+                .key(full_path.to_string_lossy())
+                .send()
+        })
+        .await
+        .is_ok()
     }
 
     pub async fn create_directory_if_not_exists(
@@ -115,30 +569,32 @@ impl<'a> AWSFileIO<'a> {
         folder_name: &Path,
     ) -> Result<(), SdkError<PutObjectError>> {
         if !self.check_folder_exist(bucket_name, folder_name).await {
-            self.client
-                .put_object()
-                .bucket(bucket_name)
-                .key(Self::add_stash_for_folder_suffix(folder_name).to_string_lossy())
-                .send()
-                .await
-                .map_or_else(
-                    |e| {
-                        let error_str = format!(
-                            "Unable to create folder {} in bucket {bucket_name}. {e}",
-                            folder_name.display()
-                        );
-                        self.project_logger.log_error(&error_str);
-                        Err(e)
-                    },
-                    |_| {
-                        let debug_str = format!(
-                            "Folder {} created in bucket {bucket_name}",
-                            folder_name.display()
-                        );
-                        self.project_logger.log_debug(&debug_str);
-                        Ok(())
-                    },
-                )
+            self.send_with_retry("put_object", || {
+                self.client
+                    .put_object()
+                    .bucket(bucket_name)
+                    .key(Self::add_stash_for_folder_suffix(folder_name).to_string_lossy())
+                    .send()
+            })
+            .await
+            .map_or_else(
+                |e| {
+                    let error_str = format!(
+                        "Unable to create folder {} in bucket {bucket_name}. {e}",
+                        folder_name.display()
+                    );
+                    self.project_logger.log_error(&error_str);
+                    Err(e)
+                },
+                |_| {
+                    let debug_str = format!(
+                        "Folder {} created in bucket {bucket_name}",
+                        folder_name.display()
+                    );
+                    self.project_logger.log_debug(&debug_str);
+                    Ok(())
+                },
+            )
         } else {
             let error_str = format!(
                 "Folder {} already exists in bucket {bucket_name}.",
@@ -159,13 +615,15 @@ impl<'a> AWSFileIO<'a> {
         let mut continuation_token = None;
         while !is_last_page {
             match self
-                .client
-                .list_objects_v2()
-                .bucket(bucket_name)
-                .prefix(Self::add_stash_for_folder_suffix(folder_name).to_string_lossy())
-                .set_continuation_token(continuation_token)
-                .max_keys(Self::MAX_KEY)
-                .send()
+                .send_with_retry("list_objects_v2", || {
+                    self.client
+                        .list_objects_v2()
+                        .bucket(bucket_name)
+                        .prefix(Self::add_stash_for_folder_suffix(folder_name).to_string_lossy())
+                        .set_continuation_token(continuation_token.clone())
+                        .max_keys(Self::MAX_KEY)
+                        .send()
+                })
                 .await
             {
                 Ok(object_list) => {
@@ -188,6 +646,44 @@ impl<'a> AWSFileIO<'a> {
         Ok(object_output_list)
     }
 
+    // The objects in the folder whose file names start with the prefix and end with the suffix,
+    // e.g. odds_ and .json, from the oldest to the latest modified.
+    pub async fn list_files_matching(
+        &self,
+        bucket_name: &str,
+        folder_name: &Path,
+        file_prefix: &str,
+        file_suffix: &str,
+    ) -> Result<Vec<FileEntry>, SdkError<ListObjectsV2Error>> {
+        let object_output_list = self
+            .get_elements_in_folder(bucket_name, folder_name)
+            .await?;
+        let mut file_entry_list: Vec<FileEntry> = object_output_list
+            .iter()
+            .flat_map(|object_output| object_output.contents().unwrap_or_default())
+            .filter_map(|element| {
+                let key = element.key()?;
+                let file_name = key.rsplit('/').next()?;
+                (!file_name.is_empty()
+                    && file_name.starts_with(file_prefix)
+                    && file_name.ends_with(file_suffix))
+                .then(|| FileEntry {
+                    path: PathBuf::from(key),
+                    size: element.size() as u64,
+                    modified: element
+                        .last_modified()
+                        .and_then(|last_modified| {
+                            Utc.timestamp_opt(last_modified.secs(), last_modified.subsec_nanos())
+                                .single()
+                        })
+                        .unwrap_or_default(),
+                })
+            })
+            .collect();
+        file_entry_list.sort_by_key(|file_entry| file_entry.modified);
+        Ok(file_entry_list)
+    }
+
     pub fn filter_element_after<T: TimeZone>(
         &self,
         element: &Object,
@@ -218,6 +714,7 @@ impl<'a> AWSFileIO<'a> {
         })
     }
 
+    #[tracing::instrument(name = "s3_operation", skip_all, fields(operation = "load", bucket = bucket_name, key = %folder_path.join(file).display()))]
     pub async fn load_file_as_string(
         &self,
         bucket_name: &str,
@@ -226,11 +723,13 @@ impl<'a> AWSFileIO<'a> {
     ) -> Result<String, AWSLoadFileError> {
         let full_path = folder_path.join(file);
         let get_object = self
-            .client
-            .get_object()
-            .bucket(bucket_name)
-            .key(full_path.to_string_lossy())
-            .send()
+            .send_with_retry("get_object", || {
+                self.client
+                    .get_object()
+                    .bucket(bucket_name)
+                    .key(full_path.to_string_lossy())
+                    .send()
+            })
             .await
             .map_err(|e| {
                 let error_str = format!(
@@ -267,33 +766,61 @@ impl<'a> AWSFileIO<'a> {
         file: &str,
         content: &str,
     ) -> Result<(), SdkError<PutObjectError>> {
-        let full_path = folder_path.join(file);
-        let content_byte = ByteStream::new(SdkBody::from(content));
-        self.client
-            .put_object()
-            .bucket(bucket_name)
-            .key(full_path.to_string_lossy())
-            .body(content_byte)
-            .send()
+        self.write_bytes_to_file(bucket_name, folder_path, file, content.as_bytes())
             .await
-            .map_or_else(
-                |e| {
-                    let error_str = format!(
-                        "Unable to save {} in bucket {bucket_name}, {e}",
-                        full_path.display()
-                    );
-                    self.project_logger.log_error(&error_str);
-                    Err(e)
-                },
-                |_| {
-                    let debug_str =
-                        format!("File {} saved in bucket {bucket_name}", full_path.display());
-                    self.project_logger.log_debug(&debug_str);
-                    Ok(())
-                },
-            )
     }
 
+    // For the binary content, e.g. images, pdfs and zips, which are written verbatim.
+    #[tracing::instrument(name = "s3_operation", skip_all, fields(operation = "write", bucket = bucket_name, key = %folder_path.join(file).display()))]
+    pub async fn write_bytes_to_file(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+        file: &str,
+        content: &[u8],
+    ) -> Result<(), SdkError<PutObjectError>> {
+        if DryRun::skip(
+            &self.project_logger,
+            &format!(
+                "writing file {} in bucket {bucket_name}",
+                folder_path.join(file).display()
+            ),
+        ) {
+            return Ok(());
+        }
+        let full_path = folder_path.join(file);
+        let content_body = Bytes::from(content.to_vec());
+        self.send_with_retry("put_object", || {
+            self.client
+                .put_object()
+                .bucket(bucket_name)
+                .key(full_path.to_string_lossy())
+                .set_tagging(self.write_options.get_tagging())
+                .set_metadata(self.write_options.get_metadata())
+                .set_storage_class(self.write_options.get_storage_class())
+                .body(clone_body(&content_body))
+                .send()
+        })
+        .await
+        .map_or_else(
+            |e| {
+                let error_str = format!(
+                    "Unable to save {} in bucket {bucket_name}, {e}",
+                    full_path.display()
+                );
+                self.project_logger.log_error(&error_str);
+                Err(e)
+            },
+            |_| {
+                let debug_str =
+                    format!("File {} saved in bucket {bucket_name}", full_path.display());
+                self.project_logger.log_debug(&debug_str);
+                Ok(())
+            },
+        )
+    }
+
+    #[tracing::instrument(name = "s3_operation", skip_all, fields(operation = "load", bucket = bucket_name, key = %folder_path.join(file).display()))]
     pub async fn load_csv_file(
         &self,
         bucket_name: &str,
@@ -302,11 +829,13 @@ impl<'a> AWSFileIO<'a> {
     ) -> Result<DataFrame, AWSLoadFileError> {
         let full_path = folder_path.join(file);
         let get_object = self
-            .client
-            .get_object()
-            .bucket(bucket_name)
-            .key(full_path.to_string_lossy())
-            .send()
+            .send_with_retry("get_object", || {
+                self.client
+                    .get_object()
+                    .bucket(bucket_name)
+                    .key(full_path.to_string_lossy())
+                    .send()
+            })
             .await
             .map_err(|e| {
                 let error_str = format!(
@@ -336,6 +865,7 @@ impl<'a> AWSFileIO<'a> {
             })
     }
 
+    #[tracing::instrument(name = "s3_operation", skip_all, fields(operation = "write", bucket = bucket_name, key = %folder_path.join(file).display()))]
     pub async fn write_csv_file(
         &self,
         bucket_name: &str,
@@ -343,6 +873,15 @@ impl<'a> AWSFileIO<'a> {
         file: &str,
         data: &mut DataFrame,
     ) -> Result<(), AWSWriteFileError> {
+        if DryRun::skip(
+            &self.project_logger,
+            &format!(
+                "writing file {} in bucket {bucket_name}",
+                folder_path.join(file).display()
+            ),
+        ) {
+            return Ok(());
+        }
         let full_path = folder_path.join(file);
         let mut buffer = Vec::new();
         let cursor = Cursor::new(&mut buffer);
@@ -359,32 +898,38 @@ impl<'a> AWSFileIO<'a> {
             self.project_logger.log_error(&error_str);
             return Err(AWSWriteFileError::PolarsError(e));
         };
-        let csv_string = ByteStream::from(buffer);
-        self.client
-            .put_object()
-            .bucket(bucket_name)
-            .key(full_path.to_string_lossy())
-            .body(csv_string)
-            .send()
-            .await
-            .map_or_else(
-                |e| {
-                    let error_str = format!(
-                        "Unable to save {} in bucket {bucket_name}, {e}",
-                        full_path.display()
-                    );
-                    self.project_logger.log_error(&error_str);
-                    Err(AWSWriteFileError::SdkError(e))
-                },
-                |_| {
-                    let debug_str =
-                        format!("File {} saved in bucket {bucket_name}", full_path.display());
-                    self.project_logger.log_debug(&debug_str);
-                    Ok(())
-                },
-            )
+        let csv_body = Bytes::from(buffer);
+        self.send_with_retry("put_object", || {
+            self.client
+                .put_object()
+                .bucket(bucket_name)
+                .key(full_path.to_string_lossy())
+                .set_tagging(self.write_options.get_tagging())
+                .set_metadata(self.write_options.get_metadata())
+                .set_storage_class(self.write_options.get_storage_class())
+                .body(clone_body(&csv_body))
+                .send()
+        })
+        .await
+        .map_or_else(
+            |e| {
+                let error_str = format!(
+                    "Unable to save {} in bucket {bucket_name}, {e}",
+                    full_path.display()
+                );
+                self.project_logger.log_error(&error_str);
+                Err(AWSWriteFileError::SdkError(e))
+            },
+            |_| {
+                let debug_str =
+                    format!("File {} saved in bucket {bucket_name}", full_path.display());
+                self.project_logger.log_debug(&debug_str);
+                Ok(())
+            },
+        )
     }
 
+    #[tracing::instrument(name = "s3_operation", skip_all, fields(operation = "load", bucket = bucket_name, key = %folder_path.join(file).display()))]
     pub async fn load_parquet_file(
         &self,
         bucket_name: &str,
@@ -393,11 +938,13 @@ impl<'a> AWSFileIO<'a> {
     ) -> Result<DataFrame, AWSLoadFileError> {
         let full_path = folder_path.join(file);
         let get_object = self
-            .client
-            .get_object()
-            .bucket(bucket_name)
-            .key(full_path.to_string_lossy())
-            .send()
+            .send_with_retry("get_object", || {
+                self.client
+                    .get_object()
+                    .bucket(bucket_name)
+                    .key(full_path.to_string_lossy())
+                    .send()
+            })
             .await
             .map_err(|e| {
                 let error_str = format!(
@@ -427,6 +974,7 @@ impl<'a> AWSFileIO<'a> {
         })
     }
 
+    #[tracing::instrument(name = "s3_operation", skip_all, fields(operation = "write", bucket = bucket_name, key = %folder_path.join(file).display()))]
     pub async fn write_parquet_file(
         &self,
         bucket_name: &str,
@@ -434,6 +982,15 @@ impl<'a> AWSFileIO<'a> {
         file: &str,
         data: &mut DataFrame,
     ) -> Result<(), AWSWriteFileError> {
+        if DryRun::skip(
+            &self.project_logger,
+            &format!(
+                "writing file {} in bucket {bucket_name}",
+                folder_path.join(file).display()
+            ),
+        ) {
+            return Ok(());
+        }
         let full_path = folder_path.join(file);
         let mut buffer = Vec::new();
         let cursor = Cursor::new(&mut buffer);
@@ -446,30 +1003,98 @@ impl<'a> AWSFileIO<'a> {
             self.project_logger.log_error(&error_str);
             return Err(AWSWriteFileError::PolarsError(e));
         };
-        let parquet_string = ByteStream::from(buffer);
-        self.client
-            .put_object()
-            .bucket(bucket_name)
-            .key(full_path.to_string_lossy())
-            .body(parquet_string)
-            .send()
-            .await
-            .map_or_else(
-                |e| {
-                    let error_str = format!(
-                        "Unable to save {} in bucket {bucket_name}, {e}",
-                        full_path.display()
-                    );
-                    self.project_logger.log_error(&error_str);
-                    Err(AWSWriteFileError::SdkError(e))
-                },
-                |_| {
-                    let debug_str =
-                        format!("File {} saved in bucket {bucket_name}", full_path.display());
-                    self.project_logger.log_debug(&debug_str);
-                    Ok(())
-                },
+        let parquet_body = Bytes::from(buffer);
+        self.send_with_retry("put_object", || {
+            self.client
+                .put_object()
+                .bucket(bucket_name)
+                .key(full_path.to_string_lossy())
+                .set_tagging(self.write_options.get_tagging())
+                .set_metadata(self.write_options.get_metadata())
+                .set_storage_class(self.write_options.get_storage_class())
+                .body(clone_body(&parquet_body))
+                .send()
+        })
+        .await
+        .map_or_else(
+            |e| {
+                let error_str = format!(
+                    "Unable to save {} in bucket {bucket_name}, {e}",
+                    full_path.display()
+                );
+                self.project_logger.log_error(&error_str);
+                Err(AWSWriteFileError::SdkError(e))
+            },
+            |_| {
+                let debug_str =
+                    format!("File {} saved in bucket {bucket_name}", full_path.display());
+                self.project_logger.log_debug(&debug_str);
+                Ok(())
+            },
+        )
+    }
+
+    // A single put replaces the object atomically, so no temporary object is needed here.
+    pub async fn upsert_parquet_file(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+        file: &str,
+        new_data: &DataFrame,
+        key_cols: &[&str],
+    ) -> crate::error::Result<usize> {
+        let merged = if self.check_file_exist(bucket_name, folder_path, file).await {
+            let existing = self
+                .load_parquet_file(bucket_name, folder_path, file)
+                .await?;
+            FileIO::merge_latest(&existing, new_data, key_cols)
+        } else {
+            FileIO::dedup_keep_latest(new_data, key_cols)
+        };
+        let mut merged = merged.map_err(|e| {
+            let error_str = format!(
+                "Unable to merge the new data into {} in bucket {bucket_name}. {e}",
+                folder_path.join(file).display()
+            );
+            self.project_logger.log_error(&error_str);
+            e
+        })?;
+        self.write_parquet_file(bucket_name, folder_path, file, &mut merged)
+            .await?;
+        Ok(merged.height())
+    }
+
+    pub async fn write_partitioned_parquet(
+        &self,
+        bucket_name: &str,
+        data: &DataFrame,
+        root_path: &Path,
+        partition_cols: &[&str],
+    ) -> Result<usize, AWSWriteFileError> {
+        let partition_list = FileIO::split_partitions(data, partition_cols).map_err(|e| {
+            let error_str = format!(
+                "Unable to partition the data by {partition_cols:?} for {} in bucket {bucket_name}. {e}",
+                root_path.display()
+            );
+            self.project_logger.log_error(&error_str);
+            AWSWriteFileError::PolarsError(e)
+        })?;
+        let num_partitions = partition_list.len();
+        for (partition_path, mut partition_data) in partition_list {
+            self.write_parquet_file(
+                bucket_name,
+                &root_path.join(partition_path),
+                FileIO::PARTITION_FILE,
+                &mut partition_data,
             )
+            .await?;
+        }
+        let debug_str = format!(
+            "Data written into {num_partitions} partitions in {} in bucket {bucket_name}.",
+            root_path.display()
+        );
+        self.project_logger.log_debug(&debug_str);
+        Ok(num_partitions)
     }
 
     pub async fn download_file(
@@ -479,14 +1104,38 @@ impl<'a> AWSFileIO<'a> {
         file: &str,
         local_path: &Path,
         local_file: &str,
+    ) -> Result<(), AWSLoadFileError> {
+        self.download_file_with_progress(
+            bucket_name,
+            folder_path,
+            file,
+            local_path,
+            local_file,
+            None,
+        )
+        .await
+    }
+
+    // The object is streamed to disk chunk by chunk instead of being collected in memory.
+    #[tracing::instrument(name = "s3_operation", skip_all, fields(operation = "download", bucket = bucket_name, key = %folder_path.join(file).display()))]
+    pub async fn download_file_with_progress(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+        file: &str,
+        local_path: &Path,
+        local_file: &str,
+        progress_func: Option<&ProgressFunc>,
     ) -> Result<(), AWSLoadFileError> {
         let full_path = folder_path.join(file);
         let get_object = self
-            .client
-            .get_object()
-            .bucket(bucket_name)
-            .key(full_path.to_string_lossy())
-            .send()
+            .send_with_retry("get_object", || {
+                self.client
+                    .get_object()
+                    .bucket(bucket_name)
+                    .key(full_path.to_string_lossy())
+                    .send()
+            })
             .await
             .map_err(|e| {
                 let error_str = format!(
@@ -495,31 +1144,44 @@ impl<'a> AWSFileIO<'a> {
                 );
                 self.project_logger.log_error(&error_str);
                 AWSLoadFileError::SdkError(e)
-            });
-        let byte = get_object?.body.collect().await.map_err(|e| {
+            })?;
+        let content_length = u64::try_from(get_object.content_length()).ok();
+        let progress_tracker = ProgressTracker::new(progress_func, content_length);
+        let mut body = get_object.body;
+        let full_local_path = local_path.join(local_file);
+        let log_io_error = |e: std::io::Error| {
+            let error_str = format!(
+                "Unable to download to file {}. {e}",
+                full_local_path.display()
+            );
+            self.project_logger.log_error(&error_str);
+            AWSLoadFileError::IOError(e)
+        };
+        if let Some(disk_guard) = self.disk_guard.as_deref() {
+            disk_guard
+                .check_space(local_path, content_length.unwrap_or_default())
+                .map_err(AWSLoadFileError::IOError)?;
+        }
+        let mut local_file = File::create(&full_local_path).await.map_err(log_io_error)?;
+        let mut transfer_progress = progress_tracker.add_bytes(0);
+        while let Some(chunk) = body.try_next().await.map_err(|e| {
             let error_str = format!(
                 "Unable to read the file {file} from folder {} in bucket {bucket_name}. {e}",
                 folder_path.display()
             );
             self.project_logger.log_error(&error_str);
             AWSLoadFileError::ByteStreamError(e)
-        });
-        let full_local_path = local_path.join(local_file);
-        tokio::fs::write(&full_local_path, byte?.to_vec())
-            .await
-            .map_or_else(
-                |e| {
-                    let error_str =
-                        format!("Unable to download to file {}. {e}", full_path.display());
-                    self.project_logger.log_error(&error_str);
-                    Err(AWSLoadFileError::IOError(e))
-                },
-                |_| {
-                    let debug_str = format!("File {} downloaded.", full_path.display());
-                    self.project_logger.log_debug(&debug_str);
-                    Ok(())
-                },
-            )
+        })? {
+            local_file.write_all(&chunk).await.map_err(log_io_error)?;
+            transfer_progress = progress_tracker.add_bytes(chunk.len() as u64);
+        }
+        local_file.flush().await.map_err(log_io_error)?;
+        let debug_str = format!(
+            "File {} downloaded. {transfer_progress}",
+            full_path.display()
+        );
+        self.project_logger.log_debug(&debug_str);
+        Ok(())
     }
 
     pub async fn upload_file(
@@ -530,6 +1192,29 @@ impl<'a> AWSFileIO<'a> {
         local_path: &Path,
         local_file: &str,
     ) -> Result<(), AWSWriteFileError> {
+        self.upload_file_with_progress(bucket_name, folder_path, file, local_path, local_file, None)
+            .await
+    }
+
+    #[tracing::instrument(name = "s3_operation", skip_all, fields(operation = "upload", bucket = bucket_name, key = %folder_path.join(file).display()))]
+    pub async fn upload_file_with_progress(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+        file: &str,
+        local_path: &Path,
+        local_file: &str,
+        progress_func: Option<&ProgressFunc>,
+    ) -> Result<(), AWSWriteFileError> {
+        if DryRun::skip(
+            &self.project_logger,
+            &format!(
+                "writing file {} in bucket {bucket_name}",
+                folder_path.join(file).display()
+            ),
+        ) {
+            return Ok(());
+        }
         let full_local_path = local_path.join(local_file);
         let full_path = folder_path.join(file);
         let temp_file = File::open(&full_local_path).await.map_err(|e| {
@@ -550,18 +1235,25 @@ impl<'a> AWSFileIO<'a> {
             AWSWriteFileError::IOError(e)
         });
         let metadata = metadata?;
+        let progress_tracker = ProgressTracker::new(progress_func, Some(metadata.len()));
         if metadata.len() >= LIMIT_SINGLE_UPLOAD as u64 {
             self.upload_multipart(
-                &mut temp_file,
                 metadata.len() as usize,
                 bucket_name,
                 &full_path,
                 &full_local_path,
+                &progress_tracker,
             )
             .await
         } else {
-            self.upload_single_object(&mut temp_file, bucket_name, &full_path, &full_local_path)
-                .await
+            self.upload_single_object(
+                &mut temp_file,
+                bucket_name,
+                &full_path,
+                &full_local_path,
+                &progress_tracker,
+            )
+            .await
         }
     }
 
@@ -571,6 +1263,7 @@ impl<'a> AWSFileIO<'a> {
         bucket_name: &str,
         full_path: &Path,
         full_local_path: &Path,
+        progress_tracker: &ProgressTracker<'_>,
     ) -> Result<(), AWSWriteFileError> {
         let mut bytes = Vec::new();
         if let Err(e) = temp_file.read_to_end(&mut bytes).await {
@@ -581,43 +1274,150 @@ impl<'a> AWSFileIO<'a> {
             self.project_logger.log_error(&error_str);
             return Err(AWSWriteFileError::IOError(e));
         };
-        let content = ByteStream::from(bytes);
-        self.client
-            .put_object()
-            .bucket(bucket_name)
-            .key(full_path.to_string_lossy())
-            .body(content)
-            .send()
+        let num_bytes = bytes.len() as u64;
+        let content_body = Bytes::from(bytes);
+        self.send_with_retry("put_object", || {
+            self.client
+                .put_object()
+                .bucket(bucket_name)
+                .key(full_path.to_string_lossy())
+                .set_tagging(self.write_options.get_tagging())
+                .set_metadata(self.write_options.get_metadata())
+                .set_storage_class(self.write_options.get_storage_class())
+                .body(clone_body(&content_body))
+                .send()
+        })
+        .await
+        .map_or_else(
+            |e| {
+                let error_str = format!("Unable to upload to file {}. {e}", full_path.display());
+                self.project_logger.log_error(&error_str);
+                Err(AWSWriteFileError::SdkError(e))
+            },
+            |_| {
+                let transfer_progress = progress_tracker.add_bytes(num_bytes);
+                let debug_str =
+                    format!("File {} uploaded. {transfer_progress}", full_path.display());
+                self.project_logger.log_debug(&debug_str);
+                Ok(())
+            },
+        )
+    }
+
+    async fn read_part(
+        full_local_path: &Path,
+        offset: u64,
+        part_size: usize,
+    ) -> std::io::Result<Vec<u8>> {
+        let mut part_file = File::open(full_local_path).await?;
+        part_file.seek(SeekFrom::Start(offset)).await?;
+        let mut part_data = vec![0; part_size];
+        part_file.read_exact(&mut part_data).await?;
+        Ok(part_data)
+    }
+
+    async fn upload_part(
+        &self,
+        bucket_name: &str,
+        full_path: &Path,
+        full_local_path: &Path,
+        upload_id: &str,
+        (part_number, offset, part_size): (i32, u64, usize),
+        progress_tracker: &ProgressTracker<'_>,
+    ) -> Result<CompletedPart, AWSWriteFileError> {
+        let part_data = Self::read_part(full_local_path, offset, part_size)
             .await
-            .map_or_else(
-                |e| {
-                    let error_str =
-                        format!("Unable to upload to file {}. {e}", full_path.display());
-                    self.project_logger.log_error(&error_str);
-                    Err(AWSWriteFileError::SdkError(e))
-                },
-                |_| {
-                    let debug_str = format!("File {} uploaded.", full_path.display());
-                    self.project_logger.log_debug(&debug_str);
-                    Ok(())
-                },
-            )
+            .map_err(|e| {
+                let error_str = format!(
+                    "Unable to read the local file {} part {part_number} as bytes. {e}",
+                    full_local_path.display()
+                );
+                self.project_logger.log_error(&error_str);
+                AWSWriteFileError::IOError(e)
+            })?;
+        let content_body = Bytes::from(part_data);
+        let uploaded_part = self
+            .send_with_retry("upload_part", || {
+                self.client
+                    .upload_part()
+                    .bucket(bucket_name)
+                    .key(full_path.to_string_lossy())
+                    .part_number(part_number)
+                    .upload_id(upload_id)
+                    .body(clone_body(&content_body))
+                    .send()
+            })
+            .await
+            .map_err(|e| {
+                let error_str = format!(
+                    "Unable to upload the file {} part {part_number}. {e}",
+                    full_path.display()
+                );
+                self.project_logger.log_error(&error_str);
+                AWSWriteFileError::UploadPartError(e)
+            })?;
+        let e_tag = uploaded_part.e_tag().ok_or_else(|| {
+            let error_str = format!(
+                "Unable to find e-tag for file {} part {part_number}",
+                full_path.display()
+            );
+            self.project_logger.log_error(&error_str);
+            AWSWriteFileError::MissingETagError(error_str)
+        })?;
+        let transfer_progress = progress_tracker.add_bytes(part_size as u64);
+        let debug_str = format!(
+            "File {} part {part_number} uploaded. {transfer_progress}",
+            full_path.display()
+        );
+        self.project_logger.log_debug(&debug_str);
+        Ok(CompletedPart::builder()
+            .part_number(part_number)
+            .e_tag(e_tag)
+            .build())
+    }
+
+    async fn abort_multipart_upload(&self, bucket_name: &str, full_path: &Path, upload_id: &str) {
+        if let Err(e) = self
+            .send_with_retry("abort_multipart_upload", || {
+                self.client
+                    .abort_multipart_upload()
+                    .bucket(bucket_name)
+                    .key(full_path.to_string_lossy())
+                    .upload_id(upload_id)
+                    .send()
+            })
+            .await
+        {
+            let error_str = format!(
+                "Unable to abort the multipart upload of {}. Orphaned parts may remain. {e}",
+                full_path.display()
+            );
+            self.project_logger.log_error(&error_str);
+        }
     }
 
+    // Uploads up to multipart_concurrency parts at a time. Each part is read from its own file
+    // handle, so at most multipart_concurrency parts are held in memory.
     async fn upload_multipart(
         &self,
-        temp_file: &mut File,
         file_size: usize,
         bucket_name: &str,
         full_path: &Path,
         full_local_path: &Path,
+        progress_tracker: &ProgressTracker<'_>,
     ) -> Result<(), AWSWriteFileError> {
+        let start_time = Instant::now();
         let upload_id = self
-            .client
-            .create_multipart_upload()
-            .bucket(bucket_name)
-            .key(full_path.to_string_lossy())
-            .send()
+            .send_with_retry("create_multipart_upload", || {
+                self.client
+                    .create_multipart_upload()
+                    .bucket(bucket_name)
+                    .key(full_path.to_string_lossy())
+                    .set_tagging(self.write_options.get_tagging())
+                    .set_metadata(self.write_options.get_metadata())
+                    .set_storage_class(self.write_options.get_storage_class())
+                    .send()
+            })
             .await
             .map_or_else(
                 |e| {
@@ -640,138 +1440,416 @@ impl<'a> AWSFileIO<'a> {
                     )
                 },
             );
-        let mut part_number = 1;
-        let mut offset: u64 = 0;
-        let mut completed_parts = CompletedMultipartUpload::builder();
-
         let upload_id = upload_id?;
-
-        while offset < file_size as u64 {
-            let mut part_data = vec![0; MULTIPART_SIZE];
-            match temp_file.seek(SeekFrom::Start(offset)).await {
-                Ok(_) => {
-                    let bytes_to_read = if (file_size - offset as usize) < MULTIPART_SIZE {
-                        temp_file.read_to_end(&mut part_data).await
-                    } else {
-                        temp_file.read_exact(&mut part_data).await
-                    };
-                    match bytes_to_read {
-                        Ok(bytes_read) => {
-                            if bytes_read == 0 {
-                                break;
-                            }
-                            let content = ByteStream::from(part_data);
-                            match self
-                                .client
-                                .upload_part()
-                                .bucket(bucket_name)
-                                .key(full_path.to_string_lossy())
-                                .part_number(part_number)
-                                .upload_id(&upload_id)
-                                .body(content)
-                                .send()
-                                .await
-                            {
-                                Ok(uploaded_part) => {
-                                    let e_tag = uploaded_part.e_tag().unwrap_or_else(|| {
-                                        panic!(
-                                            "Unable to find e-tag for file {} part {part_number}",
-                                            full_path.display()
-                                        )
-                                    });
-                                    let completed_part = CompletedPart::builder()
-                                        .part_number(part_number)
-                                        .e_tag(e_tag)
-                                        .build();
-                                    completed_parts = completed_parts.parts(completed_part);
-                                    let debug_str = format!(
-                                        "File {} part {part_number} uploaded.",
-                                        full_path.display()
-                                    );
-                                    self.project_logger.log_debug(&debug_str);
-                                }
-                                Err(e) => {
-                                    let error_str = format!(
-                                        "Unable to upload the file {} part {part_number}. {e}",
-                                        full_path.display()
-                                    );
-                                    self.project_logger.log_error(&error_str);
-                                    return Err(AWSWriteFileError::UploadPartError(e));
-                                }
-                            };
-                            offset += bytes_read as u64;
-                            part_number += 1;
-                        }
-                        Err(e) => {
-                            let error_str = format!(
-                                "Unable to read the local file {} part {part_number} as bytes. {e}",
-                                &full_local_path.display()
-                            );
-                            self.project_logger.log_error(&error_str);
-                            return Err(AWSWriteFileError::IOError(e));
-                        }
-                    }
-                }
-                Err(e) => {
-                    let error_str = format!(
-                        "Unable to seek position for file {}. {e}",
-                        full_local_path.display()
-                    );
-                    self.project_logger.log_error(&error_str);
-                    return Err(AWSWriteFileError::IOError(e));
-                }
-            };
+        let part_list: Vec<(i32, u64, usize)> = (0..file_size)
+            .step_by(MULTIPART_SIZE)
+            .enumerate()
+            .map(|(index, offset)| {
+                (
+                    index as i32 + 1,
+                    offset as u64,
+                    MULTIPART_SIZE.min(file_size - offset),
+                )
+            })
+            .collect();
+        let uploaded_parts: Result<Vec<CompletedPart>, AWSWriteFileError> = stream::iter(part_list)
+            .map(|part| {
+                self.upload_part(
+                    bucket_name,
+                    full_path,
+                    full_local_path,
+                    &upload_id,
+                    part,
+                    progress_tracker,
+                )
+            })
+            .buffer_unordered(self.multipart_concurrency)
+            .try_collect()
+            .await;
+        let mut uploaded_parts = match uploaded_parts {
+            Ok(uploaded_parts) => uploaded_parts,
+            Err(e) => {
+                self.abort_multipart_upload(bucket_name, full_path, &upload_id)
+                    .await;
+                return Err(e);
+            }
+        };
+        uploaded_parts.sort_by_key(|part| part.part_number());
+        let completed_upload = CompletedMultipartUpload::builder()
+            .set_parts(Some(uploaded_parts))
+            .build();
+        let complete_result = self
+            .send_with_retry("complete_multipart_upload", || {
+                self.client
+                    .complete_multipart_upload()
+                    .bucket(bucket_name)
+                    .key(full_path.to_string_lossy())
+                    .upload_id(&upload_id)
+                    .multipart_upload(completed_upload.clone())
+                    .send()
+            })
+            .await;
+        match complete_result {
+            Ok(_) => {
+                let elapsed_sec = start_time.elapsed().as_secs_f64();
+                let debug_str = format!(
+                    "File {} multipart uploaded in {elapsed_sec:.1} s at {:.1} MB/s.",
+                    full_path.display(),
+                    file_size as f64 / (1024.0 * 1024.0) / elapsed_sec.max(f64::EPSILON)
+                );
+                self.project_logger.log_debug(&debug_str);
+                Ok(())
+            }
+            Err(e) => {
+                let error_str = format!(
+                    "Unable to upload to file {} multipart. {e}",
+                    full_path.display()
+                );
+                self.project_logger.log_error(&error_str);
+                self.abort_multipart_upload(bucket_name, full_path, &upload_id)
+                    .await;
+                Err(AWSWriteFileError::CompleteMultipartUploadError(e))
+            }
         }
+    }
+
+    #[tracing::instrument(name = "s3_operation", skip_all, fields(operation = "copy", bucket = bucket_name, key = %target_folder.join(target_file).display(), source_key = %source_folder.join(source_file).display()))]
+    pub async fn copy_file(
+        &self,
+        bucket_name: &str,
+        source_folder: &Path,
+        source_file: &str,
+        target_folder: &Path,
+        target_file: &str,
+    ) -> Result<(), SdkError<CopyObjectError>> {
+        let source_path = source_folder.join(source_file);
+        let target_path = target_folder.join(target_file);
+        self.send_with_retry("copy_object", || {
+            self.client
+                .copy_object()
+                .bucket(bucket_name)
+                .copy_source(format!("{bucket_name}/{}", source_path.to_string_lossy()))
+                .key(target_path.to_string_lossy())
+                .send()
+        })
+        .await
+        .map_or_else(
+            |e| {
+                let error_str = format!(
+                    "Unable to copy file {} to {}. {e}",
+                    source_path.display(),
+                    target_path.display()
+                );
+                self.project_logger.log_error(&error_str);
+                Err(e)
+            },
+            |_| {
+                let debug_str = format!(
+                    "File {} copied to {}.",
+                    source_path.display(),
+                    target_path.display()
+                );
+                self.project_logger.log_debug(&debug_str);
+                Ok(())
+            },
+        )
+    }
 
-        self.client
-            .complete_multipart_upload()
-            .bucket(bucket_name)
-            .key(full_path.to_string_lossy())
-            .upload_id(&upload_id)
-            .multipart_upload(completed_parts.build())
-            .send()
+    pub async fn get_object_metadata(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+        file: &str,
+    ) -> crate::error::Result<S3ObjectMetadata> {
+        let full_path = folder_path.join(file);
+        let head_object = self
+            .send_with_retry("head_object", || {
+                self.client
+                    .head_object()
+                    .bucket(bucket_name)
+                    .key(full_path.to_string_lossy())
+                    .send()
+            })
             .await
-            .map_or_else(
-                |e| {
-                    let error_str = format!(
-                        "Unable to upload to file {} multipart. {e}",
-                        full_path.display()
-                    );
-                    self.project_logger.log_error(&error_str);
-                    Err(AWSWriteFileError::CompleteMultipartUploadError(e))
-                },
-                |_| {
-                    let debug_str = format!("File {} multipart uploaded.", full_path.display());
-                    self.project_logger.log_debug(&debug_str);
-                    Ok(())
-                },
-            )
+            .map_err(|e| {
+                let error_str = format!(
+                    "Unable to get the metadata of {} in bucket {bucket_name}. {e}",
+                    full_path.display()
+                );
+                self.project_logger.log_error(&error_str);
+                e
+            })?;
+        Ok(S3ObjectMetadata {
+            content_length: head_object.content_length(),
+            last_modified: head_object.last_modified().map(|last_modified| {
+                time_operation::utc_date_time_from_timestamp(
+                    last_modified.secs(),
+                    SecPrecision::Sec,
+                )
+            }),
+            e_tag: head_object.e_tag().map(str::to_string),
+            storage_class: head_object
+                .storage_class()
+                .map(|storage_class| storage_class.as_str().to_string()),
+            metadata: head_object.metadata().cloned().unwrap_or_default(),
+        })
     }
 
-    pub async fn delete_file(&self, bucket_name: &str, folder_path: &Path, file: &str) {
+    pub async fn get_object_tags(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+        file: &str,
+    ) -> crate::error::Result<Vec<(String, String)>> {
         let full_path = folder_path.join(file);
-        match self
-            .client
-            .delete_object()
-            .bucket(bucket_name)
-            .key(full_path.to_string_lossy())
-            .send()
+        let tagging = self
+            .send_with_retry("get_object_tagging", || {
+                self.client
+                    .get_object_tagging()
+                    .bucket(bucket_name)
+                    .key(full_path.to_string_lossy())
+                    .send()
+            })
             .await
-        {
-            Ok(_) => {
-                let debug_str = format!("File {} deleted.", full_path.display());
-                self.project_logger.log_debug(&debug_str);
-            }
-            Err(e) => {
-                let error_str = format!("Unable to delete to file {}. {e}", full_path.display());
+            .map_err(|e| {
+                let error_str = format!(
+                    "Unable to get the tags of {} in bucket {bucket_name}. {e}",
+                    full_path.display()
+                );
                 self.project_logger.log_error(&error_str);
-                panic!("{}", &error_str);
+                e
+            })?;
+        Ok(tagging
+            .tag_set()
+            .unwrap_or_default()
+            .iter()
+            .map(|tag| {
+                (
+                    tag.key().unwrap_or_default().to_string(),
+                    tag.value().unwrap_or_default().to_string(),
+                )
+            })
+            .collect())
+    }
+
+    // Replaces all existing tags of the object.
+    pub async fn set_object_tags(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+        file: &str,
+        tags: &[(&str, &str)],
+    ) -> crate::error::Result<()> {
+        let full_path = folder_path.join(file);
+        if DryRun::skip(
+            &self.project_logger,
+            &format!("tagging {} in bucket {bucket_name}", full_path.display()),
+        ) {
+            return Ok(());
+        }
+        let tag_set: Vec<Tag> = tags
+            .iter()
+            .map(|(key, value)| Tag::builder().key(*key).value(*value).build())
+            .collect();
+        self.send_with_retry("put_object_tagging", || {
+            self.client
+                .put_object_tagging()
+                .bucket(bucket_name)
+                .key(full_path.to_string_lossy())
+                .tagging(
+                    Tagging::builder()
+                        .set_tag_set(Some(tag_set.clone()))
+                        .build(),
+                )
+                .send()
+        })
+        .await
+        .map(|_| {
+            let debug_str = format!("Tags of {} updated.", full_path.display());
+            self.project_logger.log_debug(&debug_str);
+        })
+        .map_err(|e| {
+            let error_str = format!(
+                "Unable to set the tags of {} in bucket {bucket_name}. {e}",
+                full_path.display()
+            );
+            self.project_logger.log_error(&error_str);
+            e
+        })
+        .with_context(|| format!("Unable to tag file {}", full_path.display()))
+    }
+
+    #[tracing::instrument(name = "s3_operation", skip_all, fields(operation = "delete", bucket = bucket_name, key = %folder_path.join(file).display()))]
+    pub async fn delete_file(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+        file: &str,
+    ) -> crate::error::Result<()> {
+        let full_path = folder_path.join(file);
+        self.send_with_retry("delete_object", || {
+            self.client
+                .delete_object()
+                .bucket(bucket_name)
+                .key(full_path.to_string_lossy())
+                .send()
+        })
+        .await
+        .map(|_| {
+            let debug_str = format!("File {} deleted.", full_path.display());
+            self.project_logger.log_debug(&debug_str);
+        })
+        .map_err(|e| {
+            let error_str = format!("Unable to delete to file {}. {e}", full_path.display());
+            self.project_logger.log_error(&error_str);
+            e
+        })
+        .with_context(|| format!("Unable to delete file {}", full_path.display()))
+    }
+
+    // Deletes the keys with DeleteObjects, at most 1000 keys per request. Returns the keys that
+    // could not be deleted.
+    async fn delete_keys_in_batches(
+        &self,
+        bucket_name: &str,
+        key_list: &[String],
+    ) -> crate::error::Result<Vec<String>> {
+        let mut num_deleted = 0;
+        let mut failed_key_list = Vec::new();
+        for key_batch in key_list.chunks(Self::MAX_DELETE_KEY) {
+            let object_list: Vec<ObjectIdentifier> = key_batch
+                .iter()
+                .map(|key| ObjectIdentifier::builder().key(key).build())
+                .collect();
+            let delete_output = self
+                .send_with_retry("delete_objects", || {
+                    self.client
+                        .delete_objects()
+                        .bucket(bucket_name)
+                        .delete(
+                            Delete::builder()
+                                .set_objects(Some(object_list.clone()))
+                                .quiet(true)
+                                .build(),
+                        )
+                        .send()
+                })
+                .await
+                .map_err(|e| {
+                    let error_str =
+                        format!("Unable to delete objects in bucket {bucket_name}. {e}");
+                    self.project_logger.log_error(&error_str);
+                    e
+                })?;
+            let delete_errors = delete_output.errors().unwrap_or_default();
+            for delete_error in delete_errors {
+                let error_str = format!(
+                    "Unable to delete {} in bucket {bucket_name}. {}",
+                    delete_error.key().unwrap_or_default(),
+                    delete_error.message().unwrap_or_default()
+                );
+                self.project_logger.log_error(&error_str);
+                failed_key_list.push(delete_error.key().unwrap_or_default().to_string());
             }
+            num_deleted += key_batch.len() - delete_errors.len();
+            let debug_str = format!(
+                "{num_deleted} of {} objects deleted in bucket {bucket_name}.",
+                key_list.len()
+            );
+            self.project_logger.log_debug(&debug_str);
+        }
+        Ok(failed_key_list)
+    }
+
+    // Deletes the objects under the prefix last modified before the cutoff. With dry_run the
+    // objects are only counted.
+    pub async fn cleanup_old_objects<T: TimeZone>(
+        &self,
+        bucket_name: &str,
+        prefix: &Path,
+        older_than: &DateTime<T>,
+        dry_run: bool,
+    ) -> crate::error::Result<CleanupReport> {
+        let dry_run = dry_run || DryRun::is_enabled();
+        let object_output_list = self.get_elements_in_folder(bucket_name, prefix).await?;
+        let old_object_list: Vec<(String, i64)> = object_output_list
+            .iter()
+            .flat_map(|object_output| object_output.contents().unwrap_or_default())
+            .filter(|element| {
+                element.last_modified().is_some() && !self.filter_element_after(element, older_than)
+            })
+            .filter_map(|element| element.key().map(|key| (key.to_string(), element.size())))
+            .collect();
+        let failed_key_list = if dry_run {
+            Vec::new()
+        } else {
+            let key_list: Vec<String> =
+                old_object_list.iter().map(|(key, _)| key.clone()).collect();
+            self.delete_keys_in_batches(bucket_name, &key_list).await?
         };
+        let cleanup_report = old_object_list
+            .iter()
+            .filter(|(key, _)| !failed_key_list.contains(key))
+            .fold(CleanupReport::default(), |mut cleanup_report, (_, size)| {
+                cleanup_report.num_deleted += 1;
+                cleanup_report.freed_bytes += *size as u64;
+                cleanup_report
+            });
+        let info_str = format!(
+            "{}{} old objects removed from {} in bucket {bucket_name}, freeing {} bytes.",
+            if dry_run { "Dry run. " } else { "" },
+            cleanup_report.num_deleted,
+            prefix.display(),
+            cleanup_report.freed_bytes
+        );
+        self.project_logger.log_info(&info_str);
+        Ok(cleanup_report)
     }
 
-    pub async fn delete_folder(&self, bucket_name: &str, folder_path: &Path) {
-        self.delete_file(bucket_name, folder_path, "").await;
+    // Deletes every object under the prefix, including the folder marker. With dry_run the
+    // objects are only counted. Returns the number of objects deleted.
+    pub async fn delete_prefix(
+        &self,
+        bucket_name: &str,
+        prefix: &Path,
+        dry_run: bool,
+    ) -> crate::error::Result<usize> {
+        let dry_run = dry_run || DryRun::is_enabled();
+        let object_output_list = self.get_elements_in_folder(bucket_name, prefix).await?;
+        let key_list: Vec<String> = object_output_list
+            .iter()
+            .flat_map(|object_output| object_output.contents().unwrap_or_default())
+            .filter_map(|element| element.key().map(str::to_string))
+            .collect();
+        let num_deleted = if dry_run {
+            key_list.len()
+        } else {
+            let failed_key_list = self.delete_keys_in_batches(bucket_name, &key_list).await?;
+            if !failed_key_list.is_empty() {
+                return Err(crate::error::Error::AwsS3(format!(
+                    "Unable to delete {} of {} objects under {}",
+                    failed_key_list.len(),
+                    key_list.len(),
+                    prefix.display()
+                )));
+            }
+            key_list.len()
+        };
+        let info_str = format!(
+            "{}{num_deleted} objects deleted under {} in bucket {bucket_name}.",
+            if dry_run { "Dry run. " } else { "" },
+            prefix.display()
+        );
+        self.project_logger.log_info(&info_str);
+        Ok(num_deleted)
+    }
+
+    pub async fn delete_folder(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+    ) -> crate::error::Result<usize> {
+        self.delete_prefix(bucket_name, folder_path, false).await
     }
 }
 
@@ -815,6 +1893,7 @@ pub enum AWSWriteFileError {
     CreateMultipartUploadError(SdkError<CreateMultipartUploadError>),
     UploadPartError(SdkError<UploadPartError>),
     CompleteMultipartUploadError(SdkError<CompleteMultipartUploadError>),
+    MissingETagError(String),
 }
 
 impl From<SdkError<PutObjectError>> for AWSWriteFileError {
@@ -864,6 +1943,18 @@ impl APIKey {
     const PROJECT_KEY: &str = "SCTYS_PROJECT";
     const API_KEY_PATH: &str = "Secret/secret_sctys_rust_utilities";
     const API_KEY_FILE: &str = "aws_s3_api.toml";
+    const AWS_API_ID_SECRET: &str = "aws_api_id";
+    const AWS_API_SECRET_SECRET: &str = "aws_api_secret";
+    const AWS_API_REGION_SECRET: &str = "aws_api_region";
+
+    fn try_load_apikey() -> crate::error::Result<APIKey> {
+        let full_api_path = Path::new(&env::var(Self::PROJECT_KEY).unwrap_or_default())
+            .join(Self::API_KEY_PATH)
+            .join(Self::API_KEY_FILE);
+        let api_str = fs::read_to_string(&full_api_path)
+            .with_context(|| format!("Unable to load the api file {}", full_api_path.display()))?;
+        toml::from_str(&api_str).context("Unable to parse the api file")
+    }
 
     fn load_apikey() -> APIKey {
         let full_api_path =
@@ -889,6 +1980,53 @@ mod tests {
     use crate::file_io::FileIO;
     use log::LevelFilter;
 
+    #[test]
+    fn test_transfer_progress() {
+        let transfer_progress = TransferProgress {
+            transferred_bytes: 256 * 1024 * 1024,
+            total_bytes: Some(1024 * 1024 * 1024),
+            elapsed: Duration::from_secs(2),
+        };
+        assert_eq!(transfer_progress.get_percent(), Some(25.0));
+        assert_eq!(transfer_progress.get_mb_per_sec(), 128.0);
+        assert_eq!(
+            transfer_progress.to_string(),
+            "25.0% (256.0 of 1024.0 MB) at 128.0 MB/s"
+        );
+    }
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let mut retry_policy = S3RetryPolicy::new();
+        retry_policy.set_initial_backoff(Duration::from_millis(100));
+        retry_policy.set_max_backoff(Duration::from_millis(500));
+        let first_backoff = retry_policy.get_backoff(1);
+        assert!(first_backoff >= Duration::from_millis(50));
+        assert!(first_backoff <= Duration::from_millis(100));
+        let third_backoff = retry_policy.get_backoff(3);
+        assert!(third_backoff >= Duration::from_millis(200));
+        assert!(third_backoff <= Duration::from_millis(400));
+        assert!(retry_policy.get_backoff(10) <= Duration::from_millis(500));
+        assert!(S3RetryPolicy::is_retryable::<PutObjectError>(
+            &SdkError::timeout_error("timeout")
+        ));
+        assert!(!S3RetryPolicy::is_retryable::<PutObjectError>(
+            &SdkError::construction_failure("invalid request")
+        ));
+    }
+
+    #[test]
+    fn test_write_options_tagging() {
+        let mut write_options = S3WriteOptions::new();
+        assert_eq!(write_options.get_tagging(), None);
+        write_options.add_tag("source", "raw scrape");
+        write_options.add_tag("archive", "true");
+        assert_eq!(
+            write_options.get_tagging(),
+            Some("source=raw%20scrape&archive=true".to_string())
+        );
+    }
+
     #[tokio::test]
     async fn test_check_bucket_exist() {
         let logger_name = "test_aws_file_io";
@@ -1,25 +1,101 @@
+use crate::disk_guard::DiskGuard;
+use crate::dry_run::DryRun;
 use crate::logger::ProjectLogger;
+use crate::shared::Shared;
+use crate::shutdown::ShutdownSignal;
 use crate::time_operation;
 use chrono::{DateTime, TimeZone, Utc};
+use glob::Pattern;
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
 use polars::frame::DataFrame;
 use polars::io::{SerReader, SerWriter};
 use polars::lazy::frame::{LazyCsvReader, LazyFrame, ScanArgsParquet};
 use polars::prelude::*;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, DirEntry};
 use std::fs::{File, ReadDir};
+use std::future::Future;
 use std::io::{Error, ErrorKind, Result};
-use std::path::Path;
-use std::time::SystemTime;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
 use walkdir::WalkDir;
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CleanupReport {
+    pub num_deleted: usize,
+    pub freed_bytes: u64,
+}
+
+// A file found by the listing helpers. For the S3 objects the path is the key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchEvent {
+    Created,
+    Modified,
+}
+
+impl WatchEvent {
+    // The files moved into the folder count as created, as the writers often save to a temporary
+    // file and rename it at the end.
+    fn from_event_kind(event_kind: &EventKind) -> Option<Self> {
+        match event_kind {
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) => Some(Self::Created),
+            EventKind::Modify(ModifyKind::Metadata(_)) => None,
+            EventKind::Modify(_) => Some(Self::Modified),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct FileIO<'a> {
-    project_logger: &'a ProjectLogger,
+    project_logger: Shared<'a, ProjectLogger>,
+    disk_guard: Option<Shared<'a, DiskGuard<'a>>>,
 }
 
 impl<'a> FileIO<'a> {
-    pub fn new(project_logger: &'a ProjectLogger) -> Self {
-        Self { project_logger }
+    pub const PARTITION_FILE: &'static str = "part-0.parquet";
+    pub const NULL_PARTITION: &'static str = "__HIVE_DEFAULT_PARTITION__";
+    const WATCH_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+    const INVALID_FILE_NAME_CHARS: [char; 18] = [
+        '/', '\\', ':', '*', '?', '"', '<', '>', '|', '#', '%', '{', '}', '^', '~', '[', ']', '`',
+    ];
+    const RESERVED_FILE_NAMES: [&'static str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    const MAX_FILE_NAME_BYTES: usize = 255;
+    const FILE_NAME_HASH_LEN: usize = 8;
+
+    pub fn new(project_logger: impl Into<Shared<'a, ProjectLogger>>) -> Self {
+        Self {
+            project_logger: project_logger.into(),
+            disk_guard: None,
+        }
+    }
+
+    // Checked before every file written afterwards.
+    pub fn set_disk_guard(&mut self, disk_guard: impl Into<Shared<'a, DiskGuard<'a>>>) {
+        self.disk_guard = Some(disk_guard.into());
+    }
+
+    fn check_disk_space(&self, folder_path: &Path, write_size: usize) -> Result<()> {
+        match self.disk_guard.as_deref() {
+            Some(disk_guard) => disk_guard
+                .check_space(folder_path, write_size as u64)
+                .map(|_| ()),
+            None => Ok(()),
+        }
     }
 
     pub fn check_folder_exist(folder_path: &Path) -> bool {
@@ -31,6 +107,110 @@ impl<'a> FileIO<'a> {
         full_path_file.is_file()
     }
 
+    // Makes a file name taken from a url safe on every filesystem and as an S3 key. The
+    // separators, the characters invalid on Windows or S3 and the leading dots are replaced, so
+    // the name can never leave its folder. A name that has to be changed gets the hash of the
+    // original appended, so that two names differing only in the replaced characters, e.g.
+    // a/b and a:b, do not collide.
+    pub fn sanitize_file_name(file_name: &str) -> String {
+        let replaced: String = file_name
+            .chars()
+            .map(|c| {
+                if c.is_control() || Self::INVALID_FILE_NAME_CHARS.contains(&c) {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        let mut sanitized = replaced
+            .trim()
+            .trim_start_matches('.')
+            .trim_end_matches('.')
+            .to_string();
+        let stem = sanitized.split('.').next().unwrap_or_default();
+        if sanitized.is_empty()
+            || Self::RESERVED_FILE_NAMES
+                .iter()
+                .any(|reserved_name| reserved_name.eq_ignore_ascii_case(stem))
+        {
+            sanitized = format!("_{sanitized}");
+        }
+        if sanitized == file_name && sanitized.len() <= Self::MAX_FILE_NAME_BYTES {
+            return sanitized;
+        }
+        let file_name_hash: String = Sha256::digest(file_name.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .take(Self::FILE_NAME_HASH_LEN / 2)
+            .collect();
+        let (stem, extension) = match sanitized.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{extension}")),
+            _ => (sanitized.as_str(), String::new()),
+        };
+        let max_stem_bytes = Self::MAX_FILE_NAME_BYTES
+            .saturating_sub(extension.len() + Self::FILE_NAME_HASH_LEN + 1);
+        let stem_end = stem
+            .char_indices()
+            .map(|(index, c)| index + c.len_utf8())
+            .take_while(|end| *end <= max_stem_bytes)
+            .last()
+            .unwrap_or_default();
+        format!("{}_{file_name_hash}{extension}", &stem[..stem_end])
+    }
+
+    // As sanitize_file_name for a relative file path with sub folders separated by slashes, e.g.
+    // from a nested FileNameTemplate.
+    pub fn sanitize_file_path(file_path: &str) -> String {
+        file_path
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .map(Self::sanitize_file_name)
+            .collect::<Vec<String>>()
+            .join("/")
+    }
+
+    // Joins a relative file path, which may include sub folders, to the folder. Absolute paths
+    // and .. are rejected so that the file stays inside the folder.
+    pub fn safe_join(folder_path: &Path, file: &str) -> Result<PathBuf> {
+        let file_path = Path::new(file);
+        let is_safe = file_path.file_name().is_some()
+            && file_path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if is_safe {
+            Ok(folder_path.join(file_path))
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unsafe file name {file} in {}.", folder_path.display()),
+            ))
+        }
+    }
+
+    fn get_write_path(&self, folder_path: &Path, file: &str) -> Result<PathBuf> {
+        Self::safe_join(folder_path, file).map_err(|e| {
+            self.project_logger.log_error(&e.to_string());
+            e
+        })
+    }
+
+    // Creates the sub folders of a file name with sub folders before it is written.
+    pub fn create_parent_folder(&self, folder_path: &Path, file: &str) -> Result<()> {
+        let full_path = self.get_write_path(folder_path, file)?;
+        match full_path.parent() {
+            Some(parent_path) if !parent_path.is_dir() && !DryRun::is_enabled() => {
+                fs::create_dir_all(parent_path).map_err(|e| {
+                    let error_str =
+                        format!("Unable to create folder {}. {e}", parent_path.display());
+                    self.project_logger.log_error(&error_str);
+                    e
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn create_directory_if_not_exists(&self, folder_path: &Path) -> Result<()> {
         if !folder_path.is_dir() {
             fs::create_dir_all(folder_path).map_or_else(
@@ -52,6 +232,7 @@ impl<'a> FileIO<'a> {
         }
     }
 
+    #[tracing::instrument(name = "file_operation", skip_all, fields(operation = "delete", path = %folder_path.join(file).display()))]
     pub fn remove_file(&self, folder_path: &Path, file: &str) -> Result<()> {
         let full_path_file = Path::new(folder_path).join(file);
         fs::remove_file(&full_path_file).map_or_else(
@@ -130,6 +311,93 @@ impl<'a> FileIO<'a> {
         })
     }
 
+    // The files whose names match the glob, e.g. odds_*.json, searched in the sub folders too if
+    // recursive, from the oldest to the latest modified.
+    pub fn list_files_matching(
+        &self,
+        folder_path: &Path,
+        glob_pattern: &str,
+        recursive: bool,
+    ) -> Result<Vec<FileEntry>> {
+        let pattern = Pattern::new(glob_pattern).map_err(|e| {
+            let error_str = format!("Invalid glob pattern {glob_pattern}. {e}");
+            self.project_logger.log_error(&error_str);
+            Error::new(ErrorKind::InvalidInput, error_str)
+        })?;
+        self.list_files_by_name(folder_path, recursive, |file_name| {
+            pattern.matches(file_name)
+        })
+    }
+
+    pub fn list_files_matching_regex(
+        &self,
+        folder_path: &Path,
+        pattern: &Regex,
+        recursive: bool,
+    ) -> Result<Vec<FileEntry>> {
+        self.list_files_by_name(folder_path, recursive, |file_name| {
+            pattern.is_match(file_name)
+        })
+    }
+
+    fn list_files_by_name<F>(
+        &self,
+        folder_path: &Path,
+        recursive: bool,
+        filter_func: F,
+    ) -> Result<Vec<FileEntry>>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let mut walk_dir = WalkDir::new(folder_path).min_depth(1);
+        if !recursive {
+            walk_dir = walk_dir.max_depth(1);
+        }
+        let mut file_entry_list = Vec::new();
+        for dir_entry in walk_dir {
+            let dir_entry = match dir_entry {
+                Ok(dir_entry) => dir_entry,
+                Err(e) if e.depth() == 0 => {
+                    let error_str = format!(
+                        "Unable to list the files in folder {}, {e}",
+                        folder_path.display()
+                    );
+                    self.project_logger.log_error(&error_str);
+                    return Err(e.into());
+                }
+                Err(e) => {
+                    let warn_str = format!("Unable to identify the element. {e}");
+                    self.project_logger.log_warn(&warn_str);
+                    continue;
+                }
+            };
+            let is_matched = dir_entry.file_type().is_file()
+                && dir_entry.file_name().to_str().is_some_and(&filter_func);
+            if !is_matched {
+                continue;
+            }
+            match dir_entry.metadata() {
+                Ok(metadata) => file_entry_list.push(FileEntry {
+                    path: dir_entry.into_path(),
+                    size: metadata.len(),
+                    modified: metadata
+                        .modified()
+                        .map(DateTime::<Utc>::from)
+                        .unwrap_or_default(),
+                }),
+                Err(e) => {
+                    let warn_str = format!(
+                        "Unable to get the metadata of {}. {e}",
+                        dir_entry.path().display()
+                    );
+                    self.project_logger.log_warn(&warn_str);
+                }
+            }
+        }
+        file_entry_list.sort_by_key(|file_entry| file_entry.modified);
+        Ok(file_entry_list)
+    }
+
     pub fn filter_element_after<T: TimeZone>(
         &self,
         element: &Result<DirEntry>,
@@ -137,7 +405,11 @@ impl<'a> FileIO<'a> {
     ) -> bool {
         let dir_entry = match element {
             Ok(d_e) => d_e,
-            Err(e) => panic!("Unable to identify the element. {e}"),
+            Err(e) => {
+                let warn_str = format!("Unable to identify the element. {e}");
+                self.project_logger.log_warn(&warn_str);
+                return false;
+            }
         };
         let full_path = dir_entry.path();
         self.get_last_modification_time(&full_path)
@@ -197,6 +469,93 @@ impl<'a> FileIO<'a> {
             .sum()
     }
 
+    // Calls the handler for the files under the folder whose names match the pattern once they
+    // are created or changed, until the shutdown is requested. The events of a file are merged
+    // until it has been quiet for the debounce, so that a file being written is handled once
+    // complete, and a file created and then written is reported as created.
+    pub async fn watch_folder<F, Fut>(
+        &self,
+        folder_path: &Path,
+        pattern: &Regex,
+        debounce: Duration,
+        shutdown_signal: &ShutdownSignal,
+        handler: F,
+    ) -> Result<()>
+    where
+        F: Fn(WatchEvent, PathBuf) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let (event_sender, mut event_receiver) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let _ = event_sender.send(event);
+        })
+        .and_then(|mut watcher| {
+            watcher.watch(folder_path, RecursiveMode::Recursive)?;
+            Ok(watcher)
+        })
+        .map_err(|e| {
+            let error_str = format!("Unable to watch folder {}. {e}", folder_path.display());
+            self.project_logger.log_error(&error_str);
+            Error::other(e)
+        })?;
+        let info_str = format!("Start watching folder {}.", folder_path.display());
+        self.project_logger.log_info(&info_str);
+        let mut pending_files: HashMap<PathBuf, (WatchEvent, Instant)> = HashMap::new();
+        while !shutdown_signal.is_requested() {
+            match tokio::time::timeout(Self::WATCH_CHECK_INTERVAL, event_receiver.recv()).await {
+                Ok(Some(Ok(event))) => self.add_watch_event(&mut pending_files, pattern, event),
+                Ok(Some(Err(e))) => {
+                    let warn_str =
+                        format!("Error in watching folder {}. {e}", folder_path.display());
+                    self.project_logger.log_warn(&warn_str);
+                }
+                Ok(None) => break,
+                Err(_) => {}
+            }
+            let settled_files: Vec<PathBuf> = pending_files
+                .iter()
+                .filter(|(_, (_, last_event_time))| last_event_time.elapsed() >= debounce)
+                .map(|(file_path, _)| file_path.clone())
+                .collect();
+            for file_path in settled_files {
+                if let Some((watch_event, _)) = pending_files.remove(&file_path) {
+                    if file_path.is_file() {
+                        let debug_str = format!("File {} {watch_event:?}.", file_path.display());
+                        self.project_logger.log_debug(&debug_str);
+                        handler(watch_event, file_path).await;
+                    }
+                }
+            }
+        }
+        let _ = watcher.unwatch(folder_path);
+        let info_str = format!("Stop watching folder {}.", folder_path.display());
+        self.project_logger.log_info(&info_str);
+        Ok(())
+    }
+
+    fn add_watch_event(
+        &self,
+        pending_files: &mut HashMap<PathBuf, (WatchEvent, Instant)>,
+        pattern: &Regex,
+        event: Event,
+    ) {
+        let Some(watch_event) = WatchEvent::from_event_kind(&event.kind) else {
+            return;
+        };
+        for file_path in event.paths {
+            let is_matched = file_path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .is_some_and(|file_name| pattern.is_match(file_name));
+            if is_matched {
+                pending_files
+                    .entry(file_path)
+                    .and_modify(|(_, last_event_time)| *last_event_time = Instant::now())
+                    .or_insert((watch_event, Instant::now()));
+            }
+        }
+    }
+
     pub fn filter_element_between<T: TimeZone>(
         &self,
         element: &Result<DirEntry>,
@@ -205,7 +564,11 @@ impl<'a> FileIO<'a> {
     ) -> bool {
         let dir_entry = match element {
             Ok(d_e) => d_e,
-            Err(e) => panic!("Unable to identify the element. {e}"),
+            Err(e) => {
+                let warn_str = format!("Unable to identify the element. {e}");
+                self.project_logger.log_warn(&warn_str);
+                return false;
+            }
         };
         let full_path = dir_entry.path();
         self.get_last_modification_time(&full_path)
@@ -227,20 +590,19 @@ impl<'a> FileIO<'a> {
         cutoff_date_time_early: &DateTime<Utc>,
         cutoff_date_time_late: &DateTime<Utc>,
     ) -> Result<impl Iterator<Item = DateTime<Utc>>> {
-        let start_time_int = cutoff_date_time_early
-            .format("%Y%m%d")
-            .to_string()
-            .parse::<i64>()
-            .unwrap_or_else(|e| {
-                panic!("Unable to parse start time {cutoff_date_time_early} into i64. {e}")
-            });
-        let end_time_int = cutoff_date_time_late
-            .format("%Y%m%d")
-            .to_string()
-            .parse::<i64>()
-            .unwrap_or_else(|e| {
-                panic!("Unable to parse end time {cutoff_date_time_late} into i64. {e}")
-            });
+        let parse_date_int = |date_time: &DateTime<Utc>| {
+            date_time
+                .format("%Y%m%d")
+                .to_string()
+                .parse::<i64>()
+                .map_err(|e| {
+                    let error_str = format!("Unable to parse {date_time} into i64. {e}");
+                    self.project_logger.log_error(&error_str);
+                    Error::new(ErrorKind::InvalidInput, error_str)
+                })
+        };
+        let start_time_int = parse_date_int(cutoff_date_time_early)?;
+        let end_time_int = parse_date_int(cutoff_date_time_late)?;
         let elements = self.get_elements_in_folder(folder_path)?;
         Ok(elements.filter_map(move |dir| {
             dir.ok().and_then(|element| {
@@ -259,6 +621,52 @@ impl<'a> FileIO<'a> {
         }))
     }
 
+    // Removes the files under the folder last modified before the cutoff. With dry_run the files
+    // are only counted.
+    pub fn cleanup_old_files<T: TimeZone>(
+        &self,
+        folder_path: &Path,
+        older_than: &DateTime<T>,
+        dry_run: bool,
+    ) -> CleanupReport {
+        let dry_run = dry_run || DryRun::is_enabled();
+        let mut cleanup_report = CleanupReport::default();
+        for (dir_entry, metadata) in WalkDir::new(folder_path)
+            .into_iter()
+            .filter_map(|dir_entry| dir_entry.ok())
+            .filter_map(|dir_entry| {
+                let metadata = dir_entry.metadata().ok()?;
+                let modified = metadata.modified().ok()?;
+                (metadata.is_file()
+                    && time_operation::diff_system_time_date_time_sec(&modified, older_than) < 0)
+                    .then_some((dir_entry, metadata))
+            })
+        {
+            if !dry_run {
+                if let Err(e) = fs::remove_file(dir_entry.path()) {
+                    let error_str = format!(
+                        "Unable to remove the old file {}. {e}",
+                        dir_entry.path().display()
+                    );
+                    self.project_logger.log_error(&error_str);
+                    continue;
+                }
+            }
+            cleanup_report.num_deleted += 1;
+            cleanup_report.freed_bytes += metadata.len();
+        }
+        let info_str = format!(
+            "{}{} old files removed from {}, freeing {} bytes.",
+            if dry_run { "Dry run. " } else { "" },
+            cleanup_report.num_deleted,
+            folder_path.display(),
+            cleanup_report.freed_bytes
+        );
+        self.project_logger.log_info(&info_str);
+        cleanup_report
+    }
+
+    #[tracing::instrument(name = "file_operation", skip_all, fields(operation = "load", path = %folder_path.join(file).display()))]
     pub fn load_file_as_string(&self, folder_path: &Path, file: &str) -> Result<String> {
         let full_path = folder_path.join(file);
         fs::read_to_string(&full_path).map_or_else(
@@ -278,13 +686,21 @@ impl<'a> FileIO<'a> {
         )
     }
 
+    #[tracing::instrument(name = "file_operation", skip_all, fields(operation = "write", path = %folder_path.join(file).display()))]
     pub fn write_string_to_file(
         &self,
         folder_path: &Path,
         file: &str,
         content: &str,
     ) -> Result<()> {
-        let full_path = folder_path.join(file);
+        if DryRun::skip(
+            &self.project_logger,
+            &format!("writing file {}", folder_path.join(file).display()),
+        ) {
+            return Ok(());
+        }
+        self.check_disk_space(folder_path, content.len())?;
+        let full_path = self.get_write_path(folder_path, file)?;
         fs::write(&full_path, content).map_or_else(
             |e| {
                 let error_str = format!(
@@ -308,11 +724,30 @@ impl<'a> FileIO<'a> {
         file: &str,
         content: &str,
     ) -> Result<()> {
-        let full_path = folder_path.join(file);
+        self.async_write_bytes_to_file(folder_path, file, content.as_bytes())
+            .await
+    }
+
+    // For the binary content, e.g. images, pdfs and zips, which are written verbatim.
+    #[tracing::instrument(name = "file_operation", skip_all, fields(operation = "write", path = %folder_path.join(file).display()))]
+    pub async fn async_write_bytes_to_file(
+        &self,
+        folder_path: &Path,
+        file: &str,
+        content: &[u8],
+    ) -> Result<()> {
+        if DryRun::skip(
+            &self.project_logger,
+            &format!("writing file {}", folder_path.join(file).display()),
+        ) {
+            return Ok(());
+        }
+        self.check_disk_space(folder_path, content.len())?;
+        let full_path = self.get_write_path(folder_path, file)?;
         tokio::fs::write(&full_path, content).await.map_or_else(
             |e| {
                 let error_str = format!(
-                    "Unable to save string to file {}. {e}",
+                    "Unable to save content to file {}. {e}",
                     &full_path.display()
                 );
                 self.project_logger.log_error(&error_str);
@@ -350,6 +785,7 @@ impl<'a> FileIO<'a> {
     }
 
     // directly loading the csv file with default options
+    #[tracing::instrument(name = "file_operation", skip_all, fields(operation = "load", path = %folder_path.join(file).display()))]
     pub fn load_csv_file(&self, folder_path: &Path, file: &str) -> PolarsResult<DataFrame> {
         let full_path = folder_path.join(file);
         CsvReadOptions::default()
@@ -368,7 +804,7 @@ impl<'a> FileIO<'a> {
 
     // allow for more complicated writing options for the writer
     pub fn get_file_writer(&self, folder_path: &Path, file: &str) -> Result<File> {
-        let full_path = folder_path.join(file);
+        let full_path = self.get_write_path(folder_path, file)?;
         File::create(&full_path).map_or_else(
             |e| {
                 let error_str = format!("Unable to create file {}. {}", &full_path.display(), e);
@@ -384,12 +820,20 @@ impl<'a> FileIO<'a> {
     }
 
     // directly writing the csv file with default options
+    #[tracing::instrument(name = "file_operation", skip_all, fields(operation = "write", path = %folder_path.join(file).display()))]
     pub fn write_csv_file(
         &self,
         folder_path: &Path,
         file: &str,
         data: &mut DataFrame,
     ) -> PolarsResult<()> {
+        if DryRun::skip(
+            &self.project_logger,
+            &format!("writing file {}", folder_path.join(file).display()),
+        ) {
+            return Ok(());
+        }
+        self.check_disk_space(folder_path, data.estimated_size())?;
         let csv_writer = CsvWriter::new(self.get_file_writer(folder_path, file)?);
         csv_writer
             .include_header(true)
@@ -446,6 +890,7 @@ impl<'a> FileIO<'a> {
     }
 
     // directly reading the parquet file with default options
+    #[tracing::instrument(name = "file_operation", skip_all, fields(operation = "load", path = %folder_path.join(file).display()))]
     pub fn load_parquet_file(&self, folder_path: &Path, file: &str) -> PolarsResult<DataFrame> {
         let parquet_reader: ParquetReader<File> = self.get_parquet_reader(folder_path, file)?;
         parquet_reader.finish().map_err(|e| {
@@ -459,12 +904,20 @@ impl<'a> FileIO<'a> {
     }
 
     // directly writing the parquet file with default options
+    #[tracing::instrument(name = "file_operation", skip_all, fields(operation = "write", path = %folder_path.join(file).display()))]
     pub fn write_parquet_file(
         &self,
         folder_path: &Path,
         file: &str,
         data: &mut DataFrame,
     ) -> PolarsResult<()> {
+        if DryRun::skip(
+            &self.project_logger,
+            &format!("writing file {}", folder_path.join(file).display()),
+        ) {
+            return Ok(());
+        }
+        self.check_disk_space(folder_path, data.estimated_size())?;
         let parquet_writer = ParquetWriter::new(self.get_file_writer(folder_path, file)?);
         parquet_writer.finish(data).map_or_else(
             |e| {
@@ -499,6 +952,219 @@ impl<'a> FileIO<'a> {
         )
     }
 
+    // Stack the new rows under the existing ones and keep only the latest row of each key.
+    pub fn merge_latest(
+        existing: &DataFrame,
+        new_data: &DataFrame,
+        key_cols: &[&str],
+    ) -> PolarsResult<DataFrame> {
+        let combined = existing.vstack(&new_data.select(existing.get_column_names_owned())?)?;
+        Self::dedup_keep_latest(&combined, key_cols)
+    }
+
+    pub fn dedup_keep_latest(data: &DataFrame, key_cols: &[&str]) -> PolarsResult<DataFrame> {
+        let key_columns = key_cols
+            .iter()
+            .map(|col_name| data.column(col_name)?.cast(&DataType::String))
+            .collect::<PolarsResult<Vec<Column>>>()?;
+        let key_columns = key_columns
+            .iter()
+            .map(|column| column.str())
+            .collect::<PolarsResult<Vec<&StringChunked>>>()?;
+        let mut key_set = HashSet::with_capacity(data.height());
+        let mut latest_rows: Vec<IdxSize> = (0..data.height())
+            .rev()
+            .filter(|row| {
+                let key: Vec<Option<&str>> =
+                    key_columns.iter().map(|column| column.get(*row)).collect();
+                key_set.insert(key)
+            })
+            .map(|row| row as IdxSize)
+            .collect();
+        latest_rows.reverse();
+        data.take(&IdxCa::from_vec("latest_rows".into(), latest_rows))
+    }
+
+    // The merged data is written to a temporary file first and renamed over the old file, so
+    // a crash midway never leaves a truncated dataset behind.
+    pub fn upsert_parquet_file(
+        &self,
+        folder_path: &Path,
+        file: &str,
+        new_data: &DataFrame,
+        key_cols: &[&str],
+    ) -> PolarsResult<usize> {
+        if DryRun::skip(
+            &self.project_logger,
+            &format!("upserting file {}", folder_path.join(file).display()),
+        ) {
+            return Ok(0);
+        }
+        let merged = if Self::check_file_exist(folder_path, file) {
+            let existing = self.load_parquet_file(folder_path, file)?;
+            Self::merge_latest(&existing, new_data, key_cols)
+        } else {
+            Self::dedup_keep_latest(new_data, key_cols)
+        };
+        let mut merged = merged.map_err(|e| {
+            let error_str = format!(
+                "Unable to merge the new data into {}. {e}",
+                folder_path.join(file).display()
+            );
+            self.project_logger.log_error(&error_str);
+            e
+        })?;
+        let temp_file = format!(".{file}.tmp");
+        self.write_parquet_file(folder_path, &temp_file, &mut merged)?;
+        fs::rename(folder_path.join(&temp_file), folder_path.join(file)).map_err(|e| {
+            let error_str = format!(
+                "Unable to replace {} with the merged data. {e}",
+                folder_path.join(file).display()
+            );
+            self.project_logger.log_error(&error_str);
+            e
+        })?;
+        let debug_str = format!(
+            "File {} upserted with {} rows.",
+            folder_path.join(file).display(),
+            merged.height()
+        );
+        self.project_logger.log_debug(&debug_str);
+        Ok(merged.height())
+    }
+
+    // Split the data into hive-style partitions, returning the relative folder of each
+    // partition, e.g. date=2024-05-01/league=EPL, with the partition columns dropped.
+    pub fn split_partitions(
+        data: &DataFrame,
+        partition_cols: &[&str],
+    ) -> PolarsResult<Vec<(PathBuf, DataFrame)>> {
+        let partition_columns = partition_cols
+            .iter()
+            .map(|col_name| data.column(col_name)?.cast(&DataType::String))
+            .collect::<PolarsResult<Vec<Column>>>()?;
+        let partition_columns = partition_columns
+            .iter()
+            .map(|column| column.str())
+            .collect::<PolarsResult<Vec<&StringChunked>>>()?;
+        let mut partition_rows: Vec<(PathBuf, Vec<IdxSize>)> = Vec::new();
+        let mut partition_index: HashMap<PathBuf, usize> = HashMap::new();
+        for row in 0..data.height() {
+            let partition_path: PathBuf = partition_cols
+                .iter()
+                .zip(partition_columns.iter())
+                .map(|(col_name, column)| {
+                    format!(
+                        "{col_name}={}",
+                        column.get(row).unwrap_or(Self::NULL_PARTITION)
+                    )
+                })
+                .collect();
+            let index = *partition_index
+                .entry(partition_path.clone())
+                .or_insert_with(|| {
+                    partition_rows.push((partition_path, Vec::new()));
+                    partition_rows.len() - 1
+                });
+            partition_rows[index].1.push(row as IdxSize);
+        }
+        partition_rows
+            .into_iter()
+            .map(|(partition_path, rows)| {
+                let partition_data = data
+                    .take(&IdxCa::from_vec("partition_rows".into(), rows))?
+                    .drop_many(partition_cols.iter().copied());
+                Ok((partition_path, partition_data))
+            })
+            .collect()
+    }
+
+    pub fn parse_partition_values(partition_path: &Path) -> HashMap<String, String> {
+        partition_path
+            .components()
+            .filter_map(|component| component.as_os_str().to_str())
+            .filter_map(|component| component.split_once('='))
+            .map(|(col_name, value)| (col_name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    pub fn write_partitioned_parquet(
+        &self,
+        data: &DataFrame,
+        root_path: &Path,
+        partition_cols: &[&str],
+    ) -> PolarsResult<usize> {
+        let partition_list = Self::split_partitions(data, partition_cols).map_err(|e| {
+            let error_str = format!(
+                "Unable to partition the data by {partition_cols:?} for {}. {e}",
+                root_path.display()
+            );
+            self.project_logger.log_error(&error_str);
+            e
+        })?;
+        let num_partitions = partition_list.len();
+        for (partition_path, mut partition_data) in partition_list {
+            let folder_path = root_path.join(partition_path);
+            if !Self::check_folder_exist(&folder_path) {
+                fs::create_dir_all(&folder_path)?;
+            }
+            self.write_parquet_file(&folder_path, Self::PARTITION_FILE, &mut partition_data)?;
+        }
+        let debug_str = format!(
+            "Data written into {num_partitions} partitions in {}.",
+            root_path.display()
+        );
+        self.project_logger.log_debug(&debug_str);
+        Ok(num_partitions)
+    }
+
+    // Partitions rejected by the filter are never opened. The partition columns are added back
+    // as string columns.
+    pub fn scan_partitioned_parquet(
+        &self,
+        root_path: &Path,
+        partition_filter: &dyn Fn(&HashMap<String, String>) -> bool,
+    ) -> PolarsResult<LazyFrame> {
+        let mut lazy_frame_list = Vec::new();
+        for entry in WalkDir::new(root_path)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry.file_type().is_file()
+                    && entry.path().extension().and_then(|ext| ext.to_str()) == Some("parquet")
+            })
+        {
+            let partition_path = entry
+                .path()
+                .parent()
+                .and_then(|parent| parent.strip_prefix(root_path).ok())
+                .unwrap_or(Path::new(""));
+            let partition_values = Self::parse_partition_values(partition_path);
+            if !partition_filter(&partition_values) {
+                continue;
+            }
+            let partition_exprs: Vec<Expr> = partition_values
+                .iter()
+                .map(|(col_name, value)| lit(value.as_str()).alias(col_name.as_str()))
+                .collect();
+            let lazy_frame = LazyFrame::scan_parquet(entry.path(), ScanArgsParquet::default())?
+                .with_columns(partition_exprs);
+            lazy_frame_list.push(lazy_frame);
+        }
+        let debug_str = format!(
+            "{} partitions scanned in {}.",
+            lazy_frame_list.len(),
+            root_path.display()
+        );
+        self.project_logger.log_debug(&debug_str);
+        if lazy_frame_list.is_empty() {
+            Ok(DataFrame::empty().lazy())
+        } else {
+            concat(lazy_frame_list, UnionArgs::default())
+        }
+    }
+
     pub fn sink_parquet_file(
         &self,
         folder_path: &Path,
@@ -624,6 +1290,158 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(FileIO::sanitize_file_name("bakerloo.html"), "bakerloo.html");
+        for file_name in [
+            "../../etc/passwd",
+            "..",
+            "odds/1.json",
+            "odds:1.json",
+            "CON.txt",
+        ] {
+            let sanitized = FileIO::sanitize_file_name(file_name);
+            assert!(!sanitized.starts_with('.'));
+            assert!(!sanitized.contains(['/', '\\', ':']));
+            assert_eq!(
+                FileIO::safe_join(Path::new("data"), &sanitized).unwrap(),
+                Path::new("data").join(&sanitized)
+            );
+        }
+        let sanitized = FileIO::sanitize_file_name("odds/1.json");
+        assert!(sanitized.starts_with("odds_1_") && sanitized.ends_with(".json"));
+        assert_ne!(sanitized, FileIO::sanitize_file_name("odds:1.json"));
+        assert!(FileIO::sanitize_file_name("CON.txt").starts_with("_CON"));
+        let sanitized = FileIO::sanitize_file_name(&format!("{}.html", "é".repeat(200)));
+        assert!(sanitized.len() <= 255 && sanitized.ends_with(".html"));
+    }
+
+    #[test]
+    fn test_sanitize_file_path() {
+        assert_eq!(
+            FileIO::sanitize_file_path("tfl.gov.uk/20240301/bakerloo.html"),
+            "tfl.gov.uk/20240301/bakerloo.html"
+        );
+        let sanitized = FileIO::sanitize_file_path("/odds/../1.json");
+        assert!(sanitized.starts_with("odds/__") && sanitized.ends_with("/1.json"));
+        assert!(FileIO::safe_join(Path::new("data"), &sanitized).is_ok());
+    }
+
+    #[test]
+    fn test_safe_join() {
+        let folder_path = Path::new("data");
+        assert_eq!(
+            FileIO::safe_join(folder_path, "20240301/odds.json").unwrap(),
+            folder_path.join("20240301").join("odds.json")
+        );
+        for file in [
+            "../secret.txt",
+            "20240301/../../secret.txt",
+            "/etc/passwd",
+            "",
+            "..",
+        ] {
+            let e = FileIO::safe_join(folder_path, file).unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_list_files_matching() {
+        let folder_path = env::temp_dir().join("test_list_files_matching");
+        let sub_folder_path = folder_path.join("20240301");
+        fs::create_dir_all(&sub_folder_path).unwrap();
+        let file_list = [
+            (sub_folder_path.join("odds_2.json"), 100),
+            (folder_path.join("odds_1.json"), 200),
+            (folder_path.join("timetable.html"), 300),
+        ];
+        for (file_path, modified_secs) in &file_list {
+            let file = File::create(file_path).unwrap();
+            file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(*modified_secs))
+                .unwrap();
+        }
+        let logger_name = "test_file_io";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_io");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let file_io = FileIO::new(&project_logger);
+        let get_paths = |file_entry_list: Vec<FileEntry>| -> Vec<PathBuf> {
+            file_entry_list
+                .into_iter()
+                .map(|file_entry| file_entry.path)
+                .collect()
+        };
+        let file_entry_list = file_io
+            .list_files_matching(&folder_path, "odds_*.json", false)
+            .unwrap();
+        assert_eq!(get_paths(file_entry_list), vec![file_list[1].0.clone()]);
+        let file_entry_list = file_io
+            .list_files_matching(&folder_path, "odds_*.json", true)
+            .unwrap();
+        assert_eq!(file_entry_list[0].modified.timestamp(), 100);
+        assert_eq!(
+            get_paths(file_entry_list),
+            vec![file_list[0].0.clone(), file_list[1].0.clone()]
+        );
+        let pattern = Regex::new(r"\.html?$").unwrap();
+        let file_entry_list = file_io
+            .list_files_matching_regex(&folder_path, &pattern, true)
+            .unwrap();
+        assert_eq!(get_paths(file_entry_list), vec![file_list[2].0.clone()]);
+        assert!(file_io
+            .list_files_matching(&folder_path, "odds_[.json", true)
+            .is_err());
+        assert!(file_io
+            .list_files_matching(&folder_path.join("missing"), "*", true)
+            .is_err());
+        fs::remove_dir_all(&folder_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_watch_folder() {
+        let folder_path = env::temp_dir().join("test_watch_folder");
+        fs::create_dir_all(&folder_path).unwrap();
+        let logger_name = "test_file_io";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_io");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let file_io = FileIO::new(&project_logger);
+        let pattern = Regex::new(r"^odds_.*\.json$").unwrap();
+        let shutdown_signal = ShutdownSignal::new();
+        let watched_files = std::sync::Mutex::new(Vec::new());
+        let write_files = async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            fs::write(folder_path.join("timetable.html"), "<html></html>").unwrap();
+            fs::write(folder_path.join("odds_1.json"), "{}").unwrap();
+            fs::write(folder_path.join("odds_1.json"), "{\"home\": 1.5}").unwrap();
+        };
+        let watch = file_io.watch_folder(
+            &folder_path,
+            &pattern,
+            Duration::from_millis(300),
+            &shutdown_signal,
+            |watch_event, file_path| {
+                watched_files.lock().unwrap().push((watch_event, file_path));
+                shutdown_signal.request();
+                futures::future::ready(())
+            },
+        );
+        let (watch_result, ()) = tokio::time::timeout(Duration::from_secs(10), async {
+            tokio::join!(watch, write_files)
+        })
+        .await
+        .unwrap();
+        watch_result.unwrap();
+        assert_eq!(
+            watched_files.into_inner().unwrap(),
+            vec![(WatchEvent::Created, folder_path.join("odds_1.json"))]
+        );
+        fs::remove_dir_all(&folder_path).unwrap();
+    }
+
     #[test]
     fn test_count_file_modified_in_between() {
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
@@ -724,6 +1542,66 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn test_dedup_keep_latest() {
+        let existing = df!(
+            "match_id" => [1, 2, 3],
+            "home_odds" => [1.5, 2.0, 2.5],
+        )
+        .unwrap();
+        let new_data = df!(
+            "home_odds" => [2.2, 3.0, 2.4],
+            "match_id" => [2, 4, 2],
+        )
+        .unwrap();
+        let merged = FileIO::merge_latest(&existing, &new_data, &["match_id"]).unwrap();
+        let expected = df!(
+            "match_id" => [1, 3, 4, 2],
+            "home_odds" => [1.5, 2.5, 3.0, 2.4],
+        )
+        .unwrap();
+        assert!(merged.equals(&expected));
+    }
+
+    #[test]
+    fn test_partitioned_parquet() {
+        let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap())
+            .join("test_io")
+            .join("test_partitioned");
+        let logger_name = "test_file_io";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_io");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Debug);
+        let file_io = FileIO::new(&project_logger);
+        let data = df!(
+            "date" => ["2024-05-01", "2024-05-01", "2024-05-02"],
+            "league" => [Some("EPL"), None, Some("EPL")],
+            "home_odds" => [1.5, 2.0, 2.5],
+        )
+        .unwrap();
+        let num_partitions = file_io
+            .write_partitioned_parquet(&data, &folder_path, &["date", "league"])
+            .unwrap();
+        assert_eq!(num_partitions, 3);
+        assert!(FileIO::check_file_exist(
+            &folder_path
+                .join("date=2024-05-01")
+                .join(format!("league={}", FileIO::NULL_PARTITION)),
+            FileIO::PARTITION_FILE
+        ));
+        let partition_filter =
+            |partition_values: &HashMap<String, String>| partition_values["date"] == "2024-05-01";
+        let scanned = file_io
+            .scan_partitioned_parquet(&folder_path, &partition_filter)
+            .unwrap()
+            .collect()
+            .unwrap();
+        assert_eq!(scanned.height(), 2);
+        fs::remove_dir_all(&folder_path).unwrap();
+    }
+
     #[test]
     fn test_scan_parquet() {
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
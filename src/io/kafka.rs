@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::time::Duration;
+
+use apache_avro::{from_avro_datum, to_avro_datum, Schema};
+use polars::prelude::*;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::error::KafkaResult;
+use rdkafka::message::{Message, OwnedMessage};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::{Offset, TopicPartitionList};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::logger::ProjectLogger;
+use crate::Result;
+
+// The avro records are written as bare datums without the container header, so the consumers
+// have to decode them with the same schema as the producer.
+#[derive(Debug, Clone, Copy)]
+pub enum RecordFormat<'s> {
+    Json,
+    Avro(&'s Schema),
+}
+
+impl RecordFormat<'_> {
+    pub fn encode<T: Serialize>(&self, record: &T) -> Result<Vec<u8>> {
+        match self {
+            Self::Json => Ok(serde_json::to_vec(record)?),
+            Self::Avro(schema) => {
+                let value = apache_avro::to_value(record)?.resolve(schema)?;
+                Ok(to_avro_datum(schema, value)?)
+            }
+        }
+    }
+
+    pub fn decode(&self, payload: &[u8]) -> Result<Value> {
+        match self {
+            Self::Json => Ok(serde_json::from_slice(payload)?),
+            Self::Avro(schema) => {
+                let value = from_avro_datum(schema, &mut Cursor::new(payload), None)?;
+                Ok(Value::try_from(value)?)
+            }
+        }
+    }
+}
+
+// Streams the scraped records to the pipeline instead of landing them in files. The offsets are
+// not committed automatically, so a batch is only marked as consumed after it has been processed.
+pub struct Kafka<'a> {
+    project_logger: &'a ProjectLogger,
+    brokers: String,
+    send_timeout: Duration,
+}
+
+impl<'a> Kafka<'a> {
+    const BROKERS: &'a str = "127.0.0.1:9092";
+    const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new(project_logger: &'a ProjectLogger) -> Self {
+        Self {
+            project_logger,
+            brokers: Self::BROKERS.to_string(),
+            send_timeout: Self::SEND_TIMEOUT,
+        }
+    }
+
+    pub fn set_brokers(&mut self, brokers: &str) {
+        self.brokers = brokers.to_string();
+    }
+
+    pub fn set_send_timeout(&mut self, send_timeout: Duration) {
+        self.send_timeout = send_timeout;
+    }
+
+    fn log_kafka_error<T>(&self, result: KafkaResult<T>, action: &str) -> KafkaResult<T> {
+        result.map_err(|e| {
+            let error_str = format!("Fail to {action}. {e}");
+            self.project_logger.log_error(&error_str);
+            e
+        })
+    }
+
+    pub fn create_producer(&self) -> KafkaResult<FutureProducer> {
+        self.log_kafka_error(
+            ClientConfig::new()
+                .set("bootstrap.servers", &self.brokers)
+                .set(
+                    "message.timeout.ms",
+                    self.send_timeout.as_millis().to_string(),
+                )
+                .create(),
+            &format!("create kafka producer for {}", self.brokers),
+        )
+    }
+
+    pub fn create_consumer(
+        &self,
+        group_id: &str,
+        topic_list: &[&str],
+    ) -> KafkaResult<StreamConsumer> {
+        let consumer: StreamConsumer = self.log_kafka_error(
+            ClientConfig::new()
+                .set("bootstrap.servers", &self.brokers)
+                .set("group.id", group_id)
+                .set("enable.auto.commit", "false")
+                .set("auto.offset.reset", "earliest")
+                .create(),
+            &format!(
+                "create kafka consumer of group {group_id} for {}",
+                self.brokers
+            ),
+        )?;
+        self.log_kafka_error(
+            consumer.subscribe(topic_list),
+            &format!("subscribe kafka topics {}", topic_list.join(", ")),
+        )?;
+        Ok(consumer)
+    }
+
+    fn get_key(record: &Value, key_field: Option<&str>) -> Option<String> {
+        key_field
+            .and_then(|key_field| record.get(key_field))
+            .map(|key| match key {
+                Value::String(key) => key.clone(),
+                _ => key.to_string(),
+            })
+    }
+
+    // The value of the key field of a record, if given, is the message key, so that the records
+    // of the same key go to the same partition in order. Returns the number of records published.
+    pub async fn publish<T: Serialize>(
+        &self,
+        producer: &FutureProducer,
+        topic: &str,
+        records: &[T],
+        key_field: Option<&str>,
+        record_format: RecordFormat<'_>,
+    ) -> Result<usize> {
+        for record in records {
+            let payload = record_format.encode(record)?;
+            let key = Self::get_key(&serde_json::to_value(record)?, key_field);
+            let mut future_record = FutureRecord::<str, [u8]>::to(topic).payload(&payload);
+            if let Some(key) = key.as_deref() {
+                future_record = future_record.key(key);
+            }
+            self.log_kafka_error(
+                producer
+                    .send(future_record, self.send_timeout)
+                    .await
+                    .map_err(|(e, _)| e),
+                &format!("publish record to kafka topic {topic}"),
+            )?;
+        }
+        let debug_str = format!(
+            "{} records published to kafka topic {topic}.",
+            records.len()
+        );
+        self.project_logger.log_debug(&debug_str);
+        Ok(records.len())
+    }
+
+    // Returns when the batch is full or the timeout is reached, whichever comes first, so an idle
+    // topic gives an empty batch instead of blocking.
+    pub async fn consume_batch(
+        &self,
+        consumer: &StreamConsumer,
+        max_records: usize,
+        timeout: Duration,
+    ) -> KafkaResult<Vec<OwnedMessage>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut message_list = Vec::new();
+        while message_list.len() < max_records {
+            match tokio::time::timeout_at(deadline, consumer.recv()).await {
+                Ok(message) => {
+                    let message = self.log_kafka_error(message, "receive message from kafka")?;
+                    message_list.push(message.detach());
+                }
+                Err(_) => break,
+            }
+        }
+        Ok(message_list)
+    }
+
+    // Commits the offset after the last message of each partition in the batch.
+    pub fn commit_batch(
+        &self,
+        consumer: &StreamConsumer,
+        message_list: &[OwnedMessage],
+    ) -> KafkaResult<()> {
+        if message_list.is_empty() {
+            return Ok(());
+        }
+        let mut next_offsets: HashMap<(&str, i32), i64> = HashMap::new();
+        for message in message_list {
+            let next_offset = next_offsets
+                .entry((message.topic(), message.partition()))
+                .or_default();
+            *next_offset = (*next_offset).max(message.offset() + 1);
+        }
+        let mut partition_list = TopicPartitionList::new();
+        for ((topic, partition), next_offset) in next_offsets {
+            partition_list.add_partition_offset(topic, partition, Offset::Offset(next_offset))?;
+        }
+        self.log_kafka_error(
+            consumer.commit(&partition_list, CommitMode::Sync),
+            "commit kafka offsets",
+        )
+    }
+
+    // The messages without payload, e.g. the tombstones, are skipped.
+    pub fn batch_to_data_frame(
+        message_list: &[OwnedMessage],
+        record_format: RecordFormat<'_>,
+    ) -> Result<DataFrame> {
+        let record_list = message_list
+            .iter()
+            .filter_map(|message| message.payload())
+            .map(|payload| record_format.decode(payload))
+            .collect::<Result<Vec<Value>>>()?;
+        if record_list.is_empty() {
+            return Ok(DataFrame::empty());
+        }
+        let json_bytes = serde_json::to_vec(&record_list)?;
+        Ok(JsonReader::new(Cursor::new(json_bytes)).finish()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{env, path::Path};
+
+    use log::LevelFilter;
+    use rdkafka::message::Timestamp;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct OddsRecord {
+        match_id: String,
+        bookmaker: String,
+        home_odds: f64,
+    }
+
+    const ODDS_SCHEMA: &str = r#"
+        {
+            "type": "record",
+            "name": "OddsRecord",
+            "fields": [
+                {"name": "match_id", "type": "string"},
+                {"name": "bookmaker", "type": "string"},
+                {"name": "home_odds", "type": "double"}
+            ]
+        }
+    "#;
+
+    fn get_record_list() -> Vec<OddsRecord> {
+        vec![
+            OddsRecord {
+                match_id: "1001".to_string(),
+                bookmaker: "bet365".to_string(),
+                home_odds: 1.8,
+            },
+            OddsRecord {
+                match_id: "1002".to_string(),
+                bookmaker: "pinnacle".to_string(),
+                home_odds: 2.1,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_batch_to_data_frame() {
+        let schema = Schema::parse_str(ODDS_SCHEMA).unwrap();
+        for record_format in [RecordFormat::Json, RecordFormat::Avro(&schema)] {
+            let message_list: Vec<OwnedMessage> = get_record_list()
+                .iter()
+                .enumerate()
+                .map(|(offset, record)| {
+                    OwnedMessage::new(
+                        Some(record_format.encode(record).unwrap()),
+                        None,
+                        "odds".to_string(),
+                        Timestamp::NotAvailable,
+                        0,
+                        offset as i64,
+                        None,
+                    )
+                })
+                .collect();
+            let data = Kafka::batch_to_data_frame(&message_list, record_format).unwrap();
+            assert_eq!(data.shape(), (2, 3));
+            assert_eq!(
+                data.column("bookmaker").unwrap().str().unwrap().get(1),
+                Some("pinnacle")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_and_consume() {
+        let logger_name = "test_kafka";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_io");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Debug);
+        let kafka = Kafka::new(&project_logger);
+        let topic = "test_odds";
+        let producer = kafka.create_producer().unwrap();
+        let record_list = get_record_list();
+        let num_published = kafka
+            .publish(
+                &producer,
+                topic,
+                &record_list,
+                Some("match_id"),
+                RecordFormat::Json,
+            )
+            .await
+            .unwrap();
+        assert_eq!(num_published, 2);
+        let consumer = kafka.create_consumer("test_group", &[topic]).unwrap();
+        let message_list = kafka
+            .consume_batch(&consumer, 100, Duration::from_secs(10))
+            .await
+            .unwrap();
+        let data = Kafka::batch_to_data_frame(&message_list, RecordFormat::Json).unwrap();
+        dbg!(&data);
+        kafka.commit_batch(&consumer, &message_list).unwrap();
+    }
+}
@@ -0,0 +1,262 @@
+use std::io::Cursor;
+
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Bson, Document};
+use mongodb::error::Result;
+use mongodb::options::{IndexOptions, ReplaceOptions};
+use mongodb::{Client, Collection, IndexModel};
+use polars::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::logger::ProjectLogger;
+
+// For the scraped json payloads that are too nested or irregular for a table, e.g. the raw api
+// responses kept for reprocessing.
+pub struct MongoDB<'a> {
+    project_logger: &'a ProjectLogger,
+    mongo_path: String,
+}
+
+impl<'a> MongoDB<'a> {
+    const MONGO_PATH: &'a str = "mongodb://127.0.0.1:27017";
+    const ID_FIELD: &'a str = "_id";
+
+    pub fn new(project_logger: &'a ProjectLogger) -> Self {
+        Self {
+            project_logger,
+            mongo_path: Self::MONGO_PATH.to_string(),
+        }
+    }
+
+    pub fn set_mongo_path(&mut self, mongo_path: &str) {
+        self.mongo_path = mongo_path.to_string();
+    }
+
+    fn log_mongo_error<T>(&self, result: Result<T>, action: &str) -> Result<T> {
+        result.map_err(|e| {
+            let error_str = format!("Fail to {action}. {e}");
+            self.project_logger.log_error(&error_str);
+            e
+        })
+    }
+
+    pub async fn create_client(&self) -> Result<Client> {
+        let client = self.log_mongo_error(
+            Client::with_uri_str(&self.mongo_path).await,
+            "build mongodb client",
+        )?;
+        let debug_str = format!("MongoDB client for {} built.", self.mongo_path);
+        self.project_logger.log_debug(&debug_str);
+        Ok(client)
+    }
+
+    pub fn get_collection<T>(
+        client: &Client,
+        database_name: &str,
+        collection_name: &str,
+    ) -> Collection<T> {
+        client.database(database_name).collection(collection_name)
+    }
+
+    pub async fn insert_many<T: Serialize>(
+        &self,
+        collection: &Collection<T>,
+        documents: &[T],
+    ) -> Result<usize> {
+        if documents.is_empty() {
+            return Ok(0);
+        }
+        let insert_result = self.log_mongo_error(
+            collection.insert_many(documents, None).await,
+            &format!(
+                "insert documents to mongodb collection {}",
+                collection.name()
+            ),
+        )?;
+        let num_inserted = insert_result.inserted_ids.len();
+        let debug_str = format!(
+            "{num_inserted} documents inserted to mongodb collection {}.",
+            collection.name()
+        );
+        self.project_logger.log_debug(&debug_str);
+        Ok(num_inserted)
+    }
+
+    pub async fn find<T: DeserializeOwned + Unpin + Send + Sync>(
+        &self,
+        collection: &Collection<T>,
+        filter: Document,
+    ) -> Result<Vec<T>> {
+        let action = format!("find documents in mongodb collection {}", collection.name());
+        let cursor = self.log_mongo_error(collection.find(filter, None).await, &action)?;
+        self.log_mongo_error(cursor.try_collect().await, &action)
+    }
+
+    // The nested fields become struct columns. The object id is converted to its hex string so
+    // that it can be joined back with the collection.
+    pub async fn find_to_data_frame<T>(
+        &self,
+        collection: &Collection<T>,
+        filter: Document,
+    ) -> crate::Result<DataFrame> {
+        let documents = self
+            .find(&collection.clone_with_type::<Document>(), filter)
+            .await?;
+        if documents.is_empty() {
+            return Ok(DataFrame::empty());
+        }
+        let json_list: Vec<serde_json::Value> = documents
+            .into_iter()
+            .map(|mut document| {
+                if let Some(Bson::ObjectId(object_id)) = document.get(Self::ID_FIELD) {
+                    let object_id = object_id.to_hex();
+                    document.insert(Self::ID_FIELD, object_id);
+                }
+                Bson::Document(document).into_relaxed_extjson()
+            })
+            .collect();
+        let json_bytes = serde_json::to_vec(&json_list)?;
+        let data = JsonReader::new(Cursor::new(json_bytes)).finish()?;
+        Ok(data)
+    }
+
+    // Replaces the document with the same value of the key field, or inserts it if there is none,
+    // so that a payload scraped again does not duplicate. Returns the number of documents inserted
+    // or modified.
+    pub async fn upsert_by_key<T: Serialize>(
+        &self,
+        collection: &Collection<T>,
+        key_field: &str,
+        documents: &[T],
+    ) -> Result<usize> {
+        let options = ReplaceOptions::builder().upsert(true).build();
+        let mut num_changed = 0;
+        for document in documents {
+            let key_value = bson::to_document(document)?
+                .get(key_field)
+                .cloned()
+                .unwrap_or(Bson::Null);
+            let update_result = self.log_mongo_error(
+                collection
+                    .replace_one(doc! {key_field: key_value}, document, options.clone())
+                    .await,
+                &format!(
+                    "upsert document by {key_field} to mongodb collection {}",
+                    collection.name()
+                ),
+            )?;
+            if update_result.upserted_id.is_some() || update_result.modified_count > 0 {
+                num_changed += 1;
+            }
+        }
+        let debug_str = format!(
+            "{num_changed} documents upserted by {key_field} to mongodb collection {}.",
+            collection.name()
+        );
+        self.project_logger.log_debug(&debug_str);
+        Ok(num_changed)
+    }
+
+    // Returns the name of the index. Creating an index that already exists is a no-op.
+    pub async fn create_index<T>(
+        &self,
+        collection: &Collection<T>,
+        field_list: &[&str],
+        unique: bool,
+    ) -> Result<String> {
+        let keys: Document = field_list
+            .iter()
+            .map(|field| (field.to_string(), Bson::Int32(1)))
+            .collect();
+        let index_model = IndexModel::builder()
+            .keys(keys)
+            .options(IndexOptions::builder().unique(unique).build())
+            .build();
+        let index_result = self.log_mongo_error(
+            collection.create_index(index_model, None).await,
+            &format!(
+                "create index on {} of mongodb collection {}",
+                field_list.join(", "),
+                collection.name()
+            ),
+        )?;
+        Ok(index_result.index_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{env, path::Path};
+
+    use log::LevelFilter;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct MatchPayload {
+        match_id: String,
+        home_team: String,
+        odds: Vec<f64>,
+    }
+
+    #[tokio::test]
+    async fn test_insert_upsert_and_find() {
+        let logger_name = "test_mongo";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_io");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Debug);
+        let mongo_db = MongoDB::new(&project_logger);
+        let client = mongo_db.create_client().await.unwrap();
+        let collection: Collection<MatchPayload> =
+            MongoDB::get_collection(&client, "test", "match_payload");
+        collection.drop(None).await.unwrap();
+        mongo_db
+            .create_index(&collection, &["match_id"], true)
+            .await
+            .unwrap();
+        let payload_list = vec![
+            MatchPayload {
+                match_id: "1001".to_string(),
+                home_team: "Arsenal".to_string(),
+                odds: vec![1.8, 3.5, 4.2],
+            },
+            MatchPayload {
+                match_id: "1002".to_string(),
+                home_team: "Chelsea".to_string(),
+                odds: vec![2.1, 3.2, 3.4],
+            },
+        ];
+        assert_eq!(
+            mongo_db
+                .insert_many(&collection, &payload_list)
+                .await
+                .unwrap(),
+            2
+        );
+        let mut updated_payload = payload_list[0].clone();
+        updated_payload.odds = vec![1.7, 3.6, 4.5];
+        assert_eq!(
+            mongo_db
+                .upsert_by_key(&collection, "match_id", &[updated_payload.clone()])
+                .await
+                .unwrap(),
+            1
+        );
+        let found = mongo_db
+            .find(&collection, doc! {"match_id": "1001"})
+            .await
+            .unwrap();
+        assert_eq!(found, vec![updated_payload]);
+        let data = mongo_db
+            .find_to_data_frame(&collection, doc! {})
+            .await
+            .unwrap();
+        assert_eq!(data.height(), 2);
+        collection.drop(None).await.unwrap();
+    }
+}
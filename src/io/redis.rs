@@ -1,19 +1,50 @@
 use crate::logger::ProjectLogger;
-use redis::{Client, Commands, Connection, RedisResult};
+use redis::{Client, Commands, Connection, RedisResult, Script};
+use std::collections::HashMap;
+use std::time::Duration;
 
 pub struct Redis<'a> {
     project_logger: &'a ProjectLogger,
+    redis_path: String,
 }
 
 impl<'a> Redis<'a> {
     const REDIS_PATH: &'a str = "redis://127.0.0.1:6379";
+    const RELEASE_LOCK_SCRIPT: &'a str = r#"
+        if redis.call("GET", KEYS[1]) == ARGV[1] then
+            return redis.call("DEL", KEYS[1])
+        else
+            return 0
+        end
+    "#;
+    const RENEW_LOCK_SCRIPT: &'a str = r#"
+        if redis.call("GET", KEYS[1]) == ARGV[1] then
+            return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+        else
+            return 0
+        end
+    "#;
+    const INCREMENT_COUNTER_SCRIPT: &'a str = r#"
+        local count = redis.call("INCR", KEYS[1])
+        if count == 1 then
+            redis.call("PEXPIRE", KEYS[1], ARGV[1])
+        end
+        return count
+    "#;
 
     pub fn new(project_logger: &'a ProjectLogger) -> Self {
-        Self { project_logger }
+        Self {
+            project_logger,
+            redis_path: Self::REDIS_PATH.to_string(),
+        }
+    }
+
+    pub fn set_redis_path(&mut self, redis_path: &str) {
+        self.redis_path = redis_path.to_string();
     }
 
     pub fn create_connection(&self) -> RedisResult<Connection> {
-        let client = Client::open(Self::REDIS_PATH).unwrap_or_else(|e| {
+        let client = Client::open(self.redis_path.as_str()).unwrap_or_else(|e| {
             let error_str = format!("Fail to build redis connection client. {e}");
             self.project_logger.log_error(&error_str);
             panic!("{}", &error_str);
@@ -37,6 +68,173 @@ impl<'a> Redis<'a> {
             panic!("{}", &error_str);
         });
     }
+
+    fn log_redis_error<T>(&self, result: RedisResult<T>, action: &str) -> RedisResult<T> {
+        result.map_err(|e| {
+            let error_str = format!("Fail to {action}. {e}");
+            self.project_logger.log_error(&error_str);
+            e
+        })
+    }
+
+    pub fn get_string(&self, key: &str, conn: &mut Connection) -> RedisResult<Option<String>> {
+        self.log_redis_error(conn.get(key), &format!("get redis key {key}"))
+    }
+
+    pub fn set_string_with_ttl(
+        &self,
+        key: &str,
+        value: &str,
+        ttl: Duration,
+        conn: &mut Connection,
+    ) -> RedisResult<()> {
+        self.log_redis_error(
+            conn.pset_ex(key, value, ttl.as_millis() as u64),
+            &format!("set redis key {key}"),
+        )
+    }
+
+    pub fn set_hash_field(
+        &self,
+        key: &str,
+        field: &str,
+        value: &str,
+        conn: &mut Connection,
+    ) -> RedisResult<()> {
+        self.log_redis_error(
+            conn.hset(key, field, value),
+            &format!("set field {field} of redis hash {key}"),
+        )
+    }
+
+    pub fn get_hash_field(
+        &self,
+        key: &str,
+        field: &str,
+        conn: &mut Connection,
+    ) -> RedisResult<Option<String>> {
+        self.log_redis_error(
+            conn.hget(key, field),
+            &format!("get field {field} of redis hash {key}"),
+        )
+    }
+
+    pub fn get_hash(
+        &self,
+        key: &str,
+        conn: &mut Connection,
+    ) -> RedisResult<HashMap<String, String>> {
+        self.log_redis_error(conn.hgetall(key), &format!("get redis hash {key}"))
+    }
+
+    pub fn push_to_list(
+        &self,
+        key: &str,
+        value_list: &[&str],
+        conn: &mut Connection,
+    ) -> RedisResult<usize> {
+        self.log_redis_error(
+            conn.rpush(key, value_list),
+            &format!("push to redis list {key}"),
+        )
+    }
+
+    pub fn pop_from_list(&self, key: &str, conn: &mut Connection) -> RedisResult<Option<String>> {
+        self.log_redis_error(conn.lpop(key, None), &format!("pop from redis list {key}"))
+    }
+
+    pub fn get_list(&self, key: &str, conn: &mut Connection) -> RedisResult<Vec<String>> {
+        self.log_redis_error(conn.lrange(key, 0, -1), &format!("get redis list {key}"))
+    }
+
+    // Returns true only for the first caller adding the member, so it can deduplicate urls
+    // across machines.
+    pub fn add_if_new(&self, key: &str, member: &str, conn: &mut Connection) -> RedisResult<bool> {
+        let num_added: usize = self.log_redis_error(
+            conn.sadd(key, member),
+            &format!("add {member} to redis set {key}"),
+        )?;
+        Ok(num_added > 0)
+    }
+
+    pub fn acquire_lock(
+        &self,
+        key: &str,
+        token: &str,
+        ttl: Duration,
+        conn: &mut Connection,
+    ) -> RedisResult<bool> {
+        let response: Option<String> = self.log_redis_error(
+            redis::cmd("SET")
+                .arg(key)
+                .arg(token)
+                .arg("NX")
+                .arg("PX")
+                .arg(ttl.as_millis() as u64)
+                .query(conn),
+            &format!("acquire redis lock {key}"),
+        )?;
+        Ok(response.is_some())
+    }
+
+    // The lock is only deleted if it is still held by the token, so an expired lock taken over
+    // by another worker is not released by mistake.
+    pub fn release_lock(&self, key: &str, token: &str, conn: &mut Connection) -> RedisResult<bool> {
+        let num_deleted: usize = self.log_redis_error(
+            Script::new(Self::RELEASE_LOCK_SCRIPT)
+                .key(key)
+                .arg(token)
+                .invoke(conn),
+            &format!("release redis lock {key}"),
+        )?;
+        Ok(num_deleted > 0)
+    }
+
+    pub fn renew_lock(
+        &self,
+        key: &str,
+        token: &str,
+        ttl: Duration,
+        conn: &mut Connection,
+    ) -> RedisResult<bool> {
+        let num_renewed: usize = self.log_redis_error(
+            Script::new(Self::RENEW_LOCK_SCRIPT)
+                .key(key)
+                .arg(token)
+                .arg(ttl.as_millis() as u64)
+                .invoke(conn),
+            &format!("renew redis lock {key}"),
+        )?;
+        Ok(num_renewed > 0)
+    }
+
+    // Fixed window counter. The window starts with the first increment and the key expires with
+    // the window. The increment and the expiry run in one script, so a counter is never left
+    // without expiry by a client dropping in between.
+    pub fn increment_rate_counter(
+        &self,
+        key: &str,
+        window: Duration,
+        conn: &mut Connection,
+    ) -> RedisResult<u64> {
+        self.log_redis_error(
+            Script::new(Self::INCREMENT_COUNTER_SCRIPT)
+                .key(key)
+                .arg(window.as_millis() as u64)
+                .invoke(conn),
+            &format!("increment redis counter {key}"),
+        )
+    }
+
+    pub fn is_rate_limited(
+        &self,
+        key: &str,
+        limit: u64,
+        window: Duration,
+        conn: &mut Connection,
+    ) -> RedisResult<bool> {
+        Ok(self.increment_rate_counter(key, window, conn)? > limit)
+    }
 }
 
 #[cfg(test)]
@@ -81,4 +279,39 @@ mod tests {
         let value = Redis::get_value_from_key(key, &mut conn);
         assert_eq!(value, 0);
     }
+
+    #[test]
+    fn test_lock_and_rate_counter() {
+        let logger_name = "test_redis";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_io");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Debug);
+        let redis = Redis::new(&project_logger);
+        let mut conn = redis.create_connection().unwrap();
+        let lock_key = "test_lock";
+        let ttl = Duration::from_secs(10);
+        redis.reset_key(lock_key, &mut conn);
+        assert!(redis
+            .acquire_lock(lock_key, "worker_1", ttl, &mut conn)
+            .unwrap());
+        assert!(!redis
+            .acquire_lock(lock_key, "worker_2", ttl, &mut conn)
+            .unwrap());
+        assert!(!redis.release_lock(lock_key, "worker_2", &mut conn).unwrap());
+        assert!(redis.release_lock(lock_key, "worker_1", &mut conn).unwrap());
+        let counter_key = "test_rate_counter";
+        redis.reset_key(counter_key, &mut conn);
+        assert!(!redis
+            .is_rate_limited(counter_key, 2, ttl, &mut conn)
+            .unwrap());
+        assert!(!redis
+            .is_rate_limited(counter_key, 2, ttl, &mut conn)
+            .unwrap());
+        assert!(redis
+            .is_rate_limited(counter_key, 2, ttl, &mut conn)
+            .unwrap());
+        redis.reset_key(counter_key, &mut conn);
+    }
 }
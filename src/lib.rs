@@ -1,15 +1,48 @@
+pub mod error;
 pub mod io;
 pub mod logging;
 pub mod messenger;
 pub mod misc;
 pub mod netdata;
 
+pub use error::{Error, Result};
+#[cfg(feature = "s3")]
 pub use io::aws_s3;
+#[cfg(feature = "duckdb")]
 pub use io::duck_db;
 pub use io::file_compress;
 pub use io::file_io;
+#[cfg(feature = "kafka")]
+pub use io::kafka;
+#[cfg(feature = "mongo")]
+pub use io::mongo;
 pub use io::redis;
+#[cfg(feature = "s3")]
+pub use logging::log_query;
+#[cfg(feature = "s3")]
+pub use logging::log_shipper;
 pub use logging::logger;
+pub use logging::telemetry;
+#[cfg(feature = "slack")]
 pub use messenger::slack_messenger;
+pub use messenger::webhook_messenger;
+pub use misc::config;
+pub use misc::config_value;
+pub use misc::disk_guard;
+pub use misc::dry_run;
+#[cfg(all(feature = "s3", feature = "slack"))]
+pub use misc::heartbeat;
+pub use misc::lock;
+#[cfg(feature = "slack")]
+pub use misc::profiling;
+pub use misc::reporting;
+pub use misc::run_context;
+#[cfg(feature = "slack")]
+pub use misc::scheduler;
+pub use misc::secrets_provider;
+pub use misc::shared;
+pub use misc::shutdown;
 pub use misc::time_operation;
 pub use misc::utilities_function;
+#[cfg(feature = "slack")]
+pub use misc::validation;
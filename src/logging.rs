@@ -1 +1,6 @@
+#[cfg(feature = "s3")]
+pub mod log_query;
+#[cfg(feature = "s3")]
+pub mod log_shipper;
 pub mod logger;
+pub mod telemetry;
@@ -0,0 +1,270 @@
+use chrono::NaiveDateTime;
+use polars::prelude::*;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+use super::log_shipper::{split_log_line, LogShipper, LOG_TIME_FORMAT};
+use crate::aws_s3::AWSFileIO;
+use crate::logger::ProjectLogger;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    pub source: String,
+    pub time: NaiveDateTime,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+// Filters the lines of the ProjectLogger files by time range, level and a regex on the message.
+// The continuation lines of a multi-line message are kept with the line that starts it.
+#[derive(Debug, Clone, Default)]
+pub struct LogQuery {
+    start_time: Option<NaiveDateTime>,
+    end_time: Option<NaiveDateTime>,
+    level_list: Vec<String>,
+    pattern: Option<Regex>,
+}
+
+impl LogQuery {
+    pub const SOURCE_COLUMN: &'static str = "source";
+    pub const TIME_COLUMN: &'static str = "time";
+    pub const LEVEL_COLUMN: &'static str = "level";
+    pub const TARGET_COLUMN: &'static str = "target";
+    pub const MESSAGE_COLUMN: &'static str = "message";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The end time is exclusive.
+    pub fn set_time_range(
+        &mut self,
+        start_time: Option<NaiveDateTime>,
+        end_time: Option<NaiveDateTime>,
+    ) {
+        self.start_time = start_time;
+        self.end_time = end_time;
+    }
+
+    pub fn set_levels(&mut self, level_list: &[&str]) {
+        self.level_list = level_list
+            .iter()
+            .map(|level| level.to_uppercase())
+            .collect();
+    }
+
+    pub fn set_pattern(&mut self, pattern: &str) -> Result<(), regex::Error> {
+        self.pattern = Some(Regex::new(pattern)?);
+        Ok(())
+    }
+
+    fn is_in_time_range(&self, log_time: NaiveDateTime) -> bool {
+        self.start_time
+            .map_or(true, |start_time| log_time >= start_time)
+            && self.end_time.map_or(true, |end_time| log_time < end_time)
+    }
+
+    // Whether a file spanning the given times can hold any matching line.
+    pub fn overlaps(&self, file_start: NaiveDateTime, file_end: NaiveDateTime) -> bool {
+        self.start_time
+            .map_or(true, |start_time| file_end >= start_time)
+            && self.end_time.map_or(true, |end_time| file_start < end_time)
+    }
+
+    fn is_match(&self, log_record: &LogRecord) -> bool {
+        (self.level_list.is_empty() || self.level_list.contains(&log_record.level))
+            && self
+                .pattern
+                .as_ref()
+                .map_or(true, |pattern| pattern.is_match(&log_record.message))
+    }
+
+    pub fn search_content(&self, source: &str, log_content: &str) -> Vec<LogRecord> {
+        let mut record_list = Vec::new();
+        let mut current_record: Option<LogRecord> = None;
+        for line in log_content.lines() {
+            match split_log_line(line) {
+                Some((log_time, level, rest)) => {
+                    if let Some(log_record) = current_record.take() {
+                        if self.is_match(&log_record) {
+                            record_list.push(log_record);
+                        }
+                    }
+                    if self.is_in_time_range(log_time) {
+                        let (target, message) = rest.split_once(" - ").unwrap_or(("", rest));
+                        current_record = Some(LogRecord {
+                            source: source.to_string(),
+                            time: log_time,
+                            level: level.to_string(),
+                            target: target.to_string(),
+                            message: message.to_string(),
+                        });
+                    }
+                }
+                None => {
+                    if let Some(log_record) = current_record.as_mut() {
+                        log_record.message.push('\n');
+                        log_record.message.push_str(line);
+                    }
+                }
+            }
+        }
+        if let Some(log_record) = current_record {
+            if self.is_match(&log_record) {
+                record_list.push(log_record);
+            }
+        }
+        record_list
+    }
+
+    pub fn search_file(&self, log_file: &Path) -> std::io::Result<Vec<LogRecord>> {
+        let log_content = if log_file
+            .extension()
+            .is_some_and(|extension| extension == "gz")
+        {
+            LogShipper::load_archive(log_file)?
+        } else {
+            fs::read_to_string(log_file)?
+        };
+        Ok(self.search_content(&log_file.display().to_string(), &log_content))
+    }
+
+    // Searches the current log file and the rotated archives of the logger.
+    pub fn search_logger(&self, project_logger: &ProjectLogger) -> Vec<LogRecord> {
+        let mut log_file_list = vec![project_logger.get_log_file().to_path_buf()];
+        log_file_list.extend(project_logger.get_archive_file_list());
+        self.search_file_list(project_logger, &log_file_list)
+    }
+
+    // Searches every .log and .gz file in a folder, e.g. the logs collected from several hosts.
+    pub fn search_folder(
+        &self,
+        project_logger: &ProjectLogger,
+        folder_path: &Path,
+    ) -> Vec<LogRecord> {
+        let log_file_list: Vec<_> = match fs::read_dir(folder_path) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|log_file| {
+                    log_file.is_file()
+                        && log_file
+                            .extension()
+                            .is_some_and(|extension| extension == "log" || extension == "gz")
+                })
+                .collect(),
+            Err(e) => {
+                let error_str = format!("Unable to read log folder {}. {e}", folder_path.display());
+                project_logger.log_error(&error_str);
+                Vec::new()
+            }
+        };
+        self.search_file_list(project_logger, &log_file_list)
+    }
+
+    fn search_file_list<P: AsRef<Path>>(
+        &self,
+        project_logger: &ProjectLogger,
+        log_file_list: &[P],
+    ) -> Vec<LogRecord> {
+        let mut record_list = Vec::new();
+        for log_file in log_file_list {
+            match self.search_file(log_file.as_ref()) {
+                Ok(file_record_list) => record_list.extend(file_record_list),
+                Err(e) => {
+                    let warn_str = format!(
+                        "Unable to search log file {}. {e}",
+                        log_file.as_ref().display()
+                    );
+                    project_logger.log_warn(&warn_str);
+                }
+            }
+        }
+        record_list.sort_by(|a, b| a.time.cmp(&b.time));
+        record_list
+    }
+
+    // Uses the index written by the LogShipper to download only the archives overlapping the
+    // time range into the cache folder before searching them.
+    pub async fn search_shipped(
+        &self,
+        project_logger: &ProjectLogger,
+        aws_file_io: &AWSFileIO<'_>,
+        bucket_name: &str,
+        folder_path: &Path,
+        cache_path: &Path,
+    ) -> crate::error::Result<Vec<LogRecord>> {
+        let index_data = aws_file_io
+            .load_parquet_file(bucket_name, folder_path, LogShipper::INDEX_FILE)
+            .await?;
+        let file_column = index_data.column(LogShipper::FILE_COLUMN)?.str()?;
+        let start_column = index_data.column(LogShipper::START_TIME_COLUMN)?.str()?;
+        let end_column = index_data.column(LogShipper::END_TIME_COLUMN)?.str()?;
+        let parse_time = |log_time: Option<&str>| {
+            log_time
+                .and_then(|log_time| NaiveDateTime::parse_from_str(log_time, LOG_TIME_FORMAT).ok())
+        };
+        fs::create_dir_all(cache_path)?;
+        let mut log_file_list = Vec::new();
+        for ((file, file_start), file_end) in file_column
+            .into_iter()
+            .zip(start_column.into_iter())
+            .zip(end_column.into_iter())
+        {
+            let (Some(file), Some(file_start), Some(file_end)) =
+                (file, parse_time(file_start), parse_time(file_end))
+            else {
+                continue;
+            };
+            if !self.overlaps(file_start, file_end) {
+                continue;
+            }
+            if !cache_path.join(file).is_file() {
+                aws_file_io
+                    .download_file(bucket_name, folder_path, file, cache_path, file)
+                    .await?;
+            }
+            log_file_list.push(cache_path.join(file));
+        }
+        Ok(self.search_file_list(project_logger, &log_file_list))
+    }
+
+    pub fn to_data_frame(record_list: &[LogRecord]) -> PolarsResult<DataFrame> {
+        df!(
+            Self::SOURCE_COLUMN => record_list.iter().map(|x| x.source.as_str()).collect::<Vec<&str>>(),
+            Self::TIME_COLUMN => record_list.iter().map(|x| x.time).collect::<Vec<NaiveDateTime>>(),
+            Self::LEVEL_COLUMN => record_list.iter().map(|x| x.level.as_str()).collect::<Vec<&str>>(),
+            Self::TARGET_COLUMN => record_list.iter().map(|x| x.target.as_str()).collect::<Vec<&str>>(),
+            Self::MESSAGE_COLUMN => record_list.iter().map(|x| x.message.as_str()).collect::<Vec<&str>>()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_log_query() {
+        let log_content = "2024-05-01 10:00:00 | INFO  | scraper - Start\n\
+            2024-05-01 10:00:05 | ERROR | scraper - Timeout of https://www.nowgoal.com\n\
+            caused by connection reset\n\
+            2024-05-01 10:03:00 | ERROR | scraper - Blocked by https://tfl.gov.uk\n\
+            2024-05-01 11:00:00 | ERROR | scraper - Timeout of https://tfl.gov.uk\n";
+        let mut log_query = LogQuery::new();
+        log_query.set_time_range(
+            NaiveDateTime::parse_from_str("2024-05-01 10:00:00", LOG_TIME_FORMAT).ok(),
+            NaiveDateTime::parse_from_str("2024-05-01 11:00:00", LOG_TIME_FORMAT).ok(),
+        );
+        log_query.set_levels(&["error"]);
+        log_query.set_pattern("^Timeout").unwrap();
+        let record_list = log_query.search_content("host_1", log_content);
+        assert_eq!(record_list.len(), 1);
+        assert_eq!(record_list[0].target, "scraper");
+        assert!(record_list[0].message.ends_with("connection reset"));
+        let data = LogQuery::to_data_frame(&record_list).unwrap();
+        assert_eq!(data.shape(), (1, 5));
+    }
+}
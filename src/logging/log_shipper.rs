@@ -0,0 +1,298 @@
+use chrono::{NaiveDateTime, Utc};
+use flate2::read::GzDecoder;
+use polars::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::aws_s3::AWSFileIO;
+use crate::logger::ProjectLogger;
+use crate::run_context::RunContext;
+use crate::shutdown::ShutdownSignal;
+
+pub(crate) const LOG_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+pub(crate) const LEVEL_LIST: [&str; 5] = ["TRACE", "DEBUG", "INFO", "WARN", "ERROR"];
+
+// Splits a line written by the ProjectLogger pattern, "{time} | {level} | {target} - {message}",
+// into its time, level and the rest. Continuation lines of multi-line messages return None.
+pub(crate) fn split_log_line(line: &str) -> Option<(NaiveDateTime, &str, &str)> {
+    let mut field_iter = line.splitn(3, " | ");
+    let log_time = NaiveDateTime::parse_from_str(field_iter.next()?, LOG_TIME_FORMAT).ok()?;
+    let level = field_iter.next()?.trim();
+    Some((log_time, level, field_iter.next().unwrap_or_default()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFileSummary {
+    pub start_time: Option<NaiveDateTime>,
+    pub end_time: Option<NaiveDateTime>,
+    pub level_counts: BTreeMap<&'static str, u64>,
+}
+
+impl LogFileSummary {
+    pub fn from_log_content(log_content: &str) -> Self {
+        let mut start_time = None;
+        let mut end_time = None;
+        let mut level_counts: BTreeMap<&'static str, u64> =
+            LEVEL_LIST.iter().map(|level| (*level, 0)).collect();
+        for (log_time, level_str, _) in log_content.lines().filter_map(split_log_line) {
+            start_time.get_or_insert(log_time);
+            end_time = Some(log_time);
+            if let Some(level) = LEVEL_LIST.iter().find(|level| **level == level_str) {
+                *level_counts.entry(level).or_default() += 1;
+            }
+        }
+        Self {
+            start_time,
+            end_time,
+            level_counts,
+        }
+    }
+
+    // Rotated archives are renamed on every roll, so the object is named by its time span instead.
+    // The host keeps the archives of the same job on different machines apart.
+    pub fn get_object_name(&self, logger_name: &str, host: &str) -> Option<String> {
+        let (start_time, end_time) = (self.start_time?, self.end_time?);
+        Some(format!(
+            "{logger_name}_{host}_{}_{}.log.gz",
+            start_time.format("%Y%m%d%H%M%S"),
+            end_time.format("%Y%m%d%H%M%S")
+        ))
+    }
+}
+
+// Uploads the rotated log archives of a ProjectLogger to an S3 prefix, together with an index
+// parquet of the job name, time span and level counts of every archive, so that the logs of
+// recycled machines can still be searched.
+pub struct LogShipper<'a> {
+    project_logger: &'a ProjectLogger,
+    aws_file_io: &'a AWSFileIO<'a>,
+    bucket_name: String,
+    folder_path: PathBuf,
+    host: String,
+    interval: Duration,
+    shipped_objects: Mutex<HashSet<String>>,
+}
+
+impl<'a> LogShipper<'a> {
+    pub const INDEX_FILE: &'static str = "log_index.parquet";
+    pub const JOB_NAME_COLUMN: &'static str = "job_name";
+    pub const FILE_COLUMN: &'static str = "file";
+    pub const START_TIME_COLUMN: &'static str = "start_time";
+    pub const END_TIME_COLUMN: &'static str = "end_time";
+    pub const SHIPPED_TIME_COLUMN: &'static str = "shipped_time";
+    const INTERVAL: Duration = Duration::from_secs(600);
+    const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new(
+        project_logger: &'a ProjectLogger,
+        aws_file_io: &'a AWSFileIO<'a>,
+        bucket_name: &str,
+        folder_path: &Path,
+    ) -> Self {
+        Self {
+            project_logger,
+            aws_file_io,
+            bucket_name: bucket_name.to_string(),
+            folder_path: folder_path.to_path_buf(),
+            host: RunContext::get_host_name(),
+            interval: Self::INTERVAL,
+            shipped_objects: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    pub(crate) fn load_archive(archive_file: &Path) -> std::io::Result<String> {
+        let mut log_content = String::new();
+        GzDecoder::new(File::open(archive_file)?).read_to_string(&mut log_content)?;
+        Ok(log_content)
+    }
+
+    fn get_index_data(&self, shipped_list: &[(String, LogFileSummary)]) -> PolarsResult<DataFrame> {
+        let shipped_time = Utc::now().format(LOG_TIME_FORMAT).to_string();
+        let format_time = |log_time: Option<NaiveDateTime>| {
+            log_time.map(|log_time| log_time.format(LOG_TIME_FORMAT).to_string())
+        };
+        let mut column_list = vec![
+            Column::new(
+                Self::JOB_NAME_COLUMN.into(),
+                vec![self.project_logger.get_logger_name(); shipped_list.len()],
+            ),
+            Column::new(
+                Self::FILE_COLUMN.into(),
+                shipped_list
+                    .iter()
+                    .map(|(object_name, _)| object_name.as_str())
+                    .collect::<Vec<&str>>(),
+            ),
+            Column::new(
+                Self::START_TIME_COLUMN.into(),
+                shipped_list
+                    .iter()
+                    .map(|(_, summary)| format_time(summary.start_time))
+                    .collect::<Vec<Option<String>>>(),
+            ),
+            Column::new(
+                Self::END_TIME_COLUMN.into(),
+                shipped_list
+                    .iter()
+                    .map(|(_, summary)| format_time(summary.end_time))
+                    .collect::<Vec<Option<String>>>(),
+            ),
+        ];
+        for level in LEVEL_LIST {
+            column_list.push(Column::new(
+                level.to_lowercase().into(),
+                shipped_list
+                    .iter()
+                    .map(|(_, summary)| {
+                        summary.level_counts.get(level).copied().unwrap_or_default()
+                    })
+                    .collect::<Vec<u64>>(),
+            ));
+        }
+        column_list.push(Column::new(
+            Self::SHIPPED_TIME_COLUMN.into(),
+            vec![shipped_time; shipped_list.len()],
+        ));
+        DataFrame::new(column_list)
+    }
+
+    // Returns the number of archives uploaded. Archives already in the prefix, shipped by this
+    // shipper or by an earlier run on the same host, are skipped. When an upload fails, the
+    // archives uploaded before it are still indexed before the error is returned.
+    pub async fn ship_rotated_logs(&self) -> crate::error::Result<usize> {
+        let logger_name = self.project_logger.get_logger_name();
+        let mut shipped_list = Vec::new();
+        let mut upload_result = Ok(());
+        for archive_file in self.project_logger.get_archive_file_list() {
+            let log_content = match Self::load_archive(&archive_file) {
+                Ok(log_content) => log_content,
+                Err(e) => {
+                    let warn_str =
+                        format!("Unable to read log archive {}. {e}", archive_file.display());
+                    self.project_logger.log_warn(&warn_str);
+                    continue;
+                }
+            };
+            let summary = LogFileSummary::from_log_content(&log_content);
+            let Some(object_name) = summary.get_object_name(logger_name, &self.host) else {
+                continue;
+            };
+            let is_shipped = self
+                .shipped_objects
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .contains(&object_name);
+            if is_shipped
+                || self
+                    .aws_file_io
+                    .check_file_exist(&self.bucket_name, &self.folder_path, &object_name)
+                    .await
+            {
+                continue;
+            }
+            let (Some(local_path), Some(local_file)) = (
+                archive_file.parent(),
+                archive_file.file_name().and_then(|file| file.to_str()),
+            ) else {
+                continue;
+            };
+            if let Err(e) = self
+                .aws_file_io
+                .upload_file(
+                    &self.bucket_name,
+                    &self.folder_path,
+                    &object_name,
+                    local_path,
+                    local_file,
+                )
+                .await
+            {
+                upload_result = Err(e);
+                break;
+            }
+            shipped_list.push((object_name, summary));
+        }
+        if shipped_list.is_empty() {
+            return upload_result.map(|()| 0).map_err(Into::into);
+        }
+        let index_data = self.get_index_data(&shipped_list)?;
+        self.aws_file_io
+            .upsert_parquet_file(
+                &self.bucket_name,
+                &self.folder_path,
+                Self::INDEX_FILE,
+                &index_data,
+                &[Self::FILE_COLUMN],
+            )
+            .await?;
+        self.shipped_objects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend(
+                shipped_list
+                    .iter()
+                    .map(|(object_name, _)| object_name.clone()),
+            );
+        let debug_str = format!(
+            "{} log archives of {logger_name} shipped to {} in bucket {}.",
+            shipped_list.len(),
+            self.folder_path.display(),
+            self.bucket_name
+        );
+        self.project_logger.log_debug(&debug_str);
+        upload_result?;
+        Ok(shipped_list.len())
+    }
+
+    // Ships every interval until the shutdown signal is requested, then ships once more so the
+    // archives rolled near the end of the run are not left behind.
+    pub async fn run(&self, shutdown_signal: &ShutdownSignal) {
+        loop {
+            let mut waited = Duration::ZERO;
+            while waited < self.interval && !shutdown_signal.is_requested() {
+                tokio::time::sleep(Self::SHUTDOWN_CHECK_INTERVAL).await;
+                waited += Self::SHUTDOWN_CHECK_INTERVAL;
+            }
+            if let Err(e) = self.ship_rotated_logs().await {
+                let error_str = format!("Unable to ship the rotated logs. {e}");
+                self.project_logger.log_error(&error_str);
+            }
+            if shutdown_signal.is_requested() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_log_file_summary() {
+        let log_content = "2024-05-01 10:00:00 | INFO  | scraper - Start\n\
+            2024-05-01 10:00:05 | ERROR | scraper - Failed\n\
+            second line of the error\n\
+            2024-05-01 10:03:00 | WARN  | scraper - Slow\n";
+        let summary = LogFileSummary::from_log_content(log_content);
+        assert_eq!(summary.level_counts["INFO"], 1);
+        assert_eq!(summary.level_counts["ERROR"], 1);
+        assert_eq!(summary.level_counts["DEBUG"], 0);
+        assert_eq!(
+            summary.get_object_name("scraper", "worker-1").unwrap(),
+            "scraper_worker-1_20240501100000_20240501100300.log.gz"
+        );
+        assert!(LogFileSummary::from_log_content("")
+            .get_object_name("scraper", "worker-1")
+            .is_none());
+    }
+}
@@ -1,9 +1,12 @@
 extern crate byte_unit;
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, PoisonError};
 
-use log::LevelFilter;
 use log::{debug, error, info, trace, warn};
+use log::{LevelFilter, ParseLevelError};
 
 use log4rs::append::console::ConsoleAppender;
 use log4rs::append::file::FileAppender;
@@ -18,8 +21,20 @@ use log4rs::encode::pattern::PatternEncoder;
 use log4rs::config::{Appender, Logger, Root};
 use log4rs::{Config, Handle};
 
+use crate::config::LoggingConfig;
+use crate::run_context::RunContext;
+
 const DEFAULT_MAX_FILE_SIZE_MB: u128 = 10;
 const DEFAULT_ROLLER_COUNT: u32 = 10;
+const CRATE_MODULES: [&str; 5] = ["io", "logging", "messenger", "misc", "netdata"];
+const AWS_TARGETS: [&str; 6] = [
+    "aws_config",
+    "aws_sdk_s3",
+    "aws_sdk_secretsmanager",
+    "aws_sdk_ssm",
+    "aws_smithy_client",
+    "aws_smithy_http",
+];
 
 #[derive(Debug)]
 pub struct ProjectLogger {
@@ -30,6 +45,8 @@ pub struct ProjectLogger {
     archive_logger_file_name: String,
     max_file_size_mb: u128,
     roller_count: u32,
+    root_level: Mutex<LevelFilter>,
+    target_levels: Mutex<BTreeMap<String, LevelFilter>>,
 }
 
 impl ProjectLogger {
@@ -51,10 +68,87 @@ impl ProjectLogger {
             archive_logger_file_name,
             max_file_size_mb: DEFAULT_MAX_FILE_SIZE_MB,
             roller_count: DEFAULT_ROLLER_COUNT,
+            root_level: Mutex::new(LevelFilter::Info),
+            target_levels: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    // The targets are the log targets of the records, i.e. the module paths of the log and
+    // tracing macros, or the name of a ProjectLogger. The top level modules of this crate, e.g.
+    // "netdata", and "aws" for the aws sdk crates are accepted as short names.
+    pub fn resolve_target(target: &str) -> Vec<String> {
+        let crate_name = module_path!().split("::").next().unwrap_or_default();
+        if CRATE_MODULES.contains(&target) {
+            vec![format!("{crate_name}::{target}")]
+        } else if target == "aws" {
+            AWS_TARGETS
+                .iter()
+                .map(|target| target.to_string())
+                .collect()
+        } else {
+            vec![target.to_string()]
+        }
+    }
+
+    pub fn set_target_level(&mut self, target: &str, level: LevelFilter) {
+        let target_levels = self
+            .target_levels
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        for target in Self::resolve_target(target) {
+            target_levels.insert(target, level);
+        }
+    }
+
+    // Returns the level of the root logger in the config, if any, to be passed to set_logger.
+    pub fn apply_logging_config(
+        &mut self,
+        logging_config: &LoggingConfig,
+    ) -> Result<Option<LevelFilter>, ParseLevelError> {
+        for (target, level) in logging_config.targets.iter() {
+            self.set_target_level(target, LevelFilter::from_str(level)?);
         }
+        logging_config
+            .level
+            .as_deref()
+            .map(LevelFilter::from_str)
+            .transpose()
+    }
+
+    // Changes the level of one target on a running logger, e.g. to turn on the debug logs of a
+    // subsystem without restarting. LevelFilter::Off silences the target.
+    pub fn set_level(&self, handle: &Handle, target: &str, level: LevelFilter) {
+        {
+            let mut target_levels = self
+                .target_levels
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            for target in Self::resolve_target(target) {
+                target_levels.insert(target, level);
+            }
+        }
+        handle.set_config(self.build_config());
+        let info_str = format!("Log level of {target} set to {level}.");
+        self.log_info(&info_str);
+    }
+
+    pub fn get_target_levels(&self) -> BTreeMap<String, LevelFilter> {
+        self.target_levels
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
     }
 
     pub fn set_logger(&self, logger_level: LevelFilter) -> Handle {
+        *self
+            .root_level
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = logger_level;
+        log4rs::init_config(self.build_config())
+            .unwrap_or_else(|_| panic!("Error in init_config for {}", self.logger_name))
+    }
+
+    fn build_config(&self) -> Config {
         let log_line_pattern = "{d(%Y-%m-%d %H:%M:%S)} | {h({l}):5.5} | {t} - {m}{n}";
 
         let trigger_size = byte_unit::n_mb_bytes!(self.max_file_size_mb) as u64;
@@ -95,48 +189,64 @@ impl ProjectLogger {
 
         let stdout_ap = ConsoleAppender::builder().build();
 
-        let config = Config::builder()
+        let mut target_levels = self.get_target_levels();
+        let std_file_level = target_levels
+            .remove(&self.logger_name)
+            .unwrap_or(LevelFilter::Debug);
+        let error_file_level = target_levels
+            .remove(&self.error_logger_name)
+            .unwrap_or(LevelFilter::Error);
+        let logger_level = *self
+            .root_level
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        let mut config_builder = Config::builder()
             .appender(Appender::builder().build("stdout_ap", Box::new(stdout_ap)))
             .appender(Appender::builder().build("std_file_ap", Box::new(std_file_ap)))
             .appender(Appender::builder().build("err_file_ap", Box::new(err_file_ap)))
             .logger(
                 Logger::builder()
                     .appender("std_file_ap")
-                    .build(&self.logger_name, LevelFilter::Debug),
+                    .build(&self.logger_name, std_file_level),
             )
             .logger(
                 Logger::builder()
                     .appender("err_file_ap")
-                    .build(&self.error_logger_name, LevelFilter::Error),
-            )
+                    .build(&self.error_logger_name, error_file_level),
+            );
+        for (target, level) in target_levels {
+            config_builder = config_builder.logger(
+                Logger::builder()
+                    .appender("std_file_ap")
+                    .build(target, level),
+            );
+        }
+        config_builder
             .build(Root::builder().appender("stdout_ap").build(logger_level))
-            .unwrap_or_else(|_| {
-                panic!("Error in configuration of logger for {}", self.logger_name)
-            });
-
-        log4rs::init_config(config)
-            .unwrap_or_else(|_| panic!("Error in init_config for {}", self.logger_name))
+            .unwrap_or_else(|_| panic!("Error in configuration of logger for {}", self.logger_name))
     }
 
     pub fn log_trace(&self, message: &str) {
-        trace!(target: &self.logger_name, "{message}");
+        trace!(target: &self.logger_name, "{}{message}", RunContext::current_tag());
     }
 
     pub fn log_debug(&self, message: &str) {
-        debug!(target: &self.logger_name, "{message}");
+        debug!(target: &self.logger_name, "{}{message}", RunContext::current_tag());
     }
 
     pub fn log_info(&self, message: &str) {
-        info!(target: &self.logger_name, "{message}");
+        info!(target: &self.logger_name, "{}{message}", RunContext::current_tag());
     }
 
     pub fn log_warn(&self, message: &str) {
-        warn!(target: &self.logger_name, "{message}");
+        warn!(target: &self.logger_name, "{}{message}", RunContext::current_tag());
     }
 
     pub fn log_error(&self, message: &str) {
-        error!(target: &self.logger_name, "{message}");
-        error!(target: &self.error_logger_name, "{message}");
+        let tag = RunContext::current_tag();
+        error!(target: &self.logger_name, "{tag}{message}");
+        error!(target: &self.error_logger_name, "{tag}{message}");
     }
 
     pub fn get_logger_name(&self) -> &str {
@@ -147,6 +257,23 @@ impl ProjectLogger {
         &self.error_logger_name
     }
 
+    pub fn get_log_file(&self) -> &Path {
+        &self.full_logger_path_file
+    }
+
+    // The rotated archives that exist on disk, from the newest to the oldest.
+    pub fn get_archive_file_list(&self) -> Vec<PathBuf> {
+        (0..self.roller_count)
+            .map(|index| {
+                PathBuf::from(
+                    self.archive_logger_file_name
+                        .replace("{}", &index.to_string()),
+                )
+            })
+            .filter(|archive_file| archive_file.is_file())
+            .collect()
+    }
+
     pub fn set_max_file_size_mb(&mut self, max_file_size_mb: u128) {
         self.max_file_size_mb = max_file_size_mb
     }
@@ -177,4 +304,34 @@ mod tests {
         logger.log_warn(&format!("This is warn from {}", logger.get_logger_name()));
         logger.log_error(&format!("This is error from {}", logger.get_logger_name()));
     }
+
+    #[test]
+    fn test_logging_config() {
+        let logging_config: LoggingConfig = toml::from_str(
+            r#"
+            level = "warn"
+
+            [targets]
+            netdata = "debug"
+            aws = "error"
+            test = "info"
+            "#,
+        )
+        .unwrap();
+        let mut logger = ProjectLogger::new_logger(Path::new("."), "test");
+        let root_level = logger.apply_logging_config(&logging_config).unwrap();
+        assert_eq!(root_level, Some(LevelFilter::Warn));
+        let target_levels = logger.get_target_levels();
+        assert_eq!(
+            target_levels["sctys_rust_utilities::netdata"],
+            LevelFilter::Debug
+        );
+        assert_eq!(target_levels["aws_sdk_s3"], LevelFilter::Error);
+        assert_eq!(target_levels["test"], LevelFilter::Info);
+        let logging_config = LoggingConfig {
+            level: None,
+            targets: BTreeMap::from([("netdata".to_string(), "loud".to_string())]),
+        };
+        assert!(logger.apply_logging_config(&logging_config).is_err());
+    }
 }
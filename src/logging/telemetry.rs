@@ -0,0 +1,131 @@
+use log::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+use crate::config::TelemetryConfig;
+
+// Without an installed subscriber, the tracing spans and events are forwarded to the log4rs
+// logger of ProjectLogger through the log feature of tracing. Calling init switches them to the
+// tracing subscriber, optionally exporting the spans to an OTLP collector.
+#[derive(Debug, Clone)]
+pub struct Telemetry {
+    service_name: String,
+    logger_level: LevelFilter,
+    otlp_endpoint: Option<String>,
+}
+
+impl Telemetry {
+    const LOGGER_LEVEL: LevelFilter = LevelFilter::Info;
+
+    pub fn new(service_name: &str) -> Self {
+        Self {
+            service_name: service_name.to_string(),
+            logger_level: Self::LOGGER_LEVEL,
+            otlp_endpoint: None,
+        }
+    }
+
+    pub fn from_config(telemetry_config: &TelemetryConfig, default_service_name: &str) -> Self {
+        let mut telemetry = Self::new(
+            telemetry_config
+                .service_name
+                .as_deref()
+                .unwrap_or(default_service_name),
+        );
+        telemetry.otlp_endpoint = telemetry_config.otlp_endpoint.clone();
+        telemetry
+    }
+
+    pub fn set_logger_level(&mut self, logger_level: LevelFilter) {
+        self.logger_level = logger_level;
+    }
+
+    pub fn set_otlp_endpoint(&mut self, otlp_endpoint: &str) {
+        self.otlp_endpoint = Some(otlp_endpoint.to_string());
+    }
+
+    pub fn get_env_filter(&self) -> EnvFilter {
+        EnvFilter::new(self.logger_level.as_str().to_lowercase())
+    }
+
+    #[cfg(feature = "otlp")]
+    fn otlp_layer<S>(&self) -> Option<impl tracing_subscriber::Layer<S>>
+    where
+        S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+    {
+        use opentelemetry::KeyValue;
+        use opentelemetry_otlp::WithExportConfig;
+        use opentelemetry_sdk::{runtime, trace, Resource};
+
+        let otlp_endpoint = self.otlp_endpoint.as_ref()?;
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(otlp_endpoint),
+            )
+            .with_trace_config(
+                trace::config().with_resource(Resource::new(vec![KeyValue::new(
+                    "service.name",
+                    self.service_name.clone(),
+                )])),
+            )
+            .install_batch(runtime::Tokio)
+            .unwrap_or_else(|e| panic!("Unable to set up the OTLP exporter {otlp_endpoint}. {e}"));
+        Some(tracing_opentelemetry::layer().with_tracer(tracer))
+    }
+
+    #[cfg(feature = "otlp")]
+    pub fn init(&self) {
+        tracing_subscriber::registry()
+            .with(self.get_env_filter())
+            .with(fmt::layer())
+            .with(self.otlp_layer())
+            .try_init()
+            .unwrap_or_else(|e| panic!("Unable to init tracing for {}. {e}", self.service_name));
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    pub fn init(&self) {
+        if let Some(otlp_endpoint) = &self.otlp_endpoint {
+            panic!("Unable to export to {otlp_endpoint}. Build with the otlp feature.");
+        }
+        tracing_subscriber::registry()
+            .with(self.get_env_filter())
+            .with(fmt::layer())
+            .try_init()
+            .unwrap_or_else(|e| panic!("Unable to init tracing for {}. {e}", self.service_name));
+    }
+
+    // Flush the pending spans before the process exits.
+    pub fn shutdown(&self) {
+        #[cfg(feature = "otlp")]
+        if self.otlp_endpoint.is_some() {
+            opentelemetry::global::shutdown_tracer_provider();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_telemetry_from_config() {
+        let telemetry_config = TelemetryConfig {
+            service_name: None,
+            otlp_endpoint: Some("http://localhost:4317".to_string()),
+        };
+        let mut telemetry = Telemetry::from_config(&telemetry_config, "sctys_netdata");
+        assert_eq!(telemetry.service_name, "sctys_netdata");
+        assert_eq!(
+            telemetry.otlp_endpoint.as_deref(),
+            Some("http://localhost:4317")
+        );
+        telemetry.set_logger_level(LevelFilter::Debug);
+        assert_eq!(telemetry.get_env_filter().to_string(), "debug");
+    }
+}
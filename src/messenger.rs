@@ -1 +1,9 @@
+#[cfg(feature = "slack")]
 pub mod slack_messenger;
+pub mod webhook_messenger;
+
+// The interface shared by the messengers, so that the jobs can send their alerts to slack or to
+// any other endpoint without depending on the one used.
+pub trait Messenger {
+    fn retry_send_message(&self, calling_func: &str, message: &str, log_only: bool);
+}
@@ -1,51 +1,232 @@
 // extern crate slack;
 
+use crate::config::SlackConfig;
+use crate::dry_run::DryRun;
 use crate::logger::ProjectLogger;
-use crate::time_operation;
+use crate::messenger::Messenger;
+use crate::run_context::RunContext;
+use crate::secrets_provider::SecretsProvider;
+use crate::shared::Shared;
+use crate::time_operation::{self, SystemClock};
 use futures::executor;
 use serde::Deserialize;
+use serde_json::Value;
 use slack_rust as slack;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::Path;
-use std::time::Duration;
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
 use toml;
 
 const NUM_RETRY: u32 = 5;
 const RETRY_SLEEP: Duration = Duration::from_secs(5);
+const SLACK_API_URL: &str = "https://slack.com/api/";
+const ACK_POLL_INTERVAL: Duration = Duration::from_secs(30);
+const RATE_LIMIT_BURST: u32 = 10;
+const RATE_LIMIT_REFILL: Duration = Duration::from_secs(1);
+
+// Slack allows about one message per second per channel with short bursts.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_interval: Duration,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: u32, refill_interval: Duration, now: Instant) -> Self {
+        Self {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refill_interval,
+            last_refill: now,
+        }
+    }
+
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        let refill = elapsed.as_secs_f64() / self.refill_interval.as_secs_f64().max(f64::EPSILON);
+        self.tokens = (self.tokens + refill).min(self.capacity);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// Numbers are masked when fingerprinting so that alerts differing only in counts or ids, e.g.
+// "3 out of 100 fail urls" and "5 out of 100 fail urls", are treated as the same alert.
+fn normalize_message(message: &str) -> String {
+    let mut normalized = String::with_capacity(message.len());
+    let mut in_number = false;
+    for c in message
+        .to_lowercase()
+        .split_whitespace()
+        .flat_map(|word| word.chars().chain(std::iter::once(' ')))
+    {
+        if c.is_ascii_digit() {
+            if !in_number {
+                normalized.push('#');
+            }
+            in_number = true;
+        } else {
+            normalized.push(c);
+            in_number = false;
+        }
+    }
+    normalized.trim_end().to_string()
+}
+
+#[derive(Debug)]
+struct AlertCooldown {
+    cooldown: Duration,
+    history: HashMap<String, (Instant, u32)>,
+}
+
+impl AlertCooldown {
+    fn new(cooldown: Duration) -> Self {
+        Self {
+            cooldown,
+            history: HashMap::new(),
+        }
+    }
+
+    // Returns the number of alerts suppressed since the last one sent if the alert should be
+    // sent now, or None if it is suppressed.
+    fn admit(&mut self, fingerprint: String, now: Instant) -> Option<u32> {
+        let cooldown = self.cooldown;
+        self.history.retain(|_, (last_sent, suppressed)| {
+            *suppressed > 0 || now.saturating_duration_since(*last_sent) < cooldown
+        });
+        match self.history.get_mut(&fingerprint) {
+            Some((last_sent, suppressed))
+                if now.saturating_duration_since(*last_sent) < cooldown =>
+            {
+                *suppressed += 1;
+                None
+            }
+            Some((last_sent, suppressed)) => {
+                let num_suppressed = *suppressed;
+                *last_sent = now;
+                *suppressed = 0;
+                Some(num_suppressed)
+            }
+            None => {
+                self.history.insert(fingerprint, (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PendingMessage {
+    calling_func: String,
+    message: String,
+    log_only: bool,
+    queued_at: Instant,
+}
+
+fn format_digest(pending_messages: &[&PendingMessage]) -> String {
+    let lines: Vec<String> = pending_messages
+        .iter()
+        .map(|pending| format!("- {}: {}", pending.calling_func, pending.message))
+        .collect();
+    format!("{} messages\n{}", pending_messages.len(), lines.join("\n"))
+}
 
 #[derive(Debug)]
 pub struct SlackMessenger<'a> {
     api_token: String,
-    main_channel_id: &'a str,
-    log_channel_id: &'a str,
-    logger: &'a ProjectLogger,
+    main_channel_id: String,
+    log_channel_id: String,
+    logger: Shared<'a, ProjectLogger>,
     num_retry: u32,
     retry_sleep: Duration,
+    run_id: Option<String>,
+    thread_ts_map: Mutex<HashMap<String, String>>,
+    batch_window: Option<Duration>,
+    pending_messages: Mutex<Vec<PendingMessage>>,
+    rate_limit: (u32, Duration),
+    token_buckets: Mutex<HashMap<String, TokenBucket>>,
+    alert_cooldown: Option<Mutex<AlertCooldown>>,
 }
 
 impl<'a> SlackMessenger<'a> {
-    pub fn new(
-        main_channel_id: &'a str,
-        log_channel_id: &'a str,
-        logger: &'a ProjectLogger,
+    fn with_api_token(
+        api_token: String,
+        main_channel_id: &str,
+        log_channel_id: &str,
+        logger: Shared<'a, ProjectLogger>,
     ) -> Self {
-        let api_token = APIKey::load_apikey();
         Self {
             api_token,
-            main_channel_id,
-            log_channel_id,
+            main_channel_id: main_channel_id.to_string(),
+            log_channel_id: log_channel_id.to_string(),
             logger,
             num_retry: NUM_RETRY,
             retry_sleep: RETRY_SLEEP,
+            run_id: None,
+            thread_ts_map: Mutex::new(HashMap::new()),
+            batch_window: None,
+            pending_messages: Mutex::new(Vec::new()),
+            rate_limit: (RATE_LIMIT_BURST, RATE_LIMIT_REFILL),
+            token_buckets: Mutex::new(HashMap::new()),
+            alert_cooldown: None,
         }
     }
 
+    pub fn new(
+        main_channel_id: &str,
+        log_channel_id: &str,
+        logger: impl Into<Shared<'a, ProjectLogger>>,
+    ) -> Self {
+        Self::with_api_token(
+            APIKey::load_apikey(),
+            main_channel_id,
+            log_channel_id,
+            logger.into(),
+        )
+    }
+
+    pub fn from_config(
+        slack_config: &SlackConfig,
+        logger: impl Into<Shared<'a, ProjectLogger>>,
+    ) -> Self {
+        Self::with_api_token(
+            slack_config.api_token.clone(),
+            &slack_config.main_channel_id,
+            &slack_config.log_channel_id,
+            logger.into(),
+        )
+    }
+
+    pub fn from_secrets(
+        main_channel_id: &str,
+        log_channel_id: &str,
+        logger: impl Into<Shared<'a, ProjectLogger>>,
+        secrets_provider: &dyn SecretsProvider,
+    ) -> Self {
+        Self::with_api_token(
+            secrets_provider.require_secret(APIKey::API_TOKEN_SECRET),
+            main_channel_id,
+            log_channel_id,
+            logger.into(),
+        )
+    }
+
     pub fn get_channel_id(&self, log_only: bool) -> &str {
         if log_only {
-            self.log_channel_id
+            &self.log_channel_id
         } else {
-            self.main_channel_id
+            &self.main_channel_id
         }
     }
 
@@ -57,14 +238,187 @@ impl<'a> SlackMessenger<'a> {
         self.retry_sleep = retry_sleep;
     }
 
+    // Once a run id is set, the messages of the run go into one thread per channel, so that
+    // alerts from retries append to the thread of the first one.
+    pub fn set_run_id(&mut self, run_id: &str) {
+        self.run_id = Some(run_id.to_string());
+    }
+
+    // Messages within the window are coalesced into one digest per channel. The digest is sent
+    // by the first message arriving after the window, by flush_messages, or on drop.
+    pub fn set_batch_window(&mut self, batch_window: Duration) {
+        self.batch_window = Some(batch_window);
+    }
+
+    pub fn set_rate_limit(&mut self, burst: u32, refill_interval: Duration) {
+        self.rate_limit = (burst.max(1), refill_interval);
+    }
+
+    // Alerts with the same calling function and normalized message within the cooldown are
+    // suppressed, and their count is reported with the next one sent after the cooldown.
+    pub fn set_alert_cooldown(&mut self, cooldown: Duration) {
+        self.alert_cooldown = Some(Mutex::new(AlertCooldown::new(cooldown)));
+    }
+
+    fn apply_alert_cooldown(&self, calling_func: &str, message: &str) -> Option<String> {
+        let Some(alert_cooldown) = &self.alert_cooldown else {
+            return Some(message.to_string());
+        };
+        let fingerprint = format!("{calling_func}|{}", normalize_message(message));
+        let admitted = alert_cooldown
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .admit(fingerprint, Instant::now());
+        match admitted {
+            Some(0) => Some(message.to_string()),
+            Some(num_suppressed) => Some(format!(
+                "{message} ({} occurrences since the last alert)",
+                num_suppressed + 1
+            )),
+            None => {
+                self.logger.log_debug(&format!(
+                    "Alert from {calling_func} suppressed in cooldown: {message}"
+                ));
+                None
+            }
+        }
+    }
+
     pub fn retry_send_message(&self, calling_func: &str, message: &str, log_only: bool) {
+        let Some(message) = self.apply_alert_cooldown(calling_func, message) else {
+            return;
+        };
+        match self.batch_window {
+            Some(batch_window) => {
+                self.queue_message(calling_func, &message, log_only, batch_window)
+            }
+            None => self.send_message_now(calling_func, &message, log_only),
+        }
+    }
+
+    fn queue_message(
+        &self,
+        calling_func: &str,
+        message: &str,
+        log_only: bool,
+        batch_window: Duration,
+    ) {
+        let now = Instant::now();
+        let due_messages = {
+            let mut pending_messages = self
+                .pending_messages
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            pending_messages.push(PendingMessage {
+                calling_func: calling_func.to_string(),
+                message: message.to_string(),
+                log_only,
+                queued_at: now,
+            });
+            let window_start = pending_messages[0].queued_at;
+            if now.saturating_duration_since(window_start) >= batch_window {
+                std::mem::take(&mut *pending_messages)
+            } else {
+                Vec::new()
+            }
+        };
+        self.send_digest(&due_messages);
+    }
+
+    pub fn flush_messages(&self) {
+        let pending_messages = std::mem::take(
+            &mut *self
+                .pending_messages
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        self.send_digest(&pending_messages);
+    }
+
+    fn send_digest(&self, pending_messages: &[PendingMessage]) {
+        for log_only in [false, true] {
+            let channel_messages: Vec<&PendingMessage> = pending_messages
+                .iter()
+                .filter(|pending| pending.log_only == log_only)
+                .collect();
+            match channel_messages.as_slice() {
+                [] => {}
+                [pending] => {
+                    self.send_message_now(&pending.calling_func, &pending.message, log_only)
+                }
+                _ => self.send_message_now(
+                    "message digest",
+                    &format_digest(&channel_messages),
+                    log_only,
+                ),
+            }
+        }
+    }
+
+    // Falls back to the log channel when the channel is throttled, and to the project log only
+    // when both are.
+    fn acquire_channel(&self, calling_func: &str, message: &str, log_only: bool) -> Option<bool> {
+        let now = Instant::now();
+        let mut token_buckets = self
+            .token_buckets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let (burst, refill_interval) = self.rate_limit;
+        let channel_list = if log_only {
+            vec![true]
+        } else {
+            vec![false, true]
+        };
+        for channel_log_only in channel_list {
+            let channel_id = self.get_channel_id(channel_log_only).to_string();
+            if token_buckets
+                .entry(channel_id)
+                .or_insert_with(|| TokenBucket::new(burst, refill_interval, now))
+                .try_acquire(now)
+            {
+                if channel_log_only != log_only {
+                    self.logger
+                        .log_warn("Main slack channel is throttled. Send to the log channel.");
+                }
+                return Some(channel_log_only);
+            }
+        }
+        self.logger.log_warn(&format!(
+            "Slack is throttled. Message from {calling_func} logged only: {message}"
+        ));
+        None
+    }
+
+    fn format_message(calling_func: &str, message: &str) -> String {
+        format!(
+            "{}Message sending from {}: {}",
+            RunContext::current_tag(),
+            calling_func,
+            message
+        )
+    }
+
+    fn send_message_now(&self, calling_func: &str, message: &str, log_only: bool) {
+        if let Some(run_id) = &self.run_id {
+            self.send_to_run_thread(run_id, calling_func, message, log_only);
+            return;
+        }
+        if DryRun::skip(
+            &self.logger,
+            &format!("sending message from {calling_func}: {message}"),
+        ) {
+            return;
+        }
+        let Some(log_only) = self.acquire_channel(calling_func, message, log_only) else {
+            return;
+        };
         let channel_id = self.get_channel_id(log_only);
         // let client = match slack::api::requests::default_client() {
         //     Ok(c) => c,
         //     Err(e) => panic!("Unable to login for slack, {e}"),
         // };
         let client = slack::http_client::default_client();
-        let full_message = format!("Message sending from {}: {}", calling_func, message);
+        let full_message = Self::format_message(calling_func, message);
         // let request = slack::api::chat::PostMessageRequest {
         //     channel: channel_id,
         //     text: &full_message,
@@ -107,6 +461,244 @@ impl<'a> SlackMessenger<'a> {
             }
         }
     }
+
+    // The web api is called from a separate thread with the blocking client, since the
+    // messenger is also used inside tokio runtimes where blocking calls are not allowed.
+    fn call_api(&self, method: &str, params: Vec<(&'static str, String)>) -> Option<Value> {
+        let url = format!("{SLACK_API_URL}{method}");
+        let mut counter: u32 = 1;
+        while counter <= self.num_retry {
+            let url = url.clone();
+            let params = params.clone();
+            let api_token = self.api_token.clone();
+            let response = thread::spawn(move || {
+                reqwest::blocking::Client::new()
+                    .post(url)
+                    .bearer_auth(api_token)
+                    .form(&params)
+                    .send()
+                    .and_then(|response| response.json::<Value>())
+            })
+            .join();
+            match response {
+                Ok(Ok(response)) if response["ok"].as_bool() == Some(true) => {
+                    return Some(response);
+                }
+                Ok(Ok(response)) => {
+                    // Errors reported by slack, e.g. invalid_auth or message_not_found, are not
+                    // transient.
+                    let error_str = format!(
+                        "Slack api {method} returned error {}",
+                        response["error"].as_str().unwrap_or_default()
+                    );
+                    self.logger.log_error(&error_str);
+                    return None;
+                }
+                Ok(Err(e)) => {
+                    self.logger.log_error(&format!(
+                        "Error in calling slack api {method} after trial {counter}, {e}"
+                    ));
+                }
+                Err(_) => {
+                    self.logger.log_error(&format!(
+                        "Slack api {method} thread panicked after trial {counter}"
+                    ));
+                }
+            }
+            counter += 1;
+            time_operation::sleep(self.retry_sleep);
+        }
+        None
+    }
+
+    // Returns whether the message goes to the log channel, or None if it is not sent.
+    fn acquire_post_channel(
+        &self,
+        calling_func: &str,
+        message: &str,
+        log_only: bool,
+    ) -> Option<bool> {
+        if DryRun::skip(
+            &self.logger,
+            &format!("sending message from {calling_func}: {message}"),
+        ) {
+            return None;
+        }
+        self.acquire_channel(calling_func, message, log_only)
+    }
+
+    // Returns the timestamp of the message, which identifies it for threading, updating,
+    // deleting and reaction polling. A thread ts belongs to the channel asked for, so it is
+    // dropped when the message falls back to the log channel.
+    pub fn post_message(
+        &self,
+        calling_func: &str,
+        message: &str,
+        log_only: bool,
+        thread_ts: Option<&str>,
+    ) -> Option<String> {
+        let channel_log_only = self.acquire_post_channel(calling_func, message, log_only)?;
+        let thread_ts = thread_ts.filter(|_| channel_log_only == log_only);
+        self.post_to_channel(calling_func, message, channel_log_only, thread_ts)
+    }
+
+    fn post_to_channel(
+        &self,
+        calling_func: &str,
+        message: &str,
+        log_only: bool,
+        thread_ts: Option<&str>,
+    ) -> Option<String> {
+        let mut params = vec![
+            ("channel", self.get_channel_id(log_only).to_string()),
+            ("text", Self::format_message(calling_func, message)),
+        ];
+        if let Some(thread_ts) = thread_ts {
+            params.push(("thread_ts", thread_ts.to_string()));
+        }
+        self.call_api("chat.postMessage", params)
+            .and_then(|response| response["ts"].as_str().map(|ts| ts.to_string()))
+    }
+
+    // The text is the fallback shown in notifications and by clients that cannot render blocks.
+    pub fn post_blocks(
+        &self,
+        calling_func: &str,
+        text: &str,
+        blocks: &Value,
+        log_only: bool,
+    ) -> Option<String> {
+        if DryRun::skip(
+            &self.logger,
+            &format!("sending blocks from {calling_func}: {text}"),
+        ) {
+            return None;
+        }
+        let log_only = self.acquire_channel(calling_func, text, log_only)?;
+        let params = vec![
+            ("channel", self.get_channel_id(log_only).to_string()),
+            ("text", format!("{}{text}", RunContext::current_tag())),
+            ("blocks", blocks.to_string()),
+        ];
+        self.call_api("chat.postMessage", params)
+            .and_then(|response| response["ts"].as_str().map(|ts| ts.to_string()))
+    }
+
+    pub fn send_to_run_thread(
+        &self,
+        run_id: &str,
+        calling_func: &str,
+        message: &str,
+        log_only: bool,
+    ) -> Option<String> {
+        // The thread is keyed by the channel the message actually goes to, so a message
+        // falling back to the log channel joins the run thread there.
+        let log_only = self.acquire_post_channel(calling_func, message, log_only)?;
+        let thread_key = format!("{}:{run_id}", self.get_channel_id(log_only));
+        // The map is not locked while posting, so a slow or retried post does not block the other
+        // callers of the messenger. The first thread posted for the key is kept.
+        let thread_ts = self
+            .thread_ts_map
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&thread_key)
+            .cloned();
+        match thread_ts {
+            Some(thread_ts) => {
+                self.post_to_channel(calling_func, message, log_only, Some(&thread_ts))
+            }
+            None => {
+                let ts = self.post_to_channel(calling_func, message, log_only, None)?;
+                self.thread_ts_map
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .entry(thread_key)
+                    .or_insert_with(|| ts.clone());
+                Some(ts)
+            }
+        }
+    }
+
+    pub fn update_message(&self, ts: &str, message: &str, log_only: bool) -> bool {
+        if DryRun::skip(&self.logger, &format!("updating message {ts}: {message}")) {
+            return false;
+        }
+        let params = vec![
+            ("channel", self.get_channel_id(log_only).to_string()),
+            ("ts", ts.to_string()),
+            ("text", message.to_string()),
+        ];
+        self.call_api("chat.update", params).is_some()
+    }
+
+    pub fn delete_message(&self, ts: &str, log_only: bool) -> bool {
+        if DryRun::skip(&self.logger, &format!("deleting message {ts}")) {
+            return false;
+        }
+        let params = vec![
+            ("channel", self.get_channel_id(log_only).to_string()),
+            ("ts", ts.to_string()),
+        ];
+        self.call_api("chat.delete", params).is_some()
+    }
+
+    pub fn get_reactions(&self, ts: &str, log_only: bool) -> Vec<String> {
+        let params = vec![
+            ("channel", self.get_channel_id(log_only).to_string()),
+            ("timestamp", ts.to_string()),
+        ];
+        self.call_api("reactions.get", params)
+            .and_then(|response| {
+                response["message"]["reactions"]
+                    .as_array()
+                    .map(|reactions| {
+                        reactions
+                            .iter()
+                            .filter_map(|reaction| reaction["name"].as_str())
+                            .map(|name| name.to_string())
+                            .collect()
+                    })
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn is_acknowledged(&self, ts: &str, emoji: &str, log_only: bool) -> bool {
+        self.get_reactions(ts, log_only)
+            .iter()
+            .any(|name| name == emoji.trim_matches(':'))
+    }
+
+    // Reacting with the emoji is a cheap way for someone on call to acknowledge an alert.
+    pub fn wait_for_acknowledgement(
+        &self,
+        ts: &str,
+        emoji: &str,
+        log_only: bool,
+        timeout: Duration,
+    ) -> bool {
+        let deadline = time_operation::get_deadline(Some(timeout), &SystemClock);
+        loop {
+            if self.is_acknowledged(ts, emoji, log_only) {
+                return true;
+            }
+            if time_operation::is_deadline_reached(deadline, &SystemClock) {
+                return false;
+            }
+            time_operation::sleep(ACK_POLL_INTERVAL.min(timeout));
+        }
+    }
+}
+
+impl<'a> Messenger for SlackMessenger<'a> {
+    fn retry_send_message(&self, calling_func: &str, message: &str, log_only: bool) {
+        SlackMessenger::retry_send_message(self, calling_func, message, log_only)
+    }
+}
+
+impl<'a> Drop for SlackMessenger<'a> {
+    fn drop(&mut self) {
+        self.flush_messages();
+    }
 }
 
 #[derive(Deserialize)]
@@ -118,6 +710,7 @@ impl APIKey {
     const PROJECT_KEY: &str = "SCTYS_PROJECT";
     const API_KEY_PATH: &str = "Secret/secret_sctys_rust_utilities";
     const API_KEY_FILE: &str = "messenger_api.toml";
+    const API_TOKEN_SECRET: &str = "slack_api_token";
 
     fn load_apikey() -> String {
         let full_api_path =
@@ -152,6 +745,62 @@ mod tests {
         channel_id: String,
     }
 
+    #[test]
+    fn test_alert_cooldown() {
+        assert_eq!(
+            normalize_message("The urls  has 3 out of 120 fail urls."),
+            "the urls has # out of # fail urls."
+        );
+        let now = Instant::now();
+        let mut alert_cooldown = AlertCooldown::new(Duration::from_secs(60));
+        let fingerprint = || format!("scrape|{}", normalize_message("3 fail urls"));
+        assert_eq!(alert_cooldown.admit(fingerprint(), now), Some(0));
+        assert_eq!(
+            alert_cooldown.admit(fingerprint(), now + Duration::from_secs(10)),
+            None
+        );
+        assert_eq!(
+            alert_cooldown.admit(fingerprint(), now + Duration::from_secs(20)),
+            None
+        );
+        assert_eq!(
+            alert_cooldown.admit("other".to_string(), now + Duration::from_secs(20)),
+            Some(0)
+        );
+        assert_eq!(
+            alert_cooldown.admit(fingerprint(), now + Duration::from_secs(61)),
+            Some(2)
+        );
+        assert_eq!(
+            alert_cooldown.admit(fingerprint(), now + Duration::from_secs(62)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut token_bucket = TokenBucket::new(2, Duration::from_secs(1), now);
+        assert!(token_bucket.try_acquire(now));
+        assert!(token_bucket.try_acquire(now));
+        assert!(!token_bucket.try_acquire(now));
+        assert!(!token_bucket.try_acquire(now + Duration::from_millis(500)));
+        assert!(token_bucket.try_acquire(now + Duration::from_millis(1000)));
+        assert!(token_bucket.try_acquire(now + Duration::from_secs(10)));
+        assert!(token_bucket.try_acquire(now + Duration::from_secs(10)));
+        assert!(!token_bucket.try_acquire(now + Duration::from_secs(10)));
+        let pending = PendingMessage {
+            calling_func: "scrape".to_string(),
+            message: "3 fail urls".to_string(),
+            log_only: false,
+            queued_at: now,
+        };
+        assert_eq!(
+            format_digest(&[&pending, &pending]),
+            "2 messages\n- scrape: 3 fail urls\n- scrape: 3 fail urls"
+        );
+    }
+
     #[test]
     fn test_send_slack_message() {
         fn load_channel_id(channel_config_path: &Path, channel_config_file: &str) -> String {
@@ -188,5 +837,14 @@ mod tests {
         let slack_messenger = SlackMessenger::new(&channel_id, &log_channel_id, &project_logger);
         let calling_func = utilities_function::function_name!(true);
         slack_messenger.retry_send_message(calling_func, "Test message from rust", false);
+        let mut slack_messenger = slack_messenger;
+        slack_messenger.set_run_id("test_run");
+        let ts = slack_messenger
+            .send_to_run_thread("test_run", calling_func, "First alert of the run", true)
+            .unwrap();
+        slack_messenger.retry_send_message(calling_func, "Retry alert of the run", true);
+        assert!(slack_messenger.update_message(&ts, "First alert updated", true));
+        assert!(!slack_messenger.is_acknowledged(&ts, ":white_check_mark:", true));
+        assert!(slack_messenger.delete_message(&ts, true));
     }
 }
@@ -0,0 +1,194 @@
+use crate::dry_run::DryRun;
+use crate::logger::ProjectLogger;
+use crate::messenger::Messenger;
+use crate::run_context::RunContext;
+use crate::time_operation;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::thread;
+use std::time::Duration;
+
+const NUM_RETRY: u32 = 5;
+const RETRY_SLEEP: Duration = Duration::from_secs(5);
+const TIMEOUT: Duration = Duration::from_secs(30);
+const SIGNATURE_HEADER: &str = "X-Signature-256";
+const TIMESTAMP_HEADER: &str = "X-Signature-Timestamp";
+
+// The hex digest of the hmac sha256 of the timestamp and the body joined by a dot, so that the
+// receiver can verify the sender and reject replayed payloads.
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("Hmac accepts keys of any length");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+// Posts the notifications as json to arbitrary http endpoints, e.g. a PagerDuty integration or a
+// dashboard, instead of slack. The log only messages go to the log url if there is one.
+#[derive(Debug)]
+pub struct WebhookMessenger<'a> {
+    main_url: &'a str,
+    log_url: Option<&'a str>,
+    logger: &'a ProjectLogger,
+    secret: Option<String>,
+    headers: Vec<(String, String)>,
+    num_retry: u32,
+    retry_sleep: Duration,
+    timeout: Duration,
+}
+
+impl<'a> WebhookMessenger<'a> {
+    pub fn new(main_url: &'a str, logger: &'a ProjectLogger) -> Self {
+        Self {
+            main_url,
+            log_url: None,
+            logger,
+            secret: None,
+            headers: Vec::new(),
+            num_retry: NUM_RETRY,
+            retry_sleep: RETRY_SLEEP,
+            timeout: TIMEOUT,
+        }
+    }
+
+    pub fn set_log_url(&mut self, log_url: &'a str) {
+        self.log_url = Some(log_url);
+    }
+
+    pub fn set_secret(&mut self, secret: &str) {
+        self.secret = Some(secret.to_string());
+    }
+
+    pub fn add_header(&mut self, name: &str, value: &str) {
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn set_num_retry(&mut self, num_retry: u32) {
+        self.num_retry = num_retry;
+    }
+
+    pub fn set_retry_sleep(&mut self, retry_sleep: Duration) {
+        self.retry_sleep = retry_sleep;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn get_url(&self, log_only: bool) -> &str {
+        match self.log_url {
+            Some(log_url) if log_only => log_url,
+            _ => self.main_url,
+        }
+    }
+
+    pub fn build_payload(calling_func: &str, message: &str, log_only: bool) -> Value {
+        json!({
+            "calling_func": calling_func,
+            "message": message,
+            "log_only": log_only,
+            "run_context": RunContext::current(),
+            "sent_at": Utc::now().to_rfc3339(),
+        })
+    }
+
+    // For the endpoints expecting their own payload format. Returns whether the endpoint accepted
+    // the payload. The client errors are not retried.
+    pub fn post_payload(&self, payload: &Value, log_only: bool) -> bool {
+        let url = self.get_url(log_only).to_string();
+        if DryRun::skip(self.logger, &format!("posting webhook to {url}: {payload}")) {
+            return false;
+        }
+        let body = payload.to_string();
+        let mut counter: u32 = 1;
+        while counter <= self.num_retry {
+            let mut headers = self.headers.clone();
+            if let Some(secret) = &self.secret {
+                let timestamp = Utc::now().timestamp();
+                headers.push((TIMESTAMP_HEADER.to_string(), timestamp.to_string()));
+                headers.push((
+                    SIGNATURE_HEADER.to_string(),
+                    format!("sha256={}", sign_payload(secret, timestamp, &body)),
+                ));
+            }
+            let (url, body, timeout) = (url.clone(), body.clone(), self.timeout);
+            // Sent from a separate thread with the blocking client as the slack messenger, since
+            // the messenger is also used inside tokio runtimes.
+            let response = thread::spawn(move || {
+                let mut request = reqwest::blocking::Client::new()
+                    .post(url)
+                    .timeout(timeout)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(body);
+                for (name, value) in headers {
+                    request = request.header(name, value);
+                }
+                request.send().map(|response| response.status())
+            })
+            .join();
+            match response {
+                Ok(Ok(status)) if status.is_success() => return true,
+                Ok(Ok(status)) if status.is_client_error() && status.as_u16() != 429 => {
+                    let error_str = format!("Webhook {url} rejected the payload with {status}");
+                    self.logger.log_error(&error_str);
+                    return false;
+                }
+                Ok(Ok(status)) => {
+                    self.logger.log_error(&format!(
+                        "Webhook {url} returned {status} after trial {counter}"
+                    ));
+                }
+                Ok(Err(e)) => {
+                    self.logger.log_error(&format!(
+                        "Error in posting webhook {url} after trial {counter}, {e}"
+                    ));
+                }
+                Err(_) => {
+                    self.logger.log_error(&format!(
+                        "Webhook {url} thread panicked after trial {counter}"
+                    ));
+                }
+            }
+            counter += 1;
+            time_operation::sleep(self.retry_sleep);
+        }
+        false
+    }
+}
+
+impl<'a> Messenger for WebhookMessenger<'a> {
+    fn retry_send_message(&self, calling_func: &str, message: &str, log_only: bool) {
+        let payload = Self::build_payload(calling_func, message, log_only);
+        self.post_payload(&payload, log_only);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_sign_payload() {
+        let signature = sign_payload("secret", 1714557600, r#"{"message":"3 fail urls"}"#);
+        assert_eq!(signature.len(), 64);
+        assert_ne!(
+            signature,
+            sign_payload("secret", 1714557601, r#"{"message":"3 fail urls"}"#)
+        );
+        let logger = ProjectLogger::new_logger(Path::new("."), "test_webhook");
+        let mut webhook_messenger = WebhookMessenger::new("https://example.com/alert", &logger);
+        assert_eq!(webhook_messenger.get_url(true), "https://example.com/alert");
+        webhook_messenger.set_log_url("https://example.com/log");
+        assert_eq!(webhook_messenger.get_url(true), "https://example.com/log");
+        let payload = WebhookMessenger::build_payload("scrape", "3 fail urls", false);
+        assert_eq!(payload["calling_func"], "scrape");
+    }
+}
@@ -1,2 +1,20 @@
+pub mod config;
+pub mod config_value;
+pub mod disk_guard;
+pub mod dry_run;
+#[cfg(all(feature = "s3", feature = "slack"))]
+pub mod heartbeat;
+pub mod lock;
+#[cfg(feature = "slack")]
+pub mod profiling;
+pub mod reporting;
+pub mod run_context;
+#[cfg(feature = "slack")]
+pub mod scheduler;
+pub mod secrets_provider;
+pub mod shared;
+pub mod shutdown;
 pub mod time_operation;
 pub mod utilities_function;
+#[cfg(feature = "slack")]
+pub mod validation;
@@ -0,0 +1,326 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[cfg(feature = "s3")]
+use crate::aws_s3::S3Endpoint;
+use crate::config_value::ConfigDuration;
+use crate::netdata::browser_kind::BrowserKind;
+use crate::netdata::data_struct::{BrowseSetting, ClientOptions, RequestSetting};
+use crate::netdata::proxy_provider::ProxyProviderConfig;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PathConfig {
+    #[serde(default)]
+    pub project_path: PathBuf,
+    #[serde(default)]
+    pub data_path: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AWSConfig {
+    pub aws_api_id: String,
+    pub aws_api_secret: String,
+    pub aws_api_region: String,
+    pub default_bucket: Option<String>,
+    pub max_attempts: Option<u32>,
+    pub initial_backoff: Option<ConfigDuration>,
+    pub max_backoff: Option<ConfigDuration>,
+    #[cfg(feature = "s3")]
+    #[serde(default)]
+    pub endpoint: S3Endpoint,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SlackConfig {
+    pub api_token: String,
+    #[serde(default)]
+    pub main_channel_id: String,
+    #[serde(default)]
+    pub log_channel_id: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ScraperConfig {
+    pub num_retry: Option<u32>,
+    pub retry_sleep: Option<ConfigDuration>,
+    pub web_driver_port: Option<u32>,
+    pub browser_kind: Option<BrowserKind>,
+    pub chunk_size_request: Option<usize>,
+    pub chunk_size_browse: Option<usize>,
+    pub client: Option<ClientOptions>,
+    pub request_setting: Option<RequestSetting<'static>>,
+    pub browse_setting: Option<BrowseSetting<'static>>,
+    pub proxy_provider: Option<ProxyProviderConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TelemetryConfig {
+    pub service_name: Option<String>,
+    pub otlp_endpoint: Option<String>,
+}
+
+// The levels are the names accepted by LevelFilter, e.g. "debug" or "off", keyed by log target.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LoggingConfig {
+    pub level: Option<String>,
+    #[serde(default)]
+    pub targets: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UtilitiesConfig {
+    #[serde(default)]
+    pub paths: PathConfig,
+    pub aws: Option<AWSConfig>,
+    pub slack: Option<SlackConfig>,
+    #[serde(default)]
+    pub scraper: ScraperConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+}
+
+static UTILITIES_CONFIG: OnceLock<UtilitiesConfig> = OnceLock::new();
+
+impl UtilitiesConfig {
+    const CONFIG_KEY: &'static str = "SCTYS_CONFIG";
+    const PROJECT_KEY: &'static str = "SCTYS_PROJECT";
+    const DATA_KEY: &'static str = "SCTYS_DATA";
+    const AWS_API_ID_KEY: &'static str = "SCTYS_AWS_API_ID";
+    const AWS_API_SECRET_KEY: &'static str = "SCTYS_AWS_API_SECRET";
+    const AWS_API_REGION_KEY: &'static str = "SCTYS_AWS_API_REGION";
+    const SLACK_API_TOKEN_KEY: &'static str = "SCTYS_SLACK_API_TOKEN";
+    const OTLP_ENDPOINT_KEY: &'static str = "SCTYS_OTLP_ENDPOINT";
+    const CONFIG_PATH: &'static str = "Secret/secret_sctys_rust_utilities";
+    const CONFIG_FILE: &'static str = "utilities_config.toml";
+
+    pub fn from_toml_str(config_str: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(config_str)
+    }
+
+    fn default_config_file() -> Option<PathBuf> {
+        env::var(Self::CONFIG_KEY)
+            .map(PathBuf::from)
+            .or_else(|_| {
+                env::var(Self::PROJECT_KEY).map(|project_path| {
+                    Path::new(&project_path)
+                        .join(Self::CONFIG_PATH)
+                        .join(Self::CONFIG_FILE)
+                })
+            })
+            .ok()
+    }
+
+    // Env vars take precedence over the file, so a deployment can override single values
+    // without shipping another config file.
+    pub fn apply_env_overrides(&mut self, get_env: impl Fn(&str) -> Option<String>) {
+        if let Some(project_path) = get_env(Self::PROJECT_KEY) {
+            self.paths.project_path = PathBuf::from(project_path);
+        }
+        if let Some(data_path) = get_env(Self::DATA_KEY) {
+            self.paths.data_path = PathBuf::from(data_path);
+        }
+        let aws_overrides = (
+            get_env(Self::AWS_API_ID_KEY),
+            get_env(Self::AWS_API_SECRET_KEY),
+            get_env(Self::AWS_API_REGION_KEY),
+        );
+        if aws_overrides != (None, None, None) {
+            let aws_config = self.aws.get_or_insert_with(AWSConfig::default);
+            if let Some(aws_api_id) = aws_overrides.0 {
+                aws_config.aws_api_id = aws_api_id;
+            }
+            if let Some(aws_api_secret) = aws_overrides.1 {
+                aws_config.aws_api_secret = aws_api_secret;
+            }
+            if let Some(aws_api_region) = aws_overrides.2 {
+                aws_config.aws_api_region = aws_api_region;
+            }
+        }
+        if let Some(api_token) = get_env(Self::SLACK_API_TOKEN_KEY) {
+            self.slack
+                .get_or_insert_with(SlackConfig::default)
+                .api_token = api_token;
+        }
+        if let Some(otlp_endpoint) = get_env(Self::OTLP_ENDPOINT_KEY) {
+            self.telemetry.otlp_endpoint = Some(otlp_endpoint);
+        }
+    }
+
+    pub fn load_from_file(config_file: &Path) -> Self {
+        let config_str = match fs::read_to_string(config_file) {
+            Ok(config_str) => config_str,
+            Err(e) => panic!(
+                "Unable to load the config file {}. {e}",
+                config_file.display()
+            ),
+        };
+        let mut utilities_config = match Self::from_toml_str(&config_str) {
+            Ok(utilities_config) => utilities_config,
+            Err(e) => panic!(
+                "Unable to parse the config file {}. {e}",
+                config_file.display()
+            ),
+        };
+        utilities_config.apply_env_overrides(|key| env::var(key).ok());
+        utilities_config
+    }
+
+    pub fn load() -> Self {
+        match Self::default_config_file() {
+            Some(config_file) if config_file.is_file() => Self::load_from_file(&config_file),
+            _ => {
+                let mut utilities_config = Self::default();
+                utilities_config.apply_env_overrides(|key| env::var(key).ok());
+                utilities_config
+            }
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        UTILITIES_CONFIG.get_or_init(Self::load)
+    }
+
+    pub fn get_aws_config(&self) -> &AWSConfig {
+        self.aws
+            .as_ref()
+            .unwrap_or_else(|| panic!("AWS config is missing in the utilities config."))
+    }
+
+    pub fn get_slack_config(&self) -> &SlackConfig {
+        self.slack
+            .as_ref()
+            .unwrap_or_else(|| panic!("Slack config is missing in the utilities config."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::netdata::data_struct::{RequestSettingBuilder, StorageBackend, UrlFile};
+    use reqwest::Url;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[test]
+    fn test_utilities_config() {
+        let config_str = r#"
+            [paths]
+            project_path = "/home/sctys/project"
+
+            [aws]
+            aws_api_id = "file_id"
+            aws_api_secret = "file_secret"
+            aws_api_region = "eu-west-2"
+
+            [aws.endpoint]
+            endpoint_url = "http://127.0.0.1:9000"
+            force_path_style = true
+
+            [scraper]
+            num_retry = 5
+            retry_sleep = "30s"
+            browser_kind = "firefox"
+            chunk_size_request = 20
+
+            [scraper.client]
+            http2_prior_knowledge = true
+            tcp_keepalive = "60s"
+            dns_overrides = { "www.nowgoal.com" = "1.2.3.4:443" }
+
+            [scraper.request_setting]
+            storage_backend = "s3"
+            max_total_duration = "2h"
+            timeout = "30s"
+            connect_timeout = "5s"
+            min_interval = "500ms"
+            max_response_bytes = 52428800
+
+            [scraper.proxy_provider]
+            kind = "private"
+            api_url = "https://proxy.provider.com/api/list?key=abc"
+            default_scheme = "socks5"
+        "#;
+        let mut utilities_config = UtilitiesConfig::from_toml_str(config_str).unwrap();
+        let env_vars = HashMap::from([
+            ("SCTYS_DATA", "/data/sctys"),
+            ("SCTYS_AWS_API_SECRET", "env_secret"),
+            ("SCTYS_SLACK_API_TOKEN", "env_token"),
+        ]);
+        utilities_config.apply_env_overrides(|key| env_vars.get(key).map(|x| x.to_string()));
+        assert_eq!(
+            utilities_config.paths.project_path,
+            PathBuf::from("/home/sctys/project")
+        );
+        assert_eq!(
+            utilities_config.paths.data_path,
+            PathBuf::from("/data/sctys")
+        );
+        let aws_config = utilities_config.get_aws_config();
+        assert_eq!(aws_config.aws_api_id, "file_id");
+        assert_eq!(aws_config.aws_api_secret, "env_secret");
+        #[cfg(feature = "s3")]
+        assert_eq!(
+            aws_config.endpoint,
+            S3Endpoint::new("http://127.0.0.1:9000")
+        );
+        assert_eq!(utilities_config.get_slack_config().api_token, "env_token");
+        assert_eq!(utilities_config.scraper.num_retry, Some(5));
+        assert_eq!(utilities_config.scraper.chunk_size_request, Some(20));
+        assert_eq!(utilities_config.scraper.chunk_size_browse, None);
+        assert_eq!(
+            utilities_config
+                .scraper
+                .retry_sleep
+                .map(|x| x.get_duration()),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            utilities_config.scraper.browser_kind,
+            Some(BrowserKind::Firefox)
+        );
+        let client_options = utilities_config.scraper.client.unwrap();
+        assert!(client_options.http2_prior_knowledge);
+        assert!(client_options.gzip);
+        assert_eq!(
+            client_options.dns_overrides["www.nowgoal.com"].to_string(),
+            "1.2.3.4:443"
+        );
+        let request_setting = utilities_config.scraper.request_setting.unwrap();
+        assert!(request_setting.in_s3);
+        assert!(!request_setting.log_only);
+        assert_eq!(
+            request_setting.max_total_duration,
+            Some(Duration::from_secs(7200))
+        );
+        assert_eq!(
+            request_setting.get_sleep_range((Duration::ZERO, Duration::from_secs(1))),
+            (Duration::from_millis(500), Duration::from_secs(1))
+        );
+        let request_setting = RequestSettingBuilder::from(request_setting)
+            .set_calling_func("test_utilities_config")
+            .set_storage_backend(StorageBackend::Local)
+            .build();
+        assert_eq!(request_setting.calling_func, "test_utilities_config");
+        assert!(!request_setting.in_s3);
+        assert_eq!(request_setting.timeout, Some(Duration::from_secs(30)));
+        let url_file = UrlFile::new(
+            Url::parse("https://www.nowgoal.com/football/live").unwrap(),
+            "live.html".to_string(),
+        )
+        .with_timeout(Duration::from_secs(120));
+        let request_limit = request_setting.get_request_limit().for_url_file(&url_file);
+        assert_eq!(request_limit.timeout, Some(Duration::from_secs(120)));
+        assert_eq!(request_limit.connect_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(request_limit.max_response_bytes, Some(52_428_800));
+        assert!(!request_limit.truncate_response);
+        let proxy_provider = utilities_config.scraper.proxy_provider.unwrap().build();
+        assert_eq!(proxy_provider.get_name(), "private");
+    }
+}
@@ -0,0 +1,358 @@
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValueError {
+    InvalidFormat {
+        value: String,
+        expected: String,
+    },
+    OutOfRange {
+        value: String,
+        min: String,
+        max: String,
+    },
+}
+
+impl fmt::Display for ConfigValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat { value, expected } => {
+                write!(f, "Invalid config value {value:?}. Expected {expected}.")
+            }
+            Self::OutOfRange { value, min, max } => {
+                write!(
+                    f,
+                    "Config value {value:?} is out of range. It should be between {min} and {max}."
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigValueError {}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawConfigValue {
+    Int(u64),
+    Float(f64),
+    Str(String),
+}
+
+fn split_number_unit(value: &str) -> Option<(f64, String)> {
+    let value = value.trim();
+    let unit_start = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(unit_start);
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite() && *number >= 0.0)
+        .map(|number| (number, unit.trim().to_lowercase()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConfigDuration(Duration);
+
+impl ConfigDuration {
+    const EXPECTED: &'static str =
+        "a number of seconds or a number with unit ms, s, m, h or d, e.g. \"30s\"";
+
+    pub fn new(duration: Duration) -> Self {
+        Self(duration)
+    }
+
+    pub fn get_duration(&self) -> Duration {
+        self.0
+    }
+
+    pub fn validate_range(self, min: Duration, max: Duration) -> Result<Self, ConfigValueError> {
+        if self.0 < min || self.0 > max {
+            Err(ConfigValueError::OutOfRange {
+                value: format!("{:?}", self.0),
+                min: format!("{min:?}"),
+                max: format!("{max:?}"),
+            })
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl FromStr for ConfigDuration {
+    type Err = ConfigValueError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid_format = || ConfigValueError::InvalidFormat {
+            value: value.to_string(),
+            expected: Self::EXPECTED.to_string(),
+        };
+        let (number, unit) = split_number_unit(value).ok_or_else(invalid_format)?;
+        let sec_multiplier = match unit.as_str() {
+            "ms" => 0.001,
+            "" | "s" | "sec" => 1.0,
+            "m" | "min" => 60.0,
+            "h" | "hr" => 3600.0,
+            "d" | "day" => 86400.0,
+            _ => return Err(invalid_format()),
+        };
+        Duration::try_from_secs_f64(number * sec_multiplier)
+            .map(Self)
+            .map_err(|_| invalid_format())
+    }
+}
+
+impl<'de> Deserialize<'de> for ConfigDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match RawConfigValue::deserialize(deserializer)? {
+            RawConfigValue::Int(sec) => Ok(Self(Duration::from_secs(sec))),
+            // Negative, non-finite and overflowing seconds are rejected.
+            RawConfigValue::Float(sec) => {
+                Duration::try_from_secs_f64(sec).map(Self).map_err(|_| {
+                    serde::de::Error::custom(ConfigValueError::InvalidFormat {
+                        value: sec.to_string(),
+                        expected: Self::EXPECTED.to_string(),
+                    })
+                })
+            }
+            RawConfigValue::Str(value) => value.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+// For the structs that are built in code as well as read from the config, so that they keep a
+// plain Duration field, e.g. #[serde(default, deserialize_with = "deserialize_optional_duration")].
+pub fn deserialize_optional_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    Ok(Option::<ConfigDuration>::deserialize(deserializer)?.map(|x| x.get_duration()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConfigSize(u64);
+
+impl ConfigSize {
+    const EXPECTED: &'static str =
+        "a number of bytes or a number with unit B, KB, MB, GB, TB, KiB, MiB, GiB or TiB, e.g. \"500MB\"";
+
+    pub fn new(bytes: u64) -> Self {
+        Self(bytes)
+    }
+
+    pub fn get_bytes(&self) -> u64 {
+        self.0
+    }
+
+    pub fn validate_range(self, min: u64, max: u64) -> Result<Self, ConfigValueError> {
+        if self.0 < min || self.0 > max {
+            Err(ConfigValueError::OutOfRange {
+                value: format!("{} bytes", self.0),
+                min: format!("{min} bytes"),
+                max: format!("{max} bytes"),
+            })
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl FromStr for ConfigSize {
+    type Err = ConfigValueError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid_format = || ConfigValueError::InvalidFormat {
+            value: value.to_string(),
+            expected: Self::EXPECTED.to_string(),
+        };
+        let (number, unit) = split_number_unit(value).ok_or_else(invalid_format)?;
+        let multiplier: u64 = match unit.as_str() {
+            "" | "b" => 1,
+            "kb" => 1_000,
+            "mb" => 1_000_000,
+            "gb" => 1_000_000_000,
+            "tb" => 1_000_000_000_000,
+            "kib" => 1 << 10,
+            "mib" => 1 << 20,
+            "gib" => 1 << 30,
+            "tib" => 1 << 40,
+            _ => return Err(invalid_format()),
+        };
+        Ok(Self((number * multiplier as f64).round() as u64))
+    }
+}
+
+impl<'de> Deserialize<'de> for ConfigSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match RawConfigValue::deserialize(deserializer)? {
+            RawConfigValue::Int(bytes) => Ok(Self(bytes)),
+            RawConfigValue::Float(bytes) => {
+                Err(serde::de::Error::custom(ConfigValueError::InvalidFormat {
+                    value: bytes.to_string(),
+                    expected: Self::EXPECTED.to_string(),
+                }))
+            }
+            RawConfigValue::Str(value) => value.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct ConfigPercentage(f64);
+
+impl ConfigPercentage {
+    const EXPECTED: &'static str =
+        "a percentage such as \"25%\" or a fraction between 0 and 1 such as 0.25";
+
+    pub fn new(fraction: f64) -> Result<Self, ConfigValueError> {
+        Self(fraction).validate_range(0.0, 1.0)
+    }
+
+    pub fn get_fraction(&self) -> f64 {
+        self.0
+    }
+
+    pub fn get_percent(&self) -> f64 {
+        self.0 * 100.0
+    }
+
+    pub fn validate_range(self, min: f64, max: f64) -> Result<Self, ConfigValueError> {
+        if !(min..=max).contains(&self.0) {
+            Err(ConfigValueError::OutOfRange {
+                value: format!("{}%", self.get_percent()),
+                min: format!("{}%", min * 100.0),
+                max: format!("{}%", max * 100.0),
+            })
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl FromStr for ConfigPercentage {
+    type Err = ConfigValueError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid_format = || ConfigValueError::InvalidFormat {
+            value: value.to_string(),
+            expected: Self::EXPECTED.to_string(),
+        };
+        let (number, unit) = split_number_unit(value).ok_or_else(invalid_format)?;
+        match unit.as_str() {
+            "%" => Self::new(number / 100.0),
+            "" => Self::new(number),
+            _ => Err(invalid_format()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ConfigPercentage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match RawConfigValue::deserialize(deserializer)? {
+            RawConfigValue::Int(fraction) => Self::new(fraction as f64),
+            RawConfigValue::Float(fraction) => Self::new(fraction),
+            RawConfigValue::Str(value) => value.parse(),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct TestConfig {
+        retry_sleep: ConfigDuration,
+        max_response_size: ConfigSize,
+        max_failure_rate: ConfigPercentage,
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(
+            "500ms".parse::<ConfigDuration>().unwrap().get_duration(),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            "1.5m".parse::<ConfigDuration>().unwrap().get_duration(),
+            Duration::from_secs(90)
+        );
+        assert_eq!(
+            "2h".parse::<ConfigDuration>().unwrap().get_duration(),
+            Duration::from_secs(7200)
+        );
+        assert!("10 weeks".parse::<ConfigDuration>().is_err());
+        assert!("-1s".parse::<ConfigDuration>().is_err());
+        assert!("300000000000000000000d".parse::<ConfigDuration>().is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(
+            "500MB".parse::<ConfigSize>().unwrap().get_bytes(),
+            500_000_000
+        );
+        assert_eq!("1KiB".parse::<ConfigSize>().unwrap().get_bytes(), 1024);
+        assert_eq!("2048".parse::<ConfigSize>().unwrap().get_bytes(), 2048);
+        assert!("5 parsecs".parse::<ConfigSize>().is_err());
+    }
+
+    #[test]
+    fn test_parse_percentage() {
+        assert_eq!(
+            "25%".parse::<ConfigPercentage>().unwrap().get_fraction(),
+            0.25
+        );
+        assert_eq!(
+            "0.5".parse::<ConfigPercentage>().unwrap().get_fraction(),
+            0.5
+        );
+        assert!("150%".parse::<ConfigPercentage>().is_err());
+    }
+
+    #[test]
+    fn test_validate_range() {
+        let retry_sleep = ConfigDuration::new(Duration::from_secs(600));
+        assert!(retry_sleep
+            .validate_range(Duration::from_secs(1), Duration::from_secs(60))
+            .is_err());
+        let chunk_size = ConfigSize::new(100);
+        assert!(chunk_size.validate_range(1, 1000).is_ok());
+    }
+
+    #[test]
+    fn test_deserialize_from_toml() {
+        let config_str = r#"
+            retry_sleep = "30s"
+            max_response_size = "50MB"
+            max_failure_rate = "20%"
+        "#;
+        let config: TestConfig = toml::from_str(config_str).unwrap();
+        assert_eq!(config.retry_sleep.get_duration(), Duration::from_secs(30));
+        assert_eq!(config.max_response_size.get_bytes(), 50_000_000);
+        assert_eq!(config.max_failure_rate.get_fraction(), 0.2);
+        let invalid_config_str = r#"
+            retry_sleep = 10
+            max_response_size = "50MB"
+            max_failure_rate = "120%"
+        "#;
+        let error = toml::from_str::<TestConfig>(invalid_config_str)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("out of range"));
+        let overflow_config_str = r#"
+            retry_sleep = 1e20
+            max_response_size = "50MB"
+            max_failure_rate = "20%"
+        "#;
+        let error = toml::from_str::<TestConfig>(overflow_config_str)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("Invalid config value"));
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+use std::io::{Error, Result};
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::function_name;
+use crate::logger::ProjectLogger;
+use crate::messenger::Messenger;
+
+pub type CleanupHook<'a> = Box<dyn Fn(&Path) + Send + Sync + 'a>;
+
+// Checks the free space of the filesystem before a large write, so that a full disk fails the
+// write early with a clear error instead of leaving a truncated parquet file. When the space is
+// short the cleanup hook, e.g. removing the old files, is run once before giving up, and the
+// warnings to the messenger are sent at most once every warning interval.
+pub struct DiskGuard<'a> {
+    project_logger: &'a ProjectLogger,
+    min_free_bytes: u64,
+    messenger: Option<&'a dyn Messenger>,
+    log_only: bool,
+    cleanup_hook: Option<CleanupHook<'a>>,
+    warning_interval: Duration,
+    last_warning_time: Mutex<Option<Instant>>,
+}
+
+impl<'a> fmt::Debug for DiskGuard<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DiskGuard")
+            .field("min_free_bytes", &self.min_free_bytes)
+            .field("has_messenger", &self.messenger.is_some())
+            .field("has_cleanup_hook", &self.cleanup_hook.is_some())
+            .field("warning_interval", &self.warning_interval)
+            .finish()
+    }
+}
+
+impl<'a> DiskGuard<'a> {
+    const WARNING_INTERVAL: Duration = Duration::from_secs(3600);
+
+    pub fn new(project_logger: &'a ProjectLogger, min_free_bytes: u64) -> Self {
+        Self {
+            project_logger,
+            min_free_bytes,
+            messenger: None,
+            log_only: false,
+            cleanup_hook: None,
+            warning_interval: Self::WARNING_INTERVAL,
+            last_warning_time: Mutex::new(None),
+        }
+    }
+
+    pub fn set_messenger(&mut self, messenger: &'a dyn Messenger, log_only: bool) {
+        self.messenger = Some(messenger);
+        self.log_only = log_only;
+    }
+
+    pub fn set_cleanup_hook<F>(&mut self, cleanup_hook: F)
+    where
+        F: Fn(&Path) + Send + Sync + 'a,
+    {
+        self.cleanup_hook = Some(Box::new(cleanup_hook));
+    }
+
+    pub fn set_warning_interval(&mut self, warning_interval: Duration) {
+        self.warning_interval = warning_interval;
+    }
+
+    // The target may not exist yet, e.g. a file about to be written, so the space is read from
+    // its closest existing parent.
+    pub fn get_available_space(target_path: &Path) -> Result<u64> {
+        let existing_path = target_path
+            .ancestors()
+            .find(|path| path.exists())
+            .unwrap_or_else(|| Path::new("."));
+        fs2::available_space(existing_path)
+    }
+
+    // Returns the bytes available if there is room for the write on top of the minimum free
+    // space.
+    pub fn check_space(&self, target_path: &Path, write_size: u64) -> Result<u64> {
+        let required_bytes = write_size.saturating_add(self.min_free_bytes);
+        let mut available_bytes = self.read_available_space(target_path)?;
+        if available_bytes >= required_bytes {
+            return Ok(available_bytes);
+        }
+        if let Some(cleanup_hook) = &self.cleanup_hook {
+            let warn_str = format!(
+                "Low disk space for {}. {available_bytes} bytes free, {required_bytes} bytes required. Running the cleanup.",
+                target_path.display()
+            );
+            self.project_logger.log_warn(&warn_str);
+            cleanup_hook(target_path);
+            available_bytes = self.read_available_space(target_path)?;
+            if available_bytes >= required_bytes {
+                let info_str = format!(
+                    "Disk space for {} recovered by the cleanup. {available_bytes} bytes free.",
+                    target_path.display()
+                );
+                self.project_logger.log_info(&info_str);
+                return Ok(available_bytes);
+            }
+        }
+        let error_str = format!(
+            "Not enough disk space to write {}. {available_bytes} bytes free, {required_bytes} bytes required.",
+            target_path.display()
+        );
+        self.send_warning(&error_str);
+        Err(Error::other(error_str))
+    }
+
+    fn read_available_space(&self, target_path: &Path) -> Result<u64> {
+        Self::get_available_space(target_path).map_err(|e| {
+            let error_str = format!(
+                "Unable to get the available disk space for {}. {e}",
+                target_path.display()
+            );
+            self.project_logger.log_error(&error_str);
+            e
+        })
+    }
+
+    fn send_warning(&self, message: &str) {
+        let function_name = function_name!(true);
+        self.project_logger.log_error(message);
+        let Some(messenger) = self.messenger else {
+            return;
+        };
+        let mut last_warning_time = self
+            .last_warning_time
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if last_warning_time.map_or(true, |warning_time| {
+            warning_time.elapsed() >= self.warning_interval
+        }) {
+            *last_warning_time = Some(Instant::now());
+            messenger.retry_send_message(function_name, message, self.log_only);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::env;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_check_space() {
+        let logger_name = "test_disk_guard";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_misc");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let target_path = env::temp_dir().join("test_disk_guard").join("odds.parquet");
+        let disk_guard = DiskGuard::new(&project_logger, 0);
+        let available_bytes = disk_guard.check_space(&target_path, 1024).unwrap();
+        assert!(available_bytes >= 1024);
+        let num_cleanups = AtomicUsize::new(0);
+        let mut disk_guard = DiskGuard::new(&project_logger, u64::MAX / 2);
+        disk_guard.set_cleanup_hook(|_| {
+            num_cleanups.fetch_add(1, Ordering::SeqCst);
+        });
+        assert!(disk_guard.check_space(&target_path, 1024).is_err());
+        assert_eq!(num_cleanups.load(Ordering::SeqCst), 1);
+    }
+}
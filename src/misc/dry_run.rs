@@ -0,0 +1,119 @@
+use std::cell::Cell;
+use std::future::Future;
+use std::marker::PhantomData;
+
+use crate::logger::ProjectLogger;
+
+tokio::task_local! {
+    static TASK_DRY_RUN: bool;
+}
+
+thread_local! {
+    static THREAD_DRY_RUN: Cell<usize> = const { Cell::new(0) };
+}
+
+// Dry run belongs to one run, never to the process. An async run is dry within its
+// DryRun::scope, so the other runs polled in the same process, e.g. the jobs of a scheduler,
+// keep writing. A blocking run is dry on its own thread while the guard of DryRun::enable_if
+// is held.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DryRun;
+
+// Keeps dry run on for the blocking run of the current thread until dropped. It is not Send, so
+// it cannot leave the thread it counts on.
+#[derive(Debug)]
+pub struct DryRunGuard {
+    enabled: bool,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for DryRunGuard {
+    fn drop(&mut self) {
+        if self.enabled {
+            THREAD_DRY_RUN.with(|num_scoped| num_scoped.set(num_scoped.get() - 1));
+        }
+    }
+}
+
+impl DryRun {
+    // The future runs dry if the setting asks for it or the run it is part of is already dry.
+    // Spawned tasks do not inherit the scope, so wrap them in their own.
+    pub async fn scope<F: Future>(dry_run: bool, future: F) -> F::Output {
+        TASK_DRY_RUN
+            .scope(dry_run || Self::is_enabled(), future)
+            .await
+    }
+
+    // For the blocking runs only, as the tasks of an async run may share the thread with others.
+    #[must_use]
+    pub fn enable_if(dry_run: bool) -> DryRunGuard {
+        if dry_run {
+            THREAD_DRY_RUN.with(|num_scoped| num_scoped.set(num_scoped.get() + 1));
+        }
+        DryRunGuard {
+            enabled: dry_run,
+            _not_send: PhantomData,
+        }
+    }
+
+    pub fn is_enabled() -> bool {
+        TASK_DRY_RUN.try_with(|dry_run| *dry_run).unwrap_or(false)
+            || THREAD_DRY_RUN.with(|num_scoped| num_scoped.get() > 0)
+    }
+
+    pub fn skip(project_logger: &ProjectLogger, action: &str) -> bool {
+        let enabled = Self::is_enabled();
+        if enabled {
+            let info_str = format!("Dry run. Skip {action}.");
+            project_logger.log_info(&info_str);
+        }
+        enabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_dry_run_guard() {
+        assert!(!DryRun::is_enabled());
+        {
+            let _dry_run_guard = DryRun::enable_if(false);
+            assert!(!DryRun::is_enabled());
+        }
+        {
+            let _dry_run_guard = DryRun::enable_if(true);
+            assert!(DryRun::is_enabled());
+            {
+                let _dry_run_guard = DryRun::enable_if(true);
+                assert!(DryRun::is_enabled());
+            }
+            let _dry_run_guard = DryRun::enable_if(false);
+            assert!(DryRun::is_enabled());
+            let other_thread_enabled = std::thread::spawn(DryRun::is_enabled).join().unwrap();
+            assert!(!other_thread_enabled);
+        }
+        assert!(!DryRun::is_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_scope() {
+        let dry_run = DryRun::scope(true, async {
+            tokio::task::yield_now().await;
+            let nested = DryRun::scope(false, async { DryRun::is_enabled() }).await;
+            (DryRun::is_enabled(), nested)
+        });
+        let real_run = DryRun::scope(false, async {
+            tokio::task::yield_now().await;
+            DryRun::is_enabled()
+        });
+        let ((dry_run_enabled, nested_enabled), real_run_enabled) =
+            futures::join!(dry_run, real_run);
+        assert!(dry_run_enabled);
+        assert!(nested_enabled);
+        assert!(!real_run_enabled);
+        assert!(!DryRun::is_enabled());
+    }
+}
@@ -0,0 +1,356 @@
+use chrono::{DateTime, Duration as LongDuration, Utc};
+use reqwest::{Client, Url};
+use serde_json::json;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::aws_s3::AWSFileIO;
+use crate::function_name;
+use crate::logger::ProjectLogger;
+use crate::slack_messenger::SlackMessenger;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatStatus {
+    Start,
+    Alive,
+    Success,
+    Fail,
+}
+
+impl HeartbeatStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Alive => "alive",
+            Self::Success => "success",
+            Self::Fail => "fail",
+        }
+    }
+}
+
+// A ping url follows the healthchecks.io convention, where "/start" and "/fail" are appended to
+// the check url. An S3 marker is an object overwritten on every ping, whose last modified time
+// is read back by the HeartbeatMonitor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeartbeatTarget {
+    PingUrl(Url),
+    S3Marker {
+        bucket_name: String,
+        folder_path: PathBuf,
+    },
+}
+
+impl HeartbeatTarget {
+    pub fn get_ping_url(ping_url: &Url, status: HeartbeatStatus) -> Url {
+        let mut ping_url = ping_url.clone();
+        let suffix = match status {
+            HeartbeatStatus::Start => "start",
+            HeartbeatStatus::Fail => "fail",
+            HeartbeatStatus::Alive | HeartbeatStatus::Success => return ping_url,
+        };
+        if let Ok(mut path_segments) = ping_url.path_segments_mut() {
+            path_segments.pop_if_empty().push(suffix);
+        }
+        ping_url
+    }
+
+    pub fn get_marker_file(job_name: &str) -> String {
+        format!("{job_name}.heartbeat.json")
+    }
+}
+
+pub struct Heartbeat<'a> {
+    project_logger: &'a ProjectLogger,
+    job_name: String,
+    target: HeartbeatTarget,
+    interval: Duration,
+    client: Client,
+    aws_file_io: Option<&'a AWSFileIO<'a>>,
+    last_ping: Mutex<Option<Instant>>,
+}
+
+impl<'a> Heartbeat<'a> {
+    const INTERVAL: Duration = Duration::from_secs(60);
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    pub fn new(project_logger: &'a ProjectLogger, job_name: &str, target: HeartbeatTarget) -> Self {
+        Self {
+            project_logger,
+            job_name: job_name.to_string(),
+            target,
+            interval: Self::INTERVAL,
+            client: Client::new(),
+            aws_file_io: None,
+            last_ping: Mutex::new(None),
+        }
+    }
+
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    pub fn set_aws_file_io(&mut self, aws_file_io: &'a AWSFileIO<'a>) {
+        self.aws_file_io = Some(aws_file_io);
+    }
+
+    pub fn get_job_name(&self) -> &str {
+        &self.job_name
+    }
+
+    // A failed ping is only logged, the heartbeat must never stop the job it is watching.
+    pub async fn ping(&self, status: HeartbeatStatus) -> bool {
+        *self
+            .last_ping
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
+        let ping_result = match &self.target {
+            HeartbeatTarget::PingUrl(ping_url) => {
+                let ping_url = HeartbeatTarget::get_ping_url(ping_url, status);
+                self.client
+                    .get(ping_url)
+                    .timeout(Self::TIMEOUT)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+            HeartbeatTarget::S3Marker {
+                bucket_name,
+                folder_path,
+            } => match self.aws_file_io {
+                Some(aws_file_io) => {
+                    let content = json!({
+                        "job_name": self.job_name,
+                        "status": status.as_str(),
+                        "time": Utc::now().to_rfc3339(),
+                    })
+                    .to_string();
+                    aws_file_io
+                        .write_string_to_file(
+                            bucket_name,
+                            folder_path,
+                            &HeartbeatTarget::get_marker_file(&self.job_name),
+                            &content,
+                        )
+                        .await
+                        .map_err(|e| e.to_string())
+                }
+                None => Err("No AWSFileIO is set for the S3 marker".to_string()),
+            },
+        };
+        match ping_result {
+            Ok(()) => {
+                let debug_str = format!(
+                    "Heartbeat {} of job {} sent.",
+                    status.as_str(),
+                    self.job_name
+                );
+                self.project_logger.log_debug(&debug_str);
+                true
+            }
+            Err(e) => {
+                let warn_str = format!(
+                    "Unable to send heartbeat {} of job {}. {e}",
+                    status.as_str(),
+                    self.job_name
+                );
+                self.project_logger.log_warn(&warn_str);
+                false
+            }
+        }
+    }
+
+    // Cheap to call after every request of a long loop, it only pings once per interval.
+    pub async fn ping_if_due(&self) {
+        let is_due = self
+            .last_ping
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .map_or(true, |last_ping| last_ping.elapsed() >= self.interval);
+        if is_due {
+            self.ping(HeartbeatStatus::Alive).await;
+        }
+    }
+
+    // Pings at the start, every interval while the future runs, and with the outcome at the end.
+    pub async fn run_with_heartbeat<F, T, E>(&self, fut: F) -> Result<T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        self.ping(HeartbeatStatus::Start).await;
+        let mut heartbeat = tokio::time::interval(self.interval);
+        heartbeat.tick().await;
+        tokio::pin!(fut);
+        let output = loop {
+            tokio::select! {
+                output = &mut fut => break output,
+                _ = heartbeat.tick() => {
+                    self.ping(HeartbeatStatus::Alive).await;
+                }
+            }
+        };
+        let status = if output.is_ok() {
+            HeartbeatStatus::Success
+        } else {
+            HeartbeatStatus::Fail
+        };
+        self.ping(status).await;
+        output
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedJob {
+    pub job_name: String,
+    pub bucket_name: String,
+    pub folder_path: PathBuf,
+    pub window: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissedHeartbeat {
+    pub job_name: String,
+    pub last_beat: Option<DateTime<Utc>>,
+    pub window: Duration,
+}
+
+impl MissedHeartbeat {
+    pub fn get_message(&self) -> String {
+        match self.last_beat {
+            Some(last_beat) => format!(
+                "Job {} missed its heartbeat window of {} seconds. Last heartbeat at {}.",
+                self.job_name,
+                self.window.as_secs(),
+                last_beat.to_rfc3339()
+            ),
+            None => format!(
+                "Job {} has no heartbeat within its window of {} seconds.",
+                self.job_name,
+                self.window.as_secs()
+            ),
+        }
+    }
+}
+
+pub fn is_heartbeat_missed(
+    last_beat: Option<DateTime<Utc>>,
+    window: Duration,
+    now: DateTime<Utc>,
+) -> bool {
+    match (last_beat, LongDuration::from_std(window)) {
+        (Some(last_beat), Ok(window)) => now - last_beat > window,
+        (Some(_), Err(_)) => false,
+        (None, _) => true,
+    }
+}
+
+// Reads the S3 markers written by the heartbeats of other processes and alerts on the jobs
+// whose latest heartbeat is older than their window. Run it from a scheduler job of a separate
+// watchdog process, since a hung job cannot report itself.
+pub struct HeartbeatMonitor<'a> {
+    project_logger: &'a ProjectLogger,
+    slack_messenger: &'a SlackMessenger<'a>,
+    aws_file_io: &'a AWSFileIO<'a>,
+    watched_jobs: Vec<WatchedJob>,
+    log_only: bool,
+}
+
+impl<'a> HeartbeatMonitor<'a> {
+    pub fn new(
+        project_logger: &'a ProjectLogger,
+        slack_messenger: &'a SlackMessenger<'a>,
+        aws_file_io: &'a AWSFileIO<'a>,
+    ) -> Self {
+        Self {
+            project_logger,
+            slack_messenger,
+            aws_file_io,
+            watched_jobs: Vec::new(),
+            log_only: false,
+        }
+    }
+
+    pub fn set_log_only(&mut self, log_only: bool) {
+        self.log_only = log_only;
+    }
+
+    pub fn watch_job(
+        &mut self,
+        job_name: &str,
+        bucket_name: &str,
+        folder_path: &Path,
+        window: Duration,
+    ) {
+        self.watched_jobs.push(WatchedJob {
+            job_name: job_name.to_string(),
+            bucket_name: bucket_name.to_string(),
+            folder_path: folder_path.to_path_buf(),
+            window,
+        });
+    }
+
+    pub async fn evaluate(&self) -> Vec<MissedHeartbeat> {
+        let function_name = function_name!(true);
+        let mut missed_heartbeats = Vec::new();
+        for watched_job in self.watched_jobs.iter() {
+            let last_beat = self
+                .aws_file_io
+                .get_object_metadata(
+                    &watched_job.bucket_name,
+                    &watched_job.folder_path,
+                    &HeartbeatTarget::get_marker_file(&watched_job.job_name),
+                )
+                .await
+                .ok()
+                .and_then(|metadata| metadata.last_modified);
+            if is_heartbeat_missed(last_beat, watched_job.window, Utc::now()) {
+                let missed_heartbeat = MissedHeartbeat {
+                    job_name: watched_job.job_name.clone(),
+                    last_beat,
+                    window: watched_job.window,
+                };
+                let error_str = missed_heartbeat.get_message();
+                self.project_logger.log_error(&error_str);
+                self.slack_messenger
+                    .retry_send_message(function_name, &error_str, self.log_only);
+                missed_heartbeats.push(missed_heartbeat);
+            }
+        }
+        missed_heartbeats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_heartbeat_window() {
+        let ping_url = Url::parse("https://hc-ping.com/5b2f6e3a").unwrap();
+        assert_eq!(
+            HeartbeatTarget::get_ping_url(&ping_url, HeartbeatStatus::Start).as_str(),
+            "https://hc-ping.com/5b2f6e3a/start"
+        );
+        assert_eq!(
+            HeartbeatTarget::get_ping_url(&ping_url, HeartbeatStatus::Alive),
+            ping_url
+        );
+        let now = Utc::now();
+        let window = Duration::from_secs(600);
+        assert!(!is_heartbeat_missed(
+            Some(now - LongDuration::seconds(300)),
+            window,
+            now
+        ));
+        assert!(is_heartbeat_missed(
+            Some(now - LongDuration::seconds(900)),
+            window,
+            now
+        ));
+        assert!(is_heartbeat_missed(None, window, now));
+    }
+}
@@ -0,0 +1,261 @@
+use fs2::FileExt;
+use rand::{thread_rng, Rng};
+use redis::Connection;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+use crate::logger::ProjectLogger;
+use crate::redis::Redis;
+
+pub trait DistributedLock {
+    fn get_name(&self) -> &str;
+
+    fn get_ttl(&self) -> Duration;
+
+    fn try_acquire(&mut self) -> bool;
+
+    fn renew(&mut self) -> bool;
+
+    fn release(&mut self);
+}
+
+// The heartbeat of run_exclusive renews every third of the ttl and redis takes the ttl in
+// milliseconds, so shorter ttls are raised to this.
+pub const MIN_TTL: Duration = Duration::from_millis(3);
+
+fn new_lock_token() -> String {
+    let mut rng = thread_rng();
+    format!("{}-{:016x}", process::id(), rng.gen::<u64>())
+}
+
+// An fs2 advisory lock on the lock file. The os releases it when the file is closed, also when
+// the process dies, so there is no expired lock to take over and the ttl only sets how often
+// run_exclusive checks that the lock is still held.
+#[derive(Debug)]
+pub struct LocalFileLock<'a> {
+    project_logger: &'a ProjectLogger,
+    name: String,
+    lock_file: PathBuf,
+    token: String,
+    ttl: Duration,
+    locked_file: Option<File>,
+}
+
+impl<'a> LocalFileLock<'a> {
+    const TTL: Duration = Duration::from_secs(600);
+
+    pub fn new(project_logger: &'a ProjectLogger, folder_path: &Path, name: &str) -> Self {
+        Self {
+            project_logger,
+            name: name.to_string(),
+            lock_file: folder_path.join(format!("{name}.lock")),
+            token: new_lock_token(),
+            ttl: Self::TTL,
+            locked_file: None,
+        }
+    }
+
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl.max(MIN_TTL);
+    }
+
+    // The token is written for inspection only, as the lock itself is held by the open file.
+    fn lock_file(&self) -> std::io::Result<File> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.lock_file)?;
+        file.try_lock_exclusive()?;
+        file.set_len(0)?;
+        file.write_all(self.token.as_bytes())?;
+        Ok(file)
+    }
+}
+
+impl<'a> DistributedLock for LocalFileLock<'a> {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn try_acquire(&mut self) -> bool {
+        if self.locked_file.is_some() {
+            return true;
+        }
+        match self.lock_file() {
+            Ok(file) => {
+                self.locked_file = Some(file);
+                true
+            }
+            Err(e) if e.kind() == fs2::lock_contended_error().kind() => false,
+            Err(e) => {
+                let error_str = format!(
+                    "Unable to lock the lock file {}. {e}",
+                    self.lock_file.display()
+                );
+                self.project_logger.log_error(&error_str);
+                false
+            }
+        }
+    }
+
+    fn renew(&mut self) -> bool {
+        self.locked_file.is_some()
+    }
+
+    // The lock file is kept, as removing it would let another run lock a new file while this
+    // one still holds the lock of the removed one.
+    fn release(&mut self) {
+        if let Some(file) = self.locked_file.take() {
+            if let Err(e) = file.unlock() {
+                let error_str = format!(
+                    "Unable to unlock the lock file {}. {e}",
+                    self.lock_file.display()
+                );
+                self.project_logger.log_error(&error_str);
+            }
+        }
+    }
+}
+
+pub struct RedisLock<'a> {
+    redis: &'a Redis<'a>,
+    conn: Connection,
+    name: String,
+    key: String,
+    token: String,
+    ttl: Duration,
+}
+
+impl<'a> RedisLock<'a> {
+    const TTL: Duration = Duration::from_secs(600);
+    const KEY_PREFIX: &'static str = "lock";
+
+    pub fn new(redis: &'a Redis<'a>, conn: Connection, name: &str) -> Self {
+        Self {
+            redis,
+            conn,
+            name: name.to_string(),
+            key: format!("{}:{name}", Self::KEY_PREFIX),
+            token: new_lock_token(),
+            ttl: Self::TTL,
+        }
+    }
+
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl.max(MIN_TTL);
+    }
+}
+
+impl<'a> DistributedLock for RedisLock<'a> {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn try_acquire(&mut self) -> bool {
+        self.redis
+            .acquire_lock(&self.key, &self.token, self.ttl, &mut self.conn)
+            .unwrap_or(false)
+    }
+
+    fn renew(&mut self) -> bool {
+        self.redis
+            .renew_lock(&self.key, &self.token, self.ttl, &mut self.conn)
+            .unwrap_or(false)
+    }
+
+    fn release(&mut self) {
+        let _ = self
+            .redis
+            .release_lock(&self.key, &self.token, &mut self.conn);
+    }
+}
+
+// Runs the future only if the lock is free, renewing the lock every third of its ttl until the
+// future completes. Returns None if another run holds the lock.
+pub async fn run_exclusive<L, F>(
+    project_logger: &ProjectLogger,
+    lock: &mut L,
+    fut: F,
+) -> Option<F::Output>
+where
+    L: DistributedLock,
+    F: Future,
+{
+    if !lock.try_acquire() {
+        let warn_str = format!(
+            "Lock {} is held by another run. Skip this run.",
+            lock.get_name()
+        );
+        project_logger.log_warn(&warn_str);
+        return None;
+    }
+    // The ttl of other lock implementations is not checked, so it is raised here as well, since
+    // the interval panics on a zero period.
+    let mut heartbeat = tokio::time::interval(lock.get_ttl().max(MIN_TTL) / 3);
+    heartbeat.tick().await;
+    tokio::pin!(fut);
+    let mut is_held = true;
+    let output = loop {
+        tokio::select! {
+            output = &mut fut => break output,
+            _ = heartbeat.tick(), if is_held => {
+                if !lock.renew() {
+                    is_held = false;
+                    let error_str = format!(
+                        "Unable to renew lock {}. Another run may start before this one finishes.",
+                        lock.get_name()
+                    );
+                    project_logger.log_error(&error_str);
+                }
+            }
+        }
+    };
+    lock.release();
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use log::LevelFilter;
+    use std::env;
+
+    #[tokio::test]
+    async fn test_run_exclusive() {
+        let logger_name = "test_lock";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_rust_utilities");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Debug);
+        let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
+        let mut lock = LocalFileLock::new(&project_logger, &folder_path, logger_name);
+        let mut other_lock = LocalFileLock::new(&project_logger, &folder_path, logger_name);
+        lock.set_ttl(Duration::from_millis(300));
+        other_lock.set_ttl(Duration::from_millis(300));
+        let output = run_exclusive(&project_logger, &mut lock, async {
+            assert!(!other_lock.try_acquire());
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            assert!(!other_lock.try_acquire());
+            1
+        })
+        .await;
+        assert_eq!(output, Some(1));
+        assert!(other_lock.try_acquire());
+        other_lock.release();
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::function_name;
+use crate::logger::ProjectLogger;
+use crate::slack_messenger::SlackMessenger;
+
+#[derive(Debug, Clone)]
+pub struct Stopwatch {
+    start_time: Instant,
+    last_lap_time: Instant,
+    laps: Vec<(String, Duration)>,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        let start_time = Instant::now();
+        Self {
+            start_time,
+            last_lap_time: start_time,
+            laps: Vec::new(),
+        }
+    }
+
+    pub fn lap(&mut self, name: &str) -> Duration {
+        let now = Instant::now();
+        let lap_time = now - self.last_lap_time;
+        self.last_lap_time = now;
+        self.laps.push((name.to_string(), lap_time));
+        lap_time
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    pub fn get_laps(&self) -> &[(String, Duration)] {
+        &self.laps
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::start();
+    }
+
+    pub fn summary(&self) -> String {
+        let lap_str: Vec<String> = self
+            .laps
+            .iter()
+            .map(|(name, lap_time)| format!("{name}: {} ms", lap_time.as_millis()))
+            .collect();
+        format!(
+            "Total: {} ms. {}",
+            self.elapsed().as_millis(),
+            lap_str.join(", ")
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Profiler<'a> {
+    project_logger: &'a ProjectLogger,
+    slack_messenger: &'a SlackMessenger<'a>,
+    thresholds: HashMap<String, Duration>,
+    default_threshold: Option<Duration>,
+    log_only: bool,
+}
+
+impl<'a> Profiler<'a> {
+    pub fn new(project_logger: &'a ProjectLogger, slack_messenger: &'a SlackMessenger<'a>) -> Self {
+        Self {
+            project_logger,
+            slack_messenger,
+            thresholds: HashMap::new(),
+            default_threshold: None,
+            log_only: false,
+        }
+    }
+
+    pub fn set_threshold(&mut self, name: &str, threshold: Duration) {
+        self.thresholds.insert(name.to_string(), threshold);
+    }
+
+    pub fn set_default_threshold(&mut self, default_threshold: Duration) {
+        self.default_threshold = Some(default_threshold);
+    }
+
+    pub fn set_log_only(&mut self, log_only: bool) {
+        self.log_only = log_only;
+    }
+
+    pub fn get_threshold(&self, name: &str) -> Option<Duration> {
+        self.thresholds
+            .get(name)
+            .copied()
+            .or(self.default_threshold)
+    }
+
+    pub fn record(&self, name: &str, duration: Duration) {
+        let debug_str = format!("{name} took {} ms.", duration.as_millis());
+        self.project_logger.log_debug(&debug_str);
+        if let Some(threshold) = self
+            .get_threshold(name)
+            .filter(|threshold| duration > *threshold)
+        {
+            let function_name = function_name!(true);
+            let warn_str = format!(
+                "{name} took {} ms, over its budget of {} ms.",
+                duration.as_millis(),
+                threshold.as_millis()
+            );
+            self.project_logger.log_warn(&warn_str);
+            self.slack_messenger
+                .retry_send_message(function_name, &warn_str, self.log_only);
+        }
+    }
+
+    pub fn time_sync<T>(&self, name: &str, func: impl FnOnce() -> T) -> T {
+        let start_time = Instant::now();
+        let output = func();
+        self.record(name, start_time.elapsed());
+        output
+    }
+
+    pub async fn time_async<F: Future>(&self, name: &str, fut: F) -> F::Output {
+        let start_time = Instant::now();
+        let output = fut.await;
+        self.record(name, start_time.elapsed());
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_stopwatch() {
+        let mut stopwatch = Stopwatch::start();
+        thread::sleep(Duration::from_millis(20));
+        let lap_time = stopwatch.lap("load");
+        thread::sleep(Duration::from_millis(10));
+        stopwatch.lap("parse");
+        assert!(lap_time >= Duration::from_millis(20));
+        assert_eq!(stopwatch.get_laps().len(), 2);
+        assert!(stopwatch.elapsed() >= Duration::from_millis(30));
+        assert!(stopwatch.summary().contains("parse: "));
+        stopwatch.reset();
+        assert!(stopwatch.get_laps().is_empty());
+    }
+}
@@ -0,0 +1,360 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+
+#[cfg(feature = "slack")]
+use crate::function_name;
+use crate::netdata::run_report::RunReport;
+#[cfg(feature = "slack")]
+use crate::scheduler::{JobSchedule, ScheduledJob, Scheduler};
+#[cfg(feature = "slack")]
+use crate::slack_messenger::SlackMessenger;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailySummary {
+    pub project: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub num_runs: u32,
+    pub url_counts: BTreeMap<String, u64>,
+    pub bytes_scraped: u64,
+    pub bytes_written: u64,
+    pub rows_inserted: BTreeMap<String, u64>,
+    pub error_counts: BTreeMap<String, u64>,
+}
+
+impl DailySummary {
+    const SUCCESS_STATUS: &'static str = "success";
+    const MAX_ERROR_LINES: usize = 10;
+
+    pub fn num_urls(&self) -> u64 {
+        self.url_counts.values().sum()
+    }
+
+    pub fn success_rate(&self) -> Option<f64> {
+        let num_urls = self.num_urls();
+        (num_urls > 0).then(|| {
+            self.url_counts
+                .get(Self::SUCCESS_STATUS)
+                .copied()
+                .unwrap_or_default() as f64
+                / num_urls as f64
+        })
+    }
+
+    pub fn num_errors(&self) -> u64 {
+        self.error_counts.values().sum()
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.num_errors() == 0
+    }
+
+    fn get_title(&self) -> String {
+        let status_emoji = if self.is_healthy() {
+            ":white_check_mark:"
+        } else {
+            ":warning:"
+        };
+        format!(
+            "{status_emoji} Daily health digest of {} for {}",
+            self.project,
+            self.period_end.format("%Y-%m-%d")
+        )
+    }
+
+    fn get_field_list(&self) -> Vec<(&'static str, String)> {
+        let success_rate = self
+            .success_rate()
+            .map_or("-".to_string(), |rate| format!("{:.1}%", rate * 100.0));
+        let total_rows: u64 = self.rows_inserted.values().sum();
+        vec![
+            ("Runs", self.num_runs.to_string()),
+            ("Urls", self.num_urls().to_string()),
+            ("Success rate", success_rate),
+            ("Bytes scraped", self.bytes_scraped.to_string()),
+            ("Bytes written", self.bytes_written.to_string()),
+            ("Rows inserted", total_rows.to_string()),
+        ]
+    }
+
+    // Keeps the message short when a project has many distinct errors, the largest counts first.
+    fn get_error_lines(&self) -> Vec<String> {
+        let mut error_list: Vec<(&String, &u64)> = self.error_counts.iter().collect();
+        error_list.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let mut error_lines: Vec<String> = error_list
+            .iter()
+            .take(Self::MAX_ERROR_LINES)
+            .map(|(kind, count)| format!("{kind}: {count}"))
+            .collect();
+        if error_list.len() > Self::MAX_ERROR_LINES {
+            error_lines.push(format!(
+                "and {} more",
+                error_list.len() - Self::MAX_ERROR_LINES
+            ));
+        }
+        error_lines
+    }
+
+    pub fn to_text(&self) -> String {
+        let field_str = self
+            .get_field_list()
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let error_str = if self.is_healthy() {
+            "No errors.".to_string()
+        } else {
+            format!("Errors:\n{}", self.get_error_lines().join("\n"))
+        };
+        format!("{}\n{field_str}\n{error_str}", self.get_title())
+    }
+
+    pub fn to_block_kit(&self) -> Value {
+        let field_list: Vec<Value> = self
+            .get_field_list()
+            .iter()
+            .map(|(name, value)| json!({ "type": "mrkdwn", "text": format!("*{name}*\n{value}") }))
+            .collect();
+        let mut blocks = vec![
+            json!({
+                "type": "header",
+                "text": { "type": "plain_text", "text": self.get_title() }
+            }),
+            json!({ "type": "section", "fields": field_list }),
+        ];
+        if !self.rows_inserted.is_empty() {
+            let row_str = self
+                .rows_inserted
+                .iter()
+                .map(|(table, rows)| format!("`{table}`: {rows}"))
+                .collect::<Vec<String>>()
+                .join("\n");
+            blocks.push(json!({
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": format!("*Rows by table*\n{row_str}") }
+            }));
+        }
+        blocks.push(json!({ "type": "divider" }));
+        let error_str = if self.is_healthy() {
+            "*Errors*\nNone".to_string()
+        } else {
+            format!("*Errors*\n```\n{}\n```", self.get_error_lines().join("\n"))
+        };
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": error_str }
+        }));
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{
+                "type": "mrkdwn",
+                "text": format!(
+                    "{} to {}",
+                    self.period_start.format("%Y-%m-%d %H:%M UTC"),
+                    self.period_end.format("%Y-%m-%d %H:%M UTC")
+                )
+            }]
+        }));
+        Value::Array(blocks)
+    }
+
+    // There is no mail sender in this crate, the html is for callers that email the digest.
+    pub fn to_html(&self) -> String {
+        let row_html = self
+            .get_field_list()
+            .iter()
+            .map(|(name, value)| format!("<tr><th>{name}</th><td>{value}</td></tr>"))
+            .collect::<Vec<String>>()
+            .join("");
+        let error_html = if self.is_healthy() {
+            "<p>No errors.</p>".to_string()
+        } else {
+            format!(
+                "<ul>{}</ul>",
+                self.get_error_lines()
+                    .iter()
+                    .map(|line| format!("<li>{}</li>", escape_html(line)))
+                    .collect::<Vec<String>>()
+                    .join("")
+            )
+        };
+        format!(
+            "<h2>{}</h2><table>{row_html}</table><h3>Errors</h3>{error_html}",
+            escape_html(&self.get_title())
+        )
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[derive(Debug)]
+struct DailyMetrics {
+    period_start: DateTime<Utc>,
+    num_runs: u32,
+    url_counts: BTreeMap<String, u64>,
+    bytes_scraped: u64,
+    bytes_written: u64,
+    rows_inserted: BTreeMap<String, u64>,
+    error_counts: BTreeMap<String, u64>,
+}
+
+impl DailyMetrics {
+    fn new(period_start: DateTime<Utc>) -> Self {
+        Self {
+            period_start,
+            num_runs: 0,
+            url_counts: BTreeMap::new(),
+            bytes_scraped: 0,
+            bytes_written: 0,
+            rows_inserted: BTreeMap::new(),
+            error_counts: BTreeMap::new(),
+        }
+    }
+}
+
+// Collects the metrics of all runs in a process between two digests. The recorders take a
+// shared reference so that the reporter can be passed to the scrapers and the scheduler at once.
+#[derive(Debug)]
+pub struct DailyReporter {
+    project: String,
+    metrics: Mutex<DailyMetrics>,
+}
+
+impl DailyReporter {
+    pub fn new(project: &str) -> Self {
+        Self {
+            project: project.to_string(),
+            metrics: Mutex::new(DailyMetrics::new(Utc::now())),
+        }
+    }
+
+    fn with_metrics<T>(&self, func: impl FnOnce(&mut DailyMetrics) -> T) -> T {
+        func(&mut self.metrics.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    pub fn record_run_report(&self, run_report: &RunReport) {
+        self.with_metrics(|metrics| {
+            metrics.num_runs += 1;
+            for url_report in run_report.get_url_reports() {
+                *metrics
+                    .url_counts
+                    .entry(url_report.outcome.status.as_str().to_string())
+                    .or_default() += 1;
+                metrics.bytes_scraped += url_report.outcome.bytes.unwrap_or_default();
+            }
+        })
+    }
+
+    pub fn record_bytes_written(&self, bytes: u64) {
+        self.with_metrics(|metrics| metrics.bytes_written += bytes)
+    }
+
+    pub fn record_rows_inserted(&self, table: &str, rows: u64) {
+        self.with_metrics(|metrics| {
+            *metrics.rows_inserted.entry(table.to_string()).or_default() += rows
+        })
+    }
+
+    pub fn record_error(&self, kind: &str) {
+        self.with_metrics(|metrics| *metrics.error_counts.entry(kind.to_string()).or_default() += 1)
+    }
+
+    // Returns the summary since the previous call and starts a new period.
+    pub fn take_summary(&self, period_end: DateTime<Utc>) -> DailySummary {
+        let metrics =
+            self.with_metrics(|metrics| std::mem::replace(metrics, DailyMetrics::new(period_end)));
+        DailySummary {
+            project: self.project.clone(),
+            period_start: metrics.period_start,
+            period_end,
+            num_runs: metrics.num_runs,
+            url_counts: metrics.url_counts,
+            bytes_scraped: metrics.bytes_scraped,
+            bytes_written: metrics.bytes_written,
+            rows_inserted: metrics.rows_inserted,
+            error_counts: metrics.error_counts,
+        }
+    }
+
+    #[cfg(feature = "slack")]
+    pub fn send_summary(&self, slack_messenger: &SlackMessenger, log_only: bool) -> DailySummary {
+        let function_name = function_name!(true);
+        let summary = self.take_summary(Utc::now());
+        slack_messenger.post_blocks(
+            function_name,
+            &summary.to_text(),
+            &summary.to_block_kit(),
+            log_only,
+        );
+        summary
+    }
+
+    #[cfg(feature = "slack")]
+    pub fn schedule<'a, 'b>(
+        &'a self,
+        scheduler: &'b mut Scheduler<'a>,
+        slack_messenger: &'a SlackMessenger<'a>,
+        cron_expression: &str,
+        log_only: bool,
+    ) -> Result<&'b mut ScheduledJob<'a>, cron::error::Error> {
+        let schedule = JobSchedule::cron(cron_expression)?;
+        let job_name = format!("{} daily digest", self.project);
+        Ok(scheduler.add_job(&job_name, schedule, move || async move {
+            self.send_summary(slack_messenger, log_only);
+            Ok(())
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::netdata::data_struct::{ScrapeOutcome, ScrapeStatus};
+    use std::time::Duration;
+
+    #[test]
+    fn test_daily_summary() {
+        let daily_reporter = DailyReporter::new("football");
+        let mut run_report = RunReport::new();
+        run_report.add_outcome(
+            "https://www.nowgoal.com/football/live",
+            ScrapeOutcome {
+                status: ScrapeStatus::Success,
+                attempts: 1,
+                latency: Some(Duration::from_millis(100)),
+                bytes: Some(1000),
+            },
+        );
+        run_report.add_outcome(
+            "https://www.nowgoal.com/football/results",
+            ScrapeOutcome::skipped(ScrapeStatus::Blocked),
+        );
+        daily_reporter.record_run_report(&run_report);
+        daily_reporter.record_bytes_written(400);
+        daily_reporter.record_rows_inserted("matches", 20);
+        daily_reporter.record_rows_inserted("matches", 5);
+        daily_reporter.record_error("timeout");
+        let summary = daily_reporter.take_summary(Utc::now());
+        assert_eq!(summary.num_urls(), 2);
+        assert_eq!(summary.success_rate(), Some(0.5));
+        assert_eq!(summary.bytes_scraped, 1000);
+        assert_eq!(summary.rows_inserted["matches"], 25);
+        assert!(!summary.is_healthy());
+        let blocks = summary.to_block_kit();
+        assert_eq!(blocks[0]["type"], "header");
+        assert_eq!(blocks[1]["fields"][2]["text"], "*Success rate*\n50.0%");
+        assert!(summary.to_text().contains("timeout: 1"));
+        assert!(summary.to_html().contains("<li>timeout: 1</li>"));
+        let summary = daily_reporter.take_summary(Utc::now());
+        assert_eq!(summary.num_runs, 0);
+        assert!(summary.is_healthy());
+    }
+}
@@ -0,0 +1,135 @@
+use chrono::Utc;
+use polars::prelude::*;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::sync::{PoisonError, RwLock};
+
+static CURRENT_RUN_CONTEXT: RwLock<Option<RunContext>> = RwLock::new(None);
+
+// Identifies a run of a job, so that the logs, alerts, metrics and manifests written by the
+// different modules during the run can be correlated. The context is process wide, set once at
+// the start of the job and read by the ProjectLogger, SlackMessenger and the writers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunContext {
+    pub run_id: String,
+    pub job_name: String,
+    pub host: String,
+}
+
+impl RunContext {
+    pub const RUN_ID_COLUMN: &'static str = "run_id";
+    pub const JOB_NAME_COLUMN: &'static str = "job_name";
+    pub const HOST_COLUMN: &'static str = "host";
+    const UNKNOWN_HOST: &'static str = "unknown";
+
+    pub fn new(job_name: &str) -> Self {
+        let run_id = format!(
+            "{}-{:08x}",
+            Utc::now().format("%Y%m%d%H%M%S"),
+            thread_rng().gen::<u32>()
+        );
+        Self::with_run_id(job_name, &run_id)
+    }
+
+    pub fn with_run_id(job_name: &str, run_id: &str) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            job_name: job_name.to_string(),
+            host: Self::get_host_name(),
+        }
+    }
+
+    pub(crate) fn get_host_name() -> String {
+        env::var("HOSTNAME")
+            .ok()
+            .or_else(|| fs::read_to_string("/etc/hostname").ok())
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty())
+            .unwrap_or_else(|| Self::UNKNOWN_HOST.to_string())
+    }
+
+    // Creates the context of a new run of the job and makes it the current one.
+    pub fn start(job_name: &str) -> Self {
+        let run_context = Self::new(job_name);
+        run_context.set_current();
+        run_context
+    }
+
+    pub fn set_current(&self) {
+        *CURRENT_RUN_CONTEXT
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(self.clone());
+    }
+
+    pub fn clear_current() {
+        *CURRENT_RUN_CONTEXT
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    pub fn current() -> Option<Self> {
+        CURRENT_RUN_CONTEXT
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn get_tag(&self) -> String {
+        format!("[{} {}@{}]", self.job_name, self.run_id, self.host)
+    }
+
+    // The tag of the current run followed by a space, or empty outside a run.
+    pub fn current_tag() -> String {
+        Self::current().map_or(String::new(), |run_context| {
+            format!("{} ", run_context.get_tag())
+        })
+    }
+
+    pub fn stamp_data_frame(&self, data: &mut DataFrame) -> PolarsResult<()> {
+        let height = data.height();
+        data.with_column(Series::new(
+            Self::RUN_ID_COLUMN.into(),
+            vec![self.run_id.as_str(); height],
+        ))?
+        .with_column(Series::new(
+            Self::JOB_NAME_COLUMN.into(),
+            vec![self.job_name.as_str(); height],
+        ))?
+        .with_column(Series::new(
+            Self::HOST_COLUMN.into(),
+            vec![self.host.as_str(); height],
+        ))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_run_context() {
+        let run_context = RunContext::with_run_id("football", "20240501100000-0000abcd");
+        assert!(run_context
+            .get_tag()
+            .starts_with("[football 20240501100000-0000abcd@"));
+        assert_ne!(
+            RunContext::new("football").run_id,
+            RunContext::new("football").run_id
+        );
+        let mut data = df!("url" => ["https://www.nowgoal.com"]).unwrap();
+        run_context.stamp_data_frame(&mut data).unwrap();
+        assert_eq!(data.shape(), (1, 4));
+        assert_eq!(
+            data.column(RunContext::JOB_NAME_COLUMN)
+                .unwrap()
+                .str()
+                .unwrap()
+                .get(0),
+            Some("football")
+        );
+    }
+}
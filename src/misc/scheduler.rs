@@ -0,0 +1,261 @@
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use futures::future;
+use rand::{thread_rng, Rng};
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config_value::ConfigValueError;
+use crate::function_name;
+use crate::logger::ProjectLogger;
+use crate::shutdown::ShutdownSignal;
+use crate::slack_messenger::SlackMessenger;
+
+pub type JobResult = Result<(), String>;
+type JobFuture<'a> = Pin<Box<dyn Future<Output = JobResult> + 'a>>;
+type JobFunc<'a> = Box<dyn Fn() -> JobFuture<'a> + 'a>;
+
+#[derive(Debug, Clone)]
+pub enum JobSchedule {
+    Cron(Box<Schedule>),
+    Interval(Duration),
+}
+
+impl JobSchedule {
+    pub fn cron(cron_expression: &str) -> Result<Self, cron::error::Error> {
+        Ok(Self::Cron(Box::new(Schedule::from_str(cron_expression)?)))
+    }
+
+    pub fn interval(interval: Duration) -> Result<Self, ConfigValueError> {
+        if interval.is_zero() {
+            return Err(ConfigValueError::InvalidFormat {
+                value: format!("{interval:?}"),
+                expected: "a non-zero interval".to_string(),
+            });
+        }
+        Ok(Self::Interval(interval))
+    }
+
+    // A zero interval built from the variant directly never runs, as it would never move on.
+    pub fn next_run_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Cron(schedule) => schedule.after(&time).next(),
+            Self::Interval(interval) if interval.is_zero() => None,
+            Self::Interval(interval) => chrono::Duration::from_std(*interval)
+                .ok()
+                .map(|interval| time + interval),
+        }
+    }
+
+    // A run that finishes after its next scheduled time would overlap with it, so the runs
+    // scheduled before now are skipped and counted as missed.
+    pub fn skip_missed_runs(
+        &self,
+        last_run: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> (u32, Option<DateTime<Utc>>) {
+        let mut num_missed = 0;
+        let mut next_run = self.next_run_after(last_run);
+        while let Some(run_time) = next_run.filter(|run_time| *run_time < now) {
+            num_missed += 1;
+            next_run = self.next_run_after(run_time);
+        }
+        (num_missed, next_run)
+    }
+}
+
+pub struct ScheduledJob<'a> {
+    name: String,
+    schedule: JobSchedule,
+    job_func: JobFunc<'a>,
+    jitter: Duration,
+    timeout: Option<Duration>,
+}
+
+impl<'a> ScheduledJob<'a> {
+    pub fn set_jitter(&mut self, jitter: Duration) {
+        self.jitter = jitter;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_jitter(&self) -> chrono::Duration {
+        let max_jitter = self.jitter.as_millis() as i64;
+        if max_jitter == 0 {
+            chrono::Duration::zero()
+        } else {
+            let mut rng = thread_rng();
+            chrono::Duration::milliseconds(rng.gen_range(0..max_jitter))
+        }
+    }
+}
+
+pub struct Scheduler<'a> {
+    project_logger: &'a ProjectLogger,
+    slack_messenger: &'a SlackMessenger<'a>,
+    jobs: Vec<ScheduledJob<'a>>,
+    shutdown_signal: Option<ShutdownSignal>,
+    log_only: bool,
+}
+
+impl<'a> Scheduler<'a> {
+    const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new(project_logger: &'a ProjectLogger, slack_messenger: &'a SlackMessenger<'a>) -> Self {
+        Self {
+            project_logger,
+            slack_messenger,
+            jobs: Vec::new(),
+            shutdown_signal: None,
+            log_only: false,
+        }
+    }
+
+    pub fn set_shutdown_signal(&mut self, shutdown_signal: ShutdownSignal) {
+        self.shutdown_signal = Some(shutdown_signal);
+    }
+
+    pub fn set_log_only(&mut self, log_only: bool) {
+        self.log_only = log_only;
+    }
+
+    pub fn add_job<F, Fut>(
+        &mut self,
+        name: &str,
+        schedule: JobSchedule,
+        job_func: F,
+    ) -> &mut ScheduledJob<'a>
+    where
+        F: Fn() -> Fut + 'a,
+        Fut: Future<Output = JobResult> + 'a,
+    {
+        self.jobs.push(ScheduledJob {
+            name: name.to_string(),
+            schedule,
+            job_func: Box::new(move || Box::pin(job_func())),
+            jitter: Duration::ZERO,
+            timeout: None,
+        });
+        self.jobs.last_mut().unwrap()
+    }
+
+    fn is_shutdown_requested(&self) -> bool {
+        self.shutdown_signal
+            .as_ref()
+            .is_some_and(|shutdown_signal| shutdown_signal.is_requested())
+    }
+
+    fn notify_job_error(&self, message: &str) {
+        let function_name = function_name!(true);
+        self.project_logger.log_error(message);
+        self.slack_messenger
+            .retry_send_message(function_name, message, self.log_only);
+    }
+
+    // Sleep in short steps so that a shutdown signal is noticed while waiting for the next run.
+    async fn sleep_until(&self, run_time: DateTime<Utc>) -> bool {
+        while let Ok(remaining) = (run_time - Utc::now()).to_std() {
+            if self.is_shutdown_requested() {
+                return false;
+            }
+            tokio::time::sleep(remaining.min(Self::SHUTDOWN_CHECK_INTERVAL)).await;
+        }
+        !self.is_shutdown_requested()
+    }
+
+    async fn run_job_once(&self, job: &ScheduledJob<'a>) {
+        let job_future = (job.job_func)();
+        let job_result = match job.timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, job_future).await {
+                Ok(job_result) => job_result,
+                Err(_) => Err(format!("Timeout after {} seconds", timeout.as_secs())),
+            },
+            None => job_future.await,
+        };
+        match job_result {
+            Ok(()) => {
+                let debug_str = format!("Job {} finished.", job.name);
+                self.project_logger.log_debug(&debug_str);
+            }
+            Err(e) => {
+                let error_str = format!("Job {} failed. {e}", job.name);
+                self.notify_job_error(&error_str);
+            }
+        }
+    }
+
+    async fn run_job(&self, job: &ScheduledJob<'a>) {
+        let mut next_run = job.schedule.next_run_after(Utc::now());
+        while let Some(run_time) = next_run {
+            if !self.sleep_until(run_time + job.get_jitter()).await {
+                break;
+            }
+            self.run_job_once(job).await;
+            let (num_missed, following_run) = job.schedule.skip_missed_runs(run_time, Utc::now());
+            if num_missed > 0 {
+                let error_str = format!(
+                    "Job {} missed {num_missed} runs as the previous run was still in progress.",
+                    job.name
+                );
+                self.notify_job_error(&error_str);
+            }
+            next_run = following_run;
+        }
+        let info_str = format!("Job {} stopped.", job.name);
+        self.project_logger.log_info(&info_str);
+    }
+
+    pub async fn run(&self) {
+        future::join_all(self.jobs.iter().map(|job| self.run_job(job))).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_job_schedule() {
+        let start_time = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let cron_schedule = JobSchedule::cron("0 */15 * * * *").unwrap();
+        assert_eq!(
+            cron_schedule.next_run_after(start_time),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 15, 0).unwrap())
+        );
+        let (num_missed, next_run) = cron_schedule.skip_missed_runs(
+            start_time,
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 40, 0).unwrap(),
+        );
+        assert_eq!(num_missed, 2);
+        assert_eq!(
+            next_run,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 45, 0).unwrap())
+        );
+        let interval_schedule = JobSchedule::interval(Duration::from_secs(60)).unwrap();
+        let (num_missed, next_run) = interval_schedule.skip_missed_runs(
+            start_time,
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 30).unwrap(),
+        );
+        assert_eq!(num_missed, 0);
+        assert_eq!(
+            next_run,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 1, 0).unwrap())
+        );
+        assert!(JobSchedule::cron("not a cron").is_err());
+        assert!(JobSchedule::interval(Duration::ZERO).is_err());
+        assert_eq!(
+            JobSchedule::Interval(Duration::ZERO).skip_missed_runs(start_time, start_time),
+            (0, None)
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub trait SecretsProvider: Send + Sync {
+    fn get_secret(&self, key: &str) -> Option<String>;
+
+    fn require_secret(&self, key: &str) -> String {
+        self.get_secret(key)
+            .unwrap_or_else(|| panic!("Unable to find the secret {key}."))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvSecretsProvider {
+    prefix: String,
+}
+
+impl EnvSecretsProvider {
+    const PREFIX: &'static str = "SCTYS_";
+
+    pub fn new() -> Self {
+        Self {
+            prefix: Self::PREFIX.to_string(),
+        }
+    }
+
+    pub fn set_prefix(&mut self, prefix: &str) {
+        self.prefix = prefix.to_string();
+    }
+
+    pub fn get_env_key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key.to_uppercase())
+    }
+}
+
+impl Default for EnvSecretsProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecretsProvider for EnvSecretsProvider {
+    fn get_secret(&self, key: &str) -> Option<String> {
+        env::var(self.get_env_key(key)).ok()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FileSecretsProvider {
+    secrets: HashMap<String, String>,
+}
+
+impl FileSecretsProvider {
+    const PROJECT_KEY: &'static str = "SCTYS_PROJECT";
+    const SECRET_PATH: &'static str = "Secret/secret_sctys_rust_utilities";
+    const SECRET_FILE: &'static str = "secrets.toml";
+
+    pub fn from_toml_str(secret_str: &str) -> Result<Self, toml::de::Error> {
+        Ok(Self {
+            secrets: toml::from_str(secret_str)?,
+        })
+    }
+
+    pub fn load(secret_file: &Path) -> Self {
+        let secret_str = match fs::read_to_string(secret_file) {
+            Ok(secret_str) => secret_str,
+            Err(e) => panic!(
+                "Unable to load the secret file {}. {e}",
+                secret_file.display()
+            ),
+        };
+        match Self::from_toml_str(&secret_str) {
+            Ok(file_secrets_provider) => file_secrets_provider,
+            Err(e) => panic!(
+                "Unable to parse the secret file {}. {e}",
+                secret_file.display()
+            ),
+        }
+    }
+
+    pub fn default_secret_file() -> PathBuf {
+        Path::new(&env::var(Self::PROJECT_KEY).expect("Unable to find project path"))
+            .join(Self::SECRET_PATH)
+            .join(Self::SECRET_FILE)
+    }
+}
+
+impl SecretsProvider for FileSecretsProvider {
+    fn get_secret(&self, key: &str) -> Option<String> {
+        self.secrets.get(key).cloned()
+    }
+}
+
+// Secrets are fetched once when the provider is built, so lookups stay synchronous.
+#[cfg(feature = "s3")]
+#[derive(Debug, Clone, Default)]
+pub struct AWSSecretsProvider {
+    secrets: HashMap<String, String>,
+}
+
+#[cfg(feature = "s3")]
+impl AWSSecretsProvider {
+    pub async fn from_secrets_manager(secret_id: &str) -> Self {
+        let config = aws_config::from_env().load().await;
+        let client = aws_sdk_secretsmanager::Client::new(&config);
+        let secret_str = match client.get_secret_value().secret_id(secret_id).send().await {
+            Ok(output) => output.secret_string().unwrap_or_default().to_string(),
+            Err(e) => panic!("Unable to load the secret {secret_id} from Secrets Manager. {e}"),
+        };
+        let secrets = match serde_json::from_str(&secret_str) {
+            Ok(secrets) => secrets,
+            Err(e) => panic!("Unable to parse the secret {secret_id}. {e}"),
+        };
+        Self { secrets }
+    }
+
+    pub async fn from_parameter_store(parameter_path: &str) -> Self {
+        let config = aws_config::from_env().load().await;
+        let client = aws_sdk_ssm::Client::new(&config);
+        let mut secrets = HashMap::new();
+        let mut next_token = None;
+        loop {
+            let output = match client
+                .get_parameters_by_path()
+                .path(parameter_path)
+                .recursive(true)
+                .with_decryption(true)
+                .set_next_token(next_token)
+                .send()
+                .await
+            {
+                Ok(output) => output,
+                Err(e) => {
+                    panic!("Unable to load the parameters in {parameter_path} from SSM. {e}")
+                }
+            };
+            for parameter in output.parameters().unwrap_or_default() {
+                if let (Some(name), Some(value)) = (parameter.name(), parameter.value()) {
+                    let key = name.rsplit('/').next().unwrap_or(name);
+                    secrets.insert(key.to_string(), value.to_string());
+                }
+            }
+            next_token = output.next_token().map(str::to_string);
+            if next_token.is_none() {
+                break;
+            }
+        }
+        Self { secrets }
+    }
+}
+
+#[cfg(feature = "s3")]
+impl SecretsProvider for AWSSecretsProvider {
+    fn get_secret(&self, key: &str) -> Option<String> {
+        self.secrets.get(key).cloned()
+    }
+}
+
+pub struct ChainSecretsProvider {
+    providers: Vec<Box<dyn SecretsProvider>>,
+}
+
+impl ChainSecretsProvider {
+    pub fn new(providers: Vec<Box<dyn SecretsProvider>>) -> Self {
+        Self { providers }
+    }
+}
+
+impl SecretsProvider for ChainSecretsProvider {
+    fn get_secret(&self, key: &str) -> Option<String> {
+        self.providers
+            .iter()
+            .find_map(|provider| provider.get_secret(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_chain_secrets_provider() {
+        let file_secrets_provider = FileSecretsProvider::from_toml_str(
+            "aws_api_id = \"file_id\"\nslack_api_token = \"file_token\"",
+        )
+        .unwrap();
+        let mut env_secrets_provider = EnvSecretsProvider::new();
+        env_secrets_provider.set_prefix("TEST_SECRETS_PROVIDER_");
+        assert_eq!(
+            env_secrets_provider.get_env_key("aws_api_id"),
+            "TEST_SECRETS_PROVIDER_AWS_API_ID"
+        );
+        env::set_var("TEST_SECRETS_PROVIDER_AWS_API_ID", "env_id");
+        let secrets_provider = ChainSecretsProvider::new(vec![
+            Box::new(env_secrets_provider),
+            Box::new(file_secrets_provider),
+        ]);
+        assert_eq!(secrets_provider.require_secret("aws_api_id"), "env_id");
+        assert_eq!(
+            secrets_provider.get_secret("slack_api_token"),
+            Some("file_token".to_string())
+        );
+        assert_eq!(secrets_provider.get_secret("aws_api_secret"), None);
+    }
+}
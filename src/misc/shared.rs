@@ -0,0 +1,74 @@
+use std::ops::Deref;
+use std::sync::Arc;
+
+// A component either borrowed from the caller or owned through an Arc. The structs built from
+// borrowed components keep their lifetime as before, while those built from Arcs are 'static, so
+// they can be moved into spawned tasks or kept in long-lived services.
+#[derive(Debug)]
+pub enum Shared<'a, T: ?Sized> {
+    Borrowed(&'a T),
+    Owned(Arc<T>),
+}
+
+impl<T: ?Sized> Clone for Shared<'_, T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Borrowed(component) => Self::Borrowed(component),
+            Self::Owned(component) => Self::Owned(Arc::clone(component)),
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for Shared<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            Self::Borrowed(component) => component,
+            Self::Owned(component) => component,
+        }
+    }
+}
+
+impl<'a, T: ?Sized> From<&'a T> for Shared<'a, T> {
+    fn from(component: &'a T) -> Self {
+        Self::Borrowed(component)
+    }
+}
+
+impl<'a, T: ?Sized> From<&'a Arc<T>> for Shared<'a, T> {
+    fn from(component: &'a Arc<T>) -> Self {
+        Self::Borrowed(component)
+    }
+}
+
+impl<T: ?Sized> From<Arc<T>> for Shared<'_, T> {
+    fn from(component: Arc<T>) -> Self {
+        Self::Owned(component)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn assert_send_static<T: Send + Sync + 'static>(_: &T) {}
+
+    #[test]
+    fn test_shared() {
+        let borrowed_name = String::from("sctys");
+        let borrowed: Shared<String> = Shared::from(&borrowed_name);
+        assert_eq!(borrowed.as_str(), "sctys");
+        let owned: Shared<'static, String> = Shared::from(Arc::new(String::from("sctys")));
+        assert_send_static(&owned);
+        let owned_clone = owned.clone();
+        assert_eq!(*owned_clone, *borrowed);
+        match (&owned, &owned_clone) {
+            (Shared::Owned(owned), Shared::Owned(owned_clone)) => {
+                assert!(Arc::ptr_eq(owned, owned_clone))
+            }
+            _ => panic!("The clone of an owned component should share its Arc."),
+        }
+    }
+}
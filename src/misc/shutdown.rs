@@ -0,0 +1,73 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownSignal {
+    requested: Arc<AtomicBool>,
+}
+
+impl ShutdownSignal {
+    // The exit code for the binary to exit with once its run has returned after a shutdown.
+    pub const EXIT_CODE: i32 = 130;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    #[cfg(unix)]
+    async fn wait_for_signal() {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {},
+                    _ = sigterm.recv() => {},
+                }
+            }
+            Err(e) => {
+                log::warn!("Unable to listen to SIGTERM, only listen to SIGINT. {e}");
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn wait_for_signal() {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+
+    // The signal asks the running jobs to stop gracefully. The jobs return their pending urls and
+    // the exit is left to the binary, so its own cleanup still runs.
+    pub fn listen(&self) -> JoinHandle<()> {
+        let shutdown_signal = self.clone();
+        tokio::spawn(async move {
+            Self::wait_for_signal().await;
+            log::warn!("Shutdown signal received. Finishing the in-flight requests.");
+            shutdown_signal.request();
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_shutdown_request() {
+        let shutdown_signal = ShutdownSignal::new();
+        let cloned_signal = shutdown_signal.clone();
+        assert!(!cloned_signal.is_requested());
+        shutdown_signal.request();
+        assert!(cloned_signal.is_requested());
+    }
+}
@@ -1,9 +1,19 @@
 use chrono::{
-    DateTime, Datelike, Duration as LongDuration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime,
-    TimeZone, Timelike, Utc,
+    DateTime, Datelike, Duration as LongDuration, FixedOffset, LocalResult, NaiveDate,
+    NaiveDateTime, NaiveTime, ParseError, TimeZone, Timelike, Utc, Weekday,
 };
-use chrono_tz::Tz;
+use chrono_tz::{Asia, Europe, Tz};
+use cron::Schedule;
+use futures::future::BoxFuture;
 use rand::{thread_rng, Rng};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::thread;
 use std::time::{Duration, SystemTime};
 use tokio::time;
@@ -12,6 +22,9 @@ const SEC_TO_HOUR: i32 = 3600;
 const ONE_E3: i64 = 1_000;
 const ONE_E6: i64 = 1_000_000;
 const ONE_E9: i64 = 1_000_000_000;
+pub const HONG_KONG: Tz = Asia::Hong_Kong;
+pub const LONDON: Tz = Europe::London;
+const HOLIDAY_DATE_FORMAT: &str = "%Y-%m-%d";
 
 pub fn sleep(sleep_time: Duration) {
     thread::sleep(sleep_time);
@@ -41,6 +54,109 @@ pub async fn async_random_sleep((min_sleep_time, max_sleep_time): (Duration, Dur
     }
 }
 
+fn random_duration((min_sleep_time, max_sleep_time): (Duration, Duration)) -> Duration {
+    if min_sleep_time >= max_sleep_time {
+        min_sleep_time
+    } else {
+        let mut rng = thread_rng();
+        rng.gen_range(min_sleep_time..max_sleep_time)
+    }
+}
+
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    fn sleep(&self, sleep_time: Duration) -> BoxFuture<'_, ()>;
+
+    fn random_sleep(&self, sleep_range: (Duration, Duration)) -> BoxFuture<'_, ()> {
+        self.sleep(random_duration(sleep_range))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, sleep_time: Duration) -> BoxFuture<'_, ()> {
+        Box::pin(async_sleep(sleep_time))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    current_time: Arc<Mutex<DateTime<Utc>>>,
+    sleep_history: Arc<Mutex<Vec<Duration>>>,
+}
+
+impl MockClock {
+    pub fn new(start_time: DateTime<Utc>) -> Self {
+        Self {
+            current_time: Arc::new(Mutex::new(start_time)),
+            sleep_history: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut current_time = self
+            .current_time
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *current_time += LongDuration::from_std(duration)
+            .unwrap_or_else(|e| panic!("Unable to advance the mock clock by {duration:?}. {e}"));
+    }
+
+    pub fn set_time(&self, date_time: DateTime<Utc>) {
+        *self
+            .current_time
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = date_time;
+    }
+
+    pub fn get_sleep_history(&self) -> Vec<Duration> {
+        self.sleep_history
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn total_sleep_time(&self) -> Duration {
+        self.get_sleep_history().iter().sum()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self
+            .current_time
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn sleep(&self, sleep_time: Duration) -> BoxFuture<'_, ()> {
+        self.sleep_history
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(sleep_time);
+        self.advance(sleep_time);
+        Box::pin(async {})
+    }
+}
+
+// The deadlines are taken on the clock of the caller, so a mock clock reaches them with its
+// sleeps.
+pub fn get_deadline(max_duration: Option<Duration>, clock: &dyn Clock) -> Option<DateTime<Utc>> {
+    let max_duration = LongDuration::from_std(max_duration?).ok()?;
+    clock.now().checked_add_signed(max_duration)
+}
+
+pub fn is_deadline_reached(deadline: Option<DateTime<Utc>>, clock: &dyn Clock) -> bool {
+    deadline.map_or(false, |deadline| clock.now() >= deadline)
+}
+
 pub enum SecPrecision {
     Sec,
     MilliSec,
@@ -246,6 +362,187 @@ pub fn date_time_timezone_from_string(date_time_str: &str, fmt: &str) -> DateTim
     }
 }
 
+pub fn utc_to_hong_kong(date_time: &DateTime<Utc>) -> DateTime<Tz> {
+    utc_date_time_to_timezone(date_time, HONG_KONG)
+}
+
+pub fn utc_to_london(date_time: &DateTime<Utc>) -> DateTime<Tz> {
+    utc_date_time_to_timezone(date_time, LONDON)
+}
+
+pub fn convert_timezone<T: TimeZone>(date_time: &DateTime<T>, timezone: Tz) -> DateTime<Tz> {
+    date_time.with_timezone(&timezone)
+}
+
+pub fn floor_to_interval(date_time: &DateTime<Utc>, interval: Duration) -> DateTime<Utc> {
+    let interval_millis = interval.as_millis() as i64;
+    if interval_millis == 0 {
+        return *date_time;
+    }
+    let timestamp = date_time.timestamp_millis();
+    utc_date_time_from_timestamp(
+        timestamp - timestamp.rem_euclid(interval_millis),
+        SecPrecision::MilliSec,
+    )
+}
+
+pub fn ceil_to_interval(date_time: &DateTime<Utc>, interval: Duration) -> DateTime<Utc> {
+    let floor_date_time = floor_to_interval(date_time, interval);
+    if floor_date_time == *date_time {
+        floor_date_time
+    } else {
+        floor_date_time
+            + LongDuration::from_std(interval).unwrap_or_else(|e| {
+                panic!("Unable to align {date_time} to the interval {interval:?}. {e}")
+            })
+    }
+}
+
+pub fn parse_cron(cron_expr: &str) -> Result<Schedule, cron::error::Error> {
+    Schedule::from_str(cron_expr)
+}
+
+// A wall clock time skipped by a DST gap is shifted an hour later, e.g. 01:30 in the spring gap
+// of London runs at 02:30 BST, which is the instant 01:30 GMT would have been. A wall clock time
+// repeated by a DST overlap runs once on its first occurrence.
+fn local_occurrence<T: TimeZone>(timezone: &T, wall_time: &NaiveDateTime) -> Option<DateTime<T>> {
+    match timezone.from_local_datetime(wall_time) {
+        LocalResult::Single(date_time) => Some(date_time),
+        LocalResult::Ambiguous(earliest, _) => Some(earliest),
+        LocalResult::None => timezone
+            .from_local_datetime(&(*wall_time + LongDuration::hours(1)))
+            .earliest(),
+    }
+}
+
+fn next_schedule_occurrence<T: TimeZone>(
+    schedule: &Schedule,
+    from: &DateTime<T>,
+) -> Option<DateTime<T>> {
+    let timezone = from.timezone();
+    schedule
+        .after(&Utc.from_utc_datetime(&from.naive_local()))
+        .filter_map(|wall_time| local_occurrence(&timezone, &wall_time.naive_utc()))
+        .find(|date_time| date_time > from)
+}
+
+// The cron expression is evaluated on the wall clock of the time zone of from.
+pub fn next_occurrence<T: TimeZone>(
+    cron_expr: &str,
+    from: &DateTime<T>,
+) -> Result<Option<DateTime<T>>, cron::error::Error> {
+    Ok(next_schedule_occurrence(&parse_cron(cron_expr)?, from))
+}
+
+pub fn iter_occurrences<T: TimeZone>(
+    cron_expr: &str,
+    range: Range<DateTime<T>>,
+) -> Result<Vec<DateTime<T>>, cron::error::Error> {
+    let schedule = parse_cron(cron_expr)?;
+    let mut occurrence_list = Vec::new();
+    let mut from = range.start.clone() - LongDuration::seconds(1);
+    while let Some(date_time) = next_schedule_occurrence(&schedule, &from) {
+        if date_time >= range.end {
+            break;
+        }
+        occurrence_list.push(date_time.clone());
+        from = date_time;
+    }
+    Ok(occurrence_list)
+}
+
+pub fn is_weekend(date: &NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct HolidayCalendar {
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl HolidayCalendar {
+    pub fn new(holidays: &[NaiveDate]) -> Self {
+        Self {
+            holidays: holidays.iter().copied().collect(),
+        }
+    }
+
+    // The toml file holds the dates as strings, e.g. holidays = ["2024-12-25", "2024-12-26"].
+    pub fn from_toml_str(calendar_str: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(calendar_str)
+    }
+
+    // The csv file holds one date per line, with an optional header line.
+    pub fn from_csv_str(calendar_str: &str) -> Result<Self, ParseError> {
+        let holidays = calendar_str
+            .lines()
+            .map(|line| line.split(',').next().unwrap_or_default().trim())
+            .filter(|date_str| !date_str.is_empty())
+            .enumerate()
+            .filter(|(row, date_str)| {
+                *row > 0 || NaiveDate::parse_from_str(date_str, HOLIDAY_DATE_FORMAT).is_ok()
+            })
+            .map(|(_, date_str)| NaiveDate::parse_from_str(date_str, HOLIDAY_DATE_FORMAT))
+            .collect::<Result<BTreeSet<NaiveDate>, ParseError>>()?;
+        Ok(Self { holidays })
+    }
+
+    pub fn load_from_file(calendar_file: &Path) -> Self {
+        let calendar_str = fs::read_to_string(calendar_file).unwrap_or_else(|e| {
+            panic!(
+                "Unable to load the holiday calendar {}. {e}",
+                calendar_file.display()
+            )
+        });
+        let calendar = match calendar_file.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => Self::from_csv_str(&calendar_str).map_err(|e| e.to_string()),
+            _ => Self::from_toml_str(&calendar_str).map_err(|e| e.to_string()),
+        };
+        calendar.unwrap_or_else(|e| {
+            panic!(
+                "Unable to parse the holiday calendar {}. {e}",
+                calendar_file.display()
+            )
+        })
+    }
+
+    pub fn add_holiday(&mut self, date: NaiveDate) {
+        self.holidays.insert(date);
+    }
+
+    pub fn is_holiday(&self, date: &NaiveDate) -> bool {
+        self.holidays.contains(date)
+    }
+
+    pub fn is_business_day(&self, date: &NaiveDate) -> bool {
+        !is_weekend(date) && !self.is_holiday(date)
+    }
+
+    pub fn next_business_day(&self, date: &NaiveDate) -> NaiveDate {
+        let mut next_date = *date;
+        loop {
+            next_date = next_date
+                .succ_opt()
+                .unwrap_or_else(|| panic!("No business day after {date}"));
+            if self.is_business_day(&next_date) {
+                return next_date;
+            }
+        }
+    }
+
+    pub fn previous_business_day(&self, date: &NaiveDate) -> NaiveDate {
+        let mut previous_date = *date;
+        loop {
+            previous_date = previous_date
+                .pred_opt()
+                .unwrap_or_else(|| panic!("No business day before {date}"));
+            if self.is_business_day(&previous_date) {
+                return previous_date;
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -298,4 +595,118 @@ mod tests {
         let utc_datetime = utc_date_time(year, month, day, hour - 1, min, sec);
         assert_eq!(timezone_to_utc_date_time(&local_datetime), utc_datetime);
     }
+
+    #[tokio::test]
+    async fn test_mock_clock() {
+        let start_time = utc_date_time(2024, 5, 1, 10, 0, 0);
+        let clock = MockClock::new(start_time);
+        assert_eq!(clock.now(), start_time);
+        clock.sleep(Duration::from_secs(30)).await;
+        clock
+            .random_sleep((Duration::from_secs(10), Duration::from_secs(10)))
+            .await;
+        assert_eq!(clock.now(), utc_date_time(2024, 5, 1, 10, 0, 40));
+        assert_eq!(
+            clock.get_sleep_history(),
+            vec![Duration::from_secs(30), Duration::from_secs(10)]
+        );
+        assert_eq!(clock.total_sleep_time(), Duration::from_secs(40));
+    }
+
+    #[test]
+    fn test_holiday_calendar() {
+        let toml_calendar =
+            HolidayCalendar::from_toml_str("holidays = [\"2024-12-25\", \"2024-12-26\"]").unwrap();
+        let csv_calendar = HolidayCalendar::from_csv_str("date\n2024-12-25\n2024-12-26\n").unwrap();
+        assert_eq!(toml_calendar, csv_calendar);
+        assert!(is_weekend(&naive_date(2024, 12, 28)));
+        assert!(!toml_calendar.is_business_day(&naive_date(2024, 12, 25)));
+        assert_eq!(
+            toml_calendar.next_business_day(&naive_date(2024, 12, 24)),
+            naive_date(2024, 12, 27)
+        );
+        assert_eq!(
+            toml_calendar.previous_business_day(&naive_date(2024, 12, 30)),
+            naive_date(2024, 12, 27)
+        );
+    }
+
+    #[test]
+    fn test_align_to_interval() {
+        let date_time = utc_date_time(2024, 5, 1, 10, 7, 30);
+        let interval = Duration::from_secs(300);
+        assert_eq!(
+            floor_to_interval(&date_time, interval),
+            utc_date_time(2024, 5, 1, 10, 5, 0)
+        );
+        assert_eq!(
+            ceil_to_interval(&date_time, interval),
+            utc_date_time(2024, 5, 1, 10, 10, 0)
+        );
+        let aligned_time = utc_date_time(2024, 5, 1, 10, 10, 0);
+        assert_eq!(ceil_to_interval(&aligned_time, interval), aligned_time);
+        assert_eq!(
+            utc_to_hong_kong(&date_time).naive_local(),
+            naive_date_time(2024, 5, 1, 18, 7, 30)
+        );
+        assert_eq!(
+            utc_to_london(&date_time).naive_local(),
+            naive_date_time(2024, 5, 1, 11, 7, 30)
+        );
+    }
+
+    #[test]
+    fn test_cron_occurrence() {
+        let from = utc_date_time(2024, 5, 1, 10, 7, 30);
+        assert_eq!(
+            next_occurrence("0 */15 * * * *", &from).unwrap(),
+            Some(utc_date_time(2024, 5, 1, 10, 15, 0))
+        );
+        assert!(next_occurrence("not a cron", &from).is_err());
+        let occurrence_list = iter_occurrences(
+            "0 0 9 * * Mon-Fri",
+            utc_date_time(2024, 5, 3, 9, 0, 0)..utc_date_time(2024, 5, 8, 0, 0, 0),
+        )
+        .unwrap();
+        assert_eq!(
+            occurrence_list,
+            vec![
+                utc_date_time(2024, 5, 3, 9, 0, 0),
+                utc_date_time(2024, 5, 6, 9, 0, 0),
+                utc_date_time(2024, 5, 7, 9, 0, 0),
+            ]
+        );
+        let london_from =
+            naive_date_time_to_timezone(&naive_date_time(2024, 3, 30, 12, 0, 0), LONDON);
+        let gap_occurrence = next_occurrence("0 30 1 * * *", &london_from)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            timezone_to_utc_date_time(&gap_occurrence),
+            utc_date_time(2024, 3, 31, 1, 30, 0)
+        );
+        let overlap_from =
+            naive_date_time_to_timezone(&naive_date_time(2024, 10, 26, 12, 0, 0), LONDON);
+        let overlap_occurrence_list = iter_occurrences(
+            "0 30 1 * * *",
+            overlap_from.clone()..overlap_from + LongDuration::days(1),
+        )
+        .unwrap();
+        assert_eq!(overlap_occurrence_list.len(), 1);
+        assert_eq!(
+            timezone_to_utc_date_time(&overlap_occurrence_list[0]),
+            utc_date_time(2024, 10, 27, 0, 30, 0)
+        );
+    }
+
+    #[test]
+    fn test_is_deadline_reached() {
+        let mock_clock = MockClock::new(utc_date_time(2024, 1, 1, 0, 0, 0));
+        assert!(!is_deadline_reached(None, &mock_clock));
+        assert!(get_deadline(None, &mock_clock).is_none());
+        let deadline = get_deadline(Some(Duration::from_secs(60)), &mock_clock);
+        assert!(!is_deadline_reached(deadline, &mock_clock));
+        mock_clock.advance(Duration::from_secs(60));
+        assert!(is_deadline_reached(deadline, &mock_clock));
+    }
 }
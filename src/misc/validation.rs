@@ -0,0 +1,355 @@
+use polars::prelude::*;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::logger::ProjectLogger;
+use crate::slack_messenger::SlackMessenger;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnRule {
+    pub name: String,
+    pub dtype: Option<DataType>,
+    pub max_null_fraction: Option<f64>,
+    pub value_range: Option<(f64, f64)>,
+}
+
+impl ColumnRule {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            dtype: None,
+            max_null_fraction: None,
+            value_range: None,
+        }
+    }
+
+    pub fn set_dtype(&mut self, dtype: DataType) {
+        self.dtype = Some(dtype);
+    }
+
+    pub fn set_max_null_fraction(&mut self, max_null_fraction: f64) {
+        self.max_null_fraction = Some(max_null_fraction);
+    }
+
+    pub fn set_value_range(&mut self, min_value: f64, max_value: f64) {
+        self.value_range = Some((min_value, max_value));
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DataSchema {
+    pub columns: Vec<ColumnRule>,
+    pub unique_keys: Vec<String>,
+    pub max_row_count_change: Option<f64>,
+}
+
+impl DataSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_column(&mut self, column_rule: ColumnRule) {
+        self.columns.push(column_rule);
+    }
+
+    pub fn set_unique_keys(&mut self, unique_keys: &[&str]) {
+        self.unique_keys = unique_keys.iter().map(|key| key.to_string()).collect();
+    }
+
+    pub fn set_max_row_count_change(&mut self, max_row_count_change: f64) {
+        self.max_row_count_change = Some(max_row_count_change);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    MissingColumn(String),
+    WrongDtype {
+        column: String,
+        expected: DataType,
+        actual: DataType,
+    },
+    TooManyNulls {
+        column: String,
+        null_fraction: f64,
+        max_null_fraction: f64,
+    },
+    OutOfRange {
+        column: String,
+        num_rows: usize,
+    },
+    DuplicateKeys {
+        num_duplicates: usize,
+    },
+    RowCountChange {
+        previous: usize,
+        current: usize,
+    },
+    Polars(String),
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingColumn(column) => write!(f, "Missing column {column}"),
+            Self::WrongDtype {
+                column,
+                expected,
+                actual,
+            } => write!(f, "Column {column} is {actual} instead of {expected}"),
+            Self::TooManyNulls {
+                column,
+                null_fraction,
+                max_null_fraction,
+            } => write!(
+                f,
+                "Column {column} has {:.1}% nulls, over {:.1}%",
+                null_fraction * 100.0,
+                max_null_fraction * 100.0
+            ),
+            Self::OutOfRange { column, num_rows } => {
+                write!(f, "Column {column} has {num_rows} rows out of range")
+            }
+            Self::DuplicateKeys { num_duplicates } => {
+                write!(f, "{num_duplicates} rows have duplicated keys")
+            }
+            Self::RowCountChange { previous, current } => {
+                write!(f, "Row count changed from {previous} to {current}")
+            }
+            Self::Polars(e) => write!(f, "Unable to validate. {e}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    pub num_rows: usize,
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn message(&self, data_name: &str) -> String {
+        let issue_str: Vec<String> = self.issues.iter().map(|issue| issue.to_string()).collect();
+        format!(
+            "Validation of {data_name} with {} rows found {} issues. {}",
+            self.num_rows,
+            self.issues.len(),
+            issue_str.join("; ")
+        )
+    }
+}
+
+fn check_column(column: &Column, column_rule: &ColumnRule) -> PolarsResult<Vec<ValidationIssue>> {
+    let mut issues = Vec::new();
+    if let Some(expected) = column_rule.dtype.as_ref() {
+        if column.dtype() != expected {
+            issues.push(ValidationIssue::WrongDtype {
+                column: column_rule.name.clone(),
+                expected: expected.clone(),
+                actual: column.dtype().clone(),
+            });
+        }
+    }
+    if let Some(max_null_fraction) = column_rule.max_null_fraction {
+        let null_fraction = if column.is_empty() {
+            0.0
+        } else {
+            column.null_count() as f64 / column.len() as f64
+        };
+        if null_fraction > max_null_fraction {
+            issues.push(ValidationIssue::TooManyNulls {
+                column: column_rule.name.clone(),
+                null_fraction,
+                max_null_fraction,
+            });
+        }
+    }
+    if let Some((min_value, max_value)) = column_rule.value_range {
+        let num_rows = column
+            .cast(&DataType::Float64)?
+            .f64()?
+            .into_iter()
+            .flatten()
+            .filter(|value| *value < min_value || *value > max_value)
+            .count();
+        if num_rows > 0 {
+            issues.push(ValidationIssue::OutOfRange {
+                column: column_rule.name.clone(),
+                num_rows,
+            });
+        }
+    }
+    Ok(issues)
+}
+
+fn count_duplicate_keys(data: &DataFrame, unique_keys: &[String]) -> PolarsResult<usize> {
+    let key_columns = unique_keys
+        .iter()
+        .map(|key| data.column(key)?.cast(&DataType::String))
+        .collect::<PolarsResult<Vec<Column>>>()?;
+    let key_columns = key_columns
+        .iter()
+        .map(|column| column.str())
+        .collect::<PolarsResult<Vec<&StringChunked>>>()?;
+    let mut key_set = HashSet::with_capacity(data.height());
+    let num_duplicates = (0..data.height())
+        .filter(|row| {
+            let key: Vec<Option<&str>> =
+                key_columns.iter().map(|column| column.get(*row)).collect();
+            !key_set.insert(key)
+        })
+        .count();
+    Ok(num_duplicates)
+}
+
+pub fn validate_data_frame(
+    data: &DataFrame,
+    data_schema: &DataSchema,
+    previous_row_count: Option<usize>,
+) -> ValidationReport {
+    let mut issues = Vec::new();
+    for column_rule in data_schema.columns.iter() {
+        match data.column(&column_rule.name) {
+            Ok(column) => match check_column(column, column_rule) {
+                Ok(column_issues) => issues.extend(column_issues),
+                Err(e) => issues.push(ValidationIssue::Polars(e.to_string())),
+            },
+            Err(_) => issues.push(ValidationIssue::MissingColumn(column_rule.name.clone())),
+        }
+    }
+    let missing_keys: Vec<&String> = data_schema
+        .unique_keys
+        .iter()
+        .filter(|key| data.column(key).is_err())
+        .collect();
+    if missing_keys.is_empty() && !data_schema.unique_keys.is_empty() {
+        match count_duplicate_keys(data, &data_schema.unique_keys) {
+            Ok(0) => {}
+            Ok(num_duplicates) => issues.push(ValidationIssue::DuplicateKeys { num_duplicates }),
+            Err(e) => issues.push(ValidationIssue::Polars(e.to_string())),
+        }
+    } else {
+        issues.extend(
+            missing_keys
+                .into_iter()
+                .map(|key| ValidationIssue::MissingColumn(key.clone())),
+        );
+    }
+    if let (Some(max_row_count_change), Some(previous)) =
+        (data_schema.max_row_count_change, previous_row_count)
+    {
+        let current = data.height();
+        let change = if previous == 0 {
+            if current == 0 {
+                0.0
+            } else {
+                f64::INFINITY
+            }
+        } else {
+            (current as f64 - previous as f64).abs() / previous as f64
+        };
+        if change > max_row_count_change {
+            issues.push(ValidationIssue::RowCountChange { previous, current });
+        }
+    }
+    ValidationReport {
+        num_rows: data.height(),
+        issues,
+    }
+}
+
+#[derive(Debug)]
+pub struct DataValidator<'a> {
+    project_logger: &'a ProjectLogger,
+    slack_messenger: &'a SlackMessenger<'a>,
+}
+
+impl<'a> DataValidator<'a> {
+    pub fn new(project_logger: &'a ProjectLogger, slack_messenger: &'a SlackMessenger<'a>) -> Self {
+        Self {
+            project_logger,
+            slack_messenger,
+        }
+    }
+
+    pub fn validate(
+        &self,
+        data_name: &str,
+        data: &DataFrame,
+        data_schema: &DataSchema,
+        previous_row_count: Option<usize>,
+        calling_func: &str,
+        log_only: bool,
+    ) -> ValidationReport {
+        let validation_report = validate_data_frame(data, data_schema, previous_row_count);
+        let report_message = validation_report.message(data_name);
+        if validation_report.is_valid() {
+            self.project_logger.log_debug(&report_message);
+        } else {
+            self.project_logger.log_error(&report_message);
+            self.slack_messenger
+                .retry_send_message(calling_func, &report_message, log_only);
+        }
+        validation_report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_validate_data_frame() {
+        let data = df!(
+            "match_id" => [1, 2, 2, 4],
+            "home_odds" => [Some(1.5), Some(0.5), None, Some(2.1)],
+            "league" => ["EPL", "EPL", "EPL", "LaLiga"],
+        )
+        .unwrap();
+        let mut data_schema = DataSchema::new();
+        let mut match_id_rule = ColumnRule::new("match_id");
+        match_id_rule.set_dtype(DataType::Int64);
+        data_schema.add_column(match_id_rule);
+        let mut home_odds_rule = ColumnRule::new("home_odds");
+        home_odds_rule.set_max_null_fraction(0.1);
+        home_odds_rule.set_value_range(1.0, 100.0);
+        data_schema.add_column(home_odds_rule);
+        data_schema.add_column(ColumnRule::new("away_odds"));
+        data_schema.set_unique_keys(&["match_id", "league"]);
+        data_schema.set_max_row_count_change(0.5);
+        let validation_report = validate_data_frame(&data, &data_schema, Some(10));
+        assert_eq!(
+            validation_report.issues,
+            vec![
+                ValidationIssue::WrongDtype {
+                    column: "match_id".to_string(),
+                    expected: DataType::Int64,
+                    actual: DataType::Int32,
+                },
+                ValidationIssue::TooManyNulls {
+                    column: "home_odds".to_string(),
+                    null_fraction: 0.25,
+                    max_null_fraction: 0.1,
+                },
+                ValidationIssue::OutOfRange {
+                    column: "home_odds".to_string(),
+                    num_rows: 1,
+                },
+                ValidationIssue::MissingColumn("away_odds".to_string()),
+                ValidationIssue::DuplicateKeys { num_duplicates: 1 },
+                ValidationIssue::RowCountChange {
+                    previous: 10,
+                    current: 4,
+                },
+            ]
+        );
+        assert!(validation_report
+            .message("odds")
+            .starts_with("Validation of odds with 4 rows found 6 issues."));
+    }
+}
@@ -1,3 +1,52 @@
+pub mod api_client;
+#[cfg(feature = "scraper")]
 pub mod async_web_scraper;
+#[cfg(feature = "scraper")]
+pub mod async_web_scraper_builder;
+pub mod batch_outcome;
+#[cfg(feature = "browser")]
+pub mod browse_action;
+pub mod browser_kind;
+pub mod browser_stealth;
+pub mod checkpoint;
+pub mod content_type;
+pub mod content_version;
 pub mod data_struct;
+pub mod domain_failure_monitor;
+pub mod domain_profile;
+pub mod domain_stats;
+#[cfg(feature = "feed")]
+pub mod feed_reader;
+pub mod file_name_template;
+pub mod google_sheet;
+#[cfg(feature = "browser")]
+pub mod har_recorder;
+pub mod header_profile;
+pub mod http_transport;
+pub mod json_response;
+#[cfg(all(test, feature = "scraper"))]
+pub mod mock_server;
+#[cfg(feature = "scraper")]
+pub mod pipeline;
+pub mod proxy_endpoint;
+pub mod proxy_provider;
+pub mod response_cache;
+pub mod response_validator;
+pub mod run_manifest;
+pub mod run_report;
+#[cfg(feature = "scraper")]
+pub mod sse_consumer;
+#[cfg(feature = "s3")]
+pub mod staging_transaction;
+#[cfg(feature = "s3")]
+pub mod url_file_manifest;
+pub mod url_frontier;
+pub mod url_queue;
+pub mod warc_writer;
+pub mod web_driver_manager;
+#[cfg(feature = "browser")]
+pub mod web_driver_pool;
+#[cfg(all(feature = "slack", feature = "browser"))]
 pub mod web_scraper;
+#[cfg(feature = "websocket")]
+pub mod ws_collector;
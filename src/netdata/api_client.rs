@@ -0,0 +1,495 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, LINK, RETRY_AFTER};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::error::{Error, Result};
+use crate::logger::ProjectLogger;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiAuth {
+    None,
+    Bearer(String),
+    ApiKeyHeader {
+        header: String,
+        key: String,
+    },
+    // Client credentials grant, or refresh token grant when a refresh token is given. The token
+    // is fetched lazily and refreshed ahead of expiry or after a 401.
+    OAuth2 {
+        token_url: String,
+        client_id: String,
+        client_secret: String,
+        refresh_token: Option<String>,
+        scope: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pagination {
+    PageNumber {
+        page_param: String,
+        start_page: u32,
+    },
+    // The cursor for the next page is read from the json pointer of the response body.
+    Cursor {
+        cursor_param: String,
+        next_cursor_pointer: String,
+    },
+    LinkHeader,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+    refresh_token: Option<String>,
+}
+
+#[derive(Debug)]
+struct AccessToken {
+    token: String,
+    expires_at: Option<Instant>,
+    refresh_token: Option<String>,
+}
+
+impl AccessToken {
+    const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+    fn is_valid(&self) -> bool {
+        self.expires_at.map_or(true, |expires_at| {
+            Instant::now() + Self::EXPIRY_MARGIN < expires_at
+        })
+    }
+}
+
+// Retry-After is either a number of seconds or an http date.
+pub fn parse_retry_after(retry_after: &str) -> Option<Duration> {
+    let retry_after = retry_after.trim();
+    retry_after
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+        .or_else(|| {
+            DateTime::parse_from_rfc2822(retry_after)
+                .ok()
+                .map(|retry_at| {
+                    (retry_at.with_timezone(&Utc) - Utc::now())
+                        .to_std()
+                        .unwrap_or_default()
+                })
+        })
+}
+
+// Returns the target of rel="next" in a Link header, e.g. <https://api/x?page=2>; rel="next".
+pub fn parse_next_link(link_header: &str) -> Option<String> {
+    link_header.split(',').find_map(|link| {
+        let (target, params) = link.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("rel=")
+                    .is_some_and(|rel| rel.trim_matches('"').split(' ').any(|x| x == "next"))
+            })
+            .then(|| {
+                target
+                    .trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+fn get_item_list(page: &Value, items_pointer: &str) -> Vec<Value> {
+    match page.pointer(items_pointer) {
+        Some(Value::Array(item_list)) => item_list.clone(),
+        _ => Vec::new(),
+    }
+}
+
+fn get_next_cursor(page: &Value, next_cursor_pointer: &str) -> Option<String> {
+    match page.pointer(next_cursor_pointer)? {
+        Value::String(cursor) if !cursor.is_empty() => Some(cursor.clone()),
+        Value::Number(cursor) => Some(cursor.to_string()),
+        _ => None,
+    }
+}
+
+pub struct ApiClient<'a> {
+    base_url: Url,
+    client: Client,
+    auth: ApiAuth,
+    access_token: Mutex<Option<AccessToken>>,
+    project_logger: &'a ProjectLogger,
+    num_retry: u32,
+    retry_sleep: Duration,
+    max_retry_after: Duration,
+    max_pages: u32,
+}
+
+impl<'a> ApiClient<'a> {
+    const NUM_RETRY: u32 = 3;
+    const RETRY_SLEEP: Duration = Duration::from_secs(5);
+    const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+    const MAX_PAGES: u32 = 1000;
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    pub fn new(base_url: Url, project_logger: &'a ProjectLogger) -> Self {
+        let client = Client::builder()
+            .timeout(Self::TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            base_url,
+            client,
+            auth: ApiAuth::None,
+            access_token: Mutex::new(None),
+            project_logger,
+            num_retry: Self::NUM_RETRY,
+            retry_sleep: Self::RETRY_SLEEP,
+            max_retry_after: Self::MAX_RETRY_AFTER,
+            max_pages: Self::MAX_PAGES,
+        }
+    }
+
+    pub fn set_client(&mut self, client: Client) {
+        self.client = client;
+    }
+
+    pub fn set_auth(&mut self, auth: ApiAuth) {
+        self.auth = auth;
+        self.access_token = Mutex::new(None);
+    }
+
+    pub fn set_num_retry(&mut self, num_retry: u32) {
+        self.num_retry = num_retry;
+    }
+
+    pub fn set_retry_sleep(&mut self, retry_sleep: Duration) {
+        self.retry_sleep = retry_sleep;
+    }
+
+    pub fn set_max_retry_after(&mut self, max_retry_after: Duration) {
+        self.max_retry_after = max_retry_after;
+    }
+
+    pub fn set_max_pages(&mut self, max_pages: u32) {
+        self.max_pages = max_pages;
+    }
+
+    pub fn get_url(&self, path: &str) -> Result<Url> {
+        self.base_url.join(path).map_err(|e| {
+            Error::scrape(self.base_url.as_str(), &format!("Invalid path {path}. {e}"))
+        })
+    }
+
+    async fn fetch_access_token(&self, refresh_token: Option<String>) -> Result<AccessToken> {
+        let ApiAuth::OAuth2 {
+            token_url,
+            client_id,
+            client_secret,
+            refresh_token: initial_refresh_token,
+            scope,
+        } = &self.auth
+        else {
+            return Err(Error::scrape(
+                self.base_url.as_str(),
+                "OAuth2 is not configured.",
+            ));
+        };
+        let refresh_token = refresh_token.or_else(|| initial_refresh_token.clone());
+        let mut form = vec![
+            ("client_id", client_id.clone()),
+            ("client_secret", client_secret.clone()),
+        ];
+        match refresh_token.as_ref() {
+            Some(refresh_token) => {
+                form.push(("grant_type", "refresh_token".to_string()));
+                form.push(("refresh_token", refresh_token.clone()));
+            }
+            None => form.push(("grant_type", "client_credentials".to_string())),
+        }
+        if let Some(scope) = scope {
+            form.push(("scope", scope.clone()));
+        }
+        let response = self
+            .client
+            .post(token_url)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| Error::scrape(token_url, &format!("Unable to request token. {e}")))?;
+        if !response.status().is_success() {
+            return Err(Error::scrape(
+                token_url,
+                &format!("Token endpoint returned status {}.", response.status()),
+            ));
+        }
+        let token_response: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::scrape(token_url, &format!("Unable to parse token. {e}")))?;
+        let debug_str = format!("Access token refreshed from {token_url}.");
+        self.project_logger.log_debug(&debug_str);
+        Ok(AccessToken {
+            token: token_response.access_token,
+            expires_at: token_response
+                .expires_in
+                .map(|expires_in| Instant::now() + Duration::from_secs(expires_in)),
+            refresh_token: token_response.refresh_token.or(refresh_token),
+        })
+    }
+
+    async fn get_auth_headers(&self, force_refresh: bool) -> Result<HeaderMap> {
+        let mut header_map = HeaderMap::new();
+        let bearer = match &self.auth {
+            ApiAuth::None => None,
+            ApiAuth::Bearer(token) => Some(token.clone()),
+            ApiAuth::ApiKeyHeader { header, key } => {
+                let name = HeaderName::from_bytes(header.as_bytes()).map_err(|e| {
+                    Error::scrape(self.base_url.as_str(), &format!("Invalid header. {e}"))
+                })?;
+                let value = HeaderValue::from_str(key).map_err(|e| {
+                    Error::scrape(self.base_url.as_str(), &format!("Invalid api key. {e}"))
+                })?;
+                header_map.insert(name, value);
+                None
+            }
+            ApiAuth::OAuth2 { .. } => {
+                let mut access_token = self.access_token.lock().await;
+                let is_valid = access_token
+                    .as_ref()
+                    .is_some_and(|access_token| access_token.is_valid());
+                if force_refresh || !is_valid {
+                    let refresh_token = access_token
+                        .as_ref()
+                        .and_then(|access_token| access_token.refresh_token.clone());
+                    *access_token = Some(self.fetch_access_token(refresh_token).await?);
+                }
+                access_token
+                    .as_ref()
+                    .map(|access_token| access_token.token.clone())
+            }
+        };
+        if let Some(bearer) = bearer {
+            let value = HeaderValue::from_str(&format!("Bearer {bearer}")).map_err(|e| {
+                Error::scrape(self.base_url.as_str(), &format!("Invalid token. {e}"))
+            })?;
+            header_map.insert(AUTHORIZATION, value);
+        }
+        Ok(header_map)
+    }
+
+    // Retries on 429 and 5xx, sleeping for Retry-After when the server sends it. A 401 under
+    // OAuth2 refreshes the token once before giving up.
+    pub async fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        request_builder_func: &(dyn Fn(RequestBuilder) -> RequestBuilder + Sync),
+    ) -> Result<Response> {
+        let mut force_refresh = false;
+        let mut is_refreshed = false;
+        let mut attempt = 0;
+        loop {
+            let header_map = self.get_auth_headers(force_refresh).await?;
+            force_refresh = false;
+            let request_builder =
+                request_builder_func(self.client.request(method.clone(), url.clone()))
+                    .headers(header_map);
+            let (retry_delay, error_str) = match request_builder.send().await {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    let error_str = format!("Server returned status {status}.");
+                    if status == StatusCode::UNAUTHORIZED
+                        && matches!(self.auth, ApiAuth::OAuth2 { .. })
+                        && !is_refreshed
+                    {
+                        force_refresh = true;
+                        is_refreshed = true;
+                        continue;
+                    }
+                    if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+                        return Err(Error::scrape(url.as_str(), &error_str));
+                    }
+                    let retry_after = response
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|retry_after| retry_after.to_str().ok())
+                        .and_then(parse_retry_after)
+                        .map(|retry_after| retry_after.min(self.max_retry_after));
+                    (retry_after.unwrap_or(self.retry_sleep), error_str)
+                }
+                Err(e) => (self.retry_sleep, format!("Unable to send the request. {e}")),
+            };
+            attempt += 1;
+            if attempt > self.num_retry {
+                return Err(Error::scrape(url.as_str(), &error_str));
+            }
+            let warn_str = format!(
+                "Request {url} failed. {error_str} Retry {attempt} in {}s.",
+                retry_delay.as_secs_f64()
+            );
+            self.project_logger.log_warn(&warn_str);
+            tokio::time::sleep(retry_delay).await;
+        }
+    }
+
+    async fn send_json<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        url: &Url,
+        request_builder_func: &(dyn Fn(RequestBuilder) -> RequestBuilder + Sync),
+    ) -> Result<T> {
+        let response = self.send(method, url, request_builder_func).await?;
+        let response_text = response.text().await.map_err(|e| {
+            Error::scrape(url.as_str(), &format!("Unable to decode the response. {e}"))
+        })?;
+        let debug_str = format!("Request {url} loaded.");
+        self.project_logger.log_debug(&debug_str);
+        Ok(serde_json::from_str(&response_text)?)
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T> {
+        let url = self.get_url(path)?;
+        self.send_json(Method::GET, &url, &|request_builder| {
+            request_builder.query(query)
+        })
+        .await
+    }
+
+    pub async fn post<B: Serialize + Sync, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T> {
+        let url = self.get_url(path)?;
+        self.send_json(Method::POST, &url, &|request_builder| {
+            request_builder.json(body)
+        })
+        .await
+    }
+
+    // Collects the items under items_pointer (a json pointer such as /data) from every page
+    // until a page is empty, the next page is missing or max_pages is reached.
+    pub async fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+        pagination: &Pagination,
+        items_pointer: &str,
+    ) -> Result<Vec<T>> {
+        let mut url = self.get_url(path)?;
+        let mut page_query: Option<(String, String)> = match pagination {
+            Pagination::PageNumber {
+                page_param,
+                start_page,
+            } => Some((page_param.clone(), start_page.to_string())),
+            _ => None,
+        };
+        let mut item_list = Vec::new();
+        for num_pages in 1..=self.max_pages {
+            let response = self
+                .send(Method::GET, &url, &|request_builder| {
+                    let request_builder = request_builder.query(query);
+                    match page_query.as_ref() {
+                        Some(page_query) => request_builder.query(&[page_query]),
+                        None => request_builder,
+                    }
+                })
+                .await?;
+            let next_link = response
+                .headers()
+                .get(LINK)
+                .and_then(|link| link.to_str().ok())
+                .and_then(parse_next_link);
+            let page: Value = response.json().await.map_err(|e| {
+                Error::scrape(url.as_str(), &format!("Unable to parse the page. {e}"))
+            })?;
+            let page_item_list = get_item_list(&page, items_pointer);
+            if page_item_list.is_empty() {
+                break;
+            }
+            for item in page_item_list {
+                item_list.push(serde_json::from_value(item)?);
+            }
+            let has_next = match pagination {
+                Pagination::PageNumber { page_param, .. } => {
+                    let page_number = page_query
+                        .as_ref()
+                        .and_then(|(_, page)| page.parse::<u32>().ok())
+                        .unwrap_or_default();
+                    page_query = Some((page_param.clone(), (page_number + 1).to_string()));
+                    true
+                }
+                Pagination::Cursor {
+                    cursor_param,
+                    next_cursor_pointer,
+                } => match get_next_cursor(&page, next_cursor_pointer) {
+                    Some(cursor) => {
+                        page_query = Some((cursor_param.clone(), cursor));
+                        true
+                    }
+                    None => false,
+                },
+                Pagination::LinkHeader => match next_link.and_then(|x| url.join(&x).ok()) {
+                    Some(next_url) => {
+                        url = next_url;
+                        true
+                    }
+                    None => false,
+                },
+            };
+            if !has_next {
+                break;
+            }
+            if num_pages == self.max_pages {
+                let warn_str = format!(
+                    "Stop paginating {path} after reaching the maximum of {} pages.",
+                    self.max_pages
+                );
+                self.project_logger.log_warn(&warn_str);
+            }
+        }
+        let debug_str = format!("{} items loaded from {path}.", item_list.len());
+        self.project_logger.log_debug(&debug_str);
+        Ok(item_list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pagination_helpers() {
+        let link_header = r#"<https://api.example.com/items?page=1>; rel="prev", <https://api.example.com/items?page=3>; rel="next""#;
+        assert_eq!(
+            parse_next_link(link_header).as_deref(),
+            Some("https://api.example.com/items?page=3")
+        );
+        assert!(parse_next_link(r#"<https://api.example.com/items>; rel="last""#).is_none());
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert!(parse_retry_after("soon").is_none());
+        let page = json!({"data": [{"id": 1}, {"id": 2}], "meta": {"next": "abc"}});
+        assert_eq!(get_item_list(&page, "/data").len(), 2);
+        assert!(get_item_list(&page, "/items").is_empty());
+        assert_eq!(get_next_cursor(&page, "/meta/next").as_deref(), Some("abc"));
+        let page = json!({"data": [], "meta": {"next": null}});
+        assert!(get_next_cursor(&page, "/meta/next").is_none());
+    }
+}
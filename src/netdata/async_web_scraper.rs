@@ -1,36 +1,106 @@
+use chrono::{DateTime, Utc};
 use futures::future;
+use futures::stream::{FuturesUnordered, StreamExt};
 use itertools::Itertools;
-use polars::io::SerReader;
-use polars::prelude::{CsvReadOptions, DataFrame};
-use reqwest::{Client, Proxy, RequestBuilder, Url};
+use polars::prelude::{DataFrame, NamedFrom, PolarsResult, Series};
+use reqwest::header::{HeaderMap, LOCATION};
+use reqwest::{Client, ClientBuilder, Proxy, Request, RequestBuilder, Response, StatusCode, Url};
+#[cfg(feature = "proxy")]
 use sctys_proxy::{PrivateProxy, PrivateVpn, ScraperProxy};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
 use std::future::Future;
-use std::io::Cursor;
 use std::path::Path;
 use std::process::{Child, Command};
-use std::time::Duration;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 use thirtyfour::error::WebDriverResult;
-use thirtyfour::{CapabilitiesHelper, ChromeCapabilities, Proxy as BrowserProxy, WebDriver};
+use thirtyfour::extensions::cdp::ChromeDevTools;
+use thirtyfour::{
+    Capabilities, CapabilitiesHelper, ChromeCapabilities, EdgeCapabilities, FirefoxCapabilities,
+    Proxy as BrowserProxy, WebDriver,
+};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
 
-use super::data_struct::{BrowseSetting, RequestSetting, ResponseCheckResult, UrlFile};
+use super::batch_outcome::{BatchOutcome, UrlOutcome};
+use super::browser_kind::BrowserKind;
+use super::browser_stealth::StealthConfig;
+use super::checkpoint::UrlFileCheckpoint;
+use super::content_type::{ContentKind, ContentType, ResponseContent};
+use super::content_version::VersionManifest;
+use super::data_struct::{
+    BrowseSetting, ClientOptions, FallbackOutcome, RedirectChain, RedirectPolicy, RequestLimit,
+    RequestSetting, ResponseCheckResult, SaveMode, ScrapeFailure, ScrapeOutcome, ScrapeStatus,
+    ScrapeTier, UrlFile, UrlRequestOptions,
+};
+use super::domain_failure_monitor::DomainFailureMonitor;
+use super::domain_profile::{DomainProfile, DomainProfileRegistry};
+use super::domain_stats::{DomainStatsStore, ScrapeBackend};
+use super::google_sheet::{self, GoogleSheetKey, GoogleSheetReadOptions};
+use super::har_recorder::{self, HarRecorder};
+use super::header_profile::HeaderProfile;
+use super::http_transport::HttpTransport;
+use super::json_response::{self, JsonSaveOptions, JsonValidator};
+use super::proxy_endpoint::ProxyEndpoint;
+use super::proxy_provider::ProxyProvider;
+use super::response_cache::{CacheMode, ResponseCache};
+use super::response_validator::ResponseValidator;
+use super::run_manifest::RunManifest;
+use super::run_report::RunReport;
+use super::url_file_manifest::UrlFileManifest;
+use super::url_frontier::UrlFrontier;
+use super::warc_writer::{WarcExchange, WarcWriter};
+use super::web_driver_manager::WebDriverManager;
+use super::web_driver_pool::{WebDriverPool, WebDriverSession};
 use crate::aws_s3::AWSFileIO;
+use crate::config::ScraperConfig;
+use crate::dry_run::DryRun;
+use crate::error::ResultExt;
 use crate::file_io::FileIO;
 use crate::logger::ProjectLogger;
+use crate::run_context::RunContext;
+use crate::shared::Shared;
+use crate::shutdown::ShutdownSignal;
 use crate::slack_messenger::SlackMessenger;
+use crate::time_operation::{Clock, SystemClock};
 use crate::{function_name, time_operation};
 
 #[derive(Debug)]
 pub struct AsyncWebScraper<'a> {
-    project_logger: &'a ProjectLogger,
-    slack_messenger: &'a SlackMessenger<'a>,
-    file_io: &'a FileIO<'a>,
-    aws_file_io: &'a AWSFileIO<'a>,
-    aws_bucket: &'a str,
+    project_logger: Shared<'a, ProjectLogger>,
+    slack_messenger: Shared<'a, SlackMessenger<'a>>,
+    file_io: Shared<'a, FileIO<'a>>,
+    aws_file_io: Shared<'a, AWSFileIO<'a>>,
+    aws_bucket: String,
     num_retry: u32,
     retry_sleep: Duration,
     consecutive_sleep: (Duration, Duration),
     web_driver_port: u32,
-    chrome_process: Option<Child>,
+    browser_kind: BrowserKind,
+    chrome_process: Mutex<Option<Child>>,
+    save_mode: SaveMode,
+    shutdown_signal: Option<ShutdownSignal>,
+    clock: Arc<dyn Clock>,
+    web_driver_manager: Option<Shared<'a, WebDriverManager<'a>>>,
+    web_driver_pool: Option<Shared<'a, WebDriverPool<'a>>>,
+    pages_per_driver: usize,
+    chunk_size_request: usize,
+    chunk_size_browse: usize,
+    domain_failure_monitor: Option<Shared<'a, DomainFailureMonitor>>,
+    header_profile: Option<Shared<'a, HeaderProfile>>,
+    warc_writer: Option<Shared<'a, WarcWriter<'a>>>,
+    har_recorder: Option<Shared<'a, HarRecorder>>,
+    redirect_policy: RedirectPolicy,
+    client_options: ClientOptions,
+    domain_profile_registry: Option<Shared<'a, DomainProfileRegistry>>,
+    domain_stats_store: Option<Shared<'a, DomainStatsStore>>,
+    run_manifest: Option<Shared<'a, RunManifest>>,
+    proxy_provider: Option<Shared<'a, dyn ProxyProvider>>,
+    response_cache: Option<Shared<'a, ResponseCache>>,
+    http_transport: Option<Shared<'a, dyn HttpTransport>>,
+    connect_client: Mutex<Option<(Duration, Client)>>,
 }
 
 impl<'a> AsyncWebScraper<'a> {
@@ -40,30 +110,59 @@ impl<'a> AsyncWebScraper<'a> {
         (Duration::from_secs(0), Duration::from_secs(30));
     const CHUNK_SIZE_REQUEST: usize = 100;
     const CHUNK_SIZE_BROWSE: usize = 25;
+    const PAGES_PER_DRIVER: usize = 200;
+    const MAX_PROXY_SWITCH: u32 = 3;
+    const BLOCKED_STATUS_CODES: [StatusCode; 2] =
+        [StatusCode::FORBIDDEN, StatusCode::TOO_MANY_REQUESTS];
     const WEB_DRIVER_PORT: u32 = 4444;
     const WEB_DRIVER_PROG: &'a str = "http://localhost:";
-    const CHROME_PROCESS: &'a str = "chromedriver";
-    const GOOGLE_SHEET_URL: &'a str = "https://docs.google.com/spreadsheets/d/";
-    const GOOGLE_SHEET_REPLACE_TOKEN: (&'a str, &'a str) = ("edit#gid=", "export?format=csv&gid=");
+    pub const STATUS_COLUMN: &'a str = "status";
+    pub const ATTEMPTS_COLUMN: &'a str = "attempts";
+    pub const LATENCY_COLUMN: &'a str = "latency_ms";
+    pub const BYTES_COLUMN: &'a str = "bytes";
 
+    // The components are either borrowed, or passed as Arcs for a 'static scraper which can be
+    // shared across spawned tasks.
     pub fn new(
-        project_logger: &'a ProjectLogger,
-        slack_messenger: &'a SlackMessenger,
-        file_io: &'a FileIO,
-        aws_file_io: &'a AWSFileIO,
-        aws_bucket: &'a str,
+        project_logger: impl Into<Shared<'a, ProjectLogger>>,
+        slack_messenger: impl Into<Shared<'a, SlackMessenger<'a>>>,
+        file_io: impl Into<Shared<'a, FileIO<'a>>>,
+        aws_file_io: impl Into<Shared<'a, AWSFileIO<'a>>>,
+        aws_bucket: &str,
     ) -> Self {
         Self {
-            project_logger,
-            slack_messenger,
-            file_io,
-            aws_file_io,
-            aws_bucket,
+            project_logger: project_logger.into(),
+            slack_messenger: slack_messenger.into(),
+            file_io: file_io.into(),
+            aws_file_io: aws_file_io.into(),
+            aws_bucket: aws_bucket.to_string(),
             num_retry: Self::NUM_RETRY,
             retry_sleep: Self::RETRY_SLEEP,
             consecutive_sleep: Self::CONSECUTIVE_SLEEP,
             web_driver_port: Self::WEB_DRIVER_PORT,
-            chrome_process: None,
+            browser_kind: BrowserKind::default(),
+            chrome_process: Mutex::new(None),
+            save_mode: SaveMode::default(),
+            shutdown_signal: None,
+            clock: Arc::new(SystemClock),
+            web_driver_manager: None,
+            web_driver_pool: None,
+            pages_per_driver: Self::PAGES_PER_DRIVER,
+            chunk_size_request: Self::CHUNK_SIZE_REQUEST,
+            chunk_size_browse: Self::CHUNK_SIZE_BROWSE,
+            domain_failure_monitor: None,
+            header_profile: None,
+            warc_writer: None,
+            har_recorder: None,
+            redirect_policy: RedirectPolicy::default(),
+            client_options: ClientOptions::default(),
+            domain_profile_registry: None,
+            domain_stats_store: None,
+            run_manifest: None,
+            proxy_provider: None,
+            response_cache: None,
+            http_transport: None,
+            connect_client: Mutex::new(None),
         }
     }
 
@@ -79,47 +178,375 @@ impl<'a> AsyncWebScraper<'a> {
         self.consecutive_sleep = consecutive_sleep;
     }
 
+    pub fn apply_scraper_config(&mut self, scraper_config: &ScraperConfig) {
+        if let Some(num_retry) = scraper_config.num_retry {
+            self.num_retry = num_retry;
+        }
+        if let Some(retry_sleep) = scraper_config.retry_sleep {
+            self.retry_sleep = retry_sleep.get_duration();
+        }
+        if let Some(web_driver_port) = scraper_config.web_driver_port {
+            self.web_driver_port = web_driver_port;
+        }
+        if let Some(browser_kind) = scraper_config.browser_kind {
+            self.browser_kind = browser_kind;
+        }
+        if let Some(chunk_size_request) = scraper_config.chunk_size_request {
+            self.set_chunk_size_request(chunk_size_request);
+        }
+        if let Some(chunk_size_browse) = scraper_config.chunk_size_browse {
+            self.set_chunk_size_browse(chunk_size_browse);
+        }
+        if let Some(client_options) = scraper_config.client.as_ref() {
+            self.client_options = client_options.clone();
+        }
+    }
+
     pub fn set_web_driver_port(&mut self, web_driver_port: u32) {
         self.web_driver_port = web_driver_port;
     }
 
-    pub fn get_default_client(timeout: Duration) -> Client {
-        match Client::builder().timeout(timeout).build() {
-            Ok(client) => client,
-            Err(e) => {
-                let error_str = format!("Fail to build connection client. {e}");
-                panic!("{}", &error_str);
+    pub fn set_browser_kind(&mut self, browser_kind: BrowserKind) {
+        self.browser_kind = browser_kind;
+    }
+
+    pub fn set_save_mode(&mut self, save_mode: SaveMode) {
+        self.save_mode = save_mode;
+    }
+
+    pub fn set_shutdown_signal(&mut self, shutdown_signal: ShutdownSignal) {
+        self.shutdown_signal = Some(shutdown_signal);
+    }
+
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub fn set_web_driver_manager(
+        &mut self,
+        web_driver_manager: impl Into<Shared<'a, WebDriverManager<'a>>>,
+    ) {
+        self.web_driver_manager = Some(web_driver_manager.into());
+    }
+
+    pub fn set_web_driver_pool(
+        &mut self,
+        web_driver_pool: impl Into<Shared<'a, WebDriverPool<'a>>>,
+    ) {
+        self.web_driver_pool = Some(web_driver_pool.into());
+    }
+
+    pub fn set_pages_per_driver(&mut self, pages_per_driver: usize) {
+        self.pages_per_driver = pages_per_driver.max(1);
+    }
+
+    // The number of requests in flight at once, which bounds the response bodies held in memory.
+    pub fn set_chunk_size_request(&mut self, chunk_size_request: usize) {
+        self.chunk_size_request = chunk_size_request.max(1);
+    }
+
+    pub fn set_chunk_size_browse(&mut self, chunk_size_browse: usize) {
+        self.chunk_size_browse = chunk_size_browse.max(1);
+    }
+
+    pub fn set_domain_failure_monitor(
+        &mut self,
+        domain_failure_monitor: impl Into<Shared<'a, DomainFailureMonitor>>,
+    ) {
+        self.domain_failure_monitor = Some(domain_failure_monitor.into());
+    }
+
+    pub fn set_header_profile(&mut self, header_profile: impl Into<Shared<'a, HeaderProfile>>) {
+        self.header_profile = Some(header_profile.into());
+    }
+
+    pub fn set_warc_writer(&mut self, warc_writer: impl Into<Shared<'a, WarcWriter<'a>>>) {
+        self.warc_writer = Some(warc_writer.into());
+    }
+
+    // The network requests of each browsed page are saved as a HAR file next to its page source.
+    pub fn set_har_recorder(&mut self, har_recorder: impl Into<Shared<'a, HarRecorder>>) {
+        self.har_recorder = Some(har_recorder.into());
+    }
+
+    pub fn set_domain_profile_registry(
+        &mut self,
+        domain_profile_registry: impl Into<Shared<'a, DomainProfileRegistry>>,
+    ) {
+        self.domain_profile_registry = Some(domain_profile_registry.into());
+    }
+
+    // The outcomes of the browser fallback are recorded in the store, and the tier which has
+    // worked best for the domain is tried first.
+    pub fn set_domain_stats_store(
+        &mut self,
+        domain_stats_store: impl Into<Shared<'a, DomainStatsStore>>,
+    ) {
+        self.domain_stats_store = Some(domain_stats_store.into());
+    }
+
+    pub fn set_run_manifest(&mut self, run_manifest: impl Into<Shared<'a, RunManifest>>) {
+        self.run_manifest = Some(run_manifest.into());
+    }
+
+    // The proxies of the provider replace the free proxy list of ScraperProxy in the multiple
+    // requests and browses with proxy.
+    pub fn set_proxy_provider(&mut self, proxy_provider: impl Into<Shared<'a, dyn ProxyProvider>>) {
+        self.proxy_provider = Some(proxy_provider.into());
+    }
+
+    pub fn set_response_cache(&mut self, response_cache: impl Into<Shared<'a, ResponseCache>>) {
+        self.response_cache = Some(response_cache.into());
+    }
+
+    pub fn set_http_transport(&mut self, http_transport: impl Into<Shared<'a, dyn HttpTransport>>) {
+        self.http_transport = Some(http_transport.into());
+    }
+
+    pub fn get_domain_profile(&self, url: &Url) -> Option<&DomainProfile> {
+        self.domain_profile_registry
+            .as_deref()
+            .and_then(|domain_profile_registry| domain_profile_registry.get_profile(url))
+    }
+
+    fn get_retry_policy(&self, url: &Url, max_attempts: u32) -> (u32, Duration) {
+        let domain_profile = self.get_domain_profile(url);
+        (
+            domain_profile
+                .and_then(|domain_profile| domain_profile.num_retry)
+                .unwrap_or(max_attempts),
+            domain_profile
+                .and_then(|domain_profile| domain_profile.retry_sleep)
+                .map_or(self.retry_sleep, |retry_sleep| retry_sleep.get_duration()),
+        )
+    }
+
+    async fn wait_for_domain_rate_limit(&self, url: &Url) {
+        let delay = self
+            .domain_profile_registry
+            .as_deref()
+            .map_or(Duration::ZERO, |domain_profile_registry| {
+                domain_profile_registry.reserve_request_delay(url)
+            });
+        if !delay.is_zero() {
+            self.clock.sleep(delay).await;
+        }
+    }
+
+    pub fn set_client_options(&mut self, client_options: ClientOptions) {
+        self.client_options = client_options;
+    }
+
+    pub fn set_redirect_policy(&mut self, redirect_policy: RedirectPolicy) {
+        self.redirect_policy = redirect_policy;
+    }
+
+    // The request is only cloned when archiving is on.
+    fn get_warc_request(&self, request_builder: &RequestBuilder) -> Option<Request> {
+        self.warc_writer.as_deref()?;
+        request_builder
+            .try_clone()
+            .and_then(|request_builder| request_builder.build().ok())
+    }
+
+    fn archive_response(
+        &self,
+        warc_response: Option<(Request, StatusCode, HeaderMap)>,
+        response_body: &[u8],
+    ) {
+        if let (Some(warc_writer), Some((request, status, response_headers))) =
+            (self.warc_writer.as_deref(), warc_response)
+        {
+            warc_writer.archive(&WarcExchange {
+                url: request.url(),
+                method: request.method(),
+                request_headers: request.headers(),
+                status,
+                response_headers: &response_headers,
+                body: response_body,
+                fetched_at: self.clock.now(),
+            });
+        }
+    }
+
+    // The check function of the domain profile, if any, runs after the one of the caller.
+    fn archive_and_check(
+        &self,
+        url: &Url,
+        warc_response: Option<(Request, StatusCode, HeaderMap)>,
+        response_text: &str,
+        check_func: &dyn ResponseValidator,
+    ) -> ResponseCheckResult {
+        self.archive_response(warc_response, response_text.as_bytes());
+        let domain_check_func = self
+            .domain_profile_registry
+            .as_deref()
+            .and_then(|domain_profile_registry| domain_profile_registry.get_check_func(url));
+        match (check_func.check(response_text), domain_check_func) {
+            (ResponseCheckResult::Ok(content), Some(domain_check_func)) => {
+                domain_check_func.check(&content)
             }
+            (response_check_result, _) => response_check_result,
         }
     }
 
-    pub fn get_default_client_with_proxy(timeout: Duration, proxy: Proxy) -> Client {
-        match Client::builder().proxy(proxy).timeout(timeout).build() {
-            Ok(client) => client,
-            Err(e) => {
-                let error_str = format!("Fail to build connection client. {e}");
-                panic!("{}", &error_str);
+    fn apply_header_profile(&self, request_builder: RequestBuilder, url: &Url) -> RequestBuilder {
+        let request_builder = match self.header_profile.as_deref() {
+            Some(header_profile) => {
+                request_builder.headers(header_profile.get_headers_for_request(url))
+            }
+            None => request_builder,
+        };
+        match self.get_domain_profile(url) {
+            Some(domain_profile) => request_builder.headers(domain_profile.get_headers()),
+            None => request_builder,
+        }
+    }
+
+    fn is_domain_tripped(&self, url: &Url) -> bool {
+        self.domain_failure_monitor
+            .as_deref()
+            .map_or(false, |domain_failure_monitor| {
+                domain_failure_monitor.is_tripped(url)
+            })
+    }
+
+    // The alert goes out under the calling function of the run, to the channel the run reports to.
+    fn record_domain_outcome(&self, url: &Url, success: bool, calling_func: &str, log_only: bool) {
+        if let Some(domain_alert) = self
+            .domain_failure_monitor
+            .as_deref()
+            .and_then(|domain_failure_monitor| domain_failure_monitor.record(url, success))
+        {
+            let alert_message = domain_alert.get_message();
+            self.project_logger.log_error(&alert_message);
+            self.slack_messenger
+                .retry_send_message(calling_func, &alert_message, log_only);
+        }
+    }
+
+    fn is_shutdown_requested(&self) -> bool {
+        self.shutdown_signal
+            .as_ref()
+            .map_or(false, |shutdown_signal| shutdown_signal.is_requested())
+    }
+
+    pub fn get_default_client(timeout: Duration) -> crate::error::Result<Client> {
+        Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("Fail to build connection client")
+    }
+
+    pub fn get_default_client_with_proxy(
+        timeout: Duration,
+        proxy: Proxy,
+    ) -> crate::error::Result<Client> {
+        Client::builder()
+            .proxy(proxy)
+            .timeout(timeout)
+            .build()
+            .context("Fail to build connection client")
+    }
+
+    fn log_client_error(
+        &self,
+        client_result: reqwest::Result<Client>,
+    ) -> crate::error::Result<Client> {
+        client_result.map_err(|e| {
+            let error_str = format!("Fail to build connection client. {e}");
+            self.project_logger.log_error(&error_str);
+            e.into()
+        })
+    }
+
+    // Same as the default clients but with the client options of the scraper applied.
+    pub fn build_client(&self, timeout: Duration) -> crate::error::Result<Client> {
+        self.log_client_error(
+            self.client_options
+                .apply_to_builder(Client::builder().timeout(timeout))
+                .build(),
+        )
+    }
+
+    pub fn build_client_with_proxy(
+        &self,
+        timeout: Duration,
+        proxy: Proxy,
+    ) -> crate::error::Result<Client> {
+        self.log_client_error(
+            self.client_options
+                .apply_to_builder(Client::builder().proxy(proxy).timeout(timeout))
+                .build(),
+        )
+    }
+
+    // The scheme of the endpoint is kept, so a socks5h proxy resolves the host names remotely.
+    pub fn build_client_with_proxy_endpoint(
+        &self,
+        timeout: Duration,
+        proxy_endpoint: &ProxyEndpoint,
+    ) -> crate::error::Result<Client> {
+        let proxy = proxy_endpoint.get_reqwest_proxy().map_err(|e| {
+            let error_str = format!("Invalid proxy {}. {e}", proxy_endpoint.get_address());
+            self.project_logger.log_error(&error_str);
+            e
+        })?;
+        self.build_client_with_proxy(timeout, proxy)
+    }
+
+    // The client sending the requests with a connect timeout. The one without proxy is kept for
+    // the following requests with the same connect timeout.
+    fn get_connect_client(
+        &self,
+        connect_timeout: Duration,
+        proxy: Option<&Proxy>,
+    ) -> crate::error::Result<Client> {
+        let build_client = |client_builder: ClientBuilder| {
+            self.log_client_error(
+                client_builder
+                    .connect_timeout(connect_timeout)
+                    .redirect(self.redirect_policy.to_reqwest_policy())
+                    .build(),
+            )
+        };
+        let client_builder = self.client_options.apply_to_builder(Client::builder());
+        if let Some(proxy) = proxy {
+            return build_client(client_builder.proxy(proxy.clone()));
+        }
+        let mut connect_client = self
+            .connect_client
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match connect_client.as_ref() {
+            Some((client_connect_timeout, client))
+                if *client_connect_timeout == connect_timeout =>
+            {
+                Ok(client.clone())
+            }
+            _ => {
+                let client = build_client(client_builder)?;
+                *connect_client = Some((connect_timeout, client.clone()));
+                Ok(client)
             }
         }
     }
 
+    // An option failing to serialize is logged and left out, as the browser still runs without it.
     pub fn get_default_browser(&self) -> ChromeCapabilities {
         let mut browser = ChromeCapabilities::new();
         if let Err(e) = browser.set_headless() {
             let error_str = format!("Unable to set headless for the chrome browser, {e}");
             self.project_logger.log_error(&error_str);
-            panic!("{}", &error_str);
         };
         if let Err(e) = browser.set_disable_dev_shm_usage() {
             let error_str =
                 format!("Unable to set disable_dev_shm_usage for the chrome browser, {e}");
             self.project_logger.log_error(&error_str);
-            panic!("{}", &error_str);
         };
         if let Err(e) = browser.set_disable_gpu() {
             let error_str = format!("Unable to set disable_gpu for the chrome browser, {e}");
             self.project_logger.log_error(&error_str);
-            panic!("{}", &error_str);
         };
         for arg in [
             "--window-size=1920,1080",
@@ -130,17 +557,79 @@ impl<'a> AsyncWebScraper<'a> {
             if let Err(e) = browser.add_chrome_arg(arg) {
                 let error_str = format!("Unable to set the argument {arg}, {e}");
                 self.project_logger.log_error(&error_str);
-                panic!("{}", &error_str);
             };
         }
         browser
     }
 
+    pub fn get_default_firefox_browser(&self) -> FirefoxCapabilities {
+        let mut browser = FirefoxCapabilities::new();
+        if let Err(e) = browser.set_headless() {
+            let error_str = format!("Unable to set headless for the firefox browser, {e}");
+            self.project_logger.log_error(&error_str);
+        };
+        for arg in BrowserKind::Firefox.window_args().iter() {
+            if let Err(e) = browser.add_firefox_arg(arg) {
+                let error_str = format!("Unable to set the argument {arg}, {e}");
+                self.project_logger.log_error(&error_str);
+            };
+        }
+        browser
+    }
+
+    pub fn get_default_edge_browser(&self) -> EdgeCapabilities {
+        let mut browser = EdgeCapabilities::new();
+        if let Err(e) = browser.set_headless() {
+            let error_str = format!("Unable to set headless for the edge browser, {e}");
+            self.project_logger.log_error(&error_str);
+        };
+        for arg in BrowserKind::Edge.window_args().iter() {
+            if let Err(e) = browser.add_edge_arg(arg) {
+                let error_str = format!("Unable to set the argument {arg}, {e}");
+                self.project_logger.log_error(&error_str);
+            };
+        }
+        browser
+    }
+
+    // The default capabilities of the browser kind of the scraper, for the browse requests which
+    // take any browser.
+    pub fn get_default_capabilities(&self) -> Capabilities {
+        match self.browser_kind {
+            BrowserKind::Chrome => self.get_default_browser().into(),
+            BrowserKind::Firefox => self.get_default_firefox_browser().into(),
+            BrowserKind::Edge => self.get_default_edge_browser().into(),
+        }
+    }
+
     pub fn set_browser_proxy(
         &self,
         browser: &ChromeCapabilities,
         browser_proxy: &BrowserProxy,
     ) -> ChromeCapabilities {
+        self.with_browser_proxy(browser, browser_proxy)
+    }
+
+    pub fn set_capabilities_proxy(
+        &self,
+        browser: &Capabilities,
+        browser_proxy: &BrowserProxy,
+    ) -> Capabilities {
+        self.with_browser_proxy(browser, browser_proxy)
+    }
+
+    pub fn set_browser_proxy_endpoint(
+        &self,
+        browser: &Capabilities,
+        proxy_endpoint: &ProxyEndpoint,
+    ) -> Capabilities {
+        self.set_capabilities_proxy(browser, &proxy_endpoint.get_browser_proxy())
+    }
+
+    fn with_browser_proxy<C>(&self, browser: &C, browser_proxy: &BrowserProxy) -> C
+    where
+        C: CapabilitiesHelper + Clone,
+    {
         let mut browser_with_proxy = browser.clone();
         if let Err(e) = browser_with_proxy.set_proxy(browser_proxy.clone()) {
             let error_str = format!("Unable to set the proxy. {e}");
@@ -149,44 +638,60 @@ impl<'a> AsyncWebScraper<'a> {
         browser_with_proxy
     }
 
-    pub fn turn_on_chrome_process(&mut self) {
-        if self.chrome_process.is_none() {
+    pub fn turn_on_chrome_process(&mut self) -> crate::error::Result<()> {
+        let chrome_process = self
+            .chrome_process
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        if chrome_process.is_none() {
             let web_driver_port = format!("--port={}", self.web_driver_port);
-            match Command::new(Self::CHROME_PROCESS)
-                .arg(web_driver_port)
-                .spawn()
-            {
+            let driver_process = self.browser_kind.driver_process();
+            match Command::new(driver_process).arg(web_driver_port).spawn() {
                 Ok(c) => {
-                    self.chrome_process = Some(c);
+                    *chrome_process = Some(c);
                 }
                 Err(e) => {
-                    let error_str = format!("Unable to start chromedriver. {e}");
+                    let error_str = format!("Unable to start {driver_process}. {e}");
                     self.project_logger.log_error(&error_str);
-                    panic!("{}", &error_str);
+                    return Err(e.into());
                 }
             }
         }
+        Ok(())
+    }
+
+    pub fn kill_chrome_process(&mut self) -> crate::error::Result<()> {
+        self.kill_owned_chrome_process()
     }
 
-    pub fn kill_chrome_process(&mut self) {
-        let chrome_process = self.chrome_process.take();
+    fn kill_owned_chrome_process(&self) -> crate::error::Result<()> {
+        let chrome_process = self
+            .chrome_process
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
         if let Some(mut c) = chrome_process {
             match c.kill() {
                 Ok(()) => {
-                    let debug_str = format!("Chromedriver at port {} killed", self.web_driver_port);
+                    let debug_str = format!(
+                        "{} at port {} killed",
+                        self.browser_kind.driver_process(),
+                        self.web_driver_port
+                    );
                     self.project_logger.log_debug(&debug_str);
-                    self.chrome_process = None;
                 }
                 Err(e) => {
                     let error_str = format!(
-                        "Unable to kill chromedriver at port {}. {e}",
+                        "Unable to kill {} at port {}. {e}",
+                        self.browser_kind.driver_process(),
                         self.web_driver_port
                     );
                     self.project_logger.log_error(&error_str);
-                    panic!("{}", &error_str);
+                    return Err(e.into());
                 }
             }
         }
+        Ok(())
     }
 
     fn web_driver_path(&self) -> String {
@@ -197,33 +702,124 @@ impl<'a> AsyncWebScraper<'a> {
         )
     }
 
-    pub async fn set_web_driver(&self, browser: ChromeCapabilities) -> WebDriver {
-        let server_url = self.web_driver_path();
-        match WebDriver::new(&server_url, browser).await {
-            Ok(web_driver) => web_driver,
+    pub async fn set_web_driver(
+        &self,
+        browser: impl Into<Capabilities>,
+    ) -> crate::error::Result<WebDriver> {
+        let server_url = match self.web_driver_manager.as_deref() {
+            Some(web_driver_manager) => {
+                WebDriverManager::web_driver_path(web_driver_manager.next_healthy_port().await)
+            }
+            None => self.web_driver_path(),
+        };
+        self.connect_web_driver(&server_url, browser.into()).await
+    }
+
+    pub async fn set_web_driver_at_port(
+        &self,
+        browser: Capabilities,
+        port: u32,
+    ) -> crate::error::Result<WebDriver> {
+        self.connect_web_driver(&WebDriverManager::web_driver_path(port), browser)
+            .await
+    }
+
+    pub fn get_stealth_browser(
+        &self,
+        browser: &Capabilities,
+        stealth_config: Option<&StealthConfig>,
+    ) -> Capabilities {
+        match stealth_config {
+            Some(stealth_config) => stealth_config.mark_capabilities(browser),
+            None => browser.clone(),
+        }
+    }
+
+    async fn apply_stealth_session(&self, web_driver: &WebDriver, stealth_config: &StealthConfig) {
+        if self.browser_kind == BrowserKind::Firefox {
+            return;
+        }
+        let dev_tools = ChromeDevTools::new(web_driver.handle.clone());
+        for (command, params) in stealth_config.session_commands() {
+            if let Err(e) = dev_tools.execute_cdp_with_params(command, params).await {
+                let warn_str = format!("Unable to apply the stealth command {command}. {e}");
+                self.project_logger.log_warn(&warn_str);
+            }
+        }
+    }
+
+    async fn apply_har_session(&self, web_driver: &WebDriver) {
+        let dev_tools = ChromeDevTools::new(web_driver.handle.clone());
+        for (command, params) in har_recorder::session_commands() {
+            if let Err(e) = dev_tools.execute_cdp_with_params(command, params).await {
+                let warn_str = format!("Unable to apply the HAR command {command}. {e}");
+                self.project_logger.log_warn(&warn_str);
+            }
+        }
+    }
+
+    // A fresh fingerprint is sampled for every session created from stealth capabilities.
+    async fn connect_web_driver(
+        &self,
+        server_url: &str,
+        mut browser: Capabilities,
+    ) -> crate::error::Result<WebDriver> {
+        let stealth_config = StealthConfig::take_from_capabilities(&mut browser);
+        if let Some(stealth_config) = &stealth_config {
+            let fingerprint = stealth_config.sample_fingerprint(self.browser_kind);
+            stealth_config.apply_to_capabilities(&mut browser, self.browser_kind, &fingerprint);
+        }
+        let logging_prefs_key = self
+            .har_recorder
+            .as_deref()
+            .and(self.browser_kind.logging_prefs_key());
+        if let Some(logging_prefs_key) = logging_prefs_key {
+            browser.insert(
+                logging_prefs_key.to_string(),
+                json!({ "performance": "ALL" }),
+            );
+        }
+        match WebDriver::new(server_url, browser).await {
+            Ok(web_driver) => {
+                if let Some(stealth_config) = &stealth_config {
+                    self.apply_stealth_session(&web_driver, stealth_config)
+                        .await;
+                }
+                if logging_prefs_key.is_some() {
+                    self.apply_har_session(&web_driver).await;
+                }
+                Ok(web_driver)
+            }
             Err(e) => {
                 let error_str = format!("Unable to set the web driver. {e}");
                 self.project_logger.log_error(&error_str);
-                panic!("{}", &error_str);
+                Err(e.into())
             }
         }
     }
 
-    pub async fn close_web_driver(&self, web_driver: WebDriver) {
+    pub async fn close_web_driver(&self, web_driver: WebDriver) -> crate::error::Result<()> {
         match web_driver.quit().await {
             Ok(()) => {
                 let debug_str = "Web driver quitted.".to_string();
                 self.project_logger.log_debug(&debug_str);
+                Ok(())
             }
             Err(e) => {
                 let error_str =
                     format!("Unable to quit web driver. Please check and clear the process. {e}");
                 self.project_logger.log_error(&error_str);
-                panic! {"{}", &error_str};
+                Err(e.into())
             }
         }
     }
 
+    // The sessions closed while browsing only log a failure to quit, as the outcome of the url
+    // is already known.
+    async fn discard_web_driver(&self, web_driver: WebDriver) {
+        let _ = self.close_web_driver(web_driver).await;
+    }
+
     pub fn null_check_func(response: &str) -> ResponseCheckResult {
         ResponseCheckResult::Ok(response.to_string())
     }
@@ -232,775 +828,3402 @@ impl<'a> AsyncWebScraper<'a> {
         &self,
         url: &Url,
         request_builder_func: fn(Url) -> RequestBuilder,
-        check_func: fn(&str) -> ResponseCheckResult,
+        check_func: &dyn ResponseValidator,
     ) -> ResponseCheckResult {
-        let request_builder = request_builder_func(url.clone());
-        match request_builder.send().await {
-            Ok(response) => {
-                if response.status().is_success() || response.status().is_redirection() {
-                    match response.text().await {
-                        Ok(response_text) => match check_func(&response_text) {
-                            ResponseCheckResult::Ok(response_text) => {
-                                let debug_str = format!("Request {} loaded.", url.as_str());
-                                self.project_logger.log_debug(&debug_str);
-                                ResponseCheckResult::Ok(response_text)
-                            }
-                            ResponseCheckResult::ErrContinue(e) => {
-                                let warn_str = format!(
-                                    "Checking of the response failed for {}. {e}",
-                                    url.as_str()
-                                );
-                                self.project_logger.log_warn(&warn_str);
-                                ResponseCheckResult::ErrContinue(e)
-                            }
-                            ResponseCheckResult::ErrTerminate(e) => {
-                                let warn_str =
-                                    format!("Terminate to load the page {}. {e}", url.as_str());
-                                self.project_logger.log_warn(&warn_str);
-                                ResponseCheckResult::ErrTerminate(e)
-                            }
-                        },
-                        Err(e) => {
-                            let warn_str = format!("Unable to decode the response text. {e}");
-                            self.project_logger.log_warn(&warn_str);
-                            ResponseCheckResult::ErrContinue(e.to_string())
-                        }
-                    }
-                } else if response.status().is_server_error() {
-                    let warn_str = format!(
-                        "Fail in loading the page {}. Server return status code {}",
-                        url.as_str(),
-                        response.status().as_str()
-                    );
-                    self.project_logger.log_warn(&warn_str);
-                    ResponseCheckResult::ErrContinue(warn_str)
-                } else {
-                    let warn_str = format!(
-                        "Terminate to load the page {}. Server return status code {}",
-                        url.as_str(),
-                        response.status().as_str()
-                    );
-                    self.project_logger.log_warn(&warn_str);
-                    ResponseCheckResult::ErrTerminate(warn_str)
-                }
-            }
-            Err(e) => {
-                let warn_str = format!("Unable to load the page {}. {e}", url.as_str());
-                self.project_logger.log_warn(&warn_str);
-                ResponseCheckResult::ErrContinue(warn_str)
-            }
-        }
+        self.simple_request_with_redirect(url, request_builder_func, check_func)
+            .await
+            .0
     }
 
-    pub async fn request_with_proxy(
+    pub async fn simple_request_with_redirect(
         &self,
         url: &Url,
-        proxy: Proxy,
-        request_builder_func: fn(Proxy, Url) -> RequestBuilder,
-        check_func: fn(&str) -> ResponseCheckResult,
-    ) -> ResponseCheckResult {
-        let request_builder = request_builder_func(proxy, url.clone());
-        match request_builder.send().await {
-            Ok(response) => {
-                if response.status().is_success() || response.status().is_redirection() {
-                    match response.text().await {
-                        Ok(response_text) => match check_func(&response_text) {
-                            ResponseCheckResult::Ok(response_text) => {
-                                let debug_str = format!("Request {} loaded.", url.as_str());
-                                self.project_logger.log_debug(&debug_str);
-                                ResponseCheckResult::Ok(response_text)
-                            }
-                            ResponseCheckResult::ErrContinue(e) => {
-                                let warn_str = format!(
-                                    "Checking of the response failed for {}. {e}",
-                                    url.as_str()
-                                );
-                                self.project_logger.log_warn(&warn_str);
-                                ResponseCheckResult::ErrContinue(e)
-                            }
-                            ResponseCheckResult::ErrTerminate(e) => {
-                                let warn_str =
-                                    format!("Terminate to load the page {}. {e}", url.as_str());
-                                self.project_logger.log_warn(&warn_str);
-                                ResponseCheckResult::ErrTerminate(e)
-                            }
-                        },
-                        Err(e) => {
-                            let warn_str = format!("Unable to decode the response text. {e}");
-                            self.project_logger.log_warn(&warn_str);
-                            ResponseCheckResult::ErrContinue(e.to_string())
-                        }
-                    }
-                } else if response.status().is_server_error() {
-                    let warn_str = format!(
-                        "Fail in loading the page {}. Server return status code {}",
-                        url.as_str(),
-                        response.status().as_str()
-                    );
-                    self.project_logger.log_warn(&warn_str);
-                    ResponseCheckResult::ErrContinue(warn_str)
-                } else {
-                    let warn_str = format!(
-                        "Terminate to load the page {}. Server return status code {}",
-                        url.as_str(),
-                        response.status().as_str()
-                    );
-                    self.project_logger.log_warn(&warn_str);
-                    ResponseCheckResult::ErrTerminate(warn_str)
-                }
-            }
-            Err(e) => {
-                let warn_str = format!("Unable to load the page {}. {e}", url.as_str());
-                self.project_logger.log_warn(&warn_str);
-                ResponseCheckResult::ErrContinue(warn_str)
-            }
+        request_builder_func: fn(Url) -> RequestBuilder,
+        check_func: &dyn ResponseValidator,
+    ) -> (ResponseCheckResult, RedirectChain) {
+        if let Some((response_check_result, _)) = self.replay_response(url, check_func) {
+            return (response_check_result, RedirectChain::new(url));
         }
+        let (send_result, warc_request, redirect_chain) = self
+            .send_following_redirects(url, |url| Ok(request_builder_func(url)))
+            .await;
+        let response_check_result = self
+            .check_response(url, send_result, warc_request, check_func)
+            .await;
+        (response_check_result, redirect_chain)
     }
 
-    pub async fn save_request_content(
+    // The load failures are retried up to the number of retries, and the attempts are returned
+    // with the result.
+    async fn retry_simple_request(
         &self,
-        folder_path: &Path,
-        file: &str,
-        content: &str,
-        in_s3: bool,
-    ) {
-        if in_s3 {
-            self.aws_file_io
-                .write_string_to_file(self.aws_bucket, folder_path, file, content)
-                .await
-                .unwrap_or_else(|e| {
-                    let function_name = function_name!(true);
-                    let error_msg = format!(
-                        "Unable to save file {file} in {}. {e}",
-                        folder_path.display()
-                    );
-                    self.slack_messenger
-                        .retry_send_message(function_name, &error_msg, true);
-                    panic!("{error_msg}")
-                })
-        } else {
-            self.file_io
-                .async_write_string_to_file(folder_path, file, content)
-                .await
-                .unwrap_or_else(|e| {
-                    let function_name = function_name!(true);
-                    let error_msg = format!(
-                        "Unable to save file {file} in {}. {e}",
-                        folder_path.display()
-                    );
-                    self.slack_messenger
-                        .retry_send_message(function_name, &error_msg, true);
-                    panic!("{error_msg}")
-                })
+        url: &Url,
+        request_builder_func: fn(Url) -> RequestBuilder,
+        check_func: &dyn ResponseValidator,
+    ) -> (ResponseCheckResult, u32) {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let response = self
+                .simple_request(url, request_builder_func, check_func)
+                .await;
+            match response {
+                ResponseCheckResult::ErrContinue(_) if attempts < self.num_retry => {
+                    self.clock.sleep(self.retry_sleep).await;
+                }
+                response => return (response, attempts),
+            }
+        }
+    }
+
+    async fn request_json_content(
+        &self,
+        url: &Url,
+        request_builder_func: fn(Url) -> RequestBuilder,
+    ) -> (ResponseCheckResult, u32) {
+        self.retry_simple_request(url, request_builder_func, &Self::null_check_func)
+            .await
+    }
+
+    // The body of a json api is deserialized into T, e.g. a struct of the response or a
+    // serde_json::Value, and checked by the json validator. A body not matching T fails at once
+    // without retry.
+    pub async fn request_json<T: DeserializeOwned>(
+        &self,
+        url: &Url,
+        request_builder_func: fn(Url) -> RequestBuilder,
+        json_validator: &dyn JsonValidator<T>,
+    ) -> Result<T, ScrapeFailure> {
+        match self.request_json_content(url, request_builder_func).await.0 {
+            ResponseCheckResult::Ok(content) => json_response::parse_json(&content, json_validator),
+            ResponseCheckResult::ErrContinue(e)
+            | ResponseCheckResult::ErrTerminate(e)
+            | ResponseCheckResult::Blocked(e)
+            | ResponseCheckResult::TooLarge(e) => Err(e),
         }
     }
 
-    async fn request_and_save_content(
+    // The files are saved only once the body passes the json validator. The parquet of the
+    // records is an extra, so a failure to convert or to save it is logged while the data is
+    // still returned.
+    pub async fn request_json_and_save<T: DeserializeOwned>(
         &self,
         url_file: &UrlFile,
         request_builder_func: fn(Url) -> RequestBuilder,
         folder_path: &Path,
-        check_func: fn(&str) -> ResponseCheckResult,
+        json_validator: &dyn JsonValidator<T>,
+        json_save_options: &JsonSaveOptions,
         in_s3: bool,
-    ) -> Option<UrlFile> {
-        let mut counter = 0;
-        let mut fail = true;
-        while counter < self.num_retry && fail {
-            match self
-                .simple_request(&url_file.url, request_builder_func, check_func)
+    ) -> Result<T, ScrapeFailure> {
+        let started_at = Utc::now();
+        let (response, attempts) = self
+            .request_json_content(&url_file.url, request_builder_func)
+            .await;
+        let status = response.get_status();
+        let parse_result = match response {
+            ResponseCheckResult::Ok(content) => {
+                json_response::parse_json(&content, json_validator).map(|data| (data, content))
+            }
+            ResponseCheckResult::ErrContinue(e)
+            | ResponseCheckResult::ErrTerminate(e)
+            | ResponseCheckResult::Blocked(e)
+            | ResponseCheckResult::TooLarge(e) => Err(e),
+        };
+        let (data, content) = match parse_result {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let warn_str = format!("Unable to load the json of {}. {e}", url_file.url);
+                self.project_logger.log_warn(&warn_str);
+                let status = if status == ScrapeStatus::Success {
+                    ScrapeStatus::Terminated
+                } else {
+                    status
+                };
+                self.record_url_failure(url_file, status, attempts, started_at);
+                return Err(e);
+            }
+        };
+        if json_save_options.save_raw {
+            if let Err(e) = self
+                .save_url_content(
+                    url_file,
+                    folder_path,
+                    content.as_bytes(),
+                    &ContentType::parse("application/json"),
+                    in_s3,
+                    attempts,
+                    started_at,
+                )
                 .await
             {
-                ResponseCheckResult::Ok(content) => {
-                    self.save_request_content(folder_path, &url_file.file_name, &content, in_s3)
-                        .await;
-                    fail = false;
-                }
-                ResponseCheckResult::ErrContinue(_) => {
-                    counter += 1;
-                    time_operation::async_sleep(self.retry_sleep).await;
-                }
-                ResponseCheckResult::ErrTerminate(_) => {
-                    counter += self.num_retry;
-                }
+                self.record_url_failure(url_file, ScrapeStatus::Terminated, attempts, started_at);
+                return Err(ScrapeFailure::SaveFailed(e.to_string()));
             }
         }
-        if fail {
-            Some(url_file.clone())
-        } else {
-            None
+        if let Some(records_pointer) = &json_save_options.records_pointer {
+            self.save_json_records(url_file, folder_path, &content, records_pointer, in_s3)
+                .await;
         }
+        Ok(data)
     }
 
-    async fn request_with_proxy_and_save_content(
+    async fn save_json_records(
         &self,
         url_file: &UrlFile,
-        proxy: Proxy,
-        request_builder_func: fn(Proxy, Url) -> RequestBuilder,
         folder_path: &Path,
-        check_func: fn(&str) -> ResponseCheckResult,
+        content: &str,
+        records_pointer: &str,
         in_s3: bool,
-    ) -> Option<UrlFile> {
-        if let ResponseCheckResult::Ok(content) = self
-            .request_with_proxy(&url_file.url, proxy, request_builder_func, check_func)
+    ) {
+        let parquet_file = JsonSaveOptions::get_parquet_file(&url_file.file_name);
+        let convert_result = serde_json::from_str(content)
+            .map_err(|e| e.to_string())
+            .and_then(|json_value| {
+                json_response::convert_to_data_frame(&json_value, records_pointer)
+                    .map_err(|e| e.to_string())
+            });
+        let save_result = match convert_result {
+            Ok(mut data) if in_s3 => self
+                .aws_file_io
+                .write_parquet_file(&self.aws_bucket, folder_path, &parquet_file, &mut data)
+                .await
+                .map_err(|e| e.to_string()),
+            Ok(mut data) => self
+                .file_io
+                .write_parquet_file(folder_path, &parquet_file, &mut data)
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = save_result {
+            let warn_str = format!(
+                "Unable to save the records of {} as {parquet_file}. {e}",
+                url_file.url
+            );
+            self.project_logger.log_warn(&warn_str);
+        }
+    }
+
+    pub async fn request_with_proxy(
+        &self,
+        url: &Url,
+        proxy: Proxy,
+        request_builder_func: fn(Proxy, Url) -> RequestBuilder,
+        check_func: &dyn ResponseValidator,
+    ) -> ResponseCheckResult {
+        self.request_with_proxy_and_redirect(url, proxy, request_builder_func, check_func)
             .await
+            .0
+    }
+
+    pub async fn request_with_proxy_and_redirect(
+        &self,
+        url: &Url,
+        proxy: Proxy,
+        request_builder_func: fn(Proxy, Url) -> RequestBuilder,
+        check_func: &dyn ResponseValidator,
+    ) -> (ResponseCheckResult, RedirectChain) {
+        if let Some((response_check_result, _)) = self.replay_response(url, check_func) {
+            return (response_check_result, RedirectChain::new(url));
+        }
+        let (send_result, warc_request, redirect_chain) = self
+            .send_following_redirects(url, |url| Ok(request_builder_func(proxy.clone(), url)))
+            .await;
+        let response_check_result = self
+            .check_response(url, send_result, warc_request, check_func)
+            .await;
+        (response_check_result, redirect_chain)
+    }
+
+    // The request spec of the url file, if any, is applied to the first request only, as the
+    // redirects are followed with the plain requests of the builder. The timeout, if any,
+    // overrides the one of the client for every request of the chain, and the timeout of the url
+    // file overrides the one of the setting. With a connect timeout, the requests are sent by a
+    // client of the scraper, through the proxy if given, so the default headers and cookies of
+    // the client of the builder are not kept.
+    async fn request_url_file(
+        &self,
+        url_file: &UrlFile,
+        request_limit: RequestLimit,
+        proxy: Option<&Proxy>,
+        request_builder_func: impl Fn(Url) -> RequestBuilder,
+        check_func: &dyn ResponseValidator,
+    ) -> (ResponseCheckResult, ResponseContent) {
+        if let Some(replayed_response) = self.replay_response(&url_file.url, check_func) {
+            return replayed_response;
+        }
+        let request_limit = request_limit.for_url_file(url_file);
+        let connect_client = match request_limit
+            .connect_timeout
+            .map(|connect_timeout| self.get_connect_client(connect_timeout, proxy))
+            .transpose()
         {
-            self.save_request_content(folder_path, &url_file.file_name, &content, in_s3)
-                .await;
-            None
-        } else {
-            Some(url_file.clone())
+            Ok(connect_client) => connect_client,
+            Err(e) => {
+                let failure = ScrapeFailure::LoadFailed(e.to_string());
+                return (
+                    ResponseCheckResult::ErrTerminate(failure),
+                    ResponseContent::default(),
+                );
+            }
+        };
+        let (send_result, warc_request, _) = self
+            .send_following_redirects(&url_file.url, |url| {
+                let mut request_builder = match &url_file.request_spec {
+                    Some(request_spec) if url == url_file.url => {
+                        request_spec.apply_to_builder(request_builder_func(url.clone()), url)
+                    }
+                    _ => request_builder_func(url),
+                };
+                if let Some(timeout) = request_limit.timeout {
+                    request_builder = request_builder.timeout(timeout);
+                }
+                match &connect_client {
+                    Some(connect_client) => {
+                        let (_, request) = request_builder.build_split();
+                        Ok(RequestBuilder::from_parts(connect_client.clone(), request?))
+                    }
+                    None => Ok(request_builder),
+                }
+            })
+            .await;
+        self.check_response_content(
+            &url_file.url,
+            send_result,
+            warc_request,
+            &request_limit,
+            check_func,
+        )
+        .await
+    }
+
+    // Redirects are followed here under the redirect policy when the client does not follow
+    // them itself. If it does, only the final url of the chain is known.
+    async fn send_following_redirects(
+        &self,
+        url: &Url,
+        request_builder_func: impl Fn(Url) -> reqwest::Result<RequestBuilder>,
+    ) -> (reqwest::Result<Response>, Option<Request>, RedirectChain) {
+        let mut redirect_chain = RedirectChain::new(url);
+        self.wait_for_domain_rate_limit(url).await;
+        loop {
+            let current_url = redirect_chain.final_url.clone();
+            let request_builder = match request_builder_func(current_url.clone()) {
+                Ok(request_builder) => self.apply_header_profile(request_builder, &current_url),
+                Err(e) => return (Err(e), None, redirect_chain),
+            };
+            let warc_request = self.get_warc_request(&request_builder);
+            let send_result = match self.http_transport.as_deref() {
+                Some(http_transport) => http_transport.send(request_builder).await,
+                None => request_builder.send().await,
+            };
+            let response = match send_result {
+                Ok(response) => response,
+                Err(e) => return (Err(e), warc_request, redirect_chain),
+            };
+            if response.url() != &current_url {
+                redirect_chain.follow(response.url().clone());
+            }
+            if !response.status().is_redirection() {
+                return (Ok(response), warc_request, redirect_chain);
+            }
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| response.url().join(location).ok());
+            match location {
+                Some(location)
+                    if self.redirect_policy.allows(
+                        url,
+                        &location,
+                        redirect_chain.chain.len() + 1,
+                    ) =>
+                {
+                    let debug_str =
+                        format!("Follow redirect from {} to {location}.", response.url());
+                    self.project_logger.log_debug(&debug_str);
+                    redirect_chain.follow(location);
+                }
+                location => {
+                    redirect_chain.stopped_at = location;
+                    return (Ok(response), warc_request, redirect_chain);
+                }
+            }
         }
     }
 
-    pub async fn multiple_requests_sequential(
+    async fn check_response(
         &self,
-        url_file_list: &[UrlFile],
-        request_builder_func: fn(Url) -> RequestBuilder,
-        folder_path: &Path,
-        check_func: fn(&str) -> ResponseCheckResult,
-        request_setting: &RequestSetting<'a>,
-    ) -> Vec<UrlFile> {
-        let mut fail_list = Vec::new();
-        for url_file in tqdm::tqdm(url_file_list.iter()) {
-            if let Some(u_f) = self
-                .request_and_save_content(
-                    url_file,
-                    request_builder_func,
-                    folder_path,
+        url: &Url,
+        send_result: reqwest::Result<Response>,
+        warc_request: Option<Request>,
+        check_func: &dyn ResponseValidator,
+    ) -> ResponseCheckResult {
+        self.check_response_content(
+            url,
+            send_result,
+            warc_request,
+            &RequestLimit::default(),
+            check_func,
+        )
+        .await
+        .0
+    }
+
+    async fn check_response_content(
+        &self,
+        url: &Url,
+        send_result: reqwest::Result<Response>,
+        warc_request: Option<Request>,
+        request_limit: &RequestLimit,
+        check_func: &dyn ResponseValidator,
+    ) -> (ResponseCheckResult, ResponseContent) {
+        let response = match send_result {
+            Ok(response) => response,
+            // A request which cannot be built fails the same way on every retry.
+            Err(e) if e.is_builder() => {
+                let error_str = format!("Unable to build the request of {}. {e}", url.as_str());
+                self.project_logger.log_error(&error_str);
+                return (
+                    ResponseCheckResult::ErrTerminate(ScrapeFailure::LoadFailed(e.to_string())),
+                    ResponseContent::default(),
+                );
+            }
+            Err(e) => {
+                let warn_str = format!("Unable to load the page {}. {e}", url.as_str());
+                self.project_logger.log_warn(&warn_str);
+                return (
+                    ResponseCheckResult::ErrContinue(ScrapeFailure::from_load_error(&e)),
+                    ResponseContent::default(),
+                );
+            }
+        };
+        let content_type = ContentType::from_headers(response.headers());
+        let warc_response =
+            warc_request.map(|request| (request, response.status(), response.headers().clone()));
+        if response.status().is_success() || response.status().is_redirection() {
+            let (response_check_result, binary_body) = self
+                .check_response_body(
+                    url,
+                    response,
+                    &content_type,
+                    warc_response,
+                    request_limit,
                     check_func,
-                    request_setting.in_s3,
+                )
+                .await;
+            let response_content = ResponseContent {
+                content_type,
+                binary_body,
+            };
+            return (response_check_result, response_content);
+        }
+        let failure = ScrapeFailure::HttpStatus(response.status().as_u16());
+        let response_check_result = if response.status().is_server_error() {
+            let warn_str = format!("Fail in loading the page {}. {failure}", url.as_str());
+            self.project_logger.log_warn(&warn_str);
+            ResponseCheckResult::ErrContinue(failure)
+        } else if Self::BLOCKED_STATUS_CODES.contains(&response.status()) {
+            let warn_str = format!("Blocked when loading the page {}. {failure}", url.as_str());
+            self.project_logger.log_warn(&warn_str);
+            ResponseCheckResult::Blocked(failure)
+        } else {
+            let warn_str = format!("Terminate to load the page {}. {failure}", url.as_str());
+            self.project_logger.log_warn(&warn_str);
+            ResponseCheckResult::ErrTerminate(failure)
+        };
+        let response_content = ResponseContent {
+            content_type,
+            binary_body: None,
+        };
+        (response_check_result, response_content)
+    }
+
+    // The text is decoded with the charset of the content type, except for a body read under the
+    // max response bytes, which is taken as utf-8. The response is recorded in the response
+    // cache, if in record mode, before it is checked.
+    async fn check_response_body(
+        &self,
+        url: &Url,
+        response: Response,
+        content_type: &ContentType,
+        warc_response: Option<(Request, StatusCode, HeaderMap)>,
+        request_limit: &RequestLimit,
+        check_func: &dyn ResponseValidator,
+    ) -> (ResponseCheckResult, Option<Vec<u8>>) {
+        let status = response.status();
+        let response_body = if let Some(max_response_bytes) = request_limit.max_response_bytes {
+            match self
+                .read_limited_body(
+                    url,
+                    response,
+                    max_response_bytes,
+                    request_limit.truncate_response,
                 )
                 .await
             {
-                fail_list.push(u_f);
+                Ok(response_body) => Ok(response_body),
+                Err(response_check_result) => return (response_check_result, None),
+            }
+        } else if content_type.get_kind() == ContentKind::Binary {
+            response
+                .bytes()
+                .await
+                .map(|response_body| response_body.to_vec())
+        } else {
+            response.text().await.map(String::into_bytes)
+        };
+        let response_body = match response_body {
+            Ok(response_body) => response_body,
+            Err(e) => {
+                let warn_str = format!("Unable to decode the response body. {e}");
+                self.project_logger.log_warn(&warn_str);
+                return (
+                    ResponseCheckResult::ErrContinue(ScrapeFailure::from_decode_error(&e)),
+                    None,
+                );
+            }
+        };
+        if let Some(response_cache) = self
+            .response_cache
+            .as_deref()
+            .filter(|response_cache| response_cache.get_cache_mode() == CacheMode::Record)
+        {
+            if let Err(e) = response_cache.record(url, status, content_type, &response_body) {
+                let warn_str = format!("Unable to record the response of {}. {e}", url.as_str());
+                self.project_logger.log_warn(&warn_str);
+            }
+        }
+        self.check_loaded_body(url, content_type, response_body, warc_response, check_func)
+    }
+
+    // The body is read chunk by chunk, so a huge error page is dropped once over the limit
+    // instead of being loaded whole. The content length, if given, fails the response before
+    // any chunk is read.
+    async fn read_limited_body(
+        &self,
+        url: &Url,
+        mut response: Response,
+        max_response_bytes: u64,
+        truncate: bool,
+    ) -> std::result::Result<Vec<u8>, ResponseCheckResult> {
+        let too_large = |num_bytes: u64| {
+            let failure = ScrapeFailure::TooLarge {
+                num_bytes,
+                max_bytes: max_response_bytes,
             };
-            time_operation::async_random_sleep(self.consecutive_sleep).await;
+            let warn_str = format!("Response of {} is too large. {failure}", url.as_str());
+            self.project_logger.log_warn(&warn_str);
+            ResponseCheckResult::TooLarge(failure)
+        };
+        if let Some(content_length) = response
+            .content_length()
+            .filter(|content_length| !truncate && *content_length > max_response_bytes)
+        {
+            return Err(too_large(content_length));
         }
-        if !fail_list.is_empty() {
-            let fail_url_list = format!(
-                "The following urls were not loaded successfully:\n\n {}",
-                fail_list
-                    .iter()
-                    .map(|x| x.url.as_str())
-                    .collect::<Vec<&str>>()
-                    .join("\n")
-            );
-            self.project_logger.log_error(&fail_url_list);
-            let fail_url_message = format!(
-                "The urls starting with {:?} has {} out of {} fail urls.",
-                fail_list.first(),
-                fail_list.len(),
-                url_file_list.len()
-            );
-            self.slack_messenger.retry_send_message(
-                request_setting.calling_func,
-                &fail_url_message,
-                request_setting.log_only,
+        let max_len = usize::try_from(max_response_bytes).unwrap_or(usize::MAX);
+        let mut response_body = Vec::new();
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    response_body.extend_from_slice(&chunk);
+                    if response_body.len() > max_len {
+                        if !truncate {
+                            return Err(too_large(response_body.len() as u64));
+                        }
+                        response_body.truncate(max_len);
+                        let warn_str = format!(
+                            "Response of {} truncated at {max_response_bytes} bytes.",
+                            url.as_str()
+                        );
+                        self.project_logger.log_warn(&warn_str);
+                        return Ok(response_body);
+                    }
+                }
+                Ok(None) => return Ok(response_body),
+                Err(e) => {
+                    let warn_str = format!("Unable to read the response body. {e}");
+                    self.project_logger.log_warn(&warn_str);
+                    return Err(ResponseCheckResult::ErrContinue(
+                        ScrapeFailure::from_decode_error(&e),
+                    ));
+                }
+            }
+        }
+    }
+
+    // The json is parsed before the check function runs. The binary bodies, e.g. images, pdfs and
+    // zips, are returned verbatim and skip the check function, so the checked content is empty.
+    fn check_loaded_body(
+        &self,
+        url: &Url,
+        content_type: &ContentType,
+        response_body: Vec<u8>,
+        warc_response: Option<(Request, StatusCode, HeaderMap)>,
+        check_func: &dyn ResponseValidator,
+    ) -> (ResponseCheckResult, Option<Vec<u8>>) {
+        let content_kind = content_type.get_kind();
+        if content_kind == ContentKind::Binary {
+            self.archive_response(warc_response, &response_body);
+            let debug_str = format!(
+                "Request {} loaded as {}.",
+                url.as_str(),
+                content_type.mime_type.as_deref().unwrap_or_default()
             );
+            self.project_logger.log_debug(&debug_str);
+            return (ResponseCheckResult::Ok(String::new()), Some(response_body));
+        }
+        let response_text = String::from_utf8(response_body)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned());
+        if content_kind == ContentKind::Json {
+            if let Err(e) = serde_json::from_str::<serde_json::Value>(&response_text) {
+                self.archive_response(warc_response, response_text.as_bytes());
+                let warn_str = format!(
+                    "Checking of the response failed for {}. Invalid json. {e}",
+                    url.as_str()
+                );
+                self.project_logger.log_warn(&warn_str);
+                return (
+                    ResponseCheckResult::ErrContinue(ScrapeFailure::DecodeError(format!(
+                        "Invalid json. {e}"
+                    ))),
+                    None,
+                );
+            }
         }
-        fail_list
+        let response_check_result =
+            self.archive_and_check(url, warc_response, &response_text, check_func);
+        (self.log_check_result(url, response_check_result), None)
     }
 
-    pub async fn multiple_requests_with_proxy(
+    // In replay mode the response is served from the response cache, and the url not recorded is
+    // terminated, so the network is never hit. None if not in replay mode.
+    fn replay_response(
         &self,
-        url_file_list: &Vec<UrlFile>,
-        request_builder_func: fn(Proxy, Url) -> RequestBuilder,
-        folder_path: &Path,
-        check_func: fn(&str) -> ResponseCheckResult,
-        request_setting: &RequestSetting<'a>,
-    ) -> Vec<UrlFile> {
-        let mut counter = 0;
-        let mut pending_url_file_list = url_file_list.to_owned();
-        while counter < self.num_retry && !pending_url_file_list.is_empty() {
-            let mut proxy_list = ScraperProxy::generate_proxy().await;
-            let mut fail_list = Vec::new();
-            for chunk in pending_url_file_list
-                .iter()
-                .chunks(Self::CHUNK_SIZE_REQUEST)
-                .into_iter()
-            {
-                let proxy_iter =
-                    ScraperProxy::sample_proxy(&mut proxy_list, Self::CHUNK_SIZE_REQUEST);
-                let request_tasks = proxy_iter.zip(chunk).map(|(proxy_pair, url_file)| {
-                    self.request_with_proxy_and_save_content(
-                        url_file,
-                        proxy_pair.proxy.clone(),
-                        request_builder_func,
-                        folder_path,
-                        check_func,
-                        request_setting.in_s3,
-                    )
-                });
-                let request_futures = future::join_all(request_tasks).await;
-                fail_list.extend(request_futures.into_iter().flatten());
+        url: &Url,
+        check_func: &dyn ResponseValidator,
+    ) -> Option<(ResponseCheckResult, ResponseContent)> {
+        let response_cache = self
+            .response_cache
+            .as_deref()
+            .filter(|response_cache| response_cache.get_cache_mode() == CacheMode::Replay)?;
+        match response_cache.replay(url) {
+            Some((cached_response, response_body)) => {
+                let content_type = cached_response.get_content_type();
+                let (response_check_result, binary_body) =
+                    self.check_loaded_body(url, &content_type, response_body, None, check_func);
+                let response_content = ResponseContent {
+                    content_type,
+                    binary_body,
+                };
+                Some((response_check_result, response_content))
+            }
+            None => {
+                let warn_str = format!("No recorded response of {} to replay.", url.as_str());
+                self.project_logger.log_warn(&warn_str);
+                Some((
+                    ResponseCheckResult::ErrTerminate(ScrapeFailure::LoadFailed(warn_str)),
+                    ResponseContent::default(),
+                ))
             }
-            pending_url_file_list = fail_list;
-            counter += 1;
         }
-        if !pending_url_file_list.is_empty() {
-            let fail_url_list = format!(
-                "The following urls were not loaded successfully:\n\n {}",
-                pending_url_file_list
-                    .iter()
-                    .map(|x| x.url.as_str())
-                    .collect::<Vec<&str>>()
-                    .join("\n")
-            );
-            self.project_logger.log_error(&fail_url_list);
-            let fail_url_message = format!(
-                "The urls starting with {:?} has {} out of {} fail urls.",
-                pending_url_file_list.first(),
-                pending_url_file_list.len(),
-                url_file_list.len()
-            );
-            self.slack_messenger.retry_send_message(
-                request_setting.calling_func,
-                &fail_url_message,
-                request_setting.log_only,
-            );
+    }
+
+    fn log_check_result(
+        &self,
+        url: &Url,
+        response_check_result: ResponseCheckResult,
+    ) -> ResponseCheckResult {
+        match response_check_result {
+            ResponseCheckResult::Ok(response_text) => {
+                let debug_str = format!("Request {} loaded.", url.as_str());
+                self.project_logger.log_debug(&debug_str);
+                ResponseCheckResult::Ok(response_text)
+            }
+            ResponseCheckResult::ErrContinue(e) => {
+                let warn_str = format!("Checking of the response failed for {}. {e}", url.as_str());
+                self.project_logger.log_warn(&warn_str);
+                ResponseCheckResult::ErrContinue(e)
+            }
+            ResponseCheckResult::ErrTerminate(e) => {
+                let warn_str = format!("Terminate to load the page {}. {e}", url.as_str());
+                self.project_logger.log_warn(&warn_str);
+                ResponseCheckResult::ErrTerminate(e)
+            }
+            ResponseCheckResult::Blocked(e) => {
+                let warn_str = format!("Blocked when loading the page {}. {e}", url.as_str());
+                self.project_logger.log_warn(&warn_str);
+                ResponseCheckResult::Blocked(e)
+            }
+            ResponseCheckResult::TooLarge(e) => {
+                let warn_str = format!("Terminate to load the page {}. {e}", url.as_str());
+                self.project_logger.log_warn(&warn_str);
+                ResponseCheckResult::TooLarge(e)
+            }
         }
-        pending_url_file_list
     }
 
-    pub async fn multiple_requests_with_private_proxy(
+    async fn write_request_content(
         &self,
-        url_file_list: &[UrlFile],
-        private_proxy: &mut PrivateProxy,
-        request_builder_func: fn(Proxy, Url) -> RequestBuilder,
         folder_path: &Path,
-        check_func: fn(&str) -> ResponseCheckResult,
-        request_setting: &RequestSetting<'a>,
-    ) -> Vec<UrlFile> {
-        let mut fail_list = Vec::new();
-        for url_file in tqdm::tqdm(url_file_list.iter()) {
-            if let Some(proxy) = private_proxy.generate_proxy() {
-                if let Some(u_f) = self
-                    .request_with_proxy_and_save_content(
-                        url_file,
-                        proxy.clone(),
-                        request_builder_func,
-                        folder_path,
-                        check_func,
-                        request_setting.in_s3,
-                    )
-                    .await
-                {
-                    fail_list.push(u_f);
-                };
-                time_operation::async_random_sleep(self.consecutive_sleep).await;
+        file: &str,
+        content: &[u8],
+        in_s3: bool,
+    ) -> crate::error::Result<()> {
+        let save_context = || format!("Unable to save file {file} in {}", folder_path.display());
+        let write_result = if in_s3 {
+            self.aws_file_io
+                .write_bytes_to_file(&self.aws_bucket, folder_path, file, content)
+                .await
+                .with_context(save_context)
+        } else {
+            match self.file_io.create_parent_folder(folder_path, file) {
+                Ok(()) => {
+                    self.file_io
+                        .async_write_bytes_to_file(folder_path, file, content)
+                        .await
+                }
+                Err(e) => Err(e),
+            }
+            .with_context(save_context)
+        };
+        write_result.map_err(|e| {
+            let function_name = function_name!(true);
+            self.slack_messenger
+                .retry_send_message(function_name, &e.to_string(), true);
+            e
+        })
+    }
+
+    async fn load_version_manifest(
+        &self,
+        folder_path: &Path,
+        manifest_file: &str,
+        in_s3: bool,
+    ) -> VersionManifest {
+        let manifest_str = if in_s3 {
+            if self
+                .aws_file_io
+                .check_file_exist(&self.aws_bucket, folder_path, manifest_file)
+                .await
+            {
+                self.aws_file_io
+                    .load_file_as_string(&self.aws_bucket, folder_path, manifest_file)
+                    .await
+                    .ok()
+            } else {
+                None
+            }
+        } else if FileIO::check_file_exist(folder_path, manifest_file) {
+            self.file_io
+                .load_file_as_string(folder_path, manifest_file)
+                .ok()
+        } else {
+            None
+        };
+        manifest_str.map_or_else(VersionManifest::default, |manifest_str| {
+            VersionManifest::from_toml_str(&manifest_str).unwrap_or_else(|e| {
+                let warn_str = format!(
+                    "Unable to parse the version manifest {manifest_file} in {}. Start a new manifest. {e}",
+                    folder_path.display()
+                );
+                self.project_logger.log_warn(&warn_str);
+                VersionManifest::default()
+            })
+        })
+    }
+
+    async fn save_versioned_content(
+        &self,
+        folder_path: &Path,
+        file: &str,
+        content: &[u8],
+        in_s3: bool,
+    ) -> crate::error::Result<Option<String>> {
+        let manifest_file = VersionManifest::manifest_file_name(file);
+        let mut manifest = self
+            .load_version_manifest(folder_path, &manifest_file, in_s3)
+            .await;
+        let content_hash = VersionManifest::content_hash(content);
+        if manifest.is_unchanged(&content_hash) {
+            let debug_str = format!(
+                "Content of {file} in {} unchanged. Skip saving.",
+                folder_path.display()
+            );
+            self.project_logger.log_debug(&debug_str);
+            return Ok(None);
+        }
+        let saved_at = Utc::now();
+        let saved_file = match self.save_mode {
+            SaveMode::VersionOnChange => VersionManifest::versioned_file_name(file, &saved_at),
+            _ => file.to_string(),
+        };
+        self.write_request_content(folder_path, &saved_file, content, in_s3)
+            .await?;
+        manifest.add_version(&saved_file, &content_hash, saved_at);
+        match manifest.to_toml_string() {
+            Ok(manifest_str) => {
+                self.write_request_content(
+                    folder_path,
+                    &manifest_file,
+                    manifest_str.as_bytes(),
+                    in_s3,
+                )
+                .await?
+            }
+            Err(e) => {
+                let warn_str = format!(
+                    "Unable to serialize the version manifest {manifest_file} in {}. {e}",
+                    folder_path.display()
+                );
+                self.project_logger.log_warn(&warn_str);
+            }
+        }
+        Ok(Some(saved_file))
+    }
+
+    // Returns the name of the file written, which differs from the file under VersionOnChange,
+    // or None if the content is unchanged and nothing is written.
+    pub async fn save_request_content(
+        &self,
+        folder_path: &Path,
+        file: &str,
+        content: &str,
+        in_s3: bool,
+    ) -> crate::error::Result<Option<String>> {
+        self.save_request_bytes(folder_path, file, content.as_bytes(), in_s3)
+            .await
+    }
+
+    // As save_request_content, for the binary content saved verbatim. The file name is
+    // sanitized, as it is often taken from the url, and its sub folders are created if any.
+    pub async fn save_request_bytes(
+        &self,
+        folder_path: &Path,
+        file: &str,
+        content: &[u8],
+        in_s3: bool,
+    ) -> crate::error::Result<Option<String>> {
+        let file = &FileIO::sanitize_file_path(file);
+        match self.save_mode {
+            SaveMode::Overwrite => {
+                self.write_request_content(folder_path, file, content, in_s3)
+                    .await?;
+                Ok(Some(file.to_string()))
+            }
+            SaveMode::SkipUnchanged | SaveMode::VersionOnChange => {
+                self.save_versioned_content(folder_path, file, content, in_s3)
+                    .await
+            }
+        }
+    }
+
+    // Saves the content of a scraped url and records it in the run manifest, if one is set. The
+    // extension of the content type is added to the file name if it has none.
+    #[allow(clippy::too_many_arguments)]
+    async fn save_url_content(
+        &self,
+        url_file: &UrlFile,
+        folder_path: &Path,
+        content: &[u8],
+        content_type: &ContentType,
+        in_s3: bool,
+        attempts: u32,
+        started_at: DateTime<Utc>,
+    ) -> crate::error::Result<()> {
+        let file_name = content_type.get_file_name(&url_file.file_name);
+        let saved_file = self
+            .save_request_bytes(folder_path, &file_name, content, in_s3)
+            .await?;
+        if let Some(har) = self
+            .har_recorder
+            .as_deref()
+            .and_then(|har_recorder| har_recorder.take(&url_file.url))
+        {
+            let har_file = HarRecorder::get_har_file(&file_name);
+            self.write_request_content(folder_path, &har_file, har.to_string().as_bytes(), in_s3)
+                .await?;
+        }
+        if let Some(run_manifest) = self.run_manifest.as_deref() {
+            run_manifest.record(
+                url_file,
+                ScrapeStatus::Success,
+                attempts,
+                started_at,
+                saved_file.map(|saved_file| folder_path.join(saved_file).display().to_string()),
+                Some(content.len() as u64),
+                Some(VersionManifest::content_hash(content)),
+            );
+        }
+        Ok(())
+    }
+
+    fn record_url_failure(
+        &self,
+        url_file: &UrlFile,
+        status: ScrapeStatus,
+        attempts: u32,
+        started_at: DateTime<Utc>,
+    ) {
+        if let Some(har_recorder) = self.har_recorder.as_deref() {
+            har_recorder.take(&url_file.url);
+        }
+        if let Some(run_manifest) = self.run_manifest.as_deref() {
+            run_manifest.record(url_file, status, attempts, started_at, None, None, None);
+        }
+    }
+
+    #[tracing::instrument(name = "scrape_url", skip_all, fields(url = %url_file.url, tier = "http", status, retries, duration_ms))]
+    async fn request_and_save_content_with_outcome(
+        &self,
+        url_file: &UrlFile,
+        request_builder_func: fn(Url) -> RequestBuilder,
+        folder_path: &Path,
+        check_func: &dyn ResponseValidator,
+        url_request_options: UrlRequestOptions<'a>,
+    ) -> UrlOutcome {
+        let UrlRequestOptions {
+            in_s3,
+            request_limit,
+            max_attempts,
+            calling_func,
+            log_only,
+            record_domain_outcome,
+        } = url_request_options;
+        let start_time = Instant::now();
+        let started_at = Utc::now();
+        if self.is_domain_tripped(&url_file.url) {
+            self.record_url_failure(url_file, ScrapeStatus::Tripped, 0, started_at);
+            return UrlOutcome::skipped(url_file, ScrapeStatus::Tripped);
+        }
+        let (max_attempts, retry_sleep) = self.get_retry_policy(&url_file.url, max_attempts);
+        let mut counter = 0;
+        let mut attempts = 0;
+        let mut status = ScrapeStatus::Failed;
+        let mut latency = None;
+        let mut bytes = None;
+        let mut error = None;
+        while counter < max_attempts && status == ScrapeStatus::Failed {
+            attempts += 1;
+            let attempt_time = Instant::now();
+            let (response, response_content) = self
+                .request_url_file(
+                    url_file,
+                    request_limit,
+                    None,
+                    request_builder_func,
+                    check_func,
+                )
+                .await;
+            latency = Some(attempt_time.elapsed());
+            error = response.get_error();
+            match response {
+                ResponseCheckResult::Ok(content) => {
+                    let content = response_content.get_body(&content);
+                    let save_result = self
+                        .save_url_content(
+                            url_file,
+                            folder_path,
+                            content,
+                            &response_content.content_type,
+                            in_s3,
+                            attempts,
+                            started_at,
+                        )
+                        .await;
+                    match save_result {
+                        Ok(()) => {
+                            bytes = Some(content.len() as u64);
+                            status = ScrapeStatus::Success;
+                        }
+                        // The content is not requested again when the storage fails.
+                        Err(e) => {
+                            error = Some(e.to_string());
+                            status = ScrapeStatus::Terminated;
+                        }
+                    }
+                }
+                // The retries stop once the domain is tripped by the other urls of the batch,
+                // so the retry budget is not spent on a site which is down.
+                ResponseCheckResult::ErrContinue(_) if self.is_domain_tripped(&url_file.url) => {
+                    status = ScrapeStatus::Tripped;
+                }
+                ResponseCheckResult::ErrContinue(_) => {
+                    counter += 1;
+                    self.clock.sleep(retry_sleep).await;
+                }
+                ResponseCheckResult::ErrTerminate(_) | ResponseCheckResult::TooLarge(_) => {
+                    counter += max_attempts;
+                    status = ScrapeStatus::Terminated;
+                }
+                ResponseCheckResult::Blocked(_) => {
+                    counter += max_attempts;
+                    status = ScrapeStatus::Blocked;
+                }
+            }
+        }
+        if status != ScrapeStatus::Success {
+            self.record_url_failure(url_file, status, attempts, started_at);
+        }
+        if record_domain_outcome && status != ScrapeStatus::Tripped {
+            self.record_domain_outcome(
+                &url_file.url,
+                status == ScrapeStatus::Success,
+                calling_func,
+                log_only,
+            );
+        }
+        Self::record_scrape_span(&status, attempts, start_time);
+        let outcome = ScrapeOutcome {
+            status,
+            attempts,
+            latency,
+            bytes,
+        };
+        UrlOutcome::new(url_file, outcome, error)
+    }
+
+    #[tracing::instrument(name = "scrape_url", skip_all, fields(url = %url_file.url, tier = "proxy", status, retries, duration_ms))]
+    async fn request_with_proxy_and_save_content(
+        &self,
+        url_file: &UrlFile,
+        proxy: Proxy,
+        request_builder_func: fn(Proxy, Url) -> RequestBuilder,
+        folder_path: &Path,
+        check_func: &dyn ResponseValidator,
+        url_request_options: UrlRequestOptions<'a>,
+    ) -> UrlOutcome {
+        let UrlRequestOptions {
+            in_s3,
+            request_limit,
+            calling_func,
+            log_only,
+            record_domain_outcome,
+            ..
+        } = url_request_options;
+        let started_at = Utc::now();
+        if self.is_domain_tripped(&url_file.url) {
+            self.record_url_failure(url_file, ScrapeStatus::Tripped, 0, started_at);
+            return UrlOutcome::skipped(url_file, ScrapeStatus::Tripped);
+        }
+        let start_time = Instant::now();
+        let (response, response_content) = self
+            .request_url_file(
+                url_file,
+                request_limit,
+                Some(&proxy),
+                |url| request_builder_func(proxy.clone(), url),
+                check_func,
+            )
+            .await;
+        let latency = Some(start_time.elapsed());
+        let mut status = response.get_status();
+        let mut error = response.get_error();
+        let mut bytes = None;
+        if let ResponseCheckResult::Ok(content) = response {
+            let content = response_content.get_body(&content);
+            let save_result = self
+                .save_url_content(
+                    url_file,
+                    folder_path,
+                    content,
+                    &response_content.content_type,
+                    in_s3,
+                    1,
+                    started_at,
+                )
+                .await;
+            match save_result {
+                Ok(()) => bytes = Some(content.len() as u64),
+                Err(e) => {
+                    error = Some(e.to_string());
+                    status = ScrapeStatus::Terminated;
+                }
+            }
+        }
+        if status != ScrapeStatus::Success {
+            self.record_url_failure(url_file, status, 1, started_at);
+        }
+        if record_domain_outcome {
+            self.record_domain_outcome(
+                &url_file.url,
+                status == ScrapeStatus::Success,
+                calling_func,
+                log_only,
+            );
+        }
+        Self::record_scrape_span(&status, 1, start_time);
+        let outcome = ScrapeOutcome {
+            status,
+            attempts: 1,
+            latency,
+            bytes,
+        };
+        UrlOutcome::new(url_file, outcome, error)
+    }
+
+    fn record_scrape_span(status: &ScrapeStatus, attempts: u32, start_time: Instant) {
+        let span = tracing::Span::current();
+        span.record("status", status.as_str());
+        span.record("retries", attempts.saturating_sub(1));
+        span.record("duration_ms", start_time.elapsed().as_millis() as u64);
+    }
+
+    fn fail_url_message(fail_list: &[UrlFile], num_blocked: usize, num_total: usize) -> String {
+        let mut fail_url_message = format!(
+            "The urls starting with {:?} has {} out of {num_total} fail urls, of which {num_blocked} were blocked.",
+            fail_list.first().map(|url_file| url_file.url.as_str()),
+            fail_list.len()
+        );
+        if fail_list.iter().any(|url_file| url_file.category.is_some()) {
+            let mut category_count: BTreeMap<&str, usize> = BTreeMap::new();
+            for url_file in fail_list {
+                *category_count.entry(url_file.get_category()).or_default() += 1;
+            }
+            fail_url_message.push_str(&format!(" Fail urls by category: {category_count:?}."));
+        }
+        fail_url_message
+    }
+
+    pub async fn multiple_requests_sequential(
+        &self,
+        url_file_list: &[UrlFile],
+        request_builder_func: fn(Url) -> RequestBuilder,
+        folder_path: &Path,
+        check_func: &dyn ResponseValidator,
+        request_setting: &RequestSetting<'a>,
+    ) -> BatchOutcome {
+        DryRun::scope(request_setting.dry_run, async {
+            let start_time = Instant::now();
+            let deadline = request_setting.get_deadline(self.clock.as_ref());
+            let mut batch_outcome = BatchOutcome::new();
+            let mut halted_list = Vec::new();
+            for (index, url_file) in tqdm::tqdm(url_file_list.iter().enumerate()) {
+                if self.is_shutdown_requested()
+                    || time_operation::is_deadline_reached(deadline, self.clock.as_ref())
+                {
+                    halted_list = url_file_list[index..].to_vec();
+                    break;
+                }
+                batch_outcome.add(
+                    self.request_and_save_content_with_outcome(
+                        url_file,
+                        request_builder_func,
+                        folder_path,
+                        check_func,
+                        request_setting.get_url_request_options(self.num_retry),
+                    )
+                    .await,
+                );
+                self.clock
+                    .random_sleep(request_setting.get_sleep_range(self.consecutive_sleep))
+                    .await;
+            }
+            let fail_list = batch_outcome.get_fail_list();
+            if !fail_list.is_empty() {
+                let fail_url_list = format!(
+                    "The following urls were not loaded successfully:\n\n {}",
+                    fail_list
+                        .iter()
+                        .map(|x| x.url.as_str())
+                        .collect::<Vec<&str>>()
+                        .join("\n")
+                );
+                self.project_logger.log_error(&fail_url_list);
+                let fail_url_message = Self::fail_url_message(
+                    &fail_list,
+                    batch_outcome.num_blocked(),
+                    url_file_list.len(),
+                );
+                self.slack_messenger.retry_send_message(
+                    request_setting.calling_func,
+                    &fail_url_message,
+                    request_setting.log_only,
+                );
+            }
+            self.notify_deadline_reached(
+                &halted_list,
+                url_file_list.len(),
+                request_setting.max_total_duration,
+                request_setting.calling_func,
+                request_setting.log_only,
+            );
+            batch_outcome.add_halted(&halted_list);
+            if self.is_shutdown_requested() {
+                self.shutdown_with_pending_list(
+                    &batch_outcome.get_fail_list(),
+                    folder_path,
+                    request_setting.in_s3,
+                    request_setting.calling_func,
+                    request_setting.log_only,
+                )
+                .await;
+            }
+            batch_outcome.duration = start_time.elapsed();
+            batch_outcome
+        })
+        .await
+    }
+
+    // The frontier is drained in batches so urls pushed between batches, e.g. detail pages found
+    // on listing pages, are picked up by priority. Failed urls are not re-queued.
+    pub async fn multiple_requests_from_frontier(
+        &self,
+        url_frontier: &mut UrlFrontier<'_>,
+        batch_size: usize,
+        request_builder_func: fn(Url) -> RequestBuilder,
+        folder_path: &Path,
+        check_func: &dyn ResponseValidator,
+        request_setting: &RequestSetting<'a>,
+    ) -> BatchOutcome {
+        let mut batch_outcome = BatchOutcome::new();
+        while !url_frontier.is_empty() && !self.is_shutdown_requested() {
+            let url_file_list = url_frontier.pop_batch(batch_size.max(1));
+            batch_outcome.extend(
+                self.multiple_requests_sequential(
+                    &url_file_list,
+                    request_builder_func,
+                    folder_path,
+                    check_func,
+                    request_setting,
+                )
+                .await,
+            );
+        }
+        if let Err(e) = url_frontier.save_seen() {
+            let warn_str = format!("Unable to save the seen-set of the frontier. {e}");
+            self.project_logger.log_warn(&warn_str);
+        }
+        batch_outcome
+    }
+
+    async fn fetch_provider_proxy(&self, proxy_provider: &dyn ProxyProvider) -> Vec<ProxyEndpoint> {
+        let proxy_list = proxy_provider.fetch_proxy_list().await;
+        if proxy_list.is_empty() {
+            let warn_str = format!("No proxy from the {} provider.", proxy_provider.get_name());
+            self.project_logger.log_warn(&warn_str);
+        }
+        proxy_list
+    }
+
+    // Samples the proxies of the provider for a chunk, keeping the endpoints so that the blocked
+    // ones can be reported back to the provider.
+    fn sample_provider_proxy(
+        proxy_provider: &dyn ProxyProvider,
+        proxy_list: &[ProxyEndpoint],
+        num_proxy: usize,
+    ) -> Vec<(ProxyEndpoint, Proxy)> {
+        proxy_provider
+            .sample_proxy(proxy_list, num_proxy)
+            .into_iter()
+            .filter_map(|proxy_endpoint| {
+                let proxy = proxy_endpoint.get_reqwest_proxy().ok()?;
+                Some((proxy_endpoint, proxy))
+            })
+            .collect()
+    }
+
+    pub async fn multiple_requests_with_proxy(
+        &self,
+        url_file_list: &Vec<UrlFile>,
+        request_builder_func: fn(Proxy, Url) -> RequestBuilder,
+        folder_path: &Path,
+        check_func: &dyn ResponseValidator,
+        request_setting: &RequestSetting<'a>,
+    ) -> BatchOutcome {
+        DryRun::scope(request_setting.dry_run, async {
+            let start_time = Instant::now();
+            let mut counter = 0;
+            let mut num_proxy_switch = 0;
+            let mut num_blocked = 0;
+            let mut batch_outcome = BatchOutcome::new();
+            let mut pending_url_file_list = url_file_list.to_owned();
+            let mut fail_outcome_list: Vec<UrlOutcome> = Vec::new();
+            let deadline = request_setting.get_deadline(self.clock.as_ref());
+            let mut halted_list = Vec::new();
+            while counter < self.num_retry
+                && num_proxy_switch < Self::MAX_PROXY_SWITCH
+                && !pending_url_file_list.is_empty()
+                && halted_list.is_empty()
+            {
+                let provider_proxy_list = match self.proxy_provider.as_deref() {
+                    Some(proxy_provider) => self.fetch_provider_proxy(proxy_provider).await,
+                    None => Vec::new(),
+                };
+                // Without a provider the free proxy list of ScraperProxy is used, which needs the
+                // proxy feature.
+                #[cfg(feature = "proxy")]
+                let mut proxy_list = match self.proxy_provider.as_deref() {
+                    Some(_) => None,
+                    None => Some(ScraperProxy::generate_proxy().await),
+                };
+                let mut round_fail_list = Vec::new();
+                num_blocked = 0;
+                for chunk in pending_url_file_list
+                    .iter()
+                    .chunks(self.chunk_size_request)
+                    .into_iter()
+                {
+                    if self.is_shutdown_requested()
+                        || time_operation::is_deadline_reached(deadline, self.clock.as_ref())
+                    {
+                        halted_list.extend(chunk.cloned());
+                        continue;
+                    }
+                    let pending_chunk: Vec<&UrlFile> = chunk.collect();
+                    let chunk_proxy_list: Vec<(Proxy, Option<ProxyEndpoint>)> =
+                        match self.proxy_provider.as_deref() {
+                            Some(proxy_provider) => Self::sample_provider_proxy(
+                                proxy_provider,
+                                &provider_proxy_list,
+                                pending_chunk.len(),
+                            )
+                            .into_iter()
+                            .map(|(proxy_endpoint, proxy)| (proxy, Some(proxy_endpoint)))
+                            .collect(),
+                            #[cfg(feature = "proxy")]
+                            None => proxy_list.as_mut().map_or_else(Vec::new, |proxy_list| {
+                                ScraperProxy::sample_proxy(proxy_list, self.chunk_size_request)
+                                    .map(|proxy_pair| (proxy_pair.proxy.clone(), None))
+                                    .collect()
+                            }),
+                            #[cfg(not(feature = "proxy"))]
+                            None => Vec::new(),
+                        };
+                    // The urls left without a proxy, e.g. when the provider gives none, are failed
+                    // instead of dropped.
+                    for url_file in pending_chunk.iter().skip(chunk_proxy_list.len()) {
+                        round_fail_list.push(UrlOutcome::new(
+                            url_file,
+                            ScrapeOutcome::skipped(ScrapeStatus::Failed),
+                            Some("No proxy available.".to_string()),
+                        ));
+                    }
+                    // Each response is saved by its own task, and the outcomes are taken in the order
+                    // they complete, so the body of a finished url is not kept while the slower ones
+                    // of the chunk are still loading.
+                    let mut request_tasks: FuturesUnordered<_> = chunk_proxy_list
+                        .iter()
+                        .zip(pending_chunk.iter())
+                        .map(|((proxy, proxy_endpoint), url_file)| async move {
+                            let url_outcome = self
+                                .request_with_proxy_and_save_content(
+                                    url_file,
+                                    proxy.clone(),
+                                    request_builder_func,
+                                    folder_path,
+                                    check_func,
+                                    request_setting.get_url_request_options(self.num_retry),
+                                )
+                                .await;
+                            (url_outcome, proxy_endpoint)
+                        })
+                        .collect();
+                    while let Some((mut url_outcome, proxy_endpoint)) = request_tasks.next().await {
+                        // The attempts of the earlier rounds with other proxies are added up.
+                        if let Some(fail_outcome) = fail_outcome_list
+                            .iter()
+                            .find(|x| x.url_file == url_outcome.url_file)
+                        {
+                            url_outcome.outcome.attempts += fail_outcome.outcome.attempts;
+                        }
+                        match url_outcome.outcome.status {
+                            ScrapeStatus::Success | ScrapeStatus::Tripped => {
+                                batch_outcome.add(url_outcome)
+                            }
+                            ScrapeStatus::Blocked => {
+                                num_blocked += 1;
+                                if let (Some(proxy_provider), Some(proxy_endpoint)) =
+                                    (self.proxy_provider.as_deref(), proxy_endpoint)
+                                {
+                                    proxy_provider.report_failure(proxy_endpoint);
+                                }
+                                round_fail_list.push(url_outcome);
+                            }
+                            _ => round_fail_list.push(url_outcome),
+                        }
+                    }
+                }
+                // Blocked urls are retried with freshly sampled proxies without using up the retries.
+                if num_blocked == round_fail_list.len() {
+                    num_proxy_switch += 1;
+                } else {
+                    counter += 1;
+                }
+                pending_url_file_list =
+                    round_fail_list.iter().map(|x| x.url_file.clone()).collect();
+                fail_outcome_list = round_fail_list;
+            }
+            // No round runs without retries, which leaves every url unrequested.
+            if counter == 0 && num_proxy_switch == 0 {
+                halted_list.extend(pending_url_file_list.drain(..));
+            }
+            if !pending_url_file_list.is_empty() {
+                let fail_url_list = format!(
+                    "The following urls were not loaded successfully:\n\n {}",
+                    pending_url_file_list
+                        .iter()
+                        .map(|x| x.url.as_str())
+                        .collect::<Vec<&str>>()
+                        .join("\n")
+                );
+                self.project_logger.log_error(&fail_url_list);
+                let fail_url_message = Self::fail_url_message(
+                    &pending_url_file_list,
+                    num_blocked,
+                    url_file_list.len(),
+                );
+                self.slack_messenger.retry_send_message(
+                    request_setting.calling_func,
+                    &fail_url_message,
+                    request_setting.log_only,
+                );
+            }
+            self.notify_deadline_reached(
+                &halted_list,
+                url_file_list.len(),
+                request_setting.max_total_duration,
+                request_setting.calling_func,
+                request_setting.log_only,
+            );
+            fail_outcome_list
+                .into_iter()
+                .for_each(|x| batch_outcome.add(x));
+            batch_outcome.add_halted(&halted_list);
+            if self.is_shutdown_requested() {
+                self.shutdown_with_pending_list(
+                    &batch_outcome.get_fail_list(),
+                    folder_path,
+                    request_setting.in_s3,
+                    request_setting.calling_func,
+                    request_setting.log_only,
+                )
+                .await;
+            }
+            batch_outcome.duration = start_time.elapsed();
+            batch_outcome
+        })
+        .await
+    }
+
+    #[cfg(feature = "proxy")]
+    pub async fn multiple_requests_with_private_proxy(
+        &self,
+        url_file_list: &[UrlFile],
+        private_proxy: &mut PrivateProxy,
+        request_builder_func: fn(Proxy, Url) -> RequestBuilder,
+        folder_path: &Path,
+        check_func: &dyn ResponseValidator,
+        request_setting: &RequestSetting<'a>,
+    ) -> BatchOutcome {
+        DryRun::scope(request_setting.dry_run, async {
+            let start_time = Instant::now();
+            let deadline = request_setting.get_deadline(self.clock.as_ref());
+            let mut batch_outcome = BatchOutcome::new();
+            let mut halted_list = Vec::new();
+            for (index, url_file) in tqdm::tqdm(url_file_list.iter().enumerate()) {
+                if self.is_shutdown_requested()
+                    || time_operation::is_deadline_reached(deadline, self.clock.as_ref())
+                {
+                    halted_list = url_file_list[index..].to_vec();
+                    break;
+                }
+                let mut last_outcome: Option<UrlOutcome> = None;
+                for _ in 0..Self::MAX_PROXY_SWITCH {
+                    let proxy = match private_proxy.generate_proxy() {
+                        Some(proxy) => proxy.clone(),
+                        None => break,
+                    };
+                    let mut url_outcome = self
+                        .request_with_proxy_and_save_content(
+                            url_file,
+                            proxy,
+                            request_builder_func,
+                            folder_path,
+                            check_func,
+                            request_setting.get_url_request_options(self.num_retry),
+                        )
+                        .await;
+                    self.clock
+                        .random_sleep(request_setting.get_sleep_range(self.consecutive_sleep))
+                        .await;
+                    if let Some(last_outcome) = &last_outcome {
+                        url_outcome.outcome.attempts += last_outcome.outcome.attempts;
+                    }
+                    let is_blocked = url_outcome.outcome.status == ScrapeStatus::Blocked;
+                    last_outcome = Some(url_outcome);
+                    if !is_blocked {
+                        break;
+                    }
+                    let debug_str = format!("Switch proxy for the blocked url {}.", url_file.url);
+                    self.project_logger.log_debug(&debug_str);
+                }
+                batch_outcome.add(last_outcome.unwrap_or_else(|| {
+                    UrlOutcome::new(
+                        url_file,
+                        ScrapeOutcome::skipped(ScrapeStatus::Failed),
+                        Some("No private proxy available.".to_string()),
+                    )
+                }));
+            }
+            let fail_list = batch_outcome.get_fail_list();
+            if !fail_list.is_empty() {
+                let fail_url_list = format!(
+                    "The following urls were not loaded successfully:\n\n {}",
+                    fail_list
+                        .iter()
+                        .map(|x| x.url.as_str())
+                        .collect::<Vec<&str>>()
+                        .join("\n")
+                );
+                self.project_logger.log_error(&fail_url_list);
+                let fail_url_message = Self::fail_url_message(
+                    &fail_list,
+                    batch_outcome.num_blocked(),
+                    url_file_list.len(),
+                );
+                self.slack_messenger.retry_send_message(
+                    request_setting.calling_func,
+                    &fail_url_message,
+                    request_setting.log_only,
+                );
+            }
+            self.notify_deadline_reached(
+                &halted_list,
+                url_file_list.len(),
+                request_setting.max_total_duration,
+                request_setting.calling_func,
+                request_setting.log_only,
+            );
+            batch_outcome.add_halted(&halted_list);
+            if self.is_shutdown_requested() {
+                self.shutdown_with_pending_list(
+                    &batch_outcome.get_fail_list(),
+                    folder_path,
+                    request_setting.in_s3,
+                    request_setting.calling_func,
+                    request_setting.log_only,
+                )
+                .await;
+            }
+            batch_outcome.duration = start_time.elapsed();
+            batch_outcome
+        })
+        .await
+    }
+
+    fn get_scrape_backend(&self, url: &Url, tier: ScrapeTier) -> ScrapeBackend {
+        let proxy_mode = self
+            .get_domain_profile(url)
+            .map(|domain_profile| domain_profile.proxy_mode)
+            .unwrap_or_default();
+        ScrapeBackend::new(tier, proxy_mode)
+    }
+
+    // Http first, unless the domain stats show the browser has worked better for the domain.
+    fn rank_scrape_tiers(&self, url: &Url) -> [ScrapeTier; 2] {
+        let tier_list = [ScrapeTier::Http, ScrapeTier::Browser];
+        match self.domain_stats_store.as_deref() {
+            Some(domain_stats_store) => {
+                let backend_list = tier_list.map(|tier| self.get_scrape_backend(url, tier));
+                let ranked_list = domain_stats_store.rank_backends(url, &backend_list);
+                [ranked_list[0].tier, ranked_list[1].tier]
+            }
+            None => tier_list,
+        }
+    }
+
+    fn record_domain_stats(&self, url: &Url, tier: ScrapeTier, outcome: &ScrapeOutcome) {
+        if let Some(domain_stats_store) = self.domain_stats_store.as_deref() {
+            if outcome.status != ScrapeStatus::Tripped {
+                domain_stats_store.record(url, self.get_scrape_backend(url, tier), outcome);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn scrape_with_tier<F>(
+        &self,
+        tier: ScrapeTier,
+        url_file: &UrlFile,
+        request_builder_func: fn(Url) -> RequestBuilder,
+        browser: &Capabilities,
+        browse_action: &F,
+        folder_path: &Path,
+        check_func: &dyn ResponseValidator,
+        request_setting: &RequestSetting<'a>,
+        max_attempts: u32,
+    ) -> ScrapeOutcome
+    where
+        F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
+    {
+        // The domain outcome is recorded once by the caller, for the final tier of the url.
+        let url_request_options = UrlRequestOptions {
+            record_domain_outcome: false,
+            ..request_setting.get_url_request_options(max_attempts)
+        };
+        let outcome = match tier {
+            ScrapeTier::Http => {
+                self.request_and_save_content_with_outcome(
+                    url_file,
+                    request_builder_func,
+                    folder_path,
+                    check_func,
+                    url_request_options,
+                )
+                .await
+                .outcome
+            }
+            ScrapeTier::Browser => {
+                self.browse_and_save_content_with_outcome(
+                    url_file,
+                    browser,
+                    folder_path,
+                    browse_action,
+                    check_func,
+                    url_request_options,
+                )
+                .await
+            }
+        };
+        self.record_domain_stats(&url_file.url, tier, &outcome);
+        outcome
+    }
+
+    // Urls exhausting the retries of the first tier are scraped with the other tier within the
+    // same run. Http goes first unless the domain stats, if set, rank the browser higher. Both
+    // tiers share the total attempt budget, so a terminated request leaves more attempts for
+    // the other tier.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn multiple_requests_with_browser_fallback<F>(
+        &self,
+        url_file_list: &[UrlFile],
+        request_builder_func: fn(Url) -> RequestBuilder,
+        browser: &Capabilities,
+        browse_action: &F,
+        folder_path: &Path,
+        check_func: &dyn ResponseValidator,
+        request_setting: &RequestSetting<'a>,
+        max_total_attempts: u32,
+    ) -> Vec<FallbackOutcome>
+    where
+        F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
+    {
+        DryRun::scope(request_setting.dry_run, async {
+            let deadline = request_setting.get_deadline(self.clock.as_ref());
+            let mut fallback_outcome_list = Vec::with_capacity(url_file_list.len());
+            let mut halted_list = Vec::new();
+            for (index, url_file) in tqdm::tqdm(url_file_list.iter().enumerate()) {
+                if self.is_shutdown_requested()
+                    || time_operation::is_deadline_reached(deadline, self.clock.as_ref())
+                {
+                    halted_list = url_file_list[index..].to_vec();
+                    break;
+                }
+                let [mut tier, fallback_tier] = self.rank_scrape_tiers(&url_file.url);
+                let mut outcome = self
+                    .scrape_with_tier(
+                        tier,
+                        url_file,
+                        request_builder_func,
+                        browser,
+                        browse_action,
+                        folder_path,
+                        check_func,
+                        request_setting,
+                        self.num_retry.min(max_total_attempts),
+                    )
+                    .await;
+                let remaining_attempts = max_total_attempts.saturating_sub(outcome.attempts);
+                if !outcome.is_success()
+                    && outcome.status != ScrapeStatus::Tripped
+                    && remaining_attempts > 0
+                {
+                    let debug_str = format!(
+                        "Fall back to {} for {} with {remaining_attempts} attempts left.",
+                        fallback_tier.as_str(),
+                        url_file.url.as_str()
+                    );
+                    self.project_logger.log_debug(&debug_str);
+                    let previous_attempts = outcome.attempts;
+                    tier = fallback_tier;
+                    outcome = self
+                        .scrape_with_tier(
+                            tier,
+                            url_file,
+                            request_builder_func,
+                            browser,
+                            browse_action,
+                            folder_path,
+                            check_func,
+                            request_setting,
+                            remaining_attempts,
+                        )
+                        .await;
+                    outcome.attempts += previous_attempts;
+                }
+                if outcome.status != ScrapeStatus::Tripped {
+                    self.record_domain_outcome(
+                        &url_file.url,
+                        outcome.is_success(),
+                        request_setting.calling_func,
+                        request_setting.log_only,
+                    );
+                }
+                fallback_outcome_list.push(FallbackOutcome {
+                    url_file: url_file.clone(),
+                    outcome,
+                    tier,
+                });
+                self.clock
+                    .random_sleep(request_setting.get_sleep_range(self.consecutive_sleep))
+                    .await;
+            }
+            let num_success_by_tier = |tier: ScrapeTier| {
+                fallback_outcome_list
+                    .iter()
+                    .filter(|x| x.tier == tier && x.outcome.is_success())
+                    .count()
+            };
+            let fail_list: Vec<UrlFile> = fallback_outcome_list
+                .iter()
+                .filter(|x| !x.outcome.is_success())
+                .map(|x| x.url_file.clone())
+                .collect();
+            let summary_str = format!(
+                "{} urls loaded by http, {} urls loaded by browser, {} urls failed.",
+                num_success_by_tier(ScrapeTier::Http),
+                num_success_by_tier(ScrapeTier::Browser),
+                fail_list.len()
+            );
+            self.project_logger.log_info(&summary_str);
+            if !fail_list.is_empty() {
+                let num_blocked = fallback_outcome_list
+                    .iter()
+                    .filter(|x| x.outcome.status == ScrapeStatus::Blocked)
+                    .count();
+                let fail_url_message = format!(
+                    "{} Browser fallback was applied.",
+                    Self::fail_url_message(&fail_list, num_blocked, url_file_list.len())
+                );
+                self.project_logger.log_error(&fail_url_message);
+                self.slack_messenger.retry_send_message(
+                    request_setting.calling_func,
+                    &fail_url_message,
+                    request_setting.log_only,
+                );
+            }
+            self.notify_deadline_reached(
+                &halted_list,
+                url_file_list.len(),
+                request_setting.max_total_duration,
+                request_setting.calling_func,
+                request_setting.log_only,
+            );
+            if self.is_shutdown_requested() {
+                let pending_list: Vec<UrlFile> =
+                    fail_list.into_iter().chain(halted_list.clone()).collect();
+                self.shutdown_with_pending_list(
+                    &pending_list,
+                    folder_path,
+                    request_setting.in_s3,
+                    request_setting.calling_func,
+                    request_setting.log_only,
+                )
+                .await;
+            }
+            fallback_outcome_list.extend(halted_list.into_iter().map(|url_file| FallbackOutcome {
+                url_file,
+                outcome: ScrapeOutcome::skipped(ScrapeStatus::Halted),
+                tier: ScrapeTier::Http,
+            }));
+            fallback_outcome_list
+        })
+        .await
+    }
+
+    pub async fn multiple_requests_data_frame(
+        &self,
+        data: &DataFrame,
+        request_builder_func: fn(Url) -> RequestBuilder,
+        folder_path: &Path,
+        check_func: &dyn ResponseValidator,
+        request_setting: &RequestSetting<'a>,
+    ) -> PolarsResult<DataFrame> {
+        DryRun::scope(request_setting.dry_run, async {
+            let url_column = data.column(UrlFileManifest::URL_COLUMN)?.str()?;
+            let file_name_column = data.column(UrlFileManifest::FILE_NAME_COLUMN)?.str()?;
+            let deadline = request_setting.get_deadline(self.clock.as_ref());
+            let mut outcome_list = Vec::with_capacity(data.height());
+            let mut fail_list = Vec::new();
+            let mut halted_list = Vec::new();
+            for (url, file_name) in tqdm::tqdm(url_column.into_iter().zip(file_name_column)) {
+                let url_file = match (url.map(Url::parse), file_name) {
+                    (Some(Ok(url)), Some(file_name)) => UrlFile::new(url, file_name.to_string()),
+                    _ => {
+                        let warn_str = format!("Invalid url {url:?} or file name {file_name:?}.");
+                        self.project_logger.log_warn(&warn_str);
+                        outcome_list.push(ScrapeOutcome::skipped(ScrapeStatus::InvalidUrl));
+                        continue;
+                    }
+                };
+                if self.is_shutdown_requested()
+                    || time_operation::is_deadline_reached(deadline, self.clock.as_ref())
+                {
+                    outcome_list.push(ScrapeOutcome::skipped(ScrapeStatus::Halted));
+                    halted_list.push(url_file);
+                    continue;
+                }
+                let outcome = self
+                    .request_and_save_content_with_outcome(
+                        &url_file,
+                        request_builder_func,
+                        folder_path,
+                        check_func,
+                        request_setting.get_url_request_options(self.num_retry),
+                    )
+                    .await
+                    .outcome;
+                if !outcome.is_success() {
+                    fail_list.push(url_file);
+                }
+                outcome_list.push(outcome);
+                self.clock
+                    .random_sleep(request_setting.get_sleep_range(self.consecutive_sleep))
+                    .await;
+            }
+            if !fail_list.is_empty() {
+                let num_blocked = outcome_list
+                    .iter()
+                    .filter(|x| x.status == ScrapeStatus::Blocked)
+                    .count();
+                let fail_url_message =
+                    Self::fail_url_message(&fail_list, num_blocked, data.height());
+                self.project_logger.log_error(&fail_url_message);
+                self.slack_messenger.retry_send_message(
+                    request_setting.calling_func,
+                    &fail_url_message,
+                    request_setting.log_only,
+                );
+            }
+            self.notify_deadline_reached(
+                &halted_list,
+                data.height(),
+                request_setting.max_total_duration,
+                request_setting.calling_func,
+                request_setting.log_only,
+            );
+            if self.is_shutdown_requested() {
+                fail_list.extend(halted_list);
+                self.shutdown_with_pending_list(
+                    &fail_list,
+                    folder_path,
+                    request_setting.in_s3,
+                    request_setting.calling_func,
+                    request_setting.log_only,
+                )
+                .await;
+            }
+            Self::augment_data_frame_with_outcome(data, &outcome_list)
+        })
+        .await
+    }
+
+    pub fn augment_data_frame_with_outcome(
+        data: &DataFrame,
+        outcome_list: &[ScrapeOutcome],
+    ) -> PolarsResult<DataFrame> {
+        let status: Vec<&str> = outcome_list
+            .iter()
+            .map(|outcome| outcome.status.as_str())
+            .collect();
+        let attempts: Vec<u32> = outcome_list
+            .iter()
+            .map(|outcome| outcome.attempts)
+            .collect();
+        let latency: Vec<Option<f64>> = outcome_list
+            .iter()
+            .map(|outcome| {
+                outcome
+                    .latency
+                    .map(|latency| latency.as_secs_f64() * 1000.0)
+            })
+            .collect();
+        let bytes: Vec<Option<u64>> = outcome_list.iter().map(|outcome| outcome.bytes).collect();
+        let mut augmented_data = data.clone();
+        augmented_data
+            .with_column(Series::new(Self::STATUS_COLUMN.into(), status))?
+            .with_column(Series::new(Self::ATTEMPTS_COLUMN.into(), attempts))?
+            .with_column(Series::new(Self::LATENCY_COLUMN.into(), latency))?
+            .with_column(Series::new(Self::BYTES_COLUMN.into(), bytes))?;
+        Ok(augmented_data)
+    }
+
+    pub fn send_run_digest(&self, run_report: &RunReport, calling_func: &str, log_only: bool) {
+        let digest_message = run_report.digest_message();
+        self.project_logger.log_info(&digest_message);
+        self.slack_messenger
+            .retry_send_message(calling_func, &digest_message, log_only);
+    }
+
+    pub async fn save_run_metrics(
+        &self,
+        run_report: &RunReport,
+        folder_path: &Path,
+        file: &str,
+        in_s3: bool,
+    ) {
+        let mut metrics_data = match run_report.domain_summary_data_frame() {
+            Ok(metrics_data) => metrics_data,
+            Err(e) => {
+                let error_str = format!("Unable to build the run metrics for {file}. {e}");
+                self.project_logger.log_error(&error_str);
+                return;
+            }
+        };
+        if let Some(run_context) = RunContext::current() {
+            if let Err(e) = run_context.stamp_data_frame(&mut metrics_data) {
+                let warn_str = format!("Unable to stamp the run context on {file}. {e}");
+                self.project_logger.log_warn(&warn_str);
+            }
+        }
+        let write_result = if in_s3 {
+            self.aws_file_io
+                .write_parquet_file(&self.aws_bucket, folder_path, file, &mut metrics_data)
+                .await
+                .map_err(|e| format!("{e:?}"))
+        } else {
+            self.file_io
+                .write_parquet_file(folder_path, file, &mut metrics_data)
+                .map_err(|e| e.to_string())
+        };
+        if let Err(e) = write_result {
+            let error_str = format!(
+                "Unable to save the run metrics {file} in {}. {e}",
+                folder_path.display()
+            );
+            self.project_logger.log_error(&error_str);
+        }
+    }
+
+    // Writes the manifest as parquet and json, e.g. "{file_stem}.parquet" and "{file_stem}.json",
+    // and clears it so that the next run starts with an empty manifest.
+    pub async fn save_run_manifest(&self, folder_path: &Path, file_stem: &str, in_s3: bool) {
+        let Some(run_manifest) = self.run_manifest.as_deref() else {
+            self.project_logger
+                .log_warn("No run manifest is set. Skip saving the run manifest.");
+            return;
+        };
+        let parquet_file = format!("{file_stem}.{}", RunManifest::PARQUET_EXTENSION);
+        let json_file = format!("{file_stem}.{}", RunManifest::JSON_EXTENSION);
+        let (mut manifest_data, manifest_json) =
+            match (run_manifest.to_data_frame(), run_manifest.to_json_string()) {
+                (Ok(manifest_data), Ok(manifest_json)) => (manifest_data, manifest_json),
+                (Err(e), _) => {
+                    let error_str = format!("Unable to build the run manifest {file_stem}. {e}");
+                    self.project_logger.log_error(&error_str);
+                    return;
+                }
+                (_, Err(e)) => {
+                    let error_str =
+                        format!("Unable to serialize the run manifest {file_stem}. {e}");
+                    self.project_logger.log_error(&error_str);
+                    return;
+                }
+            };
+        let write_result = if in_s3 {
+            self.aws_file_io
+                .write_parquet_file(
+                    &self.aws_bucket,
+                    folder_path,
+                    &parquet_file,
+                    &mut manifest_data,
+                )
+                .await
+                .map_err(|e| format!("{e:?}"))
+        } else {
+            self.file_io
+                .write_parquet_file(folder_path, &parquet_file, &mut manifest_data)
+                .map_err(|e| e.to_string())
+        };
+        if let Err(e) = write_result {
+            let error_str = format!(
+                "Unable to save the run manifest {parquet_file} in {}. {e}",
+                folder_path.display()
+            );
+            self.project_logger.log_error(&error_str);
+            return;
+        }
+        // The manifest is kept for the next save if the json cannot be written.
+        if self
+            .write_request_content(folder_path, &json_file, manifest_json.as_bytes(), in_s3)
+            .await
+            .is_err()
+        {
+            return;
+        }
+        let debug_str = format!(
+            "Run manifest of {} urls saved as {file_stem} in {}.",
+            run_manifest.len(),
+            folder_path.display()
+        );
+        self.project_logger.log_debug(&debug_str);
+        run_manifest.clear();
+    }
+
+    fn notify_deadline_reached(
+        &self,
+        halted_list: &[UrlFile],
+        total_count: usize,
+        max_total_duration: Option<Duration>,
+        calling_func: &str,
+        log_only: bool,
+    ) {
+        if !halted_list.is_empty() && !self.is_shutdown_requested() {
+            let deadline_message = format!(
+                "Deadline of {:?} reached. The urls starting with {:?} has {} out of {} urls pending.",
+                max_total_duration.unwrap_or_default(),
+                halted_list.first(),
+                halted_list.len(),
+                total_count
+            );
+            self.project_logger.log_warn(&deadline_message);
+            self.slack_messenger
+                .retry_send_message(calling_func, &deadline_message, log_only);
+        }
+    }
+
+    pub async fn save_checkpoint(
+        &self,
+        folder_path: &Path,
+        url_file_list: &[UrlFile],
+        calling_func: &str,
+        in_s3: bool,
+    ) {
+        let checkpoint = UrlFileCheckpoint::new(calling_func, url_file_list);
+        match checkpoint.to_toml_string() {
+            Ok(checkpoint_str) => {
+                let write_result = self
+                    .write_request_content(
+                        folder_path,
+                        UrlFileCheckpoint::CHECKPOINT_FILE,
+                        checkpoint_str.as_bytes(),
+                        in_s3,
+                    )
+                    .await;
+                if write_result.is_ok() {
+                    let debug_str = format!(
+                        "Checkpoint with {} pending urls saved in {}.",
+                        url_file_list.len(),
+                        folder_path.display()
+                    );
+                    self.project_logger.log_debug(&debug_str);
+                }
+            }
+            Err(e) => {
+                let error_str = format!(
+                    "Unable to serialize the checkpoint for {} pending urls. {e}",
+                    url_file_list.len()
+                );
+                self.project_logger.log_error(&error_str);
+            }
+        }
+    }
+
+    pub async fn load_checkpoint(&self, folder_path: &Path, in_s3: bool) -> Option<Vec<UrlFile>> {
+        let checkpoint_file = UrlFileCheckpoint::CHECKPOINT_FILE;
+        let checkpoint_str = if in_s3 {
+            if self
+                .aws_file_io
+                .check_file_exist(&self.aws_bucket, folder_path, checkpoint_file)
+                .await
+            {
+                self.aws_file_io
+                    .load_file_as_string(&self.aws_bucket, folder_path, checkpoint_file)
+                    .await
+                    .ok()
+            } else {
+                None
+            }
+        } else if FileIO::check_file_exist(folder_path, checkpoint_file) {
+            self.file_io
+                .load_file_as_string(folder_path, checkpoint_file)
+                .ok()
+        } else {
+            None
+        };
+        checkpoint_str.and_then(|checkpoint_str| {
+            UrlFileCheckpoint::from_toml_str(&checkpoint_str)
+                .map_err(|e| {
+                    let warn_str = format!(
+                        "Unable to parse the checkpoint in {}. {e}",
+                        folder_path.display()
+                    );
+                    self.project_logger.log_warn(&warn_str);
+                })
+                .ok()
+                .map(|checkpoint| checkpoint.get_url_file_list())
+        })
+    }
+
+    // The pending urls are kept in the checkpoint and returned as halted by the batch, so the
+    // caller can clean up and exit.
+    async fn shutdown_with_pending_list(
+        &self,
+        pending_list: &[UrlFile],
+        folder_path: &Path,
+        in_s3: bool,
+        calling_func: &str,
+        log_only: bool,
+    ) {
+        self.save_checkpoint(folder_path, pending_list, calling_func, in_s3)
+            .await;
+        // The failure to kill the driver is logged, and the shutdown goes on regardless.
+        let _ = self.kill_owned_chrome_process();
+        let shutdown_message = format!(
+            "Shutdown requested. {} pending urls saved to checkpoint in {}.",
+            pending_list.len(),
+            folder_path.display()
+        );
+        self.project_logger.log_warn(&shutdown_message);
+        self.slack_messenger
+            .retry_send_message(calling_func, &shutdown_message, log_only);
+    }
+
+    pub async fn download_google_sheet(
+        &self,
+        google_sheet_key: &str,
+        request_builder_func: fn(Url) -> RequestBuilder,
+        check_func: &dyn ResponseValidator,
+    ) -> ResponseCheckResult {
+        match google_sheet::url_from_google_sheet_link(google_sheet_key) {
+            Some(google_sheet_url) => {
+                self.simple_request(&google_sheet_url, request_builder_func, check_func)
+                    .await
+            }
+            None => ResponseCheckResult::ErrTerminate(ScrapeFailure::LoadFailed(format!(
+                "Unable to parse the google sheet link {google_sheet_key}."
+            ))),
+        }
+    }
+
+    // Each tab is retried on its own, the same as the blocking retry_download_google_sheet_tabs.
+    pub async fn download_google_sheet_tabs(
+        &self,
+        google_sheet_key: &GoogleSheetKey,
+        gid_list: &[&str],
+        request_builder_func: fn(Url) -> RequestBuilder,
+        check_func: &dyn ResponseValidator,
+    ) -> Vec<(String, ResponseCheckResult)> {
+        let request_tasks = gid_list.iter().map(|gid| async move {
+            let response = match google_sheet_key.with_gid(gid).get_csv_url() {
+                Some(google_sheet_url) => {
+                    self.retry_simple_request(&google_sheet_url, request_builder_func, check_func)
+                        .await
+                        .0
+                }
+                None => ResponseCheckResult::ErrTerminate(ScrapeFailure::LoadFailed(format!(
+                    "Unable to parse the google sheet {} with gid {gid}.",
+                    google_sheet_key.sheet_id
+                ))),
+            };
+            (gid.to_string(), response)
+        });
+        future::join_all(request_tasks).await
+    }
+
+    pub fn convert_google_sheet_string_to_data_frame(google_sheet_csv: &str) -> Option<DataFrame> {
+        google_sheet::convert_to_data_frame(google_sheet_csv, &GoogleSheetReadOptions::default())
+            .ok()
+    }
+
+    pub async fn browse_page(web_driver: &mut WebDriver, url: &Url) -> WebDriverResult<()> {
+        web_driver.goto(url.clone()).await
+    }
+
+    pub async fn browse_request<F>(
+        web_driver: &mut WebDriver,
+        url: &Url,
+        browse_action: &F,
+    ) -> WebDriverResult<String>
+    where
+        F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
+    {
+        Self::browse_page(web_driver, url).await?;
+        browse_action(web_driver).await?;
+        web_driver.source().await
+    }
+
+    // The HAR is kept by the recorder until the page is saved, and a failure to collect it only
+    // loses the HAR of the page.
+    async fn record_har(&self, web_driver: &WebDriver, url: &Url) {
+        let Some(har_recorder) = self.har_recorder.as_deref() else {
+            return;
+        };
+        let performance_log = match self.browser_kind.logging_prefs_key() {
+            Some(_) => har_recorder::collect_performance_log(web_driver)
+                .await
+                .unwrap_or_else(|e| {
+                    let warn_str = format!(
+                        "Unable to collect the network events of {}. {e}",
+                        url.as_str()
+                    );
+                    self.project_logger.log_warn(&warn_str);
+                    Value::Null
+                }),
+            None => Value::Null,
+        };
+        match har_recorder::collect_timing_entries(web_driver).await {
+            Ok(timing_entries) => {
+                let har = har_recorder::build_har(url, &timing_entries, &performance_log);
+                har_recorder.insert(url, har);
+            }
+            Err(e) => {
+                let warn_str = format!("Unable to record the HAR of {}. {e}", url.as_str());
+                self.project_logger.log_warn(&warn_str);
+            }
+        }
+    }
+
+    pub async fn simple_browse_request<F>(
+        &self,
+        url: &Url,
+        browser: &ChromeCapabilities,
+        browse_action: &F,
+        check_func: &dyn ResponseValidator,
+    ) -> ResponseCheckResult
+    where
+        F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
+    {
+        self.simple_browse_request_with_capabilities(
+            url,
+            &browser.clone().into(),
+            browse_action,
+            check_func,
+        )
+        .await
+    }
+
+    pub async fn simple_browse_request_with_capabilities<F>(
+        &self,
+        url: &Url,
+        browser: &Capabilities,
+        browse_action: &F,
+        check_func: &dyn ResponseValidator,
+    ) -> ResponseCheckResult
+    where
+        F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
+    {
+        let web_driver = match self.set_web_driver(browser.clone()).await {
+            Ok(web_driver) => web_driver,
+            Err(e) => {
+                return ResponseCheckResult::ErrContinue(ScrapeFailure::LoadFailed(e.to_string()))
+            }
+        };
+        self.check_browse_response(web_driver, url, browse_action, check_func)
+            .await
+    }
+
+    // Sessions in the pool are reused across urls, so the same browser capabilities should be
+    // passed for every request drawn from one pool.
+    pub async fn pooled_browse_request<F>(
+        &self,
+        web_driver_pool: &WebDriverPool<'a>,
+        url: &Url,
+        browser: &Capabilities,
+        browse_action: &F,
+        check_func: &dyn ResponseValidator,
+    ) -> ResponseCheckResult
+    where
+        F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
+    {
+        let (_permit, idle_session) = web_driver_pool.acquire().await;
+        let mut session = match idle_session {
+            Some(session) => session,
+            None => match self.set_web_driver(browser.clone()).await {
+                Ok(web_driver) => WebDriverSession::new(web_driver),
+                Err(e) => {
+                    return ResponseCheckResult::ErrContinue(ScrapeFailure::LoadFailed(
+                        e.to_string(),
+                    ))
+                }
+            },
+        };
+        match Self::browse_request(&mut session.web_driver, url, browse_action).await {
+            Ok(response) => {
+                self.record_har(&session.web_driver, url).await;
+                session.record_page();
+                if let Some(session) = web_driver_pool.release(session) {
+                    self.discard_web_driver(session.web_driver).await;
+                }
+                match check_func.check(&response) {
+                    ResponseCheckResult::Ok(response) => {
+                        let debug_str = format!("Request {} browsed.", url.as_str());
+                        self.project_logger.log_debug(&debug_str);
+                        ResponseCheckResult::Ok(response)
+                    }
+                    ResponseCheckResult::ErrContinue(e) => {
+                        let warn_str =
+                            format!("Checking for the response failed for {}. {e}", url.as_str());
+                        self.project_logger.log_warn(&warn_str);
+                        ResponseCheckResult::ErrContinue(e)
+                    }
+                    ResponseCheckResult::ErrTerminate(e) | ResponseCheckResult::TooLarge(e) => {
+                        let error_str = format!("Terminate to load the page {}. {e}", url.as_str());
+                        self.project_logger.log_error(&error_str);
+                        ResponseCheckResult::ErrTerminate(e)
+                    }
+                    ResponseCheckResult::Blocked(e) => {
+                        let warn_str =
+                            format!("Blocked when loading the page {}. {e}", url.as_str());
+                        self.project_logger.log_warn(&warn_str);
+                        ResponseCheckResult::Blocked(e)
+                    }
+                }
+            }
+            Err(e) => {
+                let warn_str = format!(
+                    "Unable to browse the page {}. Discard the session. {e}",
+                    url.as_str()
+                );
+                self.project_logger.log_warn(&warn_str);
+                self.discard_web_driver(session.web_driver).await;
+                ResponseCheckResult::ErrContinue(ScrapeFailure::LoadFailed(e.to_string()))
             }
         }
-        if !fail_list.is_empty() {
-            let fail_url_list = format!(
-                "The following urls were not loaded successfully:\n\n {}",
-                fail_list
-                    .iter()
-                    .map(|x| x.url.as_str())
-                    .collect::<Vec<&str>>()
-                    .join("\n")
-            );
-            self.project_logger.log_error(&fail_url_list);
-            let fail_url_message = format!(
-                "The urls starting with {:?} has {} out of {} fail urls.",
-                fail_list.first(),
-                fail_list.len(),
-                url_file_list.len()
-            );
-            self.slack_messenger.retry_send_message(
-                request_setting.calling_func,
-                &fail_url_message,
-                request_setting.log_only,
-            );
-        }
-        fail_list
     }
 
-    fn url_from_google_sheet_link(google_sheet_key: &str) -> Url {
-        let (replace_token_from, replace_token_to) = Self::GOOGLE_SHEET_REPLACE_TOKEN;
-        let csv_link = format!(
-            "{}{}",
-            Self::GOOGLE_SHEET_URL,
-            google_sheet_key.replace(replace_token_from, replace_token_to,)
-        );
-        match Url::parse(&csv_link) {
-            Ok(u) => u,
-            Err(e) => panic!("Unable to parse the google sheet link {google_sheet_key}. {e}"),
+    pub async fn close_web_driver_pool(&self, web_driver_pool: &WebDriverPool<'a>) {
+        for session in web_driver_pool.drain() {
+            self.discard_web_driver(session.web_driver).await;
         }
     }
 
-    pub async fn download_google_sheet(
+    pub async fn browse_request_with_proxy<F>(
         &self,
-        google_sheet_key: &str,
-        request_builder_func: fn(Url) -> RequestBuilder,
-        check_func: fn(&str) -> ResponseCheckResult,
-    ) -> ResponseCheckResult {
-        let google_sheet_url = Self::url_from_google_sheet_link(google_sheet_key);
-        self.simple_request(&google_sheet_url, request_builder_func, check_func)
+        url: &Url,
+        proxy: &BrowserProxy,
+        browser: &Capabilities,
+        browse_action: &F,
+        check_func: &dyn ResponseValidator,
+    ) -> ResponseCheckResult
+    where
+        F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
+    {
+        let browser_with_proxy = self.set_capabilities_proxy(browser, proxy);
+        let web_driver = match self.set_web_driver(browser_with_proxy).await {
+            Ok(web_driver) => web_driver,
+            Err(e) => {
+                return ResponseCheckResult::ErrContinue(ScrapeFailure::LoadFailed(e.to_string()))
+            }
+        };
+        self.check_browse_response(web_driver, url, browse_action, check_func)
             .await
     }
 
-    pub fn convert_google_sheet_string_to_data_frame(google_sheet_csv: &str) -> Option<DataFrame> {
-        let cursor = Cursor::new(google_sheet_csv);
-        CsvReadOptions::default()
-            .with_has_header(true)
-            .into_reader_with_file_handle(cursor)
-            .finish()
-            .ok()
-    }
-
-    pub async fn browse_page(web_driver: &mut WebDriver, url: &Url) -> WebDriverResult<()> {
-        web_driver.goto(url.clone()).await
-    }
-
-    pub async fn browse_request<F>(
-        web_driver: &mut WebDriver,
+    #[allow(clippy::too_many_arguments)]
+    pub async fn browse_request_with_proxy_at_port<F>(
+        &self,
         url: &Url,
+        proxy: &BrowserProxy,
+        browser: &Capabilities,
+        port: u32,
         browse_action: &F,
-    ) -> WebDriverResult<String>
+        check_func: &dyn ResponseValidator,
+    ) -> ResponseCheckResult
     where
         F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
     {
-        Self::browse_page(web_driver, url).await?;
-        browse_action(web_driver).await?;
-        web_driver.source().await
+        let browser_with_proxy = self.set_capabilities_proxy(browser, proxy);
+        let web_driver = match self.set_web_driver_at_port(browser_with_proxy, port).await {
+            Ok(web_driver) => web_driver,
+            Err(e) => {
+                return ResponseCheckResult::ErrContinue(ScrapeFailure::LoadFailed(e.to_string()))
+            }
+        };
+        self.check_browse_response(web_driver, url, browse_action, check_func)
+            .await
     }
 
-    pub async fn simple_browse_request<F>(
+    async fn check_browse_response<F>(
         &self,
+        mut web_driver: WebDriver,
         url: &Url,
-        browser: &ChromeCapabilities,
         browse_action: &F,
-        check_func: fn(&str) -> ResponseCheckResult,
+        check_func: &dyn ResponseValidator,
     ) -> ResponseCheckResult
     where
         F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
     {
-        let mut web_driver = self.set_web_driver(browser.clone()).await;
-        match Self::browse_request(&mut web_driver, url, browse_action).await {
-            Ok(response) => match check_func(&response) {
+        let browse_result = Self::browse_request(&mut web_driver, url, browse_action).await;
+        if browse_result.is_ok() {
+            self.record_har(&web_driver, url).await;
+        }
+        match browse_result {
+            Ok(response) => match check_func.check(&response) {
                 ResponseCheckResult::Ok(response) => {
                     let debug_str = format!("Request {} browsed.", url.as_str());
                     self.project_logger.log_debug(&debug_str);
-                    self.close_web_driver(web_driver).await;
+                    self.discard_web_driver(web_driver).await;
                     ResponseCheckResult::Ok(response)
                 }
                 ResponseCheckResult::ErrContinue(e) => {
                     let warn_str =
                         format!("Checking for the response failed for {}. {e}", url.as_str());
                     self.project_logger.log_warn(&warn_str);
-                    self.close_web_driver(web_driver).await;
+                    self.discard_web_driver(web_driver).await;
                     ResponseCheckResult::ErrContinue(e)
                 }
-                ResponseCheckResult::ErrTerminate(e) => {
+                ResponseCheckResult::ErrTerminate(e) | ResponseCheckResult::TooLarge(e) => {
                     let error_str = format!("Terminate to load the page {}. {e}", url.as_str());
                     self.project_logger.log_error(&error_str);
-                    self.close_web_driver(web_driver).await;
+                    self.discard_web_driver(web_driver).await;
                     ResponseCheckResult::ErrTerminate(e)
                 }
+                ResponseCheckResult::Blocked(e) => {
+                    let warn_str = format!("Blocked when loading the page {}. {e}", url.as_str());
+                    self.project_logger.log_warn(&warn_str);
+                    self.discard_web_driver(web_driver).await;
+                    ResponseCheckResult::Blocked(e)
+                }
             },
             Err(e) => {
                 let warn_str = format!("Unable to browse the page {}. {e}", url.as_str());
                 self.project_logger.log_warn(&warn_str);
-                self.close_web_driver(web_driver).await;
-                ResponseCheckResult::ErrContinue(e.to_string())
+                self.discard_web_driver(web_driver).await;
+                ResponseCheckResult::ErrContinue(ScrapeFailure::LoadFailed(e.to_string()))
             }
         }
     }
 
-    pub async fn browse_request_with_proxy<F>(
+    async fn browse_with_session_choice<F>(
         &self,
         url: &Url,
-        proxy: &BrowserProxy,
-        browser: &ChromeCapabilities,
+        browser: &Capabilities,
         browse_action: &F,
-        check_func: fn(&str) -> ResponseCheckResult,
+        check_func: &dyn ResponseValidator,
     ) -> ResponseCheckResult
     where
         F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
     {
-        let browser_with_proxy = self.set_browser_proxy(browser, proxy);
-        let mut web_driver = self.set_web_driver(browser_with_proxy).await;
-        match Self::browse_request(&mut web_driver, url, browse_action).await {
-            Ok(response) => match check_func(&response) {
-                ResponseCheckResult::Ok(response) => {
-                    let debug_str = format!("Request {} browsed.", url.as_str());
-                    self.project_logger.log_debug(&debug_str);
-                    self.close_web_driver(web_driver).await;
-                    ResponseCheckResult::Ok(response)
+        match self.web_driver_pool.as_deref() {
+            Some(web_driver_pool) => {
+                self.pooled_browse_request(web_driver_pool, url, browser, browse_action, check_func)
+                    .await
+            }
+            None => {
+                self.simple_browse_request_with_capabilities(
+                    url,
+                    browser,
+                    browse_action,
+                    check_func,
+                )
+                .await
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[tracing::instrument(name = "scrape_url", skip_all, fields(url = %url_file.url, tier = "browser", status, retries, duration_ms))]
+    async fn browse_and_save_content_with_outcome<F>(
+        &self,
+        url_file: &UrlFile,
+        browser: &Capabilities,
+        folder_path: &Path,
+        browse_action: &F,
+        check_func: &dyn ResponseValidator,
+        url_request_options: UrlRequestOptions<'a>,
+    ) -> ScrapeOutcome
+    where
+        F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
+    {
+        let UrlRequestOptions {
+            in_s3,
+            max_attempts,
+            calling_func,
+            log_only,
+            record_domain_outcome,
+            ..
+        } = url_request_options;
+        let started_at = Utc::now();
+        if self.is_domain_tripped(&url_file.url) {
+            self.record_url_failure(url_file, ScrapeStatus::Tripped, 0, started_at);
+            return ScrapeOutcome::skipped(ScrapeStatus::Tripped);
+        }
+        let start_time = Instant::now();
+        let mut attempts = 0;
+        let mut status = ScrapeStatus::Failed;
+        let mut latency = None;
+        let mut bytes = None;
+        while attempts < max_attempts && status == ScrapeStatus::Failed {
+            attempts += 1;
+            let attempt_time = Instant::now();
+            let response = self
+                .browse_with_session_choice(&url_file.url, browser, browse_action, check_func)
+                .await;
+            latency = Some(attempt_time.elapsed());
+            match response {
+                ResponseCheckResult::Ok(content) => {
+                    let save_result = self
+                        .save_url_content(
+                            url_file,
+                            folder_path,
+                            content.as_bytes(),
+                            &ContentType::default(),
+                            in_s3,
+                            attempts,
+                            started_at,
+                        )
+                        .await;
+                    if save_result.is_ok() {
+                        bytes = Some(content.len() as u64);
+                        status = ScrapeStatus::Success;
+                    } else {
+                        status = ScrapeStatus::Terminated;
+                    }
                 }
-                ResponseCheckResult::ErrContinue(e) => {
-                    let warn_str =
-                        format!("Checking for the response failed for {}. {e}", url.as_str());
-                    self.project_logger.log_warn(&warn_str);
-                    self.close_web_driver(web_driver).await;
-                    ResponseCheckResult::ErrContinue(e)
+                ResponseCheckResult::ErrContinue(_) => {
+                    self.clock.sleep(self.retry_sleep).await;
                 }
-                ResponseCheckResult::ErrTerminate(e) => {
-                    let error_str = format!("Terminate to load the page {}. {e}", url.as_str());
-                    self.project_logger.log_error(&error_str);
-                    self.close_web_driver(web_driver).await;
-                    ResponseCheckResult::ErrTerminate(e)
+                ResponseCheckResult::ErrTerminate(_) | ResponseCheckResult::TooLarge(_) => {
+                    status = ScrapeStatus::Terminated;
+                }
+                ResponseCheckResult::Blocked(_) => {
+                    status = ScrapeStatus::Blocked;
                 }
-            },
-            Err(e) => {
-                let warn_str = format!("Unable to browse the page {}. {e}", url.as_str());
-                self.project_logger.log_warn(&warn_str);
-                self.close_web_driver(web_driver).await;
-                ResponseCheckResult::ErrContinue(e.to_string())
             }
         }
+        if status != ScrapeStatus::Success {
+            self.record_url_failure(url_file, status, attempts, started_at);
+        }
+        if record_domain_outcome {
+            self.record_domain_outcome(
+                &url_file.url,
+                status == ScrapeStatus::Success,
+                calling_func,
+                log_only,
+            );
+        }
+        Self::record_scrape_span(&status, attempts, start_time);
+        ScrapeOutcome {
+            status,
+            attempts,
+            latency,
+            bytes,
+        }
     }
 
     async fn browse_and_save_content<F>(
         &self,
         url_file: &UrlFile,
-        browser: &ChromeCapabilities,
+        browser: &Capabilities,
         folder_path: &Path,
         browse_action: &F,
-        check_func: fn(&str) -> ResponseCheckResult,
-        in_s3: bool,
+        check_func: &dyn ResponseValidator,
+        browse_setting: &BrowseSetting<'a>,
     ) -> Option<UrlFile>
     where
         F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
     {
-        if let ResponseCheckResult::Ok(content) = self
-            .simple_browse_request(&url_file.url, browser, browse_action, check_func)
-            .await
-        {
-            self.save_request_content(folder_path, &url_file.file_name, &content, in_s3)
+        let started_at = Utc::now();
+        if self.is_domain_tripped(&url_file.url) {
+            self.record_url_failure(url_file, ScrapeStatus::Tripped, 0, started_at);
+            return Some(url_file.clone());
+        }
+        let response = self
+            .browse_with_session_choice(&url_file.url, browser, browse_action, check_func)
+            .await;
+        if let ResponseCheckResult::Ok(content) = response {
+            self.record_domain_outcome(
+                &url_file.url,
+                true,
+                browse_setting.calling_func,
+                browse_setting.log_only,
+            );
+            let save_result = self
+                .save_url_content(
+                    url_file,
+                    folder_path,
+                    content.as_bytes(),
+                    &ContentType::default(),
+                    browse_setting.in_s3,
+                    1,
+                    started_at,
+                )
                 .await;
-            None
+            if save_result.is_ok() {
+                None
+            } else {
+                self.record_url_failure(url_file, ScrapeStatus::Terminated, 1, started_at);
+                Some(url_file.clone())
+            }
         } else {
+            self.record_url_failure(url_file, response.get_status(), 1, started_at);
+            self.record_domain_outcome(
+                &url_file.url,
+                false,
+                browse_setting.calling_func,
+                browse_setting.log_only,
+            );
             Some(url_file.clone())
         }
     }
 
+    async fn browse_and_save_content_with_retry<F>(
+        &self,
+        url_file: &UrlFile,
+        browser: &Capabilities,
+        folder_path: &Path,
+        browse_action: &F,
+        check_func: &dyn ResponseValidator,
+        browse_setting: &BrowseSetting<'a>,
+    ) -> Option<UrlFile>
+    where
+        F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
+    {
+        let mut counter = 0;
+        while counter < self.num_retry {
+            if self
+                .browse_and_save_content(
+                    url_file,
+                    browser,
+                    folder_path,
+                    browse_action,
+                    check_func,
+                    browse_setting,
+                )
+                .await
+                .is_none()
+            {
+                return None;
+            }
+            counter += 1;
+            self.clock.sleep(self.retry_sleep).await;
+        }
+        Some(url_file.clone())
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn browse_with_proxy_and_save_content<F>(
         &self,
         url_file: &UrlFile,
         proxy: &BrowserProxy,
-        browser: &ChromeCapabilities,
+        browser: &Capabilities,
         folder_path: &Path,
         browse_action: &F,
-        check_func: fn(&str) -> ResponseCheckResult,
-        in_s3: bool,
+        check_func: &dyn ResponseValidator,
+        browse_setting: &BrowseSetting<'a>,
     ) -> Option<UrlFile>
     where
         F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
     {
-        if let ResponseCheckResult::Ok(content) = self
+        let started_at = Utc::now();
+        if self.is_domain_tripped(&url_file.url) {
+            self.record_url_failure(url_file, ScrapeStatus::Tripped, 0, started_at);
+            return Some(url_file.clone());
+        }
+        let response = self
             .browse_request_with_proxy(&url_file.url, proxy, browser, browse_action, check_func)
-            .await
-        {
-            self.save_request_content(folder_path, &url_file.file_name, &content, in_s3)
+            .await;
+        if let ResponseCheckResult::Ok(content) = response {
+            self.record_domain_outcome(
+                &url_file.url,
+                true,
+                browse_setting.calling_func,
+                browse_setting.log_only,
+            );
+            let save_result = self
+                .save_url_content(
+                    url_file,
+                    folder_path,
+                    content.as_bytes(),
+                    &ContentType::default(),
+                    browse_setting.in_s3,
+                    1,
+                    started_at,
+                )
                 .await;
-            None
+            if save_result.is_ok() {
+                None
+            } else {
+                self.record_url_failure(url_file, ScrapeStatus::Terminated, 1, started_at);
+                Some(url_file.clone())
+            }
         } else {
+            self.record_url_failure(url_file, response.get_status(), 1, started_at);
+            self.record_domain_outcome(
+                &url_file.url,
+                false,
+                browse_setting.calling_func,
+                browse_setting.log_only,
+            );
             Some(url_file.clone())
         }
     }
 
-    pub async fn multiple_browse_requests_sequential<F>(
+    pub async fn multiple_browse_requests_sequential<F>(
+        &self,
+        url_file_list: &[UrlFile],
+        browser: &Capabilities,
+        folder_path: &Path,
+        browse_action: &F,
+        check_func: &dyn ResponseValidator,
+        browse_setting: BrowseSetting<'a>,
+    ) -> Vec<UrlFile>
+    where
+        F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
+    {
+        DryRun::scope(browse_setting.dry_run, async {
+            let browser = &browse_setting
+                .apply_timeout(self.get_stealth_browser(browser, browse_setting.stealth));
+            let deadline = browse_setting.get_deadline(self.clock.as_ref());
+            let mut fail_list = Vec::new();
+            let mut halted_list = Vec::new();
+            for (index, url_file) in tqdm::tqdm(url_file_list.iter().enumerate()) {
+                if self.is_shutdown_requested()
+                    || time_operation::is_deadline_reached(deadline, self.clock.as_ref())
+                {
+                    halted_list = url_file_list[index..].to_vec();
+                    break;
+                }
+                let mut counter = 0;
+                let mut fail = true;
+                while counter < self.num_retry && fail {
+                    if self
+                        .browse_and_save_content(
+                            url_file,
+                            browser,
+                            folder_path,
+                            browse_action,
+                            check_func,
+                            &browse_setting,
+                        )
+                        .await
+                        .is_some()
+                    {
+                        counter += 1;
+                        self.clock.sleep(self.retry_sleep).await;
+                    } else {
+                        fail = false;
+                    }
+                }
+                if fail {
+                    fail_list.push(url_file.clone())
+                };
+                self.clock
+                    .random_sleep(browse_setting.get_sleep_range(self.consecutive_sleep))
+                    .await;
+            }
+            if !fail_list.is_empty() {
+                let fail_url_list = format!(
+                    "The following urls were not browsed successfully:\n\n {}",
+                    fail_list
+                        .iter()
+                        .map(|x| x.url.as_str())
+                        .collect::<Vec<&str>>()
+                        .join("\n")
+                );
+                self.project_logger.log_error(&fail_url_list);
+                let fail_url_message = format!(
+                    "The urls starting with {:?} has {} out of {} fail urls.",
+                    fail_list.first(),
+                    fail_list.len(),
+                    url_file_list.len()
+                );
+                self.slack_messenger.retry_send_message(
+                    browse_setting.calling_func,
+                    &fail_url_message,
+                    browse_setting.log_only,
+                );
+            }
+            self.notify_deadline_reached(
+                &halted_list,
+                url_file_list.len(),
+                browse_setting.max_total_duration,
+                browse_setting.calling_func,
+                browse_setting.log_only,
+            );
+            fail_list.extend(halted_list);
+            if self.is_shutdown_requested() {
+                self.shutdown_with_pending_list(
+                    &fail_list,
+                    folder_path,
+                    browse_setting.in_s3,
+                    browse_setting.calling_func,
+                    browse_setting.log_only,
+                )
+                .await;
+            }
+            fail_list
+        })
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn multiple_browse_requests_with_proxy<F>(
+        &self,
+        url_file_list: &Vec<UrlFile>,
+        browser: &Capabilities,
+        folder_path: &Path,
+        browse_action: &F,
+        check_func: &dyn ResponseValidator,
+        browse_setting: BrowseSetting<'a>,
+    ) -> Vec<UrlFile>
+    where
+        F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
+    {
+        DryRun::scope(browse_setting.dry_run, async {
+            let browser = &browse_setting
+                .apply_timeout(self.get_stealth_browser(browser, browse_setting.stealth));
+            let mut counter = 0;
+            let mut pending_url_file_list = url_file_list.to_owned();
+            let deadline = browse_setting.get_deadline(self.clock.as_ref());
+            let mut halted_list = Vec::new();
+            while counter < self.num_retry
+                && !pending_url_file_list.is_empty()
+                && halted_list.is_empty()
+            {
+                let mut fail_list = Vec::new();
+                let provider_proxy_list = match self.proxy_provider.as_deref() {
+                    Some(proxy_provider) => self.fetch_provider_proxy(proxy_provider).await,
+                    None => Vec::new(),
+                };
+                // Without a provider the free proxy list of ScraperProxy is used, which needs the
+                // proxy feature.
+                #[cfg(feature = "proxy")]
+                let mut proxy_list = match self.proxy_provider.as_deref() {
+                    Some(_) => None,
+                    None => Some(ScraperProxy::generate_proxy().await),
+                };
+                for chunk in pending_url_file_list
+                    .iter()
+                    .chunks(self.chunk_size_browse)
+                    .into_iter()
+                {
+                    if self.is_shutdown_requested()
+                        || time_operation::is_deadline_reached(deadline, self.clock.as_ref())
+                    {
+                        halted_list.extend(chunk.cloned());
+                        continue;
+                    }
+                    let pending_chunk: Vec<&UrlFile> = chunk.collect();
+                    let chunk_proxy_list: Vec<(BrowserProxy, Option<ProxyEndpoint>)> =
+                        match self.proxy_provider.as_deref() {
+                            Some(proxy_provider) => Self::sample_provider_proxy(
+                                proxy_provider,
+                                &provider_proxy_list,
+                                pending_chunk.len(),
+                            )
+                            .into_iter()
+                            .map(|(proxy_endpoint, _)| {
+                                (proxy_endpoint.get_browser_proxy(), Some(proxy_endpoint))
+                            })
+                            .collect(),
+                            #[cfg(feature = "proxy")]
+                            None => proxy_list.as_mut().map_or_else(Vec::new, |proxy_list| {
+                                ScraperProxy::sample_proxy(proxy_list, self.chunk_size_browse)
+                                    .map(|proxy_pair| (proxy_pair.browser_proxy.clone(), None))
+                                    .collect()
+                            }),
+                            #[cfg(not(feature = "proxy"))]
+                            None => Vec::new(),
+                        };
+                    let request_tasks = chunk_proxy_list.iter().zip(pending_chunk.iter()).map(
+                        |((browser_proxy, _), url_file)| {
+                            self.browse_with_proxy_and_save_content(
+                                url_file,
+                                browser_proxy,
+                                browser,
+                                folder_path,
+                                browse_action,
+                                check_func,
+                                &browse_setting,
+                            )
+                        },
+                    );
+                    let request_futures = future::join_all(request_tasks).await;
+                    fail_list.extend(
+                        pending_chunk
+                            .iter()
+                            .skip(request_futures.len())
+                            .map(|url_file| (*url_file).clone()),
+                    );
+                    for (fail_url_file, (_, proxy_endpoint)) in
+                        request_futures.into_iter().zip(chunk_proxy_list.iter())
+                    {
+                        let Some(fail_url_file) = fail_url_file else {
+                            continue;
+                        };
+                        if let (Some(proxy_provider), Some(proxy_endpoint)) =
+                            (self.proxy_provider.as_deref(), proxy_endpoint)
+                        {
+                            proxy_provider.report_failure(proxy_endpoint);
+                        }
+                        fail_list.push(fail_url_file);
+                    }
+                }
+                pending_url_file_list = fail_list;
+                counter += 1;
+            }
+            if !pending_url_file_list.is_empty() {
+                let fail_url_list = format!(
+                    "The following urls were not browsed successfully:\n\n {}",
+                    pending_url_file_list
+                        .iter()
+                        .map(|x| x.url.as_str())
+                        .collect::<Vec<&str>>()
+                        .join("\n")
+                );
+                self.project_logger.log_error(&fail_url_list);
+                let fail_url_message = format!(
+                    "The urls starting with {:?} has {} out of {} fail urls.",
+                    pending_url_file_list.first(),
+                    pending_url_file_list.len(),
+                    url_file_list.len()
+                );
+                self.slack_messenger.retry_send_message(
+                    browse_setting.calling_func,
+                    &fail_url_message,
+                    browse_setting.log_only,
+                );
+            }
+            self.notify_deadline_reached(
+                &halted_list,
+                url_file_list.len(),
+                browse_setting.max_total_duration,
+                browse_setting.calling_func,
+                browse_setting.log_only,
+            );
+            pending_url_file_list.extend(halted_list);
+            if self.is_shutdown_requested() {
+                self.shutdown_with_pending_list(
+                    &pending_url_file_list,
+                    folder_path,
+                    browse_setting.in_s3,
+                    browse_setting.calling_func,
+                    browse_setting.log_only,
+                )
+                .await;
+            }
+            pending_url_file_list
+        })
+        .await
+    }
+
+    // The driver of the port is restarted before every chunk, so the memory held by chromedriver
+    // is released after at most pages_per_driver pages. Pages within a chunk are browsed one at
+    // a time. Returns the failed and the halted urls.
+    #[allow(clippy::too_many_arguments)]
+    async fn browse_chunks_at_port<F>(
+        &self,
+        web_driver_manager: &WebDriverManager<'a>,
+        port: u32,
+        chunk_list: Vec<(&[UrlFile], Vec<BrowserProxy>)>,
+        browser: &Capabilities,
+        folder_path: &Path,
+        browse_action: &F,
+        check_func: &dyn ResponseValidator,
+        browse_setting: &BrowseSetting<'a>,
+        deadline: Option<DateTime<Utc>>,
+    ) -> (Vec<UrlFile>, Vec<UrlFile>)
+    where
+        F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
+    {
+        let mut fail_list = Vec::new();
+        let mut halted_list = Vec::new();
+        for (chunk, proxy_list) in chunk_list {
+            if self.is_shutdown_requested()
+                || time_operation::is_deadline_reached(deadline, self.clock.as_ref())
+            {
+                halted_list.extend_from_slice(chunk);
+                continue;
+            }
+            if !web_driver_manager.restart_driver(port).await {
+                let warn_str = format!(
+                    "Web driver at port {port} is not healthy. Skip the chunk of {} urls.",
+                    chunk.len()
+                );
+                self.project_logger.log_warn(&warn_str);
+                fail_list.extend_from_slice(chunk);
+                continue;
+            }
+            for (index, url_file) in chunk.iter().enumerate() {
+                let Some(proxy) = proxy_list.get(index % proxy_list.len().max(1)) else {
+                    fail_list.push(url_file.clone());
+                    continue;
+                };
+                let started_at = Utc::now();
+                if self.is_domain_tripped(&url_file.url) {
+                    self.record_url_failure(url_file, ScrapeStatus::Tripped, 0, started_at);
+                    fail_list.push(url_file.clone());
+                    continue;
+                }
+                match self
+                    .browse_request_with_proxy_at_port(
+                        &url_file.url,
+                        proxy,
+                        browser,
+                        port,
+                        browse_action,
+                        check_func,
+                    )
+                    .await
+                {
+                    ResponseCheckResult::Ok(content) => {
+                        self.record_domain_outcome(
+                            &url_file.url,
+                            true,
+                            browse_setting.calling_func,
+                            browse_setting.log_only,
+                        );
+                        let save_result = self
+                            .save_url_content(
+                                url_file,
+                                folder_path,
+                                content.as_bytes(),
+                                &ContentType::default(),
+                                browse_setting.in_s3,
+                                1,
+                                started_at,
+                            )
+                            .await;
+                        if save_result.is_err() {
+                            self.record_url_failure(
+                                url_file,
+                                ScrapeStatus::Terminated,
+                                1,
+                                started_at,
+                            );
+                            fail_list.push(url_file.clone());
+                        }
+                    }
+                    response => {
+                        self.record_url_failure(url_file, response.get_status(), 1, started_at);
+                        self.record_domain_outcome(
+                            &url_file.url,
+                            false,
+                            browse_setting.calling_func,
+                            browse_setting.log_only,
+                        );
+                        fail_list.push(url_file.clone());
+                    }
+                }
+            }
+        }
+        (fail_list, halted_list)
+    }
+
+    // The urls are split into chunks of pages_per_driver which are distributed round robin over
+    // the ports of the web driver manager. The ports browse their chunks concurrently.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn multiple_browse_requests_with_driver_recycling<F>(
         &self,
         url_file_list: &[UrlFile],
-        browser: &ChromeCapabilities,
+        browser: &Capabilities,
         folder_path: &Path,
         browse_action: &F,
-        check_func: fn(&str) -> ResponseCheckResult,
+        check_func: &dyn ResponseValidator,
         browse_setting: BrowseSetting<'a>,
     ) -> Vec<UrlFile>
     where
         F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
     {
-        let mut fail_list = Vec::new();
-        for url_file in tqdm::tqdm(url_file_list.iter()) {
+        DryRun::scope(browse_setting.dry_run, async {
+            let browser = &browse_setting
+                .apply_timeout(self.get_stealth_browser(browser, browse_setting.stealth));
+            let web_driver_manager = match self.web_driver_manager.as_deref() {
+                Some(web_driver_manager) => web_driver_manager,
+                None => {
+                    let warn_str =
+                        "No web driver manager is set. Browse the urls without driver recycling.";
+                    self.project_logger.log_warn(warn_str);
+                    return self
+                        .multiple_browse_requests_with_proxy(
+                            &url_file_list.to_vec(),
+                            browser,
+                            folder_path,
+                            browse_action,
+                            check_func,
+                            browse_setting,
+                        )
+                        .await;
+                }
+            };
+            let ports = web_driver_manager.get_ports();
             let mut counter = 0;
-            let mut fail = true;
-            while counter < self.num_retry && fail {
-                if self
-                    .browse_and_save_content(
-                        url_file,
+            let mut pending_url_file_list = url_file_list.to_vec();
+            let deadline = browse_setting.get_deadline(self.clock.as_ref());
+            let mut halted_list = Vec::new();
+            while counter < self.num_retry
+                && !pending_url_file_list.is_empty()
+                && halted_list.is_empty()
+            {
+                let provider_proxy_list = match self.proxy_provider.as_deref() {
+                    Some(proxy_provider) => self.fetch_provider_proxy(proxy_provider).await,
+                    None => Vec::new(),
+                };
+                // Without a provider the free proxy list of ScraperProxy is used, which needs the
+                // proxy feature.
+                #[cfg(feature = "proxy")]
+                let mut proxy_list = match self.proxy_provider.as_deref() {
+                    Some(_) => None,
+                    None => Some(ScraperProxy::generate_proxy().await),
+                };
+                let mut port_chunk_list: Vec<Vec<(&[UrlFile], Vec<BrowserProxy>)>> =
+                    vec![Vec::new(); ports.len()];
+                for (index, chunk) in pending_url_file_list
+                    .chunks(self.pages_per_driver)
+                    .enumerate()
+                {
+                    let chunk_proxy_list = match self.proxy_provider.as_deref() {
+                        Some(proxy_provider) => Self::sample_provider_proxy(
+                            proxy_provider,
+                            &provider_proxy_list,
+                            chunk.len(),
+                        )
+                        .into_iter()
+                        .map(|(proxy_endpoint, _)| proxy_endpoint.get_browser_proxy())
+                        .collect(),
+                        #[cfg(feature = "proxy")]
+                        None => proxy_list.as_mut().map_or_else(Vec::new, |proxy_list| {
+                            ScraperProxy::sample_proxy(proxy_list, chunk.len())
+                                .map(|proxy_pair| proxy_pair.browser_proxy.clone())
+                                .collect()
+                        }),
+                        #[cfg(not(feature = "proxy"))]
+                        None => Vec::new(),
+                    };
+                    port_chunk_list[index % ports.len()].push((chunk, chunk_proxy_list));
+                }
+                let port_tasks = ports.iter().zip(port_chunk_list).map(|(port, chunk_list)| {
+                    self.browse_chunks_at_port(
+                        web_driver_manager,
+                        *port,
+                        chunk_list,
                         browser,
                         folder_path,
                         browse_action,
                         check_func,
-                        browse_setting.in_s3,
+                        &browse_setting,
+                        deadline,
                     )
-                    .await
-                    .is_some()
-                {
-                    counter += 1;
-                    time_operation::async_sleep(self.retry_sleep).await;
-                } else {
-                    fail = false;
+                });
+                let mut fail_list = Vec::new();
+                for (port_fail_list, port_halted_list) in future::join_all(port_tasks).await {
+                    fail_list.extend(port_fail_list);
+                    halted_list.extend(port_halted_list);
                 }
+                pending_url_file_list = fail_list;
+                counter += 1;
             }
-            if fail {
-                fail_list.push(url_file.clone())
-            };
-            time_operation::async_random_sleep(self.consecutive_sleep).await;
-        }
-        if !fail_list.is_empty() {
-            let fail_url_list = format!(
-                "The following urls were not browsed successfully:\n\n {}",
-                fail_list
-                    .iter()
-                    .map(|x| x.url.as_str())
-                    .collect::<Vec<&str>>()
-                    .join("\n")
-            );
-            self.project_logger.log_error(&fail_url_list);
-            let fail_url_message = format!(
-                "The urls starting with {:?} has {} out of {} fail urls.",
-                fail_list.first(),
-                fail_list.len(),
-                url_file_list.len()
-            );
-            self.slack_messenger.retry_send_message(
+            if !pending_url_file_list.is_empty() {
+                let fail_url_list = format!(
+                    "The following urls were not browsed successfully:\n\n {}",
+                    pending_url_file_list
+                        .iter()
+                        .map(|x| x.url.as_str())
+                        .collect::<Vec<&str>>()
+                        .join("\n")
+                );
+                self.project_logger.log_error(&fail_url_list);
+                let fail_url_message = format!(
+                    "The urls starting with {:?} has {} out of {} fail urls.",
+                    pending_url_file_list.first(),
+                    pending_url_file_list.len(),
+                    url_file_list.len()
+                );
+                self.slack_messenger.retry_send_message(
+                    browse_setting.calling_func,
+                    &fail_url_message,
+                    browse_setting.log_only,
+                );
+            }
+            self.notify_deadline_reached(
+                &halted_list,
+                url_file_list.len(),
+                browse_setting.max_total_duration,
                 browse_setting.calling_func,
-                &fail_url_message,
                 browse_setting.log_only,
             );
-        }
-        fail_list
+            pending_url_file_list.extend(halted_list);
+            if self.is_shutdown_requested() {
+                self.shutdown_with_pending_list(
+                    &pending_url_file_list,
+                    folder_path,
+                    browse_setting.in_s3,
+                    browse_setting.calling_func,
+                    browse_setting.log_only,
+                )
+                .await;
+            }
+            pending_url_file_list
+        })
+        .await
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub async fn multiple_browse_requests_with_proxy<F>(
+    pub async fn multiple_browse_requests_with_web_driver_pool<F>(
         &self,
-        url_file_list: &Vec<UrlFile>,
-        browser: &ChromeCapabilities,
+        url_file_list: &[UrlFile],
+        browser: &Capabilities,
         folder_path: &Path,
         browse_action: &F,
-        check_func: fn(&str) -> ResponseCheckResult,
+        check_func: &dyn ResponseValidator,
         browse_setting: BrowseSetting<'a>,
     ) -> Vec<UrlFile>
     where
         F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
     {
-        let mut counter = 0;
-        let mut pending_url_file_list = url_file_list.to_owned();
-        while counter < self.num_retry && !pending_url_file_list.is_empty() {
+        DryRun::scope(browse_setting.dry_run, async {
+            let browser = &browse_setting
+                .apply_timeout(self.get_stealth_browser(browser, browse_setting.stealth));
+            let web_driver_pool = match self.web_driver_pool.as_deref() {
+                Some(web_driver_pool) => web_driver_pool,
+                None => {
+                    let warn_str = "No web driver pool is set. Browse the urls sequentially.";
+                    self.project_logger.log_warn(warn_str);
+                    return self
+                        .multiple_browse_requests_sequential(
+                            url_file_list,
+                            browser,
+                            folder_path,
+                            browse_action,
+                            check_func,
+                            browse_setting,
+                        )
+                        .await;
+                }
+            };
+            let deadline = browse_setting.get_deadline(self.clock.as_ref());
             let mut fail_list = Vec::new();
-            let mut proxy_list = ScraperProxy::generate_proxy().await;
-            for chunk in pending_url_file_list
-                .iter()
-                .chunks(Self::CHUNK_SIZE_BROWSE)
-                .into_iter()
-            {
-                let proxy_iter =
-                    ScraperProxy::sample_proxy(&mut proxy_list, Self::CHUNK_SIZE_BROWSE);
-                let request_tasks = proxy_iter.zip(chunk).map(|(proxy_pair, url_file)| {
-                    self.browse_with_proxy_and_save_content(
+            let mut halted_list = Vec::new();
+            for chunk in tqdm::tqdm(
+                url_file_list
+                    .iter()
+                    .chunks(self.chunk_size_browse)
+                    .into_iter(),
+            ) {
+                if self.is_shutdown_requested()
+                    || time_operation::is_deadline_reached(deadline, self.clock.as_ref())
+                {
+                    halted_list.extend(chunk.cloned());
+                    continue;
+                }
+                let request_tasks = chunk.map(|url_file| {
+                    self.browse_and_save_content_with_retry(
                         url_file,
-                        &proxy_pair.browser_proxy,
                         browser,
                         folder_path,
                         browse_action,
                         check_func,
-                        browse_setting.in_s3,
+                        &browse_setting,
                     )
                 });
                 let request_futures = future::join_all(request_tasks).await;
-                fail_list.extend(request_futures.into_iter().flatten())
+                fail_list.extend(request_futures.into_iter().flatten());
             }
-            pending_url_file_list = fail_list;
-            counter += 1;
-        }
-        if !pending_url_file_list.is_empty() {
-            let fail_url_list = format!(
-                "The following urls were not browsed successfully:\n\n {}",
-                pending_url_file_list
-                    .iter()
-                    .map(|x| x.url.as_str())
-                    .collect::<Vec<&str>>()
-                    .join("\n")
-            );
-            self.project_logger.log_error(&fail_url_list);
-            let fail_url_message = format!(
-                "The urls starting with {:?} has {} out of {} fail urls.",
-                pending_url_file_list.first(),
-                pending_url_file_list.len(),
-                url_file_list.len()
-            );
-            self.slack_messenger.retry_send_message(
+            self.close_web_driver_pool(web_driver_pool).await;
+            if !fail_list.is_empty() {
+                let fail_url_list = format!(
+                    "The following urls were not browsed successfully:\n\n {}",
+                    fail_list
+                        .iter()
+                        .map(|x| x.url.as_str())
+                        .collect::<Vec<&str>>()
+                        .join("\n")
+                );
+                self.project_logger.log_error(&fail_url_list);
+                let fail_url_message = format!(
+                    "The urls starting with {:?} has {} out of {} fail urls.",
+                    fail_list.first(),
+                    fail_list.len(),
+                    url_file_list.len()
+                );
+                self.slack_messenger.retry_send_message(
+                    browse_setting.calling_func,
+                    &fail_url_message,
+                    browse_setting.log_only,
+                );
+            }
+            self.notify_deadline_reached(
+                &halted_list,
+                url_file_list.len(),
+                browse_setting.max_total_duration,
                 browse_setting.calling_func,
-                &fail_url_message,
                 browse_setting.log_only,
             );
-        }
-        pending_url_file_list
+            fail_list.extend(halted_list);
+            if self.is_shutdown_requested() {
+                self.shutdown_with_pending_list(
+                    &fail_list,
+                    folder_path,
+                    browse_setting.in_s3,
+                    browse_setting.calling_func,
+                    browse_setting.log_only,
+                )
+                .await;
+            }
+            fail_list
+        })
+        .await
     }
 
+    #[cfg(feature = "proxy")]
     #[allow(clippy::too_many_arguments)]
     pub async fn multiple_browse_requests_with_private_vpn<F>(
         &self,
         url_file_list: &[UrlFile],
         private_vpn: &mut PrivateVpn,
-        browser: &ChromeCapabilities,
+        browser: &Capabilities,
         folder_path: &Path,
         browse_action: &F,
-        check_func: fn(&str) -> ResponseCheckResult,
+        check_func: &dyn ResponseValidator,
         browse_setting: BrowseSetting<'a>,
     ) -> Vec<UrlFile>
     where
         F: for<'b> AsyncFn<&'b mut WebDriver, Output = WebDriverResult<()>>,
     {
-        private_vpn.turn_on_vpn();
-        let mut fail_list = Vec::new();
-        for url_file in tqdm::tqdm(url_file_list.iter()) {
-            let mut counter = 0;
-            let mut fail = true;
-            while counter < self.num_retry && fail {
-                private_vpn.connect_vpn();
-                if self
-                    .browse_and_save_content(
-                        url_file,
-                        browser,
-                        folder_path,
-                        browse_action,
-                        check_func,
-                        browse_setting.in_s3,
-                    )
-                    .await
-                    .is_some()
+        DryRun::scope(browse_setting.dry_run, async {
+            let browser = &browse_setting
+                .apply_timeout(self.get_stealth_browser(browser, browse_setting.stealth));
+            private_vpn.turn_on_vpn();
+            let deadline = browse_setting.get_deadline(self.clock.as_ref());
+            let mut fail_list = Vec::new();
+            let mut halted_list = Vec::new();
+            for (index, url_file) in tqdm::tqdm(url_file_list.iter().enumerate()) {
+                if self.is_shutdown_requested()
+                    || time_operation::is_deadline_reached(deadline, self.clock.as_ref())
                 {
-                    counter += 1;
-                    time_operation::async_sleep(self.retry_sleep).await;
-                } else {
-                    fail = false;
+                    halted_list = url_file_list[index..].to_vec();
+                    break;
+                }
+                let mut counter = 0;
+                let mut fail = true;
+                while counter < self.num_retry && fail {
+                    private_vpn.connect_vpn();
+                    if self
+                        .browse_and_save_content(
+                            url_file,
+                            browser,
+                            folder_path,
+                            browse_action,
+                            check_func,
+                            &browse_setting,
+                        )
+                        .await
+                        .is_some()
+                    {
+                        counter += 1;
+                        self.clock.sleep(self.retry_sleep).await;
+                    } else {
+                        fail = false;
+                    }
                 }
+                if fail {
+                    fail_list.push(url_file.clone())
+                };
+                self.clock
+                    .random_sleep(browse_setting.get_sleep_range(self.consecutive_sleep))
+                    .await;
             }
-            if fail {
-                fail_list.push(url_file.clone())
-            };
-            time_operation::async_random_sleep(self.consecutive_sleep).await;
-        }
-        private_vpn.turn_off_vpn();
-        if !fail_list.is_empty() {
-            let fail_url_list = format!(
-                "The following urls were not browsed successfully:\n\n {}",
-                fail_list
-                    .iter()
-                    .map(|x| x.url.as_str())
-                    .collect::<Vec<&str>>()
-                    .join("\n")
-            );
-            self.project_logger.log_error(&fail_url_list);
-            let fail_url_message = format!(
-                "The urls starting with {:?} has {} out of {} fail urls.",
-                fail_list.first(),
-                fail_list.len(),
-                url_file_list.len()
-            );
-            self.slack_messenger.retry_send_message(
+            private_vpn.turn_off_vpn();
+            if !fail_list.is_empty() {
+                let fail_url_list = format!(
+                    "The following urls were not browsed successfully:\n\n {}",
+                    fail_list
+                        .iter()
+                        .map(|x| x.url.as_str())
+                        .collect::<Vec<&str>>()
+                        .join("\n")
+                );
+                self.project_logger.log_error(&fail_url_list);
+                let fail_url_message = format!(
+                    "The urls starting with {:?} has {} out of {} fail urls.",
+                    fail_list.first(),
+                    fail_list.len(),
+                    url_file_list.len()
+                );
+                self.slack_messenger.retry_send_message(
+                    browse_setting.calling_func,
+                    &fail_url_message,
+                    browse_setting.log_only,
+                );
+            }
+            self.notify_deadline_reached(
+                &halted_list,
+                url_file_list.len(),
+                browse_setting.max_total_duration,
                 browse_setting.calling_func,
-                &fail_url_message,
                 browse_setting.log_only,
             );
+            fail_list.extend(halted_list);
+            if self.is_shutdown_requested() {
+                self.shutdown_with_pending_list(
+                    &fail_list,
+                    folder_path,
+                    browse_setting.in_s3,
+                    browse_setting.calling_func,
+                    browse_setting.log_only,
+                )
+                .await;
+            }
+            fail_list
+        })
+        .await
+    }
+}
+
+// Each url runs in its own tokio task, so the scraper is shared as an Arc of a 'static scraper
+// built from Arc components, e.g. from AsyncWebScraperBuilder::build_shared.
+impl AsyncWebScraper<'static> {
+    pub async fn multiple_requests_spawned(
+        self: &Arc<Self>,
+        url_file_list: &[UrlFile],
+        request_builder_func: fn(Url) -> RequestBuilder,
+        folder_path: &Path,
+        check_func: Arc<dyn ResponseValidator>,
+        request_setting: &RequestSetting<'static>,
+    ) -> Vec<(UrlFile, ScrapeOutcome)> {
+        let semaphore = Arc::new(Semaphore::new(self.chunk_size_request));
+        let url_request_options = request_setting.get_url_request_options(self.num_retry);
+        // The spawned tasks leave the dry run scope of the caller, so each takes its own.
+        let dry_run = request_setting.dry_run || DryRun::is_enabled();
+        let request_handles: Vec<JoinHandle<ScrapeOutcome>> = url_file_list
+            .iter()
+            .map(|url_file| {
+                let async_web_scraper = Arc::clone(self);
+                let url_file = url_file.clone();
+                let folder_path = folder_path.to_path_buf();
+                let check_func = Arc::clone(&check_func);
+                let semaphore = Arc::clone(&semaphore);
+                tokio::spawn(DryRun::scope(dry_run, async move {
+                    let _permit = semaphore.acquire_owned().await;
+                    async_web_scraper
+                        .request_and_save_content_with_outcome(
+                            &url_file,
+                            request_builder_func,
+                            &folder_path,
+                            check_func.as_ref(),
+                            url_request_options,
+                        )
+                        .await
+                        .outcome
+                }))
+            })
+            .collect();
+        let mut outcome_list = Vec::with_capacity(url_file_list.len());
+        for (url_file, request_handle) in url_file_list.iter().zip(request_handles) {
+            let outcome = request_handle.await.unwrap_or_else(|e| {
+                let error_str = format!(
+                    "Unable to join the request task of {}. {e}",
+                    url_file.url.as_str()
+                );
+                self.project_logger.log_error(&error_str);
+                ScrapeOutcome::skipped(ScrapeStatus::Failed)
+            });
+            outcome_list.push((url_file.clone(), outcome));
         }
-        fail_list
+        outcome_list
     }
 }
 
@@ -1020,9 +4243,17 @@ where
 #[cfg(test)]
 mod tests {
 
+    use super::super::file_name_template::FileNameTemplate;
+    use super::super::http_transport::{MockResponse, MockTransport};
+    use super::super::mock_server::{
+        ScraperMockServer, BLOCKED_FIXTURE, ODDS_FIXTURE, TIMETABLE_FIXTURE,
+    };
     use super::*;
+    use crate::config::{AWSConfig, SlackConfig};
     use crate::utilities_function;
     use log::LevelFilter;
+    use polars::df;
+    #[cfg(feature = "proxy")]
     use sctys_proxy::ScraperProxy;
     use serde::Deserialize;
     use std::env;
@@ -1100,15 +4331,21 @@ mod tests {
         let url = Url::parse("https://tfl.gov.uk/travel-information/timetables/").unwrap();
         let request_builder_func = get_request_builder;
         let content = web_scraper
-            .simple_request(&url, request_builder_func, AsyncWebScraper::null_check_func)
+            .simple_request(
+                &url,
+                request_builder_func,
+                &AsyncWebScraper::null_check_func,
+            )
             .await;
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let file = "test_scrape.html";
         web_scraper
             .save_request_content(&folder_path, file, &content.get_content().unwrap(), false)
-            .await;
+            .await
+            .unwrap();
     }
 
+    #[cfg(feature = "proxy")]
     #[tokio::test]
     async fn test_simple_scraping_with_proxy() {
         let logger_name = "test_simple_scraping";
@@ -1143,16 +4380,18 @@ mod tests {
                 &url,
                 proxy_iter.next().unwrap().proxy.clone(),
                 request_builder_func,
-                AsyncWebScraper::null_check_func,
+                &AsyncWebScraper::null_check_func,
             )
             .await;
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let file = "test_scrape.html";
         web_scraper
             .save_request_content(&folder_path, file, &content.get_content().unwrap(), false)
-            .await;
+            .await
+            .unwrap();
     }
 
+    #[cfg(feature = "proxy")]
     #[tokio::test]
     async fn test_simple_scraping_with_private_proxy() {
         let logger_name = "test_simple_scraping";
@@ -1187,14 +4426,15 @@ mod tests {
                 &url,
                 proxy.unwrap().clone(),
                 request_builder_func,
-                AsyncWebScraper::null_check_func,
+                &AsyncWebScraper::null_check_func,
             )
             .await;
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let file = "test_scrape.html";
         web_scraper
             .save_request_content(&folder_path, file, &content.get_content().unwrap(), false)
-            .await;
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -1224,27 +4464,22 @@ mod tests {
         );
         let url_suffix = ["bakerloo", "central", "circle", "district", "jubilee"];
         let url = Url::parse("http://tfl.gov.uk/tube/timetable/").unwrap();
-        let file = "test_scrape{index}.html";
-        let url_file_list = Vec::from_iter(url_suffix.iter().enumerate().map(|(i, x)| {
-            UrlFile::new(
-                url.join(&format!("{x}/")).unwrap(),
-                file.replace("{index}", &i.to_string()),
-            )
-        }));
+        let url_list: Vec<Url> = url_suffix
+            .iter()
+            .map(|x| url.join(&format!("{x}/")).unwrap())
+            .collect();
+        let url_file_list = FileNameTemplate::new("test_scrape{index}.html")
+            .get_url_file_list(&url_list, &Utc::now());
         let request_builder_func = get_request_builder;
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let calling_func = utilities_function::function_name!(true);
-        let request_setting = RequestSetting {
-            calling_func,
-            log_only: true,
-            in_s3: false,
-        };
+        let request_setting = RequestSetting::new(calling_func, true, false);
         web_scraper
             .multiple_requests_sequential(
                 &url_file_list,
                 request_builder_func,
                 &folder_path,
-                AsyncWebScraper::null_check_func,
+                &AsyncWebScraper::null_check_func,
                 &request_setting,
             )
             .await;
@@ -1277,32 +4512,28 @@ mod tests {
         );
         let url_suffix = ["bakerloo", "central", "circle", "district", "jubilee"];
         let url = Url::parse("http://tfl.gov.uk/tube/timetable/").unwrap();
-        let file = "test_scrape{index}.html";
-        let url_file_list = Vec::from_iter(url_suffix.iter().enumerate().map(|(i, x)| {
-            UrlFile::new(
-                url.join(&format!("{x}/")).unwrap(),
-                file.replace("{index}", &i.to_string()),
-            )
-        }));
+        let url_list: Vec<Url> = url_suffix
+            .iter()
+            .map(|x| url.join(&format!("{x}/")).unwrap())
+            .collect();
+        let url_file_list = FileNameTemplate::new("test_scrape{index}.html")
+            .get_url_file_list(&url_list, &Utc::now());
         let request_builder_func = get_request_builder_with_proxy;
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let calling_func = utilities_function::function_name!(true);
-        let request_setting = RequestSetting {
-            calling_func,
-            log_only: true,
-            in_s3: false,
-        };
+        let request_setting = RequestSetting::new(calling_func, true, false);
         web_scraper
             .multiple_requests_with_proxy(
                 &url_file_list,
                 request_builder_func,
                 &folder_path,
-                AsyncWebScraper::null_check_func,
+                &AsyncWebScraper::null_check_func,
                 &request_setting,
             )
             .await;
     }
 
+    #[cfg(feature = "proxy")]
     #[tokio::test]
     async fn test_multiple_requests_with_private_proxy() {
         let logger_name = "test_multiple_requests";
@@ -1330,34 +4561,79 @@ mod tests {
         );
         let url_suffix = ["bakerloo", "central", "circle", "district", "jubilee"];
         let url = Url::parse("http://tfl.gov.uk/tube/timetable/").unwrap();
-        let file = "test_scrape{index}.html";
-        let url_file_list = Vec::from_iter(url_suffix.iter().enumerate().map(|(i, x)| {
-            UrlFile::new(
-                url.join(&format!("{x}/")).unwrap(),
-                file.replace("{index}", &i.to_string()),
-            )
-        }));
+        let url_list: Vec<Url> = url_suffix
+            .iter()
+            .map(|x| url.join(&format!("{x}/")).unwrap())
+            .collect();
+        let url_file_list = FileNameTemplate::new("test_scrape{index}.html")
+            .get_url_file_list(&url_list, &Utc::now());
         let mut private_proxy = PrivateProxy::default();
         let request_builder_func = get_request_builder_with_proxy;
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let calling_func = utilities_function::function_name!(true);
-        let request_setting = RequestSetting {
-            calling_func,
-            log_only: true,
-            in_s3: false,
-        };
+        let request_setting = RequestSetting::new(calling_func, true, false);
         web_scraper
             .multiple_requests_with_private_proxy(
                 &url_file_list,
                 &mut private_proxy,
                 request_builder_func,
                 &folder_path,
-                AsyncWebScraper::null_check_func,
+                &AsyncWebScraper::null_check_func,
                 &request_setting,
             )
             .await;
     }
 
+    #[tokio::test]
+    async fn test_multiple_requests_data_frame() {
+        let logger_name = "test_multiple_requests";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_netdata");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Info);
+        let channel_config_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Config")
+            .join("config_sctys_rust_utilities");
+        let channel_config_file = "messenger_channel_id.toml";
+        let channel_id = load_channel_id(&channel_config_path, channel_config_file);
+        let log_channel_id = channel_id.clone();
+        let slack_messenger = SlackMessenger::new(&channel_id, &log_channel_id, &project_logger);
+        let file_io = FileIO::new(&project_logger);
+        let aws_file_io = AWSFileIO::new(&project_logger).await;
+        let aws_bucket = "sctys";
+        let web_scraper = AsyncWebScraper::new(
+            &project_logger,
+            &slack_messenger,
+            &file_io,
+            &aws_file_io,
+            aws_bucket,
+        );
+        let data = df!(
+            "url" => ["http://tfl.gov.uk/tube/timetable/bakerloo/", "not a url"],
+            "file_name" => ["test_scrape0.html", "test_scrape1.html"],
+            "line" => ["bakerloo", "unknown"]
+        )
+        .unwrap();
+        let request_builder_func = get_request_builder;
+        let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
+        let calling_func = utilities_function::function_name!(true);
+        let request_setting = RequestSetting::new(calling_func, true, false);
+        let result = web_scraper
+            .multiple_requests_data_frame(
+                &data,
+                request_builder_func,
+                &folder_path,
+                &AsyncWebScraper::null_check_func,
+                &request_setting,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.width(), data.width() + 4);
+        let status = result.column(AsyncWebScraper::STATUS_COLUMN).unwrap();
+        assert_eq!(status.str().unwrap().get(1), Some("invalid_url"));
+    }
+
     #[tokio::test]
     async fn test_download_google_sheet() {
         let logger_name = "test_download_google_sheet";
@@ -1386,7 +4662,7 @@ mod tests {
         let url = "14Ep-CmoqWxrMU8HshxthRcdRW8IsXvh3n2-ZHVCzqzQ/edit#gid=1855920257";
         let request_builder_func = get_request_builder;
         let content = web_scraper
-            .download_google_sheet(url, request_builder_func, AsyncWebScraper::null_check_func)
+            .download_google_sheet(url, request_builder_func, &AsyncWebScraper::null_check_func)
             .await;
         let mut data = AsyncWebScraper::convert_google_sheet_string_to_data_frame(
             &content.get_content().unwrap(),
@@ -1415,7 +4691,104 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_simple_browsing() {
+    async fn test_simple_browsing() {
+        let logger_name = "test_simple_browsing";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_netdata");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Info);
+        let channel_config_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Config")
+            .join("config_sctys_rust_utilities");
+        let channel_config_file = "messenger_channel_id.toml";
+        let channel_id = load_channel_id(&channel_config_path, channel_config_file);
+        let log_channel_id = channel_id.clone();
+        let slack_messenger = SlackMessenger::new(&channel_id, &log_channel_id, &project_logger);
+        let file_io = FileIO::new(&project_logger);
+        let aws_file_io = AWSFileIO::new(&project_logger).await;
+        let aws_bucket = "sctys";
+        let mut web_scraper = AsyncWebScraper::new(
+            &project_logger,
+            &slack_messenger,
+            &file_io,
+            &aws_file_io,
+            aws_bucket,
+        );
+        let browse_action = extra_action;
+        let url = Url::parse("https://www.nowgoal.com").unwrap();
+        web_scraper.turn_on_chrome_process().unwrap();
+        let browser = web_scraper.get_default_browser();
+        let content = web_scraper
+            .simple_browse_request(
+                &url,
+                &browser,
+                &browse_action,
+                &AsyncWebScraper::null_check_func,
+            )
+            .await;
+        let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
+        let file = "test_browse.html";
+        web_scraper
+            .save_request_content(&folder_path, file, &content.get_content().unwrap(), false)
+            .await
+            .unwrap();
+        web_scraper.kill_chrome_process().unwrap();
+    }
+
+    #[cfg(feature = "proxy")]
+    #[tokio::test]
+    async fn test_simple_browsing_with_proxy() {
+        let logger_name = "test_simple_browsing";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_netdata");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Info);
+        let channel_config_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Config")
+            .join("config_sctys_rust_utilities");
+        let channel_config_file = "messenger_channel_id.toml";
+        let channel_id = load_channel_id(&channel_config_path, channel_config_file);
+        let log_channel_id = channel_id.clone();
+        let slack_messenger = SlackMessenger::new(&channel_id, &log_channel_id, &project_logger);
+        let file_io = FileIO::new(&project_logger);
+        let aws_file_io = AWSFileIO::new(&project_logger).await;
+        let aws_bucket = "sctys";
+        let mut web_scraper = AsyncWebScraper::new(
+            &project_logger,
+            &slack_messenger,
+            &file_io,
+            &aws_file_io,
+            aws_bucket,
+        );
+        let browse_action = extra_action;
+        let url = Url::parse("http://www.nowgoal.com").unwrap();
+        web_scraper.turn_on_chrome_process().unwrap();
+        let mut proxy_list = ScraperProxy::generate_proxy().await;
+        let mut proxy_iter = ScraperProxy::sample_proxy(&mut proxy_list, 1);
+        let browser = web_scraper.get_default_capabilities();
+        let content = web_scraper
+            .browse_request_with_proxy(
+                &url,
+                &proxy_iter.next().unwrap().browser_proxy,
+                &browser,
+                &browse_action,
+                &AsyncWebScraper::null_check_func,
+            )
+            .await;
+        let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
+        let file = "test_browse.html".to_owned();
+        web_scraper
+            .save_request_content(&folder_path, &file, &content.get_content().unwrap(), false)
+            .await
+            .unwrap();
+        web_scraper.kill_chrome_process().unwrap();
+    }
+
+    #[cfg(feature = "proxy")]
+    #[tokio::test]
+    async fn test_simple_browsing_with_private_vpn() {
         let logger_name = "test_simple_browsing";
         let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
             .join("Log")
@@ -1440,28 +4813,33 @@ mod tests {
             aws_bucket,
         );
         let browse_action = extra_action;
-        let url = Url::parse("https://www.nowgoal.com").unwrap();
-        web_scraper.turn_on_chrome_process();
+        let url = Url::parse("http://www.nowgoal.com").unwrap();
+        web_scraper.turn_on_chrome_process().unwrap();
+        let mut private_vpn = PrivateVpn::default();
+        private_vpn.turn_on_vpn();
+        private_vpn.connect_vpn();
         let browser = web_scraper.get_default_browser();
         let content = web_scraper
             .simple_browse_request(
                 &url,
                 &browser,
                 &browse_action,
-                AsyncWebScraper::null_check_func,
+                &AsyncWebScraper::null_check_func,
             )
             .await;
+        private_vpn.turn_off_vpn();
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
-        let file = "test_browse.html";
+        let file = "test_browse.html".to_owned();
         web_scraper
-            .save_request_content(&folder_path, file, &content.get_content().unwrap(), false)
-            .await;
-        web_scraper.kill_chrome_process();
+            .save_request_content(&folder_path, &file, &content.get_content().unwrap(), false)
+            .await
+            .unwrap();
+        web_scraper.kill_chrome_process().unwrap();
     }
 
     #[tokio::test]
-    async fn test_simple_browsing_with_proxy() {
-        let logger_name = "test_simple_browsing";
+    async fn test_multiple_browsing() {
+        let logger_name = "test_multiple_browsing";
         let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
             .join("Log")
             .join("log_sctys_netdata");
@@ -1477,6 +4855,7 @@ mod tests {
         let file_io = FileIO::new(&project_logger);
         let aws_file_io = AWSFileIO::new(&project_logger).await;
         let aws_bucket = "sctys";
+        let browse_action = extra_action;
         let mut web_scraper = AsyncWebScraper::new(
             &project_logger,
             &slack_messenger,
@@ -1484,32 +4863,36 @@ mod tests {
             &aws_file_io,
             aws_bucket,
         );
-        let browse_action = extra_action;
-        let url = Url::parse("http://www.nowgoal.com").unwrap();
-        web_scraper.turn_on_chrome_process();
-        let mut proxy_list = ScraperProxy::generate_proxy().await;
-        let mut proxy_iter = ScraperProxy::sample_proxy(&mut proxy_list, 1);
-        let browser = web_scraper.get_default_browser();
-        let content = web_scraper
-            .browse_request_with_proxy(
-                &url,
-                &proxy_iter.next().unwrap().browser_proxy,
-                &browser,
-                &browse_action,
-                AsyncWebScraper::null_check_func,
+        let url_suffix = ["football/live", "football/results", "football/schedule"];
+        let url = Url::parse("https://www.nowgoal.com/").unwrap();
+        let file = "test_browse{index}.html";
+        let url_file_list = Vec::from_iter(url_suffix.iter().enumerate().map(|(i, x)| {
+            UrlFile::new(
+                url.join(x).unwrap(),
+                file.replace("{index}", &i.to_string()),
             )
-            .await;
+        }));
+        let browser = web_scraper.get_default_capabilities();
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
-        let file = "test_browse.html".to_owned();
+        let calling_func = utilities_function::function_name!(true);
+        let browse_setting = BrowseSetting::new(calling_func, true, false);
+        web_scraper.turn_on_chrome_process().unwrap();
         web_scraper
-            .save_request_content(&folder_path, &file, &content.get_content().unwrap(), false)
+            .multiple_browse_requests_sequential(
+                &url_file_list,
+                &browser,
+                &folder_path,
+                &browse_action,
+                &AsyncWebScraper::null_check_func,
+                browse_setting,
+            )
             .await;
-        web_scraper.kill_chrome_process();
+        web_scraper.kill_chrome_process().unwrap();
     }
 
     #[tokio::test]
-    async fn test_simple_browsing_with_private_vpn() {
-        let logger_name = "test_simple_browsing";
+    async fn test_multiple_browsing_with_web_driver_pool() {
+        let logger_name = "test_multiple_browsing";
         let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
             .join("Log")
             .join("log_sctys_netdata");
@@ -1525,6 +4908,9 @@ mod tests {
         let file_io = FileIO::new(&project_logger);
         let aws_file_io = AWSFileIO::new(&project_logger).await;
         let aws_bucket = "sctys";
+        let browse_action = extra_action;
+        let mut web_driver_pool = WebDriverPool::new(&project_logger, 2);
+        web_driver_pool.set_max_pages_per_session(2);
         let mut web_scraper = AsyncWebScraper::new(
             &project_logger,
             &slack_messenger,
@@ -1532,32 +4918,36 @@ mod tests {
             &aws_file_io,
             aws_bucket,
         );
-        let browse_action = extra_action;
-        let url = Url::parse("http://www.nowgoal.com").unwrap();
-        web_scraper.turn_on_chrome_process();
-        let mut private_vpn = PrivateVpn::default();
-        private_vpn.turn_on_vpn();
-        private_vpn.connect_vpn();
-        let browser = web_scraper.get_default_browser();
-        let content = web_scraper
-            .simple_browse_request(
-                &url,
-                &browser,
-                &browse_action,
-                AsyncWebScraper::null_check_func,
+        web_scraper.set_web_driver_pool(&web_driver_pool);
+        let url_suffix = ["football/live", "football/results", "football/schedule"];
+        let url = Url::parse("https://www.nowgoal.com/").unwrap();
+        let file = "test_browse{index}.html";
+        let url_file_list = Vec::from_iter(url_suffix.iter().enumerate().map(|(i, x)| {
+            UrlFile::new(
+                url.join(x).unwrap(),
+                file.replace("{index}", &i.to_string()),
             )
-            .await;
-        private_vpn.turn_off_vpn();
+        }));
+        let browser = web_scraper.get_default_capabilities();
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
-        let file = "test_browse.html".to_owned();
+        let calling_func = utilities_function::function_name!(true);
+        let browse_setting = BrowseSetting::new(calling_func, true, false);
+        web_scraper.turn_on_chrome_process().unwrap();
         web_scraper
-            .save_request_content(&folder_path, &file, &content.get_content().unwrap(), false)
+            .multiple_browse_requests_with_web_driver_pool(
+                &url_file_list,
+                &browser,
+                &folder_path,
+                &browse_action,
+                &AsyncWebScraper::null_check_func,
+                browse_setting,
+            )
             .await;
-        web_scraper.kill_chrome_process();
+        web_scraper.kill_chrome_process().unwrap();
     }
 
     #[tokio::test]
-    async fn test_multiple_browsing() {
+    async fn test_multiple_browsing_with_driver_recycling() {
         let logger_name = "test_multiple_browsing";
         let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
             .join("Log")
@@ -1575,6 +4965,8 @@ mod tests {
         let aws_file_io = AWSFileIO::new(&project_logger).await;
         let aws_bucket = "sctys";
         let browse_action = extra_action;
+        let web_driver_manager = WebDriverManager::new(&project_logger, &[4444, 4445]);
+        web_driver_manager.start().await;
         let mut web_scraper = AsyncWebScraper::new(
             &project_logger,
             &slack_messenger,
@@ -1582,6 +4974,8 @@ mod tests {
             &aws_file_io,
             aws_bucket,
         );
+        web_scraper.set_web_driver_manager(&web_driver_manager);
+        web_scraper.set_pages_per_driver(1);
         let url_suffix = ["football/live", "football/results", "football/schedule"];
         let url = Url::parse("https://www.nowgoal.com/").unwrap();
         let file = "test_browse{index}.html";
@@ -1591,27 +4985,81 @@ mod tests {
                 file.replace("{index}", &i.to_string()),
             )
         }));
-        let browser = web_scraper.get_default_browser();
+        let browser = web_scraper.get_default_capabilities();
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let calling_func = utilities_function::function_name!(true);
-        let browse_setting = BrowseSetting {
-            restart_web_driver: false,
-            calling_func,
-            log_only: true,
-            in_s3: false,
-        };
-        web_scraper.turn_on_chrome_process();
+        let browse_setting = BrowseSetting::new(calling_func, true, false);
         web_scraper
-            .multiple_browse_requests_sequential(
+            .multiple_browse_requests_with_driver_recycling(
                 &url_file_list,
                 &browser,
                 &folder_path,
                 &browse_action,
-                AsyncWebScraper::null_check_func,
+                &AsyncWebScraper::null_check_func,
                 browse_setting,
             )
             .await;
-        web_scraper.kill_chrome_process();
+        web_driver_manager.shutdown();
+    }
+
+    #[tokio::test]
+    async fn test_multiple_requests_with_browser_fallback() {
+        let logger_name = "test_multiple_requests";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_netdata");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Info);
+        let channel_config_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Config")
+            .join("config_sctys_rust_utilities");
+        let channel_config_file = "messenger_channel_id.toml";
+        let channel_id = load_channel_id(&channel_config_path, channel_config_file);
+        let log_channel_id = channel_id.clone();
+        let slack_messenger = SlackMessenger::new(&channel_id, &log_channel_id, &project_logger);
+        let file_io = FileIO::new(&project_logger);
+        let aws_file_io = AWSFileIO::new(&project_logger).await;
+        let aws_bucket = "sctys";
+        let browse_action = extra_action;
+        let mut web_scraper = AsyncWebScraper::new(
+            &project_logger,
+            &slack_messenger,
+            &file_io,
+            &aws_file_io,
+            aws_bucket,
+        );
+        let url_suffix = ["football/live", "football/results", "football/schedule"];
+        let url = Url::parse("https://www.nowgoal.com/").unwrap();
+        let file = "test_browse{index}.html";
+        let url_file_list = Vec::from_iter(url_suffix.iter().enumerate().map(|(i, x)| {
+            UrlFile::new(
+                url.join(x).unwrap(),
+                file.replace("{index}", &i.to_string()),
+            )
+        }));
+        let browser = web_scraper.get_default_capabilities();
+        let request_builder_func = get_request_builder;
+        let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
+        let calling_func = utilities_function::function_name!(true);
+        let request_setting = RequestSetting::new(calling_func, true, false);
+        web_scraper.turn_on_chrome_process().unwrap();
+        let fallback_outcome_list = web_scraper
+            .multiple_requests_with_browser_fallback(
+                &url_file_list,
+                request_builder_func,
+                &browser,
+                &browse_action,
+                &folder_path,
+                &AsyncWebScraper::null_check_func,
+                &request_setting,
+                5,
+            )
+            .await;
+        web_scraper.kill_chrome_process().unwrap();
+        assert_eq!(fallback_outcome_list.len(), url_file_list.len());
+        assert!(fallback_outcome_list
+            .iter()
+            .all(|fallback_outcome| fallback_outcome.outcome.attempts <= 5));
     }
 
     #[tokio::test]
@@ -1649,29 +5097,25 @@ mod tests {
                 file.replace("{index}", &i.to_string()),
             )
         }));
-        let browser = web_scraper.get_default_browser();
+        let browser = web_scraper.get_default_capabilities();
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let calling_func = utilities_function::function_name!(true);
-        let browse_setting = BrowseSetting {
-            restart_web_driver: false,
-            calling_func,
-            log_only: true,
-            in_s3: false,
-        };
-        web_scraper.turn_on_chrome_process();
+        let browse_setting = BrowseSetting::new(calling_func, true, false);
+        web_scraper.turn_on_chrome_process().unwrap();
         web_scraper
             .multiple_browse_requests_with_proxy(
                 &url_file_list,
                 &browser,
                 &folder_path,
                 &browse_action,
-                AsyncWebScraper::null_check_func,
+                &AsyncWebScraper::null_check_func,
                 browse_setting,
             )
             .await;
-        web_scraper.kill_chrome_process();
+        web_scraper.kill_chrome_process().unwrap();
     }
 
+    #[cfg(feature = "proxy")]
     #[tokio::test]
     async fn test_multiple_browsing_with_private_vpn() {
         let logger_name = "test_multiple_browsing";
@@ -1707,16 +5151,11 @@ mod tests {
                 file.replace("{index}", &i.to_string()),
             )
         }));
-        let browser = web_scraper.get_default_browser();
+        let browser = web_scraper.get_default_capabilities();
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let calling_func = utilities_function::function_name!(true);
-        let browse_setting = BrowseSetting {
-            restart_web_driver: false,
-            calling_func,
-            log_only: true,
-            in_s3: false,
-        };
-        web_scraper.turn_on_chrome_process();
+        let browse_setting = BrowseSetting::new(calling_func, true, false);
+        web_scraper.turn_on_chrome_process().unwrap();
         let mut private_vpn = PrivateVpn::default();
         web_scraper
             .multiple_browse_requests_with_private_vpn(
@@ -1725,10 +5164,390 @@ mod tests {
                 &browser,
                 &folder_path,
                 &browse_action,
-                AsyncWebScraper::null_check_func,
+                &AsyncWebScraper::null_check_func,
                 browse_setting,
             )
             .await;
-        web_scraper.kill_chrome_process();
+        web_scraper.kill_chrome_process().unwrap();
+    }
+
+    fn captcha_check_func(response: &str) -> ResponseCheckResult {
+        if response.contains("captcha") {
+            ResponseCheckResult::Blocked(ScrapeFailure::Blocked(
+                "Captcha page returned.".to_string(),
+            ))
+        } else {
+            ResponseCheckResult::Ok(response.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_and_check_with_mock_transport() {
+        let project_logger = ProjectLogger::new_logger(&env::temp_dir(), "test_mock_transport");
+        let slack_config = SlackConfig::default();
+        let slack_messenger = SlackMessenger::from_config(&slack_config, &project_logger);
+        let file_io = FileIO::new(&project_logger);
+        let aws_config = AWSConfig {
+            aws_api_region: "eu-west-2".to_string(),
+            ..AWSConfig::default()
+        };
+        let aws_file_io = AWSFileIO::from_config(&project_logger, &aws_config).await;
+        let mock_transport = MockTransport::new();
+        let mut web_scraper = AsyncWebScraper::new(
+            &project_logger,
+            &slack_messenger,
+            &file_io,
+            &aws_file_io,
+            "sctys",
+        );
+        web_scraper.set_retry_sleep(Duration::ZERO);
+        web_scraper.set_http_transport(&mock_transport as &dyn HttpTransport);
+        let url = Url::parse("https://tfl.gov.uk/tube/timetable/").unwrap();
+        let url_file_list: Vec<UrlFile> = ["bakerloo", "central", "circle"]
+            .iter()
+            .map(|x| UrlFile::new(url.join(x).unwrap(), x.to_string()))
+            .collect();
+        mock_transport.add_response(
+            &url_file_list[0].url,
+            MockResponse::status(StatusCode::INTERNAL_SERVER_ERROR),
+        );
+        mock_transport.add_response(&url_file_list[0].url, MockResponse::html(TIMETABLE_FIXTURE));
+        mock_transport.add_response(&url_file_list[1].url, MockResponse::html(BLOCKED_FIXTURE));
+        let folder_path = env::temp_dir().join("test_mock_transport");
+        fs::create_dir_all(&folder_path).unwrap();
+        let mut batch_outcome = BatchOutcome::new();
+        for url_file in url_file_list.iter() {
+            batch_outcome.add(
+                web_scraper
+                    .request_and_save_content_with_outcome(
+                        url_file,
+                        get_request_builder,
+                        &folder_path,
+                        &captcha_check_func,
+                        RequestSetting::default().get_url_request_options(3),
+                    )
+                    .await,
+            );
+        }
+        assert_eq!(batch_outcome.successes.len(), 1);
+        assert_eq!(batch_outcome.successes[0].outcome.attempts, 2);
+        assert_eq!(mock_transport.get_request_count(&url_file_list[0].url), 2);
+        assert_eq!(
+            fs::read_to_string(folder_path.join("bakerloo.html")).unwrap(),
+            TIMETABLE_FIXTURE
+        );
+        assert_eq!(mock_transport.get_request_count(&url_file_list[1].url), 1);
+        assert_eq!(
+            batch_outcome.get_status_counts(),
+            BTreeMap::from([("blocked", 1), ("success", 1), ("terminated", 1)])
+        );
+        let fail_url_message = AsyncWebScraper::fail_url_message(
+            &batch_outcome.get_fail_list(),
+            batch_outcome.num_blocked(),
+            url_file_list.len(),
+        );
+        assert!(fail_url_message.contains("2 out of 3 fail urls, of which 1 were blocked"));
+        let response_check_result = web_scraper
+            .simple_request(
+                &url_file_list[2].url,
+                get_request_builder,
+                &captcha_check_func,
+            )
+            .await;
+        assert!(matches!(
+            response_check_result,
+            ResponseCheckResult::ErrTerminate(ScrapeFailure::HttpStatus(404))
+        ));
+        let url_outcome = web_scraper
+            .request_and_save_content_with_outcome(
+                &url_file_list[2],
+                invalid_header_request_builder,
+                &folder_path,
+                &captcha_check_func,
+                UrlRequestOptions {
+                    request_limit: RequestLimit {
+                        connect_timeout: Some(Duration::from_secs(5)),
+                        ..RequestLimit::default()
+                    },
+                    ..RequestSetting::default().get_url_request_options(3)
+                },
+            )
+            .await;
+        assert_eq!(url_outcome.outcome.status, ScrapeStatus::Terminated);
+        assert_eq!(url_outcome.outcome.attempts, 1);
+        assert_eq!(mock_transport.get_request_count(&url_file_list[2].url), 1);
+        fs::remove_dir_all(&folder_path).unwrap();
+    }
+
+    fn invalid_header_request_builder(url: Url) -> RequestBuilder {
+        Client::new().get(url).header("invalid header", "sctys")
+    }
+
+    #[tokio::test]
+    async fn test_download_google_sheet_tabs_with_retry() {
+        let project_logger =
+            ProjectLogger::new_logger(&env::temp_dir(), "test_download_google_sheet_tabs");
+        let slack_messenger = SlackMessenger::from_config(&SlackConfig::default(), &project_logger);
+        let file_io = FileIO::new(&project_logger);
+        let aws_config = AWSConfig {
+            aws_api_region: "eu-west-2".to_string(),
+            ..AWSConfig::default()
+        };
+        let aws_file_io = AWSFileIO::from_config(&project_logger, &aws_config).await;
+        let mock_transport = MockTransport::new();
+        let mut web_scraper = AsyncWebScraper::new(
+            &project_logger,
+            &slack_messenger,
+            &file_io,
+            &aws_file_io,
+            "sctys",
+        );
+        web_scraper.set_retry_sleep(Duration::ZERO);
+        web_scraper.set_http_transport(&mock_transport as &dyn HttpTransport);
+        let google_sheet_key = GoogleSheetKey::parse("sheet_id/edit#gid=0").unwrap();
+        let tab_url = google_sheet_key.with_gid("1").get_csv_url().unwrap();
+        mock_transport.add_response(
+            &tab_url,
+            MockResponse::status(StatusCode::SERVICE_UNAVAILABLE),
+        );
+        mock_transport.add_response(
+            &tab_url,
+            MockResponse::new(StatusCode::OK, "text/csv", b"a,b\n1,2\n"),
+        );
+        let tab_list = web_scraper
+            .download_google_sheet_tabs(
+                &google_sheet_key,
+                &["1"],
+                get_request_builder,
+                &AsyncWebScraper::null_check_func,
+            )
+            .await;
+        assert!(matches!(tab_list[0].1, ResponseCheckResult::Ok(_)));
+        assert_eq!(mock_transport.get_request_count(&tab_url), 2);
+    }
+
+    #[tokio::test]
+    async fn test_arc_components() {
+        let project_logger = Arc::new(ProjectLogger::new_logger(
+            &env::temp_dir(),
+            "test_arc_components",
+        ));
+        let slack_messenger = Arc::new(SlackMessenger::from_config(
+            &SlackConfig::default(),
+            Arc::clone(&project_logger),
+        ));
+        let file_io = Arc::new(FileIO::new(Arc::clone(&project_logger)));
+        let aws_config = AWSConfig {
+            aws_api_region: "eu-west-2".to_string(),
+            ..AWSConfig::default()
+        };
+        let aws_file_io =
+            Arc::new(AWSFileIO::from_config(Arc::clone(&project_logger), &aws_config).await);
+        let web_scraper = Arc::new(AsyncWebScraper::new(
+            project_logger,
+            slack_messenger,
+            file_io,
+            aws_file_io,
+            "sctys",
+        ));
+        let handles: Vec<JoinHandle<Capabilities>> = (0..2)
+            .map(|_| {
+                let web_scraper = Arc::clone(&web_scraper);
+                tokio::spawn(async move { web_scraper.get_default_browser() })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.await.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_response_bytes() {
+        let project_logger = ProjectLogger::new_logger(&env::temp_dir(), "test_max_response_bytes");
+        let slack_config = SlackConfig::default();
+        let slack_messenger = SlackMessenger::from_config(&slack_config, &project_logger);
+        let file_io = FileIO::new(&project_logger);
+        let aws_config = AWSConfig {
+            aws_api_region: "eu-west-2".to_string(),
+            ..AWSConfig::default()
+        };
+        let aws_file_io = AWSFileIO::from_config(&project_logger, &aws_config).await;
+        let mock_transport = MockTransport::new();
+        let mut web_scraper = AsyncWebScraper::new(
+            &project_logger,
+            &slack_messenger,
+            &file_io,
+            &aws_file_io,
+            "sctys",
+        );
+        web_scraper.set_retry_sleep(Duration::ZERO);
+        web_scraper.set_http_transport(&mock_transport as &dyn HttpTransport);
+        let url_file = UrlFile::new(
+            Url::parse("https://tfl.gov.uk/tube/timetable/bakerloo/").unwrap(),
+            "bakerloo".to_string(),
+        );
+        mock_transport.add_response(&url_file.url, MockResponse::html(TIMETABLE_FIXTURE));
+        let folder_path = env::temp_dir().join("test_max_response_bytes");
+        fs::create_dir_all(&folder_path).unwrap();
+        let mut request_limit = RequestLimit {
+            max_response_bytes: Some(100),
+            ..RequestLimit::default()
+        };
+        let url_outcome = web_scraper
+            .request_and_save_content_with_outcome(
+                &url_file,
+                get_request_builder,
+                &folder_path,
+                &AsyncWebScraper::null_check_func,
+                UrlRequestOptions {
+                    in_s3: false,
+                    request_limit,
+                    max_attempts: 3,
+                    calling_func: "test_max_response_bytes",
+                    log_only: false,
+                    record_domain_outcome: true,
+                },
+            )
+            .await;
+        assert_eq!(url_outcome.outcome.status, ScrapeStatus::Terminated);
+        assert_eq!(url_outcome.outcome.attempts, 1);
+        assert!(!folder_path.join("bakerloo.html").exists());
+        request_limit.truncate_response = true;
+        let url_outcome = web_scraper
+            .request_and_save_content_with_outcome(
+                &url_file,
+                get_request_builder,
+                &folder_path,
+                &AsyncWebScraper::null_check_func,
+                UrlRequestOptions {
+                    in_s3: false,
+                    request_limit,
+                    max_attempts: 3,
+                    calling_func: "test_max_response_bytes",
+                    log_only: false,
+                    record_domain_outcome: true,
+                },
+            )
+            .await;
+        assert_eq!(url_outcome.outcome.status, ScrapeStatus::Success);
+        assert_eq!(
+            fs::read_to_string(folder_path.join("bakerloo.html")).unwrap(),
+            TIMETABLE_FIXTURE[..100]
+        );
+        fs::remove_dir_all(&folder_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_request_json_and_save() {
+        let project_logger = ProjectLogger::new_logger(&env::temp_dir(), "test_request_json");
+        let slack_config = SlackConfig::default();
+        let slack_messenger = SlackMessenger::from_config(&slack_config, &project_logger);
+        let file_io = FileIO::new(&project_logger);
+        let aws_config = AWSConfig {
+            aws_api_region: "eu-west-2".to_string(),
+            ..AWSConfig::default()
+        };
+        let aws_file_io = AWSFileIO::from_config(&project_logger, &aws_config).await;
+        let mock_transport = MockTransport::new();
+        let mut web_scraper = AsyncWebScraper::new(
+            &project_logger,
+            &slack_messenger,
+            &file_io,
+            &aws_file_io,
+            "sctys",
+        );
+        web_scraper.set_retry_sleep(Duration::ZERO);
+        web_scraper.set_http_transport(&mock_transport as &dyn HttpTransport);
+        let url_file = UrlFile::new(
+            Url::parse("https://live.nowgoal.com/odds/2451163").unwrap(),
+            "odds".to_string(),
+        );
+        mock_transport.add_response(
+            &url_file.url,
+            MockResponse::status(StatusCode::SERVICE_UNAVAILABLE),
+        );
+        mock_transport.add_response(&url_file.url, MockResponse::json(ODDS_FIXTURE));
+        let has_odds = |odds: &serde_json::Value| match odds["odds"].as_array() {
+            Some(odds_list) if !odds_list.is_empty() => Ok(()),
+            _ => Err(ScrapeFailure::validation_failed("No odds.")),
+        };
+        let folder_path = env::temp_dir().join("test_request_json");
+        fs::create_dir_all(&folder_path).unwrap();
+        let odds = web_scraper
+            .request_json_and_save::<serde_json::Value>(
+                &url_file,
+                get_request_builder,
+                &folder_path,
+                &has_odds,
+                &JsonSaveOptions::with_records("/odds"),
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(odds["home_team"], "Arsenal");
+        assert_eq!(mock_transport.get_request_count(&url_file.url), 2);
+        assert_eq!(
+            fs::read_to_string(folder_path.join("odds.json")).unwrap(),
+            ODDS_FIXTURE
+        );
+        let data = file_io
+            .load_parquet_file(&folder_path, "odds.parquet")
+            .unwrap();
+        assert_eq!(data.height(), 2);
+        let no_draw = |odds: &serde_json::Value| match odds["odds"][0].get("draw") {
+            Some(_) => Err(ScrapeFailure::validation_failed("Draw odds given.")),
+            None => Ok(()),
+        };
+        assert!(matches!(
+            web_scraper
+                .request_json::<serde_json::Value>(&url_file.url, get_request_builder, &no_draw)
+                .await,
+            Err(ScrapeFailure::ValidationFailed(_))
+        ));
+        fs::remove_dir_all(&folder_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_mock_server() {
+        let project_logger = ProjectLogger::new_logger(&env::temp_dir(), "test_mock_server");
+        let slack_config = SlackConfig::default();
+        let slack_messenger = SlackMessenger::from_config(&slack_config, &project_logger);
+        let file_io = FileIO::new(&project_logger);
+        let aws_config = AWSConfig {
+            aws_api_region: "eu-west-2".to_string(),
+            ..AWSConfig::default()
+        };
+        let aws_file_io = AWSFileIO::from_config(&project_logger, &aws_config).await;
+        let mut web_scraper = AsyncWebScraper::new(
+            &project_logger,
+            &slack_messenger,
+            &file_io,
+            &aws_file_io,
+            "sctys",
+        );
+        web_scraper.set_retry_sleep(Duration::ZERO);
+        let mock_server = ScraperMockServer::start().await;
+        mock_server.mount_status("/odds", 503, Some(1)).await;
+        mock_server.mount_json("/odds", ODDS_FIXTURE).await;
+        let url_file = UrlFile::new(mock_server.get_url("/odds"), "odds".to_string());
+        let folder_path = env::temp_dir().join("test_mock_server");
+        fs::create_dir_all(&folder_path).unwrap();
+        let url_outcome = web_scraper
+            .request_and_save_content_with_outcome(
+                &url_file,
+                get_request_builder,
+                &folder_path,
+                &AsyncWebScraper::null_check_func,
+                RequestSetting::default().get_url_request_options(3),
+            )
+            .await;
+        assert_eq!(url_outcome.outcome.status, ScrapeStatus::Success);
+        assert_eq!(url_outcome.outcome.attempts, 2);
+        assert_eq!(mock_server.get_request_count("/odds").await, 2);
+        assert_eq!(
+            fs::read_to_string(folder_path.join("odds.json")).unwrap(),
+            ODDS_FIXTURE
+        );
+        fs::remove_dir_all(&folder_path).unwrap();
     }
 }
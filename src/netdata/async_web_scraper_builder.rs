@@ -0,0 +1,182 @@
+use log::LevelFilter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::async_web_scraper::AsyncWebScraper;
+use crate::aws_s3::AWSFileIO;
+use crate::config::{SlackConfig, UtilitiesConfig};
+use crate::file_io::FileIO;
+use crate::logger::ProjectLogger;
+use crate::slack_messenger::SlackMessenger;
+
+#[derive(Debug, Clone)]
+pub struct AsyncWebScraperBuilder {
+    logger_path: PathBuf,
+    logger_name: String,
+    logger_level: Option<LevelFilter>,
+    main_channel_id: String,
+    log_channel_id: String,
+    aws_bucket: String,
+}
+
+impl AsyncWebScraperBuilder {
+    const LOGGER_FOLDER: &'static str = "Log";
+    const AWS_BUCKET: &'static str = "sctys";
+
+    pub fn new(logger_name: &str) -> Self {
+        let utilities_config = UtilitiesConfig::global();
+        let (main_channel_id, log_channel_id) = utilities_config.slack.as_ref().map_or(
+            (String::new(), String::new()),
+            |slack_config| {
+                (
+                    slack_config.main_channel_id.clone(),
+                    slack_config.log_channel_id.clone(),
+                )
+            },
+        );
+        let aws_bucket = utilities_config
+            .aws
+            .as_ref()
+            .and_then(|aws_config| aws_config.default_bucket.clone())
+            .unwrap_or_else(|| Self::AWS_BUCKET.to_string());
+        Self {
+            logger_path: utilities_config
+                .paths
+                .project_path
+                .join(Self::LOGGER_FOLDER)
+                .join(format!("log_{logger_name}")),
+            logger_name: logger_name.to_string(),
+            logger_level: None,
+            main_channel_id,
+            log_channel_id,
+            aws_bucket,
+        }
+    }
+
+    pub fn set_logger_path(mut self, logger_path: &Path) -> Self {
+        self.logger_path = logger_path.to_path_buf();
+        self
+    }
+
+    pub fn set_logger_level(mut self, logger_level: LevelFilter) -> Self {
+        self.logger_level = Some(logger_level);
+        self
+    }
+
+    pub fn set_slack_channels(mut self, main_channel_id: &str, log_channel_id: &str) -> Self {
+        self.main_channel_id = main_channel_id.to_string();
+        self.log_channel_id = log_channel_id.to_string();
+        self
+    }
+
+    pub fn set_aws_bucket(mut self, aws_bucket: &str) -> Self {
+        self.aws_bucket = aws_bucket.to_string();
+        self
+    }
+
+    // The components are owned by the scraper through Arcs, so the scraper is Send + 'static and
+    // can be moved into spawned tasks, and they are freed with the scraper.
+    pub async fn build(self) -> AsyncWebScraper<'static> {
+        let utilities_config = UtilitiesConfig::global();
+        let project_logger = Arc::new(ProjectLogger::new_logger(
+            &self.logger_path,
+            &self.logger_name,
+        ));
+        if let Some(logger_level) = self.logger_level {
+            project_logger.set_logger(logger_level);
+        }
+        let slack_messenger = Arc::new(match utilities_config.slack.as_ref() {
+            Some(slack_config) if !slack_config.api_token.is_empty() => {
+                let slack_config = SlackConfig {
+                    main_channel_id: self.main_channel_id,
+                    log_channel_id: self.log_channel_id,
+                    ..slack_config.clone()
+                };
+                SlackMessenger::from_config(&slack_config, Arc::clone(&project_logger))
+            }
+            _ => SlackMessenger::new(
+                &self.main_channel_id,
+                &self.log_channel_id,
+                Arc::clone(&project_logger),
+            ),
+        });
+        let file_io = Arc::new(FileIO::new(Arc::clone(&project_logger)));
+        let aws_file_io = Arc::new(match utilities_config.aws.as_ref() {
+            Some(aws_config) => {
+                AWSFileIO::from_config(Arc::clone(&project_logger), aws_config).await
+            }
+            None => AWSFileIO::new(Arc::clone(&project_logger)).await,
+        });
+        let mut async_web_scraper = AsyncWebScraper::new(
+            project_logger,
+            slack_messenger,
+            file_io,
+            aws_file_io,
+            &self.aws_bucket,
+        );
+        async_web_scraper.apply_scraper_config(&utilities_config.scraper);
+        async_web_scraper
+    }
+
+    pub async fn build_shared(self) -> Arc<AsyncWebScraper<'static>> {
+        Arc::new(self.build().await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::netdata::data_struct::{RequestSetting, UrlFile};
+    use reqwest::{Client, Url};
+    use std::env;
+
+    fn assert_send_static<T: Send + Sync + 'static>(_: &T) {}
+
+    #[tokio::test]
+    async fn test_build_async_web_scraper() {
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_netdata");
+        let async_web_scraper = AsyncWebScraperBuilder::new("test_async_web_scraper_builder")
+            .set_logger_path(&logger_path)
+            .set_aws_bucket("sctys")
+            .build()
+            .await;
+        assert_send_static(&async_web_scraper);
+        let handle = tokio::spawn(async move { async_web_scraper.get_default_browser() });
+        assert!(handle.await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_multiple_requests_spawned() {
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_netdata");
+        let async_web_scraper = AsyncWebScraperBuilder::new("test_async_web_scraper_builder")
+            .set_logger_path(&logger_path)
+            .build_shared()
+            .await;
+        let url_file_list: Vec<UrlFile> = ["bakerloo", "central", "circle"]
+            .iter()
+            .map(|line| {
+                UrlFile::new(
+                    Url::parse(&format!("https://tfl.gov.uk/tube/timetable/{line}/")).unwrap(),
+                    format!("tfl_{line}.html"),
+                )
+            })
+            .collect();
+        let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
+        let request_builder_func = |url: Url| Client::new().get(url);
+        let outcome_list = async_web_scraper
+            .multiple_requests_spawned(
+                &url_file_list,
+                request_builder_func,
+                &folder_path,
+                Arc::new(AsyncWebScraper::null_check_func),
+                &RequestSetting::new("test_multiple_requests_spawned", false, false),
+            )
+            .await;
+        assert_eq!(outcome_list.len(), 3);
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::data_struct::{ScrapeOutcome, ScrapeStatus, UrlFile};
+use super::run_report::RunReport;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlOutcome {
+    pub url_file: UrlFile,
+    pub outcome: ScrapeOutcome,
+    pub error: Option<String>,
+}
+
+impl UrlOutcome {
+    pub fn new(url_file: &UrlFile, outcome: ScrapeOutcome, error: Option<String>) -> Self {
+        Self {
+            url_file: url_file.clone(),
+            outcome,
+            error,
+        }
+    }
+
+    pub fn skipped(url_file: &UrlFile, status: ScrapeStatus) -> Self {
+        Self::new(url_file, ScrapeOutcome::skipped(status), None)
+    }
+}
+
+// The result of the multiple requests. The skipped urls were never requested, e.g. of a tripped
+// domain, and the halted ones were left when the deadline was reached or on shutdown. The fail
+// list of the older return type is the failures, skipped and halted urls together.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchOutcome {
+    pub successes: Vec<UrlOutcome>,
+    pub failures: Vec<UrlOutcome>,
+    pub skipped: Vec<UrlOutcome>,
+    pub halted: Vec<UrlFile>,
+    pub duration: Duration,
+}
+
+impl BatchOutcome {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, url_outcome: UrlOutcome) {
+        match url_outcome.outcome.status {
+            ScrapeStatus::Success => self.successes.push(url_outcome),
+            ScrapeStatus::Tripped | ScrapeStatus::InvalidUrl => self.skipped.push(url_outcome),
+            ScrapeStatus::Halted => self.halted.push(url_outcome.url_file),
+            _ => self.failures.push(url_outcome),
+        }
+    }
+
+    pub fn add_halted(&mut self, url_file_list: &[UrlFile]) {
+        self.halted.extend_from_slice(url_file_list);
+    }
+
+    pub fn extend(&mut self, batch_outcome: BatchOutcome) {
+        self.successes.extend(batch_outcome.successes);
+        self.failures.extend(batch_outcome.failures);
+        self.skipped.extend(batch_outcome.skipped);
+        self.halted.extend(batch_outcome.halted);
+        self.duration += batch_outcome.duration;
+    }
+
+    pub fn num_total(&self) -> usize {
+        self.successes.len() + self.failures.len() + self.skipped.len() + self.halted.len()
+    }
+
+    pub fn num_blocked(&self) -> usize {
+        self.failures
+            .iter()
+            .filter(|x| x.outcome.status == ScrapeStatus::Blocked)
+            .count()
+    }
+
+    pub fn is_all_success(&self) -> bool {
+        self.failures.is_empty() && self.skipped.is_empty() && self.halted.is_empty()
+    }
+
+    pub fn get_fail_list(&self) -> Vec<UrlFile> {
+        self.failures
+            .iter()
+            .chain(self.skipped.iter())
+            .map(|x| x.url_file.clone())
+            .chain(self.halted.iter().cloned())
+            .collect()
+    }
+
+    // The urls skipped as their domain was tripped, to be requested again after the cooldown.
+    pub fn get_deferred_list(&self) -> Vec<UrlFile> {
+        self.skipped
+            .iter()
+            .filter(|x| x.outcome.status == ScrapeStatus::Tripped)
+            .map(|x| x.url_file.clone())
+            .collect()
+    }
+
+    pub fn get_status_counts(&self) -> BTreeMap<&'static str, usize> {
+        let mut status_counts = BTreeMap::new();
+        for url_outcome in self
+            .successes
+            .iter()
+            .chain(self.failures.iter())
+            .chain(self.skipped.iter())
+        {
+            *status_counts
+                .entry(url_outcome.outcome.status.as_str())
+                .or_default() += 1;
+        }
+        if !self.halted.is_empty() {
+            status_counts.insert(ScrapeStatus::Halted.as_str(), self.halted.len());
+        }
+        status_counts
+    }
+
+    pub fn get_total_bytes(&self) -> u64 {
+        self.successes.iter().filter_map(|x| x.outcome.bytes).sum()
+    }
+
+    pub fn to_run_report(&self) -> RunReport {
+        let mut run_report = RunReport::new();
+        for url_outcome in self
+            .successes
+            .iter()
+            .chain(self.failures.iter())
+            .chain(self.skipped.iter())
+        {
+            run_report.add_outcome(url_outcome.url_file.url.as_str(), url_outcome.outcome);
+        }
+        for url_file in self.halted.iter() {
+            run_report.add_outcome(
+                url_file.url.as_str(),
+                ScrapeOutcome::skipped(ScrapeStatus::Halted),
+            );
+        }
+        run_report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use reqwest::Url;
+
+    #[test]
+    fn test_batch_outcome() {
+        let url = Url::parse("https://tfl.gov.uk/tube/timetable/").unwrap();
+        let url_file_list: Vec<UrlFile> = ["bakerloo", "central", "circle", "district"]
+            .iter()
+            .map(|x| UrlFile::new(url.join(x).unwrap(), format!("{x}.html")))
+            .collect();
+        let mut batch_outcome = BatchOutcome::new();
+        batch_outcome.add(UrlOutcome::new(
+            &url_file_list[0],
+            ScrapeOutcome {
+                status: ScrapeStatus::Success,
+                attempts: 1,
+                latency: Some(Duration::from_millis(200)),
+                bytes: Some(1000),
+            },
+            None,
+        ));
+        batch_outcome.add(UrlOutcome::new(
+            &url_file_list[1],
+            ScrapeOutcome {
+                status: ScrapeStatus::Blocked,
+                attempts: 1,
+                latency: Some(Duration::from_millis(100)),
+                bytes: None,
+            },
+            Some("Server return status code 403".to_string()),
+        ));
+        batch_outcome.add(UrlOutcome::skipped(
+            &url_file_list[2],
+            ScrapeStatus::Tripped,
+        ));
+        batch_outcome.add_halted(&url_file_list[3..]);
+        assert_eq!(batch_outcome.num_total(), 4);
+        assert_eq!(batch_outcome.num_blocked(), 1);
+        assert!(!batch_outcome.is_all_success());
+        assert_eq!(batch_outcome.get_fail_list(), url_file_list[1..].to_vec());
+        assert_eq!(
+            batch_outcome.get_deferred_list(),
+            vec![url_file_list[2].clone()]
+        );
+        assert_eq!(batch_outcome.get_status_counts()["halted"], 1);
+        assert_eq!(batch_outcome.get_total_bytes(), 1000);
+        assert_eq!(batch_outcome.to_run_report().get_url_reports().len(), 4);
+    }
+}
@@ -0,0 +1,156 @@
+use std::time::Duration;
+use thirtyfour::error::WebDriverResult;
+use thirtyfour::{By, WebDriver};
+
+use crate::time_operation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BrowseActionSetting {
+    pub max_iterations: u32,
+    pub wait: Duration,
+}
+
+impl BrowseActionSetting {
+    const MAX_ITERATIONS: u32 = 30;
+    const WAIT: Duration = Duration::from_secs(2);
+}
+
+impl Default for BrowseActionSetting {
+    fn default() -> Self {
+        Self {
+            max_iterations: Self::MAX_ITERATIONS,
+            wait: Self::WAIT,
+        }
+    }
+}
+
+const SCROLL_HEIGHT_SCRIPT: &str = "return document.body.scrollHeight;";
+const SCROLL_TO_BOTTOM_SCRIPT: &str = "window.scrollTo(0, document.body.scrollHeight);";
+
+async fn scroll_height(web_driver: &WebDriver) -> WebDriverResult<i64> {
+    web_driver
+        .execute(SCROLL_HEIGHT_SCRIPT, Vec::new())
+        .await?
+        .convert::<i64>()
+}
+
+// Keeps scrolling until the page height stops growing, i.e. no more content is lazily loaded.
+pub async fn scroll_to_bottom_until_stable(
+    web_driver: &mut WebDriver,
+    browse_action_setting: &BrowseActionSetting,
+) -> WebDriverResult<u32> {
+    let mut last_height = scroll_height(web_driver).await?;
+    for iteration in 0..browse_action_setting.max_iterations {
+        web_driver
+            .execute(SCROLL_TO_BOTTOM_SCRIPT, Vec::new())
+            .await?;
+        time_operation::async_sleep(browse_action_setting.wait).await;
+        let new_height = scroll_height(web_driver).await?;
+        if new_height == last_height {
+            return Ok(iteration);
+        }
+        last_height = new_height;
+    }
+    Ok(browse_action_setting.max_iterations)
+}
+
+pub async fn click_load_more_until_gone(
+    web_driver: &mut WebDriver,
+    selector: &str,
+    browse_action_setting: &BrowseActionSetting,
+) -> WebDriverResult<u32> {
+    for iteration in 0..browse_action_setting.max_iterations {
+        let load_more_button = match web_driver.find_all(By::Css(selector)).await?.pop() {
+            Some(load_more_button) => load_more_button,
+            None => return Ok(iteration),
+        };
+        if !load_more_button.is_displayed().await? {
+            return Ok(iteration);
+        }
+        load_more_button.scroll_into_view().await?;
+        load_more_button.click().await?;
+        time_operation::async_sleep(browse_action_setting.wait).await;
+    }
+    Ok(browse_action_setting.max_iterations)
+}
+
+// Returns the last seen number of matched elements, which is below min_count if the wait ran out.
+pub async fn wait_for_selector_count(
+    web_driver: &mut WebDriver,
+    selector: &str,
+    min_count: usize,
+    browse_action_setting: &BrowseActionSetting,
+) -> WebDriverResult<usize> {
+    let mut count = web_driver.find_all(By::Css(selector)).await?.len();
+    let mut iteration = 0;
+    while count < min_count && iteration < browse_action_setting.max_iterations {
+        time_operation::async_sleep(browse_action_setting.wait).await;
+        count = web_driver.find_all(By::Css(selector)).await?.len();
+        iteration += 1;
+    }
+    Ok(count)
+}
+
+pub async fn scroll_to_bottom(web_driver: &mut WebDriver) -> WebDriverResult<()> {
+    scroll_to_bottom_until_stable(web_driver, &BrowseActionSetting::default()).await?;
+    Ok(())
+}
+
+#[cfg(all(test, feature = "scraper"))]
+mod tests {
+
+    use super::*;
+    use crate::aws_s3::AWSFileIO;
+    use crate::file_io::FileIO;
+    use crate::logger::ProjectLogger;
+    use crate::netdata::async_web_scraper::AsyncWebScraper;
+    use crate::slack_messenger::SlackMessenger;
+    use log::LevelFilter;
+    use reqwest::Url;
+    use std::env;
+    use std::path::Path;
+
+    #[test]
+    fn test_default_setting() {
+        let browse_action_setting = BrowseActionSetting::default();
+        assert_eq!(browse_action_setting.max_iterations, 30);
+        assert_eq!(browse_action_setting.wait, Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_scroll_to_bottom() {
+        let logger_name = "test_browse_action";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_netdata");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Debug);
+        let slack_messenger = SlackMessenger::new("", "", &project_logger);
+        let file_io = FileIO::new(&project_logger);
+        let aws_file_io = AWSFileIO::new(&project_logger).await;
+        let mut web_scraper = AsyncWebScraper::new(
+            &project_logger,
+            &slack_messenger,
+            &file_io,
+            &aws_file_io,
+            "sctys",
+        );
+        let browser = web_scraper.get_default_browser();
+        let url = Url::parse("https://www.nowgoal.com/football/results").unwrap();
+        web_scraper.turn_on_chrome_process().unwrap();
+        let mut web_driver = web_scraper.set_web_driver(browser).await.unwrap();
+        AsyncWebScraper::browse_page(&mut web_driver, &url)
+            .await
+            .unwrap();
+        let browse_action_setting = BrowseActionSetting {
+            max_iterations: 5,
+            wait: Duration::from_secs(1),
+        };
+        let iterations = scroll_to_bottom_until_stable(&mut web_driver, &browse_action_setting)
+            .await
+            .unwrap();
+        assert!(iterations <= 5);
+        web_scraper.close_web_driver(web_driver).await.unwrap();
+        web_scraper.kill_chrome_process().unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use serde::Deserialize;
+#[cfg(feature = "browser")]
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowserKind {
+    #[default]
+    Chrome,
+    Firefox,
+    Edge,
+}
+
+impl BrowserKind {
+    pub fn driver_process(&self) -> &'static str {
+        match self {
+            Self::Chrome => "chromedriver",
+            Self::Firefox => "geckodriver",
+            Self::Edge => "msedgedriver",
+        }
+    }
+
+    pub fn browser_name(&self) -> &'static str {
+        match self {
+            Self::Chrome => "chrome",
+            Self::Firefox => "firefox",
+            Self::Edge => "edge",
+        }
+    }
+
+    pub fn options_key(&self) -> &'static str {
+        match self {
+            Self::Chrome => "goog:chromeOptions",
+            Self::Firefox => "moz:firefoxOptions",
+            Self::Edge => "ms:edgeOptions",
+        }
+    }
+
+    // The browsers whose driver keeps the performance log of the devtools network events.
+    pub fn logging_prefs_key(&self) -> Option<&'static str> {
+        match self {
+            Self::Chrome => Some("goog:loggingPrefs"),
+            Self::Firefox => None,
+            Self::Edge => Some("ms:loggingPrefs"),
+        }
+    }
+
+    pub fn window_args(&self) -> Vec<&'static str> {
+        match self {
+            Self::Chrome | Self::Edge => vec![
+                "--disable-dev-shm-usage",
+                "--disable-gpu",
+                "--window-size=1920,1080",
+                "disable-blink-features=AutomationControlled",
+            ],
+            Self::Firefox => vec!["--width=1920", "--height=1080"],
+        }
+    }
+}
+
+#[cfg(feature = "browser")]
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum BlockingBrowserCapabilities {
+    Chrome(thirtyfour_sync::ChromeCapabilities),
+    Firefox(thirtyfour_sync::FirefoxCapabilities),
+    Edge(thirtyfour_sync::EdgeCapabilities),
+}
+
+#[cfg(feature = "browser")]
+impl From<thirtyfour_sync::ChromeCapabilities> for BlockingBrowserCapabilities {
+    fn from(browser: thirtyfour_sync::ChromeCapabilities) -> Self {
+        Self::Chrome(browser)
+    }
+}
+
+#[cfg(feature = "browser")]
+impl From<thirtyfour_sync::FirefoxCapabilities> for BlockingBrowserCapabilities {
+    fn from(browser: thirtyfour_sync::FirefoxCapabilities) -> Self {
+        Self::Firefox(browser)
+    }
+}
+
+#[cfg(feature = "browser")]
+impl From<thirtyfour_sync::EdgeCapabilities> for BlockingBrowserCapabilities {
+    fn from(browser: thirtyfour_sync::EdgeCapabilities) -> Self {
+        Self::Edge(browser)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct TestConfig {
+        browser_kind: BrowserKind,
+    }
+
+    #[test]
+    fn test_browser_kind() {
+        let config: TestConfig = toml::from_str("browser_kind = \"firefox\"").unwrap();
+        assert_eq!(config.browser_kind, BrowserKind::Firefox);
+        assert_eq!(config.browser_kind.driver_process(), "geckodriver");
+        assert_eq!(BrowserKind::default().driver_process(), "chromedriver");
+        assert_eq!(BrowserKind::Edge.driver_process(), "msedgedriver");
+        assert_eq!(config.browser_kind.logging_prefs_key(), None);
+        assert_eq!(
+            BrowserKind::default().logging_prefs_key(),
+            Some("goog:loggingPrefs")
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+#[cfg(feature = "browser")]
+use thirtyfour::Capabilities;
+
+use super::browser_kind::BrowserKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BrowserFingerprint {
+    pub user_agent: &'static str,
+    pub viewport: (u32, u32),
+}
+
+const CHROME_FINGERPRINTS: [BrowserFingerprint; 4] = [
+    BrowserFingerprint {
+        user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+        viewport: (1920, 1080),
+    },
+    BrowserFingerprint {
+        user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/123.0.0.0 Safari/537.36",
+        viewport: (1536, 864),
+    },
+    BrowserFingerprint {
+        user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+        viewport: (1440, 900),
+    },
+    BrowserFingerprint {
+        user_agent: "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+        viewport: (1366, 768),
+    },
+];
+
+const FIREFOX_FINGERPRINTS: [BrowserFingerprint; 2] = [
+    BrowserFingerprint {
+        user_agent:
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0",
+        viewport: (1920, 1080),
+    },
+    BrowserFingerprint {
+        user_agent:
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:125.0) Gecko/20100101 Firefox/125.0",
+        viewport: (1440, 900),
+    },
+];
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StealthConfig {
+    #[serde(default = "default_true")]
+    pub spoof_webdriver: bool,
+    #[serde(default = "default_true")]
+    pub inject_evasions: bool,
+    #[serde(default = "default_true")]
+    pub randomize_fingerprint: bool,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    pub webgl_vendor: Option<String>,
+    pub webgl_renderer: Option<String>,
+}
+
+impl Default for StealthConfig {
+    fn default() -> Self {
+        Self {
+            spoof_webdriver: true,
+            inject_evasions: true,
+            randomize_fingerprint: true,
+            locale: None,
+            timezone: None,
+            webgl_vendor: None,
+            webgl_renderer: None,
+        }
+    }
+}
+
+// The config travels inside the capabilities under a vendor key, so that every session created
+// from them gets its own fingerprint without threading the config through the browse functions.
+// The key is taken out again before the capabilities are sent to the driver.
+impl StealthConfig {
+    pub const CAPABILITY_KEY: &'static str = "sctys:stealth";
+    const WEBGL_VENDOR: &'static str = "Intel Inc.";
+    const WEBGL_RENDERER: &'static str = "Intel Iris OpenGL Engine";
+
+    #[cfg(feature = "browser")]
+    pub fn mark_capabilities(&self, browser: &Capabilities) -> Capabilities {
+        let mut browser = browser.clone();
+        if let Ok(stealth_value) = serde_json::to_value(self) {
+            browser.insert(Self::CAPABILITY_KEY.to_string(), stealth_value);
+        }
+        browser
+    }
+
+    #[cfg(feature = "browser")]
+    pub fn take_from_capabilities(browser: &mut Capabilities) -> Option<Self> {
+        browser
+            .remove(Self::CAPABILITY_KEY)
+            .and_then(|stealth_value| serde_json::from_value(stealth_value).ok())
+    }
+
+    pub fn sample_fingerprint(&self, browser_kind: BrowserKind) -> BrowserFingerprint {
+        let fingerprint_list: &[BrowserFingerprint] = match browser_kind {
+            BrowserKind::Chrome | BrowserKind::Edge => &CHROME_FINGERPRINTS,
+            BrowserKind::Firefox => &FIREFOX_FINGERPRINTS,
+        };
+        if self.randomize_fingerprint {
+            *fingerprint_list
+                .choose(&mut rand::thread_rng())
+                .unwrap_or(&fingerprint_list[0])
+        } else {
+            fingerprint_list[0]
+        }
+    }
+
+    fn get_languages(&self) -> Vec<String> {
+        match self.locale.as_deref() {
+            Some(locale) => match locale.split_once('-') {
+                Some((language, _)) => vec![locale.to_string(), language.to_string()],
+                None => vec![locale.to_string()],
+            },
+            None => vec!["en-US".to_string(), "en".to_string()],
+        }
+    }
+
+    pub fn browser_args(
+        &self,
+        browser_kind: BrowserKind,
+        fingerprint: &BrowserFingerprint,
+    ) -> Vec<String> {
+        let (width, height) = fingerprint.viewport;
+        match browser_kind {
+            BrowserKind::Chrome | BrowserKind::Edge => {
+                let mut arg_list = vec![
+                    format!("--user-agent={}", fingerprint.user_agent),
+                    format!("--window-size={width},{height}"),
+                ];
+                if let Some(locale) = &self.locale {
+                    arg_list.push(format!("--lang={locale}"));
+                }
+                if self.spoof_webdriver {
+                    arg_list.push("--disable-blink-features=AutomationControlled".to_string());
+                }
+                arg_list
+            }
+            BrowserKind::Firefox => vec![format!("--width={width}"), format!("--height={height}")],
+        }
+    }
+
+    // Firefox has no devtools protocol through geckodriver, so its overrides go into the prefs.
+    pub fn firefox_prefs(&self, fingerprint: &BrowserFingerprint) -> Vec<(&'static str, Value)> {
+        let mut pref_list = vec![
+            ("general.useragent.override", json!(fingerprint.user_agent)),
+            (
+                "intl.accept_languages",
+                json!(self.get_languages().join(",")),
+            ),
+        ];
+        if self.spoof_webdriver {
+            pref_list.push(("dom.webdriver.enabled", json!(false)));
+            pref_list.push(("useAutomationExtension", json!(false)));
+        }
+        pref_list
+    }
+
+    #[cfg(feature = "browser")]
+    pub fn apply_to_capabilities(
+        &self,
+        browser: &mut Capabilities,
+        browser_kind: BrowserKind,
+        fingerprint: &BrowserFingerprint,
+    ) {
+        let options = browser
+            .entry(browser_kind.options_key())
+            .or_insert_with(|| json!({}));
+        let Some(options) = options.as_object_mut() else {
+            return;
+        };
+        let arg_list = options.entry("args").or_insert_with(|| json!([]));
+        if let Some(arg_list) = arg_list.as_array_mut() {
+            arg_list.retain(|arg| {
+                !arg.as_str().map_or(false, |arg| {
+                    arg.starts_with("--window-size=")
+                        || arg.starts_with("--user-agent=")
+                        || arg.starts_with("--width=")
+                        || arg.starts_with("--height=")
+                })
+            });
+            for arg in self.browser_args(browser_kind, fingerprint) {
+                if !arg_list.contains(&json!(arg)) {
+                    arg_list.push(json!(arg));
+                }
+            }
+        }
+        if browser_kind == BrowserKind::Firefox {
+            let prefs = options.entry("prefs").or_insert_with(|| json!({}));
+            if let Some(prefs) = prefs.as_object_mut() {
+                for (name, value) in self.firefox_prefs(fingerprint) {
+                    prefs.insert(name.to_string(), value);
+                }
+            }
+        } else if self.spoof_webdriver {
+            options.insert("excludeSwitches".to_string(), json!(["enable-automation"]));
+            options.insert("useAutomationExtension".to_string(), json!(false));
+        }
+    }
+
+    pub fn evasion_script(&self) -> String {
+        let mut script = String::from("(() => {\n");
+        if self.spoof_webdriver {
+            script.push_str(
+                "Object.defineProperty(Navigator.prototype, 'webdriver', { get: () => undefined });\n",
+            );
+        }
+        if self.inject_evasions {
+            let languages = serde_json::to_string(&self.get_languages()).unwrap_or_default();
+            script.push_str(&format!(
+                "window.chrome = window.chrome || {{ runtime: {{}} }};
+Object.defineProperty(navigator, 'languages', {{ get: () => {languages} }});
+Object.defineProperty(navigator, 'plugins', {{ get: () => [1, 2, 3, 4, 5] }});
+const originalQuery = window.navigator.permissions.query;
+window.navigator.permissions.query = (parameters) => parameters.name === 'notifications'
+    ? Promise.resolve({{ state: Notification.permission }})
+    : originalQuery(parameters);
+"
+            ));
+        }
+        if self.inject_evasions || self.webgl_vendor.is_some() || self.webgl_renderer.is_some() {
+            let vendor = json!(self.webgl_vendor.as_deref().unwrap_or(Self::WEBGL_VENDOR));
+            let renderer = json!(self
+                .webgl_renderer
+                .as_deref()
+                .unwrap_or(Self::WEBGL_RENDERER));
+            script.push_str(&format!(
+                "for (const context of [WebGLRenderingContext, window.WebGL2RenderingContext]) {{
+    if (!context) continue;
+    const getParameter = context.prototype.getParameter;
+    context.prototype.getParameter = function (parameter) {{
+        if (parameter === 37445) return {vendor};
+        if (parameter === 37446) return {renderer};
+        return getParameter.call(this, parameter);
+    }};
+}}
+"
+            ));
+        }
+        script.push_str("})();");
+        script
+    }
+
+    // Devtools commands to run on a new chrome or edge session before the first navigation.
+    pub fn session_commands(&self) -> Vec<(&'static str, Value)> {
+        let mut command_list = vec![(
+            "Page.addScriptToEvaluateOnNewDocument",
+            json!({ "source": self.evasion_script() }),
+        )];
+        if let Some(timezone) = &self.timezone {
+            command_list.push((
+                "Emulation.setTimezoneOverride",
+                json!({ "timezoneId": timezone }),
+            ));
+        }
+        if let Some(locale) = &self.locale {
+            command_list.push(("Emulation.setLocaleOverride", json!({ "locale": locale })));
+        }
+        command_list
+    }
+}
+
+#[cfg(all(test, feature = "browser"))]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_stealth_config() {
+        let stealth_config: StealthConfig = toml::from_str(
+            r#"
+            randomize_fingerprint = false
+            locale = "en-GB"
+            timezone = "Europe/London"
+            webgl_vendor = "Google Inc."
+            "#,
+        )
+        .unwrap();
+        assert!(stealth_config.spoof_webdriver);
+        let mut browser = Capabilities::new();
+        browser.insert(
+            BrowserKind::Chrome.options_key().to_string(),
+            json!({ "args": ["--headless", "--window-size=1920,1080"] }),
+        );
+        let mut browser = stealth_config.mark_capabilities(&browser);
+        let stealth_config = StealthConfig::take_from_capabilities(&mut browser).unwrap();
+        assert!(!browser.contains_key(StealthConfig::CAPABILITY_KEY));
+        let fingerprint = stealth_config.sample_fingerprint(BrowserKind::Chrome);
+        assert_eq!(fingerprint, CHROME_FINGERPRINTS[0]);
+        stealth_config.apply_to_capabilities(&mut browser, BrowserKind::Chrome, &fingerprint);
+        let arg_list = browser[BrowserKind::Chrome.options_key()]["args"]
+            .as_array()
+            .unwrap();
+        assert_eq!(arg_list[0], "--headless");
+        assert_eq!(
+            arg_list
+                .iter()
+                .filter(|arg| arg.as_str().unwrap().starts_with("--window-size="))
+                .count(),
+            1
+        );
+        assert!(arg_list.contains(&json!("--lang=en-GB")));
+        let script = stealth_config.evasion_script();
+        assert!(script.contains("'webdriver'"));
+        assert!(script.contains("[\"en-GB\",\"en\"]"));
+        assert!(script.contains("return \"Google Inc.\""));
+        let command_list = stealth_config.session_commands();
+        assert_eq!(command_list.len(), 3);
+        assert_eq!(command_list[1].1["timezoneId"], "Europe/London");
+    }
+}
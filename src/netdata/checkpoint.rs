@@ -0,0 +1,99 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::data_struct::UrlFile;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct UrlFileRecord {
+    url: String,
+    file_name: String,
+    #[serde(default)]
+    priority: Option<i64>,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    meta: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UrlFileCheckpoint {
+    calling_func: String,
+    url_files: Vec<UrlFileRecord>,
+}
+
+impl UrlFileCheckpoint {
+    pub const CHECKPOINT_FILE: &'static str = "pending_url_files.checkpoint.toml";
+
+    pub fn new(calling_func: &str, url_file_list: &[UrlFile]) -> Self {
+        let url_files = url_file_list
+            .iter()
+            .map(|url_file| UrlFileRecord {
+                url: url_file.url.to_string(),
+                file_name: url_file.file_name.clone(),
+                priority: url_file.priority,
+                category: url_file.category.clone(),
+                meta: url_file.meta.clone().into_iter().collect(),
+            })
+            .collect();
+        Self {
+            calling_func: calling_func.to_string(),
+            url_files,
+        }
+    }
+
+    pub fn get_calling_func(&self) -> &str {
+        &self.calling_func
+    }
+
+    pub fn from_toml_str(checkpoint_str: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(checkpoint_str)
+    }
+
+    pub fn to_toml_string(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+
+    // The request spec of a url file is not kept in the checkpoint.
+    pub fn get_url_file_list(&self) -> Vec<UrlFile> {
+        self.url_files
+            .iter()
+            .filter_map(|record| {
+                Url::parse(&record.url).ok().map(|url| UrlFile {
+                    priority: record.priority,
+                    category: record.category.clone(),
+                    meta: record.meta.clone().into_iter().collect(),
+                    ..UrlFile::new(url, record.file_name.clone())
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let url_file_list = vec![
+            UrlFile::new(
+                Url::parse("http://tfl.gov.uk/tube/timetable/bakerloo/").unwrap(),
+                "test_scrape0.html".to_string(),
+            ),
+            UrlFile::new(
+                Url::parse("http://tfl.gov.uk/tube/timetable/central/").unwrap(),
+                "test_scrape1.html".to_string(),
+            )
+            .with_priority(2)
+            .with_category("timetable")
+            .with_meta("line", "central"),
+        ];
+        let checkpoint = UrlFileCheckpoint::new("test_checkpoint", &url_file_list);
+        let checkpoint_str = checkpoint.to_toml_string().unwrap();
+        let loaded_checkpoint = UrlFileCheckpoint::from_toml_str(&checkpoint_str).unwrap();
+        assert_eq!(loaded_checkpoint.get_calling_func(), "test_checkpoint");
+        assert_eq!(loaded_checkpoint.get_url_file_list(), url_file_list);
+    }
+}
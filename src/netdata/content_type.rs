@@ -0,0 +1,153 @@
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    Text,
+    Json,
+    Binary,
+}
+
+// The mime type of a response, without the parameters, as the charset is applied by reqwest in
+// decoding the text. A response without one is taken as text, as the pages were always saved as
+// text before.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentType {
+    pub mime_type: Option<String>,
+}
+
+impl ContentType {
+    const TEXT_MIME_TYPES: [&'static str; 4] = [
+        "application/xml",
+        "application/javascript",
+        "application/ecmascript",
+        "application/x-www-form-urlencoded",
+    ];
+    const EXTENSIONS: [(&'static str, &'static str); 20] = [
+        ("text/html", "html"),
+        ("application/xhtml+xml", "html"),
+        ("text/plain", "txt"),
+        ("text/csv", "csv"),
+        ("text/xml", "xml"),
+        ("application/xml", "xml"),
+        ("application/rss+xml", "xml"),
+        ("application/atom+xml", "xml"),
+        ("text/javascript", "js"),
+        ("application/javascript", "js"),
+        ("application/json", "json"),
+        ("image/png", "png"),
+        ("image/jpeg", "jpg"),
+        ("image/gif", "gif"),
+        ("image/webp", "webp"),
+        ("image/svg+xml", "svg"),
+        ("application/pdf", "pdf"),
+        ("application/zip", "zip"),
+        ("application/gzip", "gz"),
+        (
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "xlsx",
+        ),
+    ];
+
+    // e.g. text/html; charset=ISO-8859-1
+    pub fn parse(content_type: &str) -> Self {
+        let mime_type = content_type
+            .split(';')
+            .next()
+            .map(str::trim)
+            .filter(|mime_type| !mime_type.is_empty())
+            .map(str::to_ascii_lowercase);
+        Self { mime_type }
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .map_or_else(Self::default, Self::parse)
+    }
+
+    pub fn get_kind(&self) -> ContentKind {
+        match self.mime_type.as_deref() {
+            None => ContentKind::Text,
+            Some(mime_type) if mime_type == "application/json" || mime_type.ends_with("+json") => {
+                ContentKind::Json
+            }
+            Some(mime_type)
+                if mime_type.starts_with("text/")
+                    || (mime_type.ends_with("+xml") && mime_type != "image/svg+xml")
+                    || Self::TEXT_MIME_TYPES.contains(&mime_type) =>
+            {
+                ContentKind::Text
+            }
+            Some(_) => ContentKind::Binary,
+        }
+    }
+
+    pub fn get_extension(&self) -> Option<&'static str> {
+        let mime_type = self.mime_type.as_deref()?;
+        Self::EXTENSIONS
+            .iter()
+            .find(|(extension_mime_type, _)| *extension_mime_type == mime_type)
+            .map(|(_, extension)| *extension)
+            .or_else(|| mime_type.ends_with("+json").then_some("json"))
+    }
+
+    // The extension is only added when the file name has none, so the names given by the
+    // callers are kept.
+    pub fn get_file_name(&self, file_name: &str) -> String {
+        match self.get_extension() {
+            Some(extension) if Path::new(file_name).extension().is_none() => {
+                format!("{file_name}.{extension}")
+            }
+            _ => file_name.to_string(),
+        }
+    }
+}
+
+// The body to be saved. The binary bodies are saved verbatim and skip the check function, while
+// the text and json ones are saved as the content returned by the check function.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseContent {
+    pub content_type: ContentType,
+    pub binary_body: Option<Vec<u8>>,
+}
+
+impl ResponseContent {
+    pub fn get_body<'c>(&'c self, checked_content: &'c str) -> &'c [u8] {
+        self.binary_body
+            .as_deref()
+            .unwrap_or(checked_content.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_content_type() {
+        let content_type = ContentType::parse("text/html; charset=\"ISO-8859-1\"");
+        assert_eq!(content_type.mime_type.as_deref(), Some("text/html"));
+        assert_eq!(content_type.get_kind(), ContentKind::Text);
+        assert_eq!(content_type.get_file_name("bakerloo"), "bakerloo.html");
+        assert_eq!(content_type.get_file_name("bakerloo.htm"), "bakerloo.htm");
+        let content_type = ContentType::parse("application/vnd.api+json");
+        assert_eq!(content_type.get_kind(), ContentKind::Json);
+        assert_eq!(content_type.get_file_name("odds"), "odds.json");
+        let content_type = ContentType::parse("application/pdf");
+        assert_eq!(content_type.get_kind(), ContentKind::Binary);
+        assert_eq!(content_type.get_file_name("timetable"), "timetable.pdf");
+        assert_eq!(ContentType::default().get_kind(), ContentKind::Text);
+        assert_eq!(
+            ContentType::default().get_file_name("timetable"),
+            "timetable"
+        );
+        let response_content = ResponseContent {
+            content_type,
+            binary_body: Some(vec![0x25, 0x50, 0x44, 0x46]),
+        };
+        assert_eq!(response_content.get_body(""), b"%PDF");
+    }
+}
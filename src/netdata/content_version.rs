@@ -0,0 +1,106 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentVersion {
+    pub file_name: String,
+    pub content_hash: String,
+    pub saved_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionManifest {
+    pub versions: Vec<ContentVersion>,
+}
+
+impl VersionManifest {
+    const MANIFEST_SUFFIX: &'static str = ".manifest.toml";
+    const VERSION_TIME_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S";
+
+    pub fn manifest_file_name(file: &str) -> String {
+        format!("{file}{}", Self::MANIFEST_SUFFIX)
+    }
+
+    pub fn from_toml_str(manifest_str: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(manifest_str)
+    }
+
+    pub fn to_toml_string(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+
+    pub fn latest(&self) -> Option<&ContentVersion> {
+        self.versions.last()
+    }
+
+    pub fn is_unchanged(&self, content_hash: &str) -> bool {
+        self.latest()
+            .map_or(false, |version| version.content_hash == content_hash)
+    }
+
+    pub fn add_version(&mut self, file_name: &str, content_hash: &str, saved_at: DateTime<Utc>) {
+        self.versions.push(ContentVersion {
+            file_name: file_name.to_string(),
+            content_hash: content_hash.to_string(),
+            saved_at,
+        });
+    }
+
+    pub fn content_hash(content: impl AsRef<[u8]>) -> String {
+        Sha256::digest(content.as_ref())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    pub fn versioned_file_name(file: &str, saved_at: &DateTime<Utc>) -> String {
+        let time_str = saved_at.format(Self::VERSION_TIME_FORMAT);
+        let path = Path::new(file);
+        match (
+            path.file_stem().and_then(|stem| stem.to_str()),
+            path.extension().and_then(|ext| ext.to_str()),
+        ) {
+            (Some(stem), Some(ext)) => {
+                let versioned = format!("{stem}.{time_str}.{ext}");
+                path.with_file_name(versioned).to_string_lossy().to_string()
+            }
+            _ => format!("{file}.{time_str}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::time_operation;
+
+    #[test]
+    fn test_versioned_file_name() {
+        let saved_at = time_operation::utc_date_time(2024, 5, 1, 10, 0, 0);
+        assert_eq!(
+            VersionManifest::versioned_file_name("file.html", &saved_at),
+            "file.2024-05-01T10:00:00.html"
+        );
+        assert_eq!(
+            VersionManifest::versioned_file_name("file", &saved_at),
+            "file.2024-05-01T10:00:00"
+        );
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let saved_at = time_operation::utc_date_time(2024, 5, 1, 10, 0, 0);
+        let content_hash = VersionManifest::content_hash("<html></html>");
+        let mut manifest = VersionManifest::default();
+        assert!(!manifest.is_unchanged(&content_hash));
+        manifest.add_version("file.2024-05-01T10:00:00.html", &content_hash, saved_at);
+        let manifest_str = manifest.to_toml_string().unwrap();
+        let loaded_manifest = VersionManifest::from_toml_str(&manifest_str).unwrap();
+        assert_eq!(manifest, loaded_manifest);
+        assert!(loaded_manifest.is_unchanged(&content_hash));
+        assert!(!loaded_manifest.is_unchanged(&VersionManifest::content_hash("<html/>")));
+    }
+}
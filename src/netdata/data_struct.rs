@@ -1,36 +1,836 @@
-use reqwest::Url;
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::redirect::Policy;
+use reqwest::{blocking, ClientBuilder, Method, RequestBuilder, Url};
+use serde::{Deserialize, Deserializer, Serialize};
+#[cfg(feature = "browser")]
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
+use std::time::Duration;
+#[cfg(feature = "browser")]
+use thirtyfour::Capabilities;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+use super::browser_stealth::StealthConfig;
+use crate::config_value::{deserialize_optional_duration, ConfigDuration, ConfigPercentage};
+use crate::time_operation::{self, Clock};
+
+// The request spec, if any, replaces the method and body of the request builder of the scraper
+// and adds its headers. The category and meta are carried to the run manifest and the failure
+// reports, so that the results can be grouped by the caller's own keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct UrlFile {
     pub url: Url,
     pub file_name: String,
+    pub request_spec: Option<RequestSpec>,
+    pub priority: Option<i64>,
+    pub category: Option<String>,
+    pub meta: HashMap<String, String>,
+    pub timeout: Option<Duration>,
 }
 
 impl UrlFile {
+    pub const NO_CATEGORY: &'static str = "uncategorized";
+
     pub fn new(url: Url, file_name: String) -> Self {
-        Self { url, file_name }
+        Self {
+            url,
+            file_name,
+            request_spec: None,
+            priority: None,
+            category: None,
+            meta: HashMap::new(),
+            timeout: None,
+        }
+    }
+
+    pub fn with_request_spec(mut self, request_spec: RequestSpec) -> Self {
+        self.request_spec = Some(request_spec);
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i64) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    pub fn with_category(mut self, category: &str) -> Self {
+        self.category = Some(category.to_string());
+        self
+    }
+
+    pub fn with_meta(mut self, key: &str, value: &str) -> Self {
+        self.meta.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    // Overrides the timeout of the request setting for a slow endpoint.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn get_category(&self) -> &str {
+        self.category.as_deref().unwrap_or(Self::NO_CATEGORY)
     }
 }
 
-#[derive(Debug, Clone)]
+// Hashed by the url and file name only, which identify the url file in the frontier and the
+// queue. The meta map cannot be hashed.
+impl Hash for UrlFile {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.url.hash(state);
+        self.file_name.hash(state);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    #[default]
+    Local,
+    S3,
+}
+
+impl StorageBackend {
+    pub fn is_s3(&self) -> bool {
+        *self == Self::S3
+    }
+
+    fn deserialize_in_s3<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+        Ok(Self::deserialize(deserializer)?.is_s3())
+    }
+}
+
+// The settings can be read from the config, e.g. the scraper.request_setting table, except the
+// calling function which is set in code. The storage is given as storage_backend = "s3" there.
+// The min interval is the least sleep between two requests, raising the consecutive sleep of
+// the scraper. The timeout overrides the client timeout of each request of the AsyncWebScraper.
+// The connect timeout is a client setting, so a request with one is sent by a client of the
+// scraper instead of the one of the request builder function. The body beyond the max response
+// bytes is not read, and the response fails as too large unless truncate_response is set, in
+// which case the body is cut at the limit and checked as usual.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
 pub struct RequestSetting<'a> {
+    #[serde(skip)]
     pub calling_func: &'a str,
     pub log_only: bool,
+    #[serde(
+        rename = "storage_backend",
+        deserialize_with = "StorageBackend::deserialize_in_s3"
+    )]
     pub in_s3: bool,
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub max_total_duration: Option<Duration>,
+    pub dry_run: bool,
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub timeout: Option<Duration>,
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub connect_timeout: Option<Duration>,
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub min_interval: Option<Duration>,
+    pub max_response_bytes: Option<u64>,
+    pub truncate_response: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
 pub struct BrowseSetting<'a> {
     pub restart_web_driver: bool,
+    #[serde(skip)]
     pub calling_func: &'a str,
     pub log_only: bool,
+    #[serde(
+        rename = "storage_backend",
+        deserialize_with = "StorageBackend::deserialize_in_s3"
+    )]
     pub in_s3: bool,
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub max_total_duration: Option<Duration>,
+    pub dry_run: bool,
+    #[serde(skip)]
+    pub stealth: Option<&'a StealthConfig>,
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub timeout: Option<Duration>,
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    pub min_interval: Option<Duration>,
+}
+
+impl<'a> RequestSetting<'a> {
+    pub fn new(calling_func: &'a str, log_only: bool, in_s3: bool) -> Self {
+        Self {
+            calling_func,
+            log_only,
+            in_s3,
+            ..Self::default()
+        }
+    }
+
+    pub fn builder(calling_func: &'a str) -> RequestSettingBuilder<'a> {
+        RequestSettingBuilder::from(Self::new(calling_func, false, false))
+    }
+
+    pub fn get_deadline(&self, clock: &dyn Clock) -> Option<DateTime<Utc>> {
+        time_operation::get_deadline(self.max_total_duration, clock)
+    }
+
+    pub fn get_storage_backend(&self) -> StorageBackend {
+        if self.in_s3 {
+            StorageBackend::S3
+        } else {
+            StorageBackend::Local
+        }
+    }
+
+    pub fn get_sleep_range(&self, consecutive_sleep: (Duration, Duration)) -> (Duration, Duration) {
+        get_sleep_range(consecutive_sleep, self.min_interval)
+    }
+
+    pub fn get_request_limit(&self) -> RequestLimit {
+        RequestLimit {
+            timeout: self.timeout,
+            connect_timeout: self.connect_timeout,
+            max_response_bytes: self.max_response_bytes,
+            truncate_response: self.truncate_response,
+        }
+    }
+
+    pub fn get_url_request_options(&self, max_attempts: u32) -> UrlRequestOptions<'a> {
+        UrlRequestOptions {
+            in_s3: self.in_s3,
+            request_limit: self.get_request_limit(),
+            max_attempts,
+            calling_func: self.calling_func,
+            log_only: self.log_only,
+            record_domain_outcome: true,
+        }
+    }
+}
+
+// The options of the request of one url, taken from the RequestSetting of the run. The max
+// attempts are used by the direct request, while a request through a proxy is sent once per
+// proxy. The calling function and log_only route the alerts raised for the url. The domain
+// outcome is left to the caller when it scrapes the url in more than one tier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UrlRequestOptions<'a> {
+    pub in_s3: bool,
+    pub request_limit: RequestLimit,
+    pub max_attempts: u32,
+    pub calling_func: &'a str,
+    pub log_only: bool,
+    pub record_domain_outcome: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RequestLimit {
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    pub max_response_bytes: Option<u64>,
+    pub truncate_response: bool,
+}
+
+impl RequestLimit {
+    pub fn for_url_file(&self, url_file: &UrlFile) -> Self {
+        Self {
+            timeout: url_file.timeout.or(self.timeout),
+            ..*self
+        }
+    }
+}
+
+impl<'a> BrowseSetting<'a> {
+    pub fn new(calling_func: &'a str, log_only: bool, in_s3: bool) -> Self {
+        Self {
+            calling_func,
+            log_only,
+            in_s3,
+            ..Self::default()
+        }
+    }
+
+    pub fn builder(calling_func: &'a str) -> BrowseSettingBuilder<'a> {
+        BrowseSettingBuilder::from(Self::new(calling_func, false, false))
+    }
+
+    // The timeout of a browse is the page load timeout of the web driver session.
+    #[cfg(feature = "browser")]
+    pub fn apply_timeout(&self, mut browser: Capabilities) -> Capabilities {
+        if let Some(timeout) = self.timeout {
+            browser.insert(
+                "timeouts".to_string(),
+                json!({"pageLoad": timeout.as_millis() as u64}),
+            );
+        }
+        browser
+    }
+
+    pub fn get_deadline(&self, clock: &dyn Clock) -> Option<DateTime<Utc>> {
+        time_operation::get_deadline(self.max_total_duration, clock)
+    }
+
+    pub fn get_storage_backend(&self) -> StorageBackend {
+        if self.in_s3 {
+            StorageBackend::S3
+        } else {
+            StorageBackend::Local
+        }
+    }
+
+    pub fn get_sleep_range(&self, consecutive_sleep: (Duration, Duration)) -> (Duration, Duration) {
+        get_sleep_range(consecutive_sleep, self.min_interval)
+    }
+}
+
+fn get_sleep_range(
+    consecutive_sleep: (Duration, Duration),
+    min_interval: Option<Duration>,
+) -> (Duration, Duration) {
+    let min_interval = min_interval.unwrap_or_default();
+    (
+        consecutive_sleep.0.max(min_interval),
+        consecutive_sleep.1.max(min_interval),
+    )
+}
+
+// Starts from the defaults, or from a setting read from the config, e.g.
+// RequestSettingBuilder::from(setting).set_calling_func(calling_func).build().
+#[derive(Debug, Clone)]
+pub struct RequestSettingBuilder<'a> {
+    request_setting: RequestSetting<'a>,
+}
+
+impl<'a> From<RequestSetting<'a>> for RequestSettingBuilder<'a> {
+    fn from(request_setting: RequestSetting<'a>) -> Self {
+        Self { request_setting }
+    }
+}
+
+impl<'a> RequestSettingBuilder<'a> {
+    pub fn set_calling_func(mut self, calling_func: &'a str) -> Self {
+        self.request_setting.calling_func = calling_func;
+        self
+    }
+
+    pub fn set_log_only(mut self, log_only: bool) -> Self {
+        self.request_setting.log_only = log_only;
+        self
+    }
+
+    pub fn set_storage_backend(mut self, storage_backend: StorageBackend) -> Self {
+        self.request_setting.in_s3 = storage_backend.is_s3();
+        self
+    }
+
+    pub fn set_max_total_duration(mut self, max_total_duration: Duration) -> Self {
+        self.request_setting.max_total_duration = Some(max_total_duration);
+        self
+    }
+
+    pub fn set_dry_run(mut self, dry_run: bool) -> Self {
+        self.request_setting.dry_run = dry_run;
+        self
+    }
+
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.request_setting.timeout = Some(timeout);
+        self
+    }
+
+    pub fn set_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.request_setting.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    pub fn set_min_interval(mut self, min_interval: Duration) -> Self {
+        self.request_setting.min_interval = Some(min_interval);
+        self
+    }
+
+    pub fn set_max_response_bytes(mut self, max_response_bytes: u64, truncate: bool) -> Self {
+        self.request_setting.max_response_bytes = Some(max_response_bytes);
+        self.request_setting.truncate_response = truncate;
+        self
+    }
+
+    pub fn build(self) -> RequestSetting<'a> {
+        self.request_setting
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BrowseSettingBuilder<'a> {
+    browse_setting: BrowseSetting<'a>,
+}
+
+impl<'a> From<BrowseSetting<'a>> for BrowseSettingBuilder<'a> {
+    fn from(browse_setting: BrowseSetting<'a>) -> Self {
+        Self { browse_setting }
+    }
+}
+
+impl<'a> BrowseSettingBuilder<'a> {
+    pub fn set_restart_web_driver(mut self, restart_web_driver: bool) -> Self {
+        self.browse_setting.restart_web_driver = restart_web_driver;
+        self
+    }
+
+    pub fn set_calling_func(mut self, calling_func: &'a str) -> Self {
+        self.browse_setting.calling_func = calling_func;
+        self
+    }
+
+    pub fn set_log_only(mut self, log_only: bool) -> Self {
+        self.browse_setting.log_only = log_only;
+        self
+    }
+
+    pub fn set_storage_backend(mut self, storage_backend: StorageBackend) -> Self {
+        self.browse_setting.in_s3 = storage_backend.is_s3();
+        self
+    }
+
+    pub fn set_max_total_duration(mut self, max_total_duration: Duration) -> Self {
+        self.browse_setting.max_total_duration = Some(max_total_duration);
+        self
+    }
+
+    pub fn set_dry_run(mut self, dry_run: bool) -> Self {
+        self.browse_setting.dry_run = dry_run;
+        self
+    }
+
+    pub fn set_stealth(mut self, stealth: &'a StealthConfig) -> Self {
+        self.browse_setting.stealth = Some(stealth);
+        self
+    }
+
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.browse_setting.timeout = Some(timeout);
+        self
+    }
+
+    pub fn set_min_interval(mut self, min_interval: Duration) -> Self {
+        self.browse_setting.min_interval = Some(min_interval);
+        self
+    }
+
+    pub fn build(self) -> BrowseSetting<'a> {
+        self.browse_setting
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SaveMode {
+    #[default]
+    Overwrite,
+    SkipUnchanged,
+    VersionOnChange,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct SuccessPolicy {
+    pub min_success_rate: ConfigPercentage,
+    pub max_failures: Option<usize>,
+}
+
+impl SuccessPolicy {
+    pub fn is_met(&self, num_success: usize, num_total: usize) -> bool {
+        if num_total == 0 {
+            return true;
+        }
+        let success_rate = num_success as f64 / num_total as f64;
+        let num_failures = num_total.saturating_sub(num_success);
+        success_rate >= self.min_success_rate.get_fraction()
+            && self
+                .max_failures
+                .map_or(true, |max_failures| num_failures <= max_failures)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrapeStatus {
+    Success,
+    Failed,
+    Terminated,
+    Blocked,
+    Halted,
+    Tripped,
+    InvalidUrl,
+}
+
+impl ScrapeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failed => "failed",
+            Self::Terminated => "terminated",
+            Self::Blocked => "blocked",
+            Self::Halted => "halted",
+            Self::Tripped => "tripped",
+            Self::InvalidUrl => "invalid_url",
+        }
+    }
+
+    pub fn from_str_opt(status: &str) -> Option<Self> {
+        match status {
+            "success" => Some(Self::Success),
+            "failed" => Some(Self::Failed),
+            "terminated" => Some(Self::Terminated),
+            "blocked" => Some(Self::Blocked),
+            "halted" => Some(Self::Halted),
+            "tripped" => Some(Self::Tripped),
+            "invalid_url" => Some(Self::InvalidUrl),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScrapeOutcome {
+    pub status: ScrapeStatus,
+    pub attempts: u32,
+    pub latency: Option<Duration>,
+    pub bytes: Option<u64>,
+}
+
+impl ScrapeOutcome {
+    pub fn skipped(status: ScrapeStatus) -> Self {
+        Self {
+            status,
+            attempts: 0,
+            latency: None,
+            bytes: None,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status == ScrapeStatus::Success
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrapeTier {
+    Http,
+    Browser,
+}
+
+impl ScrapeTier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Http => "http",
+            Self::Browser => "browser",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackOutcome {
+    pub url_file: UrlFile,
+    pub outcome: ScrapeOutcome,
+    pub tier: ScrapeTier,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectPolicy {
+    Follow(usize),
+    NoFollow,
+    SameDomain(usize),
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self::Follow(Self::MAX_REDIRECTS)
+    }
+}
+
+impl RedirectPolicy {
+    const MAX_REDIRECTS: usize = 10;
+
+    pub fn allows(&self, origin_url: &Url, target_url: &Url, num_redirects: usize) -> bool {
+        match self {
+            Self::Follow(max_redirects) => num_redirects <= *max_redirects,
+            Self::NoFollow => false,
+            Self::SameDomain(max_redirects) => {
+                num_redirects <= *max_redirects && origin_url.host_str() == target_url.host_str()
+            }
+        }
+    }
+
+    // A redirect disallowed by the policy returns the 3xx response instead of an error.
+    pub fn to_reqwest_policy(self) -> Policy {
+        Policy::custom(move |attempt| {
+            let num_redirects = attempt.previous().len();
+            match attempt.previous().first() {
+                Some(origin_url) if self.allows(origin_url, attempt.url(), num_redirects) => {
+                    attempt.follow()
+                }
+                _ => attempt.stop(),
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectChain {
+    pub chain: Vec<Url>,
+    pub final_url: Url,
+    pub stopped_at: Option<Url>,
+}
+
+impl RedirectChain {
+    pub fn new(url: &Url) -> Self {
+        Self {
+            chain: Vec::new(),
+            final_url: url.clone(),
+            stopped_at: None,
+        }
+    }
+
+    pub fn follow(&mut self, url: Url) {
+        let previous_url = std::mem::replace(&mut self.final_url, url);
+        self.chain.push(previous_url);
+    }
+
+    pub fn is_redirected(&self) -> bool {
+        !self.chain.is_empty() || self.stopped_at.is_some()
+    }
+
+    // Redirects to another host are typical of geo-blocks and consent walls.
+    pub fn is_cross_domain(&self) -> bool {
+        let origin_url = self.chain.first().unwrap_or(&self.final_url);
+        self.stopped_at
+            .iter()
+            .chain(std::iter::once(&self.final_url))
+            .any(|url| url.host_str() != origin_url.host_str())
+    }
+}
+
+// Client level knobs for targets that reset connections at scale. The dns overrides resolve a
+// host to a fixed address, e.g. "www.nowgoal.com" = "1.2.3.4:443".
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ClientOptions {
+    #[serde(default)]
+    pub http2_prior_knowledge: bool,
+    pub pool_max_idle_per_host: Option<usize>,
+    pub tcp_keepalive: Option<ConfigDuration>,
+    #[serde(default = "ClientOptions::default_decompression")]
+    pub gzip: bool,
+    #[serde(default = "ClientOptions::default_decompression")]
+    pub brotli: bool,
+    #[serde(default)]
+    pub dns_overrides: HashMap<String, SocketAddr>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            http2_prior_knowledge: false,
+            pool_max_idle_per_host: None,
+            tcp_keepalive: None,
+            gzip: Self::default_decompression(),
+            brotli: Self::default_decompression(),
+            dns_overrides: HashMap::new(),
+        }
+    }
+}
+
+impl ClientOptions {
+    fn default_decompression() -> bool {
+        true
+    }
+
+    pub fn apply_to_builder(&self, mut client_builder: ClientBuilder) -> ClientBuilder {
+        if self.http2_prior_knowledge {
+            client_builder = client_builder.http2_prior_knowledge();
+        }
+        if let Some(pool_max_idle_per_host) = self.pool_max_idle_per_host {
+            client_builder = client_builder.pool_max_idle_per_host(pool_max_idle_per_host);
+        }
+        for (domain, address) in self.dns_overrides.iter() {
+            client_builder = client_builder.resolve(domain, *address);
+        }
+        client_builder
+            .tcp_keepalive(self.tcp_keepalive.map(|x| x.get_duration()))
+            .gzip(self.gzip)
+            .brotli(self.brotli)
+    }
+
+    pub fn apply_to_blocking_builder(
+        &self,
+        mut client_builder: blocking::ClientBuilder,
+    ) -> blocking::ClientBuilder {
+        if self.http2_prior_knowledge {
+            client_builder = client_builder.http2_prior_knowledge();
+        }
+        if let Some(pool_max_idle_per_host) = self.pool_max_idle_per_host {
+            client_builder = client_builder.pool_max_idle_per_host(pool_max_idle_per_host);
+        }
+        for (domain, address) in self.dns_overrides.iter() {
+            client_builder = client_builder.resolve(domain, *address);
+        }
+        client_builder
+            .tcp_keepalive(self.tcp_keepalive.map(|x| x.get_duration()))
+            .gzip(self.gzip)
+            .brotli(self.brotli)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum RequestBody {
+    #[default]
+    Empty,
+    Form(Vec<(String, String)>),
+    Json(Value),
+    Text(String),
+}
+
+// A plain description of a request, so that a fresh builder can be made for every attempt even
+// when the body would make the builder itself impossible to clone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestSpec {
+    pub method: Method,
+    pub headers: HeaderMap,
+    pub body: RequestBody,
+}
+
+impl RequestSpec {
+    pub fn new(method: Method) -> Self {
+        Self {
+            method,
+            headers: HeaderMap::new(),
+            body: RequestBody::Empty,
+        }
+    }
+
+    pub fn get() -> Self {
+        Self::new(Method::GET)
+    }
+
+    pub fn post_form(form: Vec<(String, String)>) -> Self {
+        Self::new(Method::POST).with_body(RequestBody::Form(form))
+    }
+
+    pub fn post_json(json: Value) -> Self {
+        Self::new(Method::POST).with_body(RequestBody::Json(json))
+    }
+
+    pub fn put_json(json: Value) -> Self {
+        Self::new(Method::PUT).with_body(RequestBody::Json(json))
+    }
+
+    pub fn with_body(mut self, body: RequestBody) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    pub fn to_blocking_builder(
+        &self,
+        client: &blocking::Client,
+        url: Url,
+    ) -> blocking::RequestBuilder {
+        let request_builder = client
+            .request(self.method.clone(), url)
+            .headers(self.headers.clone());
+        match &self.body {
+            RequestBody::Empty => request_builder,
+            RequestBody::Form(form) => request_builder.form(form),
+            RequestBody::Json(json) => request_builder.json(json),
+            RequestBody::Text(text) => request_builder.body(text.clone()),
+        }
+    }
+
+    // Rebuilds an async request builder with the method, headers and body of the spec, keeping
+    // the client, headers and timeout of the original builder.
+    pub fn apply_to_builder(&self, request_builder: RequestBuilder, url: Url) -> RequestBuilder {
+        let (client, request) = request_builder.build_split();
+        let mut spec_builder = client.request(self.method.clone(), url);
+        if let Ok(request) = request {
+            spec_builder = spec_builder.headers(request.headers().clone());
+            if let Some(timeout) = request.timeout() {
+                spec_builder = spec_builder.timeout(*timeout);
+            }
+        }
+        spec_builder = spec_builder.headers(self.headers.clone());
+        match &self.body {
+            RequestBody::Empty => spec_builder,
+            RequestBody::Form(form) => spec_builder.form(form),
+            RequestBody::Json(json) => spec_builder.json(json),
+            RequestBody::Text(text) => spec_builder.body(text.clone()),
+        }
+    }
+}
+
+// The cause of a failed request, so that the callers can tell e.g. a timeout from a 403 without
+// parsing the message. The display is the message logged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScrapeFailure {
+    HttpStatus(u16),
+    Timeout,
+    LoadFailed(String),
+    DecodeError(String),
+    TooLarge { num_bytes: u64, max_bytes: u64 },
+    Blocked(String),
+    ValidationFailed(String),
+    SaveFailed(String),
+}
+
+impl ScrapeFailure {
+    pub fn validation_failed(reason: &str) -> Self {
+        Self::ValidationFailed(reason.to_string())
+    }
+
+    pub fn from_load_error(e: &reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::Timeout
+        } else {
+            Self::LoadFailed(e.to_string())
+        }
+    }
+
+    pub fn from_decode_error(e: &reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::Timeout
+        } else {
+            Self::DecodeError(e.to_string())
+        }
+    }
+}
+
+impl fmt::Display for ScrapeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HttpStatus(status) => write!(f, "Server return status code {status}"),
+            Self::Timeout => write!(f, "Request timed out."),
+            Self::LoadFailed(e) => write!(f, "{e}"),
+            Self::DecodeError(e) => write!(f, "Unable to decode the response. {e}"),
+            Self::TooLarge {
+                num_bytes,
+                max_bytes,
+            } => write!(f, "{num_bytes} bytes over the limit of {max_bytes} bytes."),
+            Self::Blocked(reason) | Self::ValidationFailed(reason) => write!(f, "{reason}"),
+            Self::SaveFailed(e) => write!(f, "{e}"),
+        }
+    }
 }
 
 pub enum ResponseCheckResult {
     Ok(String),
-    ErrContinue(String),
-    ErrTerminate(String),
+    ErrContinue(ScrapeFailure),
+    ErrTerminate(ScrapeFailure),
+    Blocked(ScrapeFailure),
+    TooLarge(ScrapeFailure),
 }
 
 impl ResponseCheckResult {
@@ -41,10 +841,31 @@ impl ResponseCheckResult {
         }
     }
 
+    pub fn get_failure(&self) -> Option<&ScrapeFailure> {
+        match self {
+            Self::Ok(_) => None,
+            Self::ErrContinue(e) | Self::ErrTerminate(e) | Self::Blocked(e) | Self::TooLarge(e) => {
+                Some(e)
+            }
+        }
+    }
+
     pub fn get_error(&self) -> Option<String> {
         match self {
-            Self::ErrContinue(e) | Self::ErrTerminate(e) => Some(e.to_string()),
+            Self::ErrContinue(e) | Self::ErrTerminate(e) | Self::Blocked(e) | Self::TooLarge(e) => {
+                Some(e.to_string())
+            }
             _ => None,
         }
     }
+
+    pub fn get_status(&self) -> ScrapeStatus {
+        match self {
+            Self::Ok(_) => ScrapeStatus::Success,
+            Self::ErrContinue(_) => ScrapeStatus::Failed,
+            Self::ErrTerminate(_) => ScrapeStatus::Terminated,
+            Self::Blocked(_) => ScrapeStatus::Blocked,
+            Self::TooLarge(_) => ScrapeStatus::Terminated,
+        }
+    }
 }
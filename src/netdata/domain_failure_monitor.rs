@@ -0,0 +1,235 @@
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::config_value::ConfigPercentage;
+use crate::time_operation::{Clock, SystemClock};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainAlert {
+    pub domain: String,
+    pub failure_rate: f64,
+    pub num_samples: usize,
+    pub tripped: bool,
+    pub cooldown: Option<Duration>,
+}
+
+impl DomainAlert {
+    pub fn get_message(&self) -> String {
+        let action = match (self.tripped, self.cooldown) {
+            (true, Some(cooldown)) => format!(
+                "Remaining urls of the domain are deferred for {}s.",
+                cooldown.as_secs()
+            ),
+            (true, None) => "Remaining urls of the domain are skipped.".to_string(),
+            (false, _) => "Scraping continues.".to_string(),
+        };
+        format!(
+            "Failure rate of {} reached {:.1}% over the last {} urls. {action}",
+            self.domain,
+            self.failure_rate * 100.0,
+            self.num_samples
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct DomainWindow {
+    outcomes: VecDeque<bool>,
+    alerted: bool,
+    tripped: bool,
+    tripped_at: Option<DateTime<Utc>>,
+}
+
+impl DomainWindow {
+    fn failure_rate(&self) -> f64 {
+        let num_failure = self.outcomes.iter().filter(|success| !**success).count();
+        num_failure as f64 / self.outcomes.len() as f64
+    }
+}
+
+#[derive(Debug)]
+pub struct DomainFailureMonitor {
+    failure_threshold: ConfigPercentage,
+    window_size: usize,
+    min_samples: usize,
+    trip_on_alert: bool,
+    cooldown: Option<Duration>,
+    clock: Arc<dyn Clock>,
+    domain_windows: Mutex<HashMap<String, DomainWindow>>,
+}
+
+impl DomainFailureMonitor {
+    const WINDOW_SIZE: usize = 20;
+    const MIN_SAMPLES: usize = 10;
+
+    pub fn new(failure_threshold: ConfigPercentage) -> Self {
+        Self {
+            failure_threshold,
+            window_size: Self::WINDOW_SIZE,
+            min_samples: Self::MIN_SAMPLES,
+            trip_on_alert: false,
+            cooldown: None,
+            clock: Arc::new(SystemClock),
+            domain_windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn set_window_size(&mut self, window_size: usize) {
+        self.window_size = window_size.max(1);
+        self.min_samples = self.min_samples.min(self.window_size);
+    }
+
+    pub fn set_min_samples(&mut self, min_samples: usize) {
+        self.min_samples = min_samples.clamp(1, self.window_size);
+    }
+
+    pub fn set_trip_on_alert(&mut self, trip_on_alert: bool) {
+        self.trip_on_alert = trip_on_alert;
+    }
+
+    // A tripped domain is closed again after the cooldown, with a fresh window, instead of for
+    // the rest of the run.
+    pub fn set_cooldown(&mut self, cooldown: Duration) {
+        self.cooldown = Some(cooldown);
+    }
+
+    // The cooldown runs on this clock, so set the same clock as the scraper when it is mocked.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub fn domain_of(url: &Url) -> String {
+        url.host_str().unwrap_or_default().to_string()
+    }
+
+    pub fn is_tripped(&self, url: &Url) -> bool {
+        self.get_cooldown_remaining(url).is_some()
+    }
+
+    // None if the domain is not tripped. Without a cooldown the domain stays tripped until reset,
+    // which is given as Duration::MAX.
+    pub fn get_cooldown_remaining(&self, url: &Url) -> Option<Duration> {
+        let domain = Self::domain_of(url);
+        let mut domain_windows = self
+            .domain_windows
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let domain_window = domain_windows.get(&domain).filter(|x| x.tripped)?;
+        match (self.cooldown, domain_window.tripped_at) {
+            (Some(cooldown), Some(tripped_at)) => {
+                let elapsed = (self.clock.now() - tripped_at).to_std().unwrap_or_default();
+                let cooldown_remaining = cooldown.saturating_sub(elapsed);
+                if cooldown_remaining.is_zero() {
+                    domain_windows.remove(&domain);
+                    None
+                } else {
+                    Some(cooldown_remaining)
+                }
+            }
+            (cooldown, _) => Some(cooldown.unwrap_or(Duration::MAX)),
+        }
+    }
+
+    pub fn reset_domain(&self, domain: &str) {
+        self.domain_windows
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(domain);
+    }
+
+    // An alert is raised once when the rolling failure rate crosses the threshold, and re-armed
+    // after the rate falls back below it unless the domain has been tripped.
+    pub fn record(&self, url: &Url, success: bool) -> Option<DomainAlert> {
+        let domain = Self::domain_of(url);
+        let mut domain_windows = self
+            .domain_windows
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let domain_window = domain_windows.entry(domain.clone()).or_default();
+        domain_window.outcomes.push_back(success);
+        while domain_window.outcomes.len() > self.window_size {
+            domain_window.outcomes.pop_front();
+        }
+        if domain_window.outcomes.len() < self.min_samples {
+            return None;
+        }
+        let failure_rate = domain_window.failure_rate();
+        if failure_rate > self.failure_threshold.get_fraction() {
+            if domain_window.alerted {
+                return None;
+            }
+            domain_window.alerted = true;
+            domain_window.tripped = self.trip_on_alert;
+            if domain_window.tripped {
+                domain_window.tripped_at = Some(self.clock.now());
+            }
+            Some(DomainAlert {
+                domain,
+                failure_rate,
+                num_samples: domain_window.outcomes.len(),
+                tripped: domain_window.tripped,
+                cooldown: self.cooldown.filter(|_| domain_window.tripped),
+            })
+        } else {
+            if !domain_window.tripped {
+                domain_window.alerted = false;
+            }
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::time_operation::MockClock;
+
+    #[test]
+    fn test_domain_failure_alert() {
+        let mut monitor = DomainFailureMonitor::new("50%".parse().unwrap());
+        monitor.set_window_size(4);
+        monitor.set_min_samples(4);
+        monitor.set_trip_on_alert(true);
+        let blocked_url = Url::parse("https://www.nowgoal.com/football/live").unwrap();
+        let healthy_url = Url::parse("https://tfl.gov.uk/tube/timetable/bakerloo/").unwrap();
+        for success in [true, false, false] {
+            assert!(monitor.record(&blocked_url, success).is_none());
+            assert!(monitor.record(&healthy_url, true).is_none());
+        }
+        let alert = monitor.record(&blocked_url, false).unwrap();
+        assert_eq!(alert.domain, "www.nowgoal.com");
+        assert_eq!(alert.failure_rate, 0.75);
+        assert!(alert.tripped);
+        assert!(monitor.record(&blocked_url, false).is_none());
+        assert!(monitor.is_tripped(&blocked_url));
+        assert!(!monitor.is_tripped(&healthy_url));
+        monitor.reset_domain("www.nowgoal.com");
+        assert!(!monitor.is_tripped(&blocked_url));
+    }
+
+    #[test]
+    fn test_domain_cooldown() {
+        let mut monitor = DomainFailureMonitor::new("50%".parse().unwrap());
+        monitor.set_window_size(2);
+        monitor.set_trip_on_alert(true);
+        monitor.set_cooldown(Duration::from_secs(60));
+        let mock_clock = Arc::new(MockClock::new(Utc::now()));
+        monitor.set_clock(mock_clock.clone());
+        let blocked_url = Url::parse("https://www.nowgoal.com/football/live").unwrap();
+        assert!(monitor.record(&blocked_url, false).is_none());
+        let alert = monitor.record(&blocked_url, false).unwrap();
+        assert!(alert.get_message().contains("deferred"));
+        let cooldown_remaining = monitor.get_cooldown_remaining(&blocked_url).unwrap();
+        assert_eq!(cooldown_remaining, Duration::from_secs(60));
+        mock_clock.advance(Duration::from_secs(30));
+        assert!(monitor.is_tripped(&blocked_url));
+        mock_clock.advance(Duration::from_secs(30));
+        assert!(!monitor.is_tripped(&blocked_url));
+        assert!(monitor.record(&blocked_url, true).is_none());
+        assert!(monitor.record(&blocked_url, false).is_none());
+    }
+}
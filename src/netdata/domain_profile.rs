@@ -0,0 +1,199 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use super::data_struct::ScrapeTier;
+use super::response_validator::ResponseValidator;
+use crate::config_value::ConfigDuration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    #[default]
+    Direct,
+    PublicProxy,
+    PrivateProxy,
+    PrivateVpn,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct DomainProfile {
+    pub backend: Option<ScrapeTier>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub min_interval: Option<ConfigDuration>,
+    #[serde(default)]
+    pub proxy_mode: ProxyMode,
+    pub num_retry: Option<u32>,
+    pub retry_sleep: Option<ConfigDuration>,
+    pub check_func: Option<String>,
+}
+
+impl DomainProfile {
+    pub fn get_headers(&self) -> HeaderMap {
+        self.headers
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::from_bytes(name.as_bytes()).ok()?,
+                    HeaderValue::from_str(value).ok()?,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DomainProfileFile {
+    #[serde(default)]
+    domains: HashMap<String, DomainProfile>,
+}
+
+// Profiles are keyed by host. A profile of a parent domain also applies to its subdomains unless
+// they have their own, e.g. nowgoal.com covers live.nowgoal.com. Check functions are named in
+// the config and registered in code, since closures cannot live in toml.
+#[derive(Default)]
+pub struct DomainProfileRegistry {
+    profiles: HashMap<String, DomainProfile>,
+    validators: HashMap<String, Box<dyn ResponseValidator>>,
+    next_request_times: Mutex<HashMap<String, Instant>>,
+}
+
+impl fmt::Debug for DomainProfileRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DomainProfileRegistry")
+            .field("profiles", &self.profiles)
+            .field("validators", &self.validators.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl DomainProfileRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_toml_str(registry_str: &str) -> Result<Self, toml::de::Error> {
+        let profile_file: DomainProfileFile = toml::from_str(registry_str)?;
+        Ok(Self {
+            profiles: profile_file
+                .domains
+                .into_iter()
+                .map(|(domain, domain_profile)| (domain.to_lowercase(), domain_profile))
+                .collect(),
+            ..Self::default()
+        })
+    }
+
+    pub fn set_profile(&mut self, domain: &str, domain_profile: DomainProfile) {
+        self.profiles.insert(domain.to_lowercase(), domain_profile);
+    }
+
+    pub fn register_validator(&mut self, name: &str, validator: impl ResponseValidator + 'static) {
+        self.validators
+            .insert(name.to_string(), Box::new(validator));
+    }
+
+    fn find_domain(&self, url: &Url) -> Option<&str> {
+        let host = url.host_str()?;
+        let mut domain = host;
+        loop {
+            if let Some((key, _)) = self.profiles.get_key_value(domain) {
+                return Some(key);
+            }
+            domain = domain.split_once('.')?.1;
+        }
+    }
+
+    pub fn get_profile(&self, url: &Url) -> Option<&DomainProfile> {
+        self.find_domain(url)
+            .and_then(|domain| self.profiles.get(domain))
+    }
+
+    pub fn get_check_func(&self, url: &Url) -> Option<&dyn ResponseValidator> {
+        self.get_profile(url)?
+            .check_func
+            .as_ref()
+            .and_then(|name| self.validators.get(name))
+            .map(|validator| validator.as_ref())
+    }
+
+    // Reserves the next request slot of the domain and returns how long to wait for it, so
+    // concurrent requests to the same domain are spaced by min_interval.
+    pub fn reserve_request_delay(&self, url: &Url) -> Duration {
+        let Some(domain) = self.find_domain(url) else {
+            return Duration::ZERO;
+        };
+        let Some(min_interval) = self
+            .profiles
+            .get(domain)
+            .and_then(|domain_profile| domain_profile.min_interval)
+        else {
+            return Duration::ZERO;
+        };
+        let now = Instant::now();
+        let mut next_request_times = self
+            .next_request_times
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let request_time = next_request_times
+            .get(domain)
+            .map_or(now, |next_request_time| (*next_request_time).max(now));
+        next_request_times.insert(
+            domain.to_string(),
+            request_time + min_interval.get_duration(),
+        );
+        request_time - now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::netdata::data_struct::ResponseCheckResult;
+
+    #[test]
+    fn test_domain_profile_registry() {
+        let registry_str = r#"
+            [domains."nowgoal.com"]
+            backend = "browser"
+            min_interval = "2s"
+            proxy_mode = "private_proxy"
+            num_retry = 5
+            check_func = "no_captcha"
+
+            [domains."nowgoal.com".headers]
+            Referer = "https://www.nowgoal.com/"
+
+            [domains."tfl.gov.uk"]
+            backend = "http"
+        "#;
+        let mut registry = DomainProfileRegistry::from_toml_str(registry_str).unwrap();
+        registry.register_validator("no_captcha", |response: &str| {
+            ResponseCheckResult::Ok(response.to_string())
+        });
+        let url = Url::parse("https://live.nowgoal.com/football").unwrap();
+        let domain_profile = registry.get_profile(&url).unwrap();
+        assert_eq!(domain_profile.backend, Some(ScrapeTier::Browser));
+        assert_eq!(domain_profile.proxy_mode, ProxyMode::PrivateProxy);
+        assert_eq!(domain_profile.num_retry, Some(5));
+        assert_eq!(
+            domain_profile.get_headers()["referer"],
+            "https://www.nowgoal.com/"
+        );
+        assert!(registry.get_check_func(&url).is_some());
+        assert_eq!(registry.reserve_request_delay(&url), Duration::ZERO);
+        assert!(registry.reserve_request_delay(&url) > Duration::from_secs(1));
+        let url = Url::parse("https://tfl.gov.uk/tube/timetable/bakerloo/").unwrap();
+        assert_eq!(registry.reserve_request_delay(&url), Duration::ZERO);
+        assert_eq!(registry.reserve_request_delay(&url), Duration::ZERO);
+        assert!(registry.get_check_func(&url).is_none());
+        let url = Url::parse("https://www.example.com/").unwrap();
+        assert!(registry.get_profile(&url).is_none());
+    }
+}
@@ -0,0 +1,275 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use super::data_struct::{ScrapeOutcome, ScrapeStatus, ScrapeTier};
+use super::domain_failure_monitor::DomainFailureMonitor;
+use super::domain_profile::ProxyMode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ScrapeBackend {
+    pub tier: ScrapeTier,
+    pub proxy_mode: ProxyMode,
+}
+
+impl ScrapeBackend {
+    pub fn new(tier: ScrapeTier, proxy_mode: ProxyMode) -> Self {
+        Self { tier, proxy_mode }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackendStats {
+    pub tier: ScrapeTier,
+    pub proxy_mode: ProxyMode,
+    pub num_requests: u64,
+    pub num_success: u64,
+    pub num_blocked: u64,
+    pub latencies_ms: VecDeque<u64>,
+}
+
+impl BackendStats {
+    const MAX_LATENCY_SAMPLES: usize = 100;
+
+    pub fn new(backend: ScrapeBackend) -> Self {
+        Self {
+            tier: backend.tier,
+            proxy_mode: backend.proxy_mode,
+            num_requests: 0,
+            num_success: 0,
+            num_blocked: 0,
+            latencies_ms: VecDeque::new(),
+        }
+    }
+
+    pub fn get_backend(&self) -> ScrapeBackend {
+        ScrapeBackend::new(self.tier, self.proxy_mode)
+    }
+
+    fn record(&mut self, outcome: &ScrapeOutcome) {
+        self.num_requests += 1;
+        match outcome.status {
+            ScrapeStatus::Success => self.num_success += 1,
+            ScrapeStatus::Blocked => self.num_blocked += 1,
+            _ => {}
+        }
+        if let (true, Some(latency)) = (outcome.is_success(), outcome.latency) {
+            if self.latencies_ms.len() >= Self::MAX_LATENCY_SAMPLES {
+                self.latencies_ms.pop_front();
+            }
+            self.latencies_ms.push_back(latency.as_millis() as u64);
+        }
+    }
+
+    pub fn get_success_rate(&self) -> Option<f64> {
+        (self.num_requests > 0).then(|| self.num_success as f64 / self.num_requests as f64)
+    }
+
+    pub fn get_block_rate(&self) -> Option<f64> {
+        (self.num_requests > 0).then(|| self.num_blocked as f64 / self.num_requests as f64)
+    }
+
+    // Of the latest successful requests.
+    pub fn get_median_latency(&self) -> Option<Duration> {
+        let mut latencies_ms: Vec<u64> = self.latencies_ms.iter().copied().collect();
+        latencies_ms.sort_unstable();
+        latencies_ms
+            .get(latencies_ms.len() / 2)
+            .map(|latency_ms| Duration::from_millis(*latency_ms))
+    }
+
+    // The success rate smoothed with one success and one failure, so that a backend not tried
+    // yet ranks between the ones that mostly succeed and the ones that mostly fail.
+    fn get_score(&self) -> f64 {
+        (self.num_success as f64 + 1.0) / (self.num_requests as f64 + 2.0)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DomainStatsFile {
+    #[serde(default)]
+    domains: BTreeMap<String, Vec<BackendStats>>,
+}
+
+// The outcomes of each domain by backend, kept across runs in a toml file, so that the
+// scrapers try first the backend which has worked best for the domain.
+#[derive(Debug, Default)]
+pub struct DomainStatsStore {
+    domain_stats: Mutex<BTreeMap<String, Vec<BackendStats>>>,
+}
+
+impl DomainStatsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_toml_str(stats_str: &str) -> std::result::Result<Self, toml::de::Error> {
+        let stats_file: DomainStatsFile = toml::from_str(stats_str)?;
+        Ok(Self {
+            domain_stats: Mutex::new(stats_file.domains),
+        })
+    }
+
+    pub fn to_toml_string(&self) -> std::result::Result<String, toml::ser::Error> {
+        let stats_file = DomainStatsFile {
+            domains: self.lock_domain_stats().clone(),
+        };
+        toml::to_string(&stats_file)
+    }
+
+    // A missing file gives an empty store, e.g. on the first run.
+    pub fn load(stats_file: &Path) -> Result<Self> {
+        match fs::read_to_string(stats_file) {
+            Ok(stats_str) => {
+                Self::from_toml_str(&stats_str).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, stats_file: &Path) -> Result<()> {
+        let stats_str = self
+            .to_toml_string()
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        fs::write(stats_file, stats_str)
+    }
+
+    fn lock_domain_stats(&self) -> MutexGuard<'_, BTreeMap<String, Vec<BackendStats>>> {
+        self.domain_stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn record(&self, url: &Url, backend: ScrapeBackend, outcome: &ScrapeOutcome) {
+        let mut domain_stats = self.lock_domain_stats();
+        let backend_stats_list = domain_stats
+            .entry(DomainFailureMonitor::domain_of(url))
+            .or_default();
+        match backend_stats_list
+            .iter_mut()
+            .find(|backend_stats| backend_stats.get_backend() == backend)
+        {
+            Some(backend_stats) => backend_stats.record(outcome),
+            None => {
+                let mut backend_stats = BackendStats::new(backend);
+                backend_stats.record(outcome);
+                backend_stats_list.push(backend_stats);
+            }
+        }
+    }
+
+    pub fn get_stats(&self, domain: &str, backend: ScrapeBackend) -> Option<BackendStats> {
+        self.lock_domain_stats()
+            .get(domain)?
+            .iter()
+            .find(|backend_stats| backend_stats.get_backend() == backend)
+            .cloned()
+    }
+
+    // Orders the backends from the best to the worst for the domain of the url, by the
+    // smoothed success rate and then the median latency. The backends with the same record
+    // keep their given order.
+    pub fn rank_backends(&self, url: &Url, backend_list: &[ScrapeBackend]) -> Vec<ScrapeBackend> {
+        let domain = DomainFailureMonitor::domain_of(url);
+        let mut ranked_list: Vec<(ScrapeBackend, f64, Option<Duration>)> = backend_list
+            .iter()
+            .map(|backend| {
+                let backend_stats = self
+                    .get_stats(&domain, *backend)
+                    .unwrap_or_else(|| BackendStats::new(*backend));
+                (
+                    *backend,
+                    backend_stats.get_score(),
+                    backend_stats.get_median_latency(),
+                )
+            })
+            .collect();
+        ranked_list.sort_by(|(_, score_1, latency_1), (_, score_2, latency_2)| {
+            score_2
+                .total_cmp(score_1)
+                .then_with(|| match (latency_1, latency_2) {
+                    (Some(latency_1), Some(latency_2)) => latency_1.cmp(latency_2),
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (None, None) => std::cmp::Ordering::Equal,
+                })
+        });
+        ranked_list
+            .into_iter()
+            .map(|(backend, _, _)| backend)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::env;
+
+    fn outcome(status: ScrapeStatus, latency_ms: u64) -> ScrapeOutcome {
+        ScrapeOutcome {
+            status,
+            attempts: 1,
+            latency: Some(Duration::from_millis(latency_ms)),
+            bytes: None,
+        }
+    }
+
+    #[test]
+    fn test_domain_stats_store() {
+        let domain_stats_store = DomainStatsStore::new();
+        let url = Url::parse("https://live.nowgoal.com/odds/1").unwrap();
+        let http = ScrapeBackend::new(ScrapeTier::Http, ProxyMode::Direct);
+        let browser = ScrapeBackend::new(ScrapeTier::Browser, ProxyMode::Direct);
+        assert_eq!(
+            domain_stats_store.rank_backends(&url, &[http, browser]),
+            vec![http, browser]
+        );
+        for _ in 0..3 {
+            domain_stats_store.record(&url, http, &outcome(ScrapeStatus::Blocked, 100));
+            domain_stats_store.record(&url, browser, &outcome(ScrapeStatus::Success, 3000));
+        }
+        domain_stats_store.record(&url, browser, &outcome(ScrapeStatus::Success, 1000));
+        let browser_stats = domain_stats_store
+            .get_stats("live.nowgoal.com", browser)
+            .unwrap();
+        assert_eq!(browser_stats.get_success_rate(), Some(1.0));
+        assert_eq!(
+            browser_stats.get_median_latency(),
+            Some(Duration::from_millis(3000))
+        );
+        let http_stats = domain_stats_store
+            .get_stats("live.nowgoal.com", http)
+            .unwrap();
+        assert_eq!(http_stats.get_block_rate(), Some(1.0));
+        assert_eq!(http_stats.get_median_latency(), None);
+        assert_eq!(
+            domain_stats_store.rank_backends(&url, &[http, browser]),
+            vec![browser, http]
+        );
+        let other_url = Url::parse("https://tfl.gov.uk/tube/timetable/bakerloo/").unwrap();
+        assert_eq!(
+            domain_stats_store.rank_backends(&other_url, &[http, browser]),
+            vec![http, browser]
+        );
+        let stats_file = env::temp_dir().join("test_domain_stats.toml");
+        domain_stats_store.save(&stats_file).unwrap();
+        let domain_stats_store = DomainStatsStore::load(&stats_file).unwrap();
+        assert_eq!(
+            domain_stats_store.get_stats("live.nowgoal.com", browser),
+            Some(browser_stats)
+        );
+        fs::remove_file(&stats_file).unwrap();
+        assert!(DomainStatsStore::load(&stats_file)
+            .unwrap()
+            .get_stats("live.nowgoal.com", browser)
+            .is_none());
+    }
+}
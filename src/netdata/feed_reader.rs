@@ -0,0 +1,303 @@
+use chrono::{DateTime, Utc};
+use polars::prelude::*;
+use reqwest::{Proxy, RequestBuilder, Url};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use super::async_web_scraper::AsyncWebScraper;
+use super::data_struct::{ResponseCheckResult, UrlFile};
+use super::response_validator::ResponseValidator;
+use super::url_queue::UrlQueue;
+use crate::error::{Error, Result};
+use crate::file_io::FileIO;
+use crate::logger::ProjectLogger;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedEntry {
+    pub title: String,
+    pub link: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    pub guid: String,
+}
+
+impl FeedEntry {
+    pub const TITLE_COLUMN: &'static str = "title";
+    pub const LINK_COLUMN: &'static str = "link";
+    pub const PUBLISHED_COLUMN: &'static str = "published";
+    pub const GUID_COLUMN: &'static str = "guid";
+}
+
+// Both RSS and Atom are normalized. Entries without a published date fall back to the updated
+// date, and the guid falls back to the id generated by the parser when the feed omits it.
+pub fn parse_feed(feed_str: &str) -> Result<Vec<FeedEntry>> {
+    let feed = feed_rs::parser::parse(feed_str.as_bytes())
+        .map_err(|e| Error::scrape("feed", &format!("Unable to parse the feed. {e}")))?;
+    Ok(feed
+        .entries
+        .into_iter()
+        .map(|entry| FeedEntry {
+            title: entry
+                .title
+                .map(|title| title.content.trim().to_string())
+                .unwrap_or_default(),
+            link: entry.links.first().map(|link| link.href.clone()),
+            published: entry.published.or(entry.updated),
+            guid: entry.id,
+        })
+        .collect())
+}
+
+pub fn entries_to_data_frame(entry_list: &[FeedEntry]) -> PolarsResult<DataFrame> {
+    let title: Vec<&str> = entry_list
+        .iter()
+        .map(|entry| entry.title.as_str())
+        .collect();
+    let link: Vec<Option<&str>> = entry_list
+        .iter()
+        .map(|entry| entry.link.as_deref())
+        .collect();
+    let published: Vec<Option<String>> = entry_list
+        .iter()
+        .map(|entry| entry.published.map(|published| published.to_rfc3339()))
+        .collect();
+    let guid: Vec<&str> = entry_list.iter().map(|entry| entry.guid.as_str()).collect();
+    df!(
+        FeedEntry::TITLE_COLUMN => title,
+        FeedEntry::LINK_COLUMN => link,
+        FeedEntry::PUBLISHED_COLUMN => published,
+        FeedEntry::GUID_COLUMN => guid,
+    )
+}
+
+// The guids seen so far are kept in a json file in the folder so that restarts do not re-emit
+// old entries. Only the latest max_seen guids are kept to bound the file size.
+pub struct FeedReader<'a> {
+    name: String,
+    async_web_scraper: &'a AsyncWebScraper<'a>,
+    file_io: &'a FileIO<'a>,
+    project_logger: &'a ProjectLogger,
+    folder_path: PathBuf,
+    seen_guid_list: Option<Vec<String>>,
+    max_seen: usize,
+}
+
+impl<'a> FeedReader<'a> {
+    const MAX_SEEN: usize = 10000;
+
+    pub fn new(
+        name: &str,
+        async_web_scraper: &'a AsyncWebScraper<'a>,
+        file_io: &'a FileIO<'a>,
+        project_logger: &'a ProjectLogger,
+        folder_path: &Path,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            async_web_scraper,
+            file_io,
+            project_logger,
+            folder_path: folder_path.to_path_buf(),
+            seen_guid_list: None,
+            max_seen: Self::MAX_SEEN,
+        }
+    }
+
+    pub fn set_max_seen(&mut self, max_seen: usize) {
+        self.max_seen = max_seen.max(1);
+    }
+
+    fn seen_guid_file(&self) -> String {
+        format!("{}.seen_guid.json", self.name)
+    }
+
+    fn load_seen_guid_list(&self) -> Vec<String> {
+        let seen_guid_file = self.seen_guid_file();
+        if !FileIO::check_file_exist(&self.folder_path, &seen_guid_file) {
+            return Vec::new();
+        }
+        self.file_io
+            .load_file_as_string(&self.folder_path, &seen_guid_file)
+            .ok()
+            .and_then(|seen_guid_str| {
+                serde_json::from_str(&seen_guid_str)
+                    .map_err(|e| {
+                        let warn_str = format!(
+                            "Unable to parse the seen guids {seen_guid_file} in {}. Start afresh. {e}",
+                            self.folder_path.display()
+                        );
+                        self.project_logger.log_warn(&warn_str);
+                    })
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    fn parse_response(&self, url: &Url, response: ResponseCheckResult) -> Option<Vec<FeedEntry>> {
+        let feed_str = response.get_content()?;
+        match parse_feed(&feed_str) {
+            Ok(entry_list) => {
+                let debug_str = format!("{} entries parsed from feed {url}.", entry_list.len());
+                self.project_logger.log_debug(&debug_str);
+                Some(entry_list)
+            }
+            Err(e) => {
+                let warn_str = format!("Unable to parse the feed {url}. {e}");
+                self.project_logger.log_warn(&warn_str);
+                None
+            }
+        }
+    }
+
+    pub async fn fetch(
+        &self,
+        url: &Url,
+        request_builder_func: fn(Url) -> RequestBuilder,
+        check_func: &dyn ResponseValidator,
+    ) -> Option<Vec<FeedEntry>> {
+        let response = self
+            .async_web_scraper
+            .simple_request(url, request_builder_func, check_func)
+            .await;
+        self.parse_response(url, response)
+    }
+
+    pub async fn fetch_with_proxy(
+        &self,
+        url: &Url,
+        proxy: Proxy,
+        request_builder_func: fn(Proxy, Url) -> RequestBuilder,
+        check_func: &dyn ResponseValidator,
+    ) -> Option<Vec<FeedEntry>> {
+        let response = self
+            .async_web_scraper
+            .request_with_proxy(url, proxy, request_builder_func, check_func)
+            .await;
+        self.parse_response(url, response)
+    }
+
+    // Unseen entries are returned in feed order and marked as seen. Call save_seen_guid to
+    // persist them once the entries have been handled.
+    pub fn filter_new_entries(&mut self, entry_list: Vec<FeedEntry>) -> Vec<FeedEntry> {
+        if self.seen_guid_list.is_none() {
+            self.seen_guid_list = Some(self.load_seen_guid_list());
+        }
+        let seen_guid_list = self.seen_guid_list.get_or_insert_with(Vec::new);
+        let mut seen_guid_set: HashSet<String> = seen_guid_list.iter().cloned().collect();
+        let new_entry_list: Vec<FeedEntry> = entry_list
+            .into_iter()
+            .filter(|entry| seen_guid_set.insert(entry.guid.clone()))
+            .collect();
+        seen_guid_list.extend(new_entry_list.iter().map(|entry| entry.guid.clone()));
+        let num_excess = seen_guid_list.len().saturating_sub(self.max_seen);
+        seen_guid_list.drain(..num_excess);
+        new_entry_list
+    }
+
+    pub fn save_seen_guid(&self) -> Result<()> {
+        let Some(seen_guid_list) = self.seen_guid_list.as_ref() else {
+            return Ok(());
+        };
+        let seen_guid_str = serde_json::to_string(seen_guid_list)?;
+        self.file_io
+            .create_directory_if_not_exists(&self.folder_path)?;
+        self.file_io.write_string_to_file(
+            &self.folder_path,
+            &self.seen_guid_file(),
+            &seen_guid_str,
+        )?;
+        Ok(())
+    }
+
+    pub async fn read_new_entries(
+        &mut self,
+        url: &Url,
+        request_builder_func: fn(Url) -> RequestBuilder,
+        check_func: &dyn ResponseValidator,
+    ) -> Option<DataFrame> {
+        let entry_list = self.fetch(url, request_builder_func, check_func).await?;
+        let new_entry_list = self.filter_new_entries(entry_list);
+        if let Err(e) = self.save_seen_guid() {
+            let warn_str = format!("Unable to save the seen guids of feed {}. {e}", self.name);
+            self.project_logger.log_warn(&warn_str);
+        }
+        entries_to_data_frame(&new_entry_list)
+            .map_err(|e| {
+                let warn_str = format!("Unable to convert the entries of {url} to data frame. {e}");
+                self.project_logger.log_warn(&warn_str);
+            })
+            .ok()
+    }
+
+    pub fn get_url_file_list(
+        entry_list: &[FeedEntry],
+        file_name_func: fn(&FeedEntry) -> String,
+    ) -> Vec<UrlFile> {
+        entry_list
+            .iter()
+            .filter_map(|entry| {
+                entry
+                    .link
+                    .as_deref()
+                    .and_then(|link| Url::parse(link).ok())
+                    .map(|url| UrlFile::new(url, file_name_func(entry)))
+            })
+            .collect()
+    }
+
+    pub fn enqueue_links(
+        &self,
+        url_queue: &UrlQueue,
+        entry_list: &[FeedEntry],
+        file_name_func: fn(&FeedEntry) -> String,
+    ) -> Result<usize> {
+        let url_file_list = Self::get_url_file_list(entry_list, file_name_func);
+        let num_added = url_queue.enqueue(&url_file_list)?;
+        let debug_str = format!(
+            "{num_added} new links from feed {} added to the queue.",
+            self.name
+        );
+        self.project_logger.log_debug(&debug_str);
+        Ok(num_added)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_feed() {
+        let rss_str = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>News</title>
+<item><title> Match preview </title><link>https://example.com/a</link>
+<guid>a-1</guid><pubDate>Mon, 02 Jan 2023 10:00:00 GMT</pubDate></item>
+<item><title>Result</title><link>https://example.com/b</link><guid>b-2</guid></item>
+</channel></rss>"#;
+        let entry_list = parse_feed(rss_str).unwrap();
+        assert_eq!(entry_list.len(), 2);
+        assert_eq!(entry_list[0].title, "Match preview");
+        assert_eq!(entry_list[0].guid, "a-1");
+        assert_eq!(entry_list[0].link.as_deref(), Some("https://example.com/a"));
+        assert!(entry_list[0].published.is_some());
+        assert!(entry_list[1].published.is_none());
+        let atom_str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom"><title>Odds</title><id>urn:feed</id>
+<updated>2023-01-02T10:00:00Z</updated>
+<entry><title>Line move</title><id>urn:entry:1</id><link href="https://example.com/c"/>
+<updated>2023-01-02T10:00:00Z</updated></entry></feed>"#;
+        let entry_list = parse_feed(atom_str).unwrap();
+        assert_eq!(entry_list[0].guid, "urn:entry:1");
+        assert_eq!(
+            entry_list[0].published.unwrap().to_rfc3339(),
+            "2023-01-02T10:00:00+00:00"
+        );
+        let data = entries_to_data_frame(&entry_list).unwrap();
+        assert_eq!(data.shape(), (1, 4));
+        let url_file_list = FeedReader::get_url_file_list(&entry_list, |entry| {
+            format!("{}.html", entry.guid.replace(':', "_"))
+        });
+        assert_eq!(url_file_list[0].file_name, "urn_entry_1.html");
+        assert!(parse_feed("not a feed").is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use super::data_struct::UrlFile;
+use crate::file_io::FileIO;
+
+// Generates the file names of the urls from a template, e.g. {host}/{date}/{slug}.html, instead
+// of formatting them by hand for every scraper. The tokens are
+// - {host}: the host of the url
+// - {path}: the path of the url, with the slashes replaced by underscores
+// - {slug}: the last segment of the path without its extension, or index for the root
+// - {query}: the query string of the url
+// - {hash}: the first 8 hex digits of the sha256 of the url
+// - {index}: the position of the url in the list
+// - {date} and {time}: the date time given, as %Y%m%d and %H%M%S
+// and the custom ones added with set_token. Each part of the name is sanitized. With nested the
+// slashes of the template create sub folders, otherwise they are replaced by underscores.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileNameTemplate {
+    template: String,
+    nested: bool,
+    custom_tokens: HashMap<String, String>,
+}
+
+impl FileNameTemplate {
+    const ROOT_SLUG: &'static str = "index";
+    const HASH_LEN: usize = 8;
+    const DATE_FORMAT: &'static str = "%Y%m%d";
+    const TIME_FORMAT: &'static str = "%H%M%S";
+
+    pub fn new(template: &str) -> Self {
+        Self {
+            template: template.to_string(),
+            nested: false,
+            custom_tokens: HashMap::new(),
+        }
+    }
+
+    pub fn set_nested(mut self, nested: bool) -> Self {
+        self.nested = nested;
+        self
+    }
+
+    pub fn set_token(mut self, name: &str, value: &str) -> Self {
+        self.custom_tokens
+            .insert(name.to_string(), value.to_string());
+        self
+    }
+
+    fn get_token_value(
+        &self,
+        name: &str,
+        url: &Url,
+        index: usize,
+        date_time: &DateTime<Utc>,
+    ) -> Option<String> {
+        let token_value = match name {
+            "host" => url.host_str().unwrap_or_default().to_string(),
+            "path" => match url.path().trim_matches('/') {
+                "" => Self::ROOT_SLUG.to_string(),
+                path => path.to_string(),
+            },
+            "slug" => url
+                .path_segments()
+                .and_then(|segments| segments.filter(|segment| !segment.is_empty()).last())
+                .and_then(|segment| Path::new(segment).file_stem())
+                .and_then(|stem| stem.to_str())
+                .unwrap_or(Self::ROOT_SLUG)
+                .to_string(),
+            "query" => url.query().unwrap_or_default().to_string(),
+            "hash" => Sha256::digest(url.as_str().as_bytes())
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .take(Self::HASH_LEN / 2)
+                .collect(),
+            "index" => index.to_string(),
+            "date" => date_time.format(Self::DATE_FORMAT).to_string(),
+            "time" => date_time.format(Self::TIME_FORMAT).to_string(),
+            _ => self.custom_tokens.get(name)?.clone(),
+        };
+        Some(token_value.replace(['/', '\\'], "_"))
+    }
+
+    // The unknown tokens are kept as they are.
+    pub fn render(&self, url: &Url, index: usize, date_time: &DateTime<Utc>) -> String {
+        let mut rendered = String::new();
+        let mut remaining = self.template.as_str();
+        while let Some(start) = remaining.find('{') {
+            rendered.push_str(&remaining[..start]);
+            let token = &remaining[start..];
+            match token.find('}') {
+                Some(end) => {
+                    match self.get_token_value(&token[1..end], url, index, date_time) {
+                        Some(token_value) => rendered.push_str(&token_value),
+                        None => rendered.push_str(&token[..=end]),
+                    }
+                    remaining = &token[end + 1..];
+                }
+                None => {
+                    rendered.push_str(token);
+                    remaining = "";
+                }
+            }
+        }
+        rendered.push_str(remaining);
+        let separator = if self.nested { "/" } else { "_" };
+        rendered
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .map(FileIO::sanitize_file_name)
+            .collect::<Vec<String>>()
+            .join(separator)
+    }
+
+    // The names repeated in the list get _1, _2 etc. before the extension, so that no file is
+    // overwritten by another url.
+    pub fn get_url_file_list(&self, url_list: &[Url], date_time: &DateTime<Utc>) -> Vec<UrlFile> {
+        let mut file_name_set = HashSet::new();
+        url_list
+            .iter()
+            .enumerate()
+            .map(|(index, url)| {
+                let file_name = self.render(url, index, date_time);
+                let unique_file_name = (0..)
+                    .map(|count| Self::add_count_suffix(&file_name, count))
+                    .find(|file_name| !file_name_set.contains(file_name))
+                    .expect("There is always an unused suffix");
+                file_name_set.insert(unique_file_name.clone());
+                UrlFile::new(url.clone(), unique_file_name)
+            })
+            .collect()
+    }
+
+    fn add_count_suffix(file_name: &str, count: usize) -> String {
+        if count == 0 {
+            return file_name.to_string();
+        }
+        let (folder, name) = match file_name.rsplit_once('/') {
+            Some((folder, name)) => (format!("{folder}/"), name),
+            None => (String::new(), file_name),
+        };
+        match name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => {
+                format!("{folder}{stem}_{count}.{extension}")
+            }
+            _ => format!("{folder}{name}_{count}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_file_name_template() {
+        let date_time = Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap();
+        let url_list = [
+            Url::parse("https://tfl.gov.uk/tube/timetable/bakerloo/").unwrap(),
+            Url::parse("https://tfl.gov.uk/bus/timetable/bakerloo/?direction=inbound").unwrap(),
+            Url::parse("https://live.nowgoal.com/odds/../../etc/passwd").unwrap(),
+        ];
+        let file_name_template =
+            FileNameTemplate::new("{host}/{date}/{slug}.html").set_nested(true);
+        let file_name_list: Vec<String> = file_name_template
+            .get_url_file_list(&url_list, &date_time)
+            .into_iter()
+            .map(|url_file| url_file.file_name)
+            .collect();
+        assert_eq!(
+            file_name_list,
+            vec![
+                "tfl.gov.uk/20240301/bakerloo.html",
+                "tfl.gov.uk/20240301/bakerloo_1.html",
+                "live.nowgoal.com/20240301/passwd.html",
+            ]
+        );
+        let file_name_template = FileNameTemplate::new("{league}/{index}_{path}_{query}.json")
+            .set_token("league", "premier/league");
+        assert_eq!(
+            file_name_template.render(&url_list[1], 1, &date_time),
+            "premier_league_1_bus_timetable_bakerloo_direction=inbound.json"
+        );
+        let file_name_template = FileNameTemplate::new("../{slug}_{unknown}.html");
+        let file_name = file_name_template.render(&url_list[0], 0, &date_time);
+        assert!(file_name.starts_with("__") && file_name.contains("_bakerloo__unknown__"));
+        assert!(!file_name.contains(['/', '{']));
+    }
+}
@@ -0,0 +1,163 @@
+use polars::io::SerReader;
+use polars::prelude::{CsvReadOptions, DataFrame, PolarsResult};
+use reqwest::Url;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::file_io::FileIO;
+
+const GOOGLE_SHEET_URL: &str = "https://docs.google.com/spreadsheets/d/";
+
+// Accepts the full sheet link, the key with the edit suffix, e.g.
+// "14Ep-CmoqWxrMU8HshxthRcdRW8IsXvh3n2-ZHVCzqzQ/edit#gid=1855920257", or the bare sheet id.
+// Without a gid the export returns the first tab.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoogleSheetKey {
+    pub sheet_id: String,
+    pub gid: Option<String>,
+}
+
+impl GoogleSheetKey {
+    pub fn parse(google_sheet_link: &str) -> Option<Self> {
+        let google_sheet_key = google_sheet_link
+            .trim()
+            .trim_start_matches(GOOGLE_SHEET_URL);
+        let sheet_id = google_sheet_key
+            .split(['/', '?', '#'])
+            .next()
+            .filter(|sheet_id| !sheet_id.is_empty())?;
+        let gid = google_sheet_key.split_once("gid=").and_then(|(_, gid)| {
+            let gid: String = gid.chars().take_while(|c| c.is_ascii_digit()).collect();
+            (!gid.is_empty()).then_some(gid)
+        });
+        Some(Self {
+            sheet_id: sheet_id.to_string(),
+            gid,
+        })
+    }
+
+    pub fn with_gid(&self, gid: &str) -> Self {
+        Self {
+            sheet_id: self.sheet_id.clone(),
+            gid: Some(gid.to_string()),
+        }
+    }
+
+    pub fn get_csv_url(&self) -> Option<Url> {
+        let csv_link = match &self.gid {
+            Some(gid) => format!(
+                "{GOOGLE_SHEET_URL}{}/export?format=csv&gid={gid}",
+                self.sheet_id
+            ),
+            None => format!("{GOOGLE_SHEET_URL}{}/export?format=csv", self.sheet_id),
+        };
+        Url::parse(&csv_link).ok()
+    }
+}
+
+pub fn url_from_google_sheet_link(google_sheet_link: &str) -> Option<Url> {
+    GoogleSheetKey::parse(google_sheet_link)?.get_csv_url()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GoogleSheetReadOptions {
+    pub has_header: bool,
+    pub infer_schema_length: Option<usize>,
+    pub try_parse_dates: bool,
+}
+
+impl Default for GoogleSheetReadOptions {
+    fn default() -> Self {
+        Self {
+            has_header: true,
+            infer_schema_length: Some(100),
+            try_parse_dates: false,
+        }
+    }
+}
+
+impl GoogleSheetReadOptions {
+    // Reads every column as string, for sheets whose columns mix numbers and text.
+    pub fn all_string() -> Self {
+        Self {
+            infer_schema_length: Some(0),
+            ..Self::default()
+        }
+    }
+}
+
+pub fn convert_to_data_frame(
+    google_sheet_csv: &str,
+    read_options: &GoogleSheetReadOptions,
+) -> PolarsResult<DataFrame> {
+    let cursor = Cursor::new(google_sheet_csv);
+    CsvReadOptions::default()
+        .with_has_header(read_options.has_header)
+        .with_infer_schema_length(read_options.infer_schema_length)
+        .map_parse_options(|parse_options| {
+            parse_options.with_try_parse_dates(read_options.try_parse_dates)
+        })
+        .into_reader_with_file_handle(cursor)
+        .finish()
+}
+
+pub fn write_to_parquet(
+    file_io: &FileIO,
+    folder_path: &Path,
+    file: &str,
+    google_sheet_csv: &str,
+    read_options: &GoogleSheetReadOptions,
+) -> PolarsResult<DataFrame> {
+    let mut data = convert_to_data_frame(google_sheet_csv, read_options)?;
+    file_io.write_parquet_file(folder_path, file, &mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use polars::prelude::DataType;
+
+    #[test]
+    fn test_google_sheet_key() {
+        let google_sheet_key = GoogleSheetKey::parse(
+            "14Ep-CmoqWxrMU8HshxthRcdRW8IsXvh3n2-ZHVCzqzQ/edit#gid=1855920257",
+        )
+        .unwrap();
+        assert_eq!(
+            google_sheet_key.sheet_id,
+            "14Ep-CmoqWxrMU8HshxthRcdRW8IsXvh3n2-ZHVCzqzQ"
+        );
+        assert_eq!(google_sheet_key.gid.as_deref(), Some("1855920257"));
+        assert_eq!(
+            google_sheet_key.get_csv_url().unwrap().as_str(),
+            "https://docs.google.com/spreadsheets/d/14Ep-CmoqWxrMU8HshxthRcdRW8IsXvh3n2-ZHVCzqzQ/export?format=csv&gid=1855920257"
+        );
+        let full_link = "https://docs.google.com/spreadsheets/d/abc123/edit?usp=sharing";
+        let google_sheet_key = GoogleSheetKey::parse(full_link).unwrap();
+        assert_eq!(google_sheet_key.sheet_id, "abc123");
+        assert!(google_sheet_key.gid.is_none());
+        assert_eq!(
+            google_sheet_key
+                .with_gid("7")
+                .get_csv_url()
+                .unwrap()
+                .query(),
+            Some("format=csv&gid=7")
+        );
+        assert!(GoogleSheetKey::parse("").is_none());
+    }
+
+    #[test]
+    fn test_convert_to_data_frame() {
+        let google_sheet_csv = "team,goals\nArsenal,3\nChelsea,01\n";
+        let data =
+            convert_to_data_frame(google_sheet_csv, &GoogleSheetReadOptions::default()).unwrap();
+        assert_eq!(data.shape(), (2, 2));
+        assert_eq!(data.column("goals").unwrap().dtype(), &DataType::Int64);
+        let data =
+            convert_to_data_frame(google_sheet_csv, &GoogleSheetReadOptions::all_string()).unwrap();
+        assert_eq!(data.column("goals").unwrap().dtype(), &DataType::String);
+    }
+}
@@ -0,0 +1,485 @@
+use chrono::{DateTime, SecondsFormat};
+use reqwest::Url;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use thirtyfour::common::command::FormatRequestData;
+use thirtyfour::error::WebDriverResult;
+use thirtyfour::{RequestData, RequestMethod, SessionId, WebDriver};
+
+// The resource timing buffer holds 250 entries by default and drops the later ones, so it is
+// enlarged before the scripts of every new document run.
+const TIMING_BUFFER_SCRIPT: &str = "performance.setResourceTimingBufferSize(100000);";
+
+// The navigation and resource entries of the resource timing api of the page. The xhr and fetch
+// calls of the page are among the resource entries with their initiator types.
+const TIMING_ENTRIES_SCRIPT: &str = "
+    const entries = performance.getEntriesByType('navigation')
+        .concat(performance.getEntriesByType('resource'));
+    return {
+        timeOrigin: performance.timeOrigin,
+        entries: entries.map(entry => ({
+            name: entry.name,
+            initiatorType: entry.initiatorType,
+            nextHopProtocol: entry.nextHopProtocol,
+            responseStatus: entry.responseStatus || 0,
+            startTime: entry.startTime,
+            duration: entry.duration,
+            domainLookupStart: entry.domainLookupStart,
+            domainLookupEnd: entry.domainLookupEnd,
+            connectStart: entry.connectStart,
+            secureConnectionStart: entry.secureConnectionStart,
+            connectEnd: entry.connectEnd,
+            requestStart: entry.requestStart,
+            responseStart: entry.responseStart,
+            responseEnd: entry.responseEnd,
+            transferSize: entry.transferSize,
+            encodedBodySize: entry.encodedBodySize,
+            decodedBodySize: entry.decodedBodySize,
+            domContentLoadedEventEnd: entry.domContentLoadedEventEnd,
+            loadEventEnd: entry.loadEventEnd,
+        })),
+    };
+";
+
+pub fn session_commands() -> Vec<(&'static str, Value)> {
+    vec![(
+        "Page.addScriptToEvaluateOnNewDocument",
+        json!({ "source": TIMING_BUFFER_SCRIPT }),
+    )]
+}
+
+pub async fn collect_timing_entries(web_driver: &WebDriver) -> WebDriverResult<Value> {
+    Ok(web_driver
+        .execute(TIMING_ENTRIES_SCRIPT, Vec::new())
+        .await?
+        .json()
+        .clone())
+}
+
+// The log is cleared by the driver on each read, so a pooled session only returns the events
+// since the last recorded page.
+#[derive(Debug)]
+struct PerformanceLogCommand;
+
+impl FormatRequestData for PerformanceLogCommand {
+    fn format_request(&self, session_id: &SessionId) -> RequestData {
+        RequestData::new(RequestMethod::Post, format!("/session/{session_id}/se/log"))
+            .add_body(json!({ "type": "performance" }))
+    }
+}
+
+pub async fn collect_performance_log(web_driver: &WebDriver) -> WebDriverResult<Value> {
+    web_driver
+        .handle
+        .cmd(PerformanceLogCommand)
+        .await?
+        .value_json()
+}
+
+// The request and response of the devtools network events of one url.
+#[derive(Debug, Default)]
+struct NetworkExchange {
+    request: Value,
+    response: Value,
+}
+
+// The timing entries and the network events name the same url with and without its fragment.
+fn exchange_key(url: &str) -> String {
+    Url::parse(url)
+        .map(|mut url| {
+            url.set_fragment(None);
+            url.to_string()
+        })
+        .unwrap_or_else(|_| url.to_string())
+}
+
+// The Network.requestWillBeSent and Network.responseReceived messages of the performance log,
+// keyed by url. The last request of a url is kept when it is loaded more than once.
+fn network_exchanges(performance_log: &Value) -> HashMap<String, NetworkExchange> {
+    let mut exchanges: HashMap<String, NetworkExchange> = HashMap::new();
+    let mut request_urls: HashMap<String, String> = HashMap::new();
+    let log_entries = performance_log
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    for log_entry in log_entries {
+        let Some(message) = log_entry
+            .get("message")
+            .and_then(Value::as_str)
+            .and_then(|message| serde_json::from_str::<Value>(message).ok())
+        else {
+            continue;
+        };
+        let params = &message["message"]["params"];
+        let request_id = params["requestId"].as_str().unwrap_or_default();
+        match message["message"]["method"].as_str() {
+            Some("Network.requestWillBeSent") => {
+                let url = exchange_key(params["request"]["url"].as_str().unwrap_or_default());
+                request_urls.insert(request_id.to_string(), url.clone());
+                exchanges.insert(
+                    url,
+                    NetworkExchange {
+                        request: params["request"].clone(),
+                        response: Value::Null,
+                    },
+                );
+            }
+            Some("Network.responseReceived") => {
+                if let Some(exchange) = request_urls
+                    .get(request_id)
+                    .and_then(|url| exchanges.get_mut(url))
+                {
+                    exchange.response = params["response"].clone();
+                }
+            }
+            _ => {}
+        }
+    }
+    exchanges
+}
+
+// The devtools headers are an object whose repeated headers are joined by new lines.
+fn har_headers(headers: &Value) -> Vec<Value> {
+    headers
+        .as_object()
+        .map(|headers| {
+            headers
+                .iter()
+                .flat_map(|(name, value)| {
+                    value
+                        .as_str()
+                        .unwrap_or_default()
+                        .split('\n')
+                        .map(move |value| json!({"name": name, "value": value}))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn header_value<'b>(headers: &'b Value, name: &str) -> Option<&'b str> {
+    headers.as_object().and_then(|headers| {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .and_then(|(_, value)| value.as_str())
+    })
+}
+
+fn get_ms(entry: &Value, key: &str) -> f64 {
+    entry.get(key).and_then(Value::as_f64).unwrap_or_default()
+}
+
+// The phases are zero for the cross origin resources without a Timing-Allow-Origin header, in
+// which case the whole duration is taken as waiting.
+fn har_timings(entry: &Value) -> Value {
+    let phase = |start: &str, end: &str| (get_ms(entry, end) - get_ms(entry, start)).max(0.0);
+    let dns = phase("domainLookupStart", "domainLookupEnd");
+    let connect = phase("connectStart", "connectEnd");
+    let ssl = if get_ms(entry, "secureConnectionStart") > 0.0 {
+        phase("secureConnectionStart", "connectEnd")
+    } else {
+        -1.0
+    };
+    let (wait, receive) = if get_ms(entry, "responseStart") > 0.0 {
+        (
+            phase("requestStart", "responseStart"),
+            phase("responseStart", "responseEnd"),
+        )
+    } else {
+        (get_ms(entry, "duration"), 0.0)
+    };
+    let blocked = (get_ms(entry, "duration") - dns - connect - wait - receive).max(0.0);
+    json!({
+        "blocked": blocked,
+        "dns": dns,
+        "connect": connect,
+        "ssl": ssl,
+        "send": 0.0,
+        "wait": wait,
+        "receive": receive,
+    })
+}
+
+// Without the network events, e.g. on firefox, the method is inferred from the initiator type,
+// as only a beacon posts, and a fetch or xhr which is not a GET is then misreported.
+fn har_entry(
+    entry: &Value,
+    time_origin: f64,
+    exchanges: &HashMap<String, NetworkExchange>,
+) -> Value {
+    let url = entry
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let initiator_type = entry
+        .get("initiatorType")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let exchange = exchanges.get(&exchange_key(url));
+    let (request, response) = exchange.map_or((&Value::Null, &Value::Null), |exchange| {
+        (&exchange.request, &exchange.response)
+    });
+    let method = request["method"].as_str().unwrap_or(match initiator_type {
+        "beacon" => "POST",
+        _ => "GET",
+    });
+    let status = response["status"]
+        .as_u64()
+        .or_else(|| entry.get("responseStatus").and_then(Value::as_u64))
+        .unwrap_or_default();
+    let query_string: Vec<Value> = Url::parse(url)
+        .map(|url| {
+            url.query_pairs()
+                .map(|(name, value)| json!({"name": name, "value": value}))
+                .collect()
+        })
+        .unwrap_or_default();
+    let started_at =
+        DateTime::from_timestamp_millis((time_origin + get_ms(entry, "startTime")) as i64)
+            .unwrap_or_default();
+    let http_version = entry
+        .get("nextHopProtocol")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let mut har_request = json!({
+        "method": method,
+        "url": url,
+        "httpVersion": http_version,
+        "cookies": [],
+        "headers": har_headers(&request["headers"]),
+        "queryString": query_string,
+        "headersSize": -1,
+        "bodySize": -1,
+    });
+    if let Some(post_data) = request["postData"].as_str() {
+        har_request["postData"] = json!({
+            "mimeType": header_value(&request["headers"], "content-type").unwrap_or_default(),
+            "text": post_data,
+        });
+        har_request["bodySize"] = json!(post_data.len());
+    }
+    json!({
+        "pageref": "page_1",
+        "startedDateTime": started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+        "time": get_ms(entry, "duration"),
+        "request": har_request,
+        "response": {
+            "status": status,
+            "statusText": response["statusText"].as_str().unwrap_or_default(),
+            "httpVersion": http_version,
+            "cookies": [],
+            "headers": har_headers(&response["headers"]),
+            "content": {
+                "size": get_ms(entry, "decodedBodySize") as i64,
+                "mimeType": response["mimeType"].as_str().unwrap_or_default(),
+            },
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": get_ms(entry, "encodedBodySize") as i64,
+            "_transferSize": get_ms(entry, "transferSize") as i64,
+        },
+        "cache": {},
+        "timings": har_timings(entry),
+        "_initiatorType": entry.get("initiatorType").cloned().unwrap_or(Value::Null),
+    })
+}
+
+// A HAR 1.2 log of the browsed page built from its timing entries, with the methods, headers,
+// request bodies and statuses taken from the network events of the performance log. The response
+// bodies are not kept. The initiator type, e.g. xhr or fetch, is kept in the custom
+// _initiatorType field to find the api calls which carry the data of the page.
+pub fn build_har(page_url: &Url, timing_entries: &Value, performance_log: &Value) -> Value {
+    let exchanges = network_exchanges(performance_log);
+    let time_origin = get_ms(timing_entries, "timeOrigin");
+    let entries = timing_entries
+        .get("entries")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let navigation = entries
+        .iter()
+        .find(|entry| entry.get("initiatorType").and_then(Value::as_str) == Some("navigation"));
+    let page_timing = |key: &str| navigation.map_or(-1.0, |entry| get_ms(entry, key));
+    let started_at = DateTime::from_timestamp_millis(time_origin as i64).unwrap_or_default();
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "pages": [{
+                "startedDateTime": started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                "id": "page_1",
+                "title": page_url.as_str(),
+                "pageTimings": {
+                    "onContentLoad": page_timing("domContentLoadedEventEnd"),
+                    "onLoad": page_timing("loadEventEnd"),
+                },
+            }],
+            "entries": entries
+                .iter()
+                .map(|entry| har_entry(entry, time_origin, &exchanges))
+                .collect::<Vec<Value>>(),
+        }
+    })
+}
+
+// Holds the HAR of each browsed url until the page source is saved, when it is written next to
+// it with the extension har.
+#[derive(Debug, Default)]
+pub struct HarRecorder {
+    hars: Mutex<HashMap<Url, Value>>,
+}
+
+impl HarRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, url: &Url, har: Value) {
+        self.hars
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(url.clone(), har);
+    }
+
+    pub fn take(&self, url: &Url) -> Option<Value> {
+        self.hars
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(url)
+    }
+
+    pub fn get_har_file(file_name: &str) -> String {
+        Path::new(file_name)
+            .with_extension("har")
+            .display()
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_build_har() {
+        let page_url = Url::parse("https://example.com/odds").unwrap();
+        let timing_entries = json!({
+            "timeOrigin": 1_700_000_000_000.0,
+            "entries": [
+                {
+                    "name": "https://example.com/odds",
+                    "initiatorType": "navigation",
+                    "nextHopProtocol": "h2",
+                    "responseStatus": 200,
+                    "startTime": 0.0,
+                    "duration": 120.0,
+                    "domainLookupStart": 5.0,
+                    "domainLookupEnd": 15.0,
+                    "connectStart": 15.0,
+                    "secureConnectionStart": 20.0,
+                    "connectEnd": 40.0,
+                    "requestStart": 40.0,
+                    "responseStart": 100.0,
+                    "responseEnd": 120.0,
+                    "encodedBodySize": 2048,
+                    "decodedBodySize": 8192,
+                    "domContentLoadedEventEnd": 300.0,
+                    "loadEventEnd": 450.0,
+                },
+                {
+                    "name": "https://api.example.com/matches?league=epl",
+                    "initiatorType": "fetch",
+                    "responseStatus": 0,
+                    "startTime": 350.0,
+                    "duration": 80.0,
+                }
+            ]
+        });
+        let har = build_har(&page_url, &timing_entries, &Value::Null);
+        assert_eq!(har["log"]["version"], "1.2");
+        assert_eq!(har["log"]["pages"][0]["pageTimings"]["onLoad"], 450.0);
+        let entries = har["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["response"]["status"], 200);
+        assert_eq!(entries[0]["timings"]["dns"], 10.0);
+        assert_eq!(entries[0]["timings"]["ssl"], 20.0);
+        assert_eq!(entries[0]["timings"]["wait"], 60.0);
+        assert_eq!(entries[0]["timings"]["blocked"], 5.0);
+        assert_eq!(entries[1]["_initiatorType"], "fetch");
+        assert_eq!(entries[1]["request"]["method"], "GET");
+        assert_eq!(entries[1]["timings"]["wait"], 80.0);
+        assert_eq!(
+            entries[1]["request"]["queryString"][0],
+            json!({"name": "league", "value": "epl"})
+        );
+        assert_eq!(entries[1]["startedDateTime"], "2023-11-14T22:13:20.350Z");
+        let log_message = |method: &str, params: Value| {
+            json!({
+                "level": "INFO",
+                "message": json!({"message": {"method": method, "params": params}}).to_string(),
+            })
+        };
+        let performance_log = json!([
+            log_message(
+                "Network.requestWillBeSent",
+                json!({
+                    "requestId": "7",
+                    "request": {
+                        "url": "https://api.example.com/matches?league=epl",
+                        "method": "POST",
+                        "headers": {"Content-Type": "application/json"},
+                        "postData": "{\"page\":1}",
+                    },
+                })
+            ),
+            log_message(
+                "Network.responseReceived",
+                json!({
+                    "requestId": "7",
+                    "response": {
+                        "status": 201,
+                        "statusText": "Created",
+                        "headers": {"Set-Cookie": "a=1\nb=2"},
+                        "mimeType": "application/json",
+                    },
+                })
+            ),
+        ]);
+        let har = build_har(&page_url, &timing_entries, &performance_log);
+        let entries = har["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries[0]["request"]["method"], "GET");
+        assert_eq!(entries[0]["response"]["status"], 200);
+        assert_eq!(entries[1]["request"]["method"], "POST");
+        assert_eq!(
+            entries[1]["request"]["headers"][0],
+            json!({"name": "Content-Type", "value": "application/json"})
+        );
+        assert_eq!(
+            entries[1]["request"]["postData"],
+            json!({"mimeType": "application/json", "text": "{\"page\":1}"})
+        );
+        assert_eq!(entries[1]["request"]["bodySize"], 10);
+        assert_eq!(entries[1]["response"]["status"], 201);
+        assert_eq!(entries[1]["response"]["statusText"], "Created");
+        assert_eq!(
+            entries[1]["response"]["headers"].as_array().unwrap().len(),
+            2
+        );
+        assert_eq!(
+            entries[1]["response"]["content"]["mimeType"],
+            "application/json"
+        );
+        let har_recorder = HarRecorder::new();
+        har_recorder.insert(&page_url, har);
+        assert!(har_recorder.take(&page_url).is_some());
+        assert!(har_recorder.take(&page_url).is_none());
+        assert_eq!(HarRecorder::get_har_file("odds.html"), "odds.har");
+    }
+}
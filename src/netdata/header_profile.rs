@@ -0,0 +1,113 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use reqwest::Url;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HeaderProfile {
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    user_agents: Vec<String>,
+    #[serde(default)]
+    domain_profiles: HashMap<String, HeaderProfile>,
+    #[serde(skip)]
+    user_agent_index: AtomicUsize,
+}
+
+impl HeaderProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_toml_str(profile_str: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(profile_str)
+    }
+
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        self.headers.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn set_user_agents(&mut self, user_agents: Vec<String>) {
+        self.user_agents = user_agents;
+    }
+
+    pub fn set_domain_profile(&mut self, domain: &str, header_profile: HeaderProfile) {
+        self.domain_profiles
+            .insert(domain.to_string(), header_profile);
+    }
+
+    fn next_user_agent(&self) -> Option<&str> {
+        if self.user_agents.is_empty() {
+            return None;
+        }
+        let index = self.user_agent_index.fetch_add(1, Ordering::Relaxed);
+        Some(&self.user_agents[index % self.user_agents.len()])
+    }
+
+    fn extend_header_map(header_map: &mut HeaderMap, headers: &BTreeMap<String, String>) {
+        for (name, value) in headers.iter() {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                header_map.insert(name, value);
+            }
+        }
+    }
+
+    // Headers of the matching domain profile override the static ones, and the user agent is
+    // rotated from the domain list if it has one.
+    pub fn get_headers_for_request(&self, url: &Url) -> HeaderMap {
+        let mut header_map = HeaderMap::new();
+        Self::extend_header_map(&mut header_map, &self.headers);
+        let domain_profile = url
+            .host_str()
+            .and_then(|domain| self.domain_profiles.get(domain));
+        if let Some(domain_profile) = domain_profile {
+            Self::extend_header_map(&mut header_map, &domain_profile.headers);
+        }
+        let user_agent = domain_profile
+            .and_then(|domain_profile| domain_profile.next_user_agent())
+            .or_else(|| self.next_user_agent());
+        if let Some(user_agent) = user_agent.and_then(|x| HeaderValue::from_str(x).ok()) {
+            header_map.insert(USER_AGENT, user_agent);
+        }
+        header_map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_header_profile() {
+        let profile_str = r#"
+            user_agents = ["agent_a", "agent_b"]
+
+            [headers]
+            Accept-Language = "en-GB"
+
+            [domain_profiles."www.nowgoal.com"]
+            user_agents = ["agent_c"]
+
+            [domain_profiles."www.nowgoal.com".headers]
+            Referer = "https://www.nowgoal.com/"
+        "#;
+        let header_profile = HeaderProfile::from_toml_str(profile_str).unwrap();
+        let url = Url::parse("https://tfl.gov.uk/tube/timetable/bakerloo/").unwrap();
+        let headers = header_profile.get_headers_for_request(&url);
+        assert_eq!(headers["accept-language"], "en-GB");
+        assert_eq!(headers[USER_AGENT], "agent_a");
+        let headers = header_profile.get_headers_for_request(&url);
+        assert_eq!(headers[USER_AGENT], "agent_b");
+        let url = Url::parse("https://www.nowgoal.com/football/live").unwrap();
+        let headers = header_profile.get_headers_for_request(&url);
+        assert_eq!(headers["referer"], "https://www.nowgoal.com/");
+        assert_eq!(headers["accept-language"], "en-GB");
+        assert_eq!(headers[USER_AGENT], "agent_c");
+    }
+}
@@ -0,0 +1,175 @@
+use futures::future::BoxFuture;
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::{RequestBuilder, Response, ResponseBuilderExt, StatusCode, Url};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+
+// Sends the requests of the scraper. The default sends them with the client of the builder, and
+// the tests set a mock one so that they do not hit the real sites.
+pub trait HttpTransport: fmt::Debug + Send + Sync {
+    fn send(&self, request_builder: RequestBuilder) -> BoxFuture<'_, reqwest::Result<Response>>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReqwestTransport;
+
+impl HttpTransport for ReqwestTransport {
+    fn send(&self, request_builder: RequestBuilder) -> BoxFuture<'_, reqwest::Result<Response>> {
+        Box::pin(request_builder.send())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockResponse {
+    pub status: StatusCode,
+    pub content_type: Option<String>,
+    pub location: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn new(status: StatusCode, content_type: &str, body: &[u8]) -> Self {
+        Self {
+            status,
+            content_type: Some(content_type.to_string()),
+            location: None,
+            body: body.to_vec(),
+        }
+    }
+
+    pub fn html(body: &str) -> Self {
+        Self::new(StatusCode::OK, "text/html; charset=utf-8", body.as_bytes())
+    }
+
+    pub fn json(body: &str) -> Self {
+        Self::new(StatusCode::OK, "application/json", body.as_bytes())
+    }
+
+    pub fn status(status: StatusCode) -> Self {
+        Self {
+            status,
+            content_type: None,
+            location: None,
+            body: Vec::new(),
+        }
+    }
+
+    pub fn redirect(location: &str) -> Self {
+        Self {
+            status: StatusCode::FOUND,
+            content_type: None,
+            location: Some(location.to_string()),
+            body: Vec::new(),
+        }
+    }
+
+    fn to_response(&self, url: &Url) -> Response {
+        let mut response_builder = http::Response::builder()
+            .status(self.status)
+            .url(url.clone());
+        if let Some(content_type) = &self.content_type {
+            response_builder = response_builder.header(CONTENT_TYPE, content_type);
+        }
+        if let Some(location) = &self.location {
+            response_builder = response_builder.header(LOCATION, location);
+        }
+        Response::from(
+            response_builder
+                .body(self.body.clone())
+                .expect("Mock response is always valid"),
+        )
+    }
+}
+
+// Serves the responses queued for each url in order, repeating the last one once the others are
+// served, so e.g. a server error followed by a page tests the retries. The urls without response
+// get not found.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    responses: Mutex<HashMap<String, VecDeque<MockResponse>>>,
+    request_counts: Mutex<HashMap<String, usize>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_response(&self, url: &Url, mock_response: MockResponse) {
+        self.responses
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(url.to_string())
+            .or_default()
+            .push_back(mock_response);
+    }
+
+    pub fn get_request_count(&self, url: &Url) -> usize {
+        self.request_counts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(url.as_str())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn next_response(&self, url: &Url) -> MockResponse {
+        *self
+            .request_counts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(url.to_string())
+            .or_default() += 1;
+        let mut responses = self
+            .responses
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match responses.get_mut(url.as_str()) {
+            Some(response_queue) if response_queue.len() > 1 => {
+                response_queue.pop_front().expect("Queue is not empty")
+            }
+            Some(response_queue) => response_queue
+                .front()
+                .cloned()
+                .unwrap_or_else(|| MockResponse::status(StatusCode::NOT_FOUND)),
+            None => MockResponse::status(StatusCode::NOT_FOUND),
+        }
+    }
+}
+
+impl HttpTransport for MockTransport {
+    fn send(&self, request_builder: RequestBuilder) -> BoxFuture<'_, reqwest::Result<Response>> {
+        Box::pin(async move {
+            let request = request_builder.build()?;
+            Ok(self.next_response(request.url()).to_response(request.url()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_mock_transport() {
+        let mock_transport = MockTransport::new();
+        let url = Url::parse("https://tfl.gov.uk/tube/timetable/bakerloo/").unwrap();
+        mock_transport.add_response(&url, MockResponse::status(StatusCode::BAD_GATEWAY));
+        mock_transport.add_response(&url, MockResponse::html("<html>Bakerloo</html>"));
+        let client = Client::new();
+        let response = mock_transport.send(client.get(url.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        for _ in 0..2 {
+            let response = mock_transport.send(client.get(url.clone())).await.unwrap();
+            assert_eq!(response.url(), &url);
+            assert_eq!(response.text().await.unwrap(), "<html>Bakerloo</html>");
+        }
+        assert_eq!(mock_transport.get_request_count(&url), 3);
+        let other_url = url.join("../central/").unwrap();
+        let response = mock_transport.send(client.get(other_url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,130 @@
+use polars::io::SerReader;
+use polars::prelude::{DataFrame, JsonFormat, JsonReader, PolarsError, PolarsResult};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::io::Cursor;
+use std::path::Path;
+
+use super::data_struct::ScrapeFailure;
+
+// Checks the deserialized body of a json api, e.g. that the list of odds is not empty, in place of
+// the string check function of the html pages.
+pub trait JsonValidator<T>: Send + Sync {
+    fn check(&self, data: &T) -> Result<(), ScrapeFailure>;
+}
+
+impl<T, F> JsonValidator<T> for F
+where
+    F: Fn(&T) -> Result<(), ScrapeFailure> + Send + Sync,
+{
+    fn check(&self, data: &T) -> Result<(), ScrapeFailure> {
+        self(data)
+    }
+}
+
+pub fn null_json_validator<T>(_: &T) -> Result<(), ScrapeFailure> {
+    Ok(())
+}
+
+// The raw json is saved as the file of the url file. The records at the json pointer, e.g.
+// "/data/matches", or the whole body for "", are also saved as a parquet file of the same name
+// if given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonSaveOptions {
+    pub save_raw: bool,
+    pub records_pointer: Option<String>,
+}
+
+impl Default for JsonSaveOptions {
+    fn default() -> Self {
+        Self {
+            save_raw: true,
+            records_pointer: None,
+        }
+    }
+}
+
+impl JsonSaveOptions {
+    pub fn with_records(records_pointer: &str) -> Self {
+        Self {
+            records_pointer: Some(records_pointer.to_string()),
+            ..Self::default()
+        }
+    }
+
+    pub fn get_parquet_file(file_name: &str) -> String {
+        Path::new(file_name)
+            .with_extension("parquet")
+            .display()
+            .to_string()
+    }
+}
+
+pub fn parse_json<T: DeserializeOwned>(
+    content: &str,
+    json_validator: &dyn JsonValidator<T>,
+) -> Result<T, ScrapeFailure> {
+    let data: T = serde_json::from_str(content)
+        .map_err(|e| ScrapeFailure::DecodeError(format!("Invalid json. {e}")))?;
+    json_validator.check(&data)?;
+    Ok(data)
+}
+
+// A single object is taken as one record, and an array as one record per element.
+pub fn convert_to_data_frame(json_value: &Value, records_pointer: &str) -> PolarsResult<DataFrame> {
+    let records = json_value.pointer(records_pointer).ok_or_else(|| {
+        PolarsError::NoData(format!("No records at json pointer {records_pointer}.").into())
+    })?;
+    let records_json = match records {
+        Value::Array(_) => records.to_string(),
+        _ => Value::Array(vec![records.clone()]).to_string(),
+    };
+    JsonReader::new(Cursor::new(records_json))
+        .with_json_format(JsonFormat::Json)
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct OddsResponse {
+        matches: Vec<Value>,
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let content =
+            r#"{"matches": [{"home": "Arsenal", "odds": 1.8}, {"home": "Chelsea", "odds": 2.1}]}"#;
+        let non_empty = |odds_response: &OddsResponse| {
+            if odds_response.matches.is_empty() {
+                Err(ScrapeFailure::validation_failed("No matches."))
+            } else {
+                Ok(())
+            }
+        };
+        let odds_response = parse_json::<OddsResponse>(content, &non_empty).unwrap();
+        assert_eq!(odds_response.matches.len(), 2);
+        assert!(matches!(
+            parse_json::<OddsResponse>(r#"{"matches": []}"#, &non_empty),
+            Err(ScrapeFailure::ValidationFailed(_))
+        ));
+        assert!(matches!(
+            parse_json::<OddsResponse>("<html></html>", &null_json_validator::<OddsResponse>),
+            Err(ScrapeFailure::DecodeError(_))
+        ));
+        let json_value = parse_json::<Value>(content, &null_json_validator::<Value>).unwrap();
+        let data = convert_to_data_frame(&json_value, "/matches").unwrap();
+        assert_eq!(data.shape(), (2, 2));
+        let data = convert_to_data_frame(&json_value, "/matches/0").unwrap();
+        assert_eq!(data.shape(), (1, 2));
+        assert!(convert_to_data_frame(&json_value, "/odds").is_err());
+        assert_eq!(
+            JsonSaveOptions::get_parquet_file("odds.json"),
+            "odds.parquet"
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use reqwest::Url;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+pub const TIMETABLE_FIXTURE: &str = include_str!("../../tests/fixtures/tfl_timetable.html");
+pub const ODDS_FIXTURE: &str = include_str!("../../tests/fixtures/nowgoal_odds.json");
+pub const BLOCKED_FIXTURE: &str = include_str!("../../tests/fixtures/blocked_captcha.html");
+
+// A local http server for the scraper tests, serving the fixtures in place of the real sites.
+pub struct ScraperMockServer {
+    mock_server: MockServer,
+}
+
+impl ScraperMockServer {
+    pub async fn start() -> Self {
+        Self {
+            mock_server: MockServer::start().await,
+        }
+    }
+
+    pub fn get_url(&self, url_path: &str) -> Url {
+        Url::parse(&self.mock_server.uri())
+            .and_then(|url| url.join(url_path))
+            .expect("Mock server uri is always valid")
+    }
+
+    pub async fn mount_body(&self, url_path: &str, content_type: &str, body: &str) {
+        Mock::given(method("GET"))
+            .and(path(url_path))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, content_type))
+            .mount(&self.mock_server)
+            .await;
+    }
+
+    pub async fn mount_html(&self, url_path: &str, body: &str) {
+        self.mount_body(url_path, "text/html; charset=utf-8", body)
+            .await;
+    }
+
+    pub async fn mount_json(&self, url_path: &str, body: &str) {
+        self.mount_body(url_path, "application/json", body).await;
+    }
+
+    // Served before the bodies mounted on the same path, for the first num_times requests only
+    // if given, e.g. to fail the first attempts.
+    pub async fn mount_status(&self, url_path: &str, status: u16, num_times: Option<u64>) {
+        let mock = Mock::given(method("GET"))
+            .and(path(url_path))
+            .respond_with(ResponseTemplate::new(status))
+            .with_priority(1);
+        match num_times {
+            Some(num_times) => mock.up_to_n_times(num_times),
+            None => mock,
+        }
+        .mount(&self.mock_server)
+        .await;
+    }
+
+    pub async fn get_request_count(&self, url_path: &str) -> usize {
+        self.mock_server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter(|request| request.url.path() == url_path)
+            .count()
+    }
+}
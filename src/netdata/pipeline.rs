@@ -0,0 +1,428 @@
+use polars::prelude::*;
+use reqwest::{RequestBuilder, Url};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::async_web_scraper::AsyncWebScraper;
+use super::data_struct::{RequestSetting, ScrapeStatus, UrlFile};
+use super::response_validator::ResponseValidator;
+use super::url_file_manifest::UrlFileManifest;
+use crate::aws_s3::AWSFileIO;
+use crate::file_io::FileIO;
+use crate::logger::ProjectLogger;
+use crate::slack_messenger::SlackMessenger;
+
+pub type ParseFunc = dyn Fn(&UrlFile, &str) -> PolarsResult<DataFrame> + Send + Sync;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineStage {
+    Fetch,
+    Load,
+    Parse,
+    Store,
+}
+
+impl PipelineStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fetch => "fetch",
+            Self::Load => "load",
+            Self::Parse => "parse",
+            Self::Store => "store",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageMetrics {
+    pub stage: PipelineStage,
+    pub num_input: usize,
+    pub num_success: usize,
+    pub duration: Duration,
+}
+
+impl StageMetrics {
+    pub fn get_num_failed(&self) -> usize {
+        self.num_input - self.num_success
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PipelineReport {
+    pub outcome: Option<DataFrame>,
+    pub data: Option<DataFrame>,
+    pub stage_metrics: Vec<StageMetrics>,
+}
+
+impl PipelineReport {
+    pub fn is_success(&self) -> bool {
+        self.stage_metrics
+            .iter()
+            .all(|stage_metrics| stage_metrics.get_num_failed() == 0)
+    }
+
+    pub fn digest_message(&self, pipeline_name: &str) -> String {
+        let stage_lines: Vec<String> = self
+            .stage_metrics
+            .iter()
+            .map(|stage_metrics| {
+                format!(
+                    "{}: {} out of {} succeeded in {:.1}s",
+                    stage_metrics.stage.as_str(),
+                    stage_metrics.num_success,
+                    stage_metrics.num_input,
+                    stage_metrics.duration.as_secs_f64()
+                )
+            })
+            .collect();
+        format!("Pipeline {pipeline_name}\n{}", stage_lines.join("\n"))
+    }
+}
+
+pub struct Pipeline<'a> {
+    name: String,
+    async_web_scraper: &'a AsyncWebScraper<'a>,
+    project_logger: &'a ProjectLogger,
+    slack_messenger: &'a SlackMessenger<'a>,
+    file_io: &'a FileIO<'a>,
+    aws_file_io: &'a AWSFileIO<'a>,
+    aws_bucket: &'a str,
+    parse_func: Option<&'a ParseFunc>,
+    output_path: Option<(PathBuf, String)>,
+    num_retry: u32,
+    retry_sleep: Duration,
+}
+
+impl<'a> Pipeline<'a> {
+    const NUM_RETRY: u32 = 3;
+    const RETRY_SLEEP: Duration = Duration::from_secs(5);
+
+    pub fn new(
+        name: &str,
+        async_web_scraper: &'a AsyncWebScraper<'a>,
+        project_logger: &'a ProjectLogger,
+        slack_messenger: &'a SlackMessenger,
+        file_io: &'a FileIO,
+        aws_file_io: &'a AWSFileIO,
+        aws_bucket: &'a str,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            async_web_scraper,
+            project_logger,
+            slack_messenger,
+            file_io,
+            aws_file_io,
+            aws_bucket,
+            parse_func: None,
+            output_path: None,
+            num_retry: Self::NUM_RETRY,
+            retry_sleep: Self::RETRY_SLEEP,
+        }
+    }
+
+    pub fn set_parse_func(&mut self, parse_func: &'a ParseFunc) {
+        self.parse_func = Some(parse_func);
+    }
+
+    pub fn set_output_path(&mut self, folder_path: &Path, file: &str) {
+        self.output_path = Some((folder_path.to_path_buf(), file.to_string()));
+    }
+
+    pub fn set_num_retry(&mut self, num_retry: u32) {
+        self.num_retry = num_retry;
+    }
+
+    pub fn set_retry_sleep(&mut self, retry_sleep: Duration) {
+        self.retry_sleep = retry_sleep;
+    }
+
+    async fn retry_stage<T, F, Fut>(
+        &self,
+        stage: PipelineStage,
+        target: &str,
+        stage_func: F,
+    ) -> Option<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, String>>,
+    {
+        let mut counter = 0;
+        while counter < self.num_retry {
+            match stage_func().await {
+                Ok(result) => return Some(result),
+                Err(e) => {
+                    counter += 1;
+                    let warn_str = format!(
+                        "Unable to {} {target} in pipeline {}. Attempt {counter} of {}. {e}",
+                        stage.as_str(),
+                        self.name,
+                        self.num_retry
+                    );
+                    self.project_logger.log_warn(&warn_str);
+                    if counter < self.num_retry {
+                        tokio::time::sleep(self.retry_sleep).await;
+                    }
+                }
+            }
+        }
+        let error_str = format!(
+            "Unable to {} {target} in pipeline {} after {} attempts.",
+            stage.as_str(),
+            self.name,
+            self.num_retry
+        );
+        self.project_logger.log_error(&error_str);
+        None
+    }
+
+    async fn load_raw_content(
+        &self,
+        folder_path: &Path,
+        file: &str,
+        in_s3: bool,
+    ) -> Result<String, String> {
+        if in_s3 {
+            self.aws_file_io
+                .load_file_as_string(self.aws_bucket, folder_path, file)
+                .await
+                .map_err(|e| format!("{e:?}"))
+        } else {
+            self.file_io
+                .load_file_as_string(folder_path, file)
+                .map_err(|e| e.to_string())
+        }
+    }
+
+    async fn write_output(&self, data: &DataFrame, in_s3: bool) -> Result<(), String> {
+        let (folder_path, file) = match &self.output_path {
+            Some(output_path) => output_path,
+            None => return Ok(()),
+        };
+        let mut data = data.clone();
+        if in_s3 {
+            self.aws_file_io
+                .write_parquet_file(self.aws_bucket, folder_path, file, &mut data)
+                .await
+                .map_err(|e| format!("{e:?}"))
+        } else {
+            self.file_io
+                .write_parquet_file(folder_path, file, &mut data)
+                .map_err(|e| e.to_string())
+        }
+    }
+
+    fn success_url_file_list(
+        url_file_list: &[UrlFile],
+        outcome: &DataFrame,
+    ) -> PolarsResult<Vec<UrlFile>> {
+        let status_column = outcome.column(AsyncWebScraper::STATUS_COLUMN)?.str()?;
+        Ok(url_file_list
+            .iter()
+            .zip(status_column)
+            .filter(|(_, status)| *status == Some(ScrapeStatus::Success.as_str()))
+            .map(|(url_file, _)| url_file.clone())
+            .collect())
+    }
+
+    // Each url is loaded and parsed on its own, so one bad page only drops its own rows.
+    pub async fn run(
+        &self,
+        url_file_list: &[UrlFile],
+        request_builder_func: fn(Url) -> RequestBuilder,
+        raw_folder_path: &Path,
+        check_func: &dyn ResponseValidator,
+        request_setting: &RequestSetting<'a>,
+    ) -> PipelineReport {
+        let mut pipeline_report = PipelineReport::default();
+        let start_time = Instant::now();
+        let outcome = match UrlFileManifest::url_file_list_to_data_frame(url_file_list) {
+            Ok(manifest) => {
+                self.async_web_scraper
+                    .multiple_requests_data_frame(
+                        &manifest,
+                        request_builder_func,
+                        raw_folder_path,
+                        check_func,
+                        request_setting,
+                    )
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        let success_list = match outcome
+            .as_ref()
+            .map_err(|e| e.to_string())
+            .and_then(|outcome| {
+                Self::success_url_file_list(url_file_list, outcome).map_err(|e| e.to_string())
+            }) {
+            Ok(success_list) => success_list,
+            Err(e) => {
+                let error_str = format!("Unable to fetch the urls in pipeline {}. {e}", self.name);
+                self.project_logger.log_error(&error_str);
+                Vec::new()
+            }
+        };
+        pipeline_report.stage_metrics.push(StageMetrics {
+            stage: PipelineStage::Fetch,
+            num_input: url_file_list.len(),
+            num_success: success_list.len(),
+            duration: start_time.elapsed(),
+        });
+        pipeline_report.outcome = outcome.ok();
+        let parse_func = match self.parse_func {
+            Some(parse_func) => parse_func,
+            None => {
+                self.send_pipeline_digest(&pipeline_report, request_setting);
+                return pipeline_report;
+            }
+        };
+
+        let start_time = Instant::now();
+        let mut content_list = Vec::with_capacity(success_list.len());
+        for url_file in success_list.iter() {
+            if let Some(content) = self
+                .retry_stage(PipelineStage::Load, &url_file.file_name, || {
+                    self.load_raw_content(
+                        raw_folder_path,
+                        &url_file.file_name,
+                        request_setting.in_s3,
+                    )
+                })
+                .await
+            {
+                content_list.push((url_file, content));
+            }
+        }
+        pipeline_report.stage_metrics.push(StageMetrics {
+            stage: PipelineStage::Load,
+            num_input: success_list.len(),
+            num_success: content_list.len(),
+            duration: start_time.elapsed(),
+        });
+
+        let start_time = Instant::now();
+        let mut parsed_list = Vec::with_capacity(content_list.len());
+        for (url_file, content) in content_list.iter() {
+            if let Some(data) = self
+                .retry_stage(PipelineStage::Parse, url_file.url.as_str(), || async move {
+                    parse_func(url_file, content).map_err(|e| e.to_string())
+                })
+                .await
+            {
+                parsed_list.push(data);
+            }
+        }
+        pipeline_report.stage_metrics.push(StageMetrics {
+            stage: PipelineStage::Parse,
+            num_input: content_list.len(),
+            num_success: parsed_list.len(),
+            duration: start_time.elapsed(),
+        });
+
+        let start_time = Instant::now();
+        let data =
+            parsed_list
+                .into_iter()
+                .reduce(|mut data, other| match data.vstack_mut(&other) {
+                    Ok(_) => data,
+                    Err(e) => {
+                        let warn_str = format!(
+                            "Unable to stack the parsed data in pipeline {}. {e}",
+                            self.name
+                        );
+                        self.project_logger.log_warn(&warn_str);
+                        data
+                    }
+                });
+        if let Some(data) = data {
+            let output_name = self
+                .output_path
+                .as_ref()
+                .map_or(String::new(), |(_, file)| file.clone());
+            let stored = self
+                .retry_stage(PipelineStage::Store, &output_name, || {
+                    self.write_output(&data, request_setting.in_s3)
+                })
+                .await;
+            pipeline_report.stage_metrics.push(StageMetrics {
+                stage: PipelineStage::Store,
+                num_input: 1,
+                num_success: usize::from(stored.is_some()),
+                duration: start_time.elapsed(),
+            });
+            pipeline_report.data = Some(data);
+        }
+        self.send_pipeline_digest(&pipeline_report, request_setting);
+        pipeline_report
+    }
+
+    fn send_pipeline_digest(
+        &self,
+        pipeline_report: &PipelineReport,
+        request_setting: &RequestSetting<'a>,
+    ) {
+        let digest_message = pipeline_report.digest_message(&self.name);
+        if pipeline_report.is_success() {
+            self.project_logger.log_info(&digest_message);
+        } else {
+            self.project_logger.log_error(&digest_message);
+            self.slack_messenger.retry_send_message(
+                request_setting.calling_func,
+                &digest_message,
+                request_setting.log_only,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_pipeline_report() {
+        let pipeline_report = PipelineReport {
+            outcome: None,
+            data: None,
+            stage_metrics: vec![
+                StageMetrics {
+                    stage: PipelineStage::Fetch,
+                    num_input: 3,
+                    num_success: 3,
+                    duration: Duration::from_secs(2),
+                },
+                StageMetrics {
+                    stage: PipelineStage::Parse,
+                    num_input: 3,
+                    num_success: 2,
+                    duration: Duration::from_millis(100),
+                },
+            ],
+        };
+        assert!(!pipeline_report.is_success());
+        assert_eq!(pipeline_report.stage_metrics[1].get_num_failed(), 1);
+        assert_eq!(
+            pipeline_report.digest_message("tfl"),
+            "Pipeline tfl\nfetch: 3 out of 3 succeeded in 2.0s\nparse: 2 out of 3 succeeded in 0.1s"
+        );
+    }
+
+    #[test]
+    fn test_success_url_file_list() {
+        let url_file_list: Vec<UrlFile> = ["bakerloo", "central"]
+            .iter()
+            .map(|line| {
+                UrlFile::new(
+                    Url::parse(&format!("https://tfl.gov.uk/tube/timetable/{line}/")).unwrap(),
+                    format!("tfl_{line}.html"),
+                )
+            })
+            .collect();
+        let outcome = df!(AsyncWebScraper::STATUS_COLUMN => ["success", "blocked"]).unwrap();
+        let success_list = Pipeline::success_url_file_list(&url_file_list, &outcome).unwrap();
+        assert_eq!(success_list, vec![url_file_list[0].clone()]);
+    }
+}
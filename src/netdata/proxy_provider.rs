@@ -0,0 +1,347 @@
+use futures::future::BoxFuture;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use super::proxy_endpoint::{ProxyEndpoint, ProxyScheme};
+use crate::config_value::ConfigDuration;
+
+const PROXY_COOLDOWN: Duration = Duration::from_secs(600);
+
+// Proxies reported as failed are left out of the samples until the cooldown passes.
+#[derive(Debug)]
+pub struct ProxyCooldown {
+    cooldown: Duration,
+    failed_at: Mutex<HashMap<String, Instant>>,
+}
+
+impl ProxyCooldown {
+    pub fn new(cooldown: Duration) -> Self {
+        Self {
+            cooldown,
+            failed_at: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn report_failure(&self, proxy_endpoint: &ProxyEndpoint) {
+        self.failed_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(proxy_endpoint.get_address(), Instant::now());
+    }
+
+    pub fn is_cooling_down(&self, proxy_endpoint: &ProxyEndpoint) -> bool {
+        let mut failed_at = self
+            .failed_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        failed_at.retain(|_, failed_at| failed_at.elapsed() < self.cooldown);
+        failed_at.contains_key(&proxy_endpoint.get_address())
+    }
+}
+
+impl Default for ProxyCooldown {
+    fn default() -> Self {
+        Self::new(PROXY_COOLDOWN)
+    }
+}
+
+// A source of proxies, e.g. a vendor, so that the scrapers can switch or mix the vendors by
+// config. Set one on the AsyncWebScraper to replace the free proxy list of ScraperProxy.
+pub trait ProxyProvider: fmt::Debug + Send + Sync {
+    fn get_name(&self) -> &str;
+
+    fn fetch_proxy_list(&self) -> BoxFuture<'_, Vec<ProxyEndpoint>>;
+
+    fn get_cooldown(&self) -> &ProxyCooldown;
+
+    fn report_failure(&self, proxy_endpoint: &ProxyEndpoint) {
+        self.get_cooldown().report_failure(proxy_endpoint);
+    }
+
+    fn is_cooling_down(&self, proxy_endpoint: &ProxyEndpoint) -> bool {
+        self.get_cooldown().is_cooling_down(proxy_endpoint)
+    }
+
+    // Samples with repetition when there are fewer proxies than requested. The cooling down
+    // proxies are only used when there is no other one.
+    fn sample_proxy(&self, proxy_list: &[ProxyEndpoint], num_proxy: usize) -> Vec<ProxyEndpoint> {
+        let mut available_list: Vec<&ProxyEndpoint> = proxy_list
+            .iter()
+            .filter(|proxy_endpoint| !self.is_cooling_down(proxy_endpoint))
+            .collect();
+        if available_list.is_empty() {
+            available_list = proxy_list.iter().collect();
+        }
+        available_list.shuffle(&mut thread_rng());
+        available_list
+            .into_iter()
+            .cycle()
+            .take(num_proxy)
+            .cloned()
+            .collect()
+    }
+}
+
+async fn fetch_endpoint_list(
+    client: &Client,
+    source_url: &str,
+    default_scheme: ProxyScheme,
+) -> Vec<ProxyEndpoint> {
+    match client.get(source_url).send().await {
+        Ok(response) => match response.text().await {
+            Ok(endpoint_list) => ProxyEndpoint::parse_list(&endpoint_list, default_scheme),
+            Err(_) => Vec::new(),
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+// Scrapes the public lists of free proxies, one endpoint per line. The sources failing to load
+// are skipped.
+#[derive(Debug)]
+pub struct FreeListProxyProvider {
+    source_url_list: Vec<String>,
+    default_scheme: ProxyScheme,
+    client: Client,
+    cooldown: ProxyCooldown,
+}
+
+impl FreeListProxyProvider {
+    pub fn new(source_url_list: &[String], default_scheme: ProxyScheme) -> Self {
+        Self {
+            source_url_list: source_url_list.to_vec(),
+            default_scheme,
+            client: Client::new(),
+            cooldown: ProxyCooldown::default(),
+        }
+    }
+
+    pub fn set_cooldown(&mut self, cooldown: Duration) {
+        self.cooldown = ProxyCooldown::new(cooldown);
+    }
+}
+
+impl ProxyProvider for FreeListProxyProvider {
+    fn get_name(&self) -> &str {
+        "free_list"
+    }
+
+    fn fetch_proxy_list(&self) -> BoxFuture<'_, Vec<ProxyEndpoint>> {
+        Box::pin(async move {
+            let mut proxy_list = Vec::new();
+            for source_url in self.source_url_list.iter() {
+                proxy_list.extend(
+                    fetch_endpoint_list(&self.client, source_url, self.default_scheme).await,
+                );
+            }
+            let mut address_set = HashSet::new();
+            proxy_list.retain(|proxy_endpoint| address_set.insert(proxy_endpoint.get_address()));
+            proxy_list
+        })
+    }
+
+    fn get_cooldown(&self) -> &ProxyCooldown {
+        &self.cooldown
+    }
+}
+
+// Our private provider gives its proxy list, as host:port:username:password lines, from an api
+// url carrying the api key.
+#[derive(Debug)]
+pub struct PrivateProxyProvider {
+    api_url: String,
+    default_scheme: ProxyScheme,
+    client: Client,
+    cooldown: ProxyCooldown,
+}
+
+impl PrivateProxyProvider {
+    pub fn new(api_url: &str, default_scheme: ProxyScheme) -> Self {
+        Self {
+            api_url: api_url.to_string(),
+            default_scheme,
+            client: Client::new(),
+            cooldown: ProxyCooldown::default(),
+        }
+    }
+
+    pub fn set_cooldown(&mut self, cooldown: Duration) {
+        self.cooldown = ProxyCooldown::new(cooldown);
+    }
+}
+
+impl ProxyProvider for PrivateProxyProvider {
+    fn get_name(&self) -> &str {
+        "private"
+    }
+
+    fn fetch_proxy_list(&self) -> BoxFuture<'_, Vec<ProxyEndpoint>> {
+        Box::pin(fetch_endpoint_list(
+            &self.client,
+            &self.api_url,
+            self.default_scheme,
+        ))
+    }
+
+    fn get_cooldown(&self) -> &ProxyCooldown {
+        &self.cooldown
+    }
+}
+
+// A fixed list of proxies, e.g. bought in bulk, kept in a file with one endpoint per line. The
+// file is read at each fetch, so it can be updated without restarting.
+#[derive(Debug)]
+pub struct StaticFileProxyProvider {
+    proxy_file: PathBuf,
+    default_scheme: ProxyScheme,
+    cooldown: ProxyCooldown,
+}
+
+impl StaticFileProxyProvider {
+    pub fn new(proxy_file: &Path, default_scheme: ProxyScheme) -> Self {
+        Self {
+            proxy_file: proxy_file.to_path_buf(),
+            default_scheme,
+            cooldown: ProxyCooldown::default(),
+        }
+    }
+
+    pub fn set_cooldown(&mut self, cooldown: Duration) {
+        self.cooldown = ProxyCooldown::new(cooldown);
+    }
+}
+
+impl ProxyProvider for StaticFileProxyProvider {
+    fn get_name(&self) -> &str {
+        "static_file"
+    }
+
+    fn fetch_proxy_list(&self) -> BoxFuture<'_, Vec<ProxyEndpoint>> {
+        Box::pin(async move {
+            fs::read_to_string(&self.proxy_file)
+                .map(|endpoint_list| ProxyEndpoint::parse_list(&endpoint_list, self.default_scheme))
+                .unwrap_or_default()
+        })
+    }
+
+    fn get_cooldown(&self) -> &ProxyCooldown {
+        &self.cooldown
+    }
+}
+
+// The provider in the scraper config, e.g.
+// [scraper.proxy_provider]
+// kind = "static_file"
+// proxy_file = "/data/proxy/proxy_list.txt"
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProxyProviderConfig {
+    FreeList {
+        source_url_list: Vec<String>,
+        #[serde(default)]
+        default_scheme: ProxyScheme,
+        cooldown: Option<ConfigDuration>,
+    },
+    Private {
+        api_url: String,
+        #[serde(default)]
+        default_scheme: ProxyScheme,
+        cooldown: Option<ConfigDuration>,
+    },
+    StaticFile {
+        proxy_file: PathBuf,
+        #[serde(default)]
+        default_scheme: ProxyScheme,
+        cooldown: Option<ConfigDuration>,
+    },
+}
+
+impl ProxyProviderConfig {
+    pub fn build(&self) -> Box<dyn ProxyProvider> {
+        match self {
+            Self::FreeList {
+                source_url_list,
+                default_scheme,
+                cooldown,
+            } => {
+                let mut proxy_provider =
+                    FreeListProxyProvider::new(source_url_list, *default_scheme);
+                if let Some(cooldown) = cooldown {
+                    proxy_provider.set_cooldown(cooldown.get_duration());
+                }
+                Box::new(proxy_provider)
+            }
+            Self::Private {
+                api_url,
+                default_scheme,
+                cooldown,
+            } => {
+                let mut proxy_provider = PrivateProxyProvider::new(api_url, *default_scheme);
+                if let Some(cooldown) = cooldown {
+                    proxy_provider.set_cooldown(cooldown.get_duration());
+                }
+                Box::new(proxy_provider)
+            }
+            Self::StaticFile {
+                proxy_file,
+                default_scheme,
+                cooldown,
+            } => {
+                let mut proxy_provider = StaticFileProxyProvider::new(proxy_file, *default_scheme);
+                if let Some(cooldown) = cooldown {
+                    proxy_provider.set_cooldown(cooldown.get_duration());
+                }
+                Box::new(proxy_provider)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::env;
+
+    #[tokio::test]
+    async fn test_static_file_proxy_provider() {
+        let proxy_file = env::temp_dir().join("test_static_file_proxy_provider.txt");
+        fs::write(
+            &proxy_file,
+            "10.0.0.1:8080\n10.0.0.2:8080:user:pass\nsocks5://10.0.0.3:1080\n",
+        )
+        .unwrap();
+        let proxy_provider_config: ProxyProviderConfig = toml::from_str(&format!(
+            r#"
+            kind = "static_file"
+            proxy_file = "{}"
+            cooldown = "1h"
+            "#,
+            proxy_file.display()
+        ))
+        .unwrap();
+        let proxy_provider = proxy_provider_config.build();
+        assert_eq!(proxy_provider.get_name(), "static_file");
+        let proxy_list = proxy_provider.fetch_proxy_list().await;
+        assert_eq!(proxy_list.len(), 3);
+        proxy_provider.report_failure(&proxy_list[0]);
+        proxy_provider.report_failure(&proxy_list[1]);
+        assert!(proxy_provider.is_cooling_down(&proxy_list[0]));
+        let sampled_list = proxy_provider.sample_proxy(&proxy_list, 4);
+        assert_eq!(sampled_list.len(), 4);
+        assert!(sampled_list
+            .iter()
+            .all(|proxy_endpoint| *proxy_endpoint == proxy_list[2]));
+        proxy_provider.report_failure(&proxy_list[2]);
+        assert_eq!(proxy_provider.sample_proxy(&proxy_list, 3).len(), 3);
+        fs::remove_file(&proxy_file).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use super::content_type::ContentType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheMode {
+    Record,
+    Replay,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub status: u16,
+    pub mime_type: Option<String>,
+}
+
+impl CachedResponse {
+    pub fn get_content_type(&self) -> ContentType {
+        ContentType {
+            mime_type: self.mime_type.clone(),
+        }
+    }
+}
+
+// For developing the parsers and check functions offline. In record mode the responses loaded
+// are kept in the cache folder keyed by the hash of the url, the text as decoded and the binary
+// verbatim, and in replay mode the requests are served from there without hitting the network.
+#[derive(Debug)]
+pub struct ResponseCache {
+    cache_folder: PathBuf,
+    cache_mode: CacheMode,
+}
+
+impl ResponseCache {
+    const META_SUFFIX: &'static str = ".toml";
+    const BODY_SUFFIX: &'static str = ".body";
+
+    pub fn new(cache_folder: &Path, cache_mode: CacheMode) -> Self {
+        Self {
+            cache_folder: cache_folder.to_path_buf(),
+            cache_mode,
+        }
+    }
+
+    pub fn get_cache_mode(&self) -> CacheMode {
+        self.cache_mode
+    }
+
+    pub fn cache_key(url: &Url) -> String {
+        Sha256::digest(url.as_str().as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    fn get_cache_file(&self, url: &Url, suffix: &str) -> PathBuf {
+        self.cache_folder
+            .join(format!("{}{suffix}", Self::cache_key(url)))
+    }
+
+    pub fn record(
+        &self,
+        url: &Url,
+        status: StatusCode,
+        content_type: &ContentType,
+        body: &[u8],
+    ) -> Result<()> {
+        let cached_response = CachedResponse {
+            url: url.to_string(),
+            status: status.as_u16(),
+            mime_type: content_type.mime_type.clone(),
+        };
+        let meta_str =
+            toml::to_string(&cached_response).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        fs::create_dir_all(&self.cache_folder)?;
+        fs::write(self.get_cache_file(url, Self::BODY_SUFFIX), body)?;
+        fs::write(self.get_cache_file(url, Self::META_SUFFIX), meta_str)
+    }
+
+    // None if the url has not been recorded.
+    pub fn replay(&self, url: &Url) -> Option<(CachedResponse, Vec<u8>)> {
+        let meta_str = fs::read_to_string(self.get_cache_file(url, Self::META_SUFFIX)).ok()?;
+        let cached_response = toml::from_str(&meta_str).ok()?;
+        let body = fs::read(self.get_cache_file(url, Self::BODY_SUFFIX)).ok()?;
+        Some((cached_response, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_record_and_replay() {
+        let cache_folder = env::temp_dir().join("test_response_cache");
+        let response_cache = ResponseCache::new(&cache_folder, CacheMode::Record);
+        let url = Url::parse("https://tfl.gov.uk/tube/timetable/bakerloo/").unwrap();
+        let content_type = ContentType::parse("text/html; charset=utf-8");
+        response_cache
+            .record(&url, StatusCode::OK, &content_type, b"<html></html>")
+            .unwrap();
+        let response_cache = ResponseCache::new(&cache_folder, CacheMode::Replay);
+        let (cached_response, body) = response_cache.replay(&url).unwrap();
+        assert_eq!(cached_response.url, url.as_str());
+        assert_eq!(cached_response.get_content_type(), content_type);
+        assert_eq!(body, b"<html></html>");
+        assert!(response_cache
+            .replay(&url.join("central/").unwrap())
+            .is_none());
+        fs::remove_dir_all(&cache_folder).unwrap();
+    }
+}
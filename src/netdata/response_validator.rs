@@ -0,0 +1,185 @@
+use super::data_struct::{ResponseCheckResult, ScrapeFailure};
+
+pub trait ResponseValidator: Send + Sync {
+    fn check(&self, response: &str) -> ResponseCheckResult;
+}
+
+impl<F> ResponseValidator for F
+where
+    F: Fn(&str) -> ResponseCheckResult + Send + Sync,
+{
+    fn check(&self, response: &str) -> ResponseCheckResult {
+        self(response)
+    }
+}
+
+pub fn min_length(length: usize) -> impl ResponseValidator {
+    move |response: &str| {
+        if response.len() >= length {
+            ResponseCheckResult::Ok(response.to_string())
+        } else {
+            ResponseCheckResult::ErrContinue(ScrapeFailure::ValidationFailed(format!(
+                "Response length {} is shorter than {length}.",
+                response.len()
+            )))
+        }
+    }
+}
+
+pub fn must_contain(pattern: &str) -> impl ResponseValidator {
+    let pattern = pattern.to_string();
+    move |response: &str| {
+        if response.contains(&pattern) {
+            ResponseCheckResult::Ok(response.to_string())
+        } else {
+            ResponseCheckResult::ErrContinue(ScrapeFailure::ValidationFailed(format!(
+                "Response does not contain {pattern}."
+            )))
+        }
+    }
+}
+
+// Patterns such as captcha pages usually persist across retries, so the url is terminated.
+pub fn must_not_contain(pattern: &str) -> impl ResponseValidator {
+    let pattern = pattern.to_string();
+    move |response: &str| {
+        if response.contains(&pattern) {
+            ResponseCheckResult::ErrTerminate(ScrapeFailure::ValidationFailed(format!(
+                "Response contains {pattern}."
+            )))
+        } else {
+            ResponseCheckResult::Ok(response.to_string())
+        }
+    }
+}
+
+const BLOCKING_MARKERS: [(&str, &str); 9] = [
+    ("cf-browser-verification", "Cloudflare"),
+    ("cf-challenge", "Cloudflare"),
+    ("challenges.cloudflare.com", "Cloudflare"),
+    ("Attention Required! | Cloudflare", "Cloudflare"),
+    ("You don't have permission to access", "Akamai"),
+    ("errors.edgesuite.net", "Akamai"),
+    ("g-recaptcha", "reCAPTCHA"),
+    ("h-captcha", "hCaptcha"),
+    ("px-captcha", "PerimeterX"),
+];
+
+pub fn find_blocking_marker(response: &str) -> Option<&'static str> {
+    BLOCKING_MARKERS
+        .iter()
+        .find(|(marker, _)| response.contains(marker))
+        .map(|(_, provider)| *provider)
+}
+
+pub fn detect_blocking() -> impl ResponseValidator {
+    |response: &str| match find_blocking_marker(response) {
+        Some(provider) => ResponseCheckResult::Blocked(ScrapeFailure::Blocked(format!(
+            "Anti-bot page of {provider} detected."
+        ))),
+        None => ResponseCheckResult::Ok(response.to_string()),
+    }
+}
+
+pub fn json_parse() -> impl ResponseValidator {
+    |response: &str| match serde_json::from_str::<serde_json::Value>(response) {
+        Ok(_) => ResponseCheckResult::Ok(response.to_string()),
+        Err(e) => ResponseCheckResult::ErrContinue(ScrapeFailure::DecodeError(e.to_string())),
+    }
+}
+
+pub fn all_of(validators: Vec<Box<dyn ResponseValidator>>) -> impl ResponseValidator {
+    move |response: &str| {
+        for validator in validators.iter() {
+            match validator.check(response) {
+                ResponseCheckResult::Ok(_) => {}
+                err => return err,
+            }
+        }
+        ResponseCheckResult::Ok(response.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_built_in_validators() {
+        let html = "<html><body><table>result</table></body></html>";
+        assert!(matches!(
+            min_length(10).check(html),
+            ResponseCheckResult::Ok(_)
+        ));
+        assert!(matches!(
+            min_length(1000).check(html),
+            ResponseCheckResult::ErrContinue(_)
+        ));
+        assert!(matches!(
+            must_contain("<table>").check(html),
+            ResponseCheckResult::Ok(_)
+        ));
+        assert!(matches!(
+            must_not_contain("captcha").check("Please solve the captcha"),
+            ResponseCheckResult::ErrTerminate(_)
+        ));
+        assert!(matches!(
+            json_parse().check("{\"data\": [1, 2]}"),
+            ResponseCheckResult::Ok(_)
+        ));
+        assert!(matches!(
+            json_parse().check(html),
+            ResponseCheckResult::ErrContinue(ScrapeFailure::DecodeError(_))
+        ));
+    }
+
+    #[test]
+    fn test_detect_blocking() {
+        let cloudflare_page =
+            "<html><title>Just a moment...</title><div id=\"cf-challenge\"></div></html>";
+        assert_eq!(find_blocking_marker(cloudflare_page), Some("Cloudflare"));
+        assert!(matches!(
+            detect_blocking().check(cloudflare_page),
+            ResponseCheckResult::Blocked(_)
+        ));
+        assert!(matches!(
+            detect_blocking().check("<div class=\"g-recaptcha\"></div>"),
+            ResponseCheckResult::Blocked(ScrapeFailure::Blocked(_))
+        ));
+        assert!(matches!(
+            detect_blocking().check("<table>result</table>"),
+            ResponseCheckResult::Ok(_)
+        ));
+    }
+
+    #[test]
+    fn test_combined_validator() {
+        let min_rows = 2;
+        let row_check = move |response: &str| {
+            if response.matches("<tr>").count() >= min_rows {
+                ResponseCheckResult::Ok(response.to_string())
+            } else {
+                ResponseCheckResult::ErrContinue(ScrapeFailure::validation_failed(
+                    "Not enough rows.",
+                ))
+            }
+        };
+        let validator = all_of(vec![
+            Box::new(must_not_contain("captcha")),
+            Box::new(row_check),
+        ]);
+        assert!(matches!(
+            validator.check("<tr></tr><tr></tr>"),
+            ResponseCheckResult::Ok(_)
+        ));
+        assert!(matches!(
+            validator.check("<tr></tr>"),
+            ResponseCheckResult::ErrContinue(_)
+        ));
+        assert!(matches!(
+            validator.check("<tr></tr><tr></tr>captcha"),
+            ResponseCheckResult::ErrTerminate(_)
+        ));
+    }
+}
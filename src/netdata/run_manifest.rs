@@ -0,0 +1,216 @@
+use chrono::{DateTime, Utc};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+
+use super::data_struct::{ScrapeStatus, UrlFile};
+use crate::run_context::RunContext;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub url: String,
+    pub file_name: String,
+    pub output_path: Option<String>,
+    pub bytes: Option<u64>,
+    pub content_hash: Option<String>,
+    pub status: String,
+    pub attempts: u32,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub meta: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunManifestFile {
+    pub run_context: Option<RunContext>,
+    pub created_at: DateTime<Utc>,
+    pub entries: Vec<ManifestEntry>,
+}
+
+// Collects one entry per url scraped in a run, so that downstream jobs can pick up the files of
+// a run from its manifest instead of guessing by modification time. Set it on the scraper before
+// the multiple requests and save it with AsyncWebScraper::save_run_manifest afterwards.
+#[derive(Debug, Default)]
+pub struct RunManifest {
+    run_context: Option<RunContext>,
+    entries: Mutex<Vec<ManifestEntry>>,
+}
+
+impl RunManifest {
+    pub const URL_COLUMN: &'static str = "url";
+    pub const FILE_NAME_COLUMN: &'static str = "file_name";
+    pub const OUTPUT_PATH_COLUMN: &'static str = "output_path";
+    pub const BYTES_COLUMN: &'static str = "bytes";
+    pub const CONTENT_HASH_COLUMN: &'static str = "content_hash";
+    pub const STATUS_COLUMN: &'static str = "status";
+    pub const ATTEMPTS_COLUMN: &'static str = "attempts";
+    pub const STARTED_AT_COLUMN: &'static str = "started_at";
+    pub const FINISHED_AT_COLUMN: &'static str = "finished_at";
+    pub const CATEGORY_COLUMN: &'static str = "category";
+    pub const META_COLUMN: &'static str = "meta";
+    pub const PARQUET_EXTENSION: &'static str = "parquet";
+    pub const JSON_EXTENSION: &'static str = "json";
+
+    pub fn new() -> Self {
+        Self {
+            run_context: RunContext::current(),
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    // A url retried by a later call, e.g. by the retry loops of the browse requests, updates its
+    // entry instead of adding another one, adding up the attempts.
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &self,
+        url_file: &UrlFile,
+        status: ScrapeStatus,
+        attempts: u32,
+        started_at: DateTime<Utc>,
+        output_path: Option<String>,
+        bytes: Option<u64>,
+        content_hash: Option<String>,
+    ) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = ManifestEntry {
+            url: url_file.url.to_string(),
+            file_name: url_file.file_name.clone(),
+            output_path,
+            bytes,
+            content_hash,
+            status: status.as_str().to_string(),
+            attempts,
+            started_at,
+            finished_at: Utc::now(),
+            category: url_file.category.clone(),
+            meta: url_file.meta.clone().into_iter().collect(),
+        };
+        match entries
+            .iter_mut()
+            .find(|x| x.url == entry.url && x.file_name == entry.file_name)
+        {
+            Some(existing) => {
+                let started_at = existing.started_at.min(entry.started_at);
+                let attempts = existing.attempts + entry.attempts;
+                *existing = ManifestEntry {
+                    started_at,
+                    attempts,
+                    ..entry
+                };
+            }
+            None => entries.push(entry),
+        }
+    }
+
+    pub fn get_entries(&self) -> Vec<ManifestEntry> {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    // The meta of each entry is written as a json object string.
+    pub fn to_data_frame(&self) -> PolarsResult<DataFrame> {
+        let entries = self.get_entries();
+        let meta_list: Vec<String> = entries
+            .iter()
+            .map(|x| serde_json::to_string(&x.meta).unwrap_or_default())
+            .collect();
+        let mut data = df!(
+            Self::URL_COLUMN => entries.iter().map(|x| x.url.as_str()).collect::<Vec<&str>>(),
+            Self::FILE_NAME_COLUMN => entries.iter().map(|x| x.file_name.as_str()).collect::<Vec<&str>>(),
+            Self::OUTPUT_PATH_COLUMN => entries.iter().map(|x| x.output_path.as_deref()).collect::<Vec<Option<&str>>>(),
+            Self::BYTES_COLUMN => entries.iter().map(|x| x.bytes).collect::<Vec<Option<u64>>>(),
+            Self::CONTENT_HASH_COLUMN => entries.iter().map(|x| x.content_hash.as_deref()).collect::<Vec<Option<&str>>>(),
+            Self::STATUS_COLUMN => entries.iter().map(|x| x.status.as_str()).collect::<Vec<&str>>(),
+            Self::ATTEMPTS_COLUMN => entries.iter().map(|x| x.attempts).collect::<Vec<u32>>(),
+            Self::STARTED_AT_COLUMN => entries.iter().map(|x| x.started_at.naive_utc()).collect::<Vec<_>>(),
+            Self::FINISHED_AT_COLUMN => entries.iter().map(|x| x.finished_at.naive_utc()).collect::<Vec<_>>(),
+            Self::CATEGORY_COLUMN => entries.iter().map(|x| x.category.as_deref()).collect::<Vec<Option<&str>>>(),
+            Self::META_COLUMN => meta_list
+        )?;
+        if let Some(run_context) = &self.run_context {
+            run_context.stamp_data_frame(&mut data)?;
+        }
+        Ok(data)
+    }
+
+    pub fn to_json_string(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&RunManifestFile {
+            run_context: self.run_context.clone(),
+            created_at: Utc::now(),
+            entries: self.get_entries(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use reqwest::Url;
+
+    #[test]
+    fn test_run_manifest() {
+        let run_manifest = RunManifest::new();
+        let url_file = UrlFile::new(
+            Url::parse("https://tfl.gov.uk/tube/timetable/bakerloo/").unwrap(),
+            "bakerloo.html".to_string(),
+        )
+        .with_category("timetable")
+        .with_meta("line", "bakerloo");
+        let started_at = Utc::now();
+        run_manifest.record(
+            &url_file,
+            ScrapeStatus::Failed,
+            1,
+            started_at,
+            None,
+            None,
+            None,
+        );
+        run_manifest.record(
+            &url_file,
+            ScrapeStatus::Success,
+            2,
+            Utc::now(),
+            Some("timetable/bakerloo.html".to_string()),
+            Some(1000),
+            Some("abc".to_string()),
+        );
+        let entries = run_manifest.get_entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].attempts, 3);
+        assert_eq!(entries[0].started_at, started_at);
+        assert_eq!(entries[0].status, "success");
+        assert_eq!(entries[0].category.as_deref(), Some("timetable"));
+        assert_eq!(entries[0].meta["line"], "bakerloo");
+        let data = run_manifest.to_data_frame().unwrap();
+        assert_eq!(data.height(), 1);
+        let manifest_file: RunManifestFile =
+            serde_json::from_str(&run_manifest.to_json_string().unwrap()).unwrap();
+        assert_eq!(manifest_file.entries, entries);
+    }
+}
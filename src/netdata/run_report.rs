@@ -0,0 +1,255 @@
+use polars::prelude::*;
+use reqwest::Url;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[cfg(feature = "scraper")]
+use super::async_web_scraper::AsyncWebScraper;
+use super::data_struct::{ScrapeOutcome, ScrapeStatus};
+#[cfg(feature = "scraper")]
+use super::url_file_manifest::UrlFileManifest;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrlReport {
+    pub url: String,
+    pub outcome: ScrapeOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainSummary {
+    pub domain: String,
+    pub num_urls: usize,
+    pub success_rate: f64,
+    pub block_rate: f64,
+    pub median_latency: Option<Duration>,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunReport {
+    url_reports: Vec<UrlReport>,
+}
+
+impl RunReport {
+    pub const UNKNOWN_DOMAIN: &'static str = "unknown";
+    pub const DOMAIN_COLUMN: &'static str = "domain";
+    pub const NUM_URLS_COLUMN: &'static str = "num_urls";
+    pub const SUCCESS_RATE_COLUMN: &'static str = "success_rate";
+    pub const BLOCK_RATE_COLUMN: &'static str = "block_rate";
+    pub const MEDIAN_LATENCY_COLUMN: &'static str = "median_latency_ms";
+    pub const TOTAL_BYTES_COLUMN: &'static str = "total_bytes";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_outcome(&mut self, url: &str, outcome: ScrapeOutcome) {
+        self.url_reports.push(UrlReport {
+            url: url.to_string(),
+            outcome,
+        });
+    }
+
+    pub fn get_url_reports(&self) -> &[UrlReport] {
+        &self.url_reports
+    }
+
+    #[cfg(feature = "scraper")]
+    pub fn from_data_frame(data: &DataFrame) -> PolarsResult<Self> {
+        let url_column = data.column(UrlFileManifest::URL_COLUMN)?.str()?;
+        let status_column = data.column(AsyncWebScraper::STATUS_COLUMN)?.str()?;
+        let attempts_column = data.column(AsyncWebScraper::ATTEMPTS_COLUMN)?.u32()?;
+        let latency_column = data.column(AsyncWebScraper::LATENCY_COLUMN)?.f64()?;
+        let bytes_column = data.column(AsyncWebScraper::BYTES_COLUMN)?.u64()?;
+        let mut run_report = Self::new();
+        for row in 0..data.height() {
+            let status = status_column
+                .get(row)
+                .and_then(ScrapeStatus::from_str_opt)
+                .unwrap_or(ScrapeStatus::Failed);
+            let outcome = ScrapeOutcome {
+                status,
+                attempts: attempts_column.get(row).unwrap_or_default(),
+                latency: latency_column
+                    .get(row)
+                    .map(|latency| Duration::from_secs_f64(latency / 1000.0)),
+                bytes: bytes_column.get(row),
+            };
+            run_report.add_outcome(url_column.get(row).unwrap_or_default(), outcome);
+        }
+        Ok(run_report)
+    }
+
+    fn domain_of(url: &str) -> String {
+        Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_string()))
+            .unwrap_or_else(|| Self::UNKNOWN_DOMAIN.to_string())
+    }
+
+    fn median(mut values: Vec<Duration>) -> Option<Duration> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort();
+        let mid = values.len() / 2;
+        if values.len() % 2 == 0 {
+            Some((values[mid - 1] + values[mid]) / 2)
+        } else {
+            Some(values[mid])
+        }
+    }
+
+    // Domains are sorted by success rate so the degraded sites come first.
+    pub fn domain_summary(&self) -> Vec<DomainSummary> {
+        let mut domain_reports: BTreeMap<String, Vec<&ScrapeOutcome>> = BTreeMap::new();
+        for url_report in self.url_reports.iter() {
+            domain_reports
+                .entry(Self::domain_of(&url_report.url))
+                .or_default()
+                .push(&url_report.outcome);
+        }
+        let mut domain_summary: Vec<DomainSummary> = domain_reports
+            .into_iter()
+            .map(|(domain, outcome_list)| {
+                let num_urls = outcome_list.len();
+                let num_success = outcome_list
+                    .iter()
+                    .filter(|outcome| outcome.is_success())
+                    .count();
+                let num_blocked = outcome_list
+                    .iter()
+                    .filter(|outcome| outcome.status == ScrapeStatus::Blocked)
+                    .count();
+                let latency_list = outcome_list
+                    .iter()
+                    .filter_map(|outcome| outcome.latency)
+                    .collect();
+                DomainSummary {
+                    domain,
+                    num_urls,
+                    success_rate: num_success as f64 / num_urls as f64,
+                    block_rate: num_blocked as f64 / num_urls as f64,
+                    median_latency: Self::median(latency_list),
+                    total_bytes: outcome_list
+                        .iter()
+                        .filter_map(|outcome| outcome.bytes)
+                        .sum(),
+                }
+            })
+            .collect();
+        domain_summary.sort_by(|a, b| a.success_rate.total_cmp(&b.success_rate));
+        domain_summary
+    }
+
+    pub fn domain_summary_data_frame(&self) -> PolarsResult<DataFrame> {
+        let domain_summary = self.domain_summary();
+        let domain: Vec<&str> = domain_summary.iter().map(|x| x.domain.as_str()).collect();
+        let num_urls: Vec<u64> = domain_summary.iter().map(|x| x.num_urls as u64).collect();
+        let success_rate: Vec<f64> = domain_summary.iter().map(|x| x.success_rate).collect();
+        let block_rate: Vec<f64> = domain_summary.iter().map(|x| x.block_rate).collect();
+        let median_latency: Vec<Option<f64>> = domain_summary
+            .iter()
+            .map(|x| {
+                x.median_latency
+                    .map(|latency| latency.as_secs_f64() * 1000.0)
+            })
+            .collect();
+        let total_bytes: Vec<u64> = domain_summary.iter().map(|x| x.total_bytes).collect();
+        df!(
+            Self::DOMAIN_COLUMN => domain,
+            Self::NUM_URLS_COLUMN => num_urls,
+            Self::SUCCESS_RATE_COLUMN => success_rate,
+            Self::BLOCK_RATE_COLUMN => block_rate,
+            Self::MEDIAN_LATENCY_COLUMN => median_latency,
+            Self::TOTAL_BYTES_COLUMN => total_bytes
+        )
+    }
+
+    pub fn digest_message(&self) -> String {
+        let domain_table = self
+            .domain_summary()
+            .iter()
+            .map(|x| {
+                format!(
+                    "{:<30} {:>6} {:>8.1}% {:>8.1}% {:>10} {:>12}",
+                    x.domain,
+                    x.num_urls,
+                    x.success_rate * 100.0,
+                    x.block_rate * 100.0,
+                    x.median_latency.map_or("-".to_string(), |latency| format!(
+                        "{}ms",
+                        latency.as_millis()
+                    )),
+                    x.total_bytes
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        format!(
+            "Run summary of {} urls by domain:\n```\n{:<30} {:>6} {:>9} {:>9} {:>10} {:>12}\n{domain_table}\n```",
+            self.url_reports.len(),
+            "domain",
+            "urls",
+            "success",
+            "blocked",
+            "latency",
+            "bytes"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn outcome(status: ScrapeStatus, latency_ms: u64, bytes: u64) -> ScrapeOutcome {
+        ScrapeOutcome {
+            status,
+            attempts: 1,
+            latency: Some(Duration::from_millis(latency_ms)),
+            bytes: Some(bytes),
+        }
+    }
+
+    #[test]
+    fn test_domain_summary() {
+        let mut run_report = RunReport::new();
+        run_report.add_outcome(
+            "https://tfl.gov.uk/tube/timetable/bakerloo/",
+            outcome(ScrapeStatus::Success, 100, 1000),
+        );
+        run_report.add_outcome(
+            "https://tfl.gov.uk/tube/timetable/central/",
+            outcome(ScrapeStatus::Success, 300, 2000),
+        );
+        run_report.add_outcome(
+            "https://www.nowgoal.com/football/live",
+            outcome(ScrapeStatus::Blocked, 50, 0),
+        );
+        run_report.add_outcome(
+            "https://www.nowgoal.com/football/results",
+            outcome(ScrapeStatus::Success, 70, 500),
+        );
+        run_report.add_outcome(
+            "not a url",
+            ScrapeOutcome::skipped(ScrapeStatus::InvalidUrl),
+        );
+        let domain_summary = run_report.domain_summary();
+        assert_eq!(domain_summary.len(), 3);
+        assert_eq!(domain_summary[0].domain, RunReport::UNKNOWN_DOMAIN);
+        assert_eq!(domain_summary[1].domain, "www.nowgoal.com");
+        assert_eq!(domain_summary[1].block_rate, 0.5);
+        assert_eq!(domain_summary[2].domain, "tfl.gov.uk");
+        assert_eq!(domain_summary[2].success_rate, 1.0);
+        assert_eq!(
+            domain_summary[2].median_latency,
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(domain_summary[2].total_bytes, 3000);
+        let data = run_report.domain_summary_data_frame().unwrap();
+        assert_eq!(data.height(), 3);
+        assert!(run_report.digest_message().contains("tfl.gov.uk"));
+    }
+}
@@ -0,0 +1,389 @@
+use chrono::Utc;
+use reqwest::header::{ACCEPT, CACHE_CONTROL};
+use reqwest::{Client, Url};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::async_web_scraper::AsyncWebScraper;
+use super::data_struct::ResponseCheckResult;
+use super::proxy_endpoint::ProxyEndpoint;
+use super::response_validator::ResponseValidator;
+use crate::function_name;
+use crate::logger::ProjectLogger;
+use crate::shutdown::ShutdownSignal;
+use crate::slack_messenger::SlackMessenger;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    pub id: Option<String>,
+    pub event: String,
+    pub data: String,
+    pub retry: Option<u64>,
+}
+
+impl SseEvent {
+    const DEFAULT_EVENT: &'static str = "message";
+}
+
+// Incremental parser of a text/event-stream body. Chunks may split lines and even UTF-8
+// characters, so bytes are kept until a full line arrives.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: SseEvent,
+    data_lines: Vec<String>,
+    has_field: bool,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        if !self.has_field {
+            return None;
+        }
+        let mut event = std::mem::take(&mut self.event);
+        event.data = std::mem::take(&mut self.data_lines).join("\n");
+        if event.event.is_empty() {
+            event.event = SseEvent::DEFAULT_EVENT.to_string();
+        }
+        self.has_field = false;
+        (!event.data.is_empty() || event.retry.is_some()).then_some(event)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        self.has_field = true;
+        match field {
+            "event" => self.event.event = value.to_string(),
+            "data" => self.data_lines.push(value.to_string()),
+            "id" if !value.contains('\0') => self.event.id = Some(value.to_string()),
+            "retry" => self.event.retry = value.parse().ok(),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut event_list = Vec::new();
+        while let Some(line_end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line_bytes: Vec<u8> = self.buffer.drain(..=line_end).collect();
+            let line = String::from_utf8_lossy(&line_bytes);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if let Some(event) = self.parse_line(line) {
+                event_list.push(event);
+            }
+        }
+        event_list
+    }
+}
+
+pub fn events_to_ndjson(event_list: &[SseEvent]) -> String {
+    event_list
+        .iter()
+        .map(|event| {
+            json!({
+                "id": event.id,
+                "event": event.event,
+                "data": event.data,
+            })
+            .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub struct SseConsumer<'a> {
+    name: String,
+    url: Url,
+    async_web_scraper: &'a AsyncWebScraper<'a>,
+    project_logger: &'a ProjectLogger,
+    slack_messenger: &'a SlackMessenger<'a>,
+    client: Client,
+    folder_path: PathBuf,
+    in_s3: bool,
+    last_event_id: Option<String>,
+    batch_size: usize,
+    batch_interval: Duration,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    shutdown_signal: Option<ShutdownSignal>,
+    log_only: bool,
+}
+
+impl<'a> SseConsumer<'a> {
+    const BATCH_SIZE: usize = 1000;
+    const BATCH_INTERVAL: Duration = Duration::from_secs(60);
+    const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new(
+        name: &str,
+        url: Url,
+        async_web_scraper: &'a AsyncWebScraper<'a>,
+        project_logger: &'a ProjectLogger,
+        slack_messenger: &'a SlackMessenger<'a>,
+        folder_path: &Path,
+        in_s3: bool,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            url,
+            async_web_scraper,
+            project_logger,
+            slack_messenger,
+            client: Client::new(),
+            folder_path: folder_path.to_path_buf(),
+            in_s3,
+            last_event_id: None,
+            batch_size: Self::BATCH_SIZE,
+            batch_interval: Self::BATCH_INTERVAL,
+            initial_backoff: Self::INITIAL_BACKOFF,
+            max_backoff: Self::MAX_BACKOFF,
+            shutdown_signal: None,
+            log_only: false,
+        }
+    }
+
+    pub fn set_client(&mut self, client: Client) {
+        self.client = client;
+    }
+
+    pub fn set_proxy(&mut self, proxy: &ProxyEndpoint) {
+        match proxy
+            .get_reqwest_proxy()
+            .and_then(|proxy| Client::builder().proxy(proxy).build())
+        {
+            Ok(client) => self.client = client,
+            Err(e) => {
+                let error_str = format!("Unable to set the proxy {}. {e}", proxy.get_address());
+                self.project_logger.log_error(&error_str);
+                panic!("{error_str}");
+            }
+        }
+    }
+
+    // Resume a stream from where a previous run stopped.
+    pub fn set_last_event_id(&mut self, last_event_id: &str) {
+        self.last_event_id = Some(last_event_id.to_string());
+    }
+
+    pub fn get_last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    pub fn set_batch(&mut self, batch_size: usize, batch_interval: Duration) {
+        self.batch_size = batch_size.max(1);
+        self.batch_interval = batch_interval;
+    }
+
+    pub fn set_backoff(&mut self, initial_backoff: Duration, max_backoff: Duration) {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+    }
+
+    pub fn set_shutdown_signal(&mut self, shutdown_signal: ShutdownSignal) {
+        self.shutdown_signal = Some(shutdown_signal);
+    }
+
+    pub fn set_log_only(&mut self, log_only: bool) {
+        self.log_only = log_only;
+    }
+
+    fn is_shutdown_requested(&self) -> bool {
+        self.shutdown_signal
+            .as_ref()
+            .is_some_and(|shutdown_signal| shutdown_signal.is_requested())
+    }
+
+    async fn save_events(&self, event_list: &mut Vec<SseEvent>) {
+        if event_list.is_empty() {
+            return;
+        }
+        let file = format!(
+            "{}_{}.ndjson",
+            self.name,
+            Utc::now().format("%Y%m%d_%H%M%S_%6f")
+        );
+        let save_result = self
+            .async_web_scraper
+            .save_request_content(
+                &self.folder_path,
+                &file,
+                &events_to_ndjson(event_list),
+                self.in_s3,
+            )
+            .await;
+        // The events are kept for the next save if this one fails.
+        if save_result.is_ok() {
+            let debug_str = format!("{} events saved to {file}.", event_list.len());
+            self.project_logger.log_debug(&debug_str);
+            event_list.clear();
+        }
+    }
+
+    // Returns the reconnection delay requested by the server, or None to stop consuming.
+    async fn consume_stream(
+        &mut self,
+        check_func: &dyn ResponseValidator,
+        event_list: &mut Vec<SseEvent>,
+    ) -> Result<Option<Duration>, String> {
+        let mut request_builder = self
+            .client
+            .get(self.url.clone())
+            .header(ACCEPT, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache");
+        if let Some(last_event_id) = self.last_event_id.as_ref() {
+            request_builder = request_builder.header("Last-Event-ID", last_event_id);
+        }
+        let mut response = request_builder
+            .send()
+            .await
+            .map_err(|e| format!("Unable to connect to {}. {e}", self.url))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Stream {} returned status {}.",
+                self.url,
+                response.status()
+            ));
+        }
+        let debug_str = format!("Stream {} connected.", self.url);
+        self.project_logger.log_debug(&debug_str);
+        let mut sse_parser = SseParser::new();
+        let mut server_retry = None;
+        let mut batch_start_time = Instant::now();
+        loop {
+            if self.is_shutdown_requested() {
+                return Ok(None);
+            }
+            let chunk = match tokio::time::timeout(Self::POLL_INTERVAL, response.chunk()).await {
+                Err(_) => None,
+                Ok(Ok(Some(chunk))) => Some(chunk),
+                Ok(Ok(None)) => return Ok(Some(server_retry.unwrap_or(self.initial_backoff))),
+                Ok(Err(e)) => return Err(format!("Stream {} interrupted. {e}", self.url)),
+            };
+            for event in chunk
+                .map(|chunk| sse_parser.feed(&chunk))
+                .unwrap_or_default()
+            {
+                if let Some(retry) = event.retry {
+                    server_retry = Some(Duration::from_millis(retry));
+                }
+                if event.id.is_some() {
+                    self.last_event_id = event.id.clone();
+                }
+                if event.data.is_empty() {
+                    continue;
+                }
+                match check_func.check(&event.data) {
+                    ResponseCheckResult::Ok(data) => event_list.push(SseEvent { data, ..event }),
+                    ResponseCheckResult::ErrContinue(e) => {
+                        let warn_str = format!("Skip event {:?} from {}. {e}", event.id, self.url);
+                        self.project_logger.log_warn(&warn_str);
+                    }
+                    ResponseCheckResult::ErrTerminate(e)
+                    | ResponseCheckResult::Blocked(e)
+                    | ResponseCheckResult::TooLarge(e) => {
+                        let error_str = format!("Stop consuming {}. {e}", self.url);
+                        self.project_logger.log_error(&error_str);
+                        return Ok(None);
+                    }
+                }
+            }
+            if event_list.len() >= self.batch_size
+                || batch_start_time.elapsed() >= self.batch_interval
+            {
+                self.save_events(event_list).await;
+                batch_start_time = Instant::now();
+            }
+        }
+    }
+
+    pub async fn run(&mut self, check_func: &dyn ResponseValidator) {
+        let function_name = function_name!(true);
+        let mut event_list = Vec::new();
+        let mut backoff = self.initial_backoff;
+        let mut is_alerted = false;
+        loop {
+            let reconnect_delay = match self.consume_stream(check_func, &mut event_list).await {
+                Ok(Some(server_delay)) => {
+                    backoff = self.initial_backoff;
+                    is_alerted = false;
+                    server_delay
+                }
+                Ok(None) => break,
+                Err(warn_str) => {
+                    self.project_logger.log_warn(&warn_str);
+                    let delay = backoff;
+                    backoff = (backoff * 2).min(self.max_backoff);
+                    // Alert once the backoff is maxed out, i.e. the stream keeps failing.
+                    if delay >= self.max_backoff && !is_alerted {
+                        self.slack_messenger.retry_send_message(
+                            function_name,
+                            &warn_str,
+                            self.log_only,
+                        );
+                        is_alerted = true;
+                    }
+                    delay
+                }
+            };
+            self.save_events(&mut event_list).await;
+            if self.is_shutdown_requested() {
+                break;
+            }
+            tokio::time::sleep(reconnect_delay).await;
+        }
+        self.save_events(&mut event_list).await;
+        let info_str = format!(
+            "Stream consumer {} stopped at event {:?}.",
+            self.name, self.last_event_id
+        );
+        self.project_logger.log_info(&info_str);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_sse_parser() {
+        let mut sse_parser = SseParser::new();
+        let event_list = sse_parser.feed(b": keep alive\nid: 1\nevent: odds\ndata: {\"price\"");
+        assert!(event_list.is_empty());
+        let event_list =
+            sse_parser.feed(b": 1.5}\r\n\r\nretry: 3000\ndata: line 1\ndata: line 2\n\n");
+        assert_eq!(
+            event_list,
+            vec![
+                SseEvent {
+                    id: Some("1".to_string()),
+                    event: "odds".to_string(),
+                    data: "{\"price\": 1.5}".to_string(),
+                    retry: None,
+                },
+                SseEvent {
+                    id: None,
+                    event: "message".to_string(),
+                    data: "line 1\nline 2".to_string(),
+                    retry: Some(3000),
+                },
+            ]
+        );
+        assert_eq!(events_to_ndjson(&event_list).lines().count(), 2);
+    }
+}
@@ -0,0 +1,393 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::data_struct::SuccessPolicy;
+use crate::aws_s3::AWSFileIO;
+use crate::file_io::FileIO;
+use crate::logger::ProjectLogger;
+use crate::run_context::RunContext;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishManifest {
+    pub run_id: String,
+    pub published_at: DateTime<Utc>,
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub run_context: Option<RunContext>,
+}
+
+// The manifest is the pointer to the published version. The files of a run are published into
+// their own version folder, and consumers read them from the folder of the current manifest.
+impl PublishManifest {
+    pub const MANIFEST_FILE: &'static str = "_published.manifest.toml";
+    pub const VERSION_PREFIX: &'static str = "_versions";
+
+    pub fn get_version_folder(&self, target_folder: &Path) -> PathBuf {
+        target_folder.join(Self::VERSION_PREFIX).join(&self.run_id)
+    }
+
+    pub fn from_toml_str(manifest_str: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(manifest_str)
+    }
+
+    pub fn to_toml_string(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct StagingTransaction<'a> {
+    project_logger: &'a ProjectLogger,
+    file_io: &'a FileIO<'a>,
+    aws_file_io: &'a AWSFileIO<'a>,
+    aws_bucket: &'a str,
+    target_folder: PathBuf,
+    staging_folder: PathBuf,
+    version_folder: PathBuf,
+    run_id: String,
+    in_s3: bool,
+}
+
+impl<'a> StagingTransaction<'a> {
+    const STAGING_PREFIX: &'a str = "_staging";
+    const TEMP_SUFFIX: &'a str = ".tmp";
+
+    pub fn new(
+        project_logger: &'a ProjectLogger,
+        file_io: &'a FileIO,
+        aws_file_io: &'a AWSFileIO,
+        aws_bucket: &'a str,
+        target_folder: &Path,
+        run_id: &str,
+        in_s3: bool,
+    ) -> Self {
+        Self {
+            project_logger,
+            file_io,
+            aws_file_io,
+            aws_bucket,
+            target_folder: target_folder.to_path_buf(),
+            staging_folder: target_folder.join(Self::STAGING_PREFIX).join(run_id),
+            version_folder: target_folder
+                .join(PublishManifest::VERSION_PREFIX)
+                .join(run_id),
+            run_id: run_id.to_string(),
+            in_s3,
+        }
+    }
+
+    pub fn get_staging_folder(&self) -> &Path {
+        &self.staging_folder
+    }
+
+    pub fn get_target_folder(&self) -> &Path {
+        &self.target_folder
+    }
+
+    pub fn get_version_folder(&self) -> &Path {
+        &self.version_folder
+    }
+
+    pub async fn list_staged_files(&self) -> Vec<String> {
+        if self.in_s3 {
+            let prefix = format!("{}/", self.staging_folder.to_string_lossy());
+            self.aws_file_io
+                .get_elements_in_folder(self.aws_bucket, &self.staging_folder)
+                .await
+                .map(|object_output_list| {
+                    object_output_list
+                        .iter()
+                        .flat_map(|object_output| object_output.contents().unwrap_or_default())
+                        .filter_map(|object| object.key())
+                        .filter_map(|key| key.strip_prefix(&prefix))
+                        .filter(|file| !file.is_empty() && !file.contains('/'))
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        } else if FileIO::check_folder_exist(&self.staging_folder) {
+            self.file_io
+                .list_files_matching(&self.staging_folder, "*", false)
+                .map(|file_entry_list| {
+                    file_entry_list
+                        .iter()
+                        .filter_map(|file_entry| file_entry.path.file_name()?.to_str())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub async fn prepare(&self) {
+        let result = if self.in_s3 {
+            if self
+                .aws_file_io
+                .check_folder_exist(self.aws_bucket, &self.staging_folder)
+                .await
+            {
+                return;
+            }
+            self.aws_file_io
+                .create_directory_if_not_exists(self.aws_bucket, &self.staging_folder)
+                .await
+                .map_err(|e| e.to_string())
+        } else {
+            if FileIO::check_folder_exist(&self.staging_folder) {
+                return;
+            }
+            self.file_io
+                .create_directory_if_not_exists(&self.staging_folder)
+                .map_err(|e| e.to_string())
+        };
+        if let Err(e) = result {
+            let error_str = format!(
+                "Unable to prepare the staging folder {}. {e}",
+                self.staging_folder.display()
+            );
+            self.project_logger.log_error(&error_str);
+            panic!("{error_str}");
+        }
+    }
+
+    // The staging folder is renamed as a whole, so the version folder either has every file of
+    // the run or does not exist.
+    fn publish_local_files(&self) -> crate::error::Result<()> {
+        let version_root = self.target_folder.join(PublishManifest::VERSION_PREFIX);
+        self.file_io
+            .create_directory_if_not_exists(&version_root)
+            .and_then(|()| std::fs::rename(&self.staging_folder, &self.version_folder))
+            .map_err(|e| {
+                let error_str = format!(
+                    "Unable to publish {} to {}. {e}",
+                    self.staging_folder.display(),
+                    self.version_folder.display()
+                );
+                self.project_logger.log_error(&error_str);
+                e.into()
+            })
+    }
+
+    async fn publish_s3_files(&self, staged_files: &[String]) -> crate::error::Result<()> {
+        for file in staged_files.iter() {
+            self.aws_file_io
+                .copy_file(
+                    self.aws_bucket,
+                    &self.staging_folder,
+                    file,
+                    &self.version_folder,
+                    file,
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    // Takes the version folder of a failed publish away. The local files go back to staging, so
+    // the run can be published again, while the S3 staging copies are still in place.
+    async fn rollback(&self) {
+        let result = if self.in_s3 {
+            self.aws_file_io
+                .delete_folder(self.aws_bucket, &self.version_folder)
+                .await
+                .map(|_| ())
+        } else {
+            std::fs::rename(&self.version_folder, &self.staging_folder).map_err(Into::into)
+        };
+        if let Err(e) = result {
+            let error_str = format!(
+                "Unable to roll back the version folder {}. Please remove it manually. {e}",
+                self.version_folder.display()
+            );
+            self.project_logger.log_error(&error_str);
+        }
+    }
+
+    async fn write_manifest(&self, manifest: &PublishManifest) -> bool {
+        let manifest_str = match manifest.to_toml_string() {
+            Ok(manifest_str) => manifest_str,
+            Err(e) => {
+                let error_str = format!("Unable to serialize the publish manifest. {e}");
+                self.project_logger.log_error(&error_str);
+                return false;
+            }
+        };
+        if self.in_s3 {
+            self.aws_file_io
+                .write_string_to_file(
+                    self.aws_bucket,
+                    &self.target_folder,
+                    PublishManifest::MANIFEST_FILE,
+                    &manifest_str,
+                )
+                .await
+                .is_ok()
+        } else {
+            let temp_file = format!("{}{}", PublishManifest::MANIFEST_FILE, Self::TEMP_SUFFIX);
+            self.file_io
+                .write_string_to_file(&self.target_folder, &temp_file, &manifest_str)
+                .and_then(|()| {
+                    self.file_io.rename_file(
+                        &self.target_folder,
+                        &temp_file,
+                        PublishManifest::MANIFEST_FILE,
+                    )
+                })
+                .is_ok()
+        }
+    }
+
+    // The files land in the version folder of the run first, and the manifest is swapped to point
+    // at it last, so consumers reading the manifest see either the previous version or the whole
+    // new one. The version folder is rolled back when any step fails.
+    pub async fn publish(&self) -> bool {
+        let staged_files = self.list_staged_files().await;
+        let publish_result = if self.in_s3 {
+            self.publish_s3_files(&staged_files).await
+        } else {
+            self.publish_local_files()
+        };
+        if let Err(e) = publish_result {
+            let error_str = format!(
+                "Publishing of run {} failed. The manifest is not updated. {e}",
+                self.run_id
+            );
+            self.project_logger.log_error(&error_str);
+            self.rollback().await;
+            return false;
+        }
+        let manifest = PublishManifest {
+            run_id: self.run_id.clone(),
+            published_at: Utc::now(),
+            files: staged_files,
+            run_context: RunContext::current(),
+        };
+        if !self.write_manifest(&manifest).await {
+            self.rollback().await;
+            return false;
+        }
+        // The local staging folder is already renamed into the version folder.
+        if self.in_s3 {
+            self.discard().await;
+        }
+        let info_str = format!(
+            "Run {} published {} files to {}.",
+            self.run_id,
+            manifest.files.len(),
+            self.target_folder.display()
+        );
+        self.project_logger.log_info(&info_str);
+        true
+    }
+
+    pub async fn discard(&self) {
+        if self.in_s3 {
+            if let Err(e) = self
+                .aws_file_io
+                .delete_folder(self.aws_bucket, &self.staging_folder)
+                .await
+            {
+                let warn_str = format!(
+                    "Unable to remove the staging folder {}. {e}",
+                    self.staging_folder.display()
+                );
+                self.project_logger.log_warn(&warn_str);
+            }
+        } else if let Err(e) = std::fs::remove_dir_all(&self.staging_folder) {
+            let warn_str = format!(
+                "Unable to remove the staging folder {}. {e}",
+                self.staging_folder.display()
+            );
+            self.project_logger.log_warn(&warn_str);
+        }
+    }
+
+    // The staged files are kept when the policy is not met, so the run can be inspected or retried.
+    pub async fn commit_if(
+        &self,
+        success_policy: &SuccessPolicy,
+        num_success: usize,
+        num_total: usize,
+    ) -> bool {
+        if success_policy.is_met(num_success, num_total) {
+            self.publish().await
+        } else {
+            let warn_str = format!(
+                "Run {} with {num_success} out of {num_total} successful urls does not meet the success policy. Files are kept in {}.",
+                self.run_id,
+                self.staging_folder.display()
+            );
+            self.project_logger.log_warn(&warn_str);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use log::LevelFilter;
+    use std::env;
+
+    #[test]
+    fn test_success_policy() {
+        let success_policy = SuccessPolicy {
+            min_success_rate: "90%".parse().unwrap(),
+            max_failures: Some(2),
+        };
+        assert!(success_policy.is_met(95, 100));
+        assert!(!success_policy.is_met(97, 100));
+        assert!(!success_policy.is_met(8, 10));
+        assert!(success_policy.is_met(0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_staging_publish() {
+        let logger_name = "test_staging_transaction";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_netdata");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Debug);
+        let file_io = FileIO::new(&project_logger);
+        let aws_file_io = AWSFileIO::new(&project_logger).await;
+        let target_folder = Path::new(&env::var("SCTYS_DATA").unwrap())
+            .join("test_io")
+            .join("test_staging");
+        let staging_transaction = StagingTransaction::new(
+            &project_logger,
+            &file_io,
+            &aws_file_io,
+            "sctys",
+            &target_folder,
+            "test_run",
+            false,
+        );
+        staging_transaction.prepare().await;
+        let staging_folder = staging_transaction.get_staging_folder();
+        file_io
+            .write_string_to_file(staging_folder, "test_staging0.html", "content 0")
+            .unwrap();
+        file_io
+            .write_string_to_file(staging_folder, "test_staging1.html", "content 1")
+            .unwrap();
+        let success_policy = SuccessPolicy {
+            min_success_rate: "50%".parse().unwrap(),
+            max_failures: None,
+        };
+        assert!(staging_transaction.commit_if(&success_policy, 2, 3).await);
+        assert!(FileIO::check_file_exist(
+            &target_folder,
+            PublishManifest::MANIFEST_FILE
+        ));
+        assert!(FileIO::check_file_exist(
+            staging_transaction.get_version_folder(),
+            "test_staging0.html"
+        ));
+        assert!(!FileIO::check_folder_exist(staging_folder));
+    }
+}
@@ -0,0 +1,253 @@
+use polars::prelude::*;
+use reqwest::Url;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use super::data_struct::UrlFile;
+use crate::aws_s3::{AWSFileIO, AWSLoadFileError, AWSWriteFileError};
+use crate::file_io::FileIO;
+use crate::logger::ProjectLogger;
+
+#[derive(Debug)]
+pub struct UrlFileManifest<'a> {
+    project_logger: &'a ProjectLogger,
+    file_io: &'a FileIO<'a>,
+    aws_file_io: &'a AWSFileIO<'a>,
+}
+
+impl<'a> UrlFileManifest<'a> {
+    pub const URL_COLUMN: &'a str = "url";
+    pub const FILE_NAME_COLUMN: &'a str = "file_name";
+    pub const PRIORITY_COLUMN: &'a str = "priority";
+    pub const CATEGORY_COLUMN: &'a str = "category";
+    pub const META_COLUMN: &'a str = "meta";
+
+    pub fn new(
+        project_logger: &'a ProjectLogger,
+        file_io: &'a FileIO,
+        aws_file_io: &'a AWSFileIO,
+    ) -> Self {
+        Self {
+            project_logger,
+            file_io,
+            aws_file_io,
+        }
+    }
+
+    // The priority, category and meta columns are optional. The meta is a json object string.
+    pub fn url_file_list_from_data_frame(&self, data: &DataFrame) -> PolarsResult<Vec<UrlFile>> {
+        let url_column = data.column(Self::URL_COLUMN)?.str()?;
+        let file_name_column = data.column(Self::FILE_NAME_COLUMN)?.str()?;
+        let priority_column = match data.column(Self::PRIORITY_COLUMN) {
+            Ok(column) => Some(column.cast(&DataType::Int64)?),
+            Err(_) => None,
+        };
+        let priority_column = priority_column.as_ref().map(|x| x.i64()).transpose()?;
+        let category_column = match data.column(Self::CATEGORY_COLUMN) {
+            Ok(column) => Some(column.cast(&DataType::String)?),
+            Err(_) => None,
+        };
+        let category_column = category_column.as_ref().map(|x| x.str()).transpose()?;
+        let meta_column = match data.column(Self::META_COLUMN) {
+            Ok(column) => Some(column.cast(&DataType::String)?),
+            Err(_) => None,
+        };
+        let meta_column = meta_column.as_ref().map(|x| x.str()).transpose()?;
+        let url_file_list = url_column
+            .into_iter()
+            .zip(file_name_column)
+            .enumerate()
+            .filter_map(|(row, (url, file_name))| match (url, file_name) {
+                (Some(url), Some(file_name)) => match Url::parse(url) {
+                    Ok(url) => Some(UrlFile {
+                        priority: priority_column.and_then(|x| x.get(row)),
+                        category: category_column
+                            .and_then(|x| x.get(row))
+                            .map(|category| category.to_string()),
+                        meta: meta_column
+                            .and_then(|x| x.get(row))
+                            .map(|meta| self.parse_meta(meta, row))
+                            .unwrap_or_default(),
+                        ..UrlFile::new(url, file_name.to_string())
+                    }),
+                    Err(e) => {
+                        let warn_str = format!("Unable to parse the url {url} in row {row}. {e}");
+                        self.project_logger.log_warn(&warn_str);
+                        None
+                    }
+                },
+                _ => {
+                    let warn_str = format!("Missing url or file name in row {row}.");
+                    self.project_logger.log_warn(&warn_str);
+                    None
+                }
+            })
+            .collect();
+        Ok(url_file_list)
+    }
+
+    fn parse_meta(&self, meta: &str, row: usize) -> HashMap<String, String> {
+        if meta.is_empty() {
+            return HashMap::new();
+        }
+        serde_json::from_str(meta).unwrap_or_else(|e| {
+            let warn_str = format!("Unable to parse the meta {meta} in row {row}. {e}");
+            self.project_logger.log_warn(&warn_str);
+            HashMap::new()
+        })
+    }
+
+    pub fn url_file_list_to_data_frame(url_file_list: &[UrlFile]) -> PolarsResult<DataFrame> {
+        let urls: Vec<&str> = url_file_list
+            .iter()
+            .map(|url_file| url_file.url.as_str())
+            .collect();
+        let file_names: Vec<&str> = url_file_list
+            .iter()
+            .map(|url_file| url_file.file_name.as_str())
+            .collect();
+        let priorities: Vec<Option<i64>> = url_file_list
+            .iter()
+            .map(|url_file| url_file.priority)
+            .collect();
+        let categories: Vec<Option<&str>> = url_file_list
+            .iter()
+            .map(|url_file| url_file.category.as_deref())
+            .collect();
+        let metas: Vec<String> = url_file_list
+            .iter()
+            .map(|url_file| {
+                let meta: BTreeMap<&String, &String> = url_file.meta.iter().collect();
+                serde_json::to_string(&meta).unwrap_or_default()
+            })
+            .collect();
+        df!(
+            Self::URL_COLUMN => urls,
+            Self::FILE_NAME_COLUMN => file_names,
+            Self::PRIORITY_COLUMN => priorities,
+            Self::CATEGORY_COLUMN => categories,
+            Self::META_COLUMN => metas
+        )
+    }
+
+    pub fn load_from_csv(&self, folder_path: &Path, file: &str) -> PolarsResult<Vec<UrlFile>> {
+        let data = self.file_io.load_csv_file(folder_path, file)?;
+        self.url_file_list_from_data_frame(&data)
+    }
+
+    pub fn load_from_parquet(&self, folder_path: &Path, file: &str) -> PolarsResult<Vec<UrlFile>> {
+        let data = self.file_io.load_parquet_file(folder_path, file)?;
+        self.url_file_list_from_data_frame(&data)
+    }
+
+    pub async fn load_from_s3_csv(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+        file: &str,
+    ) -> Result<Vec<UrlFile>, AWSLoadFileError> {
+        let data = self
+            .aws_file_io
+            .load_csv_file(bucket_name, folder_path, file)
+            .await?;
+        Ok(self.url_file_list_from_data_frame(&data)?)
+    }
+
+    pub async fn load_from_s3_parquet(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+        file: &str,
+    ) -> Result<Vec<UrlFile>, AWSLoadFileError> {
+        let data = self
+            .aws_file_io
+            .load_parquet_file(bucket_name, folder_path, file)
+            .await?;
+        Ok(self.url_file_list_from_data_frame(&data)?)
+    }
+
+    pub fn write_to_csv(
+        &self,
+        folder_path: &Path,
+        file: &str,
+        url_file_list: &[UrlFile],
+    ) -> PolarsResult<()> {
+        let mut data = Self::url_file_list_to_data_frame(url_file_list)?;
+        self.file_io.write_csv_file(folder_path, file, &mut data)
+    }
+
+    pub fn write_to_parquet(
+        &self,
+        folder_path: &Path,
+        file: &str,
+        url_file_list: &[UrlFile],
+    ) -> PolarsResult<()> {
+        let mut data = Self::url_file_list_to_data_frame(url_file_list)?;
+        self.file_io
+            .write_parquet_file(folder_path, file, &mut data)
+    }
+
+    pub async fn write_to_s3_csv(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+        file: &str,
+        url_file_list: &[UrlFile],
+    ) -> Result<(), AWSWriteFileError> {
+        let mut data = Self::url_file_list_to_data_frame(url_file_list)?;
+        self.aws_file_io
+            .write_csv_file(bucket_name, folder_path, file, &mut data)
+            .await
+    }
+
+    pub async fn write_to_s3_parquet(
+        &self,
+        bucket_name: &str,
+        folder_path: &Path,
+        file: &str,
+        url_file_list: &[UrlFile],
+    ) -> Result<(), AWSWriteFileError> {
+        let mut data = Self::url_file_list_to_data_frame(url_file_list)?;
+        self.aws_file_io
+            .write_parquet_file(bucket_name, folder_path, file, &mut data)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use log::LevelFilter;
+    use std::env;
+
+    #[tokio::test]
+    async fn test_url_file_manifest_round_trip() {
+        let logger_name = "test_url_file_manifest";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_netdata");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Debug);
+        let file_io = FileIO::new(&project_logger);
+        let aws_file_io = AWSFileIO::new(&project_logger).await;
+        let url_file_manifest = UrlFileManifest::new(&project_logger, &file_io, &aws_file_io);
+        let url_suffix = ["bakerloo", "central", "circle"];
+        let url = Url::parse("http://tfl.gov.uk/tube/timetable/").unwrap();
+        let url_file_list = Vec::from_iter(url_suffix.iter().enumerate().map(|(i, x)| {
+            UrlFile::new(
+                url.join(&format!("{x}/")).unwrap(),
+                format!("test_scrape{i}.html"),
+            )
+        }));
+        let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
+        let file = "test_url_file_manifest.parquet";
+        url_file_manifest
+            .write_to_parquet(&folder_path, file, &url_file_list)
+            .unwrap();
+        let loaded_url_file_list = url_file_manifest
+            .load_from_parquet(&folder_path, file)
+            .unwrap();
+        assert_eq!(loaded_url_file_list, url_file_list);
+    }
+}
@@ -0,0 +1,385 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use super::data_struct::UrlFile;
+use crate::logger::ProjectLogger;
+
+// Bloom filter with hashes derived from one sha256 digest by double hashing, so the bits stay
+// valid across builds and can be persisted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BloomFilterRecord {
+    num_bits: u64,
+    num_hashes: u32,
+    bits: String,
+}
+
+impl BloomFilter {
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let expected_items = expected_items.max(1) as f64;
+        let false_positive_rate = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-expected_items * false_positive_rate.ln() / (ln2 * ln2)).ceil() as u64;
+        let num_bits = num_bits.max(8);
+        let num_hashes = ((num_bits as f64 / expected_items) * ln2).round().max(1.0) as u32;
+        Self {
+            bits: vec![0; num_bits.div_ceil(8) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    fn bit_indices(&self, item: &str) -> impl Iterator<Item = u64> + '_ {
+        let digest = Sha256::digest(item.as_bytes());
+        let hash_a = u64::from_le_bytes(digest[..8].try_into().unwrap_or_default());
+        let hash_b = u64::from_le_bytes(digest[8..16].try_into().unwrap_or_default()) | 1;
+        (0..self.num_hashes as u64)
+            .map(move |i| hash_a.wrapping_add(i.wrapping_mul(hash_b)) % self.num_bits)
+    }
+
+    pub fn contains(&self, item: &str) -> bool {
+        self.bit_indices(item)
+            .all(|index| self.bits[(index / 8) as usize] & (1 << (index % 8)) != 0)
+    }
+
+    // Returns true if the item was not in the filter before.
+    pub fn insert(&mut self, item: &str) -> bool {
+        let index_list: Vec<u64> = self.bit_indices(item).collect();
+        let mut is_new = false;
+        for index in index_list {
+            let mask = 1 << (index % 8);
+            let byte = &mut self.bits[(index / 8) as usize];
+            is_new |= *byte & mask == 0;
+            *byte |= mask;
+        }
+        is_new
+    }
+
+    pub fn to_json_string(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&BloomFilterRecord {
+            num_bits: self.num_bits,
+            num_hashes: self.num_hashes,
+            bits: STANDARD.encode(&self.bits),
+        })
+    }
+
+    pub fn from_json_str(bloom_str: &str) -> Result<Self> {
+        let record: BloomFilterRecord = serde_json::from_str(bloom_str)?;
+        let bits = STANDARD
+            .decode(record.bits)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        if bits.len() as u64 != record.num_bits.div_ceil(8) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Bloom filter bits do not match its size.",
+            ));
+        }
+        Ok(Self {
+            bits,
+            num_bits: record.num_bits,
+            num_hashes: record.num_hashes,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlNormalizer {
+    strip_params: Vec<String>,
+    remove_fragment: bool,
+    sort_query: bool,
+    remove_trailing_slash: bool,
+}
+
+impl Default for UrlNormalizer {
+    fn default() -> Self {
+        Self {
+            strip_params: ["utm_*", "fbclid", "gclid", "mc_cid", "mc_eid"]
+                .iter()
+                .map(|param| param.to_string())
+                .collect(),
+            remove_fragment: true,
+            sort_query: true,
+            remove_trailing_slash: false,
+        }
+    }
+}
+
+impl UrlNormalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A trailing * matches any param with the prefix, e.g. utm_*.
+    pub fn set_strip_params(&mut self, strip_params: &[&str]) {
+        self.strip_params = strip_params.iter().map(|x| x.to_string()).collect();
+    }
+
+    pub fn set_remove_fragment(&mut self, remove_fragment: bool) {
+        self.remove_fragment = remove_fragment;
+    }
+
+    pub fn set_sort_query(&mut self, sort_query: bool) {
+        self.sort_query = sort_query;
+    }
+
+    pub fn set_remove_trailing_slash(&mut self, remove_trailing_slash: bool) {
+        self.remove_trailing_slash = remove_trailing_slash;
+    }
+
+    fn is_stripped(&self, param: &str) -> bool {
+        self.strip_params
+            .iter()
+            .any(|strip_param| match strip_param.strip_suffix('*') {
+                Some(prefix) => param.starts_with(prefix),
+                None => param == strip_param,
+            })
+    }
+
+    pub fn normalize(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+        if let Some(host) = url.host_str().map(|host| host.to_lowercase()) {
+            let _ = url.set_host(Some(&host));
+        }
+        if self.remove_fragment {
+            url.set_fragment(None);
+        }
+        let mut query_list: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(param, _)| !self.is_stripped(param))
+            .map(|(param, value)| (param.into_owned(), value.into_owned()))
+            .collect();
+        if self.sort_query {
+            query_list.sort();
+        }
+        if query_list.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(query_list);
+        }
+        if self.remove_trailing_slash && url.path().len() > 1 && url.path().ends_with('/') {
+            let path = url.path().trim_end_matches('/').to_string();
+            url.set_path(&path);
+        }
+        url
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FrontierItem {
+    priority: i64,
+    sequence: u64,
+    url_file: UrlFile,
+}
+
+// Higher priority first, then first in first out.
+impl Ord for FrontierItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for FrontierItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Urls are normalized before the seen check so tracking params and fragments do not make the
+// same page look new. The seen-set is a bloom filter, so a small fraction of new urls may be
+// dropped as seen, controlled by the false positive rate.
+#[derive(Debug)]
+pub struct UrlFrontier<'a> {
+    project_logger: &'a ProjectLogger,
+    seen_file: PathBuf,
+    seen_filter: BloomFilter,
+    url_normalizer: UrlNormalizer,
+    queue: BinaryHeap<FrontierItem>,
+    sequence: u64,
+}
+
+impl<'a> UrlFrontier<'a> {
+    const EXPECTED_ITEMS: usize = 1_000_000;
+    const FALSE_POSITIVE_RATE: f64 = 0.001;
+
+    pub fn new(project_logger: &'a ProjectLogger, folder_path: &Path, frontier_name: &str) -> Self {
+        Self::with_capacity(
+            project_logger,
+            folder_path,
+            frontier_name,
+            Self::EXPECTED_ITEMS,
+            Self::FALSE_POSITIVE_RATE,
+        )
+    }
+
+    // The capacity only applies to a new seen-set. An existing one is loaded as is.
+    pub fn with_capacity(
+        project_logger: &'a ProjectLogger,
+        folder_path: &Path,
+        frontier_name: &str,
+        expected_items: usize,
+        false_positive_rate: f64,
+    ) -> Self {
+        let seen_file = folder_path.join(format!("{frontier_name}.seen.json"));
+        let seen_filter = fs::read_to_string(&seen_file)
+            .ok()
+            .and_then(|bloom_str| {
+                BloomFilter::from_json_str(&bloom_str)
+                    .map_err(|e| {
+                        let warn_str = format!(
+                            "Unable to parse the seen-set {}. Start a new one. {e}",
+                            seen_file.display()
+                        );
+                        project_logger.log_warn(&warn_str);
+                    })
+                    .ok()
+            })
+            .unwrap_or_else(|| BloomFilter::new(expected_items, false_positive_rate));
+        Self {
+            project_logger,
+            seen_file,
+            seen_filter,
+            url_normalizer: UrlNormalizer::default(),
+            queue: BinaryHeap::new(),
+            sequence: 0,
+        }
+    }
+
+    pub fn set_url_normalizer(&mut self, url_normalizer: UrlNormalizer) {
+        self.url_normalizer = url_normalizer;
+    }
+
+    pub fn is_seen(&self, url: &Url) -> bool {
+        self.seen_filter
+            .contains(self.url_normalizer.normalize(url).as_str())
+    }
+
+    // Returns true if the url is new and queued. The priority of the url file, if set, overrides
+    // the given one.
+    pub fn push(&mut self, url_file: UrlFile, priority: i64) -> bool {
+        let url = self.url_normalizer.normalize(&url_file.url);
+        if !self.seen_filter.insert(url.as_str()) {
+            return false;
+        }
+        self.sequence += 1;
+        self.queue.push(FrontierItem {
+            priority: url_file.priority.unwrap_or(priority),
+            sequence: self.sequence,
+            url_file: UrlFile { url, ..url_file },
+        });
+        true
+    }
+
+    pub fn push_all(&mut self, url_file_list: &[UrlFile], priority: i64) -> usize {
+        let num_added = url_file_list
+            .iter()
+            .filter(|url_file| self.push((*url_file).clone(), priority))
+            .count();
+        let debug_str = format!(
+            "Added {num_added} out of {} urls to the frontier.",
+            url_file_list.len()
+        );
+        self.project_logger.log_debug(&debug_str);
+        num_added
+    }
+
+    pub fn pop(&mut self) -> Option<UrlFile> {
+        self.queue.pop().map(|item| item.url_file)
+    }
+
+    pub fn pop_batch(&mut self, batch_size: usize) -> Vec<UrlFile> {
+        (0..batch_size).map_while(|_| self.pop()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn save_seen(&self) -> Result<()> {
+        if let Some(folder_path) = self.seen_file.parent() {
+            fs::create_dir_all(folder_path)?;
+        }
+        let bloom_str = self.seen_filter.to_json_string()?;
+        let temp_file = self.seen_file.with_extension("json.tmp");
+        fs::write(&temp_file, bloom_str)?;
+        fs::rename(&temp_file, &self.seen_file).map_err(|e| {
+            let error_str = format!(
+                "Unable to save the seen-set {}. {e}",
+                self.seen_file.display()
+            );
+            self.project_logger.log_error(&error_str);
+            e
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_url_normalizer_and_bloom_filter() {
+        let url_normalizer = UrlNormalizer::new();
+        let url =
+            Url::parse("https://WWW.Example.com/match?b=2&utm_source=x&a=1&fbclid=y#odds").unwrap();
+        assert_eq!(
+            url_normalizer.normalize(&url).as_str(),
+            "https://www.example.com/match?a=1&b=2"
+        );
+        let url = Url::parse("https://www.example.com/match?utm_medium=email").unwrap();
+        assert_eq!(
+            url_normalizer.normalize(&url).as_str(),
+            "https://www.example.com/match"
+        );
+        let mut bloom_filter = BloomFilter::new(1000, 0.01);
+        assert!(bloom_filter.insert("https://www.example.com/a"));
+        assert!(!bloom_filter.insert("https://www.example.com/a"));
+        assert!(bloom_filter.contains("https://www.example.com/a"));
+        assert!(!bloom_filter.contains("https://www.example.com/b"));
+        let bloom_str = bloom_filter.to_json_string().unwrap();
+        assert_eq!(
+            BloomFilter::from_json_str(&bloom_str).unwrap(),
+            bloom_filter
+        );
+    }
+
+    #[test]
+    fn test_frontier_item_order() {
+        let url_file = UrlFile::new(
+            Url::parse("https://www.example.com/").unwrap(),
+            "index.html".to_string(),
+        );
+        let mut queue = BinaryHeap::new();
+        for (priority, sequence) in [(0, 1), (5, 2), (0, 3), (5, 4)] {
+            queue.push(FrontierItem {
+                priority,
+                sequence,
+                url_file: url_file.clone(),
+            });
+        }
+        let order: Vec<(i64, u64)> = std::iter::from_fn(|| queue.pop())
+            .map(|item| (item.priority, item.sequence))
+            .collect();
+        assert_eq!(order, vec![(5, 2), (5, 4), (0, 1), (0, 3)]);
+    }
+}
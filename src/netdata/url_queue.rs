@@ -0,0 +1,422 @@
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use rand::{thread_rng, Rng};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::data_struct::UrlFile;
+use crate::logger::ProjectLogger;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct QueueItem {
+    url: String,
+    file_name: String,
+    num_attempts: u32,
+    lease_id: Option<String>,
+    leased_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    priority: Option<i64>,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    meta: BTreeMap<String, String>,
+}
+
+impl QueueItem {
+    fn is_available(&self, now: DateTime<Utc>) -> bool {
+        self.leased_until
+            .map_or(true, |leased_until| leased_until <= now)
+    }
+
+    fn to_url_file(&self) -> Option<UrlFile> {
+        Url::parse(&self.url).ok().map(|url| UrlFile {
+            priority: self.priority,
+            category: self.category.clone(),
+            meta: self.meta.clone().into_iter().collect(),
+            ..UrlFile::new(url, self.file_name.clone())
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct QueueState {
+    pending: Vec<QueueItem>,
+    dead_letter: Vec<QueueItem>,
+}
+
+impl QueueState {
+    fn enqueue(&mut self, url_file_list: &[UrlFile]) -> usize {
+        let mut num_added = 0;
+        for url_file in url_file_list {
+            let url = url_file.url.as_str();
+            if !self.pending.iter().any(|item| item.url == url) {
+                self.pending.push(QueueItem {
+                    url: url.to_string(),
+                    file_name: url_file.file_name.clone(),
+                    num_attempts: 0,
+                    lease_id: None,
+                    leased_until: None,
+                    priority: url_file.priority,
+                    category: url_file.category.clone(),
+                    meta: url_file.meta.clone().into_iter().collect(),
+                });
+                num_added += 1;
+            }
+        }
+        num_added
+    }
+
+    // The available items which have used up their attempts, e.g. by crashing the worker before
+    // ack or nack, or which have no valid url are dead-lettered instead of leased again.
+    fn dead_letter_unleasable(&mut self, max_attempts: u32, now: DateTime<Utc>) {
+        let (dead_letter_list, pending_list): (Vec<QueueItem>, Vec<QueueItem>) =
+            self.pending.drain(..).partition(|item| {
+                item.is_available(now)
+                    && (item.num_attempts >= max_attempts || item.to_url_file().is_none())
+            });
+        self.pending = pending_list;
+        self.dead_letter
+            .extend(dead_letter_list.into_iter().map(|mut item| {
+                item.lease_id = None;
+                item.leased_until = None;
+                item
+            }));
+    }
+
+    fn lease(
+        &mut self,
+        num_items: usize,
+        max_attempts: u32,
+        visibility_timeout: Duration,
+        now: DateTime<Utc>,
+    ) -> Vec<UrlLease> {
+        self.dead_letter_unleasable(max_attempts, now);
+        let leased_until = now + chrono::Duration::from_std(visibility_timeout).unwrap_or_default();
+        let mut rng = thread_rng();
+        let mut lease_list = Vec::with_capacity(num_items);
+        // Higher priority first, then first in first out as the sort is stable.
+        let mut available_list: Vec<usize> = (0..self.pending.len())
+            .filter(|index| self.pending[*index].is_available(now))
+            .collect();
+        available_list.sort_by_key(|index| Reverse(self.pending[*index].priority.unwrap_or(0)));
+        for index in available_list {
+            if lease_list.len() >= num_items {
+                break;
+            }
+            let item = &mut self.pending[index];
+            if let Some(url_file) = item.to_url_file() {
+                let lease_id = format!("{:016x}", rng.gen::<u64>());
+                item.num_attempts += 1;
+                item.lease_id = Some(lease_id.clone());
+                item.leased_until = Some(leased_until);
+                lease_list.push(UrlLease {
+                    url_file,
+                    lease_id,
+                    num_attempts: item.num_attempts,
+                });
+            }
+        }
+        lease_list
+    }
+
+    fn position(&self, url_lease: &UrlLease) -> Option<usize> {
+        self.pending
+            .iter()
+            .position(|item| item.lease_id.as_deref() == Some(url_lease.lease_id.as_str()))
+    }
+
+    fn ack(&mut self, url_lease: &UrlLease) -> bool {
+        self.position(url_lease)
+            .map(|position| self.pending.remove(position))
+            .is_some()
+    }
+
+    fn nack(&mut self, url_lease: &UrlLease, max_attempts: u32) -> bool {
+        match self.position(url_lease) {
+            Some(position) => {
+                if self.pending[position].num_attempts >= max_attempts {
+                    let mut item = self.pending.remove(position);
+                    item.lease_id = None;
+                    item.leased_until = None;
+                    self.dead_letter.push(item);
+                } else {
+                    let item = &mut self.pending[position];
+                    item.lease_id = None;
+                    item.leased_until = None;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlLease {
+    pub url_file: UrlFile,
+    pub lease_id: String,
+    pub num_attempts: u32,
+}
+
+// The advisory lock is released by the os when the file is closed, also when the process dies,
+// so a crashed worker never leaves the queue locked.
+struct QueueLock {
+    lock_file: File,
+}
+
+impl Drop for QueueLock {
+    fn drop(&mut self) {
+        let _ = self.lock_file.unlock();
+    }
+}
+
+// The queue state lives in a json file next to a lock file, so worker processes on the same
+// machine or a shared mount can lease from one backlog. A lease that is neither acked nor nacked
+// within the visibility timeout becomes available again.
+#[derive(Debug)]
+pub struct UrlQueue<'a> {
+    project_logger: &'a ProjectLogger,
+    queue_file: PathBuf,
+    lock_file: PathBuf,
+    visibility_timeout: Duration,
+    max_attempts: u32,
+}
+
+impl<'a> UrlQueue<'a> {
+    const VISIBILITY_TIMEOUT: Duration = Duration::from_secs(300);
+    const MAX_ATTEMPTS: u32 = 3;
+
+    pub fn new(project_logger: &'a ProjectLogger, folder_path: &Path, queue_name: &str) -> Self {
+        Self {
+            project_logger,
+            queue_file: folder_path.join(format!("{queue_name}.queue.json")),
+            lock_file: folder_path.join(format!("{queue_name}.queue.lock")),
+            visibility_timeout: Self::VISIBILITY_TIMEOUT,
+            max_attempts: Self::MAX_ATTEMPTS,
+        }
+    }
+
+    pub fn set_visibility_timeout(&mut self, visibility_timeout: Duration) {
+        self.visibility_timeout = visibility_timeout;
+    }
+
+    pub fn set_max_attempts(&mut self, max_attempts: u32) {
+        self.max_attempts = max_attempts;
+    }
+
+    // The lock file is kept between updates. Removing it would let a worker lock a new file
+    // while another still holds the lock of the removed one.
+    fn acquire_lock(&self) -> Result<QueueLock> {
+        let lock_file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.lock_file)
+            .and_then(|lock_file| lock_file.lock_exclusive().map(|()| lock_file))
+            .map_err(|e| {
+                let error_str = format!(
+                    "Unable to lock the queue {}. {e}",
+                    self.queue_file.display()
+                );
+                self.project_logger.log_error(&error_str);
+                e
+            })?;
+        Ok(QueueLock { lock_file })
+    }
+
+    fn load_state(&self) -> Result<QueueState> {
+        if !self.queue_file.is_file() {
+            return Ok(QueueState::default());
+        }
+        let queue_str = fs::read_to_string(&self.queue_file)?;
+        serde_json::from_str(&queue_str).map_err(|e| {
+            let error_str = format!(
+                "Unable to parse the queue {}. {e}",
+                self.queue_file.display()
+            );
+            self.project_logger.log_error(&error_str);
+            Error::new(ErrorKind::InvalidData, error_str)
+        })
+    }
+
+    fn save_state(&self, queue_state: &QueueState) -> Result<()> {
+        let queue_str = serde_json::to_string(queue_state)?;
+        let temp_file = self.queue_file.with_extension("json.tmp");
+        fs::write(&temp_file, queue_str)?;
+        File::open(&temp_file)?.sync_all()?;
+        fs::rename(&temp_file, &self.queue_file)
+    }
+
+    fn update_state<T>(&self, update_func: impl FnOnce(&mut QueueState) -> T) -> Result<T> {
+        let _queue_lock = self.acquire_lock()?;
+        let mut queue_state = self.load_state()?;
+        let result = update_func(&mut queue_state);
+        self.save_state(&queue_state).map_err(|e| {
+            let error_str = format!(
+                "Unable to save the queue {}. {e}",
+                self.queue_file.display()
+            );
+            self.project_logger.log_error(&error_str);
+            e
+        })?;
+        Ok(result)
+    }
+
+    pub fn enqueue(&self, url_file_list: &[UrlFile]) -> Result<usize> {
+        let num_added = self.update_state(|queue_state| queue_state.enqueue(url_file_list))?;
+        let debug_str = format!(
+            "Enqueued {num_added} out of {} urls to {}.",
+            url_file_list.len(),
+            self.queue_file.display()
+        );
+        self.project_logger.log_debug(&debug_str);
+        Ok(num_added)
+    }
+
+    pub fn lease(&self, num_items: usize) -> Result<Vec<UrlLease>> {
+        let max_attempts = self.max_attempts;
+        let visibility_timeout = self.visibility_timeout;
+        self.update_state(|queue_state| {
+            queue_state.lease(num_items, max_attempts, visibility_timeout, Utc::now())
+        })
+    }
+
+    pub fn ack(&self, url_lease: &UrlLease) -> Result<()> {
+        if !self.update_state(|queue_state| queue_state.ack(url_lease))? {
+            let warn_str = format!(
+                "The lease of {} has expired before ack.",
+                url_lease.url_file.url
+            );
+            self.project_logger.log_warn(&warn_str);
+        }
+        Ok(())
+    }
+
+    pub fn nack(&self, url_lease: &UrlLease) -> Result<()> {
+        let max_attempts = self.max_attempts;
+        if !self.update_state(|queue_state| queue_state.nack(url_lease, max_attempts))? {
+            let warn_str = format!(
+                "The lease of {} has expired before nack.",
+                url_lease.url_file.url
+            );
+            self.project_logger.log_warn(&warn_str);
+        }
+        Ok(())
+    }
+
+    pub fn get_num_pending(&self) -> Result<usize> {
+        Ok(self.load_state()?.pending.len())
+    }
+
+    pub fn get_dead_letter_list(&self) -> Result<Vec<UrlFile>> {
+        Ok(self
+            .load_state()?
+            .dead_letter
+            .iter()
+            .filter_map(QueueItem::to_url_file)
+            .collect())
+    }
+
+    pub fn drain_dead_letter(&self) -> Result<Vec<UrlFile>> {
+        self.update_state(|queue_state| {
+            queue_state
+                .dead_letter
+                .drain(..)
+                .filter_map(|item| item.to_url_file())
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use log::LevelFilter;
+    use std::env;
+
+    fn get_url_file_list() -> Vec<UrlFile> {
+        ["bakerloo", "central", "circle"]
+            .iter()
+            .map(|line| {
+                UrlFile::new(
+                    Url::parse(&format!("https://tfl.gov.uk/tube/timetable/{line}/")).unwrap(),
+                    format!("tfl_{line}.html"),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_queue_state() {
+        let now = Utc::now();
+        let mut queue_state = QueueState::default();
+        let url_file_list = get_url_file_list();
+        assert_eq!(queue_state.enqueue(&url_file_list), 3);
+        assert_eq!(queue_state.enqueue(&url_file_list[..1]), 0);
+        let lease_list = queue_state.lease(2, 3, Duration::from_secs(60), now);
+        assert_eq!(lease_list.len(), 2);
+        let lease_list_2 = queue_state.lease(2, 3, Duration::from_secs(60), now);
+        assert_eq!(lease_list_2.len(), 1);
+        assert_eq!(lease_list_2[0].url_file, url_file_list[2]);
+        assert!(queue_state.ack(&lease_list[0]));
+        assert!(!queue_state.ack(&lease_list[0]));
+        assert!(queue_state.nack(&lease_list[1], 1));
+        assert_eq!(queue_state.pending.len(), 1);
+        assert_eq!(queue_state.dead_letter.len(), 1);
+        let expired_time = now + chrono::Duration::seconds(61);
+        let lease_list_3 = queue_state.lease(2, 3, Duration::from_secs(60), expired_time);
+        assert_eq!(lease_list_3.len(), 1);
+        assert_eq!(lease_list_3[0].num_attempts, 2);
+        assert!(!queue_state.nack(&lease_list_2[0], 3));
+        let expired_time = expired_time + chrono::Duration::seconds(61);
+        let lease_list_4 = queue_state.lease(2, 2, Duration::from_secs(60), expired_time);
+        assert!(lease_list_4.is_empty());
+        assert!(queue_state.pending.is_empty());
+        assert_eq!(queue_state.dead_letter.len(), 2);
+        assert_eq!(queue_state.dead_letter[1].num_attempts, 2);
+        assert!(queue_state.dead_letter[1].lease_id.is_none());
+        queue_state.pending.push(QueueItem {
+            url: "not a url".to_string(),
+            ..queue_state.dead_letter[1].clone()
+        });
+        assert!(queue_state
+            .lease(2, 3, Duration::from_secs(60), expired_time)
+            .is_empty());
+        assert_eq!(queue_state.dead_letter.len(), 3);
+        let mut queue_state = QueueState::default();
+        let mut url_file_list = get_url_file_list();
+        url_file_list[2] = url_file_list[2].clone().with_priority(5);
+        queue_state.enqueue(&url_file_list);
+        let lease_list = queue_state.lease(2, 3, Duration::from_secs(60), now);
+        assert_eq!(lease_list[0].url_file, url_file_list[2]);
+        assert_eq!(lease_list[1].url_file, url_file_list[0]);
+    }
+
+    #[test]
+    fn test_url_queue() {
+        let logger_name = "test_url_queue";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_netdata");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Debug);
+        let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
+        let mut url_queue = UrlQueue::new(&project_logger, &folder_path, logger_name);
+        url_queue.set_max_attempts(1);
+        url_queue.drain_dead_letter().unwrap();
+        url_queue.enqueue(&get_url_file_list()).unwrap();
+        let lease_list = url_queue.lease(3).unwrap();
+        url_queue.ack(&lease_list[0]).unwrap();
+        url_queue.ack(&lease_list[1]).unwrap();
+        url_queue.nack(&lease_list[2]).unwrap();
+        assert_eq!(url_queue.get_num_pending().unwrap(), 0);
+        assert_eq!(url_queue.drain_dead_letter().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,292 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::header::{HeaderMap, CONTENT_ENCODING, TRANSFER_ENCODING};
+use reqwest::{Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Result, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use crate::logger::ProjectLogger;
+
+#[derive(Debug, Clone)]
+pub struct WarcExchange<'b> {
+    pub url: &'b Url,
+    pub method: &'b Method,
+    pub request_headers: &'b HeaderMap,
+    pub status: StatusCode,
+    pub response_headers: &'b HeaderMap,
+    pub body: &'b [u8],
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct WarcFileState {
+    file_name: Option<String>,
+    file_index: u32,
+}
+
+fn record_id() -> String {
+    // Random uuid with the version 4 and variant bits set.
+    let uuid = (rand::random::<u128>() & !(0xf_u128 << 76)) | (0x4_u128 << 76);
+    let uuid = (uuid & !(0x3_u128 << 62)) | (0x2_u128 << 62);
+    let uuid = format!("{uuid:032x}");
+    format!(
+        "<urn:uuid:{}-{}-{}-{}-{}>",
+        &uuid[..8],
+        &uuid[8..12],
+        &uuid[12..16],
+        &uuid[16..20],
+        &uuid[20..]
+    )
+}
+
+fn block_digest(block: &[u8]) -> String {
+    let digest: String = Sha256::digest(block)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("sha256:{digest}")
+}
+
+fn format_headers(header_map: &HeaderMap) -> String {
+    header_map
+        .iter()
+        .map(|(name, value)| format!("{name}: {}\r\n", String::from_utf8_lossy(value.as_bytes())))
+        .collect()
+}
+
+pub fn http_request_block(url: &Url, method: &Method, header_map: &HeaderMap) -> Vec<u8> {
+    let target = match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_string(),
+    };
+    let host = url.host_str().unwrap_or_default();
+    format!(
+        "{method} {target} HTTP/1.1\r\nHost: {host}\r\n{}\r\n",
+        format_headers(header_map)
+    )
+    .into_bytes()
+}
+
+// The body is archived as decoded by reqwest, so the encoding headers are dropped to keep the
+// record consistent with it.
+pub fn http_response_block(status: StatusCode, header_map: &HeaderMap, body: &[u8]) -> Vec<u8> {
+    let mut header_map = header_map.clone();
+    header_map.remove(CONTENT_ENCODING);
+    header_map.remove(TRANSFER_ENCODING);
+    let mut block = format!(
+        "HTTP/1.1 {} {}\r\n{}\r\n",
+        status.as_str(),
+        status.canonical_reason().unwrap_or_default(),
+        format_headers(&header_map)
+    )
+    .into_bytes();
+    block.extend_from_slice(body);
+    block
+}
+
+pub fn warc_record(
+    warc_type: &str,
+    record_id: &str,
+    fetched_at: &DateTime<Utc>,
+    extra_headers: &[(&str, &str)],
+    content_type: &str,
+    block: &[u8],
+) -> Vec<u8> {
+    let mut header = format!(
+        "WARC/1.1\r\nWARC-Type: {warc_type}\r\nWARC-Record-ID: {record_id}\r\nWARC-Date: {}\r\n",
+        fetched_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+    );
+    for (name, value) in extra_headers {
+        header.push_str(&format!("{name}: {value}\r\n"));
+    }
+    header.push_str(&format!(
+        "WARC-Block-Digest: {}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\r\n",
+        block_digest(block),
+        block.len()
+    ));
+    let mut record = header.into_bytes();
+    record.extend_from_slice(block);
+    record.extend_from_slice(b"\r\n\r\n");
+    record
+}
+
+// Every record is compressed as its own gzip member as the WARC spec recommends, so the files
+// can be read by standard tools and a file is rotated once it grows beyond max_file_size.
+#[derive(Debug)]
+pub struct WarcWriter<'a> {
+    project_logger: &'a ProjectLogger,
+    folder_path: PathBuf,
+    prefix: String,
+    max_file_size: u64,
+    file_state: Mutex<WarcFileState>,
+}
+
+impl<'a> WarcWriter<'a> {
+    const MAX_FILE_SIZE: u64 = 1_000_000_000;
+    const FILE_TIME_FORMAT: &'static str = "%Y%m%d%H%M%S";
+
+    pub fn new(project_logger: &'a ProjectLogger, folder_path: &Path, prefix: &str) -> Self {
+        Self {
+            project_logger,
+            folder_path: folder_path.to_path_buf(),
+            prefix: prefix.to_string(),
+            max_file_size: Self::MAX_FILE_SIZE,
+            file_state: Mutex::new(WarcFileState::default()),
+        }
+    }
+
+    pub fn set_max_file_size(&mut self, max_file_size: u64) {
+        self.max_file_size = max_file_size;
+    }
+
+    pub fn get_current_file(&self) -> Option<PathBuf> {
+        self.file_state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .file_name
+            .as_ref()
+            .map(|file_name| self.folder_path.join(file_name))
+    }
+
+    fn append_gzip_member(file: &mut File, record: &[u8]) -> Result<()> {
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        encoder.write_all(record)?;
+        encoder.finish()?.flush()
+    }
+
+    fn open_file(&self, file_state: &mut WarcFileState) -> Result<File> {
+        let current_file = file_state.file_name.as_ref().and_then(|file_name| {
+            let full_path = self.folder_path.join(file_name);
+            fs::metadata(&full_path)
+                .ok()
+                .filter(|metadata| metadata.len() < self.max_file_size)
+                .map(|_| full_path)
+        });
+        if let Some(full_path) = current_file {
+            return OpenOptions::new().append(true).open(full_path);
+        }
+        fs::create_dir_all(&self.folder_path)?;
+        file_state.file_index += 1;
+        let file_name = format!(
+            "{}-{}-{:05}.warc.gz",
+            self.prefix,
+            Utc::now().format(Self::FILE_TIME_FORMAT),
+            file_state.file_index
+        );
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.folder_path.join(&file_name))?;
+        let warcinfo = format!(
+            "software: sctys_rust_utilities\r\nformat: WARC File Format 1.1\r\nfilename: {file_name}\r\n"
+        );
+        let record = warc_record(
+            "warcinfo",
+            &record_id(),
+            &Utc::now(),
+            &[("WARC-Filename", &file_name)],
+            "application/warc-fields",
+            warcinfo.as_bytes(),
+        );
+        Self::append_gzip_member(&mut file, &record)?;
+        let debug_str = format!("WARC file {file_name} opened.");
+        self.project_logger.log_debug(&debug_str);
+        file_state.file_name = Some(file_name);
+        Ok(file)
+    }
+
+    pub fn write_exchange(&self, warc_exchange: &WarcExchange) -> Result<()> {
+        let url = warc_exchange.url.as_str();
+        let response_id = record_id();
+        let request_id = record_id();
+        let response_record = warc_record(
+            "response",
+            &response_id,
+            &warc_exchange.fetched_at,
+            &[("WARC-Target-URI", url)],
+            "application/http;msgtype=response",
+            &http_response_block(
+                warc_exchange.status,
+                warc_exchange.response_headers,
+                warc_exchange.body,
+            ),
+        );
+        let request_record = warc_record(
+            "request",
+            &request_id,
+            &warc_exchange.fetched_at,
+            &[
+                ("WARC-Target-URI", url),
+                ("WARC-Concurrent-To", &response_id),
+            ],
+            "application/http;msgtype=request",
+            &http_request_block(
+                warc_exchange.url,
+                warc_exchange.method,
+                warc_exchange.request_headers,
+            ),
+        );
+        let mut file_state = self
+            .file_state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut file = self.open_file(&mut file_state)?;
+        Self::append_gzip_member(&mut file, &response_record)?;
+        Self::append_gzip_member(&mut file, &request_record)
+    }
+
+    // Archiving is best effort and never fails the scrape itself.
+    pub fn archive(&self, warc_exchange: &WarcExchange) {
+        if let Err(e) = self.write_exchange(warc_exchange) {
+            let warn_str = format!("Unable to archive {} to WARC. {e}", warc_exchange.url);
+            self.project_logger.log_warn(&warn_str);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use reqwest::header::{HeaderValue, CONTENT_TYPE};
+
+    #[test]
+    fn test_warc_record() {
+        let url = Url::parse("https://www.nowgoal.com/football/live?date=20230102").unwrap();
+        let mut header_map = HeaderMap::new();
+        header_map.insert(CONTENT_TYPE, HeaderValue::from_static("text/html"));
+        let request_block = http_request_block(&url, &Method::GET, &HeaderMap::new());
+        assert_eq!(
+            String::from_utf8(request_block).unwrap(),
+            "GET /football/live?date=20230102 HTTP/1.1\r\nHost: www.nowgoal.com\r\n\r\n"
+        );
+        let response_block = http_response_block(StatusCode::OK, &header_map, b"<html></html>");
+        assert_eq!(
+            String::from_utf8(response_block.clone()).unwrap(),
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\n\r\n<html></html>"
+        );
+        let fetched_at = DateTime::parse_from_rfc3339("2023-01-02T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let record = warc_record(
+            "response",
+            "<urn:uuid:test>",
+            &fetched_at,
+            &[("WARC-Target-URI", url.as_str())],
+            "application/http;msgtype=response",
+            &response_block,
+        );
+        let record = String::from_utf8(record).unwrap();
+        assert!(record.starts_with("WARC/1.1\r\nWARC-Type: response\r\n"));
+        assert!(record.contains("WARC-Date: 2023-01-02T10:00:00Z\r\n"));
+        assert!(record.contains(&format!("Content-Length: {}\r\n", response_block.len())));
+        assert!(record.ends_with("<html></html>\r\n\r\n"));
+        let record_id = record_id();
+        assert_eq!(record_id.len(), 47);
+        assert_eq!(&record_id[24..25], "4");
+    }
+}
@@ -0,0 +1,354 @@
+use reqwest::Client;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::logger::ProjectLogger;
+use crate::time_operation::{self, SystemClock};
+
+#[derive(Debug)]
+pub struct WebDriverManager<'a> {
+    project_logger: &'a ProjectLogger,
+    driver_process: String,
+    ports: Vec<u32>,
+    pid_folder: PathBuf,
+    processes: Mutex<HashMap<u32, Child>>,
+    next_index: AtomicUsize,
+    health_check_timeout: Duration,
+    startup_wait: Duration,
+}
+
+impl<'a> WebDriverManager<'a> {
+    const CHROME_PROCESS: &'a str = "chromedriver";
+    const WEB_DRIVER_PROG: &'a str = "http://localhost:";
+    const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+    const STARTUP_WAIT: Duration = Duration::from_secs(10);
+    const STARTUP_POLL: Duration = Duration::from_millis(500);
+
+    pub fn new(project_logger: &'a ProjectLogger, ports: &[u32]) -> Self {
+        if ports.is_empty() {
+            let error_str = "At least one web driver port is required.";
+            project_logger.log_error(error_str);
+            panic!("{error_str}");
+        }
+        Self {
+            project_logger,
+            driver_process: Self::CHROME_PROCESS.to_string(),
+            ports: ports.to_vec(),
+            pid_folder: env::temp_dir(),
+            processes: Mutex::new(HashMap::new()),
+            next_index: AtomicUsize::new(0),
+            health_check_timeout: Self::HEALTH_CHECK_TIMEOUT,
+            startup_wait: Self::STARTUP_WAIT,
+        }
+    }
+
+    pub fn set_driver_process(&mut self, driver_process: &str) {
+        self.driver_process = driver_process.to_string();
+    }
+
+    // The pid of each spawned driver is kept in a file in the folder, so that the drivers left
+    // by a crashed run with the same ports can be found and killed by the next one.
+    pub fn set_pid_folder(&mut self, pid_folder: &Path) {
+        self.pid_folder = pid_folder.to_path_buf();
+    }
+
+    pub fn set_health_check_timeout(&mut self, health_check_timeout: Duration) {
+        self.health_check_timeout = health_check_timeout;
+    }
+
+    pub fn set_startup_wait(&mut self, startup_wait: Duration) {
+        self.startup_wait = startup_wait;
+    }
+
+    pub fn get_ports(&self) -> &[u32] {
+        &self.ports
+    }
+
+    pub fn next_port(&self) -> u32 {
+        let index = self.next_index.fetch_add(1, Ordering::SeqCst);
+        self.ports[index % self.ports.len()]
+    }
+
+    pub fn web_driver_path(port: u32) -> String {
+        format!("{}{port}", Self::WEB_DRIVER_PROG)
+    }
+
+    fn pid_file(&self, port: u32) -> PathBuf {
+        let driver_name = Path::new(&self.driver_process)
+            .file_name()
+            .map_or(self.driver_process.clone(), |name| {
+                name.to_string_lossy().to_string()
+            });
+        self.pid_folder.join(format!("{driver_name}_{port}.pid"))
+    }
+
+    // The pid in the file may have been reused by an unrelated process after a reboot, so it is
+    // only taken as the driver if its command line still carries the driver and the port.
+    fn is_spawned_driver(&self, pid: u32, port: u32) -> bool {
+        Command::new("ps")
+            .args(["-p", &pid.to_string(), "-o", "args="])
+            .output()
+            .map_or(false, |output| {
+                let args = String::from_utf8_lossy(&output.stdout);
+                args.contains(&self.driver_process) && args.contains(&format!("--port={port}"))
+            })
+    }
+
+    // The browsers are started by the driver, so only the children of the driver are killed
+    // instead of every automated browser on the host.
+    fn kill_child_processes(&self, pid: u32) {
+        if let Err(e) = Command::new("pkill")
+            .args(["-P", &pid.to_string()])
+            .status()
+        {
+            let warn_str = format!("Unable to kill the child processes of {pid}. {e}");
+            self.project_logger.log_warn(&warn_str);
+        }
+    }
+
+    fn kill_orphaned_driver(&self, port: u32) {
+        let pid_file = self.pid_file(port);
+        let pid = match fs::read_to_string(&pid_file) {
+            Ok(pid) => pid.trim().parse::<u32>().ok(),
+            Err(_) => return,
+        };
+        if let Some(pid) = pid.filter(|pid| self.is_spawned_driver(*pid, port)) {
+            self.kill_child_processes(pid);
+            if let Err(e) = Command::new("kill").args(["--", &pid.to_string()]).status() {
+                let warn_str = format!("Unable to kill the orphaned process {pid}. {e}");
+                self.project_logger.log_warn(&warn_str);
+            }
+            let debug_str = format!(
+                "Orphaned {} with pid {pid} at port {port} killed.",
+                self.driver_process
+            );
+            self.project_logger.log_debug(&debug_str);
+        }
+        if let Err(e) = fs::remove_file(&pid_file) {
+            let warn_str = format!("Unable to remove the pid file {}. {e}", pid_file.display());
+            self.project_logger.log_warn(&warn_str);
+        }
+    }
+
+    pub fn cleanup_orphaned_processes(&self) {
+        for port in self.ports.iter() {
+            self.kill_orphaned_driver(*port);
+        }
+    }
+
+    fn spawn_driver(&self, port: u32) {
+        let web_driver_port = format!("--port={port}");
+        match Command::new(&self.driver_process)
+            .arg(web_driver_port)
+            .spawn()
+        {
+            Ok(c) => {
+                let debug_str = format!("{} started at port {port}", self.driver_process);
+                self.project_logger.log_debug(&debug_str);
+                if let Err(e) = fs::write(self.pid_file(port), c.id().to_string()) {
+                    let warn_str = format!("Unable to write the pid file of port {port}. {e}");
+                    self.project_logger.log_warn(&warn_str);
+                }
+                self.processes
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(port, c);
+            }
+            Err(e) => {
+                let error_str = format!(
+                    "Unable to start {} at port {port}. {e}",
+                    self.driver_process
+                );
+                self.project_logger.log_error(&error_str);
+                panic!("{}", &error_str);
+            }
+        }
+    }
+
+    fn kill_driver(&self, port: u32) {
+        let process = self
+            .processes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&port);
+        if let Some(mut c) = process {
+            self.kill_child_processes(c.id());
+            fs::remove_file(self.pid_file(port)).unwrap_or_default();
+            match c.kill().and_then(|()| c.wait()) {
+                Ok(_) => {
+                    let debug_str = format!("{} at port {port} killed", self.driver_process);
+                    self.project_logger.log_debug(&debug_str);
+                }
+                Err(e) => {
+                    let warn_str = format!(
+                        "Unable to kill {} at port {port}. It may have exited already. {e}",
+                        self.driver_process
+                    );
+                    self.project_logger.log_warn(&warn_str);
+                }
+            }
+        }
+    }
+
+    pub async fn start(&self) {
+        self.cleanup_orphaned_processes();
+        for port in self.ports.iter() {
+            self.spawn_driver(*port);
+        }
+        for port in self.ports.iter() {
+            if !self.wait_until_healthy(*port).await {
+                let warn_str = format!(
+                    "{} at port {port} is not ready after {:?}.",
+                    self.driver_process, self.startup_wait
+                );
+                self.project_logger.log_warn(&warn_str);
+            }
+        }
+    }
+
+    pub async fn is_healthy(&self, port: u32) -> bool {
+        let status_url = format!("{}/status", Self::web_driver_path(port));
+        let client = match Client::builder().timeout(self.health_check_timeout).build() {
+            Ok(client) => client,
+            Err(e) => {
+                let warn_str = format!("Unable to build the health check client. {e}");
+                self.project_logger.log_warn(&warn_str);
+                return false;
+            }
+        };
+        match client.get(&status_url).send().await {
+            Ok(response) if response.status().is_success() => {
+                response.text().await.map_or(false, |status| {
+                    status.replace(' ', "").contains("\"ready\":true")
+                })
+            }
+            Ok(response) => {
+                let debug_str = format!(
+                    "Health check of web driver at port {port} returned status code {}",
+                    response.status().as_str()
+                );
+                self.project_logger.log_debug(&debug_str);
+                false
+            }
+            Err(e) => {
+                let debug_str = format!("Health check of web driver at port {port} failed. {e}");
+                self.project_logger.log_debug(&debug_str);
+                false
+            }
+        }
+    }
+
+    async fn wait_until_healthy(&self, port: u32) -> bool {
+        let deadline = time_operation::get_deadline(Some(self.startup_wait), &SystemClock);
+        while !time_operation::is_deadline_reached(deadline, &SystemClock) {
+            if self.is_healthy(port).await {
+                return true;
+            }
+            time_operation::async_sleep(Self::STARTUP_POLL).await;
+        }
+        false
+    }
+
+    fn has_exited(&self, port: u32) -> bool {
+        let mut processes = self
+            .processes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match processes.get_mut(&port) {
+            Some(c) => !matches!(c.try_wait(), Ok(None)),
+            None => true,
+        }
+    }
+
+    pub async fn restart_driver(&self, port: u32) -> bool {
+        let warn_str = format!("Restarting {} at port {port}.", self.driver_process);
+        self.project_logger.log_warn(&warn_str);
+        self.kill_driver(port);
+        self.kill_orphaned_driver(port);
+        self.spawn_driver(port);
+        self.wait_until_healthy(port).await
+    }
+
+    pub async fn ensure_healthy(&self, port: u32) -> bool {
+        if !self.has_exited(port) && self.is_healthy(port).await {
+            true
+        } else {
+            self.restart_driver(port).await
+        }
+    }
+
+    pub async fn ensure_all_healthy(&self) -> bool {
+        let mut all_healthy = true;
+        for port in self.ports.iter() {
+            all_healthy &= self.ensure_healthy(*port).await;
+        }
+        all_healthy
+    }
+
+    pub async fn next_healthy_port(&self) -> u32 {
+        let port = self.next_port();
+        if !self.ensure_healthy(port).await {
+            let error_str = format!(
+                "{} at port {port} is not healthy after restart.",
+                self.driver_process
+            );
+            self.project_logger.log_error(&error_str);
+        }
+        port
+    }
+
+    pub fn shutdown(&self) {
+        for port in self.ports.iter() {
+            self.kill_driver(*port);
+        }
+    }
+}
+
+impl<'a> Drop for WebDriverManager<'a> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_next_port() {
+        let project_logger = ProjectLogger::new_logger(Path::new("."), "test_web_driver_manager");
+        let web_driver_manager = WebDriverManager::new(&project_logger, &[4444, 4445]);
+        assert_eq!(web_driver_manager.next_port(), 4444);
+        assert_eq!(web_driver_manager.next_port(), 4445);
+        assert_eq!(web_driver_manager.next_port(), 4444);
+        assert_eq!(
+            WebDriverManager::web_driver_path(4445),
+            "http://localhost:4445"
+        );
+    }
+
+    #[test]
+    fn test_cleanup_orphaned_processes() {
+        let project_logger =
+            ProjectLogger::new_logger(Path::new("."), "test_web_driver_manager_cleanup");
+        let mut web_driver_manager = WebDriverManager::new(&project_logger, &[4446]);
+        let pid_folder = env::temp_dir().join("test_web_driver_manager_cleanup");
+        fs::create_dir_all(&pid_folder).unwrap();
+        web_driver_manager.set_pid_folder(&pid_folder);
+        let pid_file = web_driver_manager.pid_file(4446);
+        assert_eq!(pid_file, pid_folder.join("chromedriver_4446.pid"));
+        // The pid of the test itself is not a driver at the port, so it must be left alive.
+        fs::write(&pid_file, std::process::id().to_string()).unwrap();
+        web_driver_manager.cleanup_orphaned_processes();
+        assert!(!pid_file.exists());
+        fs::remove_dir_all(&pid_folder).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::{Mutex, PoisonError};
+use thirtyfour::WebDriver;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::logger::ProjectLogger;
+
+#[derive(Debug)]
+pub struct WebDriverSession {
+    pub web_driver: WebDriver,
+    page_count: u32,
+}
+
+impl WebDriverSession {
+    pub fn new(web_driver: WebDriver) -> Self {
+        Self {
+            web_driver,
+            page_count: 0,
+        }
+    }
+
+    pub fn get_page_count(&self) -> u32 {
+        self.page_count
+    }
+
+    pub fn record_page(&mut self) {
+        self.page_count += 1;
+    }
+}
+
+#[derive(Debug)]
+pub struct WebDriverPool<'a> {
+    project_logger: &'a ProjectLogger,
+    pool_size: usize,
+    max_pages_per_session: u32,
+    idle_sessions: Mutex<Vec<WebDriverSession>>,
+    semaphore: Semaphore,
+}
+
+impl<'a> WebDriverPool<'a> {
+    const MAX_PAGES_PER_SESSION: u32 = 50;
+
+    pub fn new(project_logger: &'a ProjectLogger, pool_size: usize) -> Self {
+        if pool_size == 0 {
+            let error_str = "The web driver pool size must be at least one.";
+            project_logger.log_error(error_str);
+            panic!("{error_str}");
+        }
+        Self {
+            project_logger,
+            pool_size,
+            max_pages_per_session: Self::MAX_PAGES_PER_SESSION,
+            idle_sessions: Mutex::new(Vec::with_capacity(pool_size)),
+            semaphore: Semaphore::new(pool_size),
+        }
+    }
+
+    pub fn set_max_pages_per_session(&mut self, max_pages_per_session: u32) {
+        self.max_pages_per_session = max_pages_per_session;
+    }
+
+    pub fn get_pool_size(&self) -> usize {
+        self.pool_size
+    }
+
+    pub fn num_idle_sessions(&self) -> usize {
+        self.idle_sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    // The permit bounds the number of live sessions. An idle session is handed out if there is one,
+    // otherwise the caller should open a new session and release it back afterwards.
+    pub async fn acquire(&self) -> (SemaphorePermit<'_>, Option<WebDriverSession>) {
+        let permit = match self.semaphore.acquire().await {
+            Ok(permit) => permit,
+            Err(e) => {
+                let error_str =
+                    format!("Unable to acquire a session from the web driver pool. {e}");
+                self.project_logger.log_error(&error_str);
+                panic!("{error_str}");
+            }
+        };
+        let session = self
+            .idle_sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop();
+        (permit, session)
+    }
+
+    // Returns the session back to the caller if it has reached the page limit and should be quitted.
+    pub fn release(&self, session: WebDriverSession) -> Option<WebDriverSession> {
+        if session.page_count >= self.max_pages_per_session {
+            let debug_str = format!(
+                "Web driver session recycled after {} pages.",
+                session.page_count
+            );
+            self.project_logger.log_debug(&debug_str);
+            Some(session)
+        } else {
+            self.idle_sessions
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(session);
+            None
+        }
+    }
+
+    pub fn drain(&self) -> Vec<WebDriverSession> {
+        self.idle_sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain(..)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn test_pool_permit() {
+        let project_logger = ProjectLogger::new_logger(Path::new("."), "test_web_driver_pool");
+        let web_driver_pool = WebDriverPool::new(&project_logger, 2);
+        let (permit_0, session_0) = web_driver_pool.acquire().await;
+        let (permit_1, session_1) = web_driver_pool.acquire().await;
+        assert!(session_0.is_none() && session_1.is_none());
+        assert!(web_driver_pool.semaphore.try_acquire().is_err());
+        drop(permit_0);
+        assert!(web_driver_pool.semaphore.try_acquire().is_ok());
+        drop(permit_1);
+        assert_eq!(web_driver_pool.num_idle_sessions(), 0);
+    }
+}
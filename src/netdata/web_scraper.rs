@@ -1,19 +1,32 @@
-use polars::io::SerReader;
-use polars::prelude::{CsvReadOptions, DataFrame};
-use reqwest::blocking::{Client, RequestBuilder, Response};
-use reqwest::{Result, Url};
-use std::io::Cursor;
+use chrono::Utc;
+use polars::prelude::DataFrame;
+use reqwest::blocking::{Client, Request, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, LOCATION};
+use reqwest::{Result, StatusCode, Url};
 use std::path::Path;
 use std::process::{Child, Command};
 use std::time::Duration;
 use thirtyfour_sync::error::WebDriverResult;
-use thirtyfour_sync::{ChromeCapabilities, WebDriver, WebDriverCommands};
+use thirtyfour_sync::{
+    ChromeCapabilities, EdgeCapabilities, FirefoxCapabilities, WebDriver, WebDriverCommands,
+};
 use tqdm;
 
-use super::data_struct::{BrowseSetting, RequestSetting, ResponseCheckResult, UrlFile};
+use super::browser_kind::{BlockingBrowserCapabilities, BrowserKind};
+use super::data_struct::{
+    BrowseSetting, ClientOptions, RedirectChain, RedirectPolicy, RequestSetting, RequestSpec,
+    ResponseCheckResult, ScrapeFailure, UrlFile,
+};
+use super::google_sheet::{self, GoogleSheetKey, GoogleSheetReadOptions};
+use super::proxy_endpoint::ProxyEndpoint;
+use super::response_validator::ResponseValidator;
+use super::warc_writer::{WarcExchange, WarcWriter};
+use crate::dry_run::DryRun;
+use crate::error::ResultExt;
 use crate::file_io::FileIO;
 use crate::logger::ProjectLogger;
 use crate::slack_messenger::SlackMessenger;
+use crate::time_operation::SystemClock;
 use crate::{function_name, time_operation, utilities_function};
 
 #[derive(Debug)]
@@ -28,8 +41,13 @@ pub struct WebScraper<'a> {
     web_driver_port: u32,
     client: Option<Client>,
     web_driver: Option<WebDriver>,
-    browser: Option<ChromeCapabilities>,
+    browser_kind: BrowserKind,
+    browser: Option<BlockingBrowserCapabilities>,
     chrome_process: Option<Child>,
+    warc_writer: Option<&'a WarcWriter<'a>>,
+    redirect_policy: RedirectPolicy,
+    last_redirect: Option<RedirectChain>,
+    client_options: ClientOptions,
 }
 
 impl<'a> WebScraper<'a> {
@@ -38,11 +56,8 @@ impl<'a> WebScraper<'a> {
     const CONSECUTIVE_SLEEP: (Duration, Duration) =
         (Duration::from_secs(0), Duration::from_secs(30));
     const TIMEOUT: Duration = Duration::from_secs(120);
-    const GOOGLE_SHEET_URL: &'a str = "https://docs.google.com/spreadsheets/d/";
-    const GOOGLE_SHEET_REPLACE_TOKEN: (&'a str, &'a str) = ("edit#gid=", "export?format=csv&gid=");
     const WEB_DRIVER_PORT: u32 = 4444;
     const WEB_DRIVER_PROG: &'a str = "http://localhost:";
-    const CHROME_PROCESS: &'a str = "chromedriver";
 
     pub fn new(
         project_logger: &'a ProjectLogger,
@@ -60,8 +75,13 @@ impl<'a> WebScraper<'a> {
             web_driver_port: Self::WEB_DRIVER_PORT,
             client: None,
             web_driver: None,
+            browser_kind: BrowserKind::default(),
             browser: None,
             chrome_process: None,
+            warc_writer: None,
+            redirect_policy: RedirectPolicy::default(),
+            last_redirect: None,
+            client_options: ClientOptions::default(),
         }
     }
 
@@ -89,114 +109,239 @@ impl<'a> WebScraper<'a> {
         self.client = Some(client);
     }
 
-    pub fn set_browser(&mut self, browser: ChromeCapabilities) {
-        self.browser = Some(browser);
+    // Replaces the blocking client with one sending the requests through the proxy. The scheme of
+    // the endpoint is kept, so a socks5h proxy resolves the host names remotely.
+    pub fn set_client_proxy(&mut self, proxy_endpoint: &ProxyEndpoint) -> crate::error::Result<()> {
+        let client = proxy_endpoint
+            .get_reqwest_proxy()
+            .and_then(|proxy| {
+                self.client_options
+                    .apply_to_blocking_builder(Client::builder())
+                    .proxy(proxy)
+                    .timeout(self.timeout)
+                    .redirect(self.redirect_policy.to_reqwest_policy())
+                    .build()
+            })
+            .map_err(|e| {
+                let error_str = format!(
+                    "Unable to set the proxy {}. {e}",
+                    proxy_endpoint.get_address()
+                );
+                self.project_logger.log_error(&error_str);
+                e
+            })?;
+        self.client = Some(client);
+        Ok(())
+    }
+
+    pub fn set_browser_kind(&mut self, browser_kind: BrowserKind) {
+        self.browser_kind = browser_kind;
+    }
+
+    pub fn set_browser(&mut self, browser: impl Into<BlockingBrowserCapabilities>) {
+        self.browser = Some(browser.into());
+    }
+
+    pub fn set_warc_writer(&mut self, warc_writer: &'a WarcWriter<'a>) {
+        self.warc_writer = Some(warc_writer);
+    }
+
+    // Applies to the default blocking client, so set it before the first request.
+    pub fn set_client_options(&mut self, client_options: ClientOptions) {
+        self.client_options = client_options;
+    }
+
+    // Applies to the default blocking client, so set it before the first request.
+    pub fn set_redirect_policy(&mut self, redirect_policy: RedirectPolicy) {
+        self.redirect_policy = redirect_policy;
+    }
+
+    // The redirects of the last response. The client follows them itself, so only the final
+    // url and the location of a redirect stopped by the policy are known.
+    pub fn get_last_redirect(&self) -> Option<&RedirectChain> {
+        self.last_redirect.as_ref()
     }
 
-    pub fn get_default_blocking_client(&mut self) -> Client {
+    fn record_redirect(&mut self, url: &Url, response: &Response) {
+        let mut redirect_chain = RedirectChain::new(url);
+        if response.url() != url {
+            redirect_chain.follow(response.url().clone());
+        }
+        if response.status().is_redirection() {
+            redirect_chain.stopped_at = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| response.url().join(location).ok());
+        }
+        self.last_redirect = Some(redirect_chain);
+    }
+
+    pub fn get_default_blocking_client(&mut self) -> crate::error::Result<Client> {
+        Ok(self.build_blocking_client()?)
+    }
+
+    // Returns the error of the last trial, so the requests can surface it as a reqwest error.
+    fn build_blocking_client(&mut self) -> Result<Client> {
         let mut counter = 0;
-        while counter < self.num_retry {
-            match Client::builder().timeout(self.timeout).build() {
+        loop {
+            match self
+                .client_options
+                .apply_to_blocking_builder(Client::builder())
+                .timeout(self.timeout)
+                .redirect(self.redirect_policy.to_reqwest_policy())
+                .build()
+            {
                 Ok(c) => {
                     self.client = Some(c.clone());
-                    return c;
+                    return Ok(c);
                 }
                 Err(e) => {
                     counter += 1;
+                    if counter >= self.num_retry {
+                        let error_str = format!("Fail to build connection client. {e}");
+                        let calling_func = utilities_function::function_name!(true);
+                        self.project_logger.log_error(&error_str);
+                        self.slack_messenger
+                            .retry_send_message(calling_func, &error_str, false);
+                        return Err(e);
+                    }
                     let warn_str =
                         format!("Unable to build connection client after trial {counter}. {e}");
                     self.project_logger.log_warn(&warn_str);
                 }
             };
         }
-        let error_str = "Fail to build connection client".to_string();
-        let calling_func = utilities_function::function_name!(true);
-        self.project_logger.log_error(&error_str);
-        self.slack_messenger
-            .retry_send_message(calling_func, &error_str, false);
-        panic!("{}", &error_str);
     }
 
     pub fn get_default_browser(&mut self) -> ChromeCapabilities {
+        let browser = self.get_default_chrome_browser();
+        self.browser = Some(browser.clone().into());
+        browser
+    }
+
+    // The default capabilities of the browser kind of the scraper, which the web driver is set
+    // with unless a browser is given.
+    pub fn get_default_capabilities(&mut self) -> BlockingBrowserCapabilities {
+        let browser: BlockingBrowserCapabilities = match self.browser_kind {
+            BrowserKind::Chrome => self.get_default_chrome_browser().into(),
+            BrowserKind::Firefox => self.get_default_firefox_browser().into(),
+            BrowserKind::Edge => self.get_default_edge_browser().into(),
+        };
+        self.browser = Some(browser.clone());
+        browser
+    }
+
+    // An option failing to serialize is logged and left out, as the browser still runs without it.
+    fn get_default_chrome_browser(&self) -> ChromeCapabilities {
         let mut browser = ChromeCapabilities::new();
         if let Err(e) = browser.set_headless() {
             let error_str = format!("Unable to set headless for the chrome browser, {e}");
             self.project_logger.log_error(&error_str);
-            panic!("{}", &error_str);
         };
-        for arg in [
-            "--disable-dev-shm-usage",
-            "--disable-gpu",
-            "--window-size=1920,1080",
-            "disable-blink-features=AutomationControlled",
-        ]
-        .iter()
-        {
+        for arg in BrowserKind::Chrome.window_args().iter() {
             if let Err(e) = browser.add_chrome_arg(arg) {
                 let error_str = format!("Unable to set the argument {arg}, {e}");
                 self.project_logger.log_error(&error_str);
-                panic!("{}", &error_str);
             };
         }
-        self.browser = Some(browser.clone());
         browser
     }
 
-    pub fn turn_on_chrome_process(&mut self) {
+    fn get_default_firefox_browser(&self) -> FirefoxCapabilities {
+        let mut browser = FirefoxCapabilities::new();
+        if let Err(e) = browser.set_headless() {
+            let error_str = format!("Unable to set headless for the firefox browser, {e}");
+            self.project_logger.log_error(&error_str);
+        };
+        for arg in BrowserKind::Firefox.window_args().iter() {
+            if let Err(e) = browser.add_firefox_arg(arg) {
+                let error_str = format!("Unable to set the argument {arg}, {e}");
+                self.project_logger.log_error(&error_str);
+            };
+        }
+        browser
+    }
+
+    fn get_default_edge_browser(&self) -> EdgeCapabilities {
+        let mut browser = EdgeCapabilities::new();
+        if let Err(e) = browser.set_headless() {
+            let error_str = format!("Unable to set headless for the edge browser, {e}");
+            self.project_logger.log_error(&error_str);
+        };
+        for arg in BrowserKind::Edge.window_args().iter() {
+            if let Err(e) = browser.add_edge_arg(arg) {
+                let error_str = format!("Unable to set the argument {arg}, {e}");
+                self.project_logger.log_error(&error_str);
+            };
+        }
+        browser
+    }
+
+    pub fn turn_on_chrome_process(&mut self) -> crate::error::Result<()> {
         if self.chrome_process.is_none() {
             let web_driver_port = format!("--port={}", self.web_driver_port);
-            match Command::new(Self::CHROME_PROCESS)
-                .arg(web_driver_port)
-                .spawn()
-            {
+            let driver_process = self.browser_kind.driver_process();
+            match Command::new(driver_process).arg(web_driver_port).spawn() {
                 Ok(c) => {
                     self.chrome_process = Some(c);
                 }
                 Err(e) => {
-                    let error_str = format!("Unable to start chromedriver. {e}");
+                    let error_str = format!("Unable to start {driver_process}. {e}");
                     self.project_logger.log_error(&error_str);
-                    panic!("{}", &error_str);
+                    return Err(e.into());
                 }
             }
         }
+        Ok(())
     }
 
-    pub fn kill_chrome_process(&mut self) {
+    pub fn kill_chrome_process(&mut self) -> crate::error::Result<()> {
         let chrome_process = self.chrome_process.take();
         if let Some(mut c) = chrome_process {
             match c.kill() {
                 Ok(()) => {
-                    let debug_str = format!("Chromedriver at port {} killed", self.web_driver_port);
+                    let debug_str = format!(
+                        "{} at port {} killed",
+                        self.browser_kind.driver_process(),
+                        self.web_driver_port
+                    );
                     self.project_logger.log_debug(&debug_str);
                     self.chrome_process = None;
                 }
                 Err(e) => {
                     let error_str = format!(
-                        "Unable to kill chromedriver at port {}. {e}",
+                        "Unable to kill {} at port {}. {e}",
+                        self.browser_kind.driver_process(),
                         self.web_driver_port
                     );
                     self.project_logger.log_error(&error_str);
-                    panic!("{}", &error_str);
+                    return Err(e.into());
                 }
             }
         }
+        Ok(())
     }
 
     fn web_driver_path(&self) -> String {
         format!("{}{}", &Self::WEB_DRIVER_PROG, self.web_driver_port)
     }
 
-    pub fn set_web_driver(&mut self) {
+    #[allow(clippy::result_large_err)]
+    pub fn set_web_driver(&mut self) -> WebDriverResult<()> {
         let server_url = self.web_driver_path();
         if self.browser.is_none() {
-            self.get_default_browser();
+            self.get_default_capabilities();
         }
         match WebDriver::new_with_timeout(&server_url, &self.browser, Some(self.timeout)) {
-            Ok(w_d) => self.web_driver = Some(w_d),
+            Ok(w_d) => {
+                self.web_driver = Some(w_d);
+                Ok(())
+            }
             Err(e) => {
                 let error_str = format!("Unable to set the web driver. {e}");
                 self.project_logger.log_error(&error_str);
-                panic!("{}", &error_str);
+                Err(e)
             }
         }
     }
@@ -205,22 +350,26 @@ impl<'a> WebScraper<'a> {
         self.web_driver.as_mut()
     }
 
-    pub fn restart_web_driver(&mut self) {
-        if let Some(w_d) = &self.web_driver {
+    // The driver is dropped when it fails to close, so the next browse sets up a new one.
+    #[allow(clippy::result_large_err)]
+    pub fn restart_web_driver(&mut self) -> WebDriverResult<()> {
+        if let Some(w_d) = self.web_driver.take() {
             match w_d.close() {
-                Ok(()) => self.set_web_driver(),
+                Ok(()) => self.set_web_driver()?,
                 Err(e) => {
                     let error_str = format!(
                         "Unable to quit web driver. Please check and clear the process. {e}"
                     );
                     self.project_logger.log_error(&error_str);
-                    panic! {"{}", &error_str};
+                    return Err(e);
                 }
             }
         }
+        Ok(())
     }
 
-    pub fn close_web_driver(&mut self) {
+    #[allow(clippy::result_large_err)]
+    pub fn close_web_driver(&mut self) -> WebDriverResult<()> {
         let web_driver = self.web_driver.take();
         if let Some(w_d) = web_driver {
             match w_d.quit() {
@@ -233,19 +382,17 @@ impl<'a> WebScraper<'a> {
                         "Unable to quit web driver. Please check and clear the process. {e}"
                     );
                     self.project_logger.log_error(&error_str);
-                    panic! {"{}", &error_str};
+                    return Err(e);
                 }
             }
         }
+        Ok(())
     }
 
     fn get_request_simple(&mut self, url: Url) -> Result<Response> {
         match &self.client {
             Some(c) => c.get(url).send(),
-            None => {
-                self.get_default_blocking_client();
-                self.get_request_simple(url)
-            }
+            None => self.build_blocking_client()?.get(url).send(),
         }
     }
 
@@ -266,6 +413,36 @@ impl<'a> WebScraper<'a> {
         }
     }
 
+    // The request is only cloned when archiving is on.
+    fn get_warc_request(&self, request_builder: Option<&RequestBuilder>) -> Option<Request> {
+        self.warc_writer?;
+        request_builder
+            .and_then(|request_builder| request_builder.try_clone())
+            .and_then(|request_builder| request_builder.build().ok())
+    }
+
+    fn archive_and_check(
+        &self,
+        warc_response: Option<(Request, StatusCode, HeaderMap)>,
+        response_text: &str,
+        check_func: &dyn ResponseValidator,
+    ) -> ResponseCheckResult {
+        if let (Some(warc_writer), Some((request, status, response_headers))) =
+            (self.warc_writer, warc_response)
+        {
+            warc_writer.archive(&WarcExchange {
+                url: request.url(),
+                method: request.method(),
+                request_headers: request.headers(),
+                status,
+                response_headers: &response_headers,
+                body: response_text.as_bytes(),
+                fetched_at: Utc::now(),
+            });
+        }
+        check_func.check(response_text)
+    }
+
     pub fn null_check_func(response: &str) -> ResponseCheckResult {
         ResponseCheckResult::Ok(response.to_string())
     }
@@ -273,15 +450,23 @@ impl<'a> WebScraper<'a> {
     pub fn retry_request_simple(
         &mut self,
         url: &Url,
-        check_func: fn(&str) -> ResponseCheckResult,
+        check_func: &dyn ResponseValidator,
     ) -> ResponseCheckResult {
         let mut counter = 0;
         while counter < self.num_retry {
             match self.get_request_simple(url.clone()) {
                 Ok(response) => {
+                    self.record_redirect(url, &response);
+                    let warc_response = self
+                        .get_warc_request(self.client.as_ref().map(|c| c.get(url.clone())).as_ref())
+                        .map(|request| (request, response.status(), response.headers().clone()));
                     if response.status().is_success() || response.status().is_redirection() {
                         match response.text() {
-                            Ok(response_text) => match check_func(&response_text) {
+                            Ok(response_text) => match self.archive_and_check(
+                                warc_response,
+                                &response_text,
+                                check_func,
+                            ) {
                                 ResponseCheckResult::Ok(response_text) => {
                                     let debug_str = format!("Request {} loaded.", url.as_str());
                                     self.project_logger.log_debug(&debug_str);
@@ -295,12 +480,21 @@ impl<'a> WebScraper<'a> {
                                     self.project_logger.log_warn(&warn_str);
                                     counter += 1
                                 }
-                                ResponseCheckResult::ErrTerminate(e) => {
+                                ResponseCheckResult::ErrTerminate(e)
+                                | ResponseCheckResult::TooLarge(e) => {
                                     let warn_str =
                                         format!("Terminate to load the page {}. {e}", url.as_str());
                                     self.project_logger.log_warn(&warn_str);
                                     return ResponseCheckResult::ErrTerminate(e);
                                 }
+                                ResponseCheckResult::Blocked(e) => {
+                                    let warn_str = format!(
+                                        "Blocked when loading the page {}. {e}",
+                                        url.as_str()
+                                    );
+                                    self.project_logger.log_warn(&warn_str);
+                                    return ResponseCheckResult::Blocked(e);
+                                }
                             },
                             Err(e) => {
                                 let warn_str = format!("Unable to decode the response text. {e}");
@@ -335,22 +529,134 @@ impl<'a> WebScraper<'a> {
         }
         let error_str = format!("Fail to load the page {}.", url.as_str());
         self.project_logger.log_error(&error_str);
-        ResponseCheckResult::ErrTerminate(error_str)
+        ResponseCheckResult::ErrTerminate(ScrapeFailure::LoadFailed(error_str))
     }
 
     pub fn retry_request_from_builder(
         &mut self,
         request_builder: &RequestBuilder,
         url: &'a Url,
-        check_func: fn(&str) -> ResponseCheckResult,
+        check_func: &dyn ResponseValidator,
     ) -> ResponseCheckResult {
         let mut counter = 0;
         while counter < self.num_retry {
             match self.get_request_from_builder(request_builder, url.clone()) {
                 Ok(response) => {
+                    self.record_redirect(url, &response);
+                    let warc_response = self
+                        .get_warc_request(Some(request_builder))
+                        .map(|request| (request, response.status(), response.headers().clone()));
+                    if response.status().is_success() || response.status().is_redirection() {
+                        match response.text() {
+                            Ok(response_text) => match self.archive_and_check(
+                                warc_response,
+                                &response_text,
+                                check_func,
+                            ) {
+                                ResponseCheckResult::Ok(response_text) => {
+                                    let debug_str = format!("Request {} loaded.", url.as_str());
+                                    self.project_logger.log_debug(&debug_str);
+                                    return ResponseCheckResult::Ok(response_text);
+                                }
+                                ResponseCheckResult::ErrContinue(e) => {
+                                    let warn_str = format!(
+                                        "Checking of the response failed for {}. {e}",
+                                        url.as_str()
+                                    );
+                                    self.project_logger.log_warn(&warn_str);
+                                    counter += 1;
+                                    time_operation::sleep(self.retry_sleep);
+                                }
+                                ResponseCheckResult::ErrTerminate(e)
+                                | ResponseCheckResult::TooLarge(e) => {
+                                    let warn_str =
+                                        format!("Terminate to load the page {}. {e}", url.as_str());
+                                    self.project_logger.log_warn(&warn_str);
+                                    return ResponseCheckResult::ErrTerminate(e);
+                                }
+                                ResponseCheckResult::Blocked(e) => {
+                                    let warn_str = format!(
+                                        "Blocked when loading the page {}. {e}",
+                                        url.as_str()
+                                    );
+                                    self.project_logger.log_warn(&warn_str);
+                                    return ResponseCheckResult::Blocked(e);
+                                }
+                            },
+                            Err(e) => {
+                                let warn_str = format!("Unable to decode the response text. {e}");
+                                self.project_logger.log_warn(&warn_str);
+                                counter += 1
+                            }
+                        }
+                    } else if response.status().is_server_error() {
+                        let warn_str = format!(
+                            "Fail in loading the page {}. Server return status code {}",
+                            url.as_str(),
+                            response.status().as_str()
+                        );
+                        self.project_logger.log_warn(&warn_str);
+                        counter += 1;
+                        time_operation::sleep(self.retry_sleep);
+                    } else {
+                        let warn_str = format!(
+                            "Terminate to load the page {}. Server return status code {}",
+                            url.as_str(),
+                            response.status().as_str()
+                        );
+                        self.project_logger.log_warn(&warn_str);
+                        counter += 1;
+                        time_operation::sleep(self.retry_sleep);
+                    }
+                }
+                Err(e) => {
+                    let warn_str = format!("Unable to load the page {}. {e}", url.as_str());
+                    self.project_logger.log_warn(&warn_str);
+                    counter += 1;
+                    time_operation::sleep(self.retry_sleep);
+                }
+            }
+        }
+        let error_str = format!("Fail to load the page {}.", url.as_str());
+        self.project_logger.log_error(&error_str);
+        ResponseCheckResult::ErrTerminate(ScrapeFailure::LoadFailed(error_str))
+    }
+
+    // A fresh builder is made for every attempt, so the builders need not be cloneable and can
+    // carry any verb, headers and body.
+    pub fn retry_request_with_builder_func(
+        &mut self,
+        url: &Url,
+        request_builder_func: &dyn Fn(&Client, Url) -> RequestBuilder,
+        check_func: &dyn ResponseValidator,
+    ) -> ResponseCheckResult {
+        let client = match &self.client {
+            Some(c) => c.clone(),
+            None => match self.get_default_blocking_client() {
+                Ok(c) => c,
+                Err(e) => {
+                    return ResponseCheckResult::ErrTerminate(ScrapeFailure::LoadFailed(
+                        e.to_string(),
+                    ))
+                }
+            },
+        };
+        let mut counter = 0;
+        while counter < self.num_retry {
+            let request_builder = request_builder_func(&client, url.clone());
+            let warc_request = self.get_warc_request(Some(&request_builder));
+            match request_builder.send() {
+                Ok(response) => {
+                    self.record_redirect(url, &response);
+                    let warc_response = warc_request
+                        .map(|request| (request, response.status(), response.headers().clone()));
                     if response.status().is_success() || response.status().is_redirection() {
                         match response.text() {
-                            Ok(response_text) => match check_func(&response_text) {
+                            Ok(response_text) => match self.archive_and_check(
+                                warc_response,
+                                &response_text,
+                                check_func,
+                            ) {
                                 ResponseCheckResult::Ok(response_text) => {
                                     let debug_str = format!("Request {} loaded.", url.as_str());
                                     self.project_logger.log_debug(&debug_str);
@@ -365,12 +671,21 @@ impl<'a> WebScraper<'a> {
                                     counter += 1;
                                     time_operation::sleep(self.retry_sleep);
                                 }
-                                ResponseCheckResult::ErrTerminate(e) => {
+                                ResponseCheckResult::ErrTerminate(e)
+                                | ResponseCheckResult::TooLarge(e) => {
                                     let warn_str =
                                         format!("Terminate to load the page {}. {e}", url.as_str());
                                     self.project_logger.log_warn(&warn_str);
                                     return ResponseCheckResult::ErrTerminate(e);
                                 }
+                                ResponseCheckResult::Blocked(e) => {
+                                    let warn_str = format!(
+                                        "Blocked when loading the page {}. {e}",
+                                        url.as_str()
+                                    );
+                                    self.project_logger.log_warn(&warn_str);
+                                    return ResponseCheckResult::Blocked(e);
+                                }
                             },
                             Err(e) => {
                                 let warn_str = format!("Unable to decode the response text. {e}");
@@ -408,41 +723,72 @@ impl<'a> WebScraper<'a> {
         }
         let error_str = format!("Fail to load the page {}.", url.as_str());
         self.project_logger.log_error(&error_str);
-        ResponseCheckResult::ErrTerminate(error_str)
+        ResponseCheckResult::ErrTerminate(ScrapeFailure::LoadFailed(error_str))
+    }
+
+    pub fn retry_request_with_spec(
+        &mut self,
+        url: &Url,
+        request_spec: &RequestSpec,
+        check_func: &dyn ResponseValidator,
+    ) -> ResponseCheckResult {
+        self.retry_request_with_builder_func(
+            url,
+            &|client, url| request_spec.to_blocking_builder(client, url),
+            check_func,
+        )
     }
 
-    pub fn save_request_content(&self, folder_path: &Path, file: &str, content: &str) {
+    // The file name is sanitized, as it is often taken from the url, and its sub folders are
+    // created if any.
+    pub fn save_request_content(
+        &self,
+        folder_path: &Path,
+        file: &str,
+        content: &str,
+    ) -> crate::error::Result<()> {
+        let file = &FileIO::sanitize_file_path(file);
         self.file_io
-            .write_string_to_file(folder_path, file, content)
-            .unwrap_or_else(|e| {
+            .create_parent_folder(folder_path, file)
+            .and_then(|()| {
+                self.file_io
+                    .write_string_to_file(folder_path, file, content)
+            })
+            .with_context(|| format!("Unable to save file {file} in {}", folder_path.display()))
+            .map_err(|e| {
                 let function_name = function_name!(true);
-                let error_msg = format!(
-                    "Unable to save file {file} in {}. {e}",
-                    folder_path.display()
-                );
                 self.slack_messenger
-                    .retry_send_message(function_name, &error_msg, true);
-                panic!("{error_msg}")
-            });
+                    .retry_send_message(function_name, &e.to_string(), true);
+                e
+            })
     }
 
     pub fn multiple_requests(
         &mut self,
         url_file_list: &'a [UrlFile],
         folder_path: &Path,
-        check_func: fn(&str) -> ResponseCheckResult,
+        check_func: &dyn ResponseValidator,
         request_setting: RequestSetting,
     ) -> Vec<UrlFile> {
+        let deadline = request_setting.get_deadline(&SystemClock);
+        let _dry_run_guard = DryRun::enable_if(request_setting.dry_run);
         let mut fail_list = Vec::new();
-        for url_file in tqdm::tqdm(url_file_list.iter()) {
-            if let ResponseCheckResult::Ok(content) =
-                self.retry_request_simple(&url_file.url, check_func)
-            {
-                self.save_request_content(folder_path, &url_file.file_name, &content);
-            } else {
+        let mut deadline_list = Vec::new();
+        for (index, url_file) in tqdm::tqdm(url_file_list.iter().enumerate()) {
+            if time_operation::is_deadline_reached(deadline, &SystemClock) {
+                deadline_list = url_file_list[index..].to_vec();
+                break;
+            }
+            let saved = match self.retry_request_simple(&url_file.url, check_func) {
+                ResponseCheckResult::Ok(content) => self
+                    .save_request_content(folder_path, &url_file.file_name, &content)
+                    .is_ok(),
+                _ => false,
+            };
+            if !saved {
                 fail_list.push(url_file.clone())
             }
-            time_operation::random_sleep(self.consecutive_sleep);
+            time_operation::random_sleep(request_setting.get_sleep_range(self.consecutive_sleep));
         }
         if !fail_list.is_empty() {
             let fail_url_list = format!(
@@ -466,6 +812,14 @@ impl<'a> WebScraper<'a> {
                 request_setting.log_only,
             );
         }
+        self.notify_deadline_reached(
+            &deadline_list,
+            url_file_list.len(),
+            request_setting.max_total_duration,
+            request_setting.calling_func,
+            request_setting.log_only,
+        );
+        fail_list.extend(deadline_list);
         fail_list
     }
 
@@ -474,21 +828,34 @@ impl<'a> WebScraper<'a> {
         url_file_list: &'a [UrlFile],
         request_builder_list: &[RequestBuilder],
         folder_path: &Path,
-        check_func: fn(&str) -> ResponseCheckResult,
+        check_func: &dyn ResponseValidator,
         request_setting: RequestSetting,
     ) -> Vec<UrlFile> {
+        let deadline = request_setting.get_deadline(&SystemClock);
+        let _dry_run_guard = DryRun::enable_if(request_setting.dry_run);
         let mut fail_list = Vec::new();
-        for (url_file, request_builder) in
-            tqdm::tqdm(url_file_list.iter().zip(request_builder_list.iter()))
-        {
-            if let ResponseCheckResult::Ok(content) =
-                self.retry_request_from_builder(request_builder, &url_file.url, check_func)
-            {
-                self.save_request_content(folder_path, &url_file.file_name, &content);
-            } else {
+        let mut deadline_list = Vec::new();
+        for (index, (url_file, request_builder)) in tqdm::tqdm(
+            url_file_list
+                .iter()
+                .zip(request_builder_list.iter())
+                .enumerate(),
+        ) {
+            if time_operation::is_deadline_reached(deadline, &SystemClock) {
+                deadline_list = url_file_list[index..].to_vec();
+                break;
+            }
+            let saved =
+                match self.retry_request_from_builder(request_builder, &url_file.url, check_func) {
+                    ResponseCheckResult::Ok(content) => self
+                        .save_request_content(folder_path, &url_file.file_name, &content)
+                        .is_ok(),
+                    _ => false,
+                };
+            if !saved {
                 fail_list.push(url_file.clone())
             }
-            time_operation::random_sleep(self.consecutive_sleep);
+            time_operation::random_sleep(request_setting.get_sleep_range(self.consecutive_sleep));
         }
         if !fail_list.is_empty() {
             let fail_url_list = format!(
@@ -512,35 +879,141 @@ impl<'a> WebScraper<'a> {
                 request_setting.log_only,
             );
         }
+        self.notify_deadline_reached(
+            &deadline_list,
+            url_file_list.len(),
+            request_setting.max_total_duration,
+            request_setting.calling_func,
+            request_setting.log_only,
+        );
+        fail_list.extend(deadline_list);
         fail_list
     }
 
-    fn url_from_google_sheet_link(google_sheet_key: &str) -> Url {
-        let csv_link = format!(
-            "{}{}",
-            Self::GOOGLE_SHEET_URL,
-            google_sheet_key.replace(
-                Self::GOOGLE_SHEET_REPLACE_TOKEN.0,
-                Self::GOOGLE_SHEET_REPLACE_TOKEN.1,
-            )
+    pub fn multiple_requests_with_spec(
+        &mut self,
+        url_file_list: &'a [UrlFile],
+        request_spec_list: &[RequestSpec],
+        folder_path: &Path,
+        check_func: &dyn ResponseValidator,
+        request_setting: RequestSetting,
+    ) -> Vec<UrlFile> {
+        let deadline = request_setting.get_deadline(&SystemClock);
+        let _dry_run_guard = DryRun::enable_if(request_setting.dry_run);
+        let mut fail_list = Vec::new();
+        let mut deadline_list = Vec::new();
+        for (index, (url_file, request_spec)) in tqdm::tqdm(
+            url_file_list
+                .iter()
+                .zip(request_spec_list.iter())
+                .enumerate(),
+        ) {
+            if time_operation::is_deadline_reached(deadline, &SystemClock) {
+                deadline_list = url_file_list[index..].to_vec();
+                break;
+            }
+            let saved = match self.retry_request_with_spec(&url_file.url, request_spec, check_func)
+            {
+                ResponseCheckResult::Ok(content) => self
+                    .save_request_content(folder_path, &url_file.file_name, &content)
+                    .is_ok(),
+                _ => false,
+            };
+            if !saved {
+                fail_list.push(url_file.clone())
+            }
+            time_operation::random_sleep(request_setting.get_sleep_range(self.consecutive_sleep));
+        }
+        if !fail_list.is_empty() {
+            let fail_url_list = format!(
+                "The following urls were not loaded successfully:\n\n {}",
+                fail_list
+                    .iter()
+                    .map(|x| x.url.as_str())
+                    .collect::<Vec<&str>>()
+                    .join("\n")
+            );
+            self.project_logger.log_error(&fail_url_list);
+            let fail_url_message = format!(
+                "The urls starting with {:?} has {} out of {} fail urls.",
+                fail_list.first(),
+                fail_list.len(),
+                url_file_list.len()
+            );
+            self.slack_messenger.retry_send_message(
+                request_setting.calling_func,
+                &fail_url_message,
+                request_setting.log_only,
+            );
+        }
+        self.notify_deadline_reached(
+            &deadline_list,
+            url_file_list.len(),
+            request_setting.max_total_duration,
+            request_setting.calling_func,
+            request_setting.log_only,
         );
-        match Url::parse(&csv_link) {
-            Ok(u) => u,
-            Err(e) => panic!("Unable to parse the google sheet link {google_sheet_key}. {e}"),
+        fail_list.extend(deadline_list);
+        fail_list
+    }
+
+    fn notify_deadline_reached(
+        &self,
+        deadline_list: &[UrlFile],
+        total_count: usize,
+        max_total_duration: Option<Duration>,
+        calling_func: &str,
+        log_only: bool,
+    ) {
+        if !deadline_list.is_empty() {
+            let deadline_message = format!(
+                "Deadline of {:?} reached. The urls starting with {:?} has {} out of {} urls pending.",
+                max_total_duration.unwrap_or_default(),
+                deadline_list.first(),
+                deadline_list.len(),
+                total_count
+            );
+            self.project_logger.log_warn(&deadline_message);
+            self.slack_messenger
+                .retry_send_message(calling_func, &deadline_message, log_only);
         }
     }
 
     pub fn retry_download_google_sheet(&mut self, google_sheet_link: &str) -> ResponseCheckResult {
-        let google_sheet_url = Self::url_from_google_sheet_link(google_sheet_link);
-        self.retry_request_simple(&google_sheet_url, Self::null_check_func)
+        match google_sheet::url_from_google_sheet_link(google_sheet_link) {
+            Some(google_sheet_url) => {
+                self.retry_request_simple(&google_sheet_url, &Self::null_check_func)
+            }
+            None => ResponseCheckResult::ErrTerminate(ScrapeFailure::LoadFailed(format!(
+                "Unable to parse the google sheet link {google_sheet_link}."
+            ))),
+        }
+    }
+
+    pub fn retry_download_google_sheet_tabs(
+        &mut self,
+        google_sheet_key: &GoogleSheetKey,
+        gid_list: &[&str],
+    ) -> Vec<(String, ResponseCheckResult)> {
+        gid_list
+            .iter()
+            .map(|gid| {
+                let response = match google_sheet_key.with_gid(gid).get_csv_url() {
+                    Some(google_sheet_url) => {
+                        self.retry_request_simple(&google_sheet_url, &Self::null_check_func)
+                    }
+                    None => ResponseCheckResult::ErrTerminate(ScrapeFailure::LoadFailed(format!(
+                        "Unable to parse the google sheet {} with gid {gid}.",
+                        google_sheet_key.sheet_id
+                    ))),
+                };
+                (gid.to_string(), response)
+            })
+            .collect()
     }
 
     pub fn convert_google_sheet_string_to_data_frame(google_sheet_csv: &str) -> Option<DataFrame> {
-        let cursor = Cursor::new(google_sheet_csv);
-        CsvReadOptions::default()
-            .with_has_header(true)
-            .into_reader_with_file_handle(cursor)
-            .finish()
+        google_sheet::convert_to_data_frame(google_sheet_csv, &GoogleSheetReadOptions::default())
             .ok()
     }
 
@@ -549,7 +1022,7 @@ impl<'a> WebScraper<'a> {
         match &mut self.web_driver {
             Some(w_d) => w_d.get(url.clone()),
             None => {
-                self.set_web_driver();
+                self.set_web_driver()?;
                 self.browse_page(url)
             }
         }
@@ -568,7 +1041,7 @@ impl<'a> WebScraper<'a> {
                 w_d.page_source()
             }
             None => {
-                self.set_web_driver();
+                self.set_web_driver()?;
                 self.browse_request(url, browse_action)
             }
         }
@@ -578,13 +1051,13 @@ impl<'a> WebScraper<'a> {
         &mut self,
         url: &Url,
         browse_action: fn(&mut WebDriver) -> WebDriverResult<()>,
-        check_func: fn(&str) -> ResponseCheckResult,
+        check_func: &dyn ResponseValidator,
     ) -> ResponseCheckResult {
         let mut counter = 0;
         while counter < self.num_retry {
             match self.browse_request(url, browse_action) {
                 Ok(r) => {
-                    match check_func(&r) {
+                    match check_func.check(&r) {
                         ResponseCheckResult::Ok(r) => {
                             let debug_str = format!("Request {} browsed.", url.as_str());
                             self.project_logger.log_debug(&debug_str);
@@ -596,12 +1069,18 @@ impl<'a> WebScraper<'a> {
                             self.project_logger.log_warn(&warn_str);
                             time_operation::sleep(self.retry_sleep);
                         }
-                        ResponseCheckResult::ErrTerminate(e) => {
+                        ResponseCheckResult::ErrTerminate(e) | ResponseCheckResult::TooLarge(e) => {
                             let error_str =
                                 format!("Terminate to load the page {}. {e}", url.as_str());
                             self.project_logger.log_error(&error_str);
                             return ResponseCheckResult::ErrTerminate(e);
                         }
+                        ResponseCheckResult::Blocked(e) => {
+                            let warn_str =
+                                format!("Blocked when loading the page {}. {e}", url.as_str());
+                            self.project_logger.log_warn(&warn_str);
+                            return ResponseCheckResult::Blocked(e);
+                        }
                     };
                 }
                 Err(e) => {
@@ -617,7 +1096,7 @@ impl<'a> WebScraper<'a> {
         }
         let error_str = format!("Fail to browse the page {}.", url.as_str());
         self.project_logger.log_error(&error_str);
-        ResponseCheckResult::ErrTerminate(error_str)
+        ResponseCheckResult::ErrTerminate(ScrapeFailure::LoadFailed(error_str))
     }
 
     pub fn multiple_browse_requests(
@@ -625,21 +1104,31 @@ impl<'a> WebScraper<'a> {
         url_file_list: &'a [UrlFile],
         folder_path: &Path,
         browse_action: fn(&mut WebDriver) -> WebDriverResult<()>,
-        check_func: fn(&str) -> ResponseCheckResult,
+        check_func: &dyn ResponseValidator,
         browse_setting: BrowseSetting,
     ) -> Vec<UrlFile> {
+        let deadline = browse_setting.get_deadline(&SystemClock);
+        let _dry_run_guard = DryRun::enable_if(browse_setting.dry_run);
         let mut fail_list = Vec::new();
-        for url_file in tqdm::tqdm(url_file_list.iter()) {
-            if let ResponseCheckResult::Ok(content) =
-                self.retry_browse_request(&url_file.url, browse_action, check_func)
-            {
-                self.save_request_content(folder_path, &url_file.file_name, &content);
-            } else {
+        let mut deadline_list = Vec::new();
+        for (index, url_file) in tqdm::tqdm(url_file_list.iter().enumerate()) {
+            if time_operation::is_deadline_reached(deadline, &SystemClock) {
+                deadline_list = url_file_list[index..].to_vec();
+                break;
+            }
+            let saved = match self.retry_browse_request(&url_file.url, browse_action, check_func) {
+                ResponseCheckResult::Ok(content) => self
+                    .save_request_content(folder_path, &url_file.file_name, &content)
+                    .is_ok(),
+                _ => false,
+            };
+            if !saved {
                 fail_list.push(url_file.clone())
             }
-            time_operation::random_sleep(self.consecutive_sleep);
+            time_operation::random_sleep(browse_setting.get_sleep_range(self.consecutive_sleep));
+            // A failed restart is logged, and the next browse sets up a new driver.
             if browse_setting.restart_web_driver {
-                self.restart_web_driver();
+                let _ = self.restart_web_driver();
             }
         }
         if !fail_list.is_empty() {
@@ -664,6 +1153,14 @@ impl<'a> WebScraper<'a> {
                 browse_setting.log_only,
             );
         }
+        self.notify_deadline_reached(
+            &deadline_list,
+            url_file_list.len(),
+            browse_setting.max_total_duration,
+            browse_setting.calling_func,
+            browse_setting.log_only,
+        );
+        fail_list.extend(deadline_list);
         fail_list
     }
 }
@@ -724,10 +1221,42 @@ mod tests {
         let file_io = FileIO::new(&project_logger);
         let mut web_scraper = WebScraper::new(&project_logger, &slack_messenger, &file_io);
         let url = Url::parse("https://tfl.gov.uk/travel-information/timetables/").unwrap();
-        let content = web_scraper.retry_request_simple(&url, WebScraper::null_check_func);
+        let content = web_scraper.retry_request_simple(&url, &WebScraper::null_check_func);
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let file = "test_scrape.html";
-        web_scraper.save_request_content(&folder_path, file, &content.get_content().unwrap());
+        web_scraper
+            .save_request_content(&folder_path, file, &content.get_content().unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_request_with_spec() {
+        let logger_name = "test_simple_scraping";
+        let logger_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Log")
+            .join("log_sctys_netdata");
+        let project_logger = ProjectLogger::new_logger(&logger_path, logger_name);
+        let _handle = project_logger.set_logger(LevelFilter::Debug);
+        let channel_config_path = Path::new(&env::var("SCTYS_PROJECT").unwrap())
+            .join("Config")
+            .join("config_sctys_rust_utilities");
+        let channel_config_file = "messenger_channel_id.toml";
+        let channel_id = load_channel_id(&channel_config_path, channel_config_file);
+        let log_channel_id = channel_id.clone();
+        let slack_messenger = SlackMessenger::new(&channel_id, &log_channel_id, &project_logger);
+        let file_io = FileIO::new(&project_logger);
+        let mut web_scraper = WebScraper::new(&project_logger, &slack_messenger, &file_io);
+        let url = Url::parse("https://httpbin.org/post").unwrap();
+        let request_spec = RequestSpec::post_json(serde_json::json!({"league": "epl"}))
+            .with_header(
+                reqwest::header::ACCEPT,
+                reqwest::header::HeaderValue::from_static("application/json"),
+            );
+        let content = web_scraper
+            .retry_request_with_spec(&url, &request_spec, &WebScraper::null_check_func)
+            .get_content()
+            .unwrap();
+        assert!(content.contains("\"league\": \"epl\""));
     }
 
     #[test]
@@ -788,15 +1317,11 @@ mod tests {
         }));
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let calling_func = utilities_function::function_name!(true);
-        let request_setting = RequestSetting {
-            calling_func,
-            log_only: true,
-            in_s3: false,
-        };
+        let request_setting = RequestSetting::new(calling_func, true, false);
         web_scraper.multiple_requests(
             &url_file_list,
             &folder_path,
-            WebScraper::null_check_func,
+            &WebScraper::null_check_func,
             request_setting,
         );
     }
@@ -833,14 +1358,16 @@ mod tests {
         let mut web_scraper = WebScraper::new(&project_logger, &slack_messenger, &file_io);
         let browse_action = extra_action;
         let url = Url::parse("https://www.nowgoal.com/").unwrap();
-        web_scraper.turn_on_chrome_process();
+        web_scraper.turn_on_chrome_process().unwrap();
         let content =
-            web_scraper.retry_browse_request(&url, browse_action, WebScraper::null_check_func);
+            web_scraper.retry_browse_request(&url, browse_action, &WebScraper::null_check_func);
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let file = "test_browse.html";
-        web_scraper.save_request_content(&folder_path, file, &content.get_content().unwrap());
-        web_scraper.close_web_driver();
-        web_scraper.kill_chrome_process();
+        web_scraper
+            .save_request_content(&folder_path, file, &content.get_content().unwrap())
+            .unwrap();
+        web_scraper.close_web_driver().unwrap();
+        web_scraper.kill_chrome_process().unwrap();
     }
 
     #[test]
@@ -872,21 +1399,16 @@ mod tests {
         }));
         let folder_path = Path::new(&env::var("SCTYS_DATA").unwrap()).join("test_io");
         let calling_func = utilities_function::function_name!(true);
-        let browse_setting = BrowseSetting {
-            restart_web_driver: false,
-            calling_func,
-            log_only: true,
-            in_s3: false,
-        };
-        web_scraper.turn_on_chrome_process();
+        let browse_setting = BrowseSetting::new(calling_func, true, false);
+        web_scraper.turn_on_chrome_process().unwrap();
         web_scraper.multiple_browse_requests(
             &url_file_list,
             &folder_path,
             browse_action,
-            WebScraper::null_check_func,
+            &WebScraper::null_check_func,
             browse_setting,
         );
-        web_scraper.close_web_driver();
-        web_scraper.kill_chrome_process();
+        web_scraper.close_web_driver().unwrap();
+        web_scraper.kill_chrome_process().unwrap();
     }
 }
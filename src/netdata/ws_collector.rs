@@ -0,0 +1,500 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use polars::prelude::*;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use super::proxy_endpoint::{ProxyEndpoint, ProxyScheme};
+use crate::aws_s3::AWSFileIO;
+use crate::error::{Error, Result};
+use crate::file_io::FileIO;
+use crate::function_name;
+use crate::logger::ProjectLogger;
+use crate::shutdown::ShutdownSignal;
+use crate::slack_messenger::SlackMessenger;
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WsOutputFormat {
+    Ndjson,
+    Parquet,
+}
+
+impl WsOutputFormat {
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            Self::Ndjson => "ndjson",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WsFrame {
+    pub received_at: DateTime<Utc>,
+    pub payload: String,
+}
+
+// Placeholders in the form {key} are replaced by the value of the key.
+pub fn render_template(template: &str, params: &HashMap<String, String>) -> String {
+    params
+        .iter()
+        .fold(template.to_string(), |message, (key, value)| {
+            message.replace(&format!("{{{key}}}"), value)
+        })
+}
+
+pub fn frames_to_ndjson(frame_list: &[WsFrame]) -> String {
+    frame_list
+        .iter()
+        .map(|frame| {
+            json!({
+                "received_at": frame.received_at.to_rfc3339(),
+                "payload": frame.payload,
+            })
+            .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn frames_to_data_frame(frame_list: &[WsFrame]) -> PolarsResult<DataFrame> {
+    let received_at: Vec<String> = frame_list
+        .iter()
+        .map(|frame| frame.received_at.to_rfc3339())
+        .collect();
+    let payload: Vec<&str> = frame_list
+        .iter()
+        .map(|frame| frame.payload.as_str())
+        .collect();
+    df!(
+        "received_at" => received_at,
+        "payload" => payload,
+    )
+}
+
+pub struct WsCollector<'a> {
+    name: String,
+    url: String,
+    project_logger: &'a ProjectLogger,
+    slack_messenger: &'a SlackMessenger<'a>,
+    file_io: &'a FileIO<'a>,
+    aws_output: Option<(&'a AWSFileIO<'a>, &'a str)>,
+    output_folder: PathBuf,
+    output_format: WsOutputFormat,
+    proxy: Option<ProxyEndpoint>,
+    subscribe_messages: Vec<String>,
+    rotation_size: usize,
+    rotation_interval: Duration,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    disconnect_alert: Duration,
+    shutdown_signal: Option<ShutdownSignal>,
+    log_only: bool,
+}
+
+impl<'a> WsCollector<'a> {
+    const ROTATION_SIZE: usize = 10000;
+    const ROTATION_INTERVAL: Duration = Duration::from_secs(600);
+    const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+    const DISCONNECT_ALERT: Duration = Duration::from_secs(300);
+    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new(
+        name: &str,
+        url: &str,
+        project_logger: &'a ProjectLogger,
+        slack_messenger: &'a SlackMessenger<'a>,
+        file_io: &'a FileIO<'a>,
+        output_folder: &Path,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            project_logger,
+            slack_messenger,
+            file_io,
+            aws_output: None,
+            output_folder: output_folder.to_path_buf(),
+            output_format: WsOutputFormat::Ndjson,
+            proxy: None,
+            subscribe_messages: Vec::new(),
+            rotation_size: Self::ROTATION_SIZE,
+            rotation_interval: Self::ROTATION_INTERVAL,
+            initial_backoff: Self::INITIAL_BACKOFF,
+            max_backoff: Self::MAX_BACKOFF,
+            disconnect_alert: Self::DISCONNECT_ALERT,
+            shutdown_signal: None,
+            log_only: false,
+        }
+    }
+
+    // Write the output files to the bucket instead of the local disk.
+    pub fn set_aws_output(&mut self, aws_file_io: &'a AWSFileIO<'a>, aws_bucket: &'a str) {
+        self.aws_output = Some((aws_file_io, aws_bucket));
+    }
+
+    pub fn set_output_format(&mut self, output_format: WsOutputFormat) {
+        self.output_format = output_format;
+    }
+
+    pub fn set_proxy(&mut self, proxy: ProxyEndpoint) {
+        self.proxy = Some(proxy);
+    }
+
+    // Sent after every (re)connection, in the order added.
+    pub fn add_subscription(&mut self, template: &str, params: &HashMap<String, String>) {
+        self.subscribe_messages
+            .push(render_template(template, params));
+    }
+
+    pub fn set_rotation(&mut self, rotation_size: usize, rotation_interval: Duration) {
+        self.rotation_size = rotation_size.max(1);
+        self.rotation_interval = rotation_interval;
+    }
+
+    pub fn set_backoff(&mut self, initial_backoff: Duration, max_backoff: Duration) {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+    }
+
+    pub fn set_disconnect_alert(&mut self, disconnect_alert: Duration) {
+        self.disconnect_alert = disconnect_alert;
+    }
+
+    pub fn set_shutdown_signal(&mut self, shutdown_signal: ShutdownSignal) {
+        self.shutdown_signal = Some(shutdown_signal);
+    }
+
+    pub fn set_log_only(&mut self, log_only: bool) {
+        self.log_only = log_only;
+    }
+
+    fn is_shutdown_requested(&self) -> bool {
+        self.shutdown_signal
+            .as_ref()
+            .is_some_and(|shutdown_signal| shutdown_signal.is_requested())
+    }
+
+    fn ws_error(&self, message: &str) -> Error {
+        Error::scrape(&self.url, message)
+    }
+
+    async fn open_http_tunnel(
+        &self,
+        proxy: &ProxyEndpoint,
+        target_address: &str,
+    ) -> Result<TcpStream> {
+        let mut tcp_stream = TcpStream::connect(proxy.get_address()).await?;
+        let mut connect_request =
+            format!("CONNECT {target_address} HTTP/1.1\r\nHost: {target_address}\r\n");
+        if let (Some(username), Some(password)) = (&proxy.username, &proxy.password) {
+            let credential = STANDARD.encode(format!("{username}:{password}"));
+            connect_request.push_str(&format!("Proxy-Authorization: Basic {credential}\r\n"));
+        }
+        connect_request.push_str("\r\n");
+        tcp_stream.write_all(connect_request.as_bytes()).await?;
+        let mut response = Vec::new();
+        let mut buffer = [0; 1024];
+        while !response.ends_with(b"\r\n\r\n") {
+            let num_bytes = tcp_stream.read(&mut buffer).await?;
+            if num_bytes == 0 {
+                return Err(self.ws_error("Proxy closed the connection during CONNECT"));
+            }
+            response.extend_from_slice(&buffer[..num_bytes]);
+        }
+        let status_line = String::from_utf8_lossy(&response)
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        if status_line.split_whitespace().nth(1) == Some("200") {
+            Ok(tcp_stream)
+        } else {
+            Err(self.ws_error(&format!("Proxy refused CONNECT. {status_line}")))
+        }
+    }
+
+    async fn connect(&self) -> Result<WsStream> {
+        let ws_stream = match &self.proxy {
+            None => {
+                tokio_tungstenite::connect_async(self.url.as_str())
+                    .await
+                    .map_err(|e| self.ws_error(&e.to_string()))?
+                    .0
+            }
+            Some(proxy) => {
+                let url = reqwest::Url::parse(&self.url)
+                    .map_err(|e| self.ws_error(&format!("Invalid url. {e}")))?;
+                let host = url
+                    .host_str()
+                    .ok_or_else(|| self.ws_error("No host in url"))?
+                    .to_string();
+                let port = url
+                    .port_or_known_default()
+                    .ok_or_else(|| self.ws_error("No port in url"))?;
+                let tcp_stream = match proxy.scheme {
+                    ProxyScheme::Socks5 | ProxyScheme::Socks5h => {
+                        let target = (host.as_str(), port);
+                        let socks_stream = match (&proxy.username, &proxy.password) {
+                            (Some(username), Some(password)) => {
+                                Socks5Stream::connect_with_password(
+                                    proxy.get_address().as_str(),
+                                    target,
+                                    username,
+                                    password,
+                                )
+                                .await
+                            }
+                            _ => Socks5Stream::connect(proxy.get_address().as_str(), target).await,
+                        };
+                        socks_stream
+                            .map_err(|e| self.ws_error(&format!("Unable to reach proxy. {e}")))?
+                            .into_inner()
+                    }
+                    ProxyScheme::Http | ProxyScheme::Https => {
+                        self.open_http_tunnel(proxy, &format!("{host}:{port}"))
+                            .await?
+                    }
+                };
+                tokio_tungstenite::client_async_tls(self.url.as_str(), tcp_stream)
+                    .await
+                    .map_err(|e| self.ws_error(&e.to_string()))?
+                    .0
+            }
+        };
+        Ok(ws_stream)
+    }
+
+    async fn subscribe(&self, ws_stream: &mut WsStream) -> Result<()> {
+        for subscribe_message in self.subscribe_messages.iter() {
+            ws_stream
+                .send(Message::Text(subscribe_message.clone()))
+                .await
+                .map_err(|e| self.ws_error(&format!("Unable to subscribe. {e}")))?;
+        }
+        Ok(())
+    }
+
+    fn get_output_file(&self, start_time: &DateTime<Utc>) -> String {
+        format!(
+            "{}_{}.{}",
+            self.name,
+            start_time.format("%Y%m%d_%H%M%S_%6f"),
+            self.output_format.get_extension()
+        )
+    }
+
+    async fn flush_frames(&self, frame_list: &mut Vec<WsFrame>) {
+        let Some(first_frame) = frame_list.first() else {
+            return;
+        };
+        let file = self.get_output_file(&first_frame.received_at);
+        let write_result = match (self.output_format, self.aws_output) {
+            (WsOutputFormat::Ndjson, None) => self
+                .file_io
+                .write_string_to_file(&self.output_folder, &file, &frames_to_ndjson(frame_list))
+                .map_err(Error::from),
+            (WsOutputFormat::Ndjson, Some((aws_file_io, aws_bucket))) => aws_file_io
+                .write_string_to_file(
+                    aws_bucket,
+                    &self.output_folder,
+                    &file,
+                    &frames_to_ndjson(frame_list),
+                )
+                .await
+                .map_err(Error::from),
+            (WsOutputFormat::Parquet, aws_output) => match frames_to_data_frame(frame_list) {
+                Ok(mut data) => match aws_output {
+                    None => self
+                        .file_io
+                        .write_parquet_file(&self.output_folder, &file, &mut data)
+                        .map_err(Error::from),
+                    Some((aws_file_io, aws_bucket)) => aws_file_io
+                        .write_parquet_file(aws_bucket, &self.output_folder, &file, &mut data)
+                        .await
+                        .map_err(Error::from),
+                },
+                Err(e) => Err(Error::from(e)),
+            },
+        };
+        match write_result {
+            Ok(()) => {
+                let debug_str = format!("{} frames written to {file}.", frame_list.len());
+                self.project_logger.log_debug(&debug_str);
+            }
+            Err(e) => {
+                let error_str =
+                    format!("Unable to write {} frames to {file}. {e}", frame_list.len());
+                self.project_logger.log_error(&error_str);
+            }
+        }
+        frame_list.clear();
+    }
+
+    // Reads frames until the connection drops or a shutdown is requested, rotating the output
+    // file by size and by time. Returns true if at least one frame was received.
+    async fn collect_frames(
+        &self,
+        ws_stream: &mut WsStream,
+        frame_list: &mut Vec<WsFrame>,
+    ) -> bool {
+        let mut is_receiving = false;
+        let mut file_start_time = Instant::now();
+        loop {
+            if self.is_shutdown_requested() {
+                let _ = ws_stream.close(None).await;
+                return is_receiving;
+            }
+            let next_message = tokio::time::timeout(Self::POLL_INTERVAL, ws_stream.next()).await;
+            let payload = match next_message {
+                Err(_) => None,
+                Ok(Some(Ok(Message::Text(text)))) => Some(text),
+                Ok(Some(Ok(Message::Binary(bytes)))) => {
+                    Some(String::from_utf8_lossy(&bytes).to_string())
+                }
+                Ok(Some(Ok(Message::Close(close_frame)))) => {
+                    let warn_str =
+                        format!("WebSocket {} closed by server. {close_frame:?}", self.url);
+                    self.project_logger.log_warn(&warn_str);
+                    return is_receiving;
+                }
+                Ok(Some(Ok(_))) => None,
+                Ok(Some(Err(e))) => {
+                    let warn_str = format!("WebSocket {} disconnected. {e}", self.url);
+                    self.project_logger.log_warn(&warn_str);
+                    return is_receiving;
+                }
+                Ok(None) => return is_receiving,
+            };
+            if let Some(payload) = payload {
+                is_receiving = true;
+                frame_list.push(WsFrame {
+                    received_at: Utc::now(),
+                    payload,
+                });
+            }
+            if frame_list.len() >= self.rotation_size
+                || (file_start_time.elapsed() >= self.rotation_interval && !frame_list.is_empty())
+            {
+                self.flush_frames(frame_list).await;
+                file_start_time = Instant::now();
+            }
+        }
+    }
+
+    pub async fn run(&self) {
+        let function_name = function_name!(true);
+        let mut frame_list = Vec::new();
+        let mut backoff = self.initial_backoff;
+        let mut disconnected_since: Option<Instant> = None;
+        let mut is_alerted = false;
+        while !self.is_shutdown_requested() {
+            match self.connect().await {
+                Ok(mut ws_stream) => match self.subscribe(&mut ws_stream).await {
+                    Ok(()) => {
+                        if is_alerted {
+                            let info_str =
+                                format!("WebSocket collector {} reconnected.", self.name);
+                            self.project_logger.log_info(&info_str);
+                            self.slack_messenger.retry_send_message(
+                                function_name,
+                                &info_str,
+                                self.log_only,
+                            );
+                        }
+                        let debug_str = format!("WebSocket {} connected.", self.url);
+                        self.project_logger.log_debug(&debug_str);
+                        if self.collect_frames(&mut ws_stream, &mut frame_list).await {
+                            backoff = self.initial_backoff;
+                            disconnected_since = None;
+                            is_alerted = false;
+                        }
+                    }
+                    Err(e) => self.project_logger.log_warn(&e.to_string()),
+                },
+                Err(e) => {
+                    let warn_str = format!("Unable to connect to WebSocket {}. {e}", self.url);
+                    self.project_logger.log_warn(&warn_str);
+                }
+            }
+            self.flush_frames(&mut frame_list).await;
+            if self.is_shutdown_requested() {
+                break;
+            }
+            let disconnected_time = disconnected_since
+                .get_or_insert_with(Instant::now)
+                .elapsed();
+            if disconnected_time >= self.disconnect_alert && !is_alerted {
+                let error_str = format!(
+                    "WebSocket collector {} has been disconnected for {} s.",
+                    self.name,
+                    disconnected_time.as_secs()
+                );
+                self.project_logger.log_error(&error_str);
+                self.slack_messenger
+                    .retry_send_message(function_name, &error_str, self.log_only);
+                is_alerted = true;
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(self.max_backoff);
+        }
+        self.flush_frames(&mut frame_list).await;
+        let info_str = format!("WebSocket collector {} stopped.", self.name);
+        self.project_logger.log_info(&info_str);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let params = HashMap::from([
+            ("market".to_string(), "EPL".to_string()),
+            ("channel".to_string(), "odds".to_string()),
+        ]);
+        assert_eq!(
+            render_template(
+                r#"{"op": "subscribe", "channel": "{channel}", "market": "{market}"}"#,
+                &params
+            ),
+            r#"{"op": "subscribe", "channel": "odds", "market": "EPL"}"#
+        );
+    }
+
+    #[test]
+    fn test_frames_output() {
+        let received_at = Utc::now();
+        let frame_list = vec![
+            WsFrame {
+                received_at,
+                payload: r#"{"price": 1.5}"#.to_string(),
+            },
+            WsFrame {
+                received_at,
+                payload: "heartbeat".to_string(),
+            },
+        ];
+        let ndjson = frames_to_ndjson(&frame_list);
+        assert_eq!(ndjson.lines().count(), 2);
+        let first_line: serde_json::Value =
+            serde_json::from_str(ndjson.lines().next().unwrap()).unwrap();
+        assert_eq!(first_line["payload"], r#"{"price": 1.5}"#);
+        let data = frames_to_data_frame(&frame_list).unwrap();
+        assert_eq!(data.shape(), (2, 2));
+    }
+}